@@ -0,0 +1,170 @@
+use shared::mtp::topics;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{error, warn};
+use zenoh::key_expr::KeyExpr;
+use zenoh::sample::SampleKind;
+use zenoh::Session;
+
+use crate::chaos::Chaos;
+use crate::leader::Leadership;
+use crate::state::AlarmRecord;
+
+/// Alarms that are not cleared; these are served to queries on the alarm topic.
+pub fn is_open(alarm: &AlarmRecord) -> bool {
+    alarm.status != "cleared"
+}
+
+/// Publishes the normalized record on `entmoot/pol/alarms/{id}`.
+pub async fn publish(session: &Session, chaos: &Chaos, alarm: &AlarmRecord) {
+    let payload = serde_json::to_string(alarm).unwrap_or_else(|_| "{}".to_string());
+    if let Err(e) = chaos
+        .put(session, &topics::pol_alarm(&alarm.id), payload)
+        .await
+    {
+        warn!("Failed to publish alarm {}: {}", alarm.id, e);
+    }
+}
+
+/// Announces that an alarm was deleted.
+pub async fn publish_removed(session: &Session, alarm_id: &str) {
+    if let Err(e) = session.delete(topics::pol_alarm(alarm_id)).await {
+        warn!("Failed to publish removal of alarm {}: {}", alarm_id, e);
+    }
+}
+
+/// Answers queries on the alarm topic with the current open alarms, so late
+/// joiners can fetch the set with a `get` on `entmoot/pol/alarms/*`.
+pub async fn serve(session: Arc<Session>, alarms: Arc<RwLock<HashMap<String, AlarmRecord>>>) {
+    let queryable = match session.declare_queryable(topics::POL_ALARMS_WILDCARD).await {
+        Ok(queryable) => queryable,
+        Err(e) => {
+            error!("Failed to declare alarm queryable: {}", e);
+            return;
+        }
+    };
+
+    while let Ok(query) = queryable.recv_async().await {
+        let replies = query_replies(&*alarms.read().await, query.key_expr());
+        for (key, payload) in replies {
+            if let Err(e) = query.reply(key.as_str(), payload).await {
+                warn!("Failed to reply to alarm query: {}", e);
+            }
+        }
+    }
+}
+
+/// Keys and payloads of the open alarms whose topic intersects `query`.
+fn query_replies(
+    alarms: &HashMap<String, AlarmRecord>,
+    query: &KeyExpr<'_>,
+) -> Vec<(String, String)> {
+    alarms
+        .values()
+        .filter(|alarm| is_open(alarm))
+        .map(|alarm| (topics::pol_alarm(&alarm.id), alarm))
+        .filter(|(key, _)| {
+            KeyExpr::try_from(key.as_str()).is_ok_and(|key_expr| query.intersects(&key_expr))
+        })
+        .map(|(key, alarm)| {
+            let payload = serde_json::to_string(alarm).unwrap_or_else(|_| "{}".to_string());
+            (key, payload)
+        })
+        .collect()
+}
+
+/// Keeps a follower's alarm map in line with the records the leader publishes,
+/// since only the leader ingests raw alarms.
+pub async fn mirror(
+    session: Arc<Session>,
+    alarms: Arc<RwLock<HashMap<String, AlarmRecord>>>,
+    leadership: Leadership,
+) {
+    let subscriber = match session
+        .declare_subscriber(topics::POL_ALARMS_WILDCARD)
+        .await
+    {
+        Ok(subscriber) => subscriber,
+        Err(e) => {
+            error!(
+                "Failed to subscribe to {}: {}",
+                topics::POL_ALARMS_WILDCARD,
+                e
+            );
+            return;
+        }
+    };
+
+    while let Ok(sample) = subscriber.recv_async().await {
+        if leadership.is_leader() {
+            continue;
+        }
+        let Some(alarm_id) = sample.key_expr().as_str().rsplit('/').next() else {
+            continue;
+        };
+        match sample.kind() {
+            SampleKind::Put => {
+                match serde_json::from_slice::<AlarmRecord>(&sample.payload().to_bytes()) {
+                    Ok(alarm) => {
+                        alarms.write().await.insert(alarm.id.clone(), alarm);
+                    }
+                    Err(e) => warn!("Ignoring invalid alarm record {}: {}", alarm_id, e),
+                }
+            }
+            SampleKind::Delete => {
+                alarms.write().await.remove(alarm_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::alarm_record;
+
+    fn alarms() -> HashMap<String, AlarmRecord> {
+        let mut cleared = alarm_record("a2", "p1", "high");
+        cleared.status = "cleared".to_string();
+        [
+            alarm_record("a1", "p1", "high"),
+            cleared,
+            alarm_record("a3", "p2", "low"),
+        ]
+        .into_iter()
+        .map(|alarm| (alarm.id.clone(), alarm))
+        .collect()
+    }
+
+    #[test]
+    fn only_cleared_alarms_are_closed() {
+        let mut alarm = alarm_record("a1", "p1", "high");
+        assert!(is_open(&alarm));
+        alarm.status = "acknowledged".to_string();
+        assert!(is_open(&alarm));
+        alarm.status = "cleared".to_string();
+        assert!(!is_open(&alarm));
+    }
+
+    #[test]
+    fn queries_get_the_open_alarms_under_their_key() {
+        let alarms = alarms();
+        let wildcard = KeyExpr::try_from(topics::POL_ALARMS_WILDCARD).unwrap();
+        let mut keys: Vec<String> = query_replies(&alarms, &wildcard)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        keys.sort();
+        assert_eq!(keys, vec![topics::pol_alarm("a1"), topics::pol_alarm("a3")]);
+
+        let single = topics::pol_alarm("a3");
+        let replies = query_replies(&alarms, &KeyExpr::try_from(single.as_str()).unwrap());
+        assert_eq!(replies.len(), 1);
+        let record: AlarmRecord = serde_json::from_str(&replies[0].1).unwrap();
+        assert_eq!(record.id, "a3");
+
+        let cleared = topics::pol_alarm("a2");
+        assert!(query_replies(&alarms, &KeyExpr::try_from(cleared.as_str()).unwrap()).is_empty());
+    }
+}
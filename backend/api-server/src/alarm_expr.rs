@@ -0,0 +1,407 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Value an alarm expression operates on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExprValue {
+    Num(f64),
+    Bool(bool),
+}
+
+impl ExprValue {
+    /// Telemetry payloads: numbers and booleans, also wrapped as `{"value": ...}`
+    /// or `{"v": ...}`, and numeric strings.
+    pub fn from_json(value: &serde_json::Value) -> Option<Self> {
+        match value {
+            serde_json::Value::Bool(b) => Some(Self::Bool(*b)),
+            serde_json::Value::Number(n) => n.as_f64().map(Self::Num),
+            serde_json::Value::String(s) => s.trim().parse().ok().map(Self::Num),
+            serde_json::Value::Object(map) => map
+                .get("value")
+                .or_else(|| map.get("v"))
+                .and_then(Self::from_json),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ExprValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Num(n) => write!(f, "{}", n),
+            Self::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// Compiled alarm condition, e.g. `oil_pressure < 35 && rpm > 1500`.
+///
+/// Supports numbers, `true`/`false`, variables, `+ - * /`, comparisons,
+/// `!`, `&&`, `||` and parentheses, with C precedence.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Literal(ExprValue),
+    Var(String),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Expr, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("Unexpected '{}'", token)),
+        }
+    }
+
+    /// Names of the variables the expression reads.
+    pub fn variables(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        self.collect_variables(&mut names);
+        names
+    }
+
+    fn collect_variables(&self, names: &mut BTreeSet<String>) {
+        match self {
+            Expr::Literal(_) => {}
+            Expr::Var(name) => {
+                names.insert(name.clone());
+            }
+            Expr::Not(inner) | Expr::Neg(inner) => inner.collect_variables(names),
+            Expr::Binary(_, lhs, rhs) => {
+                lhs.collect_variables(names);
+                rhs.collect_variables(names);
+            }
+        }
+    }
+
+    /// Evaluates with variable values from `lookup`. `&&` and `||` short-circuit,
+    /// so a missing value on the side not needed is no error.
+    pub fn eval(&self, lookup: &dyn Fn(&str) -> Option<ExprValue>) -> Result<ExprValue, String> {
+        match self {
+            Expr::Literal(value) => Ok(*value),
+            Expr::Var(name) => lookup(name).ok_or_else(|| format!("No value for '{}'", name)),
+            Expr::Not(inner) => Ok(ExprValue::Bool(!as_bool(inner.eval(lookup)?)?)),
+            Expr::Neg(inner) => Ok(ExprValue::Num(-as_num(inner.eval(lookup)?)?)),
+            Expr::Binary(BinaryOp::And, lhs, rhs) => Ok(ExprValue::Bool(
+                as_bool(lhs.eval(lookup)?)? && as_bool(rhs.eval(lookup)?)?,
+            )),
+            Expr::Binary(BinaryOp::Or, lhs, rhs) => Ok(ExprValue::Bool(
+                as_bool(lhs.eval(lookup)?)? || as_bool(rhs.eval(lookup)?)?,
+            )),
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(lookup)?, rhs.eval(lookup)?);
+                match op {
+                    BinaryOp::Eq => Ok(ExprValue::Bool(same_type(lhs, rhs)? && lhs == rhs)),
+                    BinaryOp::Ne => Ok(ExprValue::Bool(same_type(lhs, rhs)? && lhs != rhs)),
+                    _ => arithmetic(*op, as_num(lhs)?, as_num(rhs)?),
+                }
+            }
+        }
+    }
+}
+
+fn as_bool(value: ExprValue) -> Result<bool, String> {
+    match value {
+        ExprValue::Bool(b) => Ok(b),
+        ExprValue::Num(n) => Err(format!("Expected a boolean, got {}", n)),
+    }
+}
+
+fn as_num(value: ExprValue) -> Result<f64, String> {
+    match value {
+        ExprValue::Num(n) => Ok(n),
+        ExprValue::Bool(b) => Err(format!("Expected a number, got {}", b)),
+    }
+}
+
+fn same_type(lhs: ExprValue, rhs: ExprValue) -> Result<bool, String> {
+    match (lhs, rhs) {
+        (ExprValue::Num(_), ExprValue::Num(_)) | (ExprValue::Bool(_), ExprValue::Bool(_)) => {
+            Ok(true)
+        }
+        _ => Err(format!("Cannot compare {} with {}", lhs, rhs)),
+    }
+}
+
+fn arithmetic(op: BinaryOp, lhs: f64, rhs: f64) -> Result<ExprValue, String> {
+    Ok(match op {
+        BinaryOp::Lt => ExprValue::Bool(lhs < rhs),
+        BinaryOp::Le => ExprValue::Bool(lhs <= rhs),
+        BinaryOp::Gt => ExprValue::Bool(lhs > rhs),
+        BinaryOp::Ge => ExprValue::Bool(lhs >= rhs),
+        BinaryOp::Add => ExprValue::Num(lhs + rhs),
+        BinaryOp::Sub => ExprValue::Num(lhs - rhs),
+        BinaryOp::Mul => ExprValue::Num(lhs * rhs),
+        BinaryOp::Div if rhs == 0.0 => return Err("Division by zero".to_string()),
+        BinaryOp::Div => ExprValue::Num(lhs / rhs),
+        BinaryOp::And | BinaryOp::Or | BinaryOp::Eq | BinaryOp::Ne => {
+            unreachable!("handled by Expr::eval")
+        }
+    })
+}
+
+// ─── Parsing ─────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Op(&'static str),
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Num(n) => write!(f, "{}", n),
+            Token::Ident(name) => write!(f, "{}", name),
+            Token::Op(op) => write!(f, "{}", op),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+        }
+    }
+}
+
+/// Longest first, so `<=` is not read as `<`.
+const OPERATORS: [&str; 13] = [
+    "&&", "||", "<=", ">=", "==", "!=", "<", ">", "!", "+", "-", "*", "/",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if c == '(' || c == ')' {
+            tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            rest = &rest[1..];
+        } else if c.is_ascii_digit() || c == '.' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            let number = rest[..end]
+                .parse()
+                .map_err(|_| format!("Invalid number '{}'", &rest[..end]))?;
+            tokens.push(Token::Num(number));
+            rest = &rest[end..];
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else {
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| format!("Unexpected character '{}'", c))?;
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        }
+    }
+    if tokens.is_empty() {
+        return Err("Expression is empty".to_string());
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Consumes the next token if it is one of `ops`.
+    fn op(&mut self, ops: &[(&str, BinaryOp)]) -> Option<BinaryOp> {
+        let Some(Token::Op(token)) = self.peek() else {
+            return None;
+        };
+        let op = ops.iter().find(|(s, _)| s == token).map(|(_, op)| *op)?;
+        self.pos += 1;
+        Some(op)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.and()?;
+        while let Some(op) = self.op(&[("||", BinaryOp::Or)]) {
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.comparison()?;
+        while let Some(op) = self.op(&[("&&", BinaryOp::And)]) {
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.comparison()?));
+        }
+        Ok(lhs)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let lhs = self.sum()?;
+        let ops = [
+            ("<", BinaryOp::Lt),
+            ("<=", BinaryOp::Le),
+            (">", BinaryOp::Gt),
+            (">=", BinaryOp::Ge),
+            ("==", BinaryOp::Eq),
+            ("!=", BinaryOp::Ne),
+        ];
+        match self.op(&ops) {
+            Some(op) => Ok(Expr::Binary(op, Box::new(lhs), Box::new(self.sum()?))),
+            None => Ok(lhs),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.term()?;
+        while let Some(op) = self.op(&[("+", BinaryOp::Add), ("-", BinaryOp::Sub)]) {
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.op(&[("*", BinaryOp::Mul), ("/", BinaryOp::Div)]) {
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some(Token::Op("!")) => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            Some(Token::Op("-")) => {
+                self.pos += 1;
+                Ok(Expr::Neg(Box::new(self.unary()?)))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Num(n)) => Ok(Expr::Literal(ExprValue::Num(n))),
+            Some(Token::Ident(name)) => Ok(match name.as_str() {
+                "true" => Expr::Literal(ExprValue::Bool(true)),
+                "false" => Expr::Literal(ExprValue::Bool(false)),
+                _ => Expr::Var(name),
+            }),
+            Some(Token::Open) => {
+                let inner = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err("Missing ')'".to_string()),
+                }
+            }
+            Some(token) => Err(format!("Unexpected '{}'", token)),
+            None => Err("Unexpected end of expression".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str, vars: &[(&str, ExprValue)]) -> Result<ExprValue, String> {
+        let lookup = |name: &str| {
+            vars.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, value)| *value)
+        };
+        Expr::parse(source)?.eval(&lookup)
+    }
+
+    #[test]
+    fn evaluates_interaction_conditions() {
+        let vars = [
+            ("oil_pressure", ExprValue::Num(30.0)),
+            ("rpm", ExprValue::Num(1600.0)),
+            ("running", ExprValue::Bool(true)),
+        ];
+        let fires = |source| eval(source, &vars).unwrap();
+        assert_eq!(
+            fires("oil_pressure < 35 && rpm > 1500"),
+            ExprValue::Bool(true)
+        );
+        assert_eq!(
+            fires("oil_pressure < 35 && rpm > 2000"),
+            ExprValue::Bool(false)
+        );
+        assert_eq!(
+            fires("!running || rpm / 2 + 1 >= 801"),
+            ExprValue::Bool(true)
+        );
+        assert_eq!(fires("-(rpm - 1000) * 2"), ExprValue::Num(-1200.0));
+        assert_eq!(
+            fires("running == true && (false || rpm != 0)"),
+            ExprValue::Bool(true)
+        );
+    }
+
+    #[test]
+    fn short_circuits_missing_values() {
+        let vars = [("rpm", ExprValue::Num(900.0))];
+        assert_eq!(
+            eval("rpm > 1500 && oil_pressure < 35", &vars),
+            Ok(ExprValue::Bool(false))
+        );
+        assert!(eval("rpm < 1500 && oil_pressure < 35", &vars).is_err());
+    }
+
+    #[test]
+    fn reports_syntax_and_type_errors() {
+        assert!(Expr::parse("").is_err());
+        assert!(Expr::parse("rpm >").is_err());
+        assert!(Expr::parse("(rpm > 1").is_err());
+        assert!(Expr::parse("rpm > 1 1").is_err());
+        assert!(Expr::parse("rpm % 2").is_err());
+        assert!(eval("1 && true", &[]).is_err());
+        assert!(eval("1 / 0", &[]).is_err());
+        assert_eq!(
+            Expr::parse("a < b || !c.state").unwrap().variables(),
+            ["a", "b", "c.state"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect()
+        );
+    }
+}
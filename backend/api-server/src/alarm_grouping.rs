@@ -0,0 +1,170 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+
+use crate::alarm_sla;
+use crate::severity_profile::SeverityProfile;
+use crate::state::{AlarmRecord, PolTopology};
+
+/// Window after a Critical alarm in which downstream alarms are grouped under it.
+pub const DEFAULT_WINDOW_S: i64 = 300;
+
+/// Grouping window from `ALARM_GROUP_WINDOW_S`; `0` turns grouping off.
+pub fn window_from_env() -> Duration {
+    let seconds = std::env::var("ALARM_GROUP_WINDOW_S")
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .filter(|value| *value >= 0)
+        .unwrap_or(DEFAULT_WINDOW_S);
+    Duration::seconds(seconds)
+}
+
+/// PEA id of an alarm source such as `entmoot/habitat/nodes/n1/pea/p1/swimlane/alarm`,
+/// or the source itself when it is a bare PEA id.
+pub fn pea_of(source: &str) -> &str {
+    let mut segments = source.split('/');
+    while let Some(segment) = segments.next() {
+        if segment == "pea" {
+            if let Some(pea) = segments.next() {
+                return pea;
+            }
+        }
+    }
+    source
+}
+
+/// Every PEA upstream of `pea` in the topology, following edges transitively.
+pub fn upstream_peas(topology: &PolTopology, pea: &str) -> HashSet<String> {
+    let mut found = HashSet::new();
+    let mut frontier = vec![pea.to_string()];
+    while let Some(current) = frontier.pop() {
+        for edge in topology.edges.iter().filter(|edge| edge.to == current) {
+            if edge.from != pea && found.insert(edge.from.clone()) {
+                frontier.push(edge.from.clone());
+            }
+        }
+    }
+    found
+}
+
+/// The primary cause for an alarm raised by `source` at `at`: the earliest
+/// active alarm of an upstream PEA at the profile's most urgent severity raised
+/// within `window` before it.
+pub fn primary_for(
+    alarms: &HashMap<String, AlarmRecord>,
+    topology: &PolTopology,
+    source: &str,
+    at: DateTime<Utc>,
+    window: Duration,
+    severities: &SeverityProfile,
+) -> Option<String> {
+    if window <= Duration::zero() {
+        return None;
+    }
+    let upstream = upstream_peas(topology, pea_of(source));
+    if upstream.is_empty() {
+        return None;
+    }
+    alarms
+        .values()
+        .filter(|alarm| alarm.parent_id.is_none() && is_active(alarm))
+        .filter(|alarm| severities.is_critical(&alarm.severity))
+        .filter(|alarm| upstream.contains(pea_of(&alarm.source)))
+        .filter_map(|alarm| {
+            let raised = alarm_sla::raised_at(alarm)?;
+            (raised <= at && at - raised <= window).then_some((raised, alarm))
+        })
+        .min_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, alarm)| alarm.id.clone())
+}
+
+/// Active alarms grouped under this one.
+pub fn active_children<'a>(
+    alarms: &'a HashMap<String, AlarmRecord>,
+    parent_id: &'a str,
+) -> impl Iterator<Item = &'a AlarmRecord> {
+    alarms
+        .values()
+        .filter(move |alarm| alarm.parent_id.as_deref() == Some(parent_id) && is_active(alarm))
+}
+
+/// Open or acknowledged, i.e. not yet cleared.
+pub fn is_active(alarm: &AlarmRecord) -> bool {
+    alarm.status == "open" || alarm.status == "acknowledged"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::PolEdge;
+    use crate::test_fixtures::alarm_record;
+
+    fn alarm(id: &str, pea: &str, severity: &str, timestamp: &str) -> AlarmRecord {
+        AlarmRecord {
+            timestamp: timestamp.to_string(),
+            ..alarm_record(id, pea, severity)
+        }
+    }
+
+    fn topology(edges: &[(&str, &str)]) -> PolTopology {
+        PolTopology {
+            edges: edges
+                .iter()
+                .map(|(from, to)| PolEdge {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                })
+                .collect(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn downstream_alarms_group_under_upstream_critical() {
+        let topology = topology(&[("feed", "reactor"), ("reactor", "dryer"), ("dryer", "feed")]);
+        let alarms: HashMap<String, AlarmRecord> = [
+            alarm("a1", "feed", "critical", "2026-05-01T10:00:00Z"),
+            alarm("a2", "reactor", "warning", "2026-05-01T09:59:00Z"),
+        ]
+        .into_iter()
+        .map(|alarm| (alarm.id.clone(), alarm))
+        .collect();
+        let at = "2026-05-01T10:02:00Z".parse::<DateTime<Utc>>().unwrap();
+        let source = "entmoot/habitat/nodes/n2/pea/dryer/swimlane/alarm";
+        let severities = SeverityProfile::default();
+
+        assert_eq!(
+            primary_for(
+                &alarms,
+                &topology,
+                source,
+                at,
+                Duration::minutes(5),
+                &severities
+            )
+            .as_deref(),
+            Some("a1")
+        );
+        assert_eq!(
+            primary_for(
+                &alarms,
+                &topology,
+                source,
+                at,
+                Duration::minutes(1),
+                &severities
+            ),
+            None
+        );
+        // The feed PEA is upstream of itself only through the cycle.
+        assert!(!upstream_peas(&topology, "feed").contains("feed"));
+    }
+
+    #[test]
+    fn pea_ids_are_read_from_alarm_sources() {
+        assert_eq!(
+            pea_of("entmoot/habitat/nodes/n1/pea/p-7/swimlane/alarm"),
+            "p-7"
+        );
+        assert_eq!(pea_of("p-7"), "p-7");
+    }
+}
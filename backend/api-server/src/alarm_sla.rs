@@ -0,0 +1,215 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::state::AlarmRecord;
+
+/// Share of a target after which a pending alarm is listed as at risk.
+pub const DEFAULT_AT_RISK_RATIO: f64 = 0.8;
+
+/// Response-time targets for alarms of one severity.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AlarmSla {
+    /// Severity the targets apply to, e.g. "critical"; stored lowercase.
+    #[serde(default)]
+    pub severity: String,
+    /// Seconds within which an alarm must be acknowledged.
+    #[serde(default)]
+    pub ack_within_s: Option<i64>,
+    /// Seconds within which an alarm must be cleared.
+    #[serde(default)]
+    pub clear_within_s: Option<i64>,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl AlarmSla {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.severity.trim().is_empty() {
+            return Err("severity is required".to_string());
+        }
+        if self.ack_within_s.is_none() && self.clear_within_s.is_none() {
+            return Err("Give ack_within_s, clear_within_s or both".to_string());
+        }
+        if [self.ack_within_s, self.clear_within_s]
+            .into_iter()
+            .flatten()
+            .any(|target| target <= 0)
+        {
+            return Err("Targets must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Sets the status of an alarm and records when it was first acknowledged or cleared.
+pub fn record_status(alarm: &mut AlarmRecord, status: &str, at: DateTime<Utc>) {
+    alarm.status = status.to_string();
+    let at = at.to_rfc3339();
+    match status {
+        "acknowledged" => {
+            alarm.acknowledged_at.get_or_insert(at);
+        }
+        "cleared" => {
+            alarm.cleared_at.get_or_insert(at);
+        }
+        _ => {}
+    }
+}
+
+/// How one response stage (acknowledge or clear) of an alarm measures up to its target.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StageCompliance {
+    pub target_s: i64,
+    /// Seconds from raise to completion, or to now while pending.
+    pub elapsed_s: i64,
+    pub completed: bool,
+    pub breached: bool,
+    pub due_at: String,
+}
+
+impl StageCompliance {
+    fn measure(
+        raised: DateTime<Utc>,
+        done: Option<DateTime<Utc>>,
+        target_s: i64,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let elapsed_s = (done.unwrap_or(now) - raised).num_seconds().max(0);
+        Self {
+            target_s,
+            elapsed_s,
+            completed: done.is_some(),
+            breached: elapsed_s > target_s,
+            due_at: (raised + Duration::seconds(target_s)).to_rfc3339(),
+        }
+    }
+
+    /// Pending and past `ratio` of the target.
+    pub fn at_risk(&self, ratio: f64) -> bool {
+        !self.completed && self.elapsed_s as f64 >= self.target_s as f64 * ratio
+    }
+}
+
+/// SLA compliance of one alarm.
+#[derive(Clone, Debug, Serialize)]
+pub struct SlaCompliance {
+    pub alarm_id: String,
+    pub severity: String,
+    pub source: String,
+    pub event: String,
+    pub status: String,
+    pub raised_at: String,
+    pub ack: Option<StageCompliance>,
+    pub clear: Option<StageCompliance>,
+}
+
+impl SlaCompliance {
+    pub fn breached(&self) -> bool {
+        self.ack
+            .iter()
+            .chain(&self.clear)
+            .any(|stage| stage.breached)
+    }
+
+    pub fn at_risk(&self, ratio: f64) -> bool {
+        self.ack
+            .iter()
+            .chain(&self.clear)
+            .any(|stage| stage.at_risk(ratio))
+    }
+}
+
+fn parse_time(value: Option<&str>) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value?)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// When the alarm was raised; alarms recorded before raise times were kept fall
+/// back to their last update.
+pub fn raised_at(alarm: &AlarmRecord) -> Option<DateTime<Utc>> {
+    parse_time(alarm.raised_at.as_deref().or(Some(&alarm.timestamp)))
+}
+
+/// Compliance of `alarm` with `sla` as of `now`. Clearing an alarm also counts
+/// as responding to it when it was never acknowledged.
+pub fn evaluate(alarm: &AlarmRecord, sla: &AlarmSla, now: DateTime<Utc>) -> Option<SlaCompliance> {
+    let raised = raised_at(alarm)?;
+    let acknowledged = parse_time(alarm.acknowledged_at.as_deref());
+    let cleared = parse_time(alarm.cleared_at.as_deref());
+    Some(SlaCompliance {
+        alarm_id: alarm.id.clone(),
+        severity: alarm.severity.clone(),
+        source: alarm.source.clone(),
+        event: alarm.event.clone(),
+        status: alarm.status.clone(),
+        raised_at: raised.to_rfc3339(),
+        ack: sla
+            .ack_within_s
+            .map(|target| StageCompliance::measure(raised, acknowledged.or(cleared), target, now)),
+        clear: sla
+            .clear_within_s
+            .map(|target| StageCompliance::measure(raised, cleared, target, now)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::alarm_record;
+
+    fn alarm() -> AlarmRecord {
+        AlarmRecord {
+            timestamp: "2026-05-01T10:05:00Z".to_string(),
+            duplicate_count: 3,
+            raised_at: Some("2026-05-01T10:00:00Z".to_string()),
+            ..alarm_record("a1", "p1", "critical")
+        }
+    }
+
+    fn sla() -> AlarmSla {
+        AlarmSla {
+            severity: "critical".to_string(),
+            ack_within_s: Some(120),
+            clear_within_s: Some(900),
+            updated_at: String::new(),
+        }
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    #[test]
+    fn pending_alarms_are_measured_against_now() {
+        let compliance = evaluate(&alarm(), &sla(), at("2026-05-01T10:01:50Z")).unwrap();
+        let ack = compliance.ack.as_ref().unwrap();
+
+        assert_eq!(ack.elapsed_s, 110);
+        assert!(!ack.breached);
+        assert!(compliance.at_risk(DEFAULT_AT_RISK_RATIO));
+        assert!(!compliance.at_risk(0.95));
+        assert_eq!(ack.due_at, at("2026-05-01T10:02:00Z").to_rfc3339());
+
+        let later = evaluate(&alarm(), &sla(), at("2026-05-01T10:03:00Z")).unwrap();
+        assert!(later.breached());
+    }
+
+    #[test]
+    fn acknowledging_stops_the_ack_clock_once() {
+        let mut alarm = alarm();
+        record_status(&mut alarm, "acknowledged", at("2026-05-01T10:01:00Z"));
+        record_status(&mut alarm, "acknowledged", at("2026-05-01T10:30:00Z"));
+
+        let compliance = evaluate(&alarm, &sla(), at("2026-05-01T10:20:00Z")).unwrap();
+        let ack = compliance.ack.unwrap();
+        assert_eq!(ack.elapsed_s, 60);
+        assert!(ack.completed && !ack.breached);
+        assert!(compliance.clear.unwrap().breached);
+
+        assert!(sla().validate().is_ok());
+        let mut invalid = sla();
+        invalid.ack_within_s = Some(0);
+        assert!(invalid.validate().is_err());
+    }
+}
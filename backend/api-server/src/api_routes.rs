@@ -1,28 +1,95 @@
 use actix_web::web;
 
 use crate::{
-    authority_handlers, binding_handlers, driver_handlers, handlers, i3x_handlers, mesh_handlers,
-    pea_handlers, pol_handlers, runtime_handlers, scenario_handlers, timeseries_handlers,
+    approval_handlers, attachment_handlers, audit_handlers, authority_handlers,
+    automation_handlers, binding_handlers, capture_handlers, chaos_handlers,
+    computed_alarm_handlers, config_bundle_handlers, desired_state_handlers, driver_handlers,
+    environment_handlers, golden_run_handlers, handlers, i3x_handlers, ingest_processor_handlers,
+    interlock_handlers, mesh_handlers, message_handlers, on_call_handlers, pea_handlers,
+    playback_handlers, pol_handlers, presence_handlers, provisioning_handlers, redaction_handlers,
+    runtime_handlers, scenario_handlers, schema_handlers, script_hook_handlers,
+    severity_profile_handlers, step_library_handlers, tenant_handlers, timeseries_handlers,
+    webhook_handlers,
 };
 
 pub fn configure_api(cfg: &mut web::ServiceConfig) {
     cfg
         // Dashboard endpoints
         .route("/metrics", web::get().to(handlers::get_metrics))
+        .route("/admin/tasks", web::get().to(handlers::get_tasks))
+        .route("/admin/chaos", web::get().to(chaos_handlers::get_chaos))
+        .route("/admin/chaos", web::put().to(chaos_handlers::put_chaos))
         .route("/machines", web::get().to(handlers::get_machines))
         .route("/machines/{id}", web::get().to(handlers::get_machine_by_id))
         .route("/alarms", web::get().to(handlers::get_alarms))
+        .route("/alarms/groups", web::get().to(handlers::get_alarm_groups))
+        .route("/alarms/sla", web::get().to(pol_handlers::get_alarm_sla_report))
+        .route("/alarms/sla/at-risk", web::get().to(pol_handlers::get_alarms_sla_at_risk))
+        .route("/alarms/{id}/soe", web::get().to(pol_handlers::get_alarm_soe))
+        .route("/soe", web::get().to(pol_handlers::list_soe_records))
         .route("/alarms/{id}/ack", web::post().to(pol_handlers::ack_alarm))
         .route("/alarms/{id}/shelve", web::post().to(pol_handlers::shelve_alarm))
         .route("/alarms/{id}/action", web::post().to(pol_handlers::action_alarm))
         .route("/alarms/{id}", web::delete().to(pol_handlers::delete_alarm))
+        .route("/alarm-slas", web::get().to(pol_handlers::list_alarm_slas))
+        .route("/alarm-slas/{severity}", web::put().to(pol_handlers::put_alarm_sla))
+        .route("/alarm-slas/{severity}", web::delete().to(pol_handlers::delete_alarm_sla))
+        .route("/severity-profile", web::get().to(severity_profile_handlers::get_severity_profile))
+        .route("/severity-profile", web::put().to(severity_profile_handlers::put_severity_profile))
+        .route(
+            "/severity-profile",
+            web::delete().to(severity_profile_handlers::reset_severity_profile),
+        )
+        .route("/messages", web::get().to(message_handlers::list_messages))
+        .route("/messages/languages", web::get().to(message_handlers::get_languages))
+        .route("/messages/{code}", web::get().to(message_handlers::get_message))
+        .route("/messages/{code}", web::put().to(message_handlers::put_message))
+        .route("/messages/{code}", web::delete().to(message_handlers::delete_message))
+        // Payload schemas per topic family
+        .route("/schemas", web::get().to(schema_handlers::list_schemas))
+        .route("/schemas/violations", web::get().to(schema_handlers::get_violations))
+        .route("/schemas/{family}", web::get().to(schema_handlers::get_schema))
+        .route("/schemas/{family}", web::put().to(schema_handlers::put_schema))
+        .route("/schemas/{family}", web::delete().to(schema_handlers::delete_schema))
+        .route("/schemas/{family}/validate", web::post().to(schema_handlers::validate_payload))
+        // Ingest processors applied to samples before storage
+        .route("/ingest/processor-kinds", web::get().to(ingest_processor_handlers::list_processor_kinds))
+        .route("/ingest/processors", web::get().to(ingest_processor_handlers::list_processors))
+        .route("/ingest/processors", web::post().to(ingest_processor_handlers::create_processor))
+        .route("/ingest/processors/preview", web::post().to(ingest_processor_handlers::preview_processors))
+        .route("/ingest/processors/{id}", web::get().to(ingest_processor_handlers::get_processor))
+        .route("/ingest/processors/{id}", web::put().to(ingest_processor_handlers::update_processor))
+        .route("/ingest/processors/{id}", web::delete().to(ingest_processor_handlers::delete_processor))
+        .route("/scripts", web::get().to(script_hook_handlers::list_script_hooks))
+        .route("/scripts", web::post().to(script_hook_handlers::create_script_hook))
+        .route("/scripts/{id}", web::get().to(script_hook_handlers::get_script_hook))
+        .route("/scripts/{id}", web::put().to(script_hook_handlers::update_script_hook))
+        .route("/scripts/{id}", web::delete().to(script_hook_handlers::delete_script_hook))
         .route("/alarm-rules", web::get().to(pol_handlers::list_alarm_rules))
         .route("/alarm-rules", web::post().to(pol_handlers::create_alarm_rule))
         .route("/alarm-rules/{id}", web::put().to(pol_handlers::update_alarm_rule))
         .route("/alarm-rules/{id}", web::delete().to(pol_handlers::delete_alarm_rule))
+        // Alarms computed from expressions over several telemetry keys
+        .route("/computed-alarms", web::get().to(computed_alarm_handlers::list_computed_alarms))
+        .route("/computed-alarms", web::post().to(computed_alarm_handlers::create_computed_alarm))
+        .route(
+            "/computed-alarms/evaluate",
+            web::post().to(computed_alarm_handlers::evaluate_computed_alarm),
+        )
+        .route("/computed-alarms/{id}", web::get().to(computed_alarm_handlers::get_computed_alarm))
+        .route(
+            "/computed-alarms/{id}",
+            web::put().to(computed_alarm_handlers::update_computed_alarm),
+        )
+        .route(
+            "/computed-alarms/{id}",
+            web::delete().to(computed_alarm_handlers::delete_computed_alarm),
+        )
         .route("/blackouts", web::get().to(pol_handlers::list_blackouts))
         .route("/blackouts", web::post().to(pol_handlers::create_blackout))
         .route("/blackouts/{id}", web::delete().to(pol_handlers::delete_blackout))
+        .route("/calendar.ics", web::get().to(pol_handlers::export_calendar))
+        .route("/calendar.ics", web::post().to(pol_handlers::import_calendar))
         .route("/timeseries/{machine_id}", web::get().to(handlers::get_timeseries))
         // Time-series historical data
         .route("/ts/keys", web::get().to(timeseries_handlers::get_ts_keys))
@@ -30,21 +97,142 @@ pub fn configure_api(cfg: &mut web::ServiceConfig) {
         .route("/ts/latest", web::get().to(timeseries_handlers::get_ts_latest))
         .route("/ts/config", web::get().to(timeseries_handlers::get_ts_config))
         .route("/ts/config", web::put().to(timeseries_handlers::update_ts_config))
+        .route("/ts/archive", web::get().to(timeseries_handlers::list_ts_archive))
+        .route("/ts/archive/restore", web::get().to(timeseries_handlers::restore_ts_archive))
+        .route("/ts/correlate", web::get().to(timeseries_handlers::correlate_timeseries))
+        .route("/ts/forecast", web::get().to(timeseries_handlers::forecast_timeseries))
+        .route(
+            "/ts/stats/distribution",
+            web::get().to(timeseries_handlers::get_ts_distribution),
+        )
+        // Condition-based capture into incident recordings
+        .route("/ts/capture-triggers", web::get().to(capture_handlers::list_capture_triggers))
+        .route("/ts/capture-triggers", web::post().to(capture_handlers::create_capture_trigger))
+        .route("/ts/capture-triggers/{id}", web::get().to(capture_handlers::get_capture_trigger))
+        .route(
+            "/ts/capture-triggers/{id}",
+            web::put().to(capture_handlers::update_capture_trigger),
+        )
+        .route(
+            "/ts/capture-triggers/{id}",
+            web::delete().to(capture_handlers::delete_capture_trigger),
+        )
+        .route("/ts/incidents", web::get().to(capture_handlers::list_incident_recordings))
+        .route("/ts/incidents/{id}", web::get().to(capture_handlers::get_incident_recording))
+        .route(
+            "/ts/incidents/{id}",
+            web::delete().to(capture_handlers::delete_incident_recording),
+        )
+        .route("/ts/bands", web::get().to(timeseries_handlers::list_bands))
+        .route("/ts/bands", web::post().to(timeseries_handlers::create_band))
+        .route("/ts/bands/{id}", web::put().to(timeseries_handlers::update_band))
+        .route("/ts/bands/{id}", web::delete().to(timeseries_handlers::delete_band))
+        .route("/ts/key-hints", web::get().to(timeseries_handlers::list_key_hints))
+        .route("/ts/key-hints", web::post().to(timeseries_handlers::create_key_hint))
+        .route("/ts/key-hints/{id}", web::put().to(timeseries_handlers::update_key_hint))
+        .route("/ts/key-hints/{id}", web::delete().to(timeseries_handlers::delete_key_hint))
+        .route("/ts/aliases", web::get().to(timeseries_handlers::list_aliases))
+        .route("/ts/aliases", web::post().to(timeseries_handlers::create_alias))
+        .route("/ts/aliases/{id}", web::put().to(timeseries_handlers::update_alias))
+        .route("/ts/aliases/{id}", web::delete().to(timeseries_handlers::delete_alias))
+        .route("/ts/aliases/{id}/migrate", web::post().to(timeseries_handlers::migrate_alias))
+        .route("/ts/saved-queries", web::get().to(timeseries_handlers::list_saved_queries))
+        .route("/ts/saved-queries", web::post().to(timeseries_handlers::create_saved_query))
+        .route("/ts/saved-queries/{id}", web::get().to(timeseries_handlers::get_saved_query))
+        .route("/ts/saved-queries/{id}", web::put().to(timeseries_handlers::update_saved_query))
+        .route("/ts/saved-queries/{id}", web::delete().to(timeseries_handlers::delete_saved_query))
+        // Historical playback
+        .route("/playback", web::get().to(playback_handlers::list_playbacks))
+        .route("/playback/start", web::post().to(playback_handlers::start_playback))
+        .route("/playback/{id}", web::get().to(playback_handlers::get_playback))
+        .route("/playback/{id}/pause", web::post().to(playback_handlers::pause_playback))
+        .route("/playback/{id}/resume", web::post().to(playback_handlers::resume_playback))
+        .route("/playback/{id}/seek", web::post().to(playback_handlers::seek_playback))
+        .route("/playback/{id}/stop", web::post().to(playback_handlers::stop_playback))
         // PEA CRUD
         .route("/pea", web::get().to(pea_handlers::list_peas))
         .route("/pea", web::post().to(pea_handlers::create_pea))
+        .route("/pea/import", web::post().to(pea_handlers::import_pea_mtp))
+        .route("/pea/opcua/browse", web::post().to(pea_handlers::browse_opcua))
+        .route("/pea/templates", web::get().to(pea_handlers::list_pea_templates))
+        .route(
+            "/pea/from-template/{template_id}",
+            web::post().to(pea_handlers::create_pea_from_template),
+        )
+        .route("/pea/import-csv", web::post().to(pea_handlers::import_pea_sheet))
+        .route("/pea/bulk", web::post().to(pea_handlers::bulk_pea_action))
+        .route("/pea/status", web::get().to(pea_handlers::list_pea_statuses))
+        .route("/pea/drift", web::get().to(pea_handlers::get_pea_drift))
+        .route("/pea/drift/reconcile", web::post().to(pea_handlers::reconcile_pea_drift))
+        .route("/apply", web::get().to(desired_state_handlers::get_desired_state))
+        .route("/apply", web::post().to(desired_state_handlers::apply_desired_state))
+        .route("/apply", web::delete().to(desired_state_handlers::clear_desired_state))
+        .route("/config/bundle", web::get().to(config_bundle_handlers::export_bundle))
+        .route("/config/bundle/diff", web::post().to(config_bundle_handlers::diff_bundle))
+        .route("/config/bundle/apply", web::post().to(config_bundle_handlers::apply_bundle))
         .route("/pea/{id}", web::get().to(pea_handlers::get_pea))
         .route("/pea/{id}", web::put().to(pea_handlers::update_pea))
         .route("/pea/{id}", web::delete().to(pea_handlers::delete_pea))
         // PEA Lifecycle
         .route("/pea/{id}/deploy", web::post().to(pea_handlers::deploy_pea))
         .route("/pea/{id}/undeploy", web::post().to(pea_handlers::undeploy_pea))
+        .route("/pea/{id}/export", web::get().to(pea_handlers::export_pea))
+        .route("/pea/{id}/clone", web::post().to(pea_handlers::clone_pea))
+        .route("/pea/{id}/diff", web::get().to(pea_handlers::diff_pea))
+        .route("/pea/{id}/restore", web::post().to(pea_handlers::restore_pea))
+        .route(
+            "/pea/{id}/archive",
+            web::delete().to(pea_handlers::purge_archived_pea),
+        )
+        .route("/pea/{id}/revisions", web::get().to(pea_handlers::list_pea_revisions))
+        .route(
+            "/pea/{id}/revisions/{revision}",
+            web::get().to(pea_handlers::get_pea_revision),
+        )
+        .route(
+            "/pea/{id}/rollback/{revision}",
+            web::post().to(pea_handlers::rollback_pea),
+        )
+        .route("/pea/{id}/birth", web::get().to(pea_handlers::get_pea_birth))
+        .route("/pea/{id}/status", web::get().to(pea_handlers::get_pea_status))
+        .route("/pea/{id}/impact", web::get().to(pea_handlers::get_pea_impact))
+        .route("/pea/{id}/capabilities", web::get().to(pea_handlers::get_pea_capabilities))
+        .route("/pea/{id}/tag-mappings", web::get().to(pea_handlers::get_pea_tag_mappings))
+        .route("/pea/{id}/tag-mappings", web::put().to(pea_handlers::update_pea_tag_mappings))
+        .route(
+            "/pea/{id}/lifecycle-history",
+            web::get().to(pea_handlers::get_pea_lifecycle_history),
+        )
+        .route("/audit", web::get().to(audit_handlers::get_audit_log))
         .route("/pea/{id}/start", web::post().to(pea_handlers::start_pea))
         .route("/pea/{id}/stop", web::post().to(pea_handlers::stop_pea))
         .route(
             "/pea/{id}/services/{service_tag}/command",
             web::post().to(pea_handlers::command_service),
         )
+        .route(
+            "/pea/{id}/services/{service_tag}/parameters",
+            web::post().to(pea_handlers::write_service_parameters),
+        )
+        .route(
+            "/pea/{id}/services/{service_tag}/state-machine",
+            web::get().to(pea_handlers::get_service_state_machine),
+        )
+        .route(
+            "/pea/{id}/services/{service_tag}/state-stats",
+            web::get().to(pea_handlers::get_service_state_stats),
+        )
+        // PEA attachments
+        .route("/pea/{id}/attachments", web::get().to(attachment_handlers::list_attachments))
+        .route("/pea/{id}/attachments", web::post().to(attachment_handlers::upload_attachment))
+        .route(
+            "/pea/{id}/attachments/{attachment_id}",
+            web::get().to(attachment_handlers::download_attachment),
+        )
+        .route(
+            "/pea/{id}/attachments/{attachment_id}",
+            web::delete().to(attachment_handlers::delete_attachment),
+        )
         // Runtime Nodes
         .route("/runtime/nodes", web::get().to(runtime_handlers::list_runtime_nodes))
         .route("/runtime/nodes", web::post().to(runtime_handlers::create_runtime_node))
@@ -54,17 +242,11 @@ pub fn configure_api(cfg: &mut web::ServiceConfig) {
             web::get().to(runtime_handlers::get_runtime_node_status),
         )
         .route("/runtime/nodes/{id}", web::put().to(runtime_handlers::update_runtime_node))
-        .route(
-            "/runtime/nodes/{id}",
-            web::delete().to(runtime_handlers::delete_runtime_node),
-        )
+        .route("/runtime/nodes/{id}", web::delete().to(runtime_handlers::delete_runtime_node))
         .route("/runtime/nodes/{id}/test", web::post().to(runtime_handlers::test_runtime_node))
         // Drivers
         .route("/drivers/catalog", web::get().to(driver_handlers::get_driver_catalog))
-        .route(
-            "/drivers/catalog/{id}/schema",
-            web::get().to(driver_handlers::get_driver_schema),
-        )
+        .route("/drivers/catalog/{id}/schema", web::get().to(driver_handlers::get_driver_schema))
         .route("/drivers", web::get().to(driver_handlers::list_drivers))
         .route("/drivers", web::post().to(driver_handlers::create_driver))
         .route("/drivers/{id}", web::get().to(driver_handlers::get_driver))
@@ -89,95 +271,223 @@ pub fn configure_api(cfg: &mut web::ServiceConfig) {
         // Authority
         .route("/authority/{pea_id}", web::get().to(authority_handlers::get_authority_state))
         .route("/authority/{pea_id}", web::post().to(authority_handlers::set_authority_state))
+        .route("/authority/{pea_id}/audit", web::get().to(authority_handlers::get_authority_audit))
+        // Interlocks
+        .route("/interlocks", web::get().to(interlock_handlers::list_interlocks))
+        .route("/interlocks", web::post().to(interlock_handlers::create_interlock))
+        .route("/interlocks/violations", web::get().to(interlock_handlers::list_violations))
+        .route("/interlocks/{id}", web::get().to(interlock_handlers::get_interlock))
+        .route("/interlocks/{id}", web::put().to(interlock_handlers::update_interlock))
+        .route("/interlocks/{id}", web::delete().to(interlock_handlers::delete_interlock))
+        // Dual-confirmation approvals
+        .route("/approval-policies", web::get().to(approval_handlers::list_approval_policies))
+        .route("/approval-policies", web::post().to(approval_handlers::create_approval_policy))
+        .route("/approval-policies/{id}", web::put().to(approval_handlers::update_approval_policy))
         .route(
-            "/authority/{pea_id}/audit",
-            web::get().to(authority_handlers::get_authority_audit),
+            "/approval-policies/{id}",
+            web::delete().to(approval_handlers::delete_approval_policy),
         )
+        .route("/approvals", web::get().to(approval_handlers::list_approvals))
+        .route("/approvals/{id}", web::get().to(approval_handlers::get_approval))
+        .route("/approvals/{id}/approve", web::post().to(approval_handlers::approve))
+        .route("/approvals/{id}/reject", web::post().to(approval_handlers::reject))
         // Recipes
         .route("/recipes", web::get().to(pea_handlers::list_recipes))
         .route("/recipes", web::post().to(pea_handlers::create_recipe))
         .route("/recipes/{id}", web::put().to(pea_handlers::update_recipe))
         .route("/recipes/{id}", web::delete().to(pea_handlers::delete_recipe))
         .route("/recipes/{id}/execute", web::post().to(pea_handlers::execute_recipe))
+        .route("/recipes/{id}/campaign", web::post().to(pea_handlers::start_recipe_campaign))
+        .route("/recipes/campaigns", web::get().to(pea_handlers::list_recipe_campaigns))
+        .route("/recipes/campaigns/{id}", web::get().to(pea_handlers::get_recipe_campaign))
+        .route(
+            "/recipes/campaigns/{id}/abort",
+            web::post().to(pea_handlers::abort_recipe_campaign),
+        )
+        .route(
+            "/recipes/{id}/steps",
+            web::get().to(step_library_handlers::get_expanded_recipe_steps),
+        )
+        .route("/environments", web::get().to(environment_handlers::list_environments))
+        .route("/environments/{id}", web::get().to(environment_handlers::get_environment))
+        .route("/environments/{id}", web::put().to(environment_handlers::put_environment))
+        .route(
+            "/environments/{id}",
+            web::delete().to(environment_handlers::delete_environment),
+        )
+        .route("/step-library", web::get().to(step_library_handlers::list_library_steps))
+        .route("/step-library", web::post().to(step_library_handlers::create_library_step))
+        .route("/step-library/{id}", web::get().to(step_library_handlers::get_library_step))
+        .route("/step-library/{id}", web::put().to(step_library_handlers::update_library_step))
+        .route(
+            "/step-library/{id}",
+            web::delete().to(step_library_handlers::delete_library_step),
+        )
+        .route("/recipes/executions", web::get().to(pea_handlers::list_recipe_executions))
+        .route("/recipes/executions/{id}", web::get().to(pea_handlers::get_recipe_execution))
         .route(
-            "/recipes/executions",
-            web::get().to(pea_handlers::list_recipe_executions),
+            "/recipes/executions/{id}/abort",
+            web::post().to(pea_handlers::abort_recipe_execution),
         )
         .route(
-            "/recipes/executions/{id}",
-            web::get().to(pea_handlers::get_recipe_execution),
+            "/recipes/executions/{id}/golden",
+            web::post().to(golden_run_handlers::mark_golden_execution),
         )
+        .route(
+            "/recipes/executions/{id}/compare/{golden_id}",
+            web::get().to(golden_run_handlers::compare_with_golden),
+        )
+        .route("/recipes/{id}/golden", web::get().to(golden_run_handlers::get_golden_run))
+        .route("/recipes/{id}/golden", web::delete().to(golden_run_handlers::delete_golden_run))
         // POL topology
         .route("/pol/topology", web::get().to(pol_handlers::get_topology))
         .route("/pol/topology", web::put().to(pol_handlers::put_topology))
+        // Operator presence
+        .route("/presence", web::get().to(presence_handlers::list_presence))
+        .route("/presence/claim", web::post().to(presence_handlers::claim_area))
+        .route("/presence/takeover", web::post().to(presence_handlers::take_over_area))
+        .route("/presence/heartbeat", web::post().to(presence_handlers::heartbeat_area))
+        .route("/presence/release", web::post().to(presence_handlers::release_area))
+        // Redaction rules
+        .route("/redaction-rules", web::get().to(redaction_handlers::list_redaction_rules))
+        .route("/redaction-rules", web::post().to(redaction_handlers::create_redaction_rule))
+        .route("/redaction-rules/{id}", web::put().to(redaction_handlers::update_redaction_rule))
+        .route("/redaction-rules/{id}", web::delete().to(redaction_handlers::delete_redaction_rule))
+        // Webhooks
+        .route("/webhooks", web::get().to(webhook_handlers::list_webhooks))
+        .route("/webhooks", web::post().to(webhook_handlers::create_webhook))
+        .route("/webhooks/{id}", web::get().to(webhook_handlers::get_webhook))
+        .route("/webhooks/{id}", web::put().to(webhook_handlers::update_webhook))
+        .route("/webhooks/{id}", web::delete().to(webhook_handlers::delete_webhook))
+        .route("/webhooks/{id}/deliveries", web::get().to(webhook_handlers::list_deliveries))
+        .route("/webhooks/{id}/test", web::post().to(webhook_handlers::test_webhook))
+        .route("/on-call/schedules", web::get().to(on_call_handlers::list_schedules))
+        .route("/on-call/schedules", web::post().to(on_call_handlers::create_schedule))
+        .route("/on-call/schedules/{id}", web::get().to(on_call_handlers::get_schedule))
+        .route("/on-call/schedules/{id}", web::put().to(on_call_handlers::update_schedule))
+        .route("/on-call/schedules/{id}", web::delete().to(on_call_handlers::delete_schedule))
+        .route(
+            "/on-call/schedules/{id}/current",
+            web::get().to(on_call_handlers::get_current_on_call),
+        )
+        .route(
+            "/on-call/schedules/{id}/overrides",
+            web::post().to(on_call_handlers::add_override),
+        )
+        .route(
+            "/on-call/schedules/{id}/overrides/{override_id}",
+            web::delete().to(on_call_handlers::delete_override),
+        )
+        .route("/on-call/escalations", web::get().to(on_call_handlers::list_escalations))
+        .route(
+            "/notification-preferences",
+            web::get().to(webhook_handlers::list_notification_preferences),
+        )
+        .route(
+            "/notification-preferences/{user_id}",
+            web::get().to(webhook_handlers::get_notification_preferences),
+        )
+        .route(
+            "/notification-preferences/{user_id}",
+            web::put().to(webhook_handlers::put_notification_preferences),
+        )
+        .route(
+            "/notification-preferences/{user_id}",
+            web::delete().to(webhook_handlers::delete_notification_preferences),
+        )
+        // Automation
+        .route("/automation/rules", web::get().to(automation_handlers::list_automation_rules))
+        .route("/automation/rules", web::post().to(automation_handlers::create_automation_rule))
+        .route("/automation/rules/{id}", web::get().to(automation_handlers::get_automation_rule))
+        .route("/automation/rules/{id}", web::put().to(automation_handlers::update_automation_rule))
+        .route(
+            "/automation/rules/{id}",
+            web::delete().to(automation_handlers::delete_automation_rule),
+        )
+        .route(
+            "/automation/rules/{id}/evaluate",
+            web::post().to(automation_handlers::evaluate_automation_rule),
+        )
+        .route("/automation/log", web::get().to(automation_handlers::list_automation_log))
+        // Tenants
+        .route("/tenants", web::get().to(tenant_handlers::list_tenants))
+        .route("/tenants", web::post().to(tenant_handlers::create_tenant))
+        .route("/tenants/{id}", web::get().to(tenant_handlers::get_tenant))
+        .route("/tenants/{id}", web::put().to(tenant_handlers::update_tenant))
+        .route("/tenants/{id}", web::delete().to(tenant_handlers::delete_tenant))
+        .route("/tenants/{id}/tokens", web::post().to(tenant_handlers::create_tenant_token))
+        .route(
+            "/tenants/{id}/tokens/{token_id}",
+            web::delete().to(tenant_handlers::delete_tenant_token),
+        )
+        .route("/tenants/{id}/usage", web::get().to(tenant_handlers::get_tenant_usage))
         // Mesh / Zenoh Admin
         .route("/mesh/nodes", web::get().to(mesh_handlers::get_nodes))
         .route("/mesh/router", web::get().to(mesh_handlers::get_router_info))
         .route("/mesh/links", web::get().to(mesh_handlers::get_links))
+        .route("/mesh/subscribers", web::get().to(mesh_handlers::get_subscribers))
         .route("/mesh/keys", web::get().to(mesh_handlers::get_keys))
+        .route("/mesh/keys", web::delete().to(mesh_handlers::delete_keys))
+        .route("/mesh/keys/tree", web::get().to(mesh_handlers::get_key_tree))
+        .route("/mesh/keys/{key_expr:.*}", web::get().to(mesh_handlers::get_key_value))
+        .route("/mesh/config", web::post().to(mesh_handlers::update_config))
+        .route("/mesh/config/changes", web::get().to(mesh_handlers::list_config_changes))
         .route(
-            "/mesh/keys/{key_expr:.*}",
-            web::get().to(mesh_handlers::get_key_value),
+            "/mesh/config/rollback/{change_id}",
+            web::post().to(mesh_handlers::rollback_config_change),
         )
-        .route("/mesh/config", web::post().to(mesh_handlers::update_config))
+        .route("/mesh/generate-config", web::post().to(mesh_handlers::generate_node_config))
+        // Node provisioning
+        .route("/provisioning/nodes", web::get().to(provisioning_handlers::list_planned_nodes))
+        .route("/provisioning/nodes", web::post().to(provisioning_handlers::create_planned_node))
+        .route("/provisioning/nodes/{id}", web::get().to(provisioning_handlers::get_planned_node))
         .route(
-            "/mesh/generate-config",
-            web::post().to(mesh_handlers::generate_node_config),
+            "/provisioning/nodes/{id}",
+            web::put().to(provisioning_handlers::update_planned_node),
+        )
+        .route(
+            "/provisioning/nodes/{id}",
+            web::delete().to(provisioning_handlers::delete_planned_node),
+        )
+        .route(
+            "/provisioning/nodes/{id}/token",
+            web::post().to(provisioning_handlers::issue_node_token),
         )
-        // Durins-Forge Scenario Launcher
-        .route("/scenarios", web::get().to(scenario_handlers::list_scenarios))
-        .route("/scenarios/launch", web::post().to(scenario_handlers::launch_scenario))
         .route(
-            "/scenarios/{run_id}/status",
-            web::get().to(scenario_handlers::get_scenario_status),
+            "/provisioning/download/{token}",
+            web::get().to(provisioning_handlers::download_node_config),
         )
         .route(
-            "/scenarios/running",
-            web::get().to(scenario_handlers::list_running_scenarios),
+            "/provisioning/activate/{token}",
+            web::post().to(provisioning_handlers::activate_node),
         )
+        // Durins-Forge Scenario Launcher
+        .route("/scenarios", web::get().to(scenario_handlers::list_scenarios))
+        .route("/scenarios/launch", web::post().to(scenario_handlers::launch_scenario))
+        .route("/scenarios/{run_id}/status", web::get().to(scenario_handlers::get_scenario_status))
+        .route("/scenarios/running", web::get().to(scenario_handlers::list_running_scenarios))
         // I3X RFC 4.1 - Exploratory (Discovery)
         .route("/namespaces", web::get().to(i3x_handlers::get_namespaces))
         .route("/objecttypes", web::get().to(i3x_handlers::get_object_types))
         .route("/objecttypes/query", web::post().to(i3x_handlers::query_object_types))
-        .route(
-            "/objecttypes/{elementId}",
-            web::get().to(i3x_handlers::get_object_type_by_id),
-        )
+        .route("/objecttypes/{elementId}", web::get().to(i3x_handlers::get_object_type_by_id))
         .route("/relationshiptypes", web::get().to(i3x_handlers::get_relationship_types))
-        .route(
-            "/relationshiptypes/query",
-            web::post().to(i3x_handlers::query_relationship_types),
-        )
+        .route("/relationshiptypes/query", web::post().to(i3x_handlers::query_relationship_types))
         .route(
             "/relationshiptypes/{elementId}",
             web::get().to(i3x_handlers::get_relationship_type_by_id),
         )
         .route("/objects", web::get().to(i3x_handlers::get_objects))
         .route("/objects/list", web::post().to(i3x_handlers::get_objects_list))
-        .route(
-            "/objects/related",
-            web::post().to(i3x_handlers::get_related_objects_bulk),
-        )
+        .route("/objects/related", web::post().to(i3x_handlers::get_related_objects_bulk))
         .route("/objects/{elementId}", web::get().to(i3x_handlers::get_object_by_id))
-        .route(
-            "/objects/{elementId}/related",
-            web::get().to(i3x_handlers::get_related_objects),
-        )
+        .route("/objects/{elementId}/related", web::get().to(i3x_handlers::get_related_objects))
         // I3X RFC 4.2.1 - Values (Read)
         .route("/objects/value", web::post().to(i3x_handlers::get_current_value_bulk))
-        .route(
-            "/objects/{elementId}/value",
-            web::get().to(i3x_handlers::get_current_value),
-        )
-        .route(
-            "/objects/{elementId}/history",
-            web::get().to(i3x_handlers::get_historical_values),
-        )
+        .route("/objects/{elementId}/value", web::get().to(i3x_handlers::get_current_value))
+        .route("/objects/{elementId}/history", web::get().to(i3x_handlers::get_historical_values))
         // I3X RFC 4.2.2 - Values (Write)
-        .route(
-            "/objects/{elementId}/value",
-            web::put().to(i3x_handlers::update_current_value),
-        )
+        .route("/objects/{elementId}/value", web::put().to(i3x_handlers::update_current_value))
         // WebSocket
         .route("/ws", web::get().to(crate::websocket::ws_handler));
 }
@@ -261,4 +571,922 @@ mod tests {
 
         assert_ne!(response.status(), StatusCode::NOT_FOUND);
     }
+
+    #[actix_web::test]
+    async fn interlock_violations_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/interlocks/violations")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn webhook_deliveries_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/webhooks/example/deliveries")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn pea_bulk_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::post()
+            .uri("/api/v1/pea/bulk?filter=name:pump")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn pea_template_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        for request in [
+            test::TestRequest::get().uri("/api/v1/pea/templates"),
+            test::TestRequest::post().uri("/api/v1/pea/from-template/pump"),
+            test::TestRequest::post().uri("/api/v1/pea/example/clone"),
+        ] {
+            let response = test::call_service(&app, request.to_request()).await;
+            assert_ne!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[actix_web::test]
+    async fn pea_revision_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        for request in [
+            test::TestRequest::get().uri("/api/v1/pea/example/revisions"),
+            test::TestRequest::get().uri("/api/v1/pea/example/revisions/1"),
+            test::TestRequest::post().uri("/api/v1/pea/example/rollback/1"),
+        ] {
+            let response = test::call_service(&app, request.to_request()).await;
+            assert_ne!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[actix_web::test]
+    async fn on_call_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        for request in [
+            test::TestRequest::get().uri("/api/v1/on-call/schedules/example/current"),
+            test::TestRequest::post().uri("/api/v1/on-call/schedules/example/overrides"),
+            test::TestRequest::get().uri("/api/v1/on-call/escalations"),
+        ] {
+            let response = test::call_service(&app, request.to_request()).await;
+            assert_ne!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[actix_web::test]
+    async fn notification_preferences_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/notification-preferences/example")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn pea_attachments_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/pea/example/attachments")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn ts_archive_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/ts/archive")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn tenant_usage_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/tenants/example/usage")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn pea_birth_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/pea/example/birth")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn automation_log_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/automation/log")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn provisioning_download_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/provisioning/download/fdn_example")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn mesh_config_rollback_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::post()
+            .uri("/api/v1/mesh/config/rollback/example")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn mesh_key_tree_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/mesh/keys/tree?prefix=entmoot")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn mesh_subscribers_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/mesh/subscribers")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn admin_tasks_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get().uri("/api/v1/admin/tasks").to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn calendar_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get().uri("/api/v1/calendar.ics").to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn environment_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        for request in [
+            test::TestRequest::get().uri("/api/v1/environments"),
+            test::TestRequest::get().uri("/api/v1/environments/prod"),
+            test::TestRequest::delete().uri("/api/v1/environments/prod"),
+        ] {
+            let response = test::call_service(&app, request.to_request()).await;
+            assert_ne!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[actix_web::test]
+    async fn step_library_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        for request in [
+            test::TestRequest::get().uri("/api/v1/step-library"),
+            test::TestRequest::get().uri("/api/v1/step-library/restart"),
+            test::TestRequest::delete().uri("/api/v1/step-library/restart"),
+            test::TestRequest::get().uri("/api/v1/recipes/example/steps"),
+        ] {
+            let response = test::call_service(&app, request.to_request()).await;
+            assert_ne!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[actix_web::test]
+    async fn recipe_campaign_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        for request in [
+            test::TestRequest::post().uri("/api/v1/recipes/example/campaign"),
+            test::TestRequest::get().uri("/api/v1/recipes/campaigns"),
+            test::TestRequest::get().uri("/api/v1/recipes/campaigns/example"),
+            test::TestRequest::post().uri("/api/v1/recipes/campaigns/example/abort"),
+        ] {
+            let response = test::call_service(&app, request.to_request()).await;
+            assert_ne!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[actix_web::test]
+    async fn golden_run_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        for request in [
+            test::TestRequest::post().uri("/api/v1/recipes/executions/example/golden"),
+            test::TestRequest::get().uri("/api/v1/recipes/executions/example/compare/golden-1"),
+            test::TestRequest::get().uri("/api/v1/recipes/example/golden"),
+            test::TestRequest::delete().uri("/api/v1/recipes/example/golden"),
+        ] {
+            let response = test::call_service(&app, request.to_request()).await;
+            assert_ne!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[actix_web::test]
+    async fn recipe_execution_abort_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::post()
+            .uri("/api/v1/recipes/executions/example/abort")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn service_parameters_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::post()
+            .uri("/api/v1/pea/example/services/Dose/parameters")
+            .set_json(serde_json::json!({"values": {"SP": 1.0}}))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn pea_impact_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/pea/example/impact")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn audit_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/audit?entity=pea&id=example")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn playback_control_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        for action in ["pause", "resume", "seek"] {
+            let request = test::TestRequest::post()
+                .uri(&format!("/api/v1/playback/example/{}", action))
+                .set_json(serde_json::json!({"position_ms": 0}))
+                .to_request();
+            let response = test::call_service(&app, request).await;
+            assert_ne!(response.status(), StatusCode::NOT_FOUND, "{}", action);
+        }
+    }
+
+    #[actix_web::test]
+    async fn pea_capabilities_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/pea/example/capabilities")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn pea_tag_mapping_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let get = test::TestRequest::get()
+            .uri("/api/v1/pea/example/tag-mappings")
+            .to_request();
+        let response = test::call_service(&app, get).await;
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+
+        let put = test::TestRequest::put()
+            .uri("/api/v1/pea/example/tag-mappings")
+            .set_json(serde_json::json!({"rows": []}))
+            .to_request();
+        let response = test::call_service(&app, put).await;
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn pea_archive_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        for request in [
+            test::TestRequest::post()
+                .uri("/api/v1/pea/example/restore")
+                .to_request(),
+            test::TestRequest::delete()
+                .uri("/api/v1/pea/example/archive")
+                .to_request(),
+        ] {
+            let response = test::call_service(&app, request).await;
+            assert_ne!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[actix_web::test]
+    async fn pea_status_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/pea/example/status?points=10")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn pea_status_list_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get().uri("/api/v1/pea/status").to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn ts_saved_queries_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/ts/saved-queries")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn ts_bands_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get().uri("/api/v1/ts/bands").to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn ts_correlate_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/ts/correlate?keys=rpm,vibration")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn ts_forecast_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/ts/forecast?key=fuel_level&horizon=3600000")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn ts_distribution_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/ts/stats/distribution?key=TT101&from=0&to=3600000")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn ts_capture_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        for uri in ["/api/v1/ts/capture-triggers", "/api/v1/ts/incidents/rec-1"] {
+            let request = test::TestRequest::get().uri(uri).to_request();
+            let response = test::call_service(&app, request).await;
+            assert_ne!(response.status(), StatusCode::NOT_FOUND, "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn ts_key_hints_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get().uri("/api/v1/ts/key-hints").to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn pea_lifecycle_history_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/pea/example/lifecycle-history")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn pea_drift_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::post()
+            .uri("/api/v1/pea/drift/reconcile")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn desired_state_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get().uri("/api/v1/apply").to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn config_bundle_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get().uri("/api/v1/config/bundle").to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn mesh_keys_delete_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::delete()
+            .uri("/api/v1/mesh/keys?prefix=entmoot/sim")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn alarm_groups_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get().uri("/api/v1/alarms/groups").to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn alarm_sla_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        for uri in ["/api/v1/alarm-slas", "/api/v1/alarms/sla", "/api/v1/alarms/sla/at-risk"] {
+            let request = test::TestRequest::get().uri(uri).to_request();
+            let response = test::call_service(&app, request).await;
+            assert_ne!(response.status(), StatusCode::NOT_FOUND, "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn soe_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        for uri in ["/api/v1/soe", "/api/v1/alarms/a1/soe"] {
+            let request = test::TestRequest::get().uri(uri).to_request();
+            let response = test::call_service(&app, request).await;
+            assert_ne!(response.status(), StatusCode::NOT_FOUND, "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn ts_aliases_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get().uri("/api/v1/ts/aliases").to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn presence_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get().uri("/api/v1/presence").to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn approval_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        for uri in ["/api/v1/approvals", "/api/v1/approval-policies"] {
+            let request = test::TestRequest::get().uri(uri).to_request();
+            let response = test::call_service(&app, request).await;
+            assert_ne!(response.status(), StatusCode::NOT_FOUND, "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn message_languages_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/messages/languages")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn severity_profile_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/severity-profile")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn chaos_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/admin/chaos")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn ingest_processor_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        for uri in [
+            "/api/v1/ingest/processor-kinds",
+            "/api/v1/ingest/processors",
+            "/api/v1/ingest/processors/scale-f-to-c",
+        ] {
+            let request = test::TestRequest::get().uri(uri).to_request();
+            let response = test::call_service(&app, request).await;
+            assert_ne!(response.status(), StatusCode::NOT_FOUND, "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn script_hook_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        for uri in ["/api/v1/scripts", "/api/v1/scripts/reject-stop-when-full"] {
+            let request = test::TestRequest::get().uri(uri).to_request();
+            let response = test::call_service(&app, request).await;
+            assert_ne!(response.status(), StatusCode::NOT_FOUND, "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn schema_routes_are_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        for uri in ["/api/v1/schemas", "/api/v1/schemas/violations"] {
+            let request = test::TestRequest::get().uri(uri).to_request();
+            let response = test::call_service(&app, request).await;
+            assert_ne!(response.status(), StatusCode::NOT_FOUND, "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn pea_mtp_import_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::post()
+            .uri("/api/v1/pea/import")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn pea_opcua_browse_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::post()
+            .uri("/api/v1/pea/opcua/browse")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn computed_alarms_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/computed-alarms")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn pea_export_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/pea/p1/export?format=aml")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn pea_diff_route_is_registered() {
+        let app = test::init_service(
+            App::new().service(web::scope("/api/v1").configure(configure_api)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/v1/pea/p1/diff?against=3")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_ne!(response.status(), StatusCode::NOT_FOUND);
+    }
 }
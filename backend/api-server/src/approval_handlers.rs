@@ -0,0 +1,401 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use shared::mtp::RecipeParameterValue;
+use tracing::{info, warn};
+
+use crate::approval_service::{
+    self, ApprovalPolicy, ApprovalStatus, GuardedAction, PendingApproval,
+};
+use crate::pea_handlers;
+use crate::runtime_store;
+use crate::service_command::{self, CommandOutcome};
+use crate::state::AppState;
+use crate::tenancy;
+
+pub const APPROVALS_TOPIC: &str = "entmoot/pol/approvals";
+
+#[derive(serde::Deserialize)]
+pub struct ApprovalPolicyPayload {
+    pub name: String,
+    pub pea_id: Option<String>,
+    pub service_tag: Option<String>,
+    pub action: GuardedAction,
+    pub enabled: Option<bool>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct ApprovalQuery {
+    pub status: Option<ApprovalStatus>,
+    pub pea_id: Option<String>,
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct DecisionPayload {
+    pub reason: Option<String>,
+}
+
+pub async fn list_approval_policies(state: web::Data<AppState>) -> impl Responder {
+    let policies = state.approval_policies.read().await;
+    let list: Vec<&ApprovalPolicy> = policies.values().collect();
+    HttpResponse::Ok().json(list)
+}
+
+pub async fn create_approval_policy(
+    state: web::Data<AppState>,
+    body: web::Json<ApprovalPolicyPayload>,
+) -> impl Responder {
+    let payload = body.into_inner();
+    if payload.name.trim().is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": "name is required"}));
+    }
+    let now = Utc::now().to_rfc3339();
+    let policy = ApprovalPolicy {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: payload.name,
+        pea_id: payload.pea_id,
+        service_tag: payload.service_tag,
+        action: payload.action,
+        enabled: payload.enabled.unwrap_or(true),
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    runtime_store::persist_json(&state.approval_policy_dir, &policy.id, &policy);
+    state
+        .approval_policies
+        .write()
+        .await
+        .insert(policy.id.clone(), policy.clone());
+    info!("Created approval policy: {} ({})", policy.name, policy.id);
+    HttpResponse::Created().json(policy)
+}
+
+pub async fn update_approval_policy(
+    state: web::Data<AppState>,
+    policy_id: web::Path<String>,
+    body: web::Json<ApprovalPolicyPayload>,
+) -> impl Responder {
+    let payload = body.into_inner();
+    if payload.name.trim().is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": "name is required"}));
+    }
+    let mut policies = state.approval_policies.write().await;
+    let Some(policy) = policies.get_mut(policy_id.as_str()) else {
+        return HttpResponse::NotFound()
+            .json(serde_json::json!({"error": "Approval policy not found"}));
+    };
+    policy.name = payload.name;
+    policy.pea_id = payload.pea_id;
+    policy.service_tag = payload.service_tag;
+    policy.action = payload.action;
+    policy.enabled = payload.enabled.unwrap_or(policy.enabled);
+    policy.updated_at = Utc::now().to_rfc3339();
+    runtime_store::persist_json(&state.approval_policy_dir, &policy.id, &*policy);
+    HttpResponse::Ok().json(&*policy)
+}
+
+pub async fn delete_approval_policy(
+    state: web::Data<AppState>,
+    policy_id: web::Path<String>,
+) -> impl Responder {
+    if state
+        .approval_policies
+        .write()
+        .await
+        .remove(policy_id.as_str())
+        .is_none()
+    {
+        return HttpResponse::NotFound()
+            .json(serde_json::json!({"error": "Approval policy not found"}));
+    }
+    runtime_store::delete_json(&state.approval_policy_dir, &policy_id);
+    HttpResponse::NoContent().finish()
+}
+
+/// GET /approvals — requests for guarded actions, newest first
+pub async fn list_approvals(
+    state: web::Data<AppState>,
+    query: web::Query<ApprovalQuery>,
+) -> impl Responder {
+    let now = Utc::now();
+    let mut approvals = state.approvals.write().await;
+    for approval in approvals.values_mut() {
+        approval.lapse(now);
+    }
+    let mut list: Vec<&PendingApproval> = approvals
+        .values()
+        .filter(|approval| query.status.is_none_or(|status| approval.status == status))
+        .filter(|approval| {
+            query
+                .pea_id
+                .as_deref()
+                .is_none_or(|pea_id| approval.pea_id == pea_id)
+        })
+        .collect();
+    list.sort_by(|a, b| b.requested_at.cmp(&a.requested_at));
+    HttpResponse::Ok().json(list)
+}
+
+pub async fn get_approval(
+    state: web::Data<AppState>,
+    approval_id: web::Path<String>,
+) -> impl Responder {
+    let mut approvals = state.approvals.write().await;
+    match approvals.get_mut(approval_id.as_str()) {
+        Some(approval) => {
+            approval.lapse(Utc::now());
+            HttpResponse::Ok().json(&*approval)
+        }
+        None => HttpResponse::NotFound().json(serde_json::json!({"error": "Approval not found"})),
+    }
+}
+
+/// POST /approvals/{id}/approve — a second user confirms the action, which is then carried out
+pub async fn approve(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    approval_id: web::Path<String>,
+    body: Option<web::Json<DecisionPayload>>,
+) -> impl Responder {
+    let reason = body.and_then(|body| body.into_inner().reason);
+    let approval = match decide(&state, &req, &approval_id, ApprovalStatus::Approved, reason).await
+    {
+        Ok(approval) => approval,
+        Err(response) => return response,
+    };
+    info!(
+        "Approval {} for {:?} on {} confirmed by {:?}",
+        approval.id, approval.action, approval.pea_id, approval.decided_by
+    );
+    carry_out(&state, &approval).await
+}
+
+/// POST /approvals/{id}/reject — a second user turns the action down
+pub async fn reject(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    approval_id: web::Path<String>,
+    body: Option<web::Json<DecisionPayload>>,
+) -> impl Responder {
+    let reason = body.and_then(|body| body.into_inner().reason);
+    match decide(&state, &req, &approval_id, ApprovalStatus::Rejected, reason).await {
+        Ok(approval) => HttpResponse::Ok().json(approval),
+        Err(response) => response,
+    }
+}
+
+async fn decide(
+    state: &AppState,
+    req: &HttpRequest,
+    approval_id: &str,
+    status: ApprovalStatus,
+    reason: Option<String>,
+) -> Result<PendingApproval, HttpResponse> {
+    let Some(caller) = tenancy::authenticated_caller(state, req).await else {
+        return Err(HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": "Deciding an approval requires an API token holding the approver role"
+        })));
+    };
+    let approval = {
+        let mut approvals = state.approvals.write().await;
+        let Some(approval) = approvals.get_mut(approval_id) else {
+            return Err(
+                HttpResponse::NotFound().json(serde_json::json!({"error": "Approval not found"}))
+            );
+        };
+        approval.lapse(Utc::now());
+        let actor_id = approval
+            .check_decider(&caller)
+            .map_err(|e| HttpResponse::Forbidden().json(serde_json::json!({"error": e})))?;
+        approval.decide(status, actor_id, reason);
+        approval.clone()
+    };
+    publish(state, &approval).await;
+    Ok(approval)
+}
+
+/// Runs a confirmed action as the user who requested it.
+async fn carry_out(state: &AppState, approval: &PendingApproval) -> HttpResponse {
+    let requested_by = approval.requested_by.clone();
+    match &approval.action {
+        GuardedAction::ServiceCommand { command } => {
+            let Some(service_tag) = approval.service_tag.as_deref() else {
+                return HttpResponse::BadRequest()
+                    .json(serde_json::json!({"error": "Approval has no service"}));
+            };
+            match service_command::publish(
+                state,
+                requested_by,
+                "approval",
+                &approval.pea_id,
+                service_tag,
+                *command,
+                approval.procedure_id,
+                approval.parameters.as_deref(),
+            )
+            .await
+            {
+                Ok(()) => {
+                    service_command::accepted(CommandOutcome::Sent, &approval.pea_id, service_tag)
+                }
+                Err(refusal) => refusal.response(),
+            }
+        }
+        GuardedAction::Deploy => {
+            pea_handlers::perform_deploy(state, requested_by, &approval.pea_id).await
+        }
+        GuardedAction::Undeploy => {
+            pea_handlers::perform_undeploy(state, requested_by, &approval.pea_id).await
+        }
+        GuardedAction::Stop => {
+            pea_handlers::perform_stop(state, requested_by, &approval.pea_id).await
+        }
+    }
+}
+
+async fn publish(state: &AppState, approval: &PendingApproval) {
+    let payload = serde_json::to_string(approval).unwrap_or_else(|_| "{}".to_string());
+    if let Err(e) = state.zenoh_session.put(APPROVALS_TOPIC, payload).await {
+        warn!("Failed to publish approval {}: {}", approval.id, e);
+    }
+}
+
+/// Parks an action guarded by an approval policy as a pending approval and
+/// answers 202; returns `None` when the action may run right away.
+pub(crate) async fn hold_for_approval(
+    state: &AppState,
+    req: &HttpRequest,
+    pea_id: &str,
+    service_tag: Option<&str>,
+    procedure_id: Option<u32>,
+    action: GuardedAction,
+) -> Option<HttpResponse> {
+    let requested_by = match authenticated_requester(state, req, pea_id, service_tag, &action).await
+    {
+        Ok(requested_by) => requested_by,
+        Err(response) => return Some(response),
+    };
+    park_for_approval(
+        state,
+        requested_by,
+        pea_id,
+        service_tag,
+        procedure_id,
+        None,
+        action,
+    )
+    .await
+    .map(|approval| pending_response(&approval))
+}
+
+/// Who asks for an action: the holder of an API token or the deployment admin.
+/// Asserted headers are not an identity an approver can be told apart from.
+async fn requester(state: &AppState, req: &HttpRequest) -> Option<String> {
+    tenancy::authenticated_caller(state, req)
+        .await
+        .and_then(|caller| caller.actor_id)
+}
+
+/// The requester of `action`; a guarded action needs an authenticated one, so
+/// the approver can be checked against them.
+pub(crate) async fn authenticated_requester(
+    state: &AppState,
+    req: &HttpRequest,
+    pea_id: &str,
+    service_tag: Option<&str>,
+    action: &GuardedAction,
+) -> Result<Option<String>, HttpResponse> {
+    let requested_by = requester(state, req).await;
+    if requested_by.is_none() && is_guarded(state, pea_id, service_tag, action).await {
+        return Err(HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": "This action needs approval and must be requested with an API token"
+        })));
+    }
+    Ok(requested_by)
+}
+
+async fn is_guarded(
+    state: &AppState,
+    pea_id: &str,
+    service_tag: Option<&str>,
+    action: &GuardedAction,
+) -> bool {
+    state.pea_configs.read().await.contains_key(pea_id)
+        && approval_service::guarding_policy(
+            state.approval_policies.read().await.values(),
+            pea_id,
+            service_tag,
+            action,
+        )
+        .is_some()
+}
+
+/// Records an action guarded by an approval policy as a pending approval;
+/// `None` when no policy guards it.
+pub(crate) async fn park_for_approval(
+    state: &AppState,
+    requested_by: Option<String>,
+    pea_id: &str,
+    service_tag: Option<&str>,
+    procedure_id: Option<u32>,
+    parameters: Option<&[RecipeParameterValue]>,
+    action: GuardedAction,
+) -> Option<PendingApproval> {
+    if !state.pea_configs.read().await.contains_key(pea_id) {
+        return None;
+    }
+    let mut approval = {
+        let policies = state.approval_policies.read().await;
+        let policy =
+            approval_service::guarding_policy(policies.values(), pea_id, service_tag, &action)?;
+        PendingApproval::new(
+            policy,
+            pea_id,
+            service_tag.map(str::to_string),
+            procedure_id,
+            requested_by,
+            Utc::now(),
+            approval_service::ttl_from_env(),
+        )
+    };
+    approval.parameters = parameters.map(<[RecipeParameterValue]>::to_vec);
+    state
+        .approvals
+        .write()
+        .await
+        .insert(approval.id.clone(), approval.clone());
+    publish(state, &approval).await;
+    info!(
+        "{:?} on {} awaits confirmation ({})",
+        approval.action, pea_id, approval.id
+    );
+    Some(approval)
+}
+
+/// Rejects a still pending approval on behalf of `actor_id`, for requests that
+/// no longer stand, such as a step of an aborted recipe execution.
+pub(crate) async fn withdraw(state: &AppState, approval_id: &str, actor_id: &str, reason: &str) {
+    let approval = {
+        let mut approvals = state.approvals.write().await;
+        let Some(approval) = approvals.get_mut(approval_id) else {
+            return;
+        };
+        approval.lapse(Utc::now());
+        if approval.status != ApprovalStatus::Pending {
+            return;
+        }
+        approval.decide(
+            ApprovalStatus::Rejected,
+            actor_id.to_string(),
+            Some(reason.to_string()),
+        );
+        approval.clone()
+    };
+    publish(state, &approval).await;
+}
+
+pub(crate) fn pending_response(approval: &PendingApproval) -> HttpResponse {
+    HttpResponse::Accepted().json(serde_json::json!({
+        "status": "pending_approval",
+        "approval": approval,
+    }))
+}
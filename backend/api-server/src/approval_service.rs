@@ -0,0 +1,237 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use shared::mtp::{RecipeParameterValue, ServiceCommand};
+
+use crate::request_context::CallerContext;
+
+/// Role a second user needs to confirm a guarded action.
+pub const APPROVER_ROLE: &str = "approver";
+
+/// Seconds a request waits for confirmation before it lapses.
+pub const DEFAULT_APPROVAL_TTL_S: i64 = 900;
+
+/// A control action that can be placed behind dual confirmation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GuardedAction {
+    ServiceCommand {
+        command: ServiceCommand,
+    },
+    Deploy,
+    Undeploy,
+    /// Stopping a whole PEA, used as its emergency stop.
+    Stop,
+}
+
+/// Puts an action on one PEA, or on every PEA, behind a second confirmation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApprovalPolicy {
+    pub id: String,
+    pub name: String,
+    /// PEA the policy applies to; all PEAs when unset.
+    pub pea_id: Option<String>,
+    /// Service the policy applies to for service commands; all when unset.
+    pub service_tag: Option<String>,
+    pub action: GuardedAction,
+    pub enabled: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl ApprovalPolicy {
+    pub fn matches(&self, pea_id: &str, service_tag: Option<&str>, action: &GuardedAction) -> bool {
+        self.enabled
+            && self.action == *action
+            && self.pea_id.as_deref().is_none_or(|id| id == pea_id)
+            && self
+                .service_tag
+                .as_deref()
+                .is_none_or(|tag| Some(tag) == service_tag)
+    }
+}
+
+/// The first enabled policy guarding `action` on `pea_id`.
+pub fn guarding_policy<'a>(
+    policies: impl IntoIterator<Item = &'a ApprovalPolicy>,
+    pea_id: &str,
+    service_tag: Option<&str>,
+    action: &GuardedAction,
+) -> Option<&'a ApprovalPolicy> {
+    policies
+        .into_iter()
+        .find(|policy| policy.matches(pea_id, service_tag, action))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStatus {
+    Pending,
+    Approved,
+    Rejected,
+    Expired,
+}
+
+/// A guarded action waiting for, or decided by, a second user.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingApproval {
+    pub id: String,
+    pub policy_id: String,
+    pub pea_id: String,
+    pub service_tag: Option<String>,
+    pub procedure_id: Option<u32>,
+    /// Parameters a recipe step sends with its command.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<Vec<RecipeParameterValue>>,
+    pub action: GuardedAction,
+    pub status: ApprovalStatus,
+    pub requested_by: Option<String>,
+    pub requested_at: String,
+    pub expires_at: String,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<String>,
+    pub reason: Option<String>,
+}
+
+impl PendingApproval {
+    pub fn new(
+        policy: &ApprovalPolicy,
+        pea_id: &str,
+        service_tag: Option<String>,
+        procedure_id: Option<u32>,
+        requested_by: Option<String>,
+        now: DateTime<Utc>,
+        ttl: Duration,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            policy_id: policy.id.clone(),
+            pea_id: pea_id.to_string(),
+            service_tag,
+            procedure_id,
+            parameters: None,
+            action: policy.action.clone(),
+            status: ApprovalStatus::Pending,
+            requested_by,
+            requested_at: now.to_rfc3339(),
+            expires_at: (now + ttl).to_rfc3339(),
+            decided_by: None,
+            decided_at: None,
+            reason: None,
+        }
+    }
+
+    /// Marks a pending request as expired once its deadline passed.
+    pub fn lapse(&mut self, now: DateTime<Utc>) {
+        let expired = DateTime::parse_from_rfc3339(&self.expires_at)
+            .is_ok_and(|expires| expires.with_timezone(&Utc) <= now);
+        if self.status == ApprovalStatus::Pending && expired {
+            self.status = ApprovalStatus::Expired;
+        }
+    }
+
+    /// Checks that `caller` may decide this request: it must be pending, and
+    /// the caller an approver other than the requester. Both identities must
+    /// come from API tokens, since headers prove neither identity nor role.
+    pub fn check_decider(&self, caller: &CallerContext) -> Result<String, String> {
+        if self.status != ApprovalStatus::Pending {
+            return Err(format!("Approval is {:?}", self.status).to_lowercase());
+        }
+        let Some(actor_id) = caller.actor_id.clone() else {
+            return Err("Deciding an approval requires an identified actor".to_string());
+        };
+        if self.requested_by.as_deref() == Some(actor_id.as_str()) {
+            return Err("A request cannot be confirmed by the user who made it".to_string());
+        }
+        if !caller.has_role(APPROVER_ROLE) {
+            return Err(format!("Confirming requires the {} role", APPROVER_ROLE));
+        }
+        Ok(actor_id)
+    }
+
+    pub fn decide(&mut self, status: ApprovalStatus, actor_id: String, reason: Option<String>) {
+        self.status = status;
+        self.decided_by = Some(actor_id);
+        self.decided_at = Some(Utc::now().to_rfc3339());
+        self.reason = reason;
+    }
+}
+
+pub fn ttl_from_env() -> Duration {
+    let seconds = std::env::var("APPROVAL_TTL_S")
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or(DEFAULT_APPROVAL_TTL_S);
+    Duration::seconds(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ApprovalPolicy {
+        ApprovalPolicy {
+            id: "p1".to_string(),
+            name: "Abort on reactor".to_string(),
+            pea_id: Some("reactor".to_string()),
+            service_tag: None,
+            action: GuardedAction::ServiceCommand {
+                command: ServiceCommand::Abort,
+            },
+            enabled: true,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    fn caller(actor_id: &str, roles: &[&str]) -> CallerContext {
+        CallerContext {
+            actor_id: Some(actor_id.to_string()),
+            roles: roles.iter().map(|role| role.to_string()).collect(),
+            ..CallerContext::default()
+        }
+    }
+
+    #[test]
+    fn policies_match_pea_and_command() {
+        let abort = GuardedAction::ServiceCommand {
+            command: ServiceCommand::Abort,
+        };
+        let policies = [policy()];
+        assert!(guarding_policy(&policies, "reactor", Some("Dose"), &abort).is_some());
+        assert!(guarding_policy(&policies, "mixer", Some("Dose"), &abort).is_none());
+        let stop = GuardedAction::ServiceCommand {
+            command: ServiceCommand::Stop,
+        };
+        assert!(guarding_policy(&policies, "reactor", Some("Dose"), &stop).is_none());
+    }
+
+    #[test]
+    fn a_second_approver_must_confirm() {
+        let now = Utc::now();
+        let mut approval = PendingApproval::new(
+            &policy(),
+            "reactor",
+            Some("Dose".to_string()),
+            None,
+            Some("alice".to_string()),
+            now,
+            Duration::seconds(60),
+        );
+
+        assert!(approval
+            .check_decider(&caller("alice", &[APPROVER_ROLE]))
+            .is_err());
+        assert!(approval.check_decider(&caller("bob", &[])).is_err());
+        assert_eq!(
+            approval.check_decider(&caller("bob", &[APPROVER_ROLE])),
+            Ok("bob".to_string())
+        );
+
+        approval.lapse(now + Duration::seconds(61));
+        assert_eq!(approval.status, ApprovalStatus::Expired);
+        assert!(approval
+            .check_decider(&caller("bob", &[APPROVER_ROLE]))
+            .is_err());
+    }
+}
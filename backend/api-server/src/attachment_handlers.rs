@@ -0,0 +1,315 @@
+use crate::db;
+use crate::pea_handlers::reject_foreign_pea;
+use crate::request_context::CallerContext;
+use crate::state::{AppState, AttachmentRecord};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use tracing::{error, info};
+
+/// Upper bound for a single uploaded document.
+pub const MAX_ATTACHMENT_BYTES: usize = 50 * 1024 * 1024;
+
+fn storage_key(pea_id: &str, attachment_id: &str) -> String {
+    format!("attachments/{}/{}", pea_id, attachment_id)
+}
+
+fn guess_content_type(filename: &str) -> &'static str {
+    let lower = filename.to_lowercase();
+    match lower.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("pdf") => "application/pdf",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("txt") => "text/plain",
+        Some("csv") => "text/csv",
+        Some("dwg") => "image/vnd.dwg",
+        Some("xlsx") => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        Some("docx") => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Types a browser may render in place. Anything else, SVG and HTML
+/// included, is sent as a download so uploaded files cannot run scripts.
+const INLINE_CONTENT_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/bmp",
+    "application/pdf",
+    "text/plain",
+];
+
+fn disposition(content_type: &str) -> &'static str {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    if INLINE_CONTENT_TYPES.contains(&essence.as_str()) {
+        "inline"
+    } else {
+        "attachment"
+    }
+}
+
+async fn pea_exists(state: &AppState, pea_id: &str) -> bool {
+    state.pea_configs.read().await.contains_key(pea_id)
+}
+
+/// GET /pea/{id}/attachments — attachment metadata, newest first.
+pub async fn list_attachments(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    if !pea_exists(&state, &pea_id).await {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"}));
+    }
+    match db::list_attachments(&state.db_client, &pea_id).await {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => {
+            error!("Failed to list attachments for {}: {}", pea_id, e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({"error": "Failed to list attachments"}))
+        }
+    }
+}
+
+/// POST /pea/{id}/attachments — multipart upload with a `file` part and optional
+/// `category` / `description` text parts.
+pub async fn upload_attachment(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+    mut payload: actix_multipart::Multipart,
+) -> impl Responder {
+    use futures_util::{StreamExt, TryStreamExt};
+
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    if !pea_exists(&state, &pea_id).await {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"}));
+    }
+
+    let mut file_bytes: Vec<u8> = Vec::new();
+    let mut filename: Option<String> = None;
+    let mut content_type: Option<String> = None;
+    let mut category: Option<String> = None;
+    let mut description = String::new();
+
+    while let Ok(Some(mut field)) = payload.try_next().await {
+        let field_name = field.name().unwrap_or_default().to_string();
+        let upload_name = field
+            .content_disposition()
+            .and_then(|d| d.get_filename())
+            .map(str::to_string);
+        let mime = field.content_type().map(|m| m.essence_str().to_string());
+
+        let mut bytes: Vec<u8> = Vec::new();
+        while let Some(Ok(chunk)) = field.next().await {
+            bytes.extend_from_slice(&chunk);
+            if bytes.len() > MAX_ATTACHMENT_BYTES {
+                return HttpResponse::PayloadTooLarge().json(serde_json::json!({
+                    "error": format!("Attachments are limited to {} bytes", MAX_ATTACHMENT_BYTES)
+                }));
+            }
+        }
+
+        match upload_name {
+            Some(name) if filename.is_none() => {
+                filename = Some(name);
+                content_type = mime;
+                file_bytes = bytes;
+            }
+            _ => {
+                let text = String::from_utf8_lossy(&bytes).trim().to_string();
+                match field_name.as_str() {
+                    "category" if !text.is_empty() => category = Some(text),
+                    "description" => description = text,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let Some(filename) = filename.filter(|_| !file_bytes.is_empty()) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": "No file uploaded"}));
+    };
+    let content_type = content_type
+        .filter(|m| m != "application/octet-stream")
+        .unwrap_or_else(|| guess_content_type(&filename).to_string());
+    let category = category.unwrap_or_else(|| {
+        if content_type.starts_with("image/") {
+            "photo".to_string()
+        } else {
+            "document".to_string()
+        }
+    });
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let attachment = AttachmentRecord {
+        id: id.clone(),
+        pea_id: pea_id.to_string(),
+        filename,
+        content_type,
+        category,
+        description,
+        size_bytes: file_bytes.len() as i64,
+        sha256: hex::encode(Sha256::digest(&file_bytes)),
+        storage_key: storage_key(&pea_id, &id),
+        uploaded_by: CallerContext::from_request(&req).actor_id,
+        created_at: Utc::now().to_rfc3339(),
+    };
+
+    if let Err(e) = state
+        .blob_store
+        .put(
+            &attachment.storage_key,
+            file_bytes,
+            &attachment.content_type,
+        )
+        .await
+    {
+        error!("Failed to store attachment {}: {}", attachment.id, e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({"error": "Failed to store attachment"}));
+    }
+    if let Err(e) = db::insert_attachment(&state.db_client, &attachment).await {
+        error!("Failed to record attachment {}: {}", attachment.id, e);
+        let _ = state.blob_store.delete(&attachment.storage_key).await;
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({"error": "Failed to record attachment"}));
+    }
+
+    info!(
+        "Stored attachment {} for PEA {} ({} bytes, {})",
+        attachment.filename,
+        attachment.pea_id,
+        attachment.size_bytes,
+        state.blob_store.backend()
+    );
+    HttpResponse::Created().json(attachment)
+}
+
+/// GET /pea/{id}/attachments/{attachment_id} — download the stored file.
+pub async fn download_attachment(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (pea_id, attachment_id) = path.into_inner();
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    let attachment = match db::get_attachment(&state.db_client, &pea_id, &attachment_id).await {
+        Ok(Some(attachment)) => attachment,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"error": "Attachment not found"}))
+        }
+        Err(e) => {
+            error!("Failed to load attachment {}: {}", attachment_id, e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({"error": "Failed to load attachment"}));
+        }
+    };
+
+    match state.blob_store.get(&attachment.storage_key).await {
+        Ok(bytes) => HttpResponse::Ok()
+            .content_type(attachment.content_type.as_str())
+            .insert_header((
+                "Content-Disposition",
+                format!(
+                    "{}; filename=\"{}\"",
+                    disposition(&attachment.content_type),
+                    attachment.filename.replace('"', "")
+                ),
+            ))
+            .insert_header(("X-Content-Type-Options", "nosniff"))
+            .body(bytes),
+        Err(e) => {
+            error!("Attachment {} missing from store: {}", attachment_id, e);
+            HttpResponse::NotFound()
+                .json(serde_json::json!({"error": "Attachment content not found"}))
+        }
+    }
+}
+
+/// DELETE /pea/{id}/attachments/{attachment_id} — remove the file and its metadata.
+pub async fn delete_attachment(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (pea_id, attachment_id) = path.into_inner();
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    let attachment = match db::get_attachment(&state.db_client, &pea_id, &attachment_id).await {
+        Ok(Some(attachment)) => attachment,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"error": "Attachment not found"}))
+        }
+        Err(e) => {
+            error!("Failed to load attachment {}: {}", attachment_id, e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({"error": "Failed to load attachment"}));
+        }
+    };
+
+    if let Err(e) = state.blob_store.delete(&attachment.storage_key).await {
+        error!(
+            "Failed to delete attachment content {}: {}",
+            attachment_id, e
+        );
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({"error": "Failed to delete attachment"}));
+    }
+    let _ = db::delete_attachment(&state.db_client, &attachment_id).await;
+    HttpResponse::NoContent().finish()
+}
+
+/// Removes every stored attachment of a PEA; called when an archived PEA is purged.
+pub async fn purge_pea_attachments(state: &AppState, pea_id: &str) {
+    let prefix = format!("attachments/{}/", pea_id);
+    match state.blob_store.list(&prefix).await {
+        Ok(keys) => {
+            for key in keys {
+                if let Err(e) = state.blob_store.delete(&key).await {
+                    error!("Failed to delete attachment content {}: {}", key, e);
+                }
+            }
+        }
+        Err(e) => error!("Failed to list attachments for {}: {}", pea_id, e),
+    }
+    match db::delete_attachments_for_pea(&state.db_client, pea_id).await {
+        Ok(0) => {}
+        Ok(n) => info!("Removed {} attachment(s) of deleted PEA {}", n, pea_id),
+        Err(e) => error!("Failed to remove attachment records for {}: {}", pea_id, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_passive_types_are_shown_inline() {
+        assert_eq!(disposition("image/png"), "inline");
+        assert_eq!(disposition("Application/PDF"), "inline");
+        assert_eq!(disposition("text/plain; charset=utf-8"), "inline");
+        assert_eq!(disposition("image/svg+xml"), "attachment");
+        assert_eq!(disposition("text/html"), "attachment");
+        assert_eq!(disposition("application/octet-stream"), "attachment");
+    }
+}
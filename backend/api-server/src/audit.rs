@@ -0,0 +1,41 @@
+use chrono::Utc;
+use shared::UserAction;
+use tracing::error;
+
+use crate::db;
+use crate::state::AppState;
+
+pub const ENTITY_PEA: &str = "pea";
+
+pub const ACTION_DEPLOY: &str = "deploy";
+pub const ACTION_UNDEPLOY: &str = "undeploy";
+pub const ACTION_START: &str = "start";
+pub const ACTION_STOP: &str = "stop";
+pub const ACTION_COMMAND: &str = "command";
+
+/// Stores one audit entry. A failed write is logged and does not fail the
+/// action, which has already happened.
+pub async fn record(
+    state: &AppState,
+    actor: Option<String>,
+    entity: &str,
+    entity_id: &str,
+    action: &str,
+    payload: serde_json::Value,
+) {
+    let entry = UserAction {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: actor,
+        entity: entity.to_string(),
+        machine_id: entity_id.to_string(),
+        action: action.to_string(),
+        payload,
+        timestamp: Utc::now(),
+    };
+    if let Err(e) = db::insert_audit_entry(&state.db_client, &entry).await {
+        error!(
+            "Failed to audit {} of {} {}: {}",
+            action, entity, entity_id, e
+        );
+    }
+}
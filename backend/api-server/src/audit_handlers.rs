@@ -0,0 +1,95 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tracing::error;
+
+use crate::audit;
+use crate::db;
+use crate::pea_handlers::reject_foreign_pea;
+use crate::state::AppState;
+use crate::tenancy::{self, TenantScope};
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    /// Kind of entity; defaults to `pea`.
+    pub entity: Option<String>,
+    /// One entity's entries; every entity of the kind when omitted.
+    pub id: Option<String>,
+    /// Start of the window as Unix milliseconds (default: 24h before `end_ms`)
+    pub start_ms: Option<i64>,
+    /// End of the window as Unix milliseconds (default: now)
+    pub end_ms: Option<i64>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+/// GET /audit — deploy, start, stop, undeploy and command entries, newest first
+pub async fn get_audit_log(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<AuditQuery>,
+) -> impl Responder {
+    let entity = query.entity.as_deref().unwrap_or(audit::ENTITY_PEA);
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    // Tenants read the entries of their own PEAs, one PEA at a time.
+    if let TenantScope::Tenant(_) = scope {
+        let Some(pea_id) = query.id.as_deref().filter(|_| entity == audit::ENTITY_PEA) else {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Tenant actors must name a PEA with entity=pea&id=..."
+            }));
+        };
+        if let Some(response) = reject_foreign_pea(&state, &req, pea_id).await {
+            return response;
+        }
+    }
+
+    let end_ms = query
+        .end_ms
+        .unwrap_or_else(|| Utc::now().timestamp_millis());
+    let start_ms = query.start_ms.unwrap_or(end_ms - 24 * 60 * 60 * 1000);
+    let (Some(start), Some(end)) = (
+        DateTime::from_timestamp_millis(start_ms),
+        DateTime::from_timestamp_millis(end_ms),
+    ) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": "Invalid window"}));
+    };
+    let offset = query.offset.unwrap_or(0);
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    match db::list_audit_entries(
+        &state.db_client,
+        entity,
+        query.id.as_deref(),
+        start,
+        end,
+        offset as i64,
+        limit as i64,
+    )
+    .await
+    {
+        Ok((entries, total)) => HttpResponse::Ok().json(serde_json::json!({
+            "entity": entity,
+            "id": query.id,
+            "start_ms": start_ms,
+            "end_ms": end_ms,
+            "total": total,
+            "offset": offset,
+            "limit": limit,
+            "entries": entries,
+        })),
+        Err(e) => {
+            error!("Failed to load audit log: {}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({"error": "Failed to load audit log"}))
+        }
+    }
+}
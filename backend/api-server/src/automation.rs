@@ -0,0 +1,427 @@
+use crate::environments;
+use crate::recipe_executor;
+use crate::scenario_handlers;
+use crate::service_command::{self, CommandOutcome, CommandRefusal, CommandSource};
+use crate::severity_profile::SeverityProfile;
+use crate::state::{AlarmRecord, AppState};
+use actix_web::web;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use shared::mtp::ServiceCommand;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Upper bound on automation log entries kept in memory.
+pub const MAX_LOG_ENTRIES: usize = 1000;
+
+pub const OUTCOME_EXECUTED: &str = "executed";
+pub const OUTCOME_DRY_RUN: &str = "dry_run";
+pub const OUTCOME_SUPPRESSED: &str = "suppressed";
+pub const OUTCOME_BLOCKED: &str = "blocked";
+pub const OUTCOME_FAILED: &str = "failed";
+
+/// Alarm condition a rule reacts to. Patterns are substring matches, like alarm rules.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AlarmCondition {
+    pub source_pattern: Option<String>,
+    pub event_pattern: Option<String>,
+    pub min_severity: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AutomationAction {
+    ServiceCommand {
+        pea_id: String,
+        service_tag: String,
+        command: ServiceCommand,
+        procedure_id: Option<u32>,
+    },
+    StartRecipe {
+        recipe_id: String,
+        /// Execution environment; see `environments::select`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        environment: Option<String>,
+    },
+    /// Stops one scenario run, or every running scenario when `run_id` is unset.
+    StopScenario {
+        run_id: Option<String>,
+    },
+}
+
+impl AutomationAction {
+    pub fn summary(&self) -> String {
+        match self {
+            AutomationAction::ServiceCommand {
+                pea_id,
+                service_tag,
+                command,
+                ..
+            } => format!("command {:?} on {}/{}", command, pea_id, service_tag),
+            AutomationAction::StartRecipe {
+                recipe_id,
+                environment: Some(environment),
+            } => format!("start recipe {} in {}", recipe_id, environment),
+            AutomationAction::StartRecipe { recipe_id, .. } => format!("start recipe {}", recipe_id),
+            AutomationAction::StopScenario { run_id: Some(id) } => format!("stop scenario {}", id),
+            AutomationAction::StopScenario { run_id: None } => {
+                "stop all running scenarios".to_string()
+            }
+        }
+    }
+}
+
+/// Limits on how often a rule may act. Service commands also pass every check
+/// an API command does, see `service_command::send`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SafetyConstraints {
+    /// Minimum time between two firings of the rule.
+    #[serde(default = "default_cooldown_s")]
+    pub cooldown_s: u64,
+    /// Firings allowed in any rolling hour.
+    #[serde(default = "default_max_per_hour")]
+    pub max_per_hour: usize,
+}
+
+fn default_cooldown_s() -> u64 {
+    300
+}
+
+fn default_max_per_hour() -> usize {
+    6
+}
+
+impl Default for SafetyConstraints {
+    fn default() -> Self {
+        Self {
+            cooldown_s: default_cooldown_s(),
+            max_per_hour: default_max_per_hour(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AutomationRule {
+    pub id: String,
+    pub name: String,
+    pub condition: AlarmCondition,
+    pub action: AutomationAction,
+    #[serde(default)]
+    pub safety: SafetyConstraints,
+    /// Dry-run rules only log what they would have done.
+    #[serde(default)]
+    pub dry_run: bool,
+    pub enabled: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl AutomationRule {
+    pub fn matches(&self, alarm: &AlarmRecord, severities: &SeverityProfile) -> bool {
+        let condition = &self.condition;
+        condition
+            .source_pattern
+            .as_deref()
+            .is_none_or(|pattern| alarm.source.contains(pattern))
+            && condition
+                .event_pattern
+                .as_deref()
+                .is_none_or(|pattern| alarm.event.contains(pattern))
+            && condition
+                .min_severity
+                .as_deref()
+                .is_none_or(|min| severities.at_least(&alarm.severity, min))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AutomationLogEntry {
+    pub id: String,
+    pub rule_id: String,
+    pub rule_name: String,
+    pub alarm_id: String,
+    pub alarm_source: String,
+    pub alarm_event: String,
+    pub action: String,
+    /// `executed`, `dry_run`, `suppressed`, `blocked` or `failed`.
+    pub outcome: String,
+    pub detail: Option<String>,
+    pub timestamp: String,
+}
+
+#[derive(Clone)]
+pub struct Automation {
+    pub rules: Arc<RwLock<HashMap<String, AutomationRule>>>,
+    pub log: Arc<RwLock<VecDeque<AutomationLogEntry>>>,
+    /// Recent firing times per rule, for the safety constraints.
+    firings: Arc<RwLock<HashMap<String, VecDeque<DateTime<Utc>>>>>,
+}
+
+impl Automation {
+    pub fn new(rules: HashMap<String, AutomationRule>) -> Self {
+        Self {
+            rules: Arc::new(RwLock::new(rules)),
+            log: Arc::new(RwLock::new(VecDeque::new())),
+            firings: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Most recent entries first, optionally for a single rule.
+    pub async fn recent(&self, rule_id: Option<&str>, limit: usize) -> Vec<AutomationLogEntry> {
+        self.log
+            .read()
+            .await
+            .iter()
+            .rev()
+            .filter(|entry| rule_id.is_none_or(|id| entry.rule_id == id))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    async fn record(&self, entry: AutomationLogEntry) {
+        let mut log = self.log.write().await;
+        log.push_back(entry);
+        while log.len() > MAX_LOG_ENTRIES {
+            log.pop_front();
+        }
+    }
+
+    /// Records a firing unless the rule's safety constraints forbid it.
+    async fn try_fire(&self, rule: &AutomationRule, now: DateTime<Utc>) -> Result<(), String> {
+        let mut firings = self.firings.write().await;
+        let history = firings.entry(rule.id.clone()).or_default();
+        check_safety(&rule.safety, history, now)?;
+        history.push_back(now);
+        Ok(())
+    }
+}
+
+/// Checks cooldown and hourly limit against previous firings, pruning those
+/// older than an hour.
+pub fn check_safety(
+    safety: &SafetyConstraints,
+    history: &mut VecDeque<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    while history
+        .front()
+        .is_some_and(|fired| now - *fired > Duration::hours(1))
+    {
+        history.pop_front();
+    }
+    if let Some(last) = history.back() {
+        let since = (now - *last).num_seconds();
+        if since < safety.cooldown_s as i64 {
+            return Err(format!(
+                "Cooldown active: last fired {}s ago, cooldown is {}s",
+                since, safety.cooldown_s
+            ));
+        }
+    }
+    if history.len() >= safety.max_per_hour {
+        return Err(format!(
+            "Hourly limit of {} firings reached",
+            safety.max_per_hour
+        ));
+    }
+    Ok(())
+}
+
+/// Runs every enabled rule matching a newly raised alarm.
+pub async fn on_alarm_raised(state: &web::Data<AppState>, alarm: &AlarmRecord) {
+    let severities = state.severity_profile.read().await.clone();
+    let rules: Vec<AutomationRule> = state
+        .automation
+        .rules
+        .read()
+        .await
+        .values()
+        .filter(|rule| rule.enabled && rule.matches(alarm, &severities))
+        .cloned()
+        .collect();
+
+    for rule in rules {
+        let (outcome, detail) = match state.automation.try_fire(&rule, Utc::now()).await {
+            Err(reason) => (OUTCOME_SUPPRESSED, Some(reason)),
+            Ok(()) if rule.dry_run => (OUTCOME_DRY_RUN, None),
+            Ok(()) => match execute(state, &rule.action).await {
+                Ok(detail) => (OUTCOME_EXECUTED, detail),
+                Err(ActionError::Blocked(reason)) => (OUTCOME_BLOCKED, Some(reason)),
+                Err(ActionError::Failed(reason)) => (OUTCOME_FAILED, Some(reason)),
+            },
+        };
+        if outcome == OUTCOME_EXECUTED {
+            info!(
+                "Automation rule {} fired on alarm {}: {}",
+                rule.name,
+                alarm.id,
+                rule.action.summary()
+            );
+        } else if outcome != OUTCOME_DRY_RUN {
+            warn!(
+                "Automation rule {} did not act on alarm {}: {} {:?}",
+                rule.name, alarm.id, outcome, detail
+            );
+        }
+
+        state
+            .automation
+            .record(AutomationLogEntry {
+                id: uuid::Uuid::new_v4().to_string(),
+                rule_id: rule.id.clone(),
+                rule_name: rule.name.clone(),
+                alarm_id: alarm.id.clone(),
+                alarm_source: alarm.source.clone(),
+                alarm_event: alarm.event.clone(),
+                action: rule.action.summary(),
+                outcome: outcome.to_string(),
+                detail,
+                timestamp: Utc::now().to_rfc3339(),
+            })
+            .await;
+    }
+}
+
+enum ActionError {
+    /// Refused by a safety check such as an interlock.
+    Blocked(String),
+    Failed(String),
+}
+
+async fn execute(
+    state: &web::Data<AppState>,
+    action: &AutomationAction,
+) -> Result<Option<String>, ActionError> {
+    match action {
+        AutomationAction::ServiceCommand {
+            pea_id,
+            service_tag,
+            command,
+            procedure_id,
+        } => {
+            let source = CommandSource {
+                actor_id: None,
+                origin: "automation",
+                interlock_override: None,
+            };
+            match service_command::send(
+                state,
+                source,
+                pea_id,
+                service_tag,
+                *command,
+                *procedure_id,
+                None,
+            )
+            .await
+            {
+                Ok(CommandOutcome::Sent) => Ok(None),
+                Ok(CommandOutcome::PendingApproval(approval)) => {
+                    Ok(Some(format!("Awaiting approval {}", approval.id)))
+                }
+                Err(refusal @ CommandRefusal::PublishFailed(_)) => {
+                    Err(ActionError::Failed(refusal.message()))
+                }
+                Err(refusal) => Err(ActionError::Blocked(refusal.message())),
+            }
+        }
+        AutomationAction::StartRecipe {
+            recipe_id,
+            environment,
+        } => {
+            let recipe = state.recipes.read().await.get(recipe_id).cloned();
+            let Some(recipe) = recipe else {
+                return Err(ActionError::Failed(format!(
+                    "Recipe '{}' not found",
+                    recipe_id
+                )));
+            };
+            let environment = environments::select(state, environment.as_deref());
+            recipe_executor::start(
+                state,
+                &recipe,
+                recipe_executor::ORIGIN_AUTOMATION,
+                environment,
+            )
+            .await
+                .map(|execution_id| Some(format!("execution {}", execution_id)))
+                .map_err(ActionError::Blocked)
+        }
+        AutomationAction::StopScenario { run_id } => {
+            let stopped = scenario_handlers::stop_runs(state, run_id.as_deref()).await;
+            if stopped.is_empty() {
+                return Err(ActionError::Failed(
+                    "No running scenario to stop".to_string(),
+                ));
+            }
+            Ok(Some(format!("stopped {}", stopped.join(", "))))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::alarm_record;
+
+    fn alarm(pea_id: &str, event: &str, severity: &str) -> AlarmRecord {
+        AlarmRecord {
+            event: event.to_string(),
+            ..alarm_record("a1", pea_id, severity)
+        }
+    }
+
+    fn rule(condition: AlarmCondition) -> AutomationRule {
+        AutomationRule {
+            id: "r1".to_string(),
+            name: "cool on overheat".to_string(),
+            condition,
+            action: AutomationAction::ServiceCommand {
+                pea_id: "reactor".to_string(),
+                service_tag: "cooling".to_string(),
+                command: ServiceCommand::Start,
+                procedure_id: None,
+            },
+            safety: SafetyConstraints::default(),
+            dry_run: false,
+            enabled: true,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn rules_match_on_source_event_and_severity() {
+        let severities = SeverityProfile::default();
+        let overheat = rule(AlarmCondition {
+            source_pattern: Some("reactor".to_string()),
+            event_pattern: Some("OVERHEAT".to_string()),
+            min_severity: Some("critical".to_string()),
+        });
+        assert!(overheat.matches(&alarm("reactor", "OVERHEAT", "critical"), &severities));
+        assert!(!overheat.matches(&alarm("reactor", "OVERHEAT", "high"), &severities));
+        assert!(!overheat.matches(&alarm("reactor", "LOW_LEVEL", "critical"), &severities));
+        assert!(rule(AlarmCondition::default()).matches(&alarm("x", "y", "info"), &severities));
+    }
+
+    #[test]
+    fn safety_enforces_cooldown_and_hourly_limit() {
+        let safety = SafetyConstraints {
+            cooldown_s: 60,
+            max_per_hour: 2,
+        };
+        let start = Utc::now();
+        let mut history = VecDeque::new();
+
+        assert!(check_safety(&safety, &mut history, start).is_ok());
+        history.push_back(start);
+        assert!(check_safety(&safety, &mut history, start + Duration::seconds(30)).is_err());
+        history.push_back(start + Duration::seconds(90));
+        assert!(check_safety(&safety, &mut history, start + Duration::seconds(200)).is_err());
+        // Once the first firing is older than an hour, there is room again.
+        assert!(check_safety(&safety, &mut history, start + Duration::minutes(61)).is_ok());
+    }
+}
@@ -0,0 +1,237 @@
+use crate::automation::{AlarmCondition, AutomationAction, AutomationRule, SafetyConstraints};
+use crate::request_context::CallerContext;
+use crate::runtime_store;
+use crate::state::{AlarmRecord, AppState};
+use crate::tenancy;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use tracing::info;
+
+#[derive(serde::Deserialize)]
+pub struct AutomationRulePayload {
+    pub name: String,
+    pub condition: AlarmCondition,
+    pub action: AutomationAction,
+    pub safety: Option<SafetyConstraints>,
+    pub dry_run: Option<bool>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct AutomationLogQuery {
+    pub rule_id: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// Alarm to evaluate a rule against in `POST /automation/rules/{id}/evaluate`.
+#[derive(serde::Deserialize)]
+pub struct SampleAlarm {
+    pub source: String,
+    pub event: String,
+    pub severity: String,
+}
+
+pub async fn list_automation_rules(state: web::Data<AppState>) -> impl Responder {
+    let rules = state.automation.rules.read().await;
+    let list: Vec<&AutomationRule> = rules.values().collect();
+    HttpResponse::Ok().json(list)
+}
+
+pub async fn get_automation_rule(
+    state: web::Data<AppState>,
+    rule_id: web::Path<String>,
+) -> impl Responder {
+    match state.automation.rules.read().await.get(rule_id.as_str()) {
+        Some(rule) => HttpResponse::Ok().json(rule),
+        None => {
+            HttpResponse::NotFound().json(serde_json::json!({"error": "Automation rule not found"}))
+        }
+    }
+}
+
+/// POST /automation/rules — new rules start in dry-run mode unless `dry_run` is false.
+pub async fn create_automation_rule(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<AutomationRulePayload>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    let payload = body.into_inner();
+    if let Err(err) = validate_payload(&payload) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": err}));
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let rule = AutomationRule {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: payload.name.trim().to_string(),
+        condition: payload.condition,
+        action: payload.action,
+        safety: payload.safety.unwrap_or_default(),
+        dry_run: payload.dry_run.unwrap_or(true),
+        enabled: payload.enabled.unwrap_or(true),
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    runtime_store::persist_json(&state.automation_dir, &rule.id, &rule);
+    state
+        .automation
+        .rules
+        .write()
+        .await
+        .insert(rule.id.clone(), rule.clone());
+
+    info!("Created automation rule: {} ({})", rule.name, rule.id);
+    HttpResponse::Created().json(rule)
+}
+
+pub async fn update_automation_rule(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    rule_id: web::Path<String>,
+    body: web::Json<AutomationRulePayload>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    let payload = body.into_inner();
+    if let Err(err) = validate_payload(&payload) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": err}));
+    }
+
+    let mut rules = state.automation.rules.write().await;
+    let Some(rule) = rules.get_mut(rule_id.as_str()) else {
+        return HttpResponse::NotFound()
+            .json(serde_json::json!({"error": "Automation rule not found"}));
+    };
+    rule.name = payload.name.trim().to_string();
+    rule.condition = payload.condition;
+    rule.action = payload.action;
+    if let Some(safety) = payload.safety {
+        rule.safety = safety;
+    }
+    if let Some(dry_run) = payload.dry_run {
+        rule.dry_run = dry_run;
+    }
+    if let Some(enabled) = payload.enabled {
+        rule.enabled = enabled;
+    }
+    rule.updated_at = Utc::now().to_rfc3339();
+
+    runtime_store::persist_json(&state.automation_dir, &rule.id, &*rule);
+    HttpResponse::Ok().json(rule.clone())
+}
+
+pub async fn delete_automation_rule(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    rule_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    state
+        .automation
+        .rules
+        .write()
+        .await
+        .remove(rule_id.as_str());
+    runtime_store::delete_json(&state.automation_dir, rule_id.as_str());
+    HttpResponse::NoContent().finish()
+}
+
+/// GET /automation/log — most recent rule firings first, including dry runs and
+/// firings suppressed or blocked by safety constraints.
+pub async fn list_automation_log(
+    state: web::Data<AppState>,
+    query: web::Query<AutomationLogQuery>,
+) -> impl Responder {
+    let entries = state
+        .automation
+        .recent(query.rule_id.as_deref(), query.limit.unwrap_or(100))
+        .await;
+    HttpResponse::Ok().json(entries)
+}
+
+/// POST /automation/rules/{id}/evaluate — whether a sample alarm would trigger the
+/// rule, without acting or counting towards its limits.
+pub async fn evaluate_automation_rule(
+    state: web::Data<AppState>,
+    rule_id: web::Path<String>,
+    body: web::Json<SampleAlarm>,
+) -> impl Responder {
+    let Some(rule) = state
+        .automation
+        .rules
+        .read()
+        .await
+        .get(rule_id.as_str())
+        .cloned()
+    else {
+        return HttpResponse::NotFound()
+            .json(serde_json::json!({"error": "Automation rule not found"}));
+    };
+    let sample = body.into_inner();
+    let alarm = AlarmRecord {
+        id: "sample".to_string(),
+        severity: sample.severity,
+        status: "open".to_string(),
+        source: sample.source,
+        event: sample.event,
+        value: String::new(),
+        description: String::new(),
+        timestamp: Utc::now().to_rfc3339(),
+        duplicate_count: 1,
+        parent_id: None,
+        raised_at: None,
+        acknowledged_at: None,
+        cleared_at: None,
+    };
+    let matches = rule.enabled && rule.matches(&alarm, &*state.severity_profile.read().await);
+    HttpResponse::Ok().json(serde_json::json!({
+        "rule_id": rule.id,
+        "matches": matches,
+        "enabled": rule.enabled,
+        "dry_run": rule.dry_run,
+        "action": rule.action.summary(),
+    }))
+}
+
+fn reject_unless_elevated(state: &AppState, req: &HttpRequest) -> Option<HttpResponse> {
+    let ctx = CallerContext::from_request(req);
+    if tenancy::is_deployment_admin(state, req) {
+        info!(
+            "Automation rules changed by {}",
+            ctx.actor_id.as_deref().unwrap_or("unknown actor")
+        );
+        return None;
+    }
+    Some(HttpResponse::Forbidden().json(serde_json::json!({
+        "error": "Managing automation rules requires the deployment admin token"
+    })))
+}
+
+fn validate_payload(payload: &AutomationRulePayload) -> Result<(), String> {
+    if payload.name.trim().is_empty() {
+        return Err("Rule name is required".to_string());
+    }
+    let condition = &payload.condition;
+    if condition
+        .source_pattern
+        .as_deref()
+        .is_none_or(str::is_empty)
+        && condition.event_pattern.as_deref().is_none_or(str::is_empty)
+    {
+        return Err("Condition needs a source_pattern or event_pattern".to_string());
+    }
+    if payload
+        .safety
+        .as_ref()
+        .is_some_and(|safety| safety.max_per_hour == 0)
+    {
+        return Err("safety.max_per_hour must be at least 1".to_string());
+    }
+    Ok(())
+}
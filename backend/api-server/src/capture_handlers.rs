@@ -0,0 +1,234 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+
+use crate::redaction_handlers;
+use crate::runtime_store;
+use crate::state::AppState;
+use crate::tenancy;
+use crate::ts_capture::{CaptureTrigger, IncidentRecording};
+
+/// GET /ts/capture-triggers
+pub async fn list_capture_triggers(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let triggers: Vec<CaptureTrigger> = state
+        .captures
+        .list()
+        .await
+        .into_iter()
+        .filter(|trigger| scope.allows(trigger.tenant_id.as_deref()))
+        .collect();
+    HttpResponse::Ok().json(triggers)
+}
+
+pub async fn get_capture_trigger(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    trigger_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    match state.captures.get(&trigger_id).await {
+        Some(trigger) if scope.allows(trigger.tenant_id.as_deref()) => {
+            HttpResponse::Ok().json(trigger)
+        }
+        _ => trigger_not_found(),
+    }
+}
+
+/// POST /ts/capture-triggers — record a set of keys whenever a condition fires
+pub async fn create_capture_trigger(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<CaptureTrigger>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut trigger = body.into_inner();
+    if let Err(e) = check_trigger(&trigger, &scope) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    let now = Utc::now().to_rfc3339();
+    trigger.id = uuid::Uuid::new_v4().to_string();
+    trigger.name = trigger.name.trim().to_string();
+    trigger.tenant_id = scope.tenant_id().map(str::to_string);
+    trigger.created_at = now.clone();
+    trigger.updated_at = now;
+    if let Err(e) = state.captures.upsert(trigger.clone()).await {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    runtime_store::persist_json(&state.capture_trigger_dir, &trigger.id, &trigger);
+    HttpResponse::Created().json(trigger)
+}
+
+/// PUT /ts/capture-triggers/{id} — replace a trigger; a capture it already
+/// started runs to its end
+pub async fn update_capture_trigger(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    trigger_id: web::Path<String>,
+    body: web::Json<CaptureTrigger>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let Some(existing) = state
+        .captures
+        .get(&trigger_id)
+        .await
+        .filter(|trigger| scope.allows(trigger.tenant_id.as_deref()))
+    else {
+        return trigger_not_found();
+    };
+    let mut trigger = body.into_inner();
+    if let Err(e) = check_trigger(&trigger, &scope) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    trigger.id = existing.id;
+    trigger.name = trigger.name.trim().to_string();
+    trigger.tenant_id = existing.tenant_id;
+    trigger.created_at = existing.created_at;
+    trigger.updated_at = Utc::now().to_rfc3339();
+    if let Err(e) = state.captures.upsert(trigger.clone()).await {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    runtime_store::persist_json(&state.capture_trigger_dir, &trigger.id, &trigger);
+    HttpResponse::Ok().json(trigger)
+}
+
+pub async fn delete_capture_trigger(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    trigger_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let visible = state
+        .captures
+        .get(&trigger_id)
+        .await
+        .is_some_and(|trigger| scope.allows(trigger.tenant_id.as_deref()));
+    if !visible || !state.captures.remove(&trigger_id).await {
+        return trigger_not_found();
+    }
+    runtime_store::delete_json(&state.capture_trigger_dir, &trigger_id);
+    HttpResponse::NoContent().finish()
+}
+
+/// GET /ts/incidents — running and completed recordings, newest first,
+/// without their points
+pub async fn list_incident_recordings(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let recordings: Vec<IncidentRecording> = state
+        .captures
+        .recordings()
+        .await
+        .into_iter()
+        .filter(|recording| scope.allows(recording.tenant_id.as_deref()))
+        .collect();
+    HttpResponse::Ok().json(recordings)
+}
+
+/// GET /ts/incidents/{id} — a recording with its captured points, less any
+/// the caller may not see
+pub async fn get_incident_recording(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    recording_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let Some(mut recording) = state
+        .captures
+        .recording(&recording_id)
+        .await
+        .filter(|recording| scope.allows(recording.tenant_id.as_deref()))
+    else {
+        return recording_not_found();
+    };
+    let policy = redaction_handlers::policy_for(&state, &req).await;
+    recording
+        .keys
+        .retain(|key| scope.allows_key(key) && !policy.hides_key(key));
+    recording.points = std::mem::take(&mut recording.points)
+        .into_iter()
+        .filter(|(key, _)| recording.keys.contains(key))
+        .map(|(key, points)| {
+            let points = points
+                .into_iter()
+                .filter_map(|mut point| {
+                    point.v = policy.apply(&key, point.v)?;
+                    Some(point)
+                })
+                .collect();
+            (key, points)
+        })
+        .collect();
+    HttpResponse::Ok().json(recording)
+}
+
+/// DELETE /ts/incidents/{id} — only completed recordings can be deleted
+pub async fn delete_incident_recording(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    recording_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let Some(recording) = state
+        .captures
+        .recording(&recording_id)
+        .await
+        .filter(|recording| scope.allows(recording.tenant_id.as_deref()))
+    else {
+        return recording_not_found();
+    };
+    if !recording.complete {
+        return HttpResponse::Conflict()
+            .json(serde_json::json!({"error": "The capture is still running"}));
+    }
+    state.captures.remove_recording(&recording_id).await;
+    runtime_store::delete_json(&state.incident_recording_dir, &recording_id);
+    HttpResponse::NoContent().finish()
+}
+
+/// Triggers may only read and record keys the caller's tenant can read.
+fn check_trigger(trigger: &CaptureTrigger, scope: &tenancy::TenantScope) -> Result<(), String> {
+    trigger.compile()?;
+    match trigger
+        .bindings
+        .values()
+        .chain(&trigger.keys)
+        .find(|key| !scope.allows_key(key))
+    {
+        Some(key) => Err(format!("Key not found: {}", key)),
+        None => Ok(()),
+    }
+}
+
+fn trigger_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({"error": "Capture trigger not found"}))
+}
+
+fn recording_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({"error": "Incident recording not found"}))
+}
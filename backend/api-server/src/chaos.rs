@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::warn;
+use zenoh::Session;
+
+/// Fault rates applied while chaos mode is on. Rates are fractions in `0..=1`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ChaosSettings {
+    /// Share of API requests answered late
+    #[serde(default)]
+    pub latency_rate: f64,
+    /// Delay added to those requests
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Share of control publishes (commands, deploys, recipe steps, alarms) dropped
+    #[serde(default)]
+    pub drop_rate: f64,
+    /// Key prefixes drops are limited to; all control keys when empty
+    #[serde(default)]
+    pub drop_keys: Vec<String>,
+}
+
+impl ChaosSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, rate) in [
+            ("latency_rate", self.latency_rate),
+            ("drop_rate", self.drop_rate),
+        ] {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(format!("{} must be between 0 and 1", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fault injection for resilience testing in staging.
+///
+/// Only active when the server starts with `CHAOS_MODE=true`; otherwise every
+/// check passes and the settings cannot be changed.
+#[derive(Clone, Default)]
+pub struct Chaos {
+    enabled: bool,
+    settings: Arc<RwLock<ChaosSettings>>,
+}
+
+impl Chaos {
+    pub fn from_env() -> Self {
+        let enabled = std::env::var("CHAOS_MODE")
+            .map(|value| matches!(value.as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+        let rate = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<f64>().ok())
+                .map(|value| value.clamp(0.0, 1.0))
+                .unwrap_or(0.0)
+        };
+        let settings = ChaosSettings {
+            latency_rate: rate("CHAOS_LATENCY_RATE"),
+            latency_ms: std::env::var("CHAOS_LATENCY_MS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(0),
+            drop_rate: rate("CHAOS_DROP_RATE"),
+            drop_keys: std::env::var("CHAOS_DROP_KEYS")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(str::to_string)
+                .collect(),
+        };
+        if enabled {
+            warn!("Chaos mode is on: {:?}", settings);
+        }
+        Self {
+            enabled,
+            settings: Arc::new(RwLock::new(settings)),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub async fn settings(&self) -> ChaosSettings {
+        self.settings.read().await.clone()
+    }
+
+    pub async fn set_settings(&self, settings: ChaosSettings) -> Result<(), String> {
+        if !self.enabled {
+            return Err("Chaos mode is off (CHAOS_MODE)".to_string());
+        }
+        settings.validate()?;
+        warn!("Chaos settings changed: {:?}", settings);
+        *self.settings.write().await = settings;
+        Ok(())
+    }
+
+    /// Delay to add to the current request, if it is picked for latency.
+    pub async fn latency(&self) -> Option<Duration> {
+        if !self.enabled {
+            return None;
+        }
+        let settings = self.settings.read().await;
+        (settings.latency_ms > 0 && roll() < settings.latency_rate)
+            .then(|| Duration::from_millis(settings.latency_ms))
+    }
+
+    /// Whether a publish on `key` is to be dropped.
+    pub async fn drops(&self, key: &str) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let settings = self.settings.read().await;
+        let in_scope = settings.drop_keys.is_empty()
+            || settings
+                .drop_keys
+                .iter()
+                .any(|prefix| key.starts_with(prefix.as_str()));
+        in_scope && roll() < settings.drop_rate
+    }
+
+    /// Publishes like `Session::put`, unless chaos drops the sample. A dropped
+    /// publish reports success, as a sample lost on the network would.
+    pub async fn put(&self, session: &Session, key: &str, payload: String) -> zenoh::Result<()> {
+        if self.drops(key).await {
+            warn!("Chaos dropped publish on {}", key);
+            return Ok(());
+        }
+        session.put(key, payload).await
+    }
+}
+
+/// Uniform in `0..1`, from the random bits of a v4 UUID.
+fn roll() -> f64 {
+    let (high, _) = uuid::Uuid::new_v4().as_u64_pair();
+    // The first six bytes carry no version or variant bits.
+    (high >> 16) as f64 / (1u64 << 48) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chaos(enabled: bool, settings: ChaosSettings) -> Chaos {
+        Chaos {
+            enabled,
+            settings: Arc::new(RwLock::new(settings)),
+        }
+    }
+
+    #[tokio::test]
+    async fn faults_only_apply_in_chaos_mode() {
+        let settings = ChaosSettings {
+            latency_rate: 1.0,
+            latency_ms: 200,
+            drop_rate: 1.0,
+            drop_keys: vec!["entmoot/habitat/".to_string()],
+        };
+        let off = chaos(false, settings.clone());
+        assert!(off.latency().await.is_none());
+        assert!(!off.drops("entmoot/habitat/nodes/n1/pea/p1/status").await);
+        assert!(off.set_settings(settings.clone()).await.is_err());
+
+        let on = chaos(true, settings);
+        assert_eq!(on.latency().await, Some(Duration::from_millis(200)));
+        assert!(on.drops("entmoot/habitat/nodes/n1/pea/p1/status").await);
+        assert!(!on.drops("entmoot/pol/alarms/a1").await);
+    }
+
+    #[tokio::test]
+    async fn settings_reject_rates_outside_zero_to_one() {
+        let on = chaos(true, ChaosSettings::default());
+        let settings = ChaosSettings {
+            drop_rate: 1.5,
+            ..ChaosSettings::default()
+        };
+        assert!(on.set_settings(settings).await.is_err());
+        assert!((0..1000).map(|_| roll()).all(|r| (0.0..1.0).contains(&r)));
+    }
+}
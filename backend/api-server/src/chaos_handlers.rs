@@ -0,0 +1,47 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use tracing::info;
+
+use crate::chaos::ChaosSettings;
+use crate::request_context::CallerContext;
+use crate::state::AppState;
+use crate::tenancy;
+
+/// GET /admin/chaos — whether chaos mode is on and the fault rates in effect
+pub async fn get_chaos(state: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(serde_json::json!({
+        "enabled": state.chaos.enabled(),
+        "settings": state.chaos.settings().await,
+    }))
+}
+
+/// PUT /admin/chaos — changes the fault rates; only while chaos mode is on
+pub async fn put_chaos(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<ChaosSettings>,
+) -> impl Responder {
+    if !tenancy::is_deployment_admin(&state, &req) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Changing chaos settings requires the deployment admin token"
+        }));
+    }
+    if !state.chaos.enabled() {
+        return HttpResponse::Conflict()
+            .json(serde_json::json!({"error": "Chaos mode is off (CHAOS_MODE)"}));
+    }
+    let settings = body.into_inner();
+    if let Err(e) = state.chaos.set_settings(settings.clone()).await {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    info!(
+        "Chaos settings changed by {}",
+        CallerContext::from_request(&req)
+            .actor_id
+            .as_deref()
+            .unwrap_or("unknown actor")
+    );
+    HttpResponse::Ok().json(serde_json::json!({
+        "enabled": true,
+        "settings": settings,
+    }))
+}
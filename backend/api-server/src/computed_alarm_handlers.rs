@@ -0,0 +1,140 @@
+use actix_web::{web, HttpResponse, Responder};
+use chrono::Utc;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::alarm_expr::{Expr, ExprValue};
+use crate::computed_alarms::ComputedAlarmRule;
+use crate::runtime_store;
+use crate::state::AppState;
+
+/// Expression to try out in `POST /computed-alarms/evaluate`.
+#[derive(Deserialize)]
+pub struct EvaluatePayload {
+    pub expression: String,
+    #[serde(default)]
+    pub bindings: BTreeMap<String, String>,
+}
+
+/// GET /computed-alarms — rules, each with whether its condition holds now
+pub async fn list_computed_alarms(state: web::Data<AppState>) -> impl Responder {
+    let mut list = Vec::new();
+    for rule in state.computed_alarms.list().await {
+        let active = state.computed_alarms.is_active(&rule.id).await;
+        list.push(with_active(&rule, active));
+    }
+    HttpResponse::Ok().json(list)
+}
+
+pub async fn get_computed_alarm(
+    state: web::Data<AppState>,
+    rule_id: web::Path<String>,
+) -> impl Responder {
+    match state.computed_alarms.get(&rule_id).await {
+        Some(rule) => {
+            let active = state.computed_alarms.is_active(&rule.id).await;
+            HttpResponse::Ok().json(with_active(&rule, active))
+        }
+        None => {
+            HttpResponse::NotFound().json(serde_json::json!({"error": "Computed alarm not found"}))
+        }
+    }
+}
+
+pub async fn create_computed_alarm(
+    state: web::Data<AppState>,
+    body: web::Json<ComputedAlarmRule>,
+) -> impl Responder {
+    let now = Utc::now().to_rfc3339();
+    let mut rule = body.into_inner();
+    rule.id = uuid::Uuid::new_v4().to_string();
+    rule.name = rule.name.trim().to_string();
+    rule.created_at = now.clone();
+    rule.updated_at = now;
+    if let Err(e) = state.computed_alarms.upsert(rule.clone()).await {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    runtime_store::persist_json(&state.computed_alarm_dir, &rule.id, &rule);
+    HttpResponse::Created().json(rule)
+}
+
+/// PUT /computed-alarms/{id} — replace a rule; an alarm it raised is cleared
+pub async fn update_computed_alarm(
+    state: web::Data<AppState>,
+    rule_id: web::Path<String>,
+    body: web::Json<ComputedAlarmRule>,
+) -> impl Responder {
+    let Some(existing) = state.computed_alarms.get(&rule_id).await else {
+        return HttpResponse::NotFound()
+            .json(serde_json::json!({"error": "Computed alarm not found"}));
+    };
+    let mut rule = body.into_inner();
+    rule.id = existing.id;
+    rule.name = rule.name.trim().to_string();
+    rule.created_at = existing.created_at;
+    rule.updated_at = Utc::now().to_rfc3339();
+    if let Err(e) = state.computed_alarms.upsert(rule.clone()).await {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    runtime_store::persist_json(&state.computed_alarm_dir, &rule.id, &rule);
+    HttpResponse::Ok().json(rule)
+}
+
+pub async fn delete_computed_alarm(
+    state: web::Data<AppState>,
+    rule_id: web::Path<String>,
+) -> impl Responder {
+    if !state.computed_alarms.remove(&rule_id).await {
+        return HttpResponse::NotFound()
+            .json(serde_json::json!({"error": "Computed alarm not found"}));
+    }
+    runtime_store::delete_json(&state.computed_alarm_dir, &rule_id);
+    HttpResponse::NoContent().finish()
+}
+
+/// POST /computed-alarms/evaluate — evaluates an expression against the latest
+/// stored value of each bound key, without raising anything
+pub async fn evaluate_computed_alarm(
+    state: web::Data<AppState>,
+    body: web::Json<EvaluatePayload>,
+) -> impl Responder {
+    let payload = body.into_inner();
+    let expr = match Expr::parse(&payload.expression) {
+        Ok(expr) => expr,
+        Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({"error": e})),
+    };
+    let values: HashMap<String, ExprValue> = {
+        let store = state.timeseries.read().await;
+        payload
+            .bindings
+            .values()
+            .filter_map(|key| {
+                let point = store.query(key, i64::MIN, i64::MAX).pop()?;
+                ExprValue::from_json(&point.value).map(|value| (key.clone(), value))
+            })
+            .collect()
+    };
+    let inputs: BTreeMap<&String, String> = payload
+        .bindings
+        .iter()
+        .filter_map(|(name, key)| values.get(key).map(|value| (name, value.to_string())))
+        .collect();
+    match ComputedAlarmRule::evaluate(&expr, &payload.bindings, &values) {
+        Ok(value) => HttpResponse::Ok().json(serde_json::json!({
+            "value": value.to_string(),
+            "fires": value == ExprValue::Bool(true),
+            "inputs": inputs,
+        })),
+        Err(e) => HttpResponse::Ok().json(serde_json::json!({
+            "error": e,
+            "fires": false,
+            "inputs": inputs,
+        })),
+    }
+}
+
+fn with_active(rule: &ComputedAlarmRule, active: bool) -> serde_json::Value {
+    let mut json = serde_json::json!(rule);
+    json["active"] = serde_json::json!(active);
+    json
+}
@@ -0,0 +1,518 @@
+use crate::alarm_bus;
+use crate::alarm_expr::{Expr, ExprValue};
+use crate::alarm_sla;
+use crate::automation;
+use crate::pol_handlers;
+use crate::soe;
+use crate::state::{AlarmRecord, AppState};
+use crate::webhook_service;
+use actix_web::web;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, RwLock};
+use tracing::{error, info, warn};
+
+/// Alarm raised while an expression over several telemetry keys holds, e.g.
+/// `oil_pressure < 35 && rpm > 1500`, and cleared once it no longer does.
+/// A separate clear expression and minimum durations keep signals hovering
+/// around a limit from raising and clearing the alarm over and over.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ComputedAlarmRule {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub expression: String,
+    /// Expression variable -> telemetry key it reads, e.g.
+    /// `rpm` -> `entmoot/habitat/nodes/n1/pea/engine/data/rpm`.
+    pub bindings: BTreeMap<String, String>,
+    /// Clears the alarm once this holds, rather than once `expression` stops
+    /// holding, e.g. raise at `temp > 90` and clear at `temp < 85`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clear_expression: Option<String>,
+    /// Seconds `expression` must hold before the alarm is raised.
+    #[serde(default)]
+    pub raise_delay_secs: u64,
+    /// Seconds the clear condition must hold before the alarm clears.
+    #[serde(default)]
+    pub clear_delay_secs: u64,
+    pub severity: String,
+    /// Source of the raised alarm; defaults to `entmoot/pol/computed-alarms/{id}`.
+    #[serde(default)]
+    pub source: Option<String>,
+    pub enabled: bool,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl ComputedAlarmRule {
+    /// Parses the raise and clear expressions and checks that every variable
+    /// is bound.
+    pub fn compile(&self) -> Result<(Expr, Option<Expr>), String> {
+        if self.name.trim().is_empty() {
+            return Err("Rule name is required".to_string());
+        }
+        let expr = self.parse_bound(&self.expression)?;
+        let clear = match self.clear_expression.as_deref().map(str::trim) {
+            Some(clear) if !clear.is_empty() => Some(
+                self.parse_bound(clear)
+                    .map_err(|e| format!("Clear expression: {}", e))?,
+            ),
+            _ => None,
+        };
+        Ok((expr, clear))
+    }
+
+    fn parse_bound(&self, expression: &str) -> Result<Expr, String> {
+        let expr = Expr::parse(expression)?;
+        let unbound: Vec<String> = expr
+            .variables()
+            .into_iter()
+            .filter(|name| !self.bindings.contains_key(name))
+            .collect();
+        if !unbound.is_empty() {
+            return Err(format!("Unbound variables: {}", unbound.join(", ")));
+        }
+        if let Some((name, _)) = self.bindings.iter().find(|(_, key)| key.trim().is_empty()) {
+            return Err(format!("Variable '{}' is bound to an empty key", name));
+        }
+        Ok(expr)
+    }
+
+    pub fn alarm_source(&self) -> String {
+        self.source
+            .clone()
+            .filter(|source| !source.trim().is_empty())
+            .unwrap_or_else(|| format!("entmoot/pol/computed-alarms/{}", self.id))
+    }
+
+    /// Evaluates against the latest value of each bound key.
+    pub fn evaluate(
+        expr: &Expr,
+        bindings: &BTreeMap<String, String>,
+        values: &HashMap<String, ExprValue>,
+    ) -> Result<ExprValue, String> {
+        expr.eval(&|name| bindings.get(name).and_then(|key| values.get(key)).copied())
+    }
+}
+
+struct CompiledRule {
+    rule: ComputedAlarmRule,
+    expr: Expr,
+    clear: Option<Expr>,
+}
+
+/// Whether a rule's alarm is up, and since when the condition to change that
+/// has held while its delay runs.
+#[derive(Default)]
+struct RuleState {
+    active: bool,
+    pending_since: Option<Instant>,
+}
+
+impl CompiledRule {
+    /// Advances the rule on the latest values, returning the transition once
+    /// the raise or clear condition has held for its delay.
+    fn step(
+        &self,
+        state: &mut RuleState,
+        latest: &HashMap<String, ExprValue>,
+        now: Instant,
+    ) -> Option<Transition> {
+        let holds = |expr: &Expr| {
+            ComputedAlarmRule::evaluate(expr, &self.rule.bindings, latest)
+                .ok()
+                .and_then(|value| match value {
+                    ExprValue::Bool(holds) => Some(holds),
+                    ExprValue::Num(_) => None,
+                })
+        };
+        // Until every needed key has reported, the rule keeps its state.
+        let changing = match (state.active, &self.clear) {
+            (false, _) => holds(&self.expr)?,
+            (true, Some(clear)) => holds(clear)?,
+            (true, None) => !holds(&self.expr)?,
+        };
+        if !changing {
+            state.pending_since = None;
+            return None;
+        }
+        let delay = if state.active {
+            self.rule.clear_delay_secs
+        } else {
+            self.rule.raise_delay_secs
+        };
+        let since = *state.pending_since.get_or_insert(now);
+        if now.duration_since(since) < Duration::from_secs(delay) {
+            return None;
+        }
+        state.pending_since = None;
+        state.active = !state.active;
+        Some(if state.active {
+            Transition::Raised {
+                rule: Box::new(self.rule.clone()),
+                values: self.snapshot(latest),
+            }
+        } else {
+            Transition::Cleared {
+                rule_id: self.rule.id.clone(),
+            }
+        })
+    }
+
+    /// `name=value` for every bound variable with a value, for the alarm record.
+    fn snapshot(&self, values: &HashMap<String, ExprValue>) -> String {
+        self.rule
+            .bindings
+            .iter()
+            .filter_map(|(name, key)| values.get(key).map(|value| format!("{}={}", name, value)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Edge of a rule's condition, handed from ingest to the alarm raiser.
+pub enum Transition {
+    Raised {
+        rule: Box<ComputedAlarmRule>,
+        values: String,
+    },
+    Cleared {
+        rule_id: String,
+    },
+}
+
+/// Computed alarm rules, evaluated on ingest of any key they bind.
+#[derive(Clone)]
+pub struct ComputedAlarms {
+    rules: Arc<RwLock<HashMap<String, CompiledRule>>>,
+    /// Latest value of every bound key.
+    latest: Arc<RwLock<HashMap<String, ExprValue>>>,
+    states: Arc<RwLock<HashMap<String, RuleState>>>,
+    transitions: mpsc::UnboundedSender<Transition>,
+    receiver: Arc<std::sync::Mutex<Option<mpsc::UnboundedReceiver<Transition>>>>,
+}
+
+impl ComputedAlarms {
+    pub fn new(rules: HashMap<String, ComputedAlarmRule>) -> Self {
+        let compiled = rules
+            .into_iter()
+            .filter_map(|(id, rule)| match rule.compile() {
+                Ok((expr, clear)) => Some((id, CompiledRule { rule, expr, clear })),
+                Err(e) => {
+                    warn!("Skipping computed alarm rule {}: {}", id, e);
+                    None
+                }
+            })
+            .collect();
+        let (transitions, receiver) = mpsc::unbounded_channel();
+        Self {
+            rules: Arc::new(RwLock::new(compiled)),
+            latest: Arc::new(RwLock::new(HashMap::new())),
+            states: Arc::new(RwLock::new(HashMap::new())),
+            transitions,
+            receiver: Arc::new(std::sync::Mutex::new(Some(receiver))),
+        }
+    }
+
+    pub async fn list(&self) -> Vec<ComputedAlarmRule> {
+        let mut rules: Vec<ComputedAlarmRule> = self
+            .rules
+            .read()
+            .await
+            .values()
+            .map(|compiled| compiled.rule.clone())
+            .collect();
+        rules.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+        rules
+    }
+
+    pub async fn get(&self, id: &str) -> Option<ComputedAlarmRule> {
+        self.rules
+            .read()
+            .await
+            .get(id)
+            .map(|compiled| compiled.rule.clone())
+    }
+
+    pub async fn is_active(&self, id: &str) -> bool {
+        self.states
+            .read()
+            .await
+            .get(id)
+            .is_some_and(|state| state.active)
+    }
+
+    /// Adds or replaces a rule; a replaced rule's alarm clears and is
+    /// re-raised on the next sample if the new condition holds.
+    pub async fn upsert(&self, rule: ComputedAlarmRule) -> Result<(), String> {
+        let (expr, clear) = rule.compile()?;
+        let id = rule.id.clone();
+        self.rules
+            .write()
+            .await
+            .insert(id.clone(), CompiledRule { rule, expr, clear });
+        self.deactivate(&id).await;
+        Ok(())
+    }
+
+    pub async fn remove(&self, id: &str) -> bool {
+        let removed = self.rules.write().await.remove(id).is_some();
+        self.deactivate(id).await;
+        removed
+    }
+
+    async fn deactivate(&self, id: &str) {
+        let removed = self.states.write().await.remove(id);
+        if removed.is_some_and(|state| state.active) {
+            let _ = self.transitions.send(Transition::Cleared {
+                rule_id: id.to_string(),
+            });
+        }
+    }
+
+    /// Records a sample of `key` and re-evaluates the enabled rules that bind it.
+    pub async fn on_sample(&self, key: &str, value: &serde_json::Value) {
+        self.record(key, value, Instant::now()).await;
+    }
+
+    async fn record(&self, key: &str, value: &serde_json::Value, now: Instant) {
+        let rules = self.rules.read().await;
+        let affected: Vec<&CompiledRule> = rules
+            .values()
+            .filter(|compiled| {
+                compiled.rule.enabled && compiled.rule.bindings.values().any(|k| k == key)
+            })
+            .collect();
+        if affected.is_empty() {
+            return;
+        }
+        let Some(value) = ExprValue::from_json(value) else {
+            return;
+        };
+        let mut latest = self.latest.write().await;
+        latest.insert(key.to_string(), value);
+
+        let mut states = self.states.write().await;
+        for compiled in affected {
+            let state = states.entry(compiled.rule.id.clone()).or_default();
+            if let Some(transition) = compiled.step(state, &latest, now) {
+                let _ = self.transitions.send(transition);
+            }
+        }
+    }
+
+    /// Settles rules whose raise or clear delay ran out without a new sample.
+    pub async fn sweep(&self) {
+        self.sweep_at(Instant::now()).await;
+    }
+
+    async fn sweep_at(&self, now: Instant) {
+        let rules = self.rules.read().await;
+        let latest = self.latest.read().await;
+        let mut states = self.states.write().await;
+        for (id, state) in states.iter_mut() {
+            if state.pending_since.is_none() {
+                continue;
+            }
+            let Some(compiled) = rules.get(id).filter(|c| c.rule.enabled) else {
+                continue;
+            };
+            if let Some(transition) = compiled.step(state, &latest, now) {
+                let _ = self.transitions.send(transition);
+            }
+        }
+    }
+
+    fn take_receiver(&self) -> Option<mpsc::UnboundedReceiver<Transition>> {
+        self.receiver.lock().ok()?.take()
+    }
+}
+
+/// Raises and clears alarms for rule transitions, like alarms reported by PEAs:
+/// stored, published on the alarm bus, and passed to webhooks and automation.
+pub async fn raise_transitions(state: web::Data<AppState>) {
+    let Some(mut receiver) = state.computed_alarms.take_receiver() else {
+        error!("Computed alarm transitions are already being raised");
+        return;
+    };
+    // Open alarm per rule, so the clear reaches the alarm that was raised.
+    let mut raised: HashMap<String, String> = HashMap::new();
+
+    while let Some(transition) = receiver.recv().await {
+        // Followers mirror the leader's records instead.
+        if !state.leadership.is_leader() {
+            continue;
+        }
+        let (changed, newly_raised) = match transition {
+            Transition::Raised { rule, values } => {
+                let now = Utc::now().to_rfc3339();
+                let alarm = AlarmRecord {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    severity: rule.severity.clone(),
+                    status: "open".to_string(),
+                    source: rule.alarm_source(),
+                    event: rule.name.clone(),
+                    value: values,
+                    description: format!("Computed alarm: {}", rule.expression),
+                    timestamp: now.clone(),
+                    duplicate_count: 1,
+                    parent_id: None,
+                    raised_at: Some(now),
+                    acknowledged_at: None,
+                    cleared_at: None,
+                };
+                info!(
+                    "Computed alarm rule {} raised alarm {}",
+                    rule.name, alarm.id
+                );
+                raised.insert(rule.id, alarm.id.clone());
+                (alarm, true)
+            }
+            Transition::Cleared { rule_id } => {
+                let Some(alarm_id) = raised.remove(&rule_id) else {
+                    continue;
+                };
+                let mut alarms = state.alarms.write().await;
+                let Some(alarm) = alarms.get_mut(&alarm_id).filter(|a| a.status != "cleared")
+                else {
+                    continue;
+                };
+                alarm_sla::record_status(alarm, "cleared", Utc::now());
+                (alarm.clone(), false)
+            }
+        };
+        {
+            let mut alarms = state.alarms.write().await;
+            alarms.insert(changed.id.clone(), changed.clone());
+            pol_handlers::persist_alarms(&state.pol_db_dir, &alarms);
+        }
+        alarm_bus::publish(&state.zenoh_session, &state.chaos, &changed).await;
+        if let Err(e) = pol_handlers::upsert_alarm_db(&state.db_client, &changed).await {
+            error!("Failed to persist alarm in Postgres: {}", e);
+        }
+        if newly_raised {
+            state
+                .webhooks
+                .emit(
+                    webhook_service::EVENT_ALARM_RAISED,
+                    serde_json::json!(changed),
+                )
+                .await;
+            automation::on_alarm_raised(&state, &changed).await;
+            state.scripts.on_alarm(&changed).await;
+            soe::capture(&state, &changed).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(expression: &str) -> ComputedAlarmRule {
+        ComputedAlarmRule {
+            id: "oil".to_string(),
+            name: "Low oil pressure under load".to_string(),
+            expression: expression.to_string(),
+            bindings: BTreeMap::from([
+                ("oil_pressure".to_string(), "engine/oil".to_string()),
+                ("rpm".to_string(), "engine/rpm".to_string()),
+            ]),
+            clear_expression: None,
+            raise_delay_secs: 0,
+            clear_delay_secs: 0,
+            severity: "high".to_string(),
+            source: None,
+            enabled: true,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn compile_requires_bound_variables() {
+        assert!(rule("oil_pressure < 35 && rpm > 1500").compile().is_ok());
+        let err = rule("oil_pressure < 35 && load > 0.8")
+            .compile()
+            .unwrap_err();
+        assert!(err.contains("load"));
+    }
+
+    #[tokio::test]
+    async fn raises_on_interaction_and_clears_when_it_ends() {
+        let alarms = ComputedAlarms::new(HashMap::from([(
+            "oil".to_string(),
+            rule("oil_pressure < 35 && rpm > 1500"),
+        )]));
+        let mut receiver = alarms.take_receiver().unwrap();
+
+        alarms.on_sample("engine/oil", &serde_json::json!(30)).await;
+        assert!(receiver.try_recv().is_err(), "rpm has not reported yet");
+        alarms
+            .on_sample("engine/rpm", &serde_json::json!({"value": 1600}))
+            .await;
+        match receiver.try_recv() {
+            Ok(Transition::Raised { values, .. }) => {
+                assert_eq!(values, "oil_pressure=30, rpm=1600")
+            }
+            _ => panic!("expected the alarm to be raised"),
+        }
+        alarms
+            .on_sample("engine/rpm", &serde_json::json!(1700))
+            .await;
+        assert!(receiver.try_recv().is_err(), "still active, no new alarm");
+
+        alarms.on_sample("engine/oil", &serde_json::json!(40)).await;
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(Transition::Cleared { rule_id }) if rule_id == "oil"
+        ));
+        assert!(!alarms.is_active("oil").await);
+    }
+
+    #[tokio::test]
+    async fn clears_at_its_own_limit_after_the_delays() {
+        let mut rule = rule("oil_pressure < 35 && rpm > 1500");
+        rule.clear_expression = Some("oil_pressure > 40".to_string());
+        rule.raise_delay_secs = 5;
+        let alarms = ComputedAlarms::new(HashMap::from([("oil".to_string(), rule)]));
+        let mut receiver = alarms.take_receiver().unwrap();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        alarms
+            .record("engine/rpm", &serde_json::json!(1600), at(0))
+            .await;
+        alarms
+            .record("engine/oil", &serde_json::json!(30), at(0))
+            .await;
+        alarms
+            .record("engine/oil", &serde_json::json!(36), at(2))
+            .await;
+        alarms
+            .record("engine/oil", &serde_json::json!(30), at(3))
+            .await;
+        alarms.sweep_at(at(7)).await;
+        assert!(receiver.try_recv().is_err(), "the dip restarted the delay");
+        alarms.sweep_at(at(8)).await;
+        assert!(matches!(receiver.try_recv(), Ok(Transition::Raised { .. })));
+
+        // Between the raise and clear limits the alarm stays up.
+        alarms
+            .record("engine/oil", &serde_json::json!(38), at(9))
+            .await;
+        assert!(alarms.is_active("oil").await);
+        alarms
+            .record("engine/oil", &serde_json::json!(41), at(10))
+            .await;
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(Transition::Cleared { .. })
+        ));
+    }
+}
@@ -0,0 +1,398 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use shared::mtp::{PeaConfig, Recipe};
+use std::collections::HashMap;
+use tracing::{error, info};
+
+use crate::config_replication;
+use crate::config_store;
+use crate::pea_revisions;
+use crate::pol_handlers;
+use crate::state::{AlarmRule, AppState, PolTopology};
+
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+pub const ACTION_CREATE: &str = "create";
+pub const ACTION_UPDATE: &str = "update";
+pub const ACTION_UNCHANGED: &str = "unchanged";
+
+/// Configuration exported from one instance and applied to another: PEAs,
+/// recipes, alarm rules and the POL topology.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ConfigBundle {
+    #[serde(default = "default_format_version")]
+    pub format_version: u32,
+    #[serde(default)]
+    pub exported_at: String,
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub peas: Vec<PeaConfig>,
+    #[serde(default)]
+    pub recipes: Vec<Recipe>,
+    #[serde(default)]
+    pub alarm_rules: Vec<AlarmRule>,
+    #[serde(default)]
+    pub topology: Option<PolTopology>,
+}
+
+fn default_format_version() -> u32 {
+    BUNDLE_FORMAT_VERSION
+}
+
+/// One item of a bundle compared with this instance. `item` names it for
+/// selective apply: `pea:<id>`, `recipe:<id>`, `alarm_rule:<id>` or `topology`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BundleChange {
+    pub item: String,
+    pub kind: String,
+    pub id: String,
+    pub name: String,
+    /// `create`, `update` or `unchanged`.
+    pub action: String,
+    /// Top-level fields that differ, for updates.
+    pub fields: Vec<String>,
+}
+
+impl ConfigBundle {
+    /// The configuration currently held by this instance.
+    pub async fn export(state: &AppState, source: Option<String>) -> ConfigBundle {
+        let mut peas: Vec<PeaConfig> = state.pea_configs.read().await.values().cloned().collect();
+        peas.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut recipes: Vec<Recipe> = state.recipes.read().await.values().cloned().collect();
+        recipes.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut alarm_rules: Vec<AlarmRule> =
+            state.alarm_rules.read().await.values().cloned().collect();
+        alarm_rules.sort_by(|a, b| a.id.cmp(&b.id));
+        ConfigBundle {
+            format_version: BUNDLE_FORMAT_VERSION,
+            exported_at: Utc::now().to_rfc3339(),
+            source,
+            peas,
+            recipes,
+            alarm_rules,
+            topology: Some(state.topology.read().await.clone()),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.format_version != BUNDLE_FORMAT_VERSION {
+            return Err(format!(
+                "Unsupported bundle format_version {} (expected {})",
+                self.format_version, BUNDLE_FORMAT_VERSION
+            ));
+        }
+        let ids = self
+            .peas
+            .iter()
+            .map(|pea| ("PEA", &pea.id))
+            .chain(self.recipes.iter().map(|recipe| ("recipe", &recipe.id)))
+            .chain(self.alarm_rules.iter().map(|rule| ("alarm rule", &rule.id)));
+        let mut seen = std::collections::HashSet::new();
+        for (kind, id) in ids {
+            if id.is_empty() {
+                return Err(format!("Every {} in the bundle needs an id", kind));
+            }
+            if !seen.insert((kind, id)) {
+                return Err(format!("{} '{}' appears more than once", kind, id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Compares every item of this bundle with `current`. Timestamps are
+    /// ignored; items only present in `current` are not listed.
+    pub fn diff(&self, current: &ConfigBundle) -> Vec<BundleChange> {
+        let mut changes = Vec::new();
+        let peas: HashMap<&str, &PeaConfig> = current
+            .peas
+            .iter()
+            .map(|pea| (pea.id.as_str(), pea))
+            .collect();
+        for pea in &self.peas {
+            changes.push(change(
+                "pea",
+                &pea.id,
+                &pea.name,
+                pea,
+                peas.get(pea.id.as_str()).copied(),
+            ));
+        }
+        let recipes: HashMap<&str, &Recipe> = current
+            .recipes
+            .iter()
+            .map(|recipe| (recipe.id.as_str(), recipe))
+            .collect();
+        for recipe in &self.recipes {
+            changes.push(change(
+                "recipe",
+                &recipe.id,
+                &recipe.name,
+                recipe,
+                recipes.get(recipe.id.as_str()).copied(),
+            ));
+        }
+        let rules: HashMap<&str, &AlarmRule> = current
+            .alarm_rules
+            .iter()
+            .map(|rule| (rule.id.as_str(), rule))
+            .collect();
+        for rule in &self.alarm_rules {
+            changes.push(change(
+                "alarm_rule",
+                &rule.id,
+                &rule.name,
+                rule,
+                rules.get(rule.id.as_str()).copied(),
+            ));
+        }
+        if let Some(topology) = &self.topology {
+            changes.push(change(
+                "topology",
+                "",
+                "POL topology",
+                topology,
+                current.topology.as_ref(),
+            ));
+        }
+        changes
+    }
+}
+
+fn change<T: Serialize>(
+    kind: &str,
+    id: &str,
+    name: &str,
+    incoming: &T,
+    existing: Option<&T>,
+) -> BundleChange {
+    let (action, fields) = match existing {
+        None => (ACTION_CREATE, Vec::new()),
+        Some(existing) => {
+            let fields = changed_fields(&comparable(incoming), &comparable(existing));
+            if fields.is_empty() {
+                (ACTION_UNCHANGED, fields)
+            } else {
+                (ACTION_UPDATE, fields)
+            }
+        }
+    };
+    BundleChange {
+        item: if id.is_empty() {
+            kind.to_string()
+        } else {
+            format!("{}:{}", kind, id)
+        },
+        kind: kind.to_string(),
+        id: id.to_string(),
+        name: name.to_string(),
+        action: action.to_string(),
+        fields,
+    }
+}
+
+/// JSON form of an item without the timestamps that always differ between
+/// instances.
+fn comparable<T: Serialize>(value: &T) -> serde_json::Map<String, serde_json::Value> {
+    let mut object = match serde_json::to_value(value) {
+        Ok(serde_json::Value::Object(object)) => object,
+        _ => serde_json::Map::new(),
+    };
+    for field in ["created_at", "updated_at"] {
+        object.remove(field);
+    }
+    object
+}
+
+fn changed_fields(
+    a: &serde_json::Map<String, serde_json::Value>,
+    b: &serde_json::Map<String, serde_json::Value>,
+) -> Vec<String> {
+    let mut fields: Vec<String> = a
+        .keys()
+        .chain(b.keys())
+        .filter(|field| a.get(*field) != b.get(*field))
+        .cloned()
+        .collect();
+    fields.sort();
+    fields.dedup();
+    fields
+}
+
+/// The created and updated items of `bundle` whose `item` passes `selected`.
+pub async fn pending_changes(
+    state: &AppState,
+    bundle: &ConfigBundle,
+    selected: impl Fn(&str) -> bool,
+) -> Vec<BundleChange> {
+    let current = ConfigBundle::export(state, None).await;
+    bundle
+        .diff(&current)
+        .into_iter()
+        .filter(|change| change.action != ACTION_UNCHANGED && selected(&change.item))
+        .collect()
+}
+
+/// The PEAs of `bundle` that `changes` create or update.
+pub fn changed_peas<'a>(bundle: &'a ConfigBundle, changes: &[BundleChange]) -> Vec<&'a PeaConfig> {
+    changes
+        .iter()
+        .filter(|change| change.kind == "pea")
+        .filter_map(|change| bundle.peas.iter().find(|pea| pea.id == change.id))
+        .collect()
+}
+
+/// Applies `changes` from `bundle`. PEAs are expected to be validated and
+/// within their tenant's quota already; each gets an import revision.
+pub async fn apply(
+    state: &AppState,
+    bundle: &ConfigBundle,
+    changes: &[BundleChange],
+    actor_id: Option<String>,
+) {
+    let now = Utc::now();
+    for change in changes {
+        match change.kind.as_str() {
+            "pea" => {
+                let Some(pea) = bundle.peas.iter().find(|pea| pea.id == change.id) else {
+                    continue;
+                };
+                let mut pea = pea.clone();
+                pea.updated_at = now;
+                config_store::save_pea_config(state.config_store.as_ref(), &pea).await;
+                state
+                    .pea_configs
+                    .write()
+                    .await
+                    .insert(pea.id.clone(), pea.clone());
+                state
+                    .pea_revisions
+                    .record(&pea, pea_revisions::REASON_IMPORT, None, actor_id.clone())
+                    .await;
+            }
+            "recipe" => {
+                let Some(recipe) = bundle.recipes.iter().find(|recipe| recipe.id == change.id)
+                else {
+                    continue;
+                };
+                config_store::save_recipe(state.config_store.as_ref(), recipe).await;
+                state
+                    .recipes
+                    .write()
+                    .await
+                    .insert(recipe.id.clone(), recipe.clone());
+            }
+            "alarm_rule" => {
+                let Some(rule) = bundle.alarm_rules.iter().find(|rule| rule.id == change.id) else {
+                    continue;
+                };
+                let mut rule = rule.clone();
+                rule.updated_at = now.to_rfc3339();
+                if let Err(e) = pol_handlers::upsert_alarm_rule_db(&state.db_client, &rule).await {
+                    error!("Failed to persist alarm rule in Postgres: {}", e);
+                }
+                config_replication::replicate_alarm_rule(state, &rule.id, Some(&rule)).await;
+                state
+                    .alarm_rules
+                    .write()
+                    .await
+                    .insert(rule.id.clone(), rule);
+            }
+            "topology" => {
+                let Some(topology) = &bundle.topology else {
+                    continue;
+                };
+                let topology = PolTopology {
+                    edges: topology.edges.clone(),
+                    updated_at: now.to_rfc3339(),
+                };
+                pol_handlers::store_topology(state, &topology).await;
+            }
+            _ => {}
+        }
+    }
+    info!(
+        "Applied configuration bundle from {}: {} change(s)",
+        bundle.source.as_deref().unwrap_or("unknown source"),
+        changes.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(id: &str, severity: &str, updated_at: &str) -> AlarmRule {
+        AlarmRule {
+            id: id.to_string(),
+            name: format!("rule {}", id),
+            severity: severity.to_string(),
+            source_pattern: "*".to_string(),
+            event_pattern: "*".to_string(),
+            enabled: true,
+            created_at: updated_at.to_string(),
+            updated_at: updated_at.to_string(),
+        }
+    }
+
+    fn bundle(alarm_rules: Vec<AlarmRule>) -> ConfigBundle {
+        ConfigBundle {
+            format_version: BUNDLE_FORMAT_VERSION,
+            exported_at: String::new(),
+            source: None,
+            peas: vec![],
+            recipes: vec![],
+            alarm_rules,
+            topology: None,
+        }
+    }
+
+    #[test]
+    fn diff_ignores_timestamps_and_names_changed_fields() {
+        let current = bundle(vec![
+            rule("r1", "warning", "2026-01-01T00:00:00Z"),
+            rule("r2", "warning", "2026-01-01T00:00:00Z"),
+        ]);
+        let incoming = bundle(vec![
+            rule("r1", "warning", "2026-03-01T00:00:00Z"),
+            rule("r2", "critical", "2026-03-01T00:00:00Z"),
+            rule("r3", "info", "2026-03-01T00:00:00Z"),
+        ]);
+
+        let changes = incoming.diff(&current);
+        let actions: Vec<(&str, &str)> = changes
+            .iter()
+            .map(|change| (change.item.as_str(), change.action.as_str()))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                ("alarm_rule:r1", ACTION_UNCHANGED),
+                ("alarm_rule:r2", ACTION_UPDATE),
+                ("alarm_rule:r3", ACTION_CREATE),
+            ]
+        );
+        assert_eq!(changes[1].fields, vec!["severity".to_string()]);
+
+        assert!(bundle(vec![rule("r1", "a", ""), rule("r1", "b", "")])
+            .validate()
+            .is_err());
+    }
+    #[test]
+    fn changed_peas_are_the_ones_being_applied() {
+        let mut incoming = bundle(vec![]);
+        incoming.peas = vec![
+            crate::test_fixtures::pea_config("p1"),
+            crate::test_fixtures::pea_config("p2"),
+        ];
+        let changes = incoming.diff(&bundle(vec![]));
+        let p2: Vec<BundleChange> = changes
+            .into_iter()
+            .filter(|change| change.item == "pea:p2")
+            .collect();
+
+        let peas = changed_peas(&incoming, &p2);
+        assert_eq!(peas.len(), 1);
+        assert_eq!(peas[0].id, "p2");
+    }
+}
@@ -0,0 +1,143 @@
+use crate::config_bundle::{self, ConfigBundle, ACTION_UNCHANGED};
+use crate::pea_handlers;
+use crate::request_context::CallerContext;
+use crate::state::AppState;
+use crate::tenancy;
+use crate::tenant_handlers;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::Deserialize;
+use shared::mtp::PeaConfig;
+use std::collections::{HashMap, HashSet};
+use tracing::info;
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    /// Label stored in the bundle, e.g. `staging`.
+    pub source: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ApplyBundleRequest {
+    pub bundle: ConfigBundle,
+    /// Items to apply, as named in the diff (`pea:<id>`, `topology`, ...);
+    /// every created or updated item when omitted.
+    #[serde(default)]
+    pub items: Option<Vec<String>>,
+}
+
+/// GET /config/bundle?source=... — exports PEAs, recipes, alarm rules and topology.
+pub async fn export_bundle(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<ExportQuery>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_admin(&state, &req) {
+        return response;
+    }
+    HttpResponse::Ok().json(ConfigBundle::export(&state, query.into_inner().source).await)
+}
+
+/// POST /config/bundle/diff — what applying the posted bundle would change here.
+pub async fn diff_bundle(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<ConfigBundle>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_admin(&state, &req) {
+        return response;
+    }
+    let bundle = body.into_inner();
+    if let Err(err) = bundle.validate() {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": err}));
+    }
+    let current = ConfigBundle::export(&state, None).await;
+    let changes = bundle.diff(&current);
+    let pending = changes
+        .iter()
+        .filter(|change| change.action != ACTION_UNCHANGED)
+        .count();
+    HttpResponse::Ok().json(serde_json::json!({
+        "source": bundle.source,
+        "exported_at": bundle.exported_at,
+        "pending": pending,
+        "changes": changes,
+    }))
+}
+
+/// POST /config/bundle/apply — applies the selected items of a bundle. Items
+/// missing from the bundle are never deleted.
+pub async fn apply_bundle(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<ApplyBundleRequest>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_admin(&state, &req) {
+        return response;
+    }
+    let ApplyBundleRequest { bundle, items } = body.into_inner();
+    if let Err(err) = bundle.validate() {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": err}));
+    }
+    let items: Option<HashSet<String>> = items.map(|items| items.into_iter().collect());
+    let applied = config_bundle::pending_changes(&state, &bundle, |item| {
+        items.as_ref().is_none_or(|items| items.contains(item))
+    })
+    .await;
+    let peas = config_bundle::changed_peas(&bundle, &applied);
+    if let Some(response) = peas
+        .iter()
+        .find_map(|pea| pea_handlers::reject_invalid_config(pea))
+    {
+        return response;
+    }
+    if let Some(response) = reject_over_quota(&state, &peas).await {
+        return response;
+    }
+    let actor_id = CallerContext::from_request(&req).actor_id;
+    config_bundle::apply(&state, &bundle, &applied, actor_id.clone()).await;
+    info!(
+        "Configuration bundle applied by {}",
+        actor_id.as_deref().unwrap_or("unknown actor")
+    );
+    HttpResponse::Ok().json(serde_json::json!({ "applied": applied }))
+}
+
+/// Bundled PEAs that join a tenant count against its PEA quota.
+async fn reject_over_quota(state: &AppState, peas: &[&PeaConfig]) -> Option<HttpResponse> {
+    let mut adding: HashMap<&str, usize> = HashMap::new();
+    {
+        let configs = state.pea_configs.read().await;
+        for pea in peas {
+            let Some(tenant_id) = pea.tenant_id.as_deref() else {
+                continue;
+            };
+            if configs.get(&pea.id).and_then(|c| c.tenant_id.as_deref()) != Some(tenant_id) {
+                *adding.entry(tenant_id).or_default() += 1;
+            }
+        }
+    }
+    for (tenant_id, count) in adding {
+        let limit = state
+            .tenants
+            .read()
+            .await
+            .get(tenant_id)
+            .and_then(|tenant| tenant.quotas.max_peas);
+        let (used, _) = tenant_handlers::usage_counts(state, tenant_id).await;
+        if let Some(response) = tenancy::check_quota(limit, used, count, "PEAs") {
+            return Some(response);
+        }
+    }
+    None
+}
+
+/// Bundles span every tenant, so only the deployment admin token may use them;
+/// tenant tokens and header-asserted Admins are refused.
+fn reject_unless_admin(state: &AppState, req: &HttpRequest) -> Option<HttpResponse> {
+    if tenancy::is_deployment_admin(state, req) {
+        return None;
+    }
+    Some(HttpResponse::Forbidden().json(serde_json::json!({
+        "error": "Configuration bundles require the deployment admin token (DEPLOYMENT_ADMIN_TOKEN)"
+    })))
+}
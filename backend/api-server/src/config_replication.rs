@@ -0,0 +1,475 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use shared::mtp::{PeaConfig, Recipe};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::{error, info, warn};
+use zenoh::Session;
+
+use crate::config_store::{
+    self, ConfigStore, COLLECTION_ARCHIVED_PEA_CONFIGS, COLLECTION_PEA_CONFIGS, COLLECTION_RECIPES,
+};
+use crate::pol_handlers;
+use crate::runtime_store;
+use crate::state::{AlarmRule, AppState};
+
+pub const COLLECTION_ALARM_RULES: &str = "alarm-rules";
+
+/// Write version used for last-writer-wins: a hybrid clock in milliseconds,
+/// with the writing instance breaking ties.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Version {
+    pub ts_ms: i64,
+    pub origin: String,
+}
+
+/// One replicated write. `document` is `None` for deletes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplicationMessage {
+    pub collection: String,
+    pub id: String,
+    pub version: Version,
+    #[serde(default)]
+    pub document: Option<serde_json::Value>,
+}
+
+/// Versions of the documents this instance has written or accepted, including
+/// deletes, so an older write arriving late never overrides a newer one.
+#[derive(Default)]
+pub struct VersionClock {
+    last_ts_ms: i64,
+    versions: HashMap<(String, String), Version>,
+}
+
+/// One entry of a [`VersionClock`] as persisted on disk.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VersionRecord {
+    pub collection: String,
+    pub id: String,
+    pub version: Version,
+}
+
+impl VersionClock {
+    pub fn from_records(records: Vec<VersionRecord>) -> Self {
+        let mut clock = Self::default();
+        for record in records {
+            clock.last_ts_ms = clock.last_ts_ms.max(record.version.ts_ms);
+            clock
+                .versions
+                .insert((record.collection, record.id), record.version);
+        }
+        clock
+    }
+
+    pub fn records(&self) -> Vec<VersionRecord> {
+        self.versions
+            .iter()
+            .map(|((collection, id), version)| VersionRecord {
+                collection: collection.clone(),
+                id: id.clone(),
+                version: version.clone(),
+            })
+            .collect()
+    }
+
+    /// Version for a local write at `now_ms`; always newer than anything seen.
+    pub fn stamp(&mut self, collection: &str, id: &str, origin: &str, now_ms: i64) -> Version {
+        self.last_ts_ms = now_ms.max(self.last_ts_ms + 1);
+        let version = Version {
+            ts_ms: self.last_ts_ms,
+            origin: origin.to_string(),
+        };
+        self.versions
+            .insert((collection.to_string(), id.to_string()), version.clone());
+        version
+    }
+
+    /// Whether a remote write is newer than what this instance holds; records
+    /// it when it is.
+    pub fn accept(&mut self, collection: &str, id: &str, version: &Version) -> bool {
+        let key = (collection.to_string(), id.to_string());
+        if self.versions.get(&key).is_some_and(|held| held >= version) {
+            return false;
+        }
+        self.last_ts_ms = self.last_ts_ms.max(version.ts_ms);
+        self.versions.insert(key, version.clone());
+        true
+    }
+}
+
+/// Publishes this instance's configuration writes on
+/// `entmoot/config/replication` and applies the writes of its peers. Missed
+/// writes are caught up from the peers' snapshots.
+#[derive(Clone)]
+pub struct Replication {
+    origin: String,
+    session: Arc<Session>,
+    clock: Arc<Mutex<VersionClock>>,
+    /// Where the clock is kept, so a restarted instance still knows which
+    /// writes it already holds.
+    versions_path: String,
+}
+
+impl Replication {
+    /// Enabled with `CONFIG_REPLICATION=true`; writes are stamped with `instance_id`.
+    pub fn from_env(session: Arc<Session>, instance_id: &str) -> Option<Self> {
+        if !std::env::var("CONFIG_REPLICATION").is_ok_and(|v| v == "true") {
+            return None;
+        }
+        let origin = instance_id.to_string();
+        let versions_path = std::env::var("CONFIG_REPLICATION_VERSIONS_PATH")
+            .unwrap_or_else(|_| "./data/replication-versions.json".to_string());
+        let records =
+            runtime_store::load_json::<Vec<VersionRecord>>(&versions_path).unwrap_or_default();
+        info!(
+            "Configuration replication enabled as instance {} ({} known versions)",
+            origin,
+            records.len()
+        );
+        Some(Self {
+            origin,
+            session,
+            clock: Arc::new(Mutex::new(VersionClock::from_records(records))),
+            versions_path,
+        })
+    }
+
+    fn clock(&self) -> std::sync::MutexGuard<'_, VersionClock> {
+        self.clock.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn persist_versions(&self) {
+        let records = self.clock().records();
+        runtime_store::persist_json_file(&self.versions_path, &records);
+    }
+
+    pub async fn publish(&self, collection: &str, id: &str, document: Option<serde_json::Value>) {
+        let version = self.clock().stamp(
+            collection,
+            id,
+            &self.origin,
+            chrono::Utc::now().timestamp_millis(),
+        );
+        self.persist_versions();
+        let message = ReplicationMessage {
+            collection: collection.to_string(),
+            id: id.to_string(),
+            version,
+            document,
+        };
+        let payload = match serde_json::to_string(&message) {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!("Failed to serialize replication message: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = self
+            .session
+            .put(shared::mtp::topics::CONFIG_REPLICATION, payload)
+            .await
+        {
+            error!("Failed to replicate {} {}: {}", collection, id, e);
+        }
+    }
+
+    fn accept(&self, message: &ReplicationMessage) -> bool {
+        let accepted = message.version.origin != self.origin
+            && self
+                .clock()
+                .accept(&message.collection, &message.id, &message.version);
+        if accepted {
+            self.persist_versions();
+        }
+        accepted
+    }
+
+    /// Applies a peer write unless this instance already holds a newer one.
+    /// Returns whether it was applied.
+    async fn receive(
+        &self,
+        state: &AppState,
+        store: &dyn ConfigStore,
+        message: ReplicationMessage,
+    ) -> bool {
+        if !self.accept(&message) {
+            return false;
+        }
+        match apply(state, store, &message).await {
+            Ok(()) => true,
+            Err(e) => {
+                error!(
+                    "Failed to apply replicated {} {}: {:#}",
+                    message.collection, message.id, e
+                );
+                false
+            }
+        }
+    }
+
+    /// Applies peer writes to the in-memory maps and to `store`, the local
+    /// store without replication, so they are not published again.
+    pub async fn serve(self, state: actix_web::web::Data<AppState>, store: Arc<dyn ConfigStore>) {
+        let subscriber = match self
+            .session
+            .declare_subscriber(shared::mtp::topics::CONFIG_REPLICATION)
+            .await
+        {
+            Ok(subscriber) => subscriber,
+            Err(e) => {
+                error!("Failed to subscribe to configuration replication: {}", e);
+                return;
+            }
+        };
+        while let Ok(sample) = subscriber.recv_async().await {
+            let message =
+                match serde_json::from_slice::<ReplicationMessage>(&sample.payload().to_bytes()) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        warn!("Ignoring invalid replication message: {}", e);
+                        continue;
+                    }
+                };
+            self.receive(&state, store.as_ref(), message).await;
+        }
+    }
+
+    /// Answers snapshot queries with every document this instance holds a
+    /// version for, deletes included.
+    pub async fn serve_snapshots(self, state: actix_web::web::Data<AppState>) {
+        let key = shared::mtp::topics::config_replication_snapshot(&self.origin);
+        let queryable = match self.session.declare_queryable(&key).await {
+            Ok(queryable) => queryable,
+            Err(e) => {
+                error!("Failed to declare replication snapshot queryable: {}", e);
+                return;
+            }
+        };
+        while let Ok(query) = queryable.recv_async().await {
+            let snapshot = self.snapshot(&state).await;
+            let payload = serde_json::to_string(&snapshot).unwrap_or_else(|_| "[]".to_string());
+            if let Err(e) = query.reply(key.as_str(), payload).await {
+                warn!("Failed to reply to replication snapshot query: {}", e);
+            }
+        }
+    }
+
+    async fn snapshot(&self, state: &AppState) -> Vec<ReplicationMessage> {
+        let records = self.clock().records();
+        let mut messages = Vec::with_capacity(records.len());
+        for record in records {
+            let document = current_document(state, &record.collection, &record.id).await;
+            messages.push(ReplicationMessage {
+                collection: record.collection,
+                id: record.id,
+                version: record.version,
+                document,
+            });
+        }
+        messages
+    }
+
+    /// Fetches the peers' snapshots and applies every write newer than what
+    /// this instance holds. Run at startup and periodically, so writes missed
+    /// while down or disconnected are not lost. Returns the writes applied.
+    pub async fn catch_up(&self, state: &AppState, store: &dyn ConfigStore) -> usize {
+        let own_key = shared::mtp::topics::config_replication_snapshot(&self.origin);
+        let replies = match self
+            .session
+            .get(shared::mtp::topics::CONFIG_REPLICATION_SNAPSHOT_WILDCARD)
+            .await
+        {
+            Ok(replies) => replies,
+            Err(e) => {
+                warn!("Failed to query replication snapshots: {}", e);
+                return 0;
+            }
+        };
+        let mut messages = Vec::new();
+        while let Ok(reply) = replies.recv_async().await {
+            let Ok(sample) = reply.into_result() else {
+                continue;
+            };
+            if sample.key_expr().as_str() == own_key {
+                continue;
+            }
+            match serde_json::from_slice::<Vec<ReplicationMessage>>(&sample.payload().to_bytes()) {
+                Ok(snapshot) => messages.extend(snapshot),
+                Err(e) => warn!(
+                    "Ignoring invalid replication snapshot from {}: {}",
+                    sample.key_expr(),
+                    e
+                ),
+            }
+        }
+        let mut applied = 0;
+        for message in messages {
+            if self.receive(state, store, message).await {
+                applied += 1;
+            }
+        }
+        applied
+    }
+}
+
+/// The document currently held for a replicated item; `None` once deleted.
+async fn current_document(
+    state: &AppState,
+    collection: &str,
+    id: &str,
+) -> Option<serde_json::Value> {
+    let document = match collection {
+        COLLECTION_PEA_CONFIGS => serde_json::to_value(state.pea_configs.read().await.get(id)?),
+        COLLECTION_ARCHIVED_PEA_CONFIGS => {
+            serde_json::to_value(state.archived_pea_configs.read().await.get(id)?)
+        }
+        COLLECTION_RECIPES => serde_json::to_value(state.recipes.read().await.get(id)?),
+        COLLECTION_ALARM_RULES => serde_json::to_value(state.alarm_rules.read().await.get(id)?),
+        _ => return None,
+    };
+    document.ok()
+}
+
+async fn apply(
+    state: &AppState,
+    store: &dyn ConfigStore,
+    message: &ReplicationMessage,
+) -> Result<()> {
+    let id = message.id.clone();
+    match (message.collection.as_str(), message.document.clone()) {
+        (COLLECTION_PEA_CONFIGS, Some(document)) => {
+            let config: PeaConfig = serde_json::from_value(document)?;
+            config_store::save_pea_config(store, &config).await;
+            state.pea_configs.write().await.insert(id, config);
+        }
+        (COLLECTION_PEA_CONFIGS, None) => {
+            config_store::delete_pea_config(store, &id).await;
+            state.pea_configs.write().await.remove(&id);
+        }
+        (COLLECTION_ARCHIVED_PEA_CONFIGS, Some(document)) => {
+            let config: PeaConfig = serde_json::from_value(document)?;
+            config_store::archive_pea_config(store, &config).await;
+            state.pea_configs.write().await.remove(&id);
+            state.archived_pea_configs.write().await.insert(id, config);
+        }
+        (COLLECTION_ARCHIVED_PEA_CONFIGS, None) => {
+            config_store::delete_archived_pea_config(store, &id).await;
+            state.archived_pea_configs.write().await.remove(&id);
+        }
+        (COLLECTION_RECIPES, Some(document)) => {
+            let recipe: Recipe = serde_json::from_value(document)?;
+            config_store::save_recipe(store, &recipe).await;
+            state.recipes.write().await.insert(id, recipe);
+        }
+        (COLLECTION_RECIPES, None) => {
+            config_store::delete_recipe(store, &id).await;
+            state.recipes.write().await.remove(&id);
+        }
+        (COLLECTION_ALARM_RULES, Some(document)) => {
+            let rule: AlarmRule = serde_json::from_value(document)?;
+            pol_handlers::upsert_alarm_rule_db(&state.db_client, &rule).await?;
+            state.alarm_rules.write().await.insert(id, rule);
+        }
+        (COLLECTION_ALARM_RULES, None) => {
+            pol_handlers::delete_alarm_rule_db(&state.db_client, &id).await?;
+            state.alarm_rules.write().await.remove(&id);
+        }
+        (other, _) => anyhow::bail!("Unknown collection '{}'", other),
+    }
+    info!(
+        "Applied replicated {} {} from {}",
+        message.collection, message.id, message.version.origin
+    );
+    Ok(())
+}
+
+/// Publishes an alarm rule write when replication is enabled.
+pub async fn replicate_alarm_rule(state: &AppState, id: &str, rule: Option<&AlarmRule>) {
+    let Some(replication) = &state.replication else {
+        return;
+    };
+    let document = rule.and_then(|rule| serde_json::to_value(rule).ok());
+    replication
+        .publish(COLLECTION_ALARM_RULES, id, document)
+        .await;
+}
+
+/// Config store that publishes every write it persists.
+pub struct ReplicatedConfigStore {
+    inner: Arc<dyn ConfigStore>,
+    replication: Replication,
+}
+
+impl ReplicatedConfigStore {
+    pub fn new(inner: Arc<dyn ConfigStore>, replication: Replication) -> Self {
+        Self { inner, replication }
+    }
+}
+
+#[async_trait]
+impl ConfigStore for ReplicatedConfigStore {
+    fn backend(&self) -> &'static str {
+        self.inner.backend()
+    }
+
+    async fn put(&self, collection: &str, id: &str, document: serde_json::Value) -> Result<()> {
+        self.inner.put(collection, id, document.clone()).await?;
+        self.replication
+            .publish(collection, id, Some(document))
+            .await;
+        Ok(())
+    }
+
+    async fn delete(&self, collection: &str, id: &str) -> Result<()> {
+        self.inner.delete(collection, id).await?;
+        self.replication.publish(collection, id, None).await;
+        Ok(())
+    }
+
+    async fn list(&self, collection: &str) -> Result<Vec<serde_json::Value>> {
+        self.inner.list(collection).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(ts_ms: i64, origin: &str) -> Version {
+        Version {
+            ts_ms,
+            origin: origin.to_string(),
+        }
+    }
+
+    #[test]
+    fn newest_write_wins_on_every_instance() {
+        let mut a = VersionClock::default();
+        let mut b = VersionClock::default();
+
+        // Concurrent writes in the same millisecond: the origin breaks the tie.
+        let from_a = a.stamp("recipes", "r1", "a", 1_000);
+        let from_b = b.stamp("recipes", "r1", "b", 1_000);
+        assert!(!a.accept("recipes", "r1", &from_a));
+        assert!(a.accept("recipes", "r1", &from_b));
+        assert!(!b.accept("recipes", "r1", &from_a));
+
+        // A late, older write is dropped, and local clocks move past what was seen.
+        assert!(!a.accept("recipes", "r1", &version(900, "c")));
+        assert!(a.stamp("recipes", "r1", "a", 500) > from_b);
+        assert!(a.accept("recipes", "r2", &version(1, "c")));
+    }
+
+    #[test]
+    fn restored_clock_remembers_what_it_held() {
+        let mut before = VersionClock::default();
+        let held = before.stamp("recipes", "r1", "a", 2_000);
+        before.accept("alarm-rules", "x", &version(3_000, "b"));
+
+        let mut after = VersionClock::from_records(before.records());
+        assert!(!after.accept("recipes", "r1", &held));
+        assert!(!after.accept("alarm-rules", "x", &version(2_500, "c")));
+        assert!(after.stamp("recipes", "r2", "a", 0) > version(3_000, "b"));
+    }
+}
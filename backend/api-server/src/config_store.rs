@@ -0,0 +1,500 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use shared::mtp::{PeaConfig, Recipe};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio_postgres::Client;
+use tracing::{error, info};
+
+use crate::blob_store::{BlobStore, S3BlobStore};
+use crate::db;
+
+pub const COLLECTION_PEA_CONFIGS: &str = "pea-configs";
+/// Deleted PEA configs, kept for restore (`archive/` under the PEA config dir).
+pub const COLLECTION_ARCHIVED_PEA_CONFIGS: &str = "pea-configs-archive";
+pub const COLLECTION_RECIPES: &str = "recipes";
+
+/// Persistence for PEA configurations and recipes: JSON files in the data
+/// directories, a Postgres table or an S3-compatible bucket. Documents are
+/// stored per collection under their id.
+#[async_trait]
+pub trait ConfigStore: Send + Sync {
+    /// Short backend name reported in logs (`file`, `postgres` or `s3`).
+    fn backend(&self) -> &'static str;
+
+    async fn put(&self, collection: &str, id: &str, document: serde_json::Value) -> Result<()>;
+
+    async fn delete(&self, collection: &str, id: &str) -> Result<()>;
+
+    /// Every document of `collection`.
+    async fn list(&self, collection: &str) -> Result<Vec<serde_json::Value>>;
+}
+
+/// Builds the store selected by `CONFIG_STORE` (`file`, the default,
+/// `postgres` or `s3`).
+pub fn from_env(
+    pea_config_dir: &str,
+    recipe_dir: &str,
+    client: Arc<Client>,
+) -> Arc<dyn ConfigStore> {
+    match std::env::var("CONFIG_STORE").as_deref() {
+        Ok("postgres") => return Arc::new(PostgresConfigStore::new(client)),
+        Ok("s3") => match S3BlobStore::from_env() {
+            Ok(store) => return Arc::new(ObjectConfigStore::new(Arc::new(store))),
+            Err(e) => error!("S3 config store misconfigured, using local files: {}", e),
+        },
+        Ok("file") | Err(_) => {}
+        Ok(other) => tracing::warn!("Unknown CONFIG_STORE '{}', using local files", other),
+    }
+    let archive_dir = Path::new(pea_config_dir).join("archive");
+    Arc::new(FileConfigStore::new([
+        (COLLECTION_PEA_CONFIGS, pea_config_dir),
+        (
+            COLLECTION_ARCHIVED_PEA_CONFIGS,
+            &*archive_dir.to_string_lossy(),
+        ),
+        (COLLECTION_RECIPES, recipe_dir),
+    ]))
+}
+
+// ─── Typed Access ────────────────────────────────────────────────────────────
+
+async fn save<T: Serialize>(store: &dyn ConfigStore, collection: &str, id: &str, value: &T) {
+    let document = match serde_json::to_value(value) {
+        Ok(document) => document,
+        Err(e) => {
+            error!("Failed to serialize {} {}: {}", collection, id, e);
+            return;
+        }
+    };
+    if let Err(e) = store.put(collection, id, document).await {
+        error!("Failed to persist {} {}: {:#}", collection, id, e);
+    }
+}
+
+async fn remove(store: &dyn ConfigStore, collection: &str, id: &str) {
+    if let Err(e) = store.delete(collection, id).await {
+        error!("Failed to delete {} {}: {:#}", collection, id, e);
+    }
+}
+
+async fn load<T: DeserializeOwned>(store: &dyn ConfigStore, collection: &str) -> Vec<T> {
+    let documents = match store.list(collection).await {
+        Ok(documents) => documents,
+        Err(e) => {
+            error!("Failed to load {}: {:#}", collection, e);
+            return Vec::new();
+        }
+    };
+    documents
+        .into_iter()
+        .filter_map(|document| match serde_json::from_value::<T>(document) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                error!("Failed to parse a document of {}: {}", collection, e);
+                None
+            }
+        })
+        .collect()
+}
+
+pub async fn save_pea_config(store: &dyn ConfigStore, config: &PeaConfig) {
+    save(store, COLLECTION_PEA_CONFIGS, &config.id, config).await;
+}
+
+pub async fn delete_pea_config(store: &dyn ConfigStore, pea_id: &str) {
+    remove(store, COLLECTION_PEA_CONFIGS, pea_id).await;
+}
+
+pub async fn load_pea_configs(store: &dyn ConfigStore) -> HashMap<String, PeaConfig> {
+    let mut configs = HashMap::new();
+    for config in load::<PeaConfig>(store, COLLECTION_PEA_CONFIGS).await {
+        info!("Loaded PEA config: {} ({})", config.name, config.id);
+        configs.insert(config.id.clone(), config);
+    }
+    info!(
+        "Loaded {} PEA configurations from {} store",
+        configs.len(),
+        store.backend()
+    );
+    configs
+}
+
+/// Moves a PEA config into the archive; `config` carries the archive stamp.
+pub async fn archive_pea_config(store: &dyn ConfigStore, config: &PeaConfig) {
+    save(store, COLLECTION_ARCHIVED_PEA_CONFIGS, &config.id, config).await;
+    remove(store, COLLECTION_PEA_CONFIGS, &config.id).await;
+}
+
+/// Moves an archived PEA config back into the active set.
+pub async fn restore_pea_config(store: &dyn ConfigStore, config: &PeaConfig) {
+    save(store, COLLECTION_PEA_CONFIGS, &config.id, config).await;
+    remove(store, COLLECTION_ARCHIVED_PEA_CONFIGS, &config.id).await;
+}
+
+pub async fn delete_archived_pea_config(store: &dyn ConfigStore, pea_id: &str) {
+    remove(store, COLLECTION_ARCHIVED_PEA_CONFIGS, pea_id).await;
+}
+
+pub async fn load_archived_pea_configs(store: &dyn ConfigStore) -> HashMap<String, PeaConfig> {
+    let configs: HashMap<String, PeaConfig> =
+        load::<PeaConfig>(store, COLLECTION_ARCHIVED_PEA_CONFIGS)
+            .await
+            .into_iter()
+            .map(|config| (config.id.clone(), config))
+            .collect();
+    info!("Loaded {} archived PEA configurations", configs.len());
+    configs
+}
+
+pub async fn save_recipe(store: &dyn ConfigStore, recipe: &Recipe) {
+    save(store, COLLECTION_RECIPES, &recipe.id, recipe).await;
+}
+
+pub async fn delete_recipe(store: &dyn ConfigStore, recipe_id: &str) {
+    remove(store, COLLECTION_RECIPES, recipe_id).await;
+}
+
+pub async fn load_recipes(store: &dyn ConfigStore) -> HashMap<String, Recipe> {
+    let mut recipes = HashMap::new();
+    for recipe in load::<Recipe>(store, COLLECTION_RECIPES).await {
+        info!("Loaded recipe: {} ({})", recipe.name, recipe.id);
+        recipes.insert(recipe.id.clone(), recipe);
+    }
+    info!(
+        "Loaded {} recipes from {} store",
+        recipes.len(),
+        store.backend()
+    );
+    recipes
+}
+
+/// Rejects ids that could escape a collection directory or prefix.
+fn validate_id(id: &str) -> Result<()> {
+    if id.is_empty() || id.contains(['/', '\\']) || id == "." || id == ".." {
+        anyhow::bail!("Invalid document id '{}'", id);
+    }
+    Ok(())
+}
+
+// ─── Local Files ─────────────────────────────────────────────────────────────
+
+/// One `<id>.json` file per document, in a directory per collection.
+pub struct FileConfigStore {
+    dirs: HashMap<String, PathBuf>,
+}
+
+impl FileConfigStore {
+    pub fn new<'a>(dirs: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        Self {
+            dirs: dirs
+                .into_iter()
+                .map(|(collection, dir)| (collection.to_string(), PathBuf::from(dir)))
+                .collect(),
+        }
+    }
+
+    fn dir(&self, collection: &str) -> Result<&Path> {
+        self.dirs
+            .get(collection)
+            .map(PathBuf::as_path)
+            .with_context(|| format!("No directory for collection '{}'", collection))
+    }
+
+    fn path_for(&self, collection: &str, id: &str) -> Result<PathBuf> {
+        validate_id(id)?;
+        Ok(self.dir(collection)?.join(format!("{}.json", id)))
+    }
+}
+
+#[async_trait]
+impl ConfigStore for FileConfigStore {
+    fn backend(&self) -> &'static str {
+        "file"
+    }
+
+    async fn put(&self, collection: &str, id: &str, document: serde_json::Value) -> Result<()> {
+        let path = self.path_for(collection, id)?;
+        tokio::fs::create_dir_all(self.dir(collection)?).await?;
+        tokio::fs::write(&path, serde_json::to_string_pretty(&document)?)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    async fn delete(&self, collection: &str, id: &str) -> Result<()> {
+        let path = self.path_for(collection, id)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn list(&self, collection: &str) -> Result<Vec<serde_json::Value>> {
+        let dir = self.dir(collection)?;
+        let mut entries = match tokio::fs::read_dir(dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tokio::fs::create_dir_all(dir).await?;
+                return Ok(Vec::new());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let mut documents = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            match tokio::fs::read_to_string(&path).await {
+                Ok(content) => match serde_json::from_str(&content) {
+                    Ok(document) => documents.push(document),
+                    Err(e) => error!("Failed to parse {}: {}", path.display(), e),
+                },
+                Err(e) => error!("Failed to read {}: {}", path.display(), e),
+            }
+        }
+        Ok(documents)
+    }
+}
+
+// ─── Postgres ────────────────────────────────────────────────────────────────
+
+/// Documents in the `config_documents` table.
+pub struct PostgresConfigStore {
+    client: Arc<Client>,
+}
+
+impl PostgresConfigStore {
+    pub fn new(client: Arc<Client>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl ConfigStore for PostgresConfigStore {
+    fn backend(&self) -> &'static str {
+        "postgres"
+    }
+
+    async fn put(&self, collection: &str, id: &str, document: serde_json::Value) -> Result<()> {
+        db::upsert_config_document(&self.client, collection, id, &document).await
+    }
+
+    async fn delete(&self, collection: &str, id: &str) -> Result<()> {
+        db::delete_config_document(&self.client, collection, id).await
+    }
+
+    async fn list(&self, collection: &str) -> Result<Vec<serde_json::Value>> {
+        db::list_config_documents(&self.client, collection).await
+    }
+}
+
+// ─── Object Storage ──────────────────────────────────────────────────────────
+
+/// Documents stored as `config/<collection>/<id>.json` objects.
+pub struct ObjectConfigStore {
+    blobs: Arc<dyn BlobStore>,
+}
+
+impl ObjectConfigStore {
+    pub fn new(blobs: Arc<dyn BlobStore>) -> Self {
+        Self { blobs }
+    }
+
+    fn key_for(collection: &str, id: &str) -> Result<String> {
+        validate_id(id)?;
+        Ok(format!("config/{}/{}.json", collection, id))
+    }
+}
+
+#[async_trait]
+impl ConfigStore for ObjectConfigStore {
+    fn backend(&self) -> &'static str {
+        self.blobs.backend()
+    }
+
+    async fn put(&self, collection: &str, id: &str, document: serde_json::Value) -> Result<()> {
+        let key = Self::key_for(collection, id)?;
+        self.blobs
+            .put(
+                &key,
+                serde_json::to_vec_pretty(&document)?,
+                "application/json",
+            )
+            .await
+    }
+
+    async fn delete(&self, collection: &str, id: &str) -> Result<()> {
+        self.blobs.delete(&Self::key_for(collection, id)?).await
+    }
+
+    async fn list(&self, collection: &str) -> Result<Vec<serde_json::Value>> {
+        let prefix = format!("config/{}/", collection);
+        let mut documents = Vec::new();
+        for key in self.blobs.list(&prefix).await? {
+            if !key.ends_with(".json") {
+                continue;
+            }
+            let bytes = self.blobs.get(&key).await?;
+            match serde_json::from_slice(&bytes) {
+                Ok(document) => documents.push(document),
+                Err(e) => error!("Failed to parse {}: {}", key, e),
+            }
+        }
+        Ok(documents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use shared::mtp::{OpcUaConfig, PeaMode, ServiceConfig, WriterInfo};
+
+    fn unique_temp_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!(
+            "fendtastic-config-store-{name}-{}",
+            uuid::Uuid::new_v4()
+        ));
+        dir.to_string_lossy().to_string()
+    }
+
+    fn sample_pea_config(id: &str, name: &str) -> PeaConfig {
+        PeaConfig {
+            id: id.to_string(),
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            description: "test pea".to_string(),
+            writer: WriterInfo {
+                name: "test-writer".to_string(),
+                version: "1.0.0".to_string(),
+                vendor: "tests".to_string(),
+            },
+            services: vec![ServiceConfig {
+                tag: "svc.main".to_string(),
+                name: "Main Service".to_string(),
+                description: "primary service".to_string(),
+                config_parameters: vec![],
+                procedures: vec![],
+            }],
+            active_elements: vec![],
+            opcua_config: OpcUaConfig {
+                endpoint: "opc.tcp://127.0.0.1:4841/test".to_string(),
+                namespace_uri: "urn:fendtastic:test".to_string(),
+                security_policy: "Basic256Sha256".to_string(),
+            },
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            tenant_id: None,
+            mode: PeaMode::Simulated,
+            archived_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn load_pea_configs_reads_local_json_files() {
+        let dir = unique_temp_dir("load-peas");
+        let store = FileConfigStore::new([(COLLECTION_PEA_CONFIGS, dir.as_str())]);
+        let config = sample_pea_config("pea-1", "Test PEA");
+        save_pea_config(&store, &config).await;
+
+        let configs = load_pea_configs(&store).await;
+
+        assert_eq!(configs.len(), 1);
+        let loaded = configs.get("pea-1").expect("missing persisted pea");
+        assert_eq!(loaded.name, "Test PEA");
+
+        delete_pea_config(&store, "pea-1").await;
+        assert!(load_pea_configs(&store).await.is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn archived_pea_configs_move_to_the_archive_folder_and_back() {
+        let dir = unique_temp_dir("archive-peas");
+        let archive_dir = format!("{}/archive", dir);
+        let store = FileConfigStore::new([
+            (COLLECTION_PEA_CONFIGS, dir.as_str()),
+            (COLLECTION_ARCHIVED_PEA_CONFIGS, archive_dir.as_str()),
+        ]);
+        let mut config = sample_pea_config("pea-1", "Test PEA");
+        save_pea_config(&store, &config).await;
+
+        config.archived_at = Some(Utc::now());
+        archive_pea_config(&store, &config).await;
+        assert!(load_pea_configs(&store).await.is_empty());
+        assert!(Path::new(&archive_dir).join("pea-1.json").exists());
+        let archived = load_archived_pea_configs(&store).await;
+        assert!(archived["pea-1"].archived_at.is_some());
+
+        config.archived_at = None;
+        restore_pea_config(&store, &config).await;
+        assert!(load_archived_pea_configs(&store).await.is_empty());
+        assert_eq!(load_pea_configs(&store).await["pea-1"].name, "Test PEA");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn pea_configs_without_a_mode_load_as_simulated() {
+        let mut value = serde_json::to_value(sample_pea_config("pea-1", "Test PEA")).unwrap();
+        value.as_object_mut().unwrap().remove("mode");
+        let config: PeaConfig = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(config.mode, PeaMode::Simulated);
+
+        value["mode"] = serde_json::json!("external");
+        let config: PeaConfig = serde_json::from_value(value).unwrap();
+        assert_eq!(config.mode, PeaMode::External);
+        assert_eq!(config.mode.origin(), "external");
+    }
+
+    #[tokio::test]
+    async fn load_recipes_reads_local_json_files() {
+        let dir = unique_temp_dir("load-recipes");
+        let store = FileConfigStore::new([(COLLECTION_RECIPES, dir.as_str())]);
+        let recipe = Recipe {
+            id: "recipe-1".to_string(),
+            name: "Test Recipe".to_string(),
+            description: "test recipe".to_string(),
+            steps: vec![],
+            library_steps: vec![],
+            triggers: vec![],
+            created_at: Utc::now(),
+            tenant_id: None,
+        };
+        save_recipe(&store, &recipe).await;
+
+        let recipes = load_recipes(&store).await;
+
+        assert_eq!(recipes.len(), 1);
+        let loaded = recipes.get("recipe-1").expect("missing persisted recipe");
+        assert_eq!(loaded.name, "Test Recipe");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn object_store_keeps_documents_under_the_collection_prefix() {
+        let root = unique_temp_dir("objects");
+        let blobs = Arc::new(crate::blob_store::LocalBlobStore::new(&root));
+        let store = ObjectConfigStore::new(blobs.clone());
+        save_pea_config(&store, &sample_pea_config("pea-1", "Test PEA")).await;
+
+        assert_eq!(
+            blobs.list("config/").await.unwrap(),
+            vec!["config/pea-configs/pea-1.json".to_string()]
+        );
+        assert_eq!(load_pea_configs(&store).await.len(), 1);
+        assert!(store
+            .put(COLLECTION_RECIPES, "../x", serde_json::json!({}))
+            .await
+            .is_err());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
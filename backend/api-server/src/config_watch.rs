@@ -0,0 +1,301 @@
+use actix_web::web;
+use chrono::Utc;
+use notify::{RecursiveMode, Watcher};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use shared::mtp::{PeaConfig, Recipe};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::RwLock;
+use tracing::{error, info, warn};
+
+use crate::config_store::{COLLECTION_PEA_CONFIGS, COLLECTION_RECIPES};
+use crate::pea_revisions;
+use crate::pea_validation;
+use crate::state::AppState;
+use crate::task_registry::TaskHandle;
+
+pub const ACTION_UPSERTED: &str = "upserted";
+pub const ACTION_REMOVED: &str = "removed";
+
+/// A data directory whose JSON files back one collection.
+pub struct WatchedDir {
+    pub collection: &'static str,
+    pub dir: PathBuf,
+}
+
+/// The collection and document id a changed file belongs to: `<id>.json`
+/// directly inside one of the watched directories.
+pub fn document_for(path: &Path, dirs: &[WatchedDir]) -> Option<(&'static str, String)> {
+    if path.extension().is_none_or(|ext| ext != "json") {
+        return None;
+    }
+    let parent = path.parent()?;
+    let watched = dirs.iter().find(|watched| watched.dir == parent)?;
+    let id = path.file_stem()?.to_str()?.to_string();
+    Some((watched.collection, id))
+}
+
+/// Hot-loads PEA configuration and recipe files added, changed or removed in
+/// `dirs` by something other than the API, and announces each change on
+/// `entmoot/config/changes`. Writes made through the API leave the file equal
+/// to the in-memory copy and are not announced twice. PEA configs are
+/// validated like API writes and each loaded one is kept as a revision.
+pub async fn watch(state: web::Data<AppState>, dirs: Vec<WatchedDir>, task: TaskHandle) {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = match notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    }) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            task.fail(format!("Failed to create config directory watcher: {}", e));
+            return;
+        }
+    };
+
+    let mut watched = Vec::new();
+    for WatchedDir { collection, dir } in dirs {
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            error!("Failed to create {}: {}", dir.display(), e);
+            continue;
+        }
+        // Events carry absolute paths, so match them against the canonical directory.
+        let dir = dir.canonicalize().unwrap_or(dir);
+        match watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            Ok(()) => {
+                info!("Watching {} for {} changes", dir.display(), collection);
+                watched.push(WatchedDir { collection, dir });
+            }
+            Err(e) => error!("Failed to watch {}: {}", dir.display(), e),
+        }
+    }
+    if watched.is_empty() {
+        task.fail("No config directory could be watched");
+        return;
+    }
+
+    while let Some(event) = rx.recv().await {
+        task.beat();
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                warn!("Config directory watcher error: {}", e);
+                continue;
+            }
+        };
+        if event.kind.is_access() || event.kind.is_other() {
+            continue;
+        }
+        for path in &event.paths {
+            let Some((collection, id)) = document_for(path, &watched) else {
+                continue;
+            };
+            let action = match collection {
+                COLLECTION_PEA_CONFIGS => {
+                    let action = sync_file(
+                        &state.pea_configs,
+                        &id,
+                        path,
+                        |config: &PeaConfig| config.id.clone(),
+                        check_pea_config,
+                    )
+                    .await;
+                    if action == Some(ACTION_UPSERTED) {
+                        record_revision(&state, &id).await;
+                    }
+                    action
+                }
+                COLLECTION_RECIPES => {
+                    sync_file(
+                        &state.recipes,
+                        &id,
+                        path,
+                        |recipe: &Recipe| recipe.id.clone(),
+                        |_| Ok(()),
+                    )
+                    .await
+                }
+                _ => None,
+            };
+            if let Some(action) = action {
+                info!("Config file {} {} ({})", path.display(), action, collection);
+                publish_change(&state, collection, &id, action).await;
+            }
+        }
+    }
+}
+
+/// Brings `map` in line with the file at `path`. Returns the action taken, or
+/// `None` when the file is unreadable, fails `check`, is named after another
+/// id than the document's, or already matches.
+async fn sync_file<T, F, C>(
+    map: &RwLock<HashMap<String, T>>,
+    file_id: &str,
+    path: &Path,
+    id_of: F,
+    check: C,
+) -> Option<&'static str>
+where
+    T: DeserializeOwned + Serialize,
+    F: Fn(&T) -> String,
+    C: Fn(&T) -> Result<(), String>,
+{
+    let content = match tokio::fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return map.write().await.remove(file_id).map(|_| ACTION_REMOVED);
+        }
+        Err(e) => {
+            warn!("Failed to read {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    // Editors often write in several steps; the last event carries the complete file.
+    let document: T = match serde_json::from_str(&content) {
+        Ok(document) => document,
+        Err(e) => {
+            warn!("Ignoring {} until it parses: {}", path.display(), e);
+            return None;
+        }
+    };
+    let id = id_of(&document);
+    // Removals go by file name, so a file must be named after its document.
+    if id != file_id {
+        warn!(
+            "Ignoring {}: it holds '{}' and must be named {}.json",
+            path.display(),
+            id,
+            id
+        );
+        return None;
+    }
+    if let Err(e) = check(&document) {
+        warn!("Ignoring {}: {}", path.display(), e);
+        return None;
+    }
+    let mut map = map.write().await;
+    let unchanged = map.get(&id).is_some_and(|current| {
+        serde_json::to_value(current).ok() == serde_json::to_value(&document).ok()
+    });
+    if unchanged {
+        return None;
+    }
+    map.insert(id, document);
+    Some(ACTION_UPSERTED)
+}
+
+fn check_pea_config(config: &PeaConfig) -> Result<(), String> {
+    let errors = pea_validation::validate_pea_config(config);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let errors: Vec<String> = errors
+        .iter()
+        .map(|error| format!("{}: {}", error.path, error.message))
+        .collect();
+    Err(format!("PEA config is invalid ({})", errors.join("; ")))
+}
+
+async fn record_revision(state: &AppState, pea_id: &str) {
+    let config = state.pea_configs.read().await.get(pea_id).cloned();
+    if let Some(config) = config {
+        state
+            .pea_revisions
+            .record(&config, pea_revisions::REASON_FILE, None, None)
+            .await;
+    }
+}
+
+async fn publish_change(state: &AppState, collection: &str, id: &str, action: &str) {
+    let payload = serde_json::json!({
+        "collection": collection,
+        "id": id,
+        "action": action,
+        "source": "file",
+        "at": Utc::now().to_rfc3339(),
+    });
+    if let Err(e) = state
+        .zenoh_session
+        .put(shared::mtp::topics::CONFIG_CHANGES, payload.to_string())
+        .await
+    {
+        error!(
+            "Failed to publish config change for {} {}: {}",
+            collection, id, e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_json_files_directly_in_a_watched_dir_count() {
+        let dirs = [
+            WatchedDir {
+                collection: COLLECTION_PEA_CONFIGS,
+                dir: PathBuf::from("/data/pea-configs"),
+            },
+            WatchedDir {
+                collection: COLLECTION_RECIPES,
+                dir: PathBuf::from("/data/recipes"),
+            },
+        ];
+
+        assert_eq!(
+            document_for(Path::new("/data/recipes/r1.json"), &dirs),
+            Some((COLLECTION_RECIPES, "r1".to_string()))
+        );
+        assert_eq!(
+            document_for(Path::new("/data/pea-configs/p1.json"), &dirs),
+            Some((COLLECTION_PEA_CONFIGS, "p1".to_string()))
+        );
+        assert_eq!(
+            document_for(Path::new("/data/recipes/r1.json.swp"), &dirs),
+            None
+        );
+        assert_eq!(
+            document_for(Path::new("/data/recipes/old/r1.json"), &dirs),
+            None
+        );
+        assert_eq!(document_for(Path::new("/data/other/r1.json"), &dirs), None);
+    }
+
+    #[tokio::test]
+    async fn files_must_be_named_after_a_valid_document() {
+        let dir = std::env::temp_dir().join(format!("config-watch-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("p1.json");
+        let map: RwLock<HashMap<String, PeaConfig>> = RwLock::new(HashMap::new());
+        let sync = |map, path| {
+            sync_file(
+                map,
+                "p1",
+                path,
+                |config: &PeaConfig| config.id.clone(),
+                check_pea_config,
+            )
+        };
+
+        let renamed = crate::test_fixtures::pea_config("p2");
+        std::fs::write(&path, serde_json::to_string(&renamed).unwrap()).unwrap();
+        assert_eq!(sync(&map, &path).await, None);
+        assert!(map.read().await.is_empty());
+
+        let mut invalid = crate::test_fixtures::pea_config("p1");
+        let service = invalid.services[0].clone();
+        invalid.services.push(service);
+        std::fs::write(&path, serde_json::to_string(&invalid).unwrap()).unwrap();
+        assert_eq!(sync(&map, &path).await, None);
+
+        let valid = crate::test_fixtures::pea_config("p1");
+        std::fs::write(&path, serde_json::to_string(&valid).unwrap()).unwrap();
+        assert_eq!(sync(&map, &path).await, Some(ACTION_UPSERTED));
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(sync(&map, &path).await, Some(ACTION_REMOVED));
+        assert!(map.read().await.is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
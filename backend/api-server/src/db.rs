@@ -2,7 +2,14 @@ use chrono::{DateTime, Utc};
 use tokio_postgres::{Client, NoTls};
 use tracing::{error, info};
 
-use crate::state::{AlarmRecord, AlarmRule, BlackoutWindow, PolEdge, PolTopology};
+use crate::pea_lifecycle::{LifecycleEvent, Phase};
+use crate::pea_revisions::PeaRevision;
+use crate::playback_handlers::PlaybackInfo;
+use crate::state::{
+    AlarmRecord, AlarmRule, AttachmentRecord, BlackoutWindow, PolEdge, PolTopology, TimeSeriesPoint,
+};
+use crate::ts_provenance::Producer;
+use shared::UserAction;
 
 pub async fn connect_and_migrate(db_url: &str) -> anyhow::Result<Client> {
     let (client, connection) = tokio_postgres::connect(db_url, NoTls).await?;
@@ -27,6 +34,11 @@ pub async fn connect_and_migrate(db_url: &str) -> anyhow::Result<Client> {
                 duplicate_count INTEGER NOT NULL DEFAULT 1
             );
 
+            ALTER TABLE alarms ADD COLUMN IF NOT EXISTS parent_id TEXT;
+            ALTER TABLE alarms ADD COLUMN IF NOT EXISTS raised_at TIMESTAMPTZ;
+            ALTER TABLE alarms ADD COLUMN IF NOT EXISTS acknowledged_at TIMESTAMPTZ;
+            ALTER TABLE alarms ADD COLUMN IF NOT EXISTS cleared_at TIMESTAMPTZ;
+
             CREATE TABLE IF NOT EXISTS alarm_rules (
                 id TEXT PRIMARY KEY,
                 name TEXT NOT NULL,
@@ -53,6 +65,81 @@ pub async fn connect_and_migrate(db_url: &str) -> anyhow::Result<Client> {
                 updated_at TIMESTAMPTZ NOT NULL,
                 PRIMARY KEY (source_pea, target_pea)
             );
+
+            CREATE TABLE IF NOT EXISTS pea_attachments (
+                id TEXT PRIMARY KEY,
+                pea_id TEXT NOT NULL,
+                filename TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                category TEXT NOT NULL DEFAULT 'document',
+                description TEXT NOT NULL DEFAULT '',
+                size_bytes BIGINT NOT NULL,
+                sha256 TEXT NOT NULL,
+                storage_key TEXT NOT NULL,
+                uploaded_by TEXT,
+                created_at TIMESTAMPTZ NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS pea_attachments_pea_id_idx ON pea_attachments (pea_id);
+
+            CREATE TABLE IF NOT EXISTS pea_lifecycle_events (
+                id TEXT PRIMARY KEY,
+                pea_id TEXT NOT NULL,
+                transition TEXT NOT NULL,
+                cause TEXT NOT NULL,
+                actor TEXT,
+                occurred_at TIMESTAMPTZ NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS pea_lifecycle_events_pea_idx
+                ON pea_lifecycle_events (pea_id, occurred_at);
+
+            CREATE TABLE IF NOT EXISTS config_documents (
+                collection TEXT NOT NULL,
+                id TEXT NOT NULL,
+                document JSONB NOT NULL,
+                updated_at TIMESTAMPTZ NOT NULL,
+                PRIMARY KEY (collection, id)
+            );
+
+            CREATE TABLE IF NOT EXISTS ts_points (
+                key TEXT NOT NULL,
+                ts_ms BIGINT NOT NULL,
+                value JSONB NOT NULL,
+                PRIMARY KEY (key, ts_ms)
+            );
+
+            ALTER TABLE ts_points ADD COLUMN IF NOT EXISTS producer TEXT;
+
+            CREATE TABLE IF NOT EXISTS pea_config_revisions (
+                pea_id TEXT NOT NULL,
+                revision INTEGER NOT NULL,
+                reason TEXT NOT NULL,
+                restored_from INTEGER,
+                actor TEXT,
+                created_at TIMESTAMPTZ NOT NULL,
+                config JSONB NOT NULL,
+                PRIMARY KEY (pea_id, revision)
+            );
+
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id TEXT PRIMARY KEY,
+                entity TEXT NOT NULL,
+                entity_id TEXT NOT NULL,
+                action TEXT NOT NULL,
+                actor TEXT,
+                payload JSONB NOT NULL,
+                occurred_at TIMESTAMPTZ NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS audit_log_entity_idx
+                ON audit_log (entity, entity_id, occurred_at);
+
+            CREATE TABLE IF NOT EXISTS playback_sessions (
+                id TEXT PRIMARY KEY,
+                info JSONB NOT NULL,
+                last_active_at TIMESTAMPTZ NOT NULL
+            );
             ",
         )
         .await?;
@@ -61,12 +148,16 @@ pub async fn connect_and_migrate(db_url: &str) -> anyhow::Result<Client> {
     Ok(client)
 }
 
+fn rfc3339(time: Option<DateTime<Utc>>) -> Option<String> {
+    time.map(|time| time.to_rfc3339())
+}
+
 pub async fn load_alarms(
     client: &Client,
 ) -> anyhow::Result<std::collections::HashMap<String, AlarmRecord>> {
     let rows = client
         .query(
-            "SELECT id, severity, status, source, event, value, description, timestamp, duplicate_count FROM alarms",
+            "SELECT id, severity, status, source, event, value, description, timestamp, duplicate_count, parent_id, raised_at, acknowledged_at, cleared_at FROM alarms",
             &[],
         )
         .await?;
@@ -85,6 +176,10 @@ pub async fn load_alarms(
                 description: row.get(6),
                 timestamp: row.get::<_, DateTime<Utc>>(7).to_rfc3339(),
                 duplicate_count: row.get::<_, i32>(8) as u32,
+                parent_id: row.get(9),
+                raised_at: rfc3339(row.get(10)),
+                acknowledged_at: rfc3339(row.get(11)),
+                cleared_at: rfc3339(row.get(12)),
             },
         );
     }
@@ -162,3 +257,496 @@ pub async fn load_topology(client: &Client) -> anyhow::Result<PolTopology> {
     }
     Ok(PolTopology { edges, updated_at })
 }
+
+const ATTACHMENT_COLUMNS: &str = "id, pea_id, filename, content_type, category, description, size_bytes, sha256, storage_key, uploaded_by, created_at";
+
+fn attachment_from_row(row: &tokio_postgres::Row) -> AttachmentRecord {
+    AttachmentRecord {
+        id: row.get(0),
+        pea_id: row.get(1),
+        filename: row.get(2),
+        content_type: row.get(3),
+        category: row.get(4),
+        description: row.get(5),
+        size_bytes: row.get(6),
+        sha256: row.get(7),
+        storage_key: row.get(8),
+        uploaded_by: row.get(9),
+        created_at: row.get::<_, DateTime<Utc>>(10).to_rfc3339(),
+    }
+}
+
+pub async fn list_attachments(
+    client: &Client,
+    pea_id: &str,
+) -> anyhow::Result<Vec<AttachmentRecord>> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM pea_attachments WHERE pea_id=$1 ORDER BY created_at DESC",
+                ATTACHMENT_COLUMNS
+            ),
+            &[&pea_id],
+        )
+        .await?;
+    Ok(rows.iter().map(attachment_from_row).collect())
+}
+
+pub async fn get_attachment(
+    client: &Client,
+    pea_id: &str,
+    attachment_id: &str,
+) -> anyhow::Result<Option<AttachmentRecord>> {
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM pea_attachments WHERE pea_id=$1 AND id=$2",
+                ATTACHMENT_COLUMNS
+            ),
+            &[&pea_id, &attachment_id],
+        )
+        .await?;
+    Ok(row.as_ref().map(attachment_from_row))
+}
+
+pub async fn insert_attachment(
+    client: &Client,
+    attachment: &AttachmentRecord,
+) -> anyhow::Result<()> {
+    let created_at = DateTime::parse_from_rfc3339(&attachment.created_at)?.with_timezone(&Utc);
+    client
+        .execute(
+            &format!(
+                "INSERT INTO pea_attachments ({}) VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11)",
+                ATTACHMENT_COLUMNS
+            ),
+            &[
+                &attachment.id,
+                &attachment.pea_id,
+                &attachment.filename,
+                &attachment.content_type,
+                &attachment.category,
+                &attachment.description,
+                &attachment.size_bytes,
+                &attachment.sha256,
+                &attachment.storage_key,
+                &attachment.uploaded_by,
+                &created_at,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_attachment(client: &Client, attachment_id: &str) -> anyhow::Result<()> {
+    client
+        .execute("DELETE FROM pea_attachments WHERE id=$1", &[&attachment_id])
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_attachments_for_pea(client: &Client, pea_id: &str) -> anyhow::Result<u64> {
+    let removed = client
+        .execute("DELETE FROM pea_attachments WHERE pea_id=$1", &[&pea_id])
+        .await?;
+    Ok(removed)
+}
+
+fn lifecycle_event_from_row(row: &tokio_postgres::Row) -> LifecycleEvent {
+    LifecycleEvent {
+        id: row.get(0),
+        pea_id: row.get(1),
+        transition: row.get(2),
+        cause: row.get(3),
+        actor: row.get(4),
+        occurred_at: row.get(5),
+    }
+}
+
+pub async fn insert_lifecycle_event(client: &Client, event: &LifecycleEvent) -> anyhow::Result<()> {
+    client
+        .execute(
+            "INSERT INTO pea_lifecycle_events (id, pea_id, transition, cause, actor, occurred_at)
+             VALUES ($1,$2,$3,$4,$5,$6)",
+            &[
+                &event.id,
+                &event.pea_id,
+                &event.transition,
+                &event.cause,
+                &event.actor,
+                &event.occurred_at,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+/// Events of a PEA in `[start, end]`, oldest first.
+pub async fn list_lifecycle_events(
+    client: &Client,
+    pea_id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> anyhow::Result<Vec<LifecycleEvent>> {
+    let rows = client
+        .query(
+            "SELECT id, pea_id, transition, cause, actor, occurred_at FROM pea_lifecycle_events
+             WHERE pea_id=$1 AND occurred_at >= $2 AND occurred_at <= $3
+             ORDER BY occurred_at, id",
+            &[&pea_id, &start, &end],
+        )
+        .await?;
+    Ok(rows.iter().map(lifecycle_event_from_row).collect())
+}
+
+/// The phase a PEA was in at `at`, from its last earlier event.
+pub async fn lifecycle_phase_at(
+    client: &Client,
+    pea_id: &str,
+    at: DateTime<Utc>,
+) -> anyhow::Result<Phase> {
+    let row = client
+        .query_opt(
+            "SELECT transition FROM pea_lifecycle_events WHERE pea_id=$1 AND occurred_at < $2
+             ORDER BY occurred_at DESC, id DESC LIMIT 1",
+            &[&pea_id, &at],
+        )
+        .await?;
+    Ok(row
+        .map(|row| Phase::after(row.get::<_, &str>(0)))
+        .unwrap_or_default())
+}
+
+/// The current phase of every PEA with recorded events, or with `cause` set,
+/// the phase its last event of that cause left it in.
+pub async fn load_lifecycle_phases(
+    client: &Client,
+    cause: Option<&str>,
+) -> anyhow::Result<std::collections::HashMap<String, Phase>> {
+    let rows = client
+        .query(
+            "SELECT DISTINCT ON (pea_id) pea_id, transition FROM pea_lifecycle_events
+             WHERE $1::TEXT IS NULL OR cause = $1
+             ORDER BY pea_id, occurred_at DESC, id DESC",
+            &[&cause],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| (row.get(0), Phase::after(row.get::<_, &str>(1))))
+        .collect())
+}
+
+pub async fn upsert_config_document(
+    client: &Client,
+    collection: &str,
+    id: &str,
+    document: &serde_json::Value,
+) -> anyhow::Result<()> {
+    client
+        .execute(
+            "INSERT INTO config_documents (collection, id, document, updated_at)
+             VALUES ($1,$2,$3,$4)
+             ON CONFLICT (collection, id) DO UPDATE SET
+               document=EXCLUDED.document,
+               updated_at=EXCLUDED.updated_at",
+            &[&collection, &id, document, &Utc::now()],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_config_document(
+    client: &Client,
+    collection: &str,
+    id: &str,
+) -> anyhow::Result<()> {
+    client
+        .execute(
+            "DELETE FROM config_documents WHERE collection=$1 AND id=$2",
+            &[&collection, &id],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn list_config_documents(
+    client: &Client,
+    collection: &str,
+) -> anyhow::Result<Vec<serde_json::Value>> {
+    let rows = client
+        .query(
+            "SELECT document FROM config_documents WHERE collection=$1 ORDER BY id",
+            &[&collection],
+        )
+        .await?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+pub async fn insert_pea_config_revision(
+    client: &Client,
+    revision: &PeaRevision,
+) -> anyhow::Result<()> {
+    client
+        .execute(
+            "INSERT INTO pea_config_revisions
+             (pea_id, revision, reason, restored_from, actor, created_at, config)
+             VALUES ($1,$2,$3,$4,$5,$6,$7)",
+            &[
+                &revision.pea_id,
+                &(revision.revision as i32),
+                &revision.reason,
+                &revision.restored_from.map(|n| n as i32),
+                &revision.actor,
+                &revision.created_at,
+                &serde_json::to_value(&revision.config)?,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn latest_pea_config_revision(client: &Client, pea_id: &str) -> anyhow::Result<u32> {
+    let row = client
+        .query_one(
+            "SELECT COALESCE(MAX(revision), 0) FROM pea_config_revisions WHERE pea_id=$1",
+            &[&pea_id],
+        )
+        .await?;
+    Ok(row.get::<_, i32>(0) as u32)
+}
+
+/// Revisions of a PEA, oldest first.
+pub async fn list_pea_config_revisions(
+    client: &Client,
+    pea_id: &str,
+) -> anyhow::Result<Vec<PeaRevision>> {
+    let rows = client
+        .query(
+            "SELECT pea_id, revision, reason, restored_from, actor, created_at, config
+             FROM pea_config_revisions WHERE pea_id=$1 ORDER BY revision",
+            &[&pea_id],
+        )
+        .await?;
+    rows.iter().map(pea_revision_from_row).collect()
+}
+
+pub async fn get_pea_config_revision(
+    client: &Client,
+    pea_id: &str,
+    revision: u32,
+) -> anyhow::Result<Option<PeaRevision>> {
+    let row = client
+        .query_opt(
+            "SELECT pea_id, revision, reason, restored_from, actor, created_at, config
+             FROM pea_config_revisions WHERE pea_id=$1 AND revision=$2",
+            &[&pea_id, &(revision as i32)],
+        )
+        .await?;
+    row.as_ref().map(pea_revision_from_row).transpose()
+}
+
+fn pea_revision_from_row(row: &tokio_postgres::Row) -> anyhow::Result<PeaRevision> {
+    Ok(PeaRevision {
+        pea_id: row.get(0),
+        revision: row.get::<_, i32>(1) as u32,
+        reason: row.get(2),
+        restored_from: row.get::<_, Option<i32>>(3).map(|n| n as u32),
+        actor: row.get(4),
+        created_at: row.get(5),
+        config: serde_json::from_value(row.get(6))?,
+    })
+}
+
+pub async fn insert_audit_entry(client: &Client, entry: &UserAction) -> anyhow::Result<()> {
+    client
+        .execute(
+            "INSERT INTO audit_log (id, entity, entity_id, action, actor, payload, occurred_at)
+             VALUES ($1,$2,$3,$4,$5,$6,$7)",
+            &[
+                &entry.id,
+                &entry.entity,
+                &entry.machine_id,
+                &entry.action,
+                &entry.user_id,
+                &entry.payload,
+                &entry.timestamp,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+/// One page of the audit entries of an entity kind in `[start, end]`, newest
+/// first, with the number of entries in the window; all entities of the kind
+/// when `entity_id` is `None`.
+pub async fn list_audit_entries(
+    client: &Client,
+    entity: &str,
+    entity_id: Option<&str>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    offset: i64,
+    limit: i64,
+) -> anyhow::Result<(Vec<UserAction>, i64)> {
+    let filter = "entity=$1 AND ($2::TEXT IS NULL OR entity_id=$2)
+                  AND occurred_at >= $3 AND occurred_at <= $4";
+    let total = client
+        .query_one(
+            &format!("SELECT COUNT(*) FROM audit_log WHERE {}", filter),
+            &[&entity, &entity_id, &start, &end],
+        )
+        .await?
+        .get::<_, i64>(0);
+    let rows = client
+        .query(
+            &format!(
+                "SELECT id, entity, entity_id, action, actor, payload, occurred_at FROM audit_log
+                 WHERE {} ORDER BY occurred_at DESC, id DESC OFFSET $5 LIMIT $6",
+                filter
+            ),
+            &[&entity, &entity_id, &start, &end, &offset, &limit],
+        )
+        .await?;
+    let entries = rows
+        .iter()
+        .map(|row| UserAction {
+            id: row.get(0),
+            entity: row.get(1),
+            machine_id: row.get(2),
+            action: row.get(3),
+            user_id: row.get(4),
+            payload: row.get(5),
+            timestamp: row.get(6),
+        })
+        .collect();
+    Ok((entries, total))
+}
+
+pub async fn upsert_playback_session(client: &Client, info: &PlaybackInfo) -> anyhow::Result<()> {
+    client
+        .execute(
+            "INSERT INTO playback_sessions (id, info, last_active_at)
+             VALUES ($1,$2,$3)
+             ON CONFLICT (id) DO UPDATE SET
+               info=EXCLUDED.info,
+               last_active_at=EXCLUDED.last_active_at",
+            &[&info.id, &serde_json::to_value(info)?, &info.last_active_at],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_playback_session(client: &Client, id: &str) -> anyhow::Result<()> {
+    client
+        .execute("DELETE FROM playback_sessions WHERE id=$1", &[&id])
+        .await?;
+    Ok(())
+}
+
+/// Saved playback sessions active at or after `since`.
+pub async fn load_playback_sessions(
+    client: &Client,
+    since: DateTime<Utc>,
+) -> anyhow::Result<Vec<serde_json::Value>> {
+    let rows = client
+        .query(
+            "SELECT info FROM playback_sessions WHERE last_active_at >= $1",
+            &[&since],
+        )
+        .await?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+pub async fn delete_idle_playback_sessions(
+    client: &Client,
+    before: DateTime<Utc>,
+) -> anyhow::Result<u64> {
+    let deleted = client
+        .execute(
+            "DELETE FROM playback_sessions WHERE last_active_at < $1",
+            &[&before],
+        )
+        .await?;
+    Ok(deleted)
+}
+
+/// Writes historian points; a point already stored for the same key and time is kept.
+pub async fn insert_ts_points(
+    client: &Client,
+    points: &[(String, TimeSeriesPoint)],
+) -> anyhow::Result<u64> {
+    let keys: Vec<&str> = points.iter().map(|(key, _)| key.as_str()).collect();
+    let timestamps: Vec<i64> = points.iter().map(|(_, point)| point.timestamp_ms).collect();
+    let values: Vec<&serde_json::Value> = points.iter().map(|(_, point)| &point.value).collect();
+    let producers: Vec<Option<&str>> = points
+        .iter()
+        .map(|(_, point)| point.producer.map(Producer::as_str))
+        .collect();
+    let inserted = client
+        .execute(
+            "INSERT INTO ts_points (key, ts_ms, value, producer)
+             SELECT * FROM UNNEST($1::text[], $2::bigint[], $3::jsonb[], $4::text[])
+             ON CONFLICT (key, ts_ms) DO NOTHING",
+            &[&keys, &timestamps, &values, &producers],
+        )
+        .await?;
+    Ok(inserted)
+}
+
+pub async fn query_ts_points(
+    client: &Client,
+    key: &str,
+    start_ms: i64,
+    end_ms: i64,
+) -> anyhow::Result<Vec<TimeSeriesPoint>> {
+    let rows = client
+        .query(
+            "SELECT ts_ms, value, producer FROM ts_points
+             WHERE key=$1 AND ts_ms BETWEEN $2 AND $3
+             ORDER BY ts_ms",
+            &[&key, &start_ms, &end_ms],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| TimeSeriesPoint {
+            timestamp_ms: row.get(0),
+            value: row.get(1),
+            producer: row.get::<_, Option<&str>>(2).and_then(Producer::parse),
+        })
+        .collect())
+}
+
+pub async fn delete_ts_points_under(client: &Client, prefix: &str) -> anyhow::Result<u64> {
+    let deleted = client
+        .execute(
+            "DELETE FROM ts_points WHERE key=$1 OR starts_with(key, $1 || '/')",
+            &[&prefix],
+        )
+        .await?;
+    Ok(deleted)
+}
+
+/// Moves historian points from keys under `old_prefix` to the same keys under
+/// `new_prefix`; points already stored under the new key are kept.
+pub async fn rename_ts_points(
+    client: &Client,
+    old_prefix: &str,
+    new_prefix: &str,
+) -> anyhow::Result<u64> {
+    let moved = client
+        .execute(
+            "WITH moved AS (
+                 DELETE FROM ts_points
+                 WHERE key=$1 OR starts_with(key, $1 || '/')
+                 RETURNING key, ts_ms, value, producer
+             )
+             INSERT INTO ts_points (key, ts_ms, value, producer)
+             SELECT $2 || substr(key, length($1) + 1), ts_ms, value, producer FROM moved
+             ON CONFLICT (key, ts_ms) DO NOTHING",
+            &[&old_prefix, &new_prefix],
+        )
+        .await?;
+    Ok(moved)
+}
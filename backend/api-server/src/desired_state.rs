@@ -0,0 +1,194 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use shared::mtp::{PeaConfig, Recipe};
+use std::collections::{HashMap, HashSet};
+use tracing::info;
+
+use crate::config_store;
+use crate::pea_drift::{self, PeaDrift, ReconcileReport};
+use crate::pea_lifecycle::Phase;
+use crate::runtime_store;
+use crate::state::AppState;
+use crate::tenancy::TenantScope;
+
+/// Reconciliation trigger recorded for runs of the desired-state loop.
+pub const TRIGGER_DESIRED_STATE: &str = "desired_state";
+
+/// One PEA of a desired-state document.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DesiredPea {
+    pub pea_id: String,
+    #[serde(default)]
+    pub deployed: bool,
+    #[serde(default)]
+    pub running: bool,
+    /// Configuration to create or replace before the PEA is reconciled; the
+    /// stored configuration is used when omitted.
+    #[serde(default)]
+    pub config: Option<PeaConfig>,
+}
+
+impl DesiredPea {
+    pub fn phase(&self) -> Phase {
+        Phase {
+            deployed: self.deployed || self.running,
+            running: self.running,
+        }
+    }
+}
+
+/// Declarative state of the PEA fleet, applied through `POST /apply`. PEAs not
+/// listed are left alone.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DesiredState {
+    #[serde(default)]
+    pub peas: Vec<DesiredPea>,
+    /// Recipes to create or replace.
+    #[serde(default)]
+    pub recipes: Vec<Recipe>,
+    #[serde(default)]
+    pub applied_at: String,
+    #[serde(default)]
+    pub applied_by: Option<String>,
+}
+
+impl DesiredState {
+    /// Checks that every PEA is listed once and is either already configured
+    /// (`known`) or carries its configuration.
+    pub fn validate(&self, known: &HashSet<String>) -> Result<(), String> {
+        let mut seen = HashSet::new();
+        for pea in &self.peas {
+            if pea.pea_id.is_empty() {
+                return Err("Every PEA needs a pea_id".to_string());
+            }
+            if !seen.insert(pea.pea_id.as_str()) {
+                return Err(format!("PEA '{}' is listed more than once", pea.pea_id));
+            }
+            match &pea.config {
+                Some(config) if !config.id.is_empty() && config.id != pea.pea_id => {
+                    return Err(format!(
+                        "Config id '{}' does not match PEA '{}'",
+                        config.id, pea.pea_id
+                    ));
+                }
+                None if !known.contains(&pea.pea_id) => {
+                    return Err(format!(
+                        "PEA '{}' is not configured and has no config",
+                        pea.pea_id
+                    ));
+                }
+                _ => {}
+            }
+        }
+        let mut recipe_ids = HashSet::new();
+        for recipe in &self.recipes {
+            if recipe.id.is_empty() {
+                return Err("Every recipe needs an id".to_string());
+            }
+            if !recipe_ids.insert(recipe.id.as_str()) {
+                return Err(format!("Recipe '{}' is listed more than once", recipe.id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Desired phase of each listed PEA.
+    pub fn phases(&self) -> HashMap<String, Phase> {
+        self.peas
+            .iter()
+            .map(|pea| (pea.pea_id.clone(), pea.phase()))
+            .collect()
+    }
+}
+
+/// Stores the configurations and recipes of `document` and makes it the
+/// document the reconciler drives towards.
+pub async fn apply(state: &AppState, mut document: DesiredState) -> DesiredState {
+    let now = Utc::now();
+    for pea in &mut document.peas {
+        let Some(config) = pea.config.as_mut() else {
+            continue;
+        };
+        config.id = pea.pea_id.clone();
+        config.updated_at = now;
+        let mut configs = state.pea_configs.write().await;
+        if let Some(existing) = configs.get(&config.id) {
+            config.created_at = existing.created_at;
+            config.tenant_id = config.tenant_id.take().or(existing.tenant_id.clone());
+        }
+        config_store::save_pea_config(state.config_store.as_ref(), config).await;
+        configs.insert(config.id.clone(), config.clone());
+    }
+    {
+        let mut recipes = state.recipes.write().await;
+        for recipe in &document.recipes {
+            config_store::save_recipe(state.config_store.as_ref(), recipe).await;
+            recipes.insert(recipe.id.clone(), recipe.clone());
+        }
+    }
+    document.applied_at = now.to_rfc3339();
+    runtime_store::persist_json_file(&state.desired_state_path, &document);
+    *state.desired_state.write().await = Some(document.clone());
+    info!(
+        "Applied desired state: {} PEA(s), {} recipe(s)",
+        document.peas.len(),
+        document.recipes.len()
+    );
+    document
+}
+
+/// Drift of the listed PEAs from the applied document.
+pub async fn detect(state: &AppState, document: &DesiredState) -> Vec<PeaDrift> {
+    pea_drift::detect(state, &TenantScope::Deployment, &document.phases(), None).await
+}
+
+/// One pass of the reconciler: issues deploy, start, stop and undeploy
+/// commands until the listed PEAs match the applied document. Returns `None`
+/// when no document is applied or nothing has drifted.
+pub async fn reconcile(state: &AppState) -> Option<ReconcileReport> {
+    let document = state.desired_state.read().await.clone()?;
+    let drifts = detect(state, &document).await;
+    if drifts.is_empty() {
+        return None;
+    }
+    Some(pea_drift::reconcile(state, &drifts, TRIGGER_DESIRED_STATE, true).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pea(pea_id: &str, deployed: bool, running: bool) -> DesiredPea {
+        DesiredPea {
+            pea_id: pea_id.to_string(),
+            deployed,
+            running,
+            config: None,
+        }
+    }
+
+    #[test]
+    fn documents_list_known_peas_once() {
+        let known: HashSet<String> = ["p1".to_string(), "p2".to_string()].into();
+        let document = |peas: Vec<DesiredPea>| DesiredState {
+            peas,
+            recipes: vec![],
+            applied_at: String::new(),
+            applied_by: None,
+        };
+
+        let valid = document(vec![pea("p1", true, false), pea("p2", false, true)]);
+        assert!(valid.validate(&known).is_ok());
+        assert_eq!(valid.phases()["p1"], Phase::IDLE);
+        assert_eq!(valid.phases()["p2"], Phase::RUNNING);
+
+        assert!(
+            document(vec![pea("p1", true, false), pea("p1", false, false)])
+                .validate(&known)
+                .is_err()
+        );
+        assert!(document(vec![pea("p9", true, false)])
+            .validate(&known)
+            .is_err());
+    }
+}
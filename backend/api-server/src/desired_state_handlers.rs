@@ -0,0 +1,95 @@
+use crate::desired_state::{self, DesiredState};
+use crate::request_context::CallerContext;
+use crate::state::AppState;
+use crate::tenancy;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::Deserialize;
+use std::collections::HashSet;
+use tracing::info;
+
+#[derive(Deserialize)]
+pub struct ApplyQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// GET /apply — the applied desired-state document, the current drift from it
+/// and the last reconciliation run.
+pub async fn get_desired_state(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    let document = state.desired_state.read().await.clone();
+    let drift = match &document {
+        Some(document) => desired_state::detect(&state, document).await,
+        None => Vec::new(),
+    };
+    let last_reconcile = state.pea_reconcile_report.read().await.clone();
+    HttpResponse::Ok().json(serde_json::json!({
+        "document": document,
+        "drift": drift,
+        "last_reconcile": last_reconcile,
+    }))
+}
+
+/// POST /apply?dry_run=... — replaces the desired-state document; with
+/// `dry_run=true` only validates it and reports the drift it would cause.
+pub async fn apply_desired_state(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<ApplyQuery>,
+    body: web::Json<DesiredState>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    let mut document = body.into_inner();
+    let known: HashSet<String> = state.pea_configs.read().await.keys().cloned().collect();
+    if let Err(err) = document.validate(&known) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": err}));
+    }
+    if query.dry_run {
+        let drift = desired_state::detect(&state, &document).await;
+        return HttpResponse::Ok().json(serde_json::json!({
+            "dry_run": true,
+            "document": document,
+            "drift": drift,
+        }));
+    }
+
+    document.applied_by = CallerContext::from_request(&req).actor_id;
+    let document = desired_state::apply(&state, document).await;
+    let drift = desired_state::detect(&state, &document).await;
+    HttpResponse::Ok().json(serde_json::json!({
+        "dry_run": false,
+        "document": document,
+        "drift": drift,
+    }))
+}
+
+/// DELETE /apply — leaves desired-state mode. PEAs keep their current state.
+pub async fn clear_desired_state(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    if state.desired_state.write().await.take().is_none() {
+        return HttpResponse::NotFound()
+            .json(serde_json::json!({"error": "No desired state is applied"}));
+    }
+    if let Err(err) = std::fs::remove_file(&state.desired_state_path) {
+        if err.kind() != std::io::ErrorKind::NotFound {
+            tracing::error!("Failed to delete {}: {}", state.desired_state_path, err);
+        }
+    }
+    info!("Desired-state mode disabled");
+    HttpResponse::NoContent().finish()
+}
+
+fn reject_unless_elevated(state: &AppState, req: &HttpRequest) -> Option<HttpResponse> {
+    if tenancy::is_deployment_admin(state, req) {
+        return None;
+    }
+    Some(HttpResponse::Forbidden().json(serde_json::json!({
+        "error": "Desired-state mode requires the deployment admin token"
+    })))
+}
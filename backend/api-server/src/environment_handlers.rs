@@ -0,0 +1,135 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+
+use crate::environments::ExecutionEnvironment;
+use crate::runtime_store;
+use crate::state::AppState;
+use crate::tenancy::{self, TenantScope};
+
+pub async fn list_environments(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let environments = state.environments.read().await;
+    let mut list: Vec<&ExecutionEnvironment> = environments
+        .values()
+        .filter(|environment| scope.allows(environment.tenant_id.as_deref()))
+        .collect();
+    list.sort_by(|a, b| a.id.cmp(&b.id));
+    HttpResponse::Ok().json(serde_json::json!({
+        "default": state.default_environment,
+        "environments": list,
+    }))
+}
+
+pub async fn get_environment(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    environment_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    match state
+        .environments
+        .read()
+        .await
+        .get(environment_id.as_str())
+        .filter(|environment| scope.allows(environment.tenant_id.as_deref()))
+    {
+        Some(environment) => HttpResponse::Ok().json(environment),
+        None => environment_not_found(),
+    }
+}
+
+/// PUT /environments/{id} — create or replace an environment's bindings
+pub async fn put_environment(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    environment_id: web::Path<String>,
+    body: web::Json<ExecutionEnvironment>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut environment = body.into_inner();
+    environment.id = environment_id.into_inner();
+    if let Err(e) = environment.validate() {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    if let Some(response) = reject_unknown_peas(&state, &scope, &environment).await {
+        return response;
+    }
+
+    let mut environments = state.environments.write().await;
+    if environments
+        .get(&environment.id)
+        .is_some_and(|existing| !scope.allows(existing.tenant_id.as_deref()))
+    {
+        return HttpResponse::Conflict()
+            .json(serde_json::json!({"error": "Environment id is already in use"}));
+    }
+    let created = !environments.contains_key(&environment.id);
+    environment.tenant_id = scope.tenant_id().map(str::to_string);
+    environment.updated_at = Utc::now().to_rfc3339();
+    runtime_store::persist_json(&state.environment_dir, &environment.id, &environment);
+    environments.insert(environment.id.clone(), environment.clone());
+    if created {
+        HttpResponse::Created().json(environment)
+    } else {
+        HttpResponse::Ok().json(environment)
+    }
+}
+
+pub async fn delete_environment(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    environment_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut environments = state.environments.write().await;
+    match environments.get(environment_id.as_str()) {
+        Some(environment) if scope.allows(environment.tenant_id.as_deref()) => {}
+        _ => return environment_not_found(),
+    }
+    environments.remove(environment_id.as_str());
+    runtime_store::delete_json(&state.environment_dir, &environment_id);
+    HttpResponse::NoContent().finish()
+}
+
+/// Bindings may only point at PEAs the caller can see.
+async fn reject_unknown_peas(
+    state: &AppState,
+    scope: &TenantScope,
+    environment: &ExecutionEnvironment,
+) -> Option<HttpResponse> {
+    let configs = state.pea_configs.read().await;
+    let mut unknown: Vec<&str> = environment
+        .bindings
+        .values()
+        .filter(|pea_id| {
+            !configs
+                .get(pea_id.as_str())
+                .is_some_and(|config| scope.allows(config.tenant_id.as_deref()))
+        })
+        .map(String::as_str)
+        .collect();
+    unknown.sort();
+    unknown.dedup();
+    (!unknown.is_empty()).then(|| {
+        HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Bindings reference unknown PEAs",
+            "pea_ids": unknown,
+        }))
+    })
+}
+
+fn environment_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({"error": "Environment not found"}))
+}
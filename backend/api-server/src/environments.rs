@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use shared::mtp::{Recipe, RecipeStep};
+use std::collections::BTreeMap;
+
+use crate::state::AppState;
+
+/// Where a recipe runs, e.g. `sim` or `prod`: maps the PEA ids or logical
+/// roles named by recipe steps onto the PEAs of that environment.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExecutionEnvironment {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    /// Role or authored PEA id → PEA id in this environment.
+    #[serde(default)]
+    pub bindings: BTreeMap<String, String>,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl ExecutionEnvironment {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.id.is_empty()
+            || !self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "Environment id '{}' may only use letters, digits, - and _",
+                self.id
+            ));
+        }
+        if let Some((role, _)) = self
+            .bindings
+            .iter()
+            .find(|(role, pea_id)| role.trim().is_empty() || pea_id.trim().is_empty())
+        {
+            return Err(format!("Binding '{}' needs a role and a PEA id", role));
+        }
+        Ok(())
+    }
+
+    /// `steps` with every bound role replaced by its PEA; unbound ids are kept.
+    pub fn bind(&self, steps: Vec<RecipeStep>) -> Vec<RecipeStep> {
+        steps
+            .into_iter()
+            .map(|mut step| {
+                if let Some(pea_id) = self.bindings.get(&step.pea_id) {
+                    step.pea_id = pea_id.clone();
+                }
+                step
+            })
+            .collect()
+    }
+}
+
+/// The environment a run uses: the requested one, none when the request
+/// names an empty one, otherwise `EXECUTION_ENVIRONMENT`.
+pub fn select<'a>(state: &'a AppState, requested: Option<&'a str>) -> Option<&'a str> {
+    match requested {
+        Some("") => None,
+        Some(id) => Some(id),
+        None => state.default_environment.as_deref(),
+    }
+}
+
+/// Binds the steps of `recipe` to an environment of its tenant. Every bound
+/// step must then name an existing PEA.
+pub async fn bind_steps(
+    state: &AppState,
+    recipe: &Recipe,
+    steps: Vec<RecipeStep>,
+    environment_id: &str,
+) -> Result<Vec<RecipeStep>, String> {
+    let environment = state
+        .environments
+        .read()
+        .await
+        .get(environment_id)
+        .filter(|environment| environment.tenant_id == recipe.tenant_id)
+        .cloned()
+        .ok_or_else(|| format!("Environment '{}' not found", environment_id))?;
+    let steps = environment.bind(steps);
+    let configs = state.pea_configs.read().await;
+    if let Some(step) = steps
+        .iter()
+        .find(|step| !configs.contains_key(&step.pea_id))
+    {
+        return Err(format!(
+            "'{}' is neither a PEA nor bound in environment '{}'",
+            step.pea_id, environment_id
+        ));
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(pea_id: &str) -> RecipeStep {
+        serde_json::from_value(serde_json::json!({
+            "order": 1, "pea_id": pea_id, "service_tag": "Dose",
+            "command": "Start", "procedure_id": null, "parameters": [],
+            "wait_for_state": null, "timeout_ms": null
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn bound_roles_are_replaced_and_others_kept() {
+        let environment: ExecutionEnvironment = serde_json::from_value(serde_json::json!({
+            "id": "prod",
+            "bindings": {"dosing": "dosing-line-2", "sim-reactor": "reactor-1"}
+        }))
+        .unwrap();
+        assert!(environment.validate().is_ok());
+
+        let steps = environment.bind(vec![step("dosing"), step("sim-reactor"), step("mixer")]);
+        let ids: Vec<&str> = steps.iter().map(|s| s.pea_id.as_str()).collect();
+        assert_eq!(ids, vec!["dosing-line-2", "reactor-1", "mixer"]);
+
+        let invalid = ExecutionEnvironment {
+            id: "prod/2".to_string(),
+            ..environment.clone()
+        };
+        assert!(invalid.validate().is_err());
+        let mut unbound = environment;
+        unbound
+            .bindings
+            .insert("heater".to_string(), " ".to_string());
+        assert!(unbound.validate().is_err());
+    }
+}
@@ -0,0 +1,198 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::golden_runs::{self, GoldenRun, Tolerances};
+use crate::pea_handlers::{execution_visible, visible_recipe_ids};
+use crate::request_context::CallerContext;
+use crate::runtime_store;
+use crate::state::AppState;
+use crate::tenancy;
+
+/// POST /recipes/executions/{id}/golden — makes a completed execution the
+/// reference of its recipe, replacing the previous one
+pub async fn mark_golden_execution(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    execution_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let visible = visible_recipe_ids(&state, &scope).await;
+    let Some(execution) = state
+        .recipe_executions
+        .read()
+        .await
+        .get(execution_id.as_str())
+        .filter(|exec| execution_visible(exec, visible.as_ref()))
+        .cloned()
+    else {
+        return execution_not_found();
+    };
+    if execution["state"] != "completed" {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Only completed executions can be a golden run",
+        }));
+    }
+
+    let profile = golden_runs::profile(&execution, &*state.timeseries.read().await);
+    let golden = GoldenRun {
+        recipe_id: execution["recipe_id"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        execution_id: execution_id.into_inner(),
+        recipe_name: execution["recipe_name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        started_at: execution["started_at"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        marked_at: Utc::now().to_rfc3339(),
+        marked_by: CallerContext::from_request(&req).actor_id,
+        profile,
+    };
+    runtime_store::persist_json(&state.golden_run_dir, &golden.recipe_id, &golden);
+    state
+        .golden_runs
+        .write()
+        .await
+        .insert(golden.recipe_id.clone(), golden.clone());
+    HttpResponse::Ok().json(golden)
+}
+
+pub async fn get_golden_run(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    recipe_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let visible = visible_recipe_ids(&state, &scope).await;
+    if visible.is_some_and(|ids| !ids.contains(recipe_id.as_str())) {
+        return golden_run_not_found();
+    }
+    match state.golden_runs.read().await.get(recipe_id.as_str()) {
+        Some(golden) => HttpResponse::Ok().json(golden),
+        None => golden_run_not_found(),
+    }
+}
+
+pub async fn delete_golden_run(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    recipe_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let visible = visible_recipe_ids(&state, &scope).await;
+    if visible.is_some_and(|ids| !ids.contains(recipe_id.as_str())) {
+        return golden_run_not_found();
+    }
+    if state
+        .golden_runs
+        .write()
+        .await
+        .remove(recipe_id.as_str())
+        .is_none()
+    {
+        return golden_run_not_found();
+    }
+    runtime_store::delete_json(&state.golden_run_dir, &recipe_id);
+    HttpResponse::NoContent().finish()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompareQuery {
+    /// Allowed step duration deviation in percent (default 10).
+    pub duration_tolerance_pct: Option<f64>,
+    /// Allowed deviation of telemetry statistics in percent (default 5).
+    pub telemetry_tolerance_pct: Option<f64>,
+}
+
+/// GET /recipes/executions/{id}/compare/{golden_id} — step durations and
+/// telemetry statistics of an execution against the golden run of its recipe
+pub async fn compare_with_golden(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    query: web::Query<CompareQuery>,
+) -> impl Responder {
+    let (execution_id, golden_id) = path.into_inner();
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let visible = visible_recipe_ids(&state, &scope).await;
+    let Some(execution) = state
+        .recipe_executions
+        .read()
+        .await
+        .get(&execution_id)
+        .filter(|exec| execution_visible(exec, visible.as_ref()))
+        .cloned()
+    else {
+        return execution_not_found();
+    };
+    let Some(golden) = state
+        .golden_runs
+        .read()
+        .await
+        .values()
+        .find(|golden| golden.execution_id == golden_id)
+        .filter(|golden| {
+            visible
+                .as_ref()
+                .is_none_or(|ids| ids.contains(&golden.recipe_id))
+        })
+        .cloned()
+    else {
+        return golden_run_not_found();
+    };
+    if execution["recipe_id"] != golden.recipe_id.as_str() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "The golden run belongs to another recipe",
+        }));
+    }
+
+    let tolerances = Tolerances {
+        duration_pct: query
+            .duration_tolerance_pct
+            .unwrap_or(golden_runs::DEFAULT_DURATION_TOLERANCE_PCT),
+        telemetry_pct: query
+            .telemetry_tolerance_pct
+            .unwrap_or(golden_runs::DEFAULT_TELEMETRY_TOLERANCE_PCT),
+    };
+    if tolerances.duration_pct < 0.0 || tolerances.telemetry_pct < 0.0 {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": "Tolerances must not be negative"}));
+    }
+    let profile = golden_runs::profile(&execution, &*state.timeseries.read().await);
+    let comparison = golden_runs::compare(&profile, &golden.profile, tolerances);
+    HttpResponse::Ok().json(serde_json::json!({
+        "execution_id": execution_id,
+        "golden_id": golden_id,
+        "recipe_id": golden.recipe_id,
+        "duration_tolerance_pct": tolerances.duration_pct,
+        "telemetry_tolerance_pct": tolerances.telemetry_pct,
+        "deviations": comparison.deviations,
+        "steps": comparison.steps,
+        "telemetry": comparison.telemetry,
+    }))
+}
+
+fn execution_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({"error": "Execution not found"}))
+}
+
+fn golden_run_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({"error": "Golden run not found"}))
+}
@@ -0,0 +1,321 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::state::TimeSeriesStore;
+use crate::timeseries_handlers::extract_numeric_value;
+
+/// Default tolerances, in percent of the golden value.
+pub const DEFAULT_DURATION_TOLERANCE_PCT: f64 = 10.0;
+pub const DEFAULT_TELEMETRY_TOLERANCE_PCT: f64 = 5.0;
+
+/// Step durations and telemetry statistics of one execution.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RunProfile {
+    pub steps: Vec<StepProfile>,
+    /// Statistics per data key of the PEAs the run drove, over the run.
+    pub telemetry: BTreeMap<String, SeriesStats>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StepProfile {
+    pub pea_id: String,
+    pub service_tag: String,
+    pub duration_ms: Option<i64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SeriesStats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+/// The reference execution of a recipe, kept with its profile so it can be
+/// compared after the execution and its telemetry have aged out.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GoldenRun {
+    pub recipe_id: String,
+    pub execution_id: String,
+    pub recipe_name: String,
+    pub started_at: String,
+    pub marked_at: String,
+    #[serde(default)]
+    pub marked_by: Option<String>,
+    pub profile: RunProfile,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerances {
+    pub duration_pct: f64,
+    pub telemetry_pct: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StepDeviation {
+    /// 1-based step position.
+    pub step: usize,
+    pub pea_id: String,
+    pub service_tag: String,
+    pub duration_ms: Option<i64>,
+    pub golden_duration_ms: Option<i64>,
+    pub deviation_pct: Option<f64>,
+    pub flagged: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TelemetryDeviation {
+    pub key: String,
+    /// `mean`, `min` or `max`.
+    pub statistic: &'static str,
+    pub value: Option<f64>,
+    pub golden: Option<f64>,
+    pub deviation_pct: Option<f64>,
+    pub flagged: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Comparison {
+    pub steps: Vec<StepDeviation>,
+    pub telemetry: Vec<TelemetryDeviation>,
+    /// Number of flagged steps and statistics.
+    pub deviations: usize,
+}
+
+/// Profile of an execution record from its `step_timings`, with telemetry
+/// statistics of the data keys of its PEAs between start and end.
+pub fn profile(execution: &serde_json::Value, timeseries: &TimeSeriesStore) -> RunProfile {
+    let timings = execution["step_timings"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let at = |value: &serde_json::Value| {
+        value
+            .as_str()
+            .and_then(|text| DateTime::parse_from_rfc3339(text).ok())
+            .map(|time| time.with_timezone(&Utc))
+    };
+    let steps: Vec<StepProfile> = timings
+        .iter()
+        .map(|timing| StepProfile {
+            pea_id: timing["pea_id"].as_str().unwrap_or_default().to_string(),
+            service_tag: timing["service_tag"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            duration_ms: at(&timing["started_at"])
+                .zip(at(&timing["finished_at"]))
+                .map(|(start, end)| (end - start).num_milliseconds()),
+        })
+        .collect();
+
+    let start_ms = at(&execution["started_at"]).map(|t| t.timestamp_millis());
+    let end_ms = at(&execution["updated_at"]).map(|t| t.timestamp_millis());
+    let mut telemetry = BTreeMap::new();
+    if let (Some(start_ms), Some(end_ms)) = (start_ms, end_ms) {
+        let peas: BTreeSet<&str> = steps.iter().map(|step| step.pea_id.as_str()).collect();
+        for (key, points) in &timeseries.data {
+            if !peas
+                .iter()
+                .any(|pea| key.contains(&format!("/pea/{}/data/", pea)))
+            {
+                continue;
+            }
+            let values: Vec<f64> = points
+                .iter()
+                .filter(|point| point.timestamp_ms >= start_ms && point.timestamp_ms <= end_ms)
+                .filter_map(|point| extract_numeric_value(&point.value))
+                .collect();
+            if let Some(stats) = stats(&values) {
+                telemetry.insert(key.clone(), stats);
+            }
+        }
+    }
+    RunProfile { steps, telemetry }
+}
+
+fn stats(values: &[f64]) -> Option<SeriesStats> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(SeriesStats {
+        count: values.len(),
+        min: values.iter().copied().fold(f64::INFINITY, f64::min),
+        max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        mean: values.iter().sum::<f64>() / values.len() as f64,
+    })
+}
+
+/// Deviation of `value` from `golden` in percent of the golden value.
+fn deviation_pct(value: f64, golden: f64) -> f64 {
+    if golden == 0.0 {
+        if value == 0.0 {
+            0.0
+        } else {
+            f64::INFINITY
+        }
+    } else {
+        ((value - golden) / golden.abs()) * 100.0
+    }
+}
+
+/// Aligns steps by position and telemetry by key and statistic; a deviation
+/// beyond the tolerance, a step on another service or a missing counterpart
+/// is flagged.
+pub fn compare(run: &RunProfile, golden: &RunProfile, tolerances: Tolerances) -> Comparison {
+    let mut steps = Vec::new();
+    for index in 0..run.steps.len().max(golden.steps.len()) {
+        let current = run.steps.get(index);
+        let reference = golden.steps.get(index);
+        let (pea_id, service_tag) = current
+            .or(reference)
+            .map(|step| (step.pea_id.clone(), step.service_tag.clone()))
+            .unwrap_or_default();
+        let duration_ms = current.and_then(|step| step.duration_ms);
+        let golden_duration_ms = reference.and_then(|step| step.duration_ms);
+        let deviation = duration_ms
+            .zip(golden_duration_ms)
+            .map(|(value, golden)| deviation_pct(value as f64, golden as f64));
+        let note = match (current, reference) {
+            (Some(_), None) => Some("Step is not in the golden run".to_string()),
+            (None, Some(_)) => Some("Step did not run".to_string()),
+            (Some(a), Some(b)) if a.pea_id != b.pea_id || a.service_tag != b.service_tag => Some(
+                format!("Golden run used {}/{} here", b.pea_id, b.service_tag),
+            ),
+            _ => None,
+        };
+        steps.push(StepDeviation {
+            step: index + 1,
+            pea_id,
+            service_tag,
+            duration_ms,
+            golden_duration_ms,
+            deviation_pct: deviation,
+            flagged: note.is_some() || deviation.is_some_and(|d| d.abs() > tolerances.duration_pct),
+            note,
+        });
+    }
+
+    let mut telemetry = Vec::new();
+    let keys: BTreeSet<&String> = run
+        .telemetry
+        .keys()
+        .chain(golden.telemetry.keys())
+        .collect();
+    for key in keys {
+        let current = run.telemetry.get(key);
+        let reference = golden.telemetry.get(key);
+        for (statistic, pick) in [
+            (
+                "mean",
+                (|s: &SeriesStats| s.mean) as fn(&SeriesStats) -> f64,
+            ),
+            ("min", |s: &SeriesStats| s.min),
+            ("max", |s: &SeriesStats| s.max),
+        ] {
+            let value = current.map(pick);
+            let golden = reference.map(pick);
+            let deviation = value.zip(golden).map(|(v, g)| deviation_pct(v, g));
+            telemetry.push(TelemetryDeviation {
+                key: key.clone(),
+                statistic,
+                value,
+                golden,
+                deviation_pct: deviation,
+                flagged: deviation.is_none_or(|d| d.abs() > tolerances.telemetry_pct),
+            });
+        }
+    }
+
+    let deviations =
+        steps.iter().filter(|s| s.flagged).count() + telemetry.iter().filter(|t| t.flagged).count();
+    Comparison {
+        steps,
+        telemetry,
+        deviations,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::TimeSeriesPoint;
+
+    #[test]
+    fn profiles_compare_within_and_beyond_tolerances() {
+        let execution = |start: &str, end: &str, step_end: &str| {
+            serde_json::json!({
+                "started_at": start,
+                "updated_at": end,
+                "step_timings": [
+                    {"pea_id": "reactor", "service_tag": "Heat",
+                     "started_at": start, "finished_at": step_end},
+                    {"pea_id": "reactor", "service_tag": "Hold",
+                     "started_at": step_end, "finished_at": end}
+                ]
+            })
+        };
+        let key = "entmoot/habitat/nodes/n1/pea/reactor/data/TT01";
+        let mut timeseries = TimeSeriesStore::new(100);
+        timeseries.data.insert(
+            key.to_string(),
+            [(0, 60.0), (30_000, 64.0), (90_000, 80.0), (200_000, 10.0)]
+                .into_iter()
+                .map(|(offset, v)| TimeSeriesPoint {
+                    timestamp_ms: 1_767_225_600_000 + offset,
+                    value: serde_json::json!({"v": v}),
+                    producer: None,
+                })
+                .collect(),
+        );
+
+        let golden = profile(
+            &execution(
+                "2026-01-01T00:00:00Z",
+                "2026-01-01T00:01:00Z",
+                "2026-01-01T00:00:40Z",
+            ),
+            &timeseries,
+        );
+        assert_eq!(golden.steps[0].duration_ms, Some(40_000));
+        assert_eq!(
+            golden.telemetry[key],
+            SeriesStats {
+                count: 2,
+                min: 60.0,
+                max: 64.0,
+                mean: 62.0
+            }
+        );
+
+        let slower = profile(
+            &execution(
+                "2026-01-01T00:00:00Z",
+                "2026-01-01T00:02:00Z",
+                "2026-01-01T00:00:42Z",
+            ),
+            &timeseries,
+        );
+        let comparison = compare(
+            &slower,
+            &golden,
+            Tolerances {
+                duration_pct: DEFAULT_DURATION_TOLERANCE_PCT,
+                telemetry_pct: DEFAULT_TELEMETRY_TOLERANCE_PCT,
+            },
+        );
+        let flagged: Vec<bool> = comparison.steps.iter().map(|s| s.flagged).collect();
+        assert_eq!(flagged, vec![false, true]);
+        let max = comparison
+            .telemetry
+            .iter()
+            .find(|t| t.statistic == "max")
+            .unwrap();
+        assert_eq!(max.value, Some(80.0));
+        assert!(max.flagged);
+        assert_eq!(comparison.deviations, 3);
+    }
+}
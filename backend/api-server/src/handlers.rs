@@ -1,8 +1,12 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use chrono::Utc;
 use serde_json::json;
 
-use crate::state::AppState;
+use crate::alarm_grouping;
+use crate::message_catalog;
+use crate::state::{AlarmRecord, AppState};
+use crate::task_registry;
+use crate::tenancy;
 
 pub async fn get_metrics(state: web::Data<AppState>) -> impl Responder {
     let store = state.timeseries.read().await;
@@ -23,6 +27,25 @@ pub async fn get_metrics(state: web::Data<AppState>) -> impl Responder {
     }))
 }
 
+/// GET /admin/tasks — background tasks with their status, restart count and last activity.
+pub async fn get_tasks(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    if !tenancy::is_deployment_admin(&state, &req) {
+        return HttpResponse::Forbidden()
+            .json(json!({"error": "Task health requires the deployment admin token"}));
+    }
+    let tasks = state.tasks.snapshot();
+    let running = tasks
+        .iter()
+        .filter(|task| task.status == task_registry::STATUS_RUNNING)
+        .count();
+    HttpResponse::Ok().json(json!({
+        "running": running,
+        "tasks": tasks,
+        "leader": state.leadership.snapshot(),
+        "timestamp": Utc::now().to_rfc3339()
+    }))
+}
+
 pub async fn get_machines(state: web::Data<AppState>) -> impl Responder {
     let store = state.timeseries.read().await;
     let mut machines: Vec<serde_json::Value> = Vec::new();
@@ -70,20 +93,81 @@ pub async fn get_machine_by_id(
     }))
 }
 
-pub async fn get_alarms(state: web::Data<AppState>) -> impl Responder {
+pub async fn get_alarms(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let owned_peas = tenancy::owned_pea_ids(&state, &scope).await;
     let alarms = state.alarms.read().await;
-    let list: Vec<_> = alarms.values().cloned().collect();
+    let list: Vec<_> = alarms
+        .values()
+        .filter(|a| scope.allows_source(&a.source, &owned_peas))
+        .collect();
     let active = list
         .iter()
         .filter(|a| a.status == "open" || a.status == "acknowledged")
         .count();
+    let grouped = list.iter().filter(|a| a.parent_id.is_some()).count();
+    let localize = localizer(&state, &req).await;
     HttpResponse::Ok().json(json!({
-        "alarms": list,
-        "total": alarms.len(),
-        "active": active
+        "total": list.len(),
+        "alarms": list.iter().copied().map(&localize).collect::<Vec<_>>(),
+        "active": active,
+        "grouped": grouped
     }))
 }
 
+/// GET /alarms/groups — primary alarms with the active downstream alarms grouped under them
+pub async fn get_alarm_groups(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let owned_peas = tenancy::owned_pea_ids(&state, &scope).await;
+    let alarms = state.alarms.read().await;
+    let mut groups: Vec<_> = alarms
+        .values()
+        .filter(|a| a.parent_id.is_none() && scope.allows_source(&a.source, &owned_peas))
+        .filter_map(|primary| {
+            let mut children: Vec<_> = alarm_grouping::active_children(&alarms, &primary.id)
+                .filter(|a| scope.allows_source(&a.source, &owned_peas))
+                .collect();
+            if children.is_empty() {
+                return None;
+            }
+            children.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+            Some((primary, children))
+        })
+        .collect();
+    groups.sort_by(|(a, _), (b, _)| b.timestamp.cmp(&a.timestamp));
+    let localize = localizer(&state, &req).await;
+    let groups: Vec<_> = groups
+        .into_iter()
+        .map(|(primary, children)| {
+            json!({
+                "primary": localize(primary),
+                "child_count": children.len(),
+                "children": children.into_iter().map(&localize).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    HttpResponse::Ok().json(json!({ "groups": groups }))
+}
+
+/// Renders alarms with the catalog text for the caller's languages.
+async fn localizer(
+    state: &AppState,
+    req: &HttpRequest,
+) -> impl Fn(&AlarmRecord) -> serde_json::Value {
+    let catalog = state.message_catalog.read().await.clone();
+    let languages = state.message_languages.clone();
+    let requested = message_catalog::requested_languages(req);
+    move |alarm: &AlarmRecord| {
+        message_catalog::localize_alarm(alarm, &catalog, &requested, &languages)
+    }
+}
+
 pub async fn get_timeseries(
     _state: web::Data<AppState>,
     machine_id: web::Path<String>,
@@ -0,0 +1,300 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+
+use crate::state::BlackoutWindow;
+
+const PRODID: &str = "-//fendtastic//Alarm Blackouts//EN";
+/// UIDs of exported events end in this, so a re-import maps back to the same window.
+const UID_DOMAIN: &str = "@fendtastic";
+/// Carries the blackout scope (`global` or a source key fragment such as a PEA id).
+const SCOPE_PROPERTY: &str = "X-FENDTASTIC-SCOPE";
+const MAX_LINE_OCTETS: usize = 75;
+
+/// A blackout window read from an iCal `VEVENT`.
+#[derive(Debug, PartialEq)]
+pub struct ImportedWindow {
+    pub id: String,
+    pub name: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub scope: String,
+}
+
+/// Renders blackout windows as an RFC 5545 calendar, one event per window.
+pub fn export(windows: &[BlackoutWindow], now: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODID),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:fendtastic blackouts".to_string(),
+    ];
+    for window in windows {
+        let (Ok(starts_at), Ok(ends_at)) = (
+            DateTime::parse_from_rfc3339(&window.starts_at),
+            DateTime::parse_from_rfc3339(&window.ends_at),
+        ) else {
+            continue;
+        };
+        let category = if window.scope == "global" {
+            "BLACKOUT"
+        } else {
+            "MAINTENANCE"
+        };
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}{}", window.id, UID_DOMAIN),
+            format!("DTSTAMP:{}", format_utc(now)),
+            format!("DTSTART:{}", format_utc(starts_at.with_timezone(&Utc))),
+            format!("DTEND:{}", format_utc(ends_at.with_timezone(&Utc))),
+            format!("SUMMARY:{}", escape_text(&window.name)),
+            format!(
+                "DESCRIPTION:{}",
+                escape_text(&format!("Alarm suppression scope: {}", window.scope))
+            ),
+            format!("CATEGORIES:{}", category),
+            format!("{}:{}", SCOPE_PROPERTY, escape_text(&window.scope)),
+            "TRANSP:TRANSPARENT".to_string(),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut out = String::new();
+    for line in lines {
+        fold_line(&line, &mut out);
+    }
+    out
+}
+
+/// Reads the `VEVENT`s of a calendar as blackout windows. Events that cannot be
+/// used are reported by position in the error list.
+pub fn import(calendar: &str) -> (Vec<ImportedWindow>, Vec<String>) {
+    let mut windows = Vec::new();
+    let mut errors = Vec::new();
+    let mut event: Option<Vec<(String, String)>> = None;
+    let mut event_count = 0;
+
+    for line in unfold(calendar) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        match (name.to_ascii_uppercase().as_str(), value) {
+            ("BEGIN", "VEVENT") => event = Some(Vec::new()),
+            ("END", "VEVENT") => {
+                if let Some(properties) = event.take() {
+                    event_count += 1;
+                    match parse_event(&properties) {
+                        Ok(window) => windows.push(window),
+                        Err(e) => errors.push(format!("event {}: {}", event_count, e)),
+                    }
+                }
+            }
+            _ => {
+                if let Some(properties) = event.as_mut() {
+                    properties.push((name.to_string(), value.to_string()));
+                }
+            }
+        }
+    }
+    (windows, errors)
+}
+
+fn parse_event(properties: &[(String, String)]) -> Result<ImportedWindow, String> {
+    let get = |wanted: &str| {
+        properties.iter().find(|(name, _)| {
+            name.split(';')
+                .next()
+                .is_some_and(|n| n.eq_ignore_ascii_case(wanted))
+        })
+    };
+    let uid = get("UID")
+        .map(|(_, v)| v.trim().to_string())
+        .filter(|uid| !uid.is_empty())
+        .ok_or("missing UID")?;
+    let (start_name, start_value) = get("DTSTART").ok_or("missing DTSTART")?;
+    let starts_at = parse_date_time(start_name, start_value)?;
+    let ends_at = match get("DTEND") {
+        Some((name, value)) => parse_date_time(name, value)?,
+        None => match get("DURATION") {
+            Some((_, value)) => starts_at + parse_duration(value)?,
+            None if start_name.to_ascii_uppercase().contains("VALUE=DATE") => {
+                starts_at + Duration::days(1)
+            }
+            None => return Err(format!("{}: missing DTEND or DURATION", uid)),
+        },
+    };
+    if ends_at <= starts_at {
+        return Err(format!("{}: ends before it starts", uid));
+    }
+
+    let id = match uid.strip_suffix(UID_DOMAIN) {
+        Some(id) => id.to_string(),
+        None => format!("ical-{}", &crate::tenancy::hash_token(&uid)[..16]),
+    };
+    Ok(ImportedWindow {
+        id,
+        name: get("SUMMARY")
+            .map(|(_, v)| unescape_text(v))
+            .unwrap_or_else(|| uid.clone()),
+        starts_at,
+        ends_at,
+        scope: get(SCOPE_PROPERTY)
+            .map(|(_, v)| unescape_text(v))
+            .filter(|scope| !scope.is_empty())
+            .unwrap_or_else(|| "global".to_string()),
+    })
+}
+
+/// Accepts UTC (`...Z`), floating and all-day values. Floating and `TZID` times
+/// are taken as UTC.
+fn parse_date_time(name: &str, value: &str) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if name.to_ascii_uppercase().contains("VALUE=DATE") && !value.contains('T') {
+        return NaiveDate::parse_from_str(value, "%Y%m%d")
+            .map(|date| date.and_time(Default::default()).and_utc())
+            .map_err(|_| format!("invalid date '{}'", value));
+    }
+    NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S")
+        .map(|dt| dt.and_utc())
+        .map_err(|_| format!("invalid date-time '{}'", value))
+}
+
+/// Parses the day/time subset of RFC 5545 durations, e.g. `PT2H30M` or `P1D`.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration '{}'", value);
+    let rest = value.trim().strip_prefix('P').ok_or_else(invalid)?;
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in rest.chars() {
+        match c {
+            'T' => {}
+            '0'..='9' => number.push(c),
+            unit => {
+                let n: i64 = number.parse().map_err(|_| invalid())?;
+                number.clear();
+                total += match unit {
+                    'W' => Duration::weeks(n),
+                    'D' => Duration::days(n),
+                    'H' => Duration::hours(n),
+                    'M' => Duration::minutes(n),
+                    'S' => Duration::seconds(n),
+                    _ => return Err(invalid()),
+                };
+            }
+        }
+    }
+    if !number.is_empty() || total <= Duration::zero() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+fn format_utc(dt: DateTime<Utc>) -> String {
+    dt.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+/// Splits content lines longer than 75 octets, as RFC 5545 requires.
+fn fold_line(line: &str, out: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn unfold(calendar: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in calendar.split('\n') {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        match (raw.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(raw.to_string()),
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exported_windows_round_trip_through_import() {
+        let window = BlackoutWindow {
+            id: "b1".to_string(),
+            name: "Reactor R1 overhaul; phase 2, with a long name that needs folding".to_string(),
+            starts_at: "2026-03-01T06:00:00+00:00".to_string(),
+            ends_at: "2026-03-01T18:00:00+00:00".to_string(),
+            scope: "pea-r1".to_string(),
+            created_at: String::new(),
+        };
+        let calendar = export(std::slice::from_ref(&window), Utc::now());
+        assert!(calendar
+            .lines()
+            .all(|line| line.len() <= MAX_LINE_OCTETS + 1));
+        assert!(calendar.contains("CATEGORIES:MAINTENANCE"));
+
+        let (windows, errors) = import(&calendar);
+        assert!(errors.is_empty());
+        assert_eq!(
+            windows,
+            vec![ImportedWindow {
+                id: "b1".to_string(),
+                name: window.name.clone(),
+                starts_at: "2026-03-01T06:00:00Z".parse().unwrap(),
+                ends_at: "2026-03-01T18:00:00Z".parse().unwrap(),
+                scope: "pea-r1".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn foreign_events_get_stable_ids_and_durations() {
+        let calendar = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:shutdown-42@plant\r\n\
+            SUMMARY:Plant shutdown\r\nDTSTART;VALUE=DATE:20260704\r\nEND:VEVENT\r\n\
+            BEGIN:VEVENT\r\nUID:short@plant\r\nDTSTART:20260705T080000Z\r\nDURATION:PT1H30M\r\n\
+            END:VEVENT\r\nBEGIN:VEVENT\r\nUID:broken@plant\r\nDTSTART:yesterday\r\nEND:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+
+        let (windows, errors) = import(calendar);
+        assert_eq!(windows.len(), 2);
+        assert_eq!(errors.len(), 1);
+        assert!(windows[0].id.starts_with("ical-"));
+        assert_eq!(windows[0].id, import(calendar).0[0].id);
+        assert_eq!(windows[0].scope, "global");
+        assert_eq!(windows[0].ends_at - windows[0].starts_at, Duration::days(1));
+        assert_eq!(
+            windows[1].ends_at - windows[1].starts_at,
+            Duration::minutes(90)
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::ingest_processors::{ProcessorConfig, KINDS};
+use crate::request_context::CallerContext;
+use crate::runtime_store;
+use crate::state::AppState;
+use crate::tenancy;
+
+/// GET /ingest/processor-kinds — the kinds processors can be configured with
+pub async fn list_processor_kinds() -> impl Responder {
+    let kinds: Vec<serde_json::Value> = KINDS
+        .iter()
+        .map(|kind| {
+            serde_json::json!({
+                "name": kind.name,
+                "description": kind.description,
+                "params": (kind.params)(),
+            })
+        })
+        .collect();
+    HttpResponse::Ok().json(kinds)
+}
+
+/// GET /ingest/processors — configured processors in the order they run
+pub async fn list_processors(state: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(state.ingest_processors.list().await)
+}
+
+pub async fn get_processor(
+    state: web::Data<AppState>,
+    processor_id: web::Path<String>,
+) -> impl Responder {
+    match state.ingest_processors.get(&processor_id).await {
+        Some(config) => HttpResponse::Ok().json(config),
+        None => processor_not_found(),
+    }
+}
+
+/// POST /ingest/processors — transform samples on matching keys before storage
+pub async fn create_processor(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<ProcessorConfig>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    let now = Utc::now().to_rfc3339();
+    let mut config = body.into_inner();
+    config.id = uuid::Uuid::new_v4().to_string();
+    config.name = config.name.trim().to_string();
+    config.created_at = now.clone();
+    config.updated_at = now;
+    if let Err(e) = state.ingest_processors.upsert(config.clone()).await {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    runtime_store::persist_json(&state.ingest_processor_dir, &config.id, &config);
+    HttpResponse::Created().json(config)
+}
+
+/// PUT /ingest/processors/{id} — replace a processor; a deadband starts over
+pub async fn update_processor(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    processor_id: web::Path<String>,
+    body: web::Json<ProcessorConfig>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    let Some(existing) = state.ingest_processors.get(&processor_id).await else {
+        return processor_not_found();
+    };
+    let mut config = body.into_inner();
+    config.id = existing.id;
+    config.name = config.name.trim().to_string();
+    config.created_at = existing.created_at;
+    config.updated_at = Utc::now().to_rfc3339();
+    if let Err(e) = state.ingest_processors.upsert(config.clone()).await {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    runtime_store::persist_json(&state.ingest_processor_dir, &config.id, &config);
+    HttpResponse::Ok().json(config)
+}
+
+pub async fn delete_processor(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    processor_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    if !state.ingest_processors.remove(&processor_id).await {
+        return processor_not_found();
+    }
+    runtime_store::delete_json(&state.ingest_processor_dir, &processor_id);
+    HttpResponse::NoContent().finish()
+}
+
+#[derive(Deserialize)]
+pub struct PreviewRequest {
+    pub key: String,
+    pub value: serde_json::Value,
+}
+
+/// POST /ingest/processors/preview — what the processors would store for a sample
+pub async fn preview_processors(
+    state: web::Data<AppState>,
+    body: web::Json<PreviewRequest>,
+) -> impl Responder {
+    let body = body.into_inner();
+    let value = state.ingest_processors.preview(&body.key, body.value).await;
+    HttpResponse::Ok().json(serde_json::json!({
+        "key": body.key,
+        "dropped": value.is_none(),
+        "value": value,
+    }))
+}
+
+fn reject_unless_elevated(state: &AppState, req: &HttpRequest) -> Option<HttpResponse> {
+    let ctx = CallerContext::from_request(req);
+    if tenancy::is_deployment_admin(state, req) {
+        info!(
+            "Ingest processors changed by {}",
+            ctx.actor_id.as_deref().unwrap_or("unknown actor")
+        );
+        return None;
+    }
+    Some(HttpResponse::Forbidden().json(serde_json::json!({
+        "error": "Configuring ingest processors requires the deployment admin token"
+    })))
+}
+
+fn processor_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({"error": "Ingest processor not found"}))
+}
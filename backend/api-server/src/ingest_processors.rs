@@ -0,0 +1,490 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+use zenoh::key_expr::KeyExpr;
+
+use crate::ts_bands::key_expr_includes;
+
+/// A transform applied to each ingested sample between the Zenoh subscriber
+/// and storage, before schema checks, computed alarms, captures and the
+/// historian see it.
+pub trait IngestProcessor: Send + Sync {
+    /// The value to ingest instead of `value`, or `None` to drop the sample.
+    fn process(&self, key: &str, value: Value) -> Option<Value>;
+}
+
+/// A processor implementation that configs refer to by `name`.
+pub struct ProcessorKind {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Example of the kind's `params`.
+    pub params: fn() -> Value,
+    build: fn(&Value) -> Result<Box<dyn IngestProcessor>, String>,
+}
+
+/// The processor kinds available to configs. Site-specific transforms are
+/// added here as further kinds rather than in the ingest path.
+pub static KINDS: &[ProcessorKind] = &[
+    ProcessorKind {
+        name: "scale",
+        description:
+            "Multiplies a numeric value by `factor` and adds `offset`, e.g. to convert units",
+        params: || json!({"factor": 0.5556, "offset": -17.78, "field": "value"}),
+        build: Scale::build,
+    },
+    ProcessorKind {
+        name: "range_filter",
+        description: "Drops samples whose numeric value lies outside `min`..`max`",
+        params: || json!({"min": 0.0, "max": 100.0, "field": "value"}),
+        build: RangeFilter::build,
+    },
+    ProcessorKind {
+        name: "deadband",
+        description:
+            "Drops samples whose numeric value moved less than `delta` from the last one kept",
+        params: || json!({"delta": 0.1, "field": "value"}),
+        build: Deadband::build,
+    },
+    ProcessorKind {
+        name: "enrich",
+        description: "Adds the `fields` to object payloads, e.g. a site or an engineering unit",
+        params: || json!({"fields": {"site": "plant-1", "unit": "degC"}}),
+        build: Enrich::build,
+    },
+    ProcessorKind {
+        name: "drop",
+        description: "Drops every sample, keeping the keys out of storage",
+        params: || json!({}),
+        build: |_| Ok(Box::new(DropAll)),
+    },
+];
+
+/// A processor of a `kind` applied to the keys matching `key_pattern`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProcessorConfig {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub key_pattern: String,
+    pub kind: String,
+    #[serde(default)]
+    pub params: Value,
+    /// Processors run in ascending order, then by id.
+    #[serde(default)]
+    pub order: i32,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl ProcessorConfig {
+    pub fn compile(&self) -> Result<Box<dyn IngestProcessor>, String> {
+        if self.name.trim().is_empty() {
+            return Err("name is required".to_string());
+        }
+        if KeyExpr::try_from(self.key_pattern.as_str()).is_err() {
+            return Err(format!("Invalid key expression '{}'", self.key_pattern));
+        }
+        let kind = KINDS
+            .iter()
+            .find(|kind| kind.name == self.kind)
+            .ok_or_else(|| format!("Unknown processor kind '{}'", self.kind))?;
+        let params = match &self.params {
+            Value::Null => json!({}),
+            Value::Object(_) => self.params.clone(),
+            _ => return Err("params must be an object".to_string()),
+        };
+        (kind.build)(&params)
+    }
+}
+
+struct Active {
+    config: ProcessorConfig,
+    processor: Arc<dyn IngestProcessor>,
+}
+
+/// The configured processors, applied by the time-series collector.
+#[derive(Clone)]
+pub struct IngestProcessors {
+    active: Arc<RwLock<Vec<Active>>>,
+}
+
+impl IngestProcessors {
+    /// Loads stored configs; ones that no longer compile are skipped.
+    pub fn new(configs: HashMap<String, ProcessorConfig>) -> Self {
+        let mut active: Vec<Active> = configs
+            .into_values()
+            .filter_map(|config| match config.compile() {
+                Ok(processor) => Some(Active {
+                    config,
+                    processor: Arc::from(processor),
+                }),
+                Err(e) => {
+                    tracing::warn!("Skipping ingest processor {}: {}", config.id, e);
+                    None
+                }
+            })
+            .collect();
+        sort(&mut active);
+        Self {
+            active: Arc::new(RwLock::new(active)),
+        }
+    }
+
+    /// Configs in the order they run.
+    pub async fn list(&self) -> Vec<ProcessorConfig> {
+        let active = self.active.read().await;
+        active.iter().map(|active| active.config.clone()).collect()
+    }
+
+    pub async fn get(&self, id: &str) -> Option<ProcessorConfig> {
+        let active = self.active.read().await;
+        active
+            .iter()
+            .find(|active| active.config.id == id)
+            .map(|active| active.config.clone())
+    }
+
+    /// Adds or replaces the processor with the config's id.
+    pub async fn upsert(&self, config: ProcessorConfig) -> Result<(), String> {
+        let processor = Arc::from(config.compile()?);
+        let mut active = self.active.write().await;
+        active.retain(|active| active.config.id != config.id);
+        active.push(Active { config, processor });
+        sort(&mut active);
+        Ok(())
+    }
+
+    pub async fn remove(&self, id: &str) -> bool {
+        let mut active = self.active.write().await;
+        let before = active.len();
+        active.retain(|active| active.config.id != id);
+        active.len() != before
+    }
+
+    /// Runs the enabled processors matching `key` over `value`, in order;
+    /// `None` when one of them dropped the sample.
+    pub async fn apply(&self, key: &str, mut value: Value) -> Option<Value> {
+        let active = self.active.read().await;
+        for active in active.iter().filter(|active| active.config.enabled) {
+            if key_expr_includes(&active.config.key_pattern, key) {
+                value = active.processor.process(key, value)?;
+            }
+        }
+        Some(value)
+    }
+
+    /// Like `apply`, but with fresh processors, so previewing a sample does
+    /// not move the live deadbands; a deadband never drops a preview.
+    pub async fn preview(&self, key: &str, mut value: Value) -> Option<Value> {
+        let active = self.active.read().await;
+        for active in active.iter().filter(|active| active.config.enabled) {
+            if key_expr_includes(&active.config.key_pattern, key) {
+                let processor = active.config.compile().ok()?;
+                value = processor.process(key, value)?;
+            }
+        }
+        Some(value)
+    }
+}
+
+fn sort(active: &mut [Active]) {
+    active.sort_by(|a, b| (a.config.order, &a.config.id).cmp(&(b.config.order, &b.config.id)));
+}
+
+// ─── Built-in kinds ─────────────────────────────────────────────────────────
+
+/// The `field` param: a dot path into object payloads; the whole value without it.
+fn field_param(params: &Value) -> Result<Option<String>, String> {
+    match params.get("field") {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(path)) if !path.trim().is_empty() => Ok(Some(path.clone())),
+        Some(_) => Err("field must be a non-empty dot path".to_string()),
+    }
+}
+
+fn number_param(params: &Value, name: &str) -> Result<Option<f64>, String> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => value
+            .as_f64()
+            .filter(|n| n.is_finite())
+            .map(Some)
+            .ok_or_else(|| format!("{} must be a number", name)),
+    }
+}
+
+fn field_mut<'a>(value: &'a mut Value, field: Option<&str>) -> Option<&'a mut Value> {
+    let Some(field) = field else {
+        return Some(value);
+    };
+    field
+        .split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |current, segment| current.get_mut(segment))
+}
+
+fn number_at(value: &Value, field: Option<&str>) -> Option<f64> {
+    let mut value = value;
+    if let Some(field) = field {
+        for segment in field.split('.').filter(|segment| !segment.is_empty()) {
+            value = value.get(segment)?;
+        }
+    }
+    value.as_f64()
+}
+
+struct Scale {
+    factor: f64,
+    offset: f64,
+    field: Option<String>,
+}
+
+impl Scale {
+    fn build(params: &Value) -> Result<Box<dyn IngestProcessor>, String> {
+        Ok(Box::new(Self {
+            factor: number_param(params, "factor")?.unwrap_or(1.0),
+            offset: number_param(params, "offset")?.unwrap_or(0.0),
+            field: field_param(params)?,
+        }))
+    }
+}
+
+impl IngestProcessor for Scale {
+    fn process(&self, _key: &str, mut value: Value) -> Option<Value> {
+        if let Some(target) = field_mut(&mut value, self.field.as_deref()) {
+            if let Some(n) = target.as_f64() {
+                if let Some(scaled) = serde_json::Number::from_f64(n * self.factor + self.offset) {
+                    *target = Value::Number(scaled);
+                }
+            }
+        }
+        Some(value)
+    }
+}
+
+struct RangeFilter {
+    min: Option<f64>,
+    max: Option<f64>,
+    field: Option<String>,
+}
+
+impl RangeFilter {
+    fn build(params: &Value) -> Result<Box<dyn IngestProcessor>, String> {
+        let (min, max) = (number_param(params, "min")?, number_param(params, "max")?);
+        if min.is_none() && max.is_none() {
+            return Err("range_filter needs min or max".to_string());
+        }
+        if let (Some(min), Some(max)) = (min, max) {
+            if min > max {
+                return Err("min must not exceed max".to_string());
+            }
+        }
+        Ok(Box::new(Self {
+            min,
+            max,
+            field: field_param(params)?,
+        }))
+    }
+}
+
+impl IngestProcessor for RangeFilter {
+    fn process(&self, _key: &str, value: Value) -> Option<Value> {
+        // Samples without a number at the field pass; there is nothing to compare.
+        let Some(n) = number_at(&value, self.field.as_deref()) else {
+            return Some(value);
+        };
+        let below = self.min.is_some_and(|min| n < min);
+        let above = self.max.is_some_and(|max| n > max);
+        (!below && !above).then_some(value)
+    }
+}
+
+struct Deadband {
+    delta: f64,
+    field: Option<String>,
+    /// Last kept value per key.
+    last: Mutex<HashMap<String, f64>>,
+}
+
+impl Deadband {
+    fn build(params: &Value) -> Result<Box<dyn IngestProcessor>, String> {
+        let delta = number_param(params, "delta")?
+            .filter(|delta| *delta > 0.0)
+            .ok_or("deadband needs a positive delta")?;
+        Ok(Box::new(Self {
+            delta,
+            field: field_param(params)?,
+            last: Mutex::new(HashMap::new()),
+        }))
+    }
+}
+
+impl IngestProcessor for Deadband {
+    fn process(&self, key: &str, value: Value) -> Option<Value> {
+        let Some(n) = number_at(&value, self.field.as_deref()) else {
+            return Some(value);
+        };
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        if last
+            .get(key)
+            .is_some_and(|kept| (n - kept).abs() < self.delta)
+        {
+            return None;
+        }
+        last.insert(key.to_string(), n);
+        Some(value)
+    }
+}
+
+struct Enrich {
+    fields: serde_json::Map<String, Value>,
+}
+
+impl Enrich {
+    fn build(params: &Value) -> Result<Box<dyn IngestProcessor>, String> {
+        match params.get("fields") {
+            Some(Value::Object(fields)) if !fields.is_empty() => Ok(Box::new(Self {
+                fields: fields.clone(),
+            })),
+            _ => Err("enrich needs a non-empty fields object".to_string()),
+        }
+    }
+}
+
+impl IngestProcessor for Enrich {
+    fn process(&self, _key: &str, mut value: Value) -> Option<Value> {
+        if let Value::Object(object) = &mut value {
+            for (name, field) in &self.fields {
+                object.insert(name.clone(), field.clone());
+            }
+        }
+        Some(value)
+    }
+}
+
+struct DropAll;
+
+impl IngestProcessor for DropAll {
+    fn process(&self, _key: &str, _value: Value) -> Option<Value> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(
+        id: &str,
+        key_pattern: &str,
+        kind: &str,
+        params: Value,
+        order: i32,
+    ) -> ProcessorConfig {
+        ProcessorConfig {
+            id: id.to_string(),
+            name: id.to_string(),
+            key_pattern: key_pattern.to_string(),
+            kind: kind.to_string(),
+            params,
+            order,
+            enabled: true,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn processors_run_in_order_on_matching_keys() {
+        let processors = IngestProcessors::new(HashMap::new());
+        let tank = "entmoot/habitat/nodes/n1/tank/level";
+        processors
+            .upsert(config(
+                "to-percent",
+                "entmoot/**/tank/*",
+                "scale",
+                json!({"factor": 100.0, "field": "v"}),
+                0,
+            ))
+            .await
+            .unwrap();
+        processors
+            .upsert(config(
+                "plausible",
+                "entmoot/**/level",
+                "range_filter",
+                json!({"min": 0.0, "max": 100.0, "field": "v"}),
+                1,
+            ))
+            .await
+            .unwrap();
+        processors
+            .upsert(config(
+                "steady",
+                "entmoot/**/level",
+                "deadband",
+                json!({"delta": 1.0, "field": "v"}),
+                2,
+            ))
+            .await
+            .unwrap();
+        processors
+            .upsert(config(
+                "site",
+                "entmoot/**",
+                "enrich",
+                json!({"fields": {"site": "plant-1"}}),
+                3,
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            processors.apply(tank, json!({"v": 0.5})).await,
+            Some(json!({"v": 50.0, "site": "plant-1"}))
+        );
+        assert_eq!(
+            processors.apply(tank, json!({"v": 1.5})).await,
+            None,
+            "out of range"
+        );
+        assert_eq!(
+            processors.apply(tank, json!({"v": 0.505})).await,
+            None,
+            "in deadband"
+        );
+        assert_eq!(
+            processors.apply("pea/reactor/temp", json!(21.5)).await,
+            Some(json!(21.5))
+        );
+
+        let ids: Vec<String> = processors.list().await.into_iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec!["to-percent", "plausible", "steady", "site"]);
+        assert!(processors.remove("plausible").await);
+        assert_eq!(
+            processors.apply(tank, json!({"v": 1.5})).await,
+            Some(json!({"v": 150.0, "site": "plant-1"}))
+        );
+
+        assert!(config("x", "entmoot/**", "unknown", json!({}), 0)
+            .compile()
+            .is_err());
+        assert!(config("x", "entmoot/**", "deadband", json!({}), 0)
+            .compile()
+            .is_err());
+        for kind in KINDS {
+            let example = config("x", "entmoot/**", kind.name, (kind.params)(), 0);
+            assert!(example.compile().is_ok(), "{}", kind.name);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::runtime_store;
+use crate::state::AppState;
+use actix_web::{web, HttpResponse, Responder};
+use chrono::Utc;
+use shared::domain::authority::ActorClass;
+use shared::domain::interlock::{InterlockCondition, InterlockRule, InterlockTarget};
+use tracing::info;
+
+#[derive(serde::Deserialize)]
+pub struct InterlockRulePayload {
+    pub name: String,
+    pub description: Option<String>,
+    pub target: InterlockTarget,
+    pub condition: InterlockCondition,
+    pub override_actor_classes: Option<Vec<ActorClass>>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct ViolationQuery {
+    pub pea_id: Option<String>,
+    pub limit: Option<usize>,
+}
+
+pub async fn list_interlocks(state: web::Data<AppState>) -> impl Responder {
+    let interlocks = state.interlocks.read().await;
+    let list: Vec<&InterlockRule> = interlocks.values().collect();
+    HttpResponse::Ok().json(list)
+}
+
+pub async fn get_interlock(
+    state: web::Data<AppState>,
+    rule_id: web::Path<String>,
+) -> impl Responder {
+    let interlocks = state.interlocks.read().await;
+    match interlocks.get(rule_id.as_str()) {
+        Some(rule) => HttpResponse::Ok().json(rule),
+        None => HttpResponse::NotFound().json(serde_json::json!({"error": "Interlock not found"})),
+    }
+}
+
+pub async fn create_interlock(
+    state: web::Data<AppState>,
+    body: web::Json<InterlockRulePayload>,
+) -> impl Responder {
+    let payload = body.into_inner();
+    if let Err(err) = validate_payload(&payload) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": err}));
+    }
+
+    let now = Utc::now();
+    let rule = InterlockRule {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: payload.name,
+        description: payload.description.unwrap_or_default(),
+        target: payload.target,
+        condition: payload.condition,
+        override_actor_classes: payload.override_actor_classes.unwrap_or_default(),
+        enabled: payload.enabled.unwrap_or(true),
+        created_at: now,
+        updated_at: now,
+    };
+
+    runtime_store::persist_json(&state.interlock_dir, &rule.id, &rule);
+    state
+        .interlocks
+        .write()
+        .await
+        .insert(rule.id.clone(), rule.clone());
+
+    info!("Created interlock: {} ({})", rule.name, rule.id);
+    HttpResponse::Created().json(rule)
+}
+
+pub async fn update_interlock(
+    state: web::Data<AppState>,
+    rule_id: web::Path<String>,
+    body: web::Json<InterlockRulePayload>,
+) -> impl Responder {
+    let payload = body.into_inner();
+    if let Err(err) = validate_payload(&payload) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": err}));
+    }
+
+    let mut interlocks = state.interlocks.write().await;
+    let Some(rule) = interlocks.get_mut(rule_id.as_str()) else {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Interlock not found"}));
+    };
+
+    rule.name = payload.name;
+    rule.description = payload.description.unwrap_or_default();
+    rule.target = payload.target;
+    rule.condition = payload.condition;
+    rule.override_actor_classes = payload.override_actor_classes.unwrap_or_default();
+    rule.enabled = payload.enabled.unwrap_or(true);
+    rule.updated_at = Utc::now();
+
+    runtime_store::persist_json(&state.interlock_dir, &rule.id, &*rule);
+    HttpResponse::Ok().json(rule.clone())
+}
+
+pub async fn delete_interlock(
+    state: web::Data<AppState>,
+    rule_id: web::Path<String>,
+) -> impl Responder {
+    if state
+        .interlocks
+        .write()
+        .await
+        .remove(rule_id.as_str())
+        .is_none()
+    {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Interlock not found"}));
+    }
+    runtime_store::delete_json(&state.interlock_dir, rule_id.as_str());
+    HttpResponse::NoContent().finish()
+}
+
+/// GET /interlocks/violations — most recent violations first, optionally filtered by target PEA.
+pub async fn list_violations(
+    state: web::Data<AppState>,
+    query: web::Query<ViolationQuery>,
+) -> impl Responder {
+    let violations = state.interlock_violations.read().await;
+    let limit = query.limit.unwrap_or(100);
+    let list: Vec<_> = violations
+        .iter()
+        .rev()
+        .filter(|v| {
+            query
+                .pea_id
+                .as_deref()
+                .is_none_or(|pea_id| v.pea_id == pea_id)
+        })
+        .take(limit)
+        .cloned()
+        .collect();
+    HttpResponse::Ok().json(list)
+}
+
+fn validate_payload(payload: &InterlockRulePayload) -> Result<(), String> {
+    if payload.name.trim().is_empty() {
+        return Err("Interlock name is required".to_string());
+    }
+    if payload.target.pea_id.is_empty() || payload.condition.pea_id.is_empty() {
+        return Err("Interlock target and condition must reference a PEA".to_string());
+    }
+    if payload.target.commands.is_empty() {
+        return Err("Interlock must block at least one command".to_string());
+    }
+    if payload.condition.states.is_empty() {
+        return Err("Interlock condition must list at least one service state".to_string());
+    }
+    Ok(())
+}
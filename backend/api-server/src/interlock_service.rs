@@ -0,0 +1,293 @@
+use crate::request_context::CallerContext;
+use crate::state::TimeSeriesStore;
+use crate::state_analytics::service_states_from_status;
+use shared::domain::authority::ActorClass;
+use shared::domain::interlock::{InterlockOverride, InterlockRule, InterlockViolation};
+use shared::mtp::{ServiceCommand, ServiceState};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use zenoh::Session;
+
+pub const INTERLOCK_VIOLATIONS_TOPIC: &str = "entmoot/pol/interlocks/violations";
+
+/// Upper bound on violations kept in memory for GET /interlocks/violations.
+pub const MAX_VIOLATIONS: usize = 1000;
+
+/// A rule that tripped for a command, together with the state that blocked it.
+#[derive(Debug, Clone)]
+pub struct InterlockHit {
+    pub rule: InterlockRule,
+    pub blocking_service_tag: String,
+    pub blocking_state: ServiceState,
+}
+
+/// Evaluates all enabled rules targeting `pea_id`/`service_tag`/`command`
+/// against the latest known status of each referenced PEA.
+pub fn evaluate(
+    rules: &[InterlockRule],
+    pea_id: &str,
+    service_tag: &str,
+    command: ServiceCommand,
+    statuses: &HashMap<String, serde_json::Value>,
+) -> Vec<InterlockHit> {
+    let mut hits = Vec::new();
+
+    for rule in rules.iter().filter(|rule| rule.enabled) {
+        let target = &rule.target;
+        if target.pea_id != pea_id
+            || target
+                .service_tag
+                .as_deref()
+                .is_some_and(|tag| tag != service_tag)
+            || !target.commands.contains(&command)
+        {
+            continue;
+        }
+
+        let Some(status) = statuses.get(&rule.condition.pea_id) else {
+            continue;
+        };
+
+        let blocking = service_states_from_status(status)
+            .into_iter()
+            .find(|(tag, state)| {
+                rule.condition
+                    .service_tag
+                    .as_deref()
+                    .is_none_or(|wanted| wanted == tag)
+                    && rule.condition.states.contains(state)
+            });
+
+        if let Some((blocking_service_tag, blocking_state)) = blocking {
+            hits.push(InterlockHit {
+                rule: rule.clone(),
+                blocking_service_tag,
+                blocking_state,
+            });
+        }
+    }
+
+    hits
+}
+
+pub fn override_permitted(hits: &[InterlockHit], actor_class: &ActorClass) -> bool {
+    hits.iter()
+        .all(|hit| hit.rule.override_actor_classes.contains(actor_class))
+}
+
+/// The override an authenticated caller asks for. Who overrides is taken from
+/// the caller, never from the request body; `None` without an actor class.
+pub fn override_for(caller: &CallerContext, reason: Option<String>) -> Option<InterlockOverride> {
+    Some(InterlockOverride {
+        actor_id: caller.actor_id.clone()?,
+        actor_class: caller.actor_class.clone()?,
+        reason,
+    })
+}
+
+/// Collects the latest status payload for every PEA referenced as a condition
+/// by `rules`, read from the time-series buffer fed by the status topics.
+pub fn latest_statuses(
+    rules: &[InterlockRule],
+    timeseries: &TimeSeriesStore,
+) -> HashMap<String, serde_json::Value> {
+    let mut statuses = HashMap::new();
+    for rule in rules {
+        let pea_id = &rule.condition.pea_id;
+        if statuses.contains_key(pea_id) {
+            continue;
+        }
+        let key = shared::mtp::topics::pea_status(pea_id);
+        if let Some(last) = timeseries.data.get(&key).and_then(|buf| buf.back()) {
+            statuses.insert(pea_id.clone(), last.value.clone());
+        }
+    }
+    statuses
+}
+
+pub fn violation_records(
+    hits: &[InterlockHit],
+    pea_id: &str,
+    service_tag: &str,
+    command: ServiceCommand,
+    source: &str,
+    override_request: Option<&InterlockOverride>,
+) -> Vec<InterlockViolation> {
+    let occurred_at = chrono::Utc::now();
+    hits.iter()
+        .map(|hit| InterlockViolation {
+            id: uuid::Uuid::new_v4().to_string(),
+            rule_id: hit.rule.id.clone(),
+            rule_name: hit.rule.name.clone(),
+            pea_id: pea_id.to_string(),
+            service_tag: service_tag.to_string(),
+            command,
+            source: source.to_string(),
+            blocking_pea_id: hit.rule.condition.pea_id.clone(),
+            blocking_service_tag: hit.blocking_service_tag.clone(),
+            blocking_state: hit.blocking_state,
+            overridden: override_request.is_some(),
+            override_actor_id: override_request.map(|o| o.actor_id.clone()),
+            override_actor_class: override_request.map(|o| o.actor_class.clone()),
+            override_reason: override_request.and_then(|o| o.reason.clone()),
+            occurred_at,
+        })
+        .collect()
+}
+
+/// Evaluates the configured interlocks for a command about to be published.
+pub async fn check_command(
+    interlocks: &RwLock<HashMap<String, InterlockRule>>,
+    timeseries: &RwLock<TimeSeriesStore>,
+    pea_id: &str,
+    service_tag: &str,
+    command: ServiceCommand,
+) -> Vec<InterlockHit> {
+    let rules: Vec<InterlockRule> = interlocks.read().await.values().cloned().collect();
+    if rules.is_empty() {
+        return Vec::new();
+    }
+    let statuses = latest_statuses(&rules, &*timeseries.read().await);
+    evaluate(&rules, pea_id, service_tag, command, &statuses)
+}
+
+/// Stores violations for reporting and announces them on the bus.
+pub async fn record_violations(
+    store: &RwLock<Vec<InterlockViolation>>,
+    zenoh: &Session,
+    records: Vec<InterlockViolation>,
+) {
+    for record in &records {
+        let _ = zenoh
+            .put(
+                INTERLOCK_VIOLATIONS_TOPIC,
+                serde_json::to_string(record).unwrap_or_else(|_| "{}".to_string()),
+            )
+            .await;
+    }
+
+    let mut violations = store.write().await;
+    violations.extend(records);
+    if violations.len() > MAX_VIOLATIONS {
+        let excess = violations.len() - MAX_VIOLATIONS;
+        violations.drain(..excess);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::domain::interlock::{InterlockCondition, InterlockTarget};
+
+    fn rule(override_classes: Vec<ActorClass>) -> InterlockRule {
+        InterlockRule {
+            id: "rule-1".to_string(),
+            name: "B may not start while A executes".to_string(),
+            description: String::new(),
+            target: InterlockTarget {
+                pea_id: "pea-b".to_string(),
+                service_tag: None,
+                commands: vec![ServiceCommand::Start],
+            },
+            condition: InterlockCondition {
+                pea_id: "pea-a".to_string(),
+                service_tag: Some("svc.main".to_string()),
+                states: vec![ServiceState::Execute],
+            },
+            override_actor_classes: override_classes,
+            enabled: true,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    fn status_with(state: &str, code: u32) -> HashMap<String, serde_json::Value> {
+        let mut statuses = HashMap::new();
+        statuses.insert(
+            "pea-a".to_string(),
+            serde_json::json!({
+                "pea_id": "pea-a",
+                "services": [{ "tag": "svc.main", "state": state, "state_code": code }],
+            }),
+        );
+        statuses
+    }
+
+    #[test]
+    fn blocks_command_while_condition_state_is_active() {
+        let hits = evaluate(
+            &[rule(vec![])],
+            "pea-b",
+            "svc.any",
+            ServiceCommand::Start,
+            &status_with("Execute", 64),
+        );
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].blocking_state, ServiceState::Execute);
+    }
+
+    #[test]
+    fn ignores_other_commands_and_inactive_states() {
+        let rules = [rule(vec![])];
+        assert!(evaluate(
+            &rules,
+            "pea-b",
+            "svc.any",
+            ServiceCommand::Stop,
+            &status_with("Execute", 64)
+        )
+        .is_empty());
+        assert!(evaluate(
+            &rules,
+            "pea-b",
+            "svc.any",
+            ServiceCommand::Start,
+            &status_with("Idle", 16)
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn falls_back_to_state_code() {
+        let hits = evaluate(
+            &[rule(vec![])],
+            "pea-b",
+            "svc.any",
+            ServiceCommand::Start,
+            &status_with("", 64),
+        );
+        assert_eq!(hits.len(), 1);
+    }
+
+    #[test]
+    fn override_requires_permitted_class_on_every_rule() {
+        let hits = evaluate(
+            &[rule(vec![ActorClass::Maintenance])],
+            "pea-b",
+            "svc.any",
+            ServiceCommand::Start,
+            &status_with("Execute", 64),
+        );
+        assert!(override_permitted(&hits, &ActorClass::Maintenance));
+        assert!(!override_permitted(&hits, &ActorClass::Operator));
+    }
+
+    #[test]
+    fn overrides_take_the_callers_identity() {
+        let caller = CallerContext {
+            actor_id: Some("maint-token".to_string()),
+            actor_class: Some(ActorClass::Maintenance),
+            roles: Vec::new(),
+        };
+        let o = override_for(&caller, Some("flush line".to_string())).unwrap();
+        assert_eq!(o.actor_id, "maint-token");
+        assert_eq!(o.actor_class, ActorClass::Maintenance);
+        assert_eq!(o.reason.as_deref(), Some("flush line"));
+
+        let classless = CallerContext {
+            actor_class: None,
+            ..caller
+        };
+        assert!(override_for(&classless, None).is_none());
+    }
+}
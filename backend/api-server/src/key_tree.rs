@@ -0,0 +1,135 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// One child of a level in the key hierarchy.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct KeyTreeNode {
+    /// The chunk of the key below the parent, e.g. `pea` for `entmoot/pea`.
+    pub segment: String,
+    pub key_expr: String,
+    /// Whether a value is stored at exactly this key.
+    pub has_value: bool,
+    /// Number of distinct chunks directly below this node.
+    pub child_count: usize,
+    /// Number of keys with a value at or below this node.
+    pub key_count: usize,
+}
+
+#[derive(Default)]
+struct Children {
+    has_value: bool,
+    next: BTreeSet<String>,
+    key_count: usize,
+}
+
+/// Groups `keys` into the level directly below `prefix` (empty for the root),
+/// sorted by segment. Keys outside `prefix` are ignored.
+pub fn level(prefix: &str, keys: impl IntoIterator<Item = impl AsRef<str>>) -> Vec<KeyTreeNode> {
+    let prefix = prefix.trim_matches('/');
+    let mut children: BTreeMap<String, Children> = BTreeMap::new();
+
+    for key in keys {
+        let key = key.as_ref();
+        let rest = if prefix.is_empty() {
+            key
+        } else {
+            match key
+                .strip_prefix(prefix)
+                .and_then(|rest| rest.strip_prefix('/'))
+            {
+                Some(rest) => rest,
+                None => continue,
+            }
+        };
+        let mut chunks = rest.splitn(3, '/');
+        let Some(segment) = chunks.next().filter(|s| !s.is_empty()) else {
+            continue;
+        };
+        let child = children.entry(segment.to_string()).or_default();
+        child.key_count += 1;
+        match chunks.next() {
+            Some(next) => {
+                child.next.insert(next.to_string());
+            }
+            None => child.has_value = true,
+        }
+    }
+
+    children
+        .into_iter()
+        .map(|(segment, child)| KeyTreeNode {
+            key_expr: if prefix.is_empty() {
+                segment.clone()
+            } else {
+                format!("{}/{}", prefix, segment)
+            },
+            segment,
+            has_value: child.has_value,
+            child_count: child.next.len(),
+            key_count: child.key_count,
+        })
+        .collect()
+}
+
+/// Whether `key` is `prefix` itself or lies below it.
+pub fn is_under(prefix: &str, key: &str) -> bool {
+    let prefix = prefix.trim_matches('/');
+    prefix.is_empty()
+        || key
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_under_matches_whole_chunks_only() {
+        assert!(is_under("entmoot/sim", "entmoot/sim"));
+        assert!(is_under("entmoot/sim/", "entmoot/sim/pea-1/status"));
+        assert!(!is_under("entmoot/sim", "entmoot/simulator/pea-1"));
+        assert!(!is_under("entmoot/sim", "entmoot"));
+    }
+
+    #[test]
+    fn groups_keys_one_level_below_the_prefix() {
+        let keys = [
+            "entmoot/pea/p1/status",
+            "entmoot/pea/p1/services/dose",
+            "entmoot/pea/p2/status",
+            "entmoot/alarms",
+            "entmoot/alarms/a1",
+            "other/key",
+        ];
+
+        let nodes = level("entmoot", keys);
+        assert_eq!(
+            nodes,
+            vec![
+                KeyTreeNode {
+                    segment: "alarms".to_string(),
+                    key_expr: "entmoot/alarms".to_string(),
+                    has_value: true,
+                    child_count: 1,
+                    key_count: 2,
+                },
+                KeyTreeNode {
+                    segment: "pea".to_string(),
+                    key_expr: "entmoot/pea".to_string(),
+                    has_value: false,
+                    child_count: 2,
+                    key_count: 3,
+                },
+            ]
+        );
+
+        let roots = level("", keys);
+        assert_eq!(roots.len(), 2);
+        assert_eq!(roots[0].key_expr, "entmoot");
+        assert_eq!(roots[0].key_count, 5);
+    }
+}
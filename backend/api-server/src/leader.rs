@@ -0,0 +1,154 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use tracing::{error, info};
+use zenoh::sample::SampleKind;
+use zenoh::Session;
+
+use crate::task_registry::TaskHandle;
+
+/// Liveliness tokens held by running api-server instances taking part in the election.
+pub const INSTANCES_WILDCARD: &str = "entmoot/api/instances/*";
+
+pub fn instance_key(instance_id: &str) -> String {
+    format!("entmoot/api/instances/{}", instance_id)
+}
+
+/// Whether `instance_id` leads among the `alive` instances: the lowest id wins.
+pub fn elect(instance_id: &str, alive: &BTreeSet<String>) -> bool {
+    alive
+        .iter()
+        .next()
+        .is_none_or(|lowest| lowest.as_str() >= instance_id)
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LeaderSnapshot {
+    pub enabled: bool,
+    pub instance_id: String,
+    pub is_leader: bool,
+    pub instances: Vec<String>,
+}
+
+/// Decides which api-server replica runs the singleton duties: raw alarm
+/// ingestion, desired-state reconciliation and time-series archival.
+///
+/// Every instance holds a Zenoh liveliness token under `entmoot/api/instances`;
+/// the one with the lowest instance id leads. A replica that stops or drops off
+/// the bus loses its token, and the next one takes over.
+#[derive(Clone)]
+pub struct Leadership {
+    enabled: bool,
+    instance_id: String,
+    leader: Arc<AtomicBool>,
+    alive: Arc<RwLock<BTreeSet<String>>>,
+}
+
+impl Leadership {
+    /// Enabled with `LEADER_ELECTION=true`; otherwise this instance always leads.
+    pub fn from_env(instance_id: &str) -> Self {
+        let enabled = std::env::var("LEADER_ELECTION").is_ok_and(|v| v == "true");
+        Self {
+            enabled,
+            instance_id: instance_id.to_string(),
+            // Followers until the election has seen the other instances.
+            leader: Arc::new(AtomicBool::new(!enabled)),
+            alive: Arc::new(RwLock::new(BTreeSet::new())),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.leader.load(Ordering::Relaxed)
+    }
+
+    pub fn snapshot(&self) -> LeaderSnapshot {
+        LeaderSnapshot {
+            enabled: self.enabled,
+            instance_id: self.instance_id.clone(),
+            is_leader: self.is_leader(),
+            instances: self
+                .alive
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .iter()
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn update(&self, key: &str, kind: SampleKind) {
+        let Some(id) = key.rsplit('/').next() else {
+            return;
+        };
+        let mut alive = self.alive.write().unwrap_or_else(|e| e.into_inner());
+        match kind {
+            SampleKind::Put => alive.insert(id.to_string()),
+            SampleKind::Delete => alive.remove(id),
+        };
+        let leader = elect(&self.instance_id, &alive);
+        if self.leader.swap(leader, Ordering::Relaxed) != leader {
+            if leader {
+                info!("Instance {} is now the leader", self.instance_id);
+            } else {
+                info!("Instance {} is now a follower", self.instance_id);
+            }
+        }
+    }
+
+    /// Declares this instance's token and follows the others' for as long as
+    /// the process runs.
+    pub async fn campaign(self, session: Arc<Session>, task: TaskHandle) {
+        let _token = match session
+            .liveliness()
+            .declare_token(instance_key(&self.instance_id))
+            .await
+        {
+            Ok(token) => token,
+            Err(e) => {
+                task.fail(format!("Failed to declare instance liveliness: {}", e));
+                return;
+            }
+        };
+        let subscriber = match session
+            .liveliness()
+            .declare_subscriber(INSTANCES_WILDCARD)
+            .history(true)
+            .await
+        {
+            Ok(subscriber) => subscriber,
+            Err(e) => {
+                task.fail(format!("Failed to follow instance liveliness: {}", e));
+                return;
+            }
+        };
+        info!("Leader election started as instance {}", self.instance_id);
+        while let Ok(sample) = subscriber.recv_async().await {
+            task.beat();
+            self.update(sample.key_expr().as_str(), sample.kind());
+        }
+        error!("Instance liveliness subscription closed");
+        self.leader.store(false, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lowest_live_instance_leads() {
+        let alive: BTreeSet<String> = ["api-b", "api-c"].iter().map(|id| id.to_string()).collect();
+
+        assert!(elect("api-b", &alive));
+        assert!(!elect("api-c", &alive));
+        // An instance that has not seen its own token yet only leads below the rest.
+        assert!(elect("api-a", &alive));
+        assert!(!elect("api-d", &alive));
+        assert!(elect("api-c", &BTreeSet::new()));
+    }
+}
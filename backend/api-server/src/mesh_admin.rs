@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+
+/// Config subtrees Zenoh applies at runtime when written through the admin space,
+/// relative to `@/<zid>/<router|peer>/config/`.
+pub const WRITABLE_CONFIG_PREFIXES: &[&str] = &["plugins/"];
+
+/// A write to the Zenoh admin space, with what it replaced so it can be undone.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MeshConfigChange {
+    pub id: String,
+    pub admin_key: String,
+    /// `None` when the key did not exist before the write.
+    pub previous_value: Option<serde_json::Value>,
+    /// `None` when the write deleted the key.
+    pub new_value: Option<serde_json::Value>,
+    pub actor: Option<String>,
+    /// Set on changes made by `POST /mesh/config/rollback/{id}`.
+    pub rollback_of: Option<String>,
+    /// Id of the change that undid this one.
+    pub rolled_back_by: Option<String>,
+    pub created_at: String,
+}
+
+/// Accepts only concrete keys of the form `@/<zid>/<router|peer>/config/<path>`
+/// below one of [`WRITABLE_CONFIG_PREFIXES`].
+pub fn validate_admin_key(key: &str) -> Result<(), String> {
+    let Some(rest) = key.strip_prefix("@/") else {
+        return Err("admin_key must start with @/".to_string());
+    };
+    if key.contains('*') || key.contains('$') || key.ends_with('/') || key.contains("//") {
+        return Err("admin_key must be a concrete key without wildcards".to_string());
+    }
+    let mut parts = rest.splitn(4, '/');
+    let zid = parts.next().unwrap_or_default();
+    let kind = parts.next().unwrap_or_default();
+    let section = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+
+    if zid.is_empty() || !zid.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("admin_key must name a Zenoh id, e.g. @/<zid>/router/config/...".to_string());
+    }
+    if !matches!(kind, "router" | "peer") || section != "config" {
+        return Err(
+            "Only @/<zid>/router/config/... and @/<zid>/peer/config/... are writable".to_string(),
+        );
+    }
+    if !WRITABLE_CONFIG_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix) && path.len() > prefix.len())
+    {
+        return Err(format!(
+            "Config path '{}' is not writable at runtime; allowed: {}",
+            path,
+            WRITABLE_CONFIG_PREFIXES.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// A subscription declared on the mesh, as reported by a node's admin space under
+/// `@/<zid>/<router|peer>/subscriber/<key_expr>`.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct MeshSubscriber {
+    pub key_expr: String,
+    /// Node whose admin space reported the subscription.
+    pub reported_by: String,
+    pub whatami: String,
+    /// Admin-space payload, e.g. the sessions the subscription originates from.
+    pub info: serde_json::Value,
+}
+
+/// Parses an admin-space subscriber entry; other keys yield `None`.
+pub fn parse_subscriber(admin_key: &str, info: serde_json::Value) -> Option<MeshSubscriber> {
+    let rest = admin_key.strip_prefix("@/")?;
+    let mut parts = rest.splitn(4, '/');
+    let zid = parts.next()?;
+    let whatami = parts.next()?;
+    if parts.next()? != "subscriber" {
+        return None;
+    }
+    let key_expr = parts.next().filter(|ke| !ke.is_empty())?;
+    Some(MeshSubscriber {
+        key_expr: key_expr.to_string(),
+        reported_by: zid.to_string(),
+        whatami: whatami.to_string(),
+        info,
+    })
+}
+
+/// Whether a subscription on `subscribed` would receive publications on `key_expr`.
+pub fn subscription_covers(subscribed: &str, key_expr: &str) -> bool {
+    match (
+        zenoh::key_expr::KeyExpr::try_from(subscribed),
+        zenoh::key_expr::KeyExpr::try_from(key_expr),
+    ) {
+        (Ok(subscribed), Ok(key_expr)) => subscribed.intersects(&key_expr),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_concrete_plugin_config_keys_are_writable() {
+        assert!(
+            validate_admin_key("@/a1b2c3/router/config/plugins/storage_manager/storages/demo")
+                .is_ok()
+        );
+        assert!(validate_admin_key("@/a1b2c3/peer/config/plugins/rest/http_port").is_ok());
+
+        assert!(validate_admin_key("a1b2c3/router/config/plugins/rest").is_err());
+        assert!(validate_admin_key("@/*/router/config/plugins/rest").is_err());
+        assert!(validate_admin_key("@/a1b2c3/router/config/plugins/**").is_err());
+        assert!(validate_admin_key("@/a1b2c3/router/config/listen/endpoints").is_err());
+        assert!(validate_admin_key("@/a1b2c3/router/config/plugins/").is_err());
+        assert!(validate_admin_key("@/a1b2c3/session/config/plugins/rest").is_err());
+        assert!(validate_admin_key("@/not-a-zid/router/config/plugins/rest").is_err());
+    }
+
+    #[test]
+    fn subscriber_entries_are_parsed_and_matched() {
+        let sub = parse_subscriber(
+            "@/a1b2/router/subscriber/entmoot/pea/*/commands/**",
+            serde_json::json!({"sources": ["c3d4"]}),
+        )
+        .unwrap();
+        assert_eq!(sub.key_expr, "entmoot/pea/*/commands/**");
+        assert_eq!(sub.reported_by, "a1b2");
+        assert_eq!(sub.whatami, "router");
+        assert!(parse_subscriber(
+            "@/a1b2/router/queryable/entmoot/**",
+            serde_json::Value::Null
+        )
+        .is_none());
+
+        assert!(subscription_covers(
+            &sub.key_expr,
+            "entmoot/pea/p1/commands/start"
+        ));
+        assert!(!subscription_covers(&sub.key_expr, "entmoot/pea/p1/status"));
+        assert!(!subscription_covers(&sub.key_expr, "bad//key"));
+    }
+}
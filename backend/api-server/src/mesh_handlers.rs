@@ -1,5 +1,12 @@
+use crate::db;
+use crate::key_tree;
+use crate::mesh_admin::{self, MeshConfigChange};
+use crate::redaction_handlers;
+use crate::request_context::CallerContext;
+use crate::runtime_store;
 use crate::state::AppState;
-use actix_web::{web, HttpResponse, Responder};
+use crate::tenancy::{self, TenantScope};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use serde::Deserialize;
 use tracing::{error, info};
 
@@ -10,12 +17,41 @@ pub struct KeysQuery {
     pub prefix: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct DeleteKeysQuery {
+    /// Keys at and below this prefix are deleted; wildcards are not allowed.
+    pub prefix: String,
+    /// List what would be deleted without deleting it.
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct KeyTreeQuery {
+    /// Key the level is listed below; the root when omitted.
+    pub prefix: Option<String>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct SubscribersQuery {
+    /// Only list subscriptions that would receive publications on this key.
+    pub key_expr: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct ConfigUpdateBody {
     pub admin_key: String,
+    /// `null` deletes the key.
     pub value: serde_json::Value,
 }
 
+#[derive(Deserialize)]
+pub struct ConfigChangesQuery {
+    pub admin_key: Option<String>,
+    pub limit: Option<usize>,
+}
+
 // ─── Helper: query Zenoh and collect results ─────────────────────────────────
 
 async fn query_zenoh(
@@ -63,6 +99,35 @@ async fn query_zenoh(
     Ok(results)
 }
 
+/// Keeps the `query_zenoh` results whose keys are visible in `scope`.
+fn scope_entries(scope: &TenantScope, entries: Vec<serde_json::Value>) -> Vec<serde_json::Value> {
+    entries
+        .into_iter()
+        .filter(|entry| scope.allows_key(entry["key"].as_str().unwrap_or_default()))
+        .collect()
+}
+
+/// Drops hidden keys and strips sensitive fields from `query_zenoh` results.
+fn redact_entries(
+    policy: &crate::redaction::RedactionPolicy,
+    entries: Vec<serde_json::Value>,
+) -> Vec<serde_json::Value> {
+    if policy.is_empty() {
+        return entries;
+    }
+    entries
+        .into_iter()
+        .filter_map(|mut entry| {
+            let Some(key) = entry["key"].as_str().map(str::to_string) else {
+                return Some(entry);
+            };
+            let value = policy.apply(&key, entry["value"].take())?;
+            entry["value"] = value;
+            Some(entry)
+        })
+        .collect()
+}
+
 // ─── GET /mesh/nodes ─────────────────────────────────────────────────────────
 
 /// Returns connected Zenoh sessions (nodes) from the admin space.
@@ -180,27 +245,86 @@ pub async fn get_links(state: web::Data<AppState>) -> impl Responder {
     }
 }
 
+// ─── GET /mesh/subscribers ───────────────────────────────────────────────────
+
+/// Lists subscriptions declared on the mesh, from the routers' admin space. With
+/// `key_expr`, only those that would receive a publication on that key.
+pub async fn get_subscribers(
+    state: web::Data<AppState>,
+    query: web::Query<SubscribersQuery>,
+) -> impl Responder {
+    let session = &*state.zenoh_session;
+    if let Some(key_expr) = &query.key_expr {
+        if zenoh::key_expr::KeyExpr::try_from(key_expr.as_str()).is_err() {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Invalid key_expr",
+            }));
+        }
+    }
+
+    match query_zenoh(session, "@/*/*/subscriber/**").await {
+        Ok(entries) => {
+            let mut subscribers: Vec<mesh_admin::MeshSubscriber> = entries
+                .into_iter()
+                .filter_map(|mut e| {
+                    let key = e["key"].as_str()?.to_string();
+                    mesh_admin::parse_subscriber(&key, e["value"].take())
+                })
+                .filter(|sub| {
+                    query
+                        .key_expr
+                        .as_deref()
+                        .is_none_or(|ke| mesh_admin::subscription_covers(&sub.key_expr, ke))
+                })
+                .collect();
+            subscribers
+                .sort_by(|a, b| (&a.key_expr, &a.reported_by).cmp(&(&b.key_expr, &b.reported_by)));
+            HttpResponse::Ok().json(serde_json::json!({
+                "key_expr": query.key_expr,
+                "has_subscribers": !subscribers.is_empty(),
+                "subscribers": subscribers,
+            }))
+        }
+        Err(e) => {
+            error!("Failed to query mesh subscribers: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": e,
+            }))
+        }
+    }
+}
+
 // ─── GET /mesh/keys?prefix=entmoot/** ────────────────────────────────────────
 
 /// Lists stored keys and their latest values by querying the Zenoh storage.
-pub async fn get_keys(state: web::Data<AppState>, query: web::Query<KeysQuery>) -> impl Responder {
+pub async fn get_keys(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<KeysQuery>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
     let prefix = query.prefix.as_deref().unwrap_or("entmoot/**");
+    let policy = redaction_handlers::policy_for(&state, &req).await;
 
     let session = &*state.zenoh_session;
 
     match query_zenoh(session, prefix).await {
         Ok(entries) => {
-            let keys: Vec<serde_json::Value> = entries
-                .into_iter()
-                .map(|e| {
-                    serde_json::json!({
-                        "key_expr": e["key"],
-                        "value": e["value"],
-                        "encoding": "application/json",
-                        "timestamp": serde_json::Value::Null,
+            let keys: Vec<serde_json::Value> =
+                redact_entries(&policy, scope_entries(&scope, entries))
+                    .into_iter()
+                    .map(|e| {
+                        serde_json::json!({
+                            "key_expr": e["key"],
+                            "value": e["value"],
+                            "encoding": "application/json",
+                            "timestamp": serde_json::Value::Null,
+                        })
                     })
-                })
-                .collect();
+                    .collect();
             HttpResponse::Ok().json(keys)
         }
         Err(e) => {
@@ -212,17 +336,183 @@ pub async fn get_keys(state: web::Data<AppState>, query: web::Query<KeysQuery>)
     }
 }
 
+// ─── DELETE /mesh/keys?prefix=entmoot/sim ────────────────────────────────────
+
+/// Clears stale keys below `prefix`: issues a Zenoh delete for every key the
+/// storages hold there and drops the matching time-series history, so old demo
+/// data can be removed without restarting the router.
+pub async fn delete_keys(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<DeleteKeysQuery>,
+) -> impl Responder {
+    if !tenancy::is_deployment_admin(&state, &req) {
+        return HttpResponse::Forbidden().json(
+            serde_json::json!({"error": "Deleting keys requires the deployment admin token"}),
+        );
+    }
+    let prefix = query.prefix.trim_matches('/');
+    if prefix.is_empty() || prefix.contains('*') || prefix.contains('$') {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "prefix is required and must not contain wildcards",
+        }));
+    }
+    let dry_run = query.dry_run.unwrap_or(false);
+    let session = &*state.zenoh_session;
+
+    let entries = match query_zenoh(session, &format!("{}/**", prefix)).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("Failed to query keys under {}: {}", prefix, e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({ "error": e }));
+        }
+    };
+    let mut storage_keys: Vec<String> = entries
+        .iter()
+        .filter_map(|e| e["key"].as_str())
+        .filter(|key| key_tree::is_under(prefix, key))
+        .map(str::to_string)
+        .collect();
+    storage_keys.sort();
+    storage_keys.dedup();
+
+    let timeseries_keys: Vec<String> = {
+        let store = state.timeseries.read().await;
+        let mut keys: Vec<String> = store
+            .data
+            .keys()
+            .filter(|key| key_tree::is_under(prefix, key))
+            .cloned()
+            .collect();
+        keys.sort();
+        keys
+    };
+
+    if dry_run {
+        return HttpResponse::Ok().json(serde_json::json!({
+            "prefix": prefix,
+            "dry_run": true,
+            "storage_keys": storage_keys,
+            "timeseries_keys": timeseries_keys,
+        }));
+    }
+
+    let mut failed = Vec::new();
+    for key in &storage_keys {
+        if let Err(e) = session.delete(key.as_str()).await {
+            error!("Failed to delete {}: {}", key, e);
+            failed.push(key.clone());
+        }
+    }
+    {
+        let mut store = state.timeseries.write().await;
+        store.data.retain(|key, _| !key_tree::is_under(prefix, key));
+        store
+            .evicted
+            .retain(|key, _| !key_tree::is_under(prefix, key));
+    }
+    let historian_points = if state.historian.enabled() {
+        match db::delete_ts_points_under(&state.db_client, prefix).await {
+            Ok(deleted) => deleted,
+            Err(e) => {
+                error!("Failed to delete historian points under {}: {}", prefix, e);
+                0
+            }
+        }
+    } else {
+        0
+    };
+
+    info!(
+        "Deleted {} storage key(s) and {} time-series key(s) under {}",
+        storage_keys.len() - failed.len(),
+        timeseries_keys.len(),
+        prefix
+    );
+    HttpResponse::Ok().json(serde_json::json!({
+        "prefix": prefix,
+        "dry_run": false,
+        "storage_keys": storage_keys,
+        "timeseries_keys": timeseries_keys,
+        "historian_points": historian_points,
+        "failed": failed,
+    }))
+}
+
+// ─── GET /mesh/keys/tree ─────────────────────────────────────────────────────
+
+/// Lists one level of the key hierarchy below `prefix`, a page at a time, with
+/// the number of children and keys under each entry.
+pub async fn get_key_tree(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<KeyTreeQuery>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let prefix = query.prefix.as_deref().unwrap_or("").trim_matches('/');
+    if prefix.contains('*') || prefix.contains('$') {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "prefix must not contain wildcards",
+        }));
+    }
+    let selector = if prefix.is_empty() {
+        "**".to_string()
+    } else {
+        format!("{}/**", prefix)
+    };
+    let policy = redaction_handlers::policy_for(&state, &req).await;
+    let session = &*state.zenoh_session;
+
+    match query_zenoh(session, &selector).await {
+        Ok(entries) => {
+            let entries = redact_entries(&policy, scope_entries(&scope, entries));
+            let keys = entries.iter().filter_map(|e| e["key"].as_str());
+            let nodes = key_tree::level(prefix, keys);
+            let offset = query.offset.unwrap_or(0);
+            let limit = query
+                .limit
+                .unwrap_or(key_tree::DEFAULT_PAGE_SIZE)
+                .clamp(1, key_tree::MAX_PAGE_SIZE);
+            let total = nodes.len();
+            let page: Vec<_> = nodes.into_iter().skip(offset).take(limit).collect();
+            HttpResponse::Ok().json(serde_json::json!({
+                "prefix": prefix,
+                "total": total,
+                "offset": offset,
+                "limit": limit,
+                "children": page,
+            }))
+        }
+        Err(e) => {
+            error!("Failed to query key tree: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": e,
+            }))
+        }
+    }
+}
+
 // ─── GET /mesh/keys/{key_expr:.*} ────────────────────────────────────────────
 
 /// Gets a specific key's current value.
 pub async fn get_key_value(
+    req: HttpRequest,
     state: web::Data<AppState>,
     key_expr: web::Path<String>,
 ) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let policy = redaction_handlers::policy_for(&state, &req).await;
     let session = &*state.zenoh_session;
 
     match query_zenoh(session, key_expr.as_str()).await {
         Ok(entries) => {
+            let entries = redact_entries(&policy, scope_entries(&scope, entries));
             if entries.is_empty() {
                 HttpResponse::NotFound().json(serde_json::json!({
                     "error": "Key not found",
@@ -246,41 +536,172 @@ pub async fn get_key_value(
 
 // ─── POST /mesh/config ───────────────────────────────────────────────────────
 
-/// Pushes a config update to the Zenoh admin space.
+/// Pushes a config update to the Zenoh admin space, recording the value it
+/// replaces so the change can be rolled back.
 pub async fn update_config(
+    req: HttpRequest,
     state: web::Data<AppState>,
     body: web::Json<ConfigUpdateBody>,
 ) -> impl Responder {
-    let session = &*state.zenoh_session;
-
-    // Validate admin key starts with @/
-    if !body.admin_key.starts_with("@/") {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "admin_key must start with @/",
-        }));
+    if let Err(e) = mesh_admin::validate_admin_key(&body.admin_key) {
+        return HttpResponse::BadRequest().json(serde_json::json!({ "error": e }));
     }
+    let new_value = (!body.value.is_null()).then(|| body.value.clone());
+    let actor = CallerContext::from_request(&req).actor_id;
 
-    let payload = body.value.to_string();
-    info!("Updating admin config: {} = {}", body.admin_key, payload);
-
-    match session.put(&body.admin_key, payload).await {
-        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+    match apply_config_change(&state, &body.admin_key, new_value, actor, None).await {
+        Ok(change) => HttpResponse::Ok().json(serde_json::json!({
             "status": "updated",
             "admin_key": body.admin_key,
+            "change_id": change.id,
+            "previous_value": change.previous_value,
         })),
         Err(e) => {
             error!("Failed to update config: {}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Admin put failed: {}", e),
-            }))
+            HttpResponse::InternalServerError().json(serde_json::json!({ "error": e }))
         }
     }
 }
 
+// ─── GET /mesh/config/changes ────────────────────────────────────────────────
+
+/// Lists admin-space changes, most recent first.
+pub async fn list_config_changes(
+    state: web::Data<AppState>,
+    query: web::Query<ConfigChangesQuery>,
+) -> impl Responder {
+    let changes = state.mesh_config_changes.read().await;
+    let mut list: Vec<&MeshConfigChange> = changes
+        .values()
+        .filter(|change| {
+            query
+                .admin_key
+                .as_deref()
+                .is_none_or(|key| change.admin_key == key)
+        })
+        .collect();
+    list.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    list.truncate(query.limit.unwrap_or(100));
+    HttpResponse::Ok().json(list)
+}
+
+// ─── POST /mesh/config/rollback/{change_id} ──────────────────────────────────
+
+/// Restores the value an earlier change replaced. The rollback is itself logged.
+pub async fn rollback_config_change(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    change_id: web::Path<String>,
+) -> impl Responder {
+    let Some(original) = state
+        .mesh_config_changes
+        .read()
+        .await
+        .get(change_id.as_str())
+        .cloned()
+    else {
+        return HttpResponse::NotFound().json(serde_json::json!({ "error": "Change not found" }));
+    };
+    if let Some(rolled_back_by) = &original.rolled_back_by {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Change was already rolled back",
+            "rolled_back_by": rolled_back_by,
+        }));
+    }
+
+    let actor = CallerContext::from_request(&req).actor_id;
+    let rollback = match apply_config_change(
+        &state,
+        &original.admin_key,
+        original.previous_value.clone(),
+        actor,
+        Some(original.id.clone()),
+    )
+    .await
+    {
+        Ok(rollback) => rollback,
+        Err(e) => {
+            error!("Failed to roll back config change {}: {}", original.id, e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({ "error": e }));
+        }
+    };
+
+    let mut changes = state.mesh_config_changes.write().await;
+    if let Some(change) = changes.get_mut(&original.id) {
+        change.rolled_back_by = Some(rollback.id.clone());
+        runtime_store::persist_json(&state.mesh_change_dir, &change.id, &*change);
+    }
+    info!(
+        "Rolled back admin config change {} on {}",
+        original.id, original.admin_key
+    );
+    HttpResponse::Ok().json(rollback)
+}
+
+/// Writes (or, for `None`, deletes) an admin key after capturing its current value,
+/// and records the change.
+async fn apply_config_change(
+    state: &AppState,
+    admin_key: &str,
+    new_value: Option<serde_json::Value>,
+    actor: Option<String>,
+    rollback_of: Option<String>,
+) -> Result<MeshConfigChange, String> {
+    let session = &*state.zenoh_session;
+    let previous_value = query_zenoh(session, admin_key)
+        .await?
+        .into_iter()
+        .find(|entry| entry["key"] == admin_key)
+        .map(|mut entry| entry["value"].take());
+
+    match &new_value {
+        Some(value) => {
+            info!("Updating admin config: {} = {}", admin_key, value);
+            session
+                .put(admin_key, value.to_string())
+                .await
+                .map_err(|e| format!("Admin put failed: {}", e))?;
+        }
+        None => {
+            info!("Deleting admin config: {}", admin_key);
+            session
+                .delete(admin_key)
+                .await
+                .map_err(|e| format!("Admin delete failed: {}", e))?;
+        }
+    }
+
+    let change = MeshConfigChange {
+        id: uuid::Uuid::new_v4().to_string(),
+        admin_key: admin_key.to_string(),
+        previous_value,
+        new_value,
+        actor,
+        rollback_of,
+        rolled_back_by: None,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    runtime_store::persist_json(&state.mesh_change_dir, &change.id, &change);
+    state
+        .mesh_config_changes
+        .write()
+        .await
+        .insert(change.id.clone(), change.clone());
+    Ok(change)
+}
+
 // ─── POST /mesh/generate-config ──────────────────────────────────────────────
 
 /// Generates a Zenoh configuration JSON for a new node.
 pub async fn generate_node_config(body: web::Json<serde_json::Value>) -> impl Responder {
+    let config = build_node_config(&body);
+    info!("Generated Zenoh config for mode={}", config["mode"]);
+    HttpResponse::Ok().json(config)
+}
+
+/// Zenoh configuration for a node from generation options (`mode`,
+/// `listen_endpoints`, `connect_endpoints`, `multicast_scouting`, `storage_*`).
+pub fn build_node_config(body: &serde_json::Value) -> serde_json::Value {
     let mode = body["mode"].as_str().unwrap_or("client");
     let listen = body["listen_endpoints"]
         .as_array()
@@ -330,6 +751,5 @@ pub async fn generate_node_config(body: web::Json<serde_json::Value>) -> impl Re
         });
     }
 
-    info!("Generated Zenoh config for mode={}", mode);
-    HttpResponse::Ok().json(config)
+    config
 }
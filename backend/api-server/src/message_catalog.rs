@@ -0,0 +1,230 @@
+use actix_web::HttpRequest;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::alarm_grouping;
+use crate::state::AlarmRecord;
+
+/// Operator-language texts for one event code, e.g. the alarm text a PEA raises.
+///
+/// Texts may refer to the alarm with `{source}`, `{pea}` and `{value}`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MessageEntry {
+    #[serde(default)]
+    pub code: String,
+    /// Language tag (lowercase, e.g. "de" or "pt-br") -> text
+    pub texts: BTreeMap<String, String>,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl MessageEntry {
+    pub fn validate(&self, languages: &Languages) -> Result<(), String> {
+        if self.code.trim().is_empty() {
+            return Err("code is required".to_string());
+        }
+        // Codes double as file names in the catalog directory.
+        if self.code.contains(['/', '\\']) || self.code.starts_with('.') {
+            return Err("code may not contain path separators or start with '.'".to_string());
+        }
+        if self.texts.is_empty() {
+            return Err("At least one text is required".to_string());
+        }
+        if let Some(unknown) = self
+            .texts
+            .keys()
+            .find(|language| !languages.supported.contains(*language))
+        {
+            return Err(format!(
+                "Language '{}' is not configured (MESSAGE_LANGUAGES)",
+                unknown
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Languages the catalog may hold, in preference order after the caller's.
+#[derive(Clone, Debug, Serialize)]
+pub struct Languages {
+    pub supported: Vec<String>,
+    pub default: String,
+}
+
+impl Languages {
+    pub fn from_env() -> Self {
+        let supported: Vec<String> = std::env::var("MESSAGE_LANGUAGES")
+            .unwrap_or_else(|_| "en".to_string())
+            .split(',')
+            .map(|language| language.trim().to_ascii_lowercase())
+            .filter(|language| !language.is_empty())
+            .collect();
+        let default = std::env::var("MESSAGE_DEFAULT_LANGUAGE")
+            .map(|language| language.trim().to_ascii_lowercase())
+            .ok()
+            .filter(|language| supported.contains(language))
+            .or_else(|| supported.first().cloned())
+            .unwrap_or_else(|| "en".to_string());
+        Self { supported, default }
+    }
+}
+
+/// Language tags of an `Accept-Language` header, most preferred first.
+/// Tags with `q=0` are dropped.
+pub fn parse_accept_language(header: &str) -> Vec<String> {
+    let mut tags: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|part| {
+            let mut fields = part.split(';');
+            let tag = fields.next()?.trim().to_ascii_lowercase();
+            let q = fields
+                .find_map(|field| field.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (!tag.is_empty() && q > 0.0).then_some((tag, q))
+        })
+        .collect();
+    // Stable, so equally weighted tags keep header order.
+    tags.sort_by(|a, b| b.1.total_cmp(&a.1));
+    tags.into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// Languages a caller asked for: `?lang=` first, then `Accept-Language`.
+pub fn requested_languages(req: &HttpRequest) -> Vec<String> {
+    let query = actix_web::web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .map(|q| q.into_inner())
+        .unwrap_or_default();
+    let mut requested: Vec<String> = query
+        .get("lang")
+        .map(|lang| parse_accept_language(lang))
+        .unwrap_or_default();
+    if let Some(header) = req
+        .headers()
+        .get(actix_web::http::header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+    {
+        requested.extend(parse_accept_language(header));
+    }
+    requested
+}
+
+/// Picks the text for the first requested language the entry has, matching
+/// `de-ch` to `de` when there is no exact text, then the default language.
+pub fn negotiate<'a>(
+    entry: &'a MessageEntry,
+    requested: &[String],
+    languages: &Languages,
+) -> Option<(&'a str, &'a str)> {
+    let lookup = |tag: &str| {
+        entry
+            .texts
+            .get_key_value(tag)
+            .map(|(language, text)| (language.as_str(), text.as_str()))
+    };
+    requested
+        .iter()
+        .find_map(|tag| {
+            lookup(tag).or_else(|| tag.split_once('-').and_then(|(primary, _)| lookup(primary)))
+        })
+        .or_else(|| lookup(&languages.default))
+}
+
+fn render(template: &str, alarm: &AlarmRecord) -> String {
+    template
+        .replace("{source}", &alarm.source)
+        .replace("{pea}", alarm_grouping::pea_of(&alarm.source))
+        .replace("{value}", &alarm.value)
+}
+
+/// The alarm as JSON with `message` (and `message_language` when the catalog
+/// had a text) added; alarms without a catalog entry keep their raw event text.
+pub fn localize_alarm(
+    alarm: &AlarmRecord,
+    catalog: &HashMap<String, MessageEntry>,
+    requested: &[String],
+    languages: &Languages,
+) -> serde_json::Value {
+    let mut value = serde_json::to_value(alarm).unwrap_or_default();
+    let localized = catalog
+        .get(&alarm.event)
+        .and_then(|entry| negotiate(entry, requested, languages));
+    match localized {
+        Some((language, text)) => {
+            value["message"] = serde_json::json!(render(text, alarm));
+            value["message_language"] = serde_json::json!(language);
+        }
+        None => value["message"] = serde_json::json!(alarm.event),
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn languages() -> Languages {
+        Languages {
+            supported: vec!["en".to_string(), "de".to_string(), "fr".to_string()],
+            default: "en".to_string(),
+        }
+    }
+
+    fn entry() -> MessageEntry {
+        MessageEntry {
+            code: "HIGH_TEMP".to_string(),
+            texts: BTreeMap::from([
+                (
+                    "en".to_string(),
+                    "High temperature on {pea} ({value})".to_string(),
+                ),
+                (
+                    "de".to_string(),
+                    "Übertemperatur an {pea} ({value})".to_string(),
+                ),
+            ]),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn accept_language_is_ordered_by_quality() {
+        assert_eq!(
+            parse_accept_language("fr;q=0.5, de-CH, en;q=0.8, it;q=0"),
+            vec!["de-ch", "en", "fr"]
+        );
+    }
+
+    #[test]
+    fn alarms_get_the_best_matching_text() {
+        let alarm = AlarmRecord {
+            id: "a1".to_string(),
+            severity: "critical".to_string(),
+            status: "open".to_string(),
+            source: "entmoot/habitat/nodes/n1/pea/reactor/swimlane/alarm".to_string(),
+            event: "HIGH_TEMP".to_string(),
+            value: "92.5".to_string(),
+            description: String::new(),
+            timestamp: String::new(),
+            duplicate_count: 1,
+            parent_id: None,
+            raised_at: None,
+            acknowledged_at: None,
+            cleared_at: None,
+        };
+        let catalog = HashMap::from([("HIGH_TEMP".to_string(), entry())]);
+
+        let localized = localize_alarm(&alarm, &catalog, &["de-ch".to_string()], &languages());
+        assert_eq!(localized["message"], "Übertemperatur an reactor (92.5)");
+        assert_eq!(localized["message_language"], "de");
+
+        let fallback = localize_alarm(&alarm, &catalog, &["fr".to_string()], &languages());
+        assert_eq!(fallback["message_language"], "en");
+
+        let uncatalogued = localize_alarm(&alarm, &HashMap::new(), &[], &languages());
+        assert_eq!(uncatalogued["message"], "HIGH_TEMP");
+
+        let mut unknown = entry();
+        unknown.texts.insert("ja".to_string(), "高温".to_string());
+        assert!(unknown.validate(&languages()).is_err());
+    }
+}
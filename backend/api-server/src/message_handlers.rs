@@ -0,0 +1,79 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+
+use crate::message_catalog::{self, MessageEntry};
+use crate::runtime_store;
+use crate::state::AppState;
+
+/// GET /messages — the message catalog, by code
+pub async fn list_messages(state: web::Data<AppState>) -> impl Responder {
+    let catalog = state.message_catalog.read().await;
+    let mut list: Vec<&MessageEntry> = catalog.values().collect();
+    list.sort_by(|a, b| a.code.cmp(&b.code));
+    HttpResponse::Ok().json(list)
+}
+
+/// GET /messages/languages — languages texts may be given in, and the fallback
+pub async fn get_languages(state: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(&state.message_languages)
+}
+
+/// GET /messages/{code} — the entry with the text chosen for the caller's languages
+pub async fn get_message(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    code: web::Path<String>,
+) -> impl Responder {
+    let catalog = state.message_catalog.read().await;
+    let Some(entry) = catalog.get(code.as_str()) else {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Message not found"}));
+    };
+    let requested = message_catalog::requested_languages(&req);
+    let localized = message_catalog::negotiate(entry, &requested, &state.message_languages);
+    HttpResponse::Ok().json(serde_json::json!({
+        "entry": entry,
+        "language": localized.map(|(language, _)| language),
+        "text": localized.map(|(_, text)| text),
+    }))
+}
+
+/// PUT /messages/{code} — set the texts of an event code
+pub async fn put_message(
+    state: web::Data<AppState>,
+    code: web::Path<String>,
+    body: web::Json<MessageEntry>,
+) -> impl Responder {
+    let mut entry = body.into_inner();
+    entry.code = code.trim().to_string();
+    entry.texts = entry
+        .texts
+        .into_iter()
+        .map(|(language, text)| (language.trim().to_ascii_lowercase(), text))
+        .collect();
+    if let Err(e) = entry.validate(&state.message_languages) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    entry.updated_at = Utc::now().to_rfc3339();
+    runtime_store::persist_json(&state.message_catalog_dir, &entry.code, &entry);
+    state
+        .message_catalog
+        .write()
+        .await
+        .insert(entry.code.clone(), entry.clone());
+    HttpResponse::Ok().json(entry)
+}
+
+/// DELETE /messages/{code}
+pub async fn delete_message(state: web::Data<AppState>, code: web::Path<String>) -> impl Responder {
+    if state
+        .message_catalog
+        .write()
+        .await
+        .remove(code.as_str())
+        .is_none()
+    {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Message not found"}));
+    }
+    runtime_store::delete_json(&state.message_catalog_dir, &code);
+    HttpResponse::NoContent().finish()
+}
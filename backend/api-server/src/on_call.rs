@@ -0,0 +1,443 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::info;
+
+use crate::alarm_grouping;
+use crate::alarm_sla;
+use crate::severity_profile::SeverityProfile;
+use crate::state::{AlarmRecord, AppState};
+use crate::webhook_service;
+
+fn default_escalate_after_minutes() -> u32 {
+    15
+}
+
+fn default_min_severity() -> String {
+    "critical".to_string()
+}
+
+/// A rotation of users taking fixed-length shifts, and the chain paged while
+/// an alarm stays unacknowledged.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OnCallSchedule {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// Users taking shifts, in rotation order.
+    pub users: Vec<String>,
+    pub shift_hours: u32,
+    /// Start of the first shift; the rotation repeats from here.
+    pub rotation_start: DateTime<Utc>,
+    /// Users paged after the on-call user, in order.
+    #[serde(default)]
+    pub fallback: Vec<String>,
+    /// Minutes to wait for an acknowledgement before paging the next user.
+    #[serde(default = "default_escalate_after_minutes")]
+    pub escalate_after_minutes: u32,
+    /// Lowest alarm severity that pages (default: critical).
+    #[serde(default = "default_min_severity")]
+    pub min_severity: String,
+    /// PEAs whose alarms the schedule covers; empty covers every alarm.
+    #[serde(default)]
+    pub pea_ids: Vec<String>,
+    #[serde(default)]
+    pub overrides: Vec<OnCallOverride>,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+/// Someone covering the rotation for a while, e.g. a swapped shift.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OnCallOverride {
+    #[serde(default)]
+    pub id: String,
+    pub user: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl OnCallOverride {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.user.trim().is_empty() {
+            return Err("user is required".to_string());
+        }
+        if self.ends_at <= self.starts_at {
+            return Err("ends_at must be after starts_at".to_string());
+        }
+        Ok(())
+    }
+
+    fn covers(&self, at: DateTime<Utc>) -> bool {
+        self.starts_at <= at && at < self.ends_at
+    }
+}
+
+impl OnCallSchedule {
+    pub fn validate(&self, severities: &SeverityProfile) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name is required".to_string());
+        }
+        if self.users.is_empty() || self.users.iter().any(|user| user.trim().is_empty()) {
+            return Err("users must name at least one user".to_string());
+        }
+        if self.shift_hours == 0 {
+            return Err("shift_hours must be positive".to_string());
+        }
+        if self.escalate_after_minutes == 0 {
+            return Err("escalate_after_minutes must be positive".to_string());
+        }
+        if severities.level(&self.min_severity).is_none() {
+            return Err(format!("Unknown alarm severity '{}'", self.min_severity));
+        }
+        self.overrides.iter().try_for_each(OnCallOverride::validate)
+    }
+
+    /// The rotation user whose shift includes `at`, ignoring overrides.
+    pub fn rotation_user_at(&self, at: DateTime<Utc>) -> Option<&str> {
+        if self.users.is_empty() || self.shift_hours == 0 {
+            return None;
+        }
+        let shift = Duration::hours(i64::from(self.shift_hours)).num_seconds();
+        let index = (at - self.rotation_start)
+            .num_seconds()
+            .div_euclid(shift)
+            .rem_euclid(self.users.len() as i64);
+        self.users.get(index as usize).map(String::as_str)
+    }
+
+    /// The latest-starting override covering `at`.
+    pub fn override_at(&self, at: DateTime<Utc>) -> Option<&OnCallOverride> {
+        self.overrides
+            .iter()
+            .filter(|o| o.covers(at))
+            .max_by_key(|o| o.starts_at)
+    }
+
+    /// Who is on call at `at`: the override covering it, else the rotation.
+    pub fn on_call_at(&self, at: DateTime<Utc>) -> Option<&str> {
+        self.override_at(at)
+            .map(|o| o.user.as_str())
+            .or_else(|| self.rotation_user_at(at))
+    }
+
+    /// Users paged in turn for an alarm at `at`: the on-call user, then the
+    /// fallback order, each once.
+    pub fn chain_at(&self, at: DateTime<Utc>) -> Vec<String> {
+        let mut chain: Vec<String> = Vec::new();
+        for user in self
+            .on_call_at(at)
+            .into_iter()
+            .chain(self.fallback.iter().map(String::as_str))
+        {
+            if !chain.iter().any(|paged| paged == user) {
+                chain.push(user.to_string());
+            }
+        }
+        chain
+    }
+
+    /// Whether an alarm pages through this schedule.
+    pub fn covers(&self, alarm: &AlarmRecord, severities: &SeverityProfile) -> bool {
+        severities.at_least(&alarm.severity, &self.min_severity)
+            && (self.pea_ids.is_empty()
+                || self
+                    .pea_ids
+                    .iter()
+                    .any(|pea_id| pea_id == alarm_grouping::pea_of(&alarm.source)))
+    }
+}
+
+/// Chain position to page after an alarm has gone unacknowledged for
+/// `elapsed`; stays on the last user once the chain is exhausted.
+pub fn due_level(elapsed: Duration, escalate_after_minutes: u32, chain_len: usize) -> usize {
+    let step = i64::from(escalate_after_minutes.max(1));
+    let level = (elapsed.num_minutes().max(0) / step) as usize;
+    level.min(chain_len.saturating_sub(1))
+}
+
+/// One user paged for an alarm.
+#[derive(Clone, Debug, Serialize)]
+pub struct Page {
+    pub user: String,
+    pub level: usize,
+    pub paged_at: String,
+}
+
+/// Paging progress of an unacknowledged alarm through one schedule.
+#[derive(Clone, Debug, Serialize)]
+pub struct Escalation {
+    pub alarm_id: String,
+    pub schedule_id: String,
+    pub severity: String,
+    pub source: String,
+    pub level: usize,
+    pub pages: Vec<Page>,
+}
+
+/// On-call schedules and the escalations running against them. Escalations
+/// are kept in memory; after a restart the due user of each open alarm is
+/// paged again.
+#[derive(Clone)]
+pub struct OnCall {
+    schedules: Arc<RwLock<HashMap<String, OnCallSchedule>>>,
+    escalations: Arc<RwLock<HashMap<(String, String), Escalation>>>,
+}
+
+impl OnCall {
+    pub fn new(schedules: HashMap<String, OnCallSchedule>) -> Self {
+        Self {
+            schedules: Arc::new(RwLock::new(schedules)),
+            escalations: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub async fn list(&self) -> Vec<OnCallSchedule> {
+        let mut schedules: Vec<OnCallSchedule> =
+            self.schedules.read().await.values().cloned().collect();
+        schedules.sort_by(|a, b| a.name.cmp(&b.name));
+        schedules
+    }
+
+    pub async fn get(&self, schedule_id: &str) -> Option<OnCallSchedule> {
+        self.schedules.read().await.get(schedule_id).cloned()
+    }
+
+    pub async fn upsert(
+        &self,
+        schedule: OnCallSchedule,
+        severities: &SeverityProfile,
+    ) -> Result<(), String> {
+        schedule.validate(severities)?;
+        self.schedules
+            .write()
+            .await
+            .insert(schedule.id.clone(), schedule);
+        Ok(())
+    }
+
+    /// Removes a schedule and stops its escalations.
+    pub async fn remove(&self, schedule_id: &str) -> bool {
+        self.escalations
+            .write()
+            .await
+            .retain(|(_, schedule), _| schedule != schedule_id);
+        self.schedules.write().await.remove(schedule_id).is_some()
+    }
+
+    /// Escalations in progress, oldest alarm first.
+    pub async fn escalations(&self) -> Vec<Escalation> {
+        let mut escalations: Vec<Escalation> =
+            self.escalations.read().await.values().cloned().collect();
+        escalations.sort_by(|a, b| {
+            let first = |e: &Escalation| e.pages.first().map(|p| p.paged_at.clone());
+            first(a).cmp(&first(b))
+        });
+        escalations
+    }
+
+    /// Advances every escalation to the user due at `now` and returns the new
+    /// pages with the alarm they are for. Escalations of alarms that were
+    /// acknowledged, cleared or shelved end.
+    pub async fn advance(
+        &self,
+        alarms: &HashMap<String, AlarmRecord>,
+        now: DateTime<Utc>,
+        severities: &SeverityProfile,
+    ) -> Vec<(AlarmRecord, OnCallSchedule, Page)> {
+        let schedules = self.schedules.read().await;
+        let mut escalations = self.escalations.write().await;
+        escalations.retain(|(alarm_id, _), _| {
+            alarms
+                .get(alarm_id)
+                .is_some_and(|alarm| alarm.status == "open")
+        });
+
+        let mut pages = Vec::new();
+        // Grouped alarms follow their primary cause, which pages for them.
+        for alarm in alarms
+            .values()
+            .filter(|alarm| alarm.status == "open" && alarm.parent_id.is_none())
+        {
+            let Some(raised) = alarm_sla::raised_at(alarm) else {
+                continue;
+            };
+            for schedule in schedules
+                .values()
+                .filter(|schedule| schedule.covers(alarm, severities))
+            {
+                let chain = schedule.chain_at(now);
+                if chain.is_empty() {
+                    continue;
+                }
+                let level = due_level(now - raised, schedule.escalate_after_minutes, chain.len());
+                let key = (alarm.id.clone(), schedule.id.clone());
+                let escalation = escalations.entry(key).or_insert_with(|| Escalation {
+                    alarm_id: alarm.id.clone(),
+                    schedule_id: schedule.id.clone(),
+                    severity: alarm.severity.clone(),
+                    source: alarm.source.clone(),
+                    level,
+                    pages: Vec::new(),
+                });
+                if !escalation.pages.is_empty() && escalation.level >= level {
+                    continue;
+                }
+                let page = Page {
+                    user: chain[level].clone(),
+                    level,
+                    paged_at: now.to_rfc3339(),
+                };
+                escalation.level = level;
+                escalation.pages.push(page.clone());
+                pages.push((alarm.clone(), schedule.clone(), page));
+            }
+        }
+        pages
+    }
+}
+
+/// Pages the on-call chain for unacknowledged alarms; each page goes out as
+/// an `alarm.escalated` webhook event.
+pub async fn escalate(state: &AppState, now: DateTime<Utc>) {
+    let alarms = state.alarms.read().await.clone();
+    let severities = state.severity_profile.read().await.clone();
+    let pages = state.on_call.advance(&alarms, now, &severities).await;
+    for (alarm, schedule, page) in pages {
+        info!(
+            "Paging {} (level {}) via on-call schedule {} for alarm {}",
+            page.user, page.level, schedule.name, alarm.id
+        );
+        state
+            .webhooks
+            .emit(
+                webhook_service::EVENT_ALARM_ESCALATED,
+                serde_json::json!({
+                    "user": page.user,
+                    "level": page.level,
+                    "schedule_id": schedule.id,
+                    "schedule_name": schedule.name,
+                    "alarm_id": alarm.id,
+                    "severity": alarm.severity,
+                    "source": alarm.source,
+                    "event": alarm.event,
+                    "description": alarm.description,
+                    "raised_at": alarm.raised_at,
+                }),
+            )
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::alarm_record;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        format!("2026-05-01T{}:00Z", time).parse().unwrap()
+    }
+
+    fn schedule() -> OnCallSchedule {
+        OnCallSchedule {
+            id: "s1".to_string(),
+            name: "Plant".to_string(),
+            users: vec!["ana".to_string(), "ben".to_string(), "cem".to_string()],
+            shift_hours: 8,
+            rotation_start: at("06:00"),
+            fallback: vec!["lead".to_string(), "ana".to_string()],
+            escalate_after_minutes: 10,
+            min_severity: default_min_severity(),
+            pea_ids: Vec::new(),
+            overrides: Vec::new(),
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    fn alarm(status: &str, severity: &str, raised: DateTime<Utc>) -> AlarmRecord {
+        AlarmRecord {
+            status: status.to_string(),
+            timestamp: raised.to_rfc3339(),
+            raised_at: Some(raised.to_rfc3339()),
+            ..alarm_record("a1", "p1", severity)
+        }
+    }
+
+    #[test]
+    fn rotation_and_overrides_decide_who_is_on_call() {
+        let mut schedule = schedule();
+        assert!(schedule.validate(&SeverityProfile::default()).is_ok());
+        assert_eq!(schedule.on_call_at(at("07:00")), Some("ana"));
+        assert_eq!(schedule.on_call_at(at("14:00")), Some("ben"));
+        assert_eq!(schedule.on_call_at(at("23:00")), Some("cem"));
+        // Before the rotation start the rotation runs backwards.
+        assert_eq!(schedule.on_call_at(at("05:59")), Some("cem"));
+
+        schedule.overrides.push(OnCallOverride {
+            id: "o1".to_string(),
+            user: "dee".to_string(),
+            starts_at: at("12:00"),
+            ends_at: at("16:00"),
+            reason: None,
+        });
+        assert_eq!(schedule.on_call_at(at("14:00")), Some("dee"));
+        assert_eq!(schedule.on_call_at(at("16:00")), Some("ben"));
+        assert_eq!(schedule.chain_at(at("07:00")), vec!["ana", "lead"]);
+    }
+
+    #[tokio::test]
+    async fn unacknowledged_alarms_escalate_up_the_chain() {
+        let on_call = OnCall::new(HashMap::from([("s1".to_string(), schedule())]));
+        let raised = at("14:00");
+        let mut alarms = HashMap::from([("a1".to_string(), alarm("open", "critical", raised))]);
+        let severities = SeverityProfile::default();
+
+        let users = |pages: Vec<(AlarmRecord, OnCallSchedule, Page)>| {
+            pages
+                .into_iter()
+                .map(|(_, _, page)| page.user)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            users(on_call.advance(&alarms, at("14:01"), &severities).await),
+            vec!["ben"]
+        );
+        assert!(on_call
+            .advance(&alarms, at("14:05"), &severities)
+            .await
+            .is_empty());
+        assert_eq!(
+            users(on_call.advance(&alarms, at("14:10"), &severities).await),
+            vec!["lead"]
+        );
+        assert_eq!(
+            users(on_call.advance(&alarms, at("14:20"), &severities).await),
+            vec!["ana"]
+        );
+        assert!(on_call
+            .advance(&alarms, at("15:00"), &severities)
+            .await
+            .is_empty());
+        assert_eq!(on_call.escalations().await[0].pages.len(), 3);
+
+        alarms.get_mut("a1").unwrap().status = "acknowledged".to_string();
+        assert!(on_call
+            .advance(&alarms, at("15:10"), &severities)
+            .await
+            .is_empty());
+        assert!(on_call.escalations().await.is_empty());
+
+        alarms.insert("a1".to_string(), alarm("open", "high", raised));
+        assert!(on_call
+            .advance(&alarms, at("15:20"), &severities)
+            .await
+            .is_empty());
+    }
+}
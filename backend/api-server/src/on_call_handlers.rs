@@ -0,0 +1,166 @@
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::on_call::{OnCallOverride, OnCallSchedule};
+use crate::runtime_store;
+use crate::state::AppState;
+
+#[derive(Deserialize)]
+pub struct CurrentQuery {
+    /// Instant to resolve, RFC 3339 (default: now).
+    pub at: Option<DateTime<Utc>>,
+}
+
+pub async fn list_schedules(state: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(state.on_call.list().await)
+}
+
+pub async fn get_schedule(
+    state: web::Data<AppState>,
+    schedule_id: web::Path<String>,
+) -> impl Responder {
+    match state.on_call.get(&schedule_id).await {
+        Some(schedule) => HttpResponse::Ok().json(schedule),
+        None => schedule_not_found(),
+    }
+}
+
+pub async fn create_schedule(
+    state: web::Data<AppState>,
+    body: web::Json<OnCallSchedule>,
+) -> impl Responder {
+    let now = Utc::now().to_rfc3339();
+    let mut schedule = body.into_inner();
+    schedule.id = uuid::Uuid::new_v4().to_string();
+    schedule.name = schedule.name.trim().to_string();
+    for override_ in &mut schedule.overrides {
+        override_.id = uuid::Uuid::new_v4().to_string();
+    }
+    schedule.created_at = now.clone();
+    schedule.updated_at = now;
+    match save_schedule(&state, schedule).await {
+        Ok(schedule) => HttpResponse::Created().json(schedule),
+        Err(response) => response,
+    }
+}
+
+/// PUT /on-call/schedules/{id} — replaces a schedule; overrides sent without an id get one
+pub async fn update_schedule(
+    state: web::Data<AppState>,
+    schedule_id: web::Path<String>,
+    body: web::Json<OnCallSchedule>,
+) -> impl Responder {
+    let Some(existing) = state.on_call.get(&schedule_id).await else {
+        return schedule_not_found();
+    };
+    let mut schedule = body.into_inner();
+    schedule.id = existing.id;
+    schedule.name = schedule.name.trim().to_string();
+    for override_ in schedule.overrides.iter_mut().filter(|o| o.id.is_empty()) {
+        override_.id = uuid::Uuid::new_v4().to_string();
+    }
+    schedule.created_at = existing.created_at;
+    schedule.updated_at = Utc::now().to_rfc3339();
+    match save_schedule(&state, schedule).await {
+        Ok(schedule) => HttpResponse::Ok().json(schedule),
+        Err(response) => response,
+    }
+}
+
+pub async fn delete_schedule(
+    state: web::Data<AppState>,
+    schedule_id: web::Path<String>,
+) -> impl Responder {
+    if !state.on_call.remove(&schedule_id).await {
+        return schedule_not_found();
+    }
+    runtime_store::delete_json(&state.on_call_dir, &schedule_id);
+    HttpResponse::NoContent().finish()
+}
+
+/// GET /on-call/schedules/{id}/current — who is on call and the escalation chain behind them
+pub async fn get_current_on_call(
+    state: web::Data<AppState>,
+    schedule_id: web::Path<String>,
+    query: web::Query<CurrentQuery>,
+) -> impl Responder {
+    let Some(schedule) = state.on_call.get(&schedule_id).await else {
+        return schedule_not_found();
+    };
+    let at = query.at.unwrap_or_else(Utc::now);
+    HttpResponse::Ok().json(serde_json::json!({
+        "schedule_id": schedule.id,
+        "at": at,
+        "user": schedule.on_call_at(at),
+        "rotation_user": schedule.rotation_user_at(at),
+        "override": schedule.override_at(at),
+        "chain": schedule.chain_at(at),
+    }))
+}
+
+/// POST /on-call/schedules/{id}/overrides — someone covers the rotation for a while
+pub async fn add_override(
+    state: web::Data<AppState>,
+    schedule_id: web::Path<String>,
+    body: web::Json<OnCallOverride>,
+) -> impl Responder {
+    let Some(mut schedule) = state.on_call.get(&schedule_id).await else {
+        return schedule_not_found();
+    };
+    let mut override_ = body.into_inner();
+    override_.id = uuid::Uuid::new_v4().to_string();
+    if let Err(e) = override_.validate() {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    // Overrides that have ended no longer matter.
+    let now = Utc::now();
+    schedule.overrides.retain(|o| o.ends_at > now);
+    schedule.overrides.push(override_.clone());
+    schedule.updated_at = now.to_rfc3339();
+    match save_schedule(&state, schedule).await {
+        Ok(_) => HttpResponse::Created().json(override_),
+        Err(response) => response,
+    }
+}
+
+pub async fn delete_override(
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (schedule_id, override_id) = path.into_inner();
+    let Some(mut schedule) = state.on_call.get(&schedule_id).await else {
+        return schedule_not_found();
+    };
+    let before = schedule.overrides.len();
+    schedule.overrides.retain(|o| o.id != override_id);
+    if schedule.overrides.len() == before {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Override not found"}));
+    }
+    schedule.updated_at = Utc::now().to_rfc3339();
+    match save_schedule(&state, schedule).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(response) => response,
+    }
+}
+
+/// GET /on-call/escalations — unacknowledged alarms being paged and who was paged so far
+pub async fn list_escalations(state: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(state.on_call.escalations().await)
+}
+
+async fn save_schedule(
+    state: &AppState,
+    schedule: OnCallSchedule,
+) -> Result<OnCallSchedule, HttpResponse> {
+    let severities = state.severity_profile.read().await.clone();
+    if let Err(e) = state.on_call.upsert(schedule.clone(), &severities).await {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({"error": e})));
+    }
+    runtime_store::persist_json(&state.on_call_dir, &schedule.id, &schedule);
+    Ok(schedule)
+}
+
+fn schedule_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({"error": "On-call schedule not found"}))
+}
@@ -0,0 +1,1131 @@
+use anyhow::{anyhow, bail, Context, Result};
+use shared::mtp::{ProtocolType, TagMapping};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+// Minimal OPC UA client over the binary `opc.tcp` transport: security
+// policy None, an anonymous session, and the Browse and Read services —
+// enough to list the variables of a server's address space.
+
+const SECURITY_POLICY_NONE: &str = "http://opcfoundation.org/UA/SecurityPolicy#None";
+const DEFAULT_PORT: u16 = 4840;
+const BUFFER_SIZE: u32 = 65_535;
+/// Largest response accepted, over all of its chunks.
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+// Binary encoding ids (namespace 0) of the messages used.
+const SERVICE_FAULT: u32 = 397;
+const OPEN_SECURE_CHANNEL_REQUEST: u32 = 446;
+const OPEN_SECURE_CHANNEL_RESPONSE: u32 = 449;
+const CLOSE_SECURE_CHANNEL_REQUEST: u32 = 452;
+const CREATE_SESSION_REQUEST: u32 = 461;
+const CREATE_SESSION_RESPONSE: u32 = 464;
+const ACTIVATE_SESSION_REQUEST: u32 = 467;
+const ACTIVATE_SESSION_RESPONSE: u32 = 470;
+const CLOSE_SESSION_REQUEST: u32 = 473;
+const CLOSE_SESSION_RESPONSE: u32 = 476;
+const BROWSE_REQUEST: u32 = 527;
+const BROWSE_RESPONSE: u32 = 530;
+const BROWSE_NEXT_REQUEST: u32 = 533;
+const BROWSE_NEXT_RESPONSE: u32 = 536;
+const READ_REQUEST: u32 = 631;
+const READ_RESPONSE: u32 = 634;
+const ANONYMOUS_IDENTITY_TOKEN: u32 = 321;
+
+const HIERARCHICAL_REFERENCES: u32 = 33;
+/// The standard `Objects` folder, where browsing starts by default.
+pub const OBJECTS_FOLDER: u32 = 85;
+const ATTRIBUTE_DATA_TYPE: u32 = 14;
+const NODE_CLASS_OBJECT: u32 = 1;
+const NODE_CLASS_VARIABLE: u32 = 2;
+/// Nodes per Browse or Read request.
+const BATCH_SIZE: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Identifier {
+    Numeric(u32),
+    String(String),
+    Guid([u8; 16]),
+    Opaque(Vec<u8>),
+}
+
+/// A node id, written as in the OPC UA text format, e.g. `i=85` or
+/// `ns=2;s=Dosing.FIC101.SP`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    pub namespace: u16,
+    pub identifier: Identifier,
+}
+
+impl NodeId {
+    pub fn numeric(namespace: u16, id: u32) -> Self {
+        Self {
+            namespace,
+            identifier: Identifier::Numeric(id),
+        }
+    }
+
+    fn null() -> Self {
+        Self::numeric(0, 0)
+    }
+
+    /// Parses `[ns=<index>;]<i|s|g|b>=<identifier>`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (namespace, identifier) = match text.strip_prefix("ns=") {
+            Some(rest) => {
+                let (ns, identifier) = rest.split_once(';')?;
+                (ns.parse().ok()?, identifier)
+            }
+            None => (0, text),
+        };
+        let (kind, value) = identifier.split_once('=')?;
+        let identifier = match kind {
+            "i" => Identifier::Numeric(value.parse().ok()?),
+            "s" if !value.is_empty() => Identifier::String(value.to_string()),
+            "g" => Identifier::Guid(parse_guid(value)?),
+            "b" => Identifier::Opaque(
+                base64::Engine::decode(&base64::engine::general_purpose::STANDARD, value).ok()?,
+            ),
+            _ => return None,
+        };
+        Some(Self {
+            namespace,
+            identifier,
+        })
+    }
+}
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.namespace != 0 {
+            write!(f, "ns={};", self.namespace)?;
+        }
+        match &self.identifier {
+            Identifier::Numeric(id) => write!(f, "i={}", id),
+            Identifier::String(id) => write!(f, "s={}", id),
+            Identifier::Guid(bytes) => write!(f, "g={}", format_guid(bytes)),
+            Identifier::Opaque(bytes) => write!(
+                f,
+                "b={}",
+                base64::Engine::encode(&base64::engine::general_purpose::STANDARD, bytes)
+            ),
+        }
+    }
+}
+
+/// Guids are encoded with their first three fields little-endian.
+fn parse_guid(text: &str) -> Option<[u8; 16]> {
+    let hex: String = text.chars().filter(|c| *c != '-').collect();
+    if hex.len() != 32 || text.len() != 36 {
+        return None;
+    }
+    let mut bytes = [0u8; 16];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    bytes[0..4].reverse();
+    bytes[4..6].reverse();
+    bytes[6..8].reverse();
+    Some(bytes)
+}
+
+fn format_guid(bytes: &[u8; 16]) -> String {
+    let mut ordered = *bytes;
+    ordered[0..4].reverse();
+    ordered[4..6].reverse();
+    ordered[6..8].reverse();
+    let hex = hex::encode(ordered);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Name of a built-in data type, or the data type's node id.
+fn data_type_name(data_type: &NodeId) -> String {
+    let name = match (data_type.namespace, &data_type.identifier) {
+        (0, Identifier::Numeric(id)) => match id {
+            1 => "Boolean",
+            2 => "SByte",
+            3 => "Byte",
+            4 => "Int16",
+            5 => "UInt16",
+            6 => "Int32",
+            7 => "UInt32",
+            8 => "Int64",
+            9 => "UInt64",
+            10 => "Float",
+            11 => "Double",
+            12 => "String",
+            13 => "DateTime",
+            14 => "Guid",
+            15 => "ByteString",
+            17 => "NodeId",
+            20 => "QualifiedName",
+            21 => "LocalizedText",
+            26 => "Number",
+            27 => "Integer",
+            28 => "UInteger",
+            29 => "Enumeration",
+            _ => "",
+        },
+        _ => "",
+    };
+    if name.is_empty() {
+        data_type.to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+// ─── Binary encoding ────────────────────────────────────────────────────────
+
+#[derive(Default)]
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) -> &mut Self {
+        self.buf.push(value);
+        self
+    }
+
+    fn u16(&mut self, value: u16) -> &mut Self {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn u32(&mut self, value: u32) -> &mut Self {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn i32(&mut self, value: i32) -> &mut Self {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn i64(&mut self, value: i64) -> &mut Self {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn f64(&mut self, value: f64) -> &mut Self {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn bool(&mut self, value: bool) -> &mut Self {
+        self.u8(value as u8)
+    }
+
+    fn bytes(&mut self, value: Option<&[u8]>) -> &mut Self {
+        match value {
+            Some(bytes) => {
+                self.i32(bytes.len() as i32);
+                self.buf.extend_from_slice(bytes);
+                self
+            }
+            None => self.i32(-1),
+        }
+    }
+
+    fn string(&mut self, value: Option<&str>) -> &mut Self {
+        self.bytes(value.map(str::as_bytes))
+    }
+
+    fn localized_text(&mut self, text: &str) -> &mut Self {
+        self.u8(0x02).string(Some(text))
+    }
+
+    fn node_id(&mut self, node_id: &NodeId) -> &mut Self {
+        let ns = node_id.namespace;
+        match &node_id.identifier {
+            Identifier::Numeric(id) if ns == 0 && *id <= 0xFF => self.u8(0x00).u8(*id as u8),
+            Identifier::Numeric(id) if ns <= 0xFF && *id <= 0xFFFF => {
+                self.u8(0x01).u8(ns as u8).u16(*id as u16)
+            }
+            Identifier::Numeric(id) => self.u8(0x02).u16(ns).u32(*id),
+            Identifier::String(id) => self.u8(0x03).u16(ns).string(Some(id)),
+            Identifier::Guid(bytes) => {
+                self.u8(0x04).u16(ns);
+                self.buf.extend_from_slice(bytes);
+                self
+            }
+            Identifier::Opaque(bytes) => self.u8(0x05).u16(ns).bytes(Some(bytes)),
+        }
+    }
+
+    fn null_extension_object(&mut self) -> &mut Self {
+        self.node_id(&NodeId::null()).u8(0)
+    }
+
+    fn request_header(&mut self, auth_token: &NodeId, handle: u32) -> &mut Self {
+        self.node_id(auth_token)
+            .i64(0)
+            .u32(handle)
+            .u32(0)
+            .string(None)
+            .u32(10_000)
+            .null_extension_object()
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.buf.len())
+            .ok_or_else(|| anyhow!("Truncated OPC UA message"))?;
+        let bytes = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn bool(&mut self) -> Result<bool> {
+        Ok(self.u8()? != 0)
+    }
+
+    fn bytes(&mut self) -> Result<Option<&'a [u8]>> {
+        let len = self.i32()?;
+        if len < 0 {
+            return Ok(None);
+        }
+        self.take(len as usize).map(Some)
+    }
+
+    fn string(&mut self) -> Result<Option<String>> {
+        Ok(self
+            .bytes()?
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned()))
+    }
+
+    /// Length of an array; a null array counts as empty.
+    fn len(&mut self) -> Result<usize> {
+        let len = self.i32()?.max(0) as usize;
+        // Every element takes at least a byte.
+        if len > self.buf.len() - self.pos {
+            bail!("Truncated OPC UA message");
+        }
+        Ok(len)
+    }
+
+    fn array<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+        (0..self.len()?).map(|_| item(self)).collect()
+    }
+
+    fn node_id(&mut self) -> Result<NodeId> {
+        Ok(self.expanded_node_id()?.0)
+    }
+
+    /// Node id with the namespace URI and server index of an expanded one.
+    fn expanded_node_id(&mut self) -> Result<(NodeId, Option<String>, u32)> {
+        let encoding = self.u8()?;
+        let (namespace, identifier) = match encoding & 0x3F {
+            0x00 => (0, Identifier::Numeric(self.u8()? as u32)),
+            0x01 => (self.u8()? as u16, Identifier::Numeric(self.u16()? as u32)),
+            0x02 => (self.u16()?, Identifier::Numeric(self.u32()?)),
+            0x03 => (
+                self.u16()?,
+                Identifier::String(self.string()?.unwrap_or_default()),
+            ),
+            0x04 => (self.u16()?, Identifier::Guid(self.take(16)?.try_into()?)),
+            0x05 => (
+                self.u16()?,
+                Identifier::Opaque(self.bytes()?.unwrap_or_default().to_vec()),
+            ),
+            other => bail!("Unknown node id encoding {:#04x}", other),
+        };
+        let uri = if encoding & 0x80 != 0 {
+            self.string()?
+        } else {
+            None
+        };
+        let server_index = if encoding & 0x40 != 0 { self.u32()? } else { 0 };
+        Ok((
+            NodeId {
+                namespace,
+                identifier,
+            },
+            uri,
+            server_index,
+        ))
+    }
+
+    fn localized_text(&mut self) -> Result<Option<String>> {
+        let mask = self.u8()?;
+        if mask & 0x01 != 0 {
+            self.string()?;
+        }
+        if mask & 0x02 != 0 {
+            return self.string();
+        }
+        Ok(None)
+    }
+
+    fn qualified_name(&mut self) -> Result<String> {
+        self.u16()?;
+        Ok(self.string()?.unwrap_or_default())
+    }
+
+    fn skip_diagnostic_info(&mut self) -> Result<()> {
+        let mask = self.u8()?;
+        for bit in [0x01, 0x02, 0x04, 0x08] {
+            if mask & bit != 0 {
+                self.i32()?;
+            }
+        }
+        if mask & 0x10 != 0 {
+            self.string()?;
+        }
+        if mask & 0x20 != 0 {
+            self.u32()?;
+        }
+        if mask & 0x40 != 0 {
+            self.skip_diagnostic_info()?;
+        }
+        Ok(())
+    }
+
+    fn skip_extension_object(&mut self) -> Result<()> {
+        self.node_id()?;
+        if self.u8()? != 0 {
+            self.bytes()?;
+        }
+        Ok(())
+    }
+
+    /// Reads a response header, failing on a bad service result.
+    fn response_header(&mut self) -> Result<()> {
+        self.take(8)?;
+        self.u32()?;
+        let service_result = self.u32()?;
+        self.skip_diagnostic_info()?;
+        self.array(|r| r.string())?;
+        self.skip_extension_object()?;
+        if service_result & 0x8000_0000 != 0 {
+            bail!("OPC UA service failed with status {:#010x}", service_result);
+        }
+        Ok(())
+    }
+
+    /// The data type a Read of the DataType attribute returned, if any.
+    fn data_type_value(&mut self) -> Result<Option<NodeId>> {
+        let mask = self.u8()?;
+        let mut value = None;
+        if mask & 0x01 != 0 {
+            let encoding = self.u8()?;
+            // Only a scalar NodeId is expected; anything else cannot be skipped
+            // without decoding every type.
+            if encoding != 17 {
+                bail!("Unexpected variant type {} for a data type", encoding);
+            }
+            value = Some(self.node_id()?);
+        }
+        if mask & 0x02 != 0 {
+            self.u32()?;
+        }
+        if mask & 0x04 != 0 {
+            self.take(8)?;
+        }
+        if mask & 0x10 != 0 {
+            self.u16()?;
+        }
+        if mask & 0x08 != 0 {
+            self.take(8)?;
+        }
+        if mask & 0x20 != 0 {
+            self.u16()?;
+        }
+        Ok(value)
+    }
+}
+
+/// A reference found while browsing.
+struct Reference {
+    node_id: NodeId,
+    namespace_uri: Option<String>,
+    browse_name: String,
+    display_name: Option<String>,
+    node_class: u32,
+}
+
+struct BrowseResult {
+    continuation_point: Option<Vec<u8>>,
+    references: Vec<Reference>,
+}
+
+fn browse_results(reader: &mut Reader) -> Result<Vec<BrowseResult>> {
+    reader.array(|r| {
+        let status = r.u32()?;
+        let continuation_point = r.bytes()?.filter(|cp| !cp.is_empty()).map(<[u8]>::to_vec);
+        let references = r.array(|r| {
+            r.node_id()?;
+            r.bool()?;
+            let (node_id, namespace_uri, server_index) = r.expanded_node_id()?;
+            let browse_name = r.qualified_name()?;
+            let display_name = r.localized_text()?;
+            let node_class = r.u32()?;
+            r.expanded_node_id()?;
+            Ok((server_index == 0).then_some(Reference {
+                node_id,
+                namespace_uri,
+                browse_name,
+                display_name,
+                node_class,
+            }))
+        })?;
+        Ok(BrowseResult {
+            continuation_point: (status & 0x8000_0000 == 0)
+                .then_some(continuation_point)
+                .flatten(),
+            references: references.into_iter().flatten().collect(),
+        })
+    })
+}
+
+// ─── Connection ─────────────────────────────────────────────────────────────
+
+/// `host:port` of an `opc.tcp://host[:port][/path]` endpoint.
+fn endpoint_address(endpoint: &str) -> Result<String> {
+    let rest = endpoint
+        .trim()
+        .strip_prefix("opc.tcp://")
+        .ok_or_else(|| anyhow!("Endpoint must start with opc.tcp://"))?;
+    let authority = rest.split('/').next().unwrap_or_default();
+    if authority.is_empty() {
+        bail!("Endpoint has no host");
+    }
+    let has_port = match authority.rsplit_once(':') {
+        Some((_, port)) => !authority.ends_with(']') && port.parse::<u16>().is_ok(),
+        None => false,
+    };
+    Ok(if has_port {
+        authority.to_string()
+    } else {
+        format!("{}:{}", authority, DEFAULT_PORT)
+    })
+}
+
+struct Session {
+    stream: TcpStream,
+    endpoint: String,
+    timeout: Duration,
+    channel_id: u32,
+    token_id: u32,
+    sequence: u32,
+    request_id: u32,
+    auth_token: NodeId,
+}
+
+impl Session {
+    /// Opens a secure channel without security and an anonymous session.
+    async fn connect(endpoint: &str, timeout: Duration) -> Result<Self> {
+        let address = endpoint_address(endpoint)?;
+        let stream = tokio::time::timeout(timeout, TcpStream::connect(&address))
+            .await
+            .map_err(|_| anyhow!("Timed out connecting to {}", address))?
+            .with_context(|| format!("Failed to connect to {}", address))?;
+        let mut session = Self {
+            stream,
+            endpoint: endpoint.trim().to_string(),
+            timeout,
+            channel_id: 0,
+            token_id: 0,
+            sequence: 0,
+            request_id: 0,
+            auth_token: NodeId::null(),
+        };
+        session.hello().await?;
+        session.open_channel().await?;
+        session.create_session().await?;
+        Ok(session)
+    }
+
+    async fn hello(&mut self) -> Result<()> {
+        let mut body = Writer::default();
+        body.u32(0)
+            .u32(BUFFER_SIZE)
+            .u32(BUFFER_SIZE)
+            .u32(0)
+            .u32(0)
+            .string(Some(&self.endpoint));
+        self.send_frame(b"HEL", &body.buf).await?;
+        let (kind, _, payload) = self.read_frame().await?;
+        match &kind {
+            b"ACK" => Ok(()),
+            b"ERR" => Err(transport_error(&payload)),
+            _ => bail!("Expected an acknowledge from the server"),
+        }
+    }
+
+    async fn open_channel(&mut self) -> Result<()> {
+        let request_id = self.next_request_id();
+        let mut body = Writer::default();
+        body.u32(0)
+            .string(Some(SECURITY_POLICY_NONE))
+            .bytes(None)
+            .bytes(None)
+            .u32(self.next_sequence())
+            .u32(request_id)
+            .node_id(&NodeId::numeric(0, OPEN_SECURE_CHANNEL_REQUEST))
+            .request_header(&NodeId::null(), request_id)
+            .u32(0)
+            .u32(0)
+            .u32(1)
+            .bytes(None)
+            .u32(600_000);
+        self.send_frame(b"OPN", &body.buf).await?;
+        let (kind, _, payload) = self.read_frame().await?;
+        match &kind {
+            b"OPN" => {}
+            b"ERR" => return Err(transport_error(&payload)),
+            _ => bail!("Expected the secure channel to open"),
+        }
+        let mut reader = Reader::new(&payload);
+        reader.u32()?;
+        reader.string()?;
+        reader.bytes()?;
+        reader.bytes()?;
+        reader.u32()?;
+        reader.u32()?;
+        expect_type(&mut reader, OPEN_SECURE_CHANNEL_RESPONSE)?;
+        reader.response_header()?;
+        reader.u32()?;
+        self.channel_id = reader.u32()?;
+        self.token_id = reader.u32()?;
+        Ok(())
+    }
+
+    async fn create_session(&mut self) -> Result<()> {
+        let nonce: Vec<u8> = [uuid::Uuid::new_v4(), uuid::Uuid::new_v4()]
+            .iter()
+            .flat_map(|id| *id.as_bytes())
+            .collect();
+        let mut body = Writer::default();
+        body.string(Some("urn:entmoot:api-server"))
+            .string(Some("urn:entmoot"))
+            .localized_text("Entmoot API Server")
+            .u32(1)
+            .string(None)
+            .string(None)
+            .i32(0)
+            .string(None)
+            .string(Some(&self.endpoint))
+            .string(Some("entmoot-browse"))
+            .bytes(Some(&nonce))
+            .bytes(None)
+            .f64(60_000.0)
+            .u32(0);
+        let payload = self.call(CREATE_SESSION_REQUEST, body).await?;
+        let mut reader = Reader::new(&payload);
+        expect_type(&mut reader, CREATE_SESSION_RESPONSE)?;
+        reader.response_header()?;
+        reader.node_id()?;
+        self.auth_token = reader.node_id()?;
+        reader.take(8)?;
+        reader.bytes()?;
+        reader.bytes()?;
+        let policy_id = anonymous_policy(&mut reader).unwrap_or_else(|| "anonymous".to_string());
+
+        let mut identity = Writer::default();
+        identity.string(Some(&policy_id));
+        let mut body = Writer::default();
+        body.string(None)
+            .bytes(None)
+            .i32(0)
+            .i32(0)
+            .node_id(&NodeId::numeric(0, ANONYMOUS_IDENTITY_TOKEN))
+            .u8(0x01)
+            .bytes(Some(&identity.buf))
+            .string(None)
+            .bytes(None);
+        let payload = self.call(ACTIVATE_SESSION_REQUEST, body).await?;
+        let mut reader = Reader::new(&payload);
+        expect_type(&mut reader, ACTIVATE_SESSION_RESPONSE)?;
+        reader.response_header()
+    }
+
+    /// Browses the hierarchical references of `nodes`, following
+    /// continuation points. Results are in the order of `nodes`.
+    async fn browse(&mut self, nodes: &[NodeId]) -> Result<Vec<Vec<Reference>>> {
+        let mut body = Writer::default();
+        body.node_id(&NodeId::null())
+            .i64(0)
+            .u32(0)
+            .u32(1000)
+            .i32(nodes.len() as i32);
+        for node in nodes {
+            body.node_id(node)
+                .u32(0)
+                .node_id(&NodeId::numeric(0, HIERARCHICAL_REFERENCES))
+                .bool(true)
+                .u32(0)
+                .u32(0x3F);
+        }
+        let payload = self.call(BROWSE_REQUEST, body).await?;
+        let mut reader = Reader::new(&payload);
+        expect_type(&mut reader, BROWSE_RESPONSE)?;
+        reader.response_header()?;
+        let mut results = browse_results(&mut reader)?;
+        if results.len() != nodes.len() {
+            bail!(
+                "Browse returned {} results for {} nodes",
+                results.len(),
+                nodes.len()
+            );
+        }
+
+        let mut references: Vec<Vec<Reference>> = Vec::new();
+        let mut pending: Vec<(usize, Vec<u8>)> = Vec::new();
+        for (index, result) in results.drain(..).enumerate() {
+            if let Some(point) = result.continuation_point {
+                pending.push((index, point));
+            }
+            references.push(result.references);
+        }
+        while !pending.is_empty() {
+            let mut body = Writer::default();
+            body.bool(false).i32(pending.len() as i32);
+            for (_, point) in &pending {
+                body.bytes(Some(point));
+            }
+            let payload = self.call(BROWSE_NEXT_REQUEST, body).await?;
+            let mut reader = Reader::new(&payload);
+            expect_type(&mut reader, BROWSE_NEXT_RESPONSE)?;
+            reader.response_header()?;
+            let results = browse_results(&mut reader)?;
+            let mut next = Vec::new();
+            for ((index, _), result) in pending.iter().zip(results) {
+                references[*index].extend(result.references);
+                if let Some(point) = result.continuation_point {
+                    next.push((*index, point));
+                }
+            }
+            pending = next;
+        }
+        Ok(references)
+    }
+
+    /// Reads the DataType attribute of `nodes`.
+    async fn data_types(&mut self, nodes: &[NodeId]) -> Result<Vec<Option<NodeId>>> {
+        let mut body = Writer::default();
+        body.f64(0.0).u32(3).i32(nodes.len() as i32);
+        for node in nodes {
+            body.node_id(node)
+                .u32(ATTRIBUTE_DATA_TYPE)
+                .string(None)
+                .u16(0)
+                .string(None);
+        }
+        let payload = self.call(READ_REQUEST, body).await?;
+        let mut reader = Reader::new(&payload);
+        expect_type(&mut reader, READ_RESPONSE)?;
+        reader.response_header()?;
+        reader.array(|r| r.data_type_value())
+    }
+
+    /// Closes the session and the secure channel; errors are ignored, as the
+    /// connection is dropped anyway.
+    async fn close(mut self) {
+        let mut body = Writer::default();
+        body.bool(true);
+        if let Ok(payload) = self.call(CLOSE_SESSION_REQUEST, body).await {
+            let _ = expect_type(&mut Reader::new(&payload), CLOSE_SESSION_RESPONSE);
+        }
+        let request_id = self.next_request_id();
+        let mut body = Writer::default();
+        body.u32(self.channel_id)
+            .u32(self.token_id)
+            .u32(self.next_sequence())
+            .u32(request_id)
+            .node_id(&NodeId::numeric(0, CLOSE_SECURE_CHANNEL_REQUEST))
+            .request_header(&self.auth_token, request_id);
+        let _ = self.send_frame(b"CLO", &body.buf).await;
+        let _ = self.stream.shutdown().await;
+    }
+
+    /// Sends a service request and returns the response body, starting with
+    /// its type id.
+    async fn call(&mut self, type_id: u32, request: Writer) -> Result<Vec<u8>> {
+        let request_id = self.next_request_id();
+        let mut body = Writer::default();
+        body.u32(self.channel_id)
+            .u32(self.token_id)
+            .u32(self.next_sequence())
+            .u32(request_id)
+            .node_id(&NodeId::numeric(0, type_id))
+            .request_header(&self.auth_token, request_id);
+        body.buf.extend_from_slice(&request.buf);
+        self.send_frame(b"MSG", &body.buf).await?;
+
+        let mut response = Vec::new();
+        loop {
+            let (kind, chunk, payload) = self.read_frame().await?;
+            match &kind {
+                b"MSG" => {}
+                b"ERR" => return Err(transport_error(&payload)),
+                _ => bail!("Unexpected {} message", String::from_utf8_lossy(&kind)),
+            }
+            // Channel id, token id, sequence number and request id.
+            let body = payload
+                .get(16..)
+                .ok_or_else(|| anyhow!("Truncated OPC UA message"))?;
+            match chunk {
+                b'A' => {
+                    let mut reader = Reader::new(body);
+                    let status = reader.u32()?;
+                    let reason = reader.string()?.unwrap_or_default();
+                    bail!("Server aborted the response ({:#010x}) {}", status, reason);
+                }
+                b'C' | b'F' => response.extend_from_slice(body),
+                other => bail!("Unknown chunk type {}", other as char),
+            }
+            if response.len() > MAX_MESSAGE_SIZE {
+                bail!("OPC UA response exceeds {} bytes", MAX_MESSAGE_SIZE);
+            }
+            if chunk == b'F' {
+                break;
+            }
+        }
+        let mut reader = Reader::new(&response);
+        if reader.node_id()? == NodeId::numeric(0, SERVICE_FAULT) {
+            reader.response_header()?;
+            bail!("OPC UA service fault");
+        }
+        Ok(response)
+    }
+
+    async fn send_frame(&mut self, kind: &[u8; 3], body: &[u8]) -> Result<()> {
+        let mut frame = Vec::with_capacity(body.len() + 8);
+        frame.extend_from_slice(kind);
+        frame.push(b'F');
+        frame.extend_from_slice(&((body.len() + 8) as u32).to_le_bytes());
+        frame.extend_from_slice(body);
+        tokio::time::timeout(self.timeout, self.stream.write_all(&frame))
+            .await
+            .map_err(|_| anyhow!("Timed out writing to the server"))??;
+        Ok(())
+    }
+
+    /// Reads one message chunk: its type, chunk type and the rest after the header.
+    async fn read_frame(&mut self) -> Result<([u8; 3], u8, Vec<u8>)> {
+        tokio::time::timeout(self.timeout, async {
+            let mut header = [0u8; 8];
+            self.stream.read_exact(&mut header).await?;
+            let size = u32::from_le_bytes(header[4..8].try_into()?) as usize;
+            if !(8..=MAX_MESSAGE_SIZE).contains(&size) {
+                bail!("Invalid OPC UA message size {}", size);
+            }
+            let mut payload = vec![0u8; size - 8];
+            self.stream.read_exact(&mut payload).await?;
+            Ok(([header[0], header[1], header[2]], header[3], payload))
+        })
+        .await
+        .map_err(|_| anyhow!("Timed out waiting for the server"))?
+    }
+
+    fn next_sequence(&mut self) -> u32 {
+        self.sequence += 1;
+        self.sequence
+    }
+
+    fn next_request_id(&mut self) -> u32 {
+        self.request_id += 1;
+        self.request_id
+    }
+}
+
+fn expect_type(reader: &mut Reader, type_id: u32) -> Result<()> {
+    let found = reader.node_id()?;
+    if found != NodeId::numeric(0, type_id) {
+        bail!("Unexpected OPC UA response type {}", found);
+    }
+    Ok(())
+}
+
+fn transport_error(payload: &[u8]) -> anyhow::Error {
+    let mut reader = Reader::new(payload);
+    match (reader.u32(), reader.string()) {
+        (Ok(status), Ok(reason)) => anyhow!(
+            "Server rejected the connection ({:#010x}) {}",
+            status,
+            reason.unwrap_or_default()
+        ),
+        _ => anyhow!("Server rejected the connection"),
+    }
+}
+
+/// Policy id of the anonymous user token on an endpoint without security,
+/// from the endpoints of a create-session response.
+fn anonymous_policy(reader: &mut Reader) -> Option<String> {
+    let endpoints = reader
+        .array(|r| {
+            r.string()?;
+            r.string()?;
+            r.string()?;
+            r.localized_text()?;
+            r.u32()?;
+            r.string()?;
+            r.string()?;
+            r.array(|r| r.string())?;
+            r.bytes()?;
+            r.u32()?;
+            let security_policy = r.string()?.unwrap_or_default();
+            let tokens = r.array(|r| {
+                let policy_id = r.string()?.unwrap_or_default();
+                let token_type = r.u32()?;
+                r.string()?;
+                r.string()?;
+                r.string()?;
+                Ok((policy_id, token_type))
+            })?;
+            r.string()?;
+            r.u8()?;
+            Ok((security_policy, tokens))
+        })
+        .ok()?;
+    let anonymous = |(policy, tokens): &(String, Vec<(String, u32)>)| {
+        let none = policy == SECURITY_POLICY_NONE;
+        tokens
+            .iter()
+            .find(|(_, token_type)| *token_type == 0)
+            .map(|(id, _)| (none, id.clone()))
+    };
+    let mut found: Vec<(bool, String)> = endpoints.iter().filter_map(anonymous).collect();
+    found.sort_by_key(|(none, _)| !none);
+    found.into_iter().next().map(|(_, id)| id)
+}
+
+// ─── Browsing ───────────────────────────────────────────────────────────────
+
+/// A variable found while browsing, a candidate for a tag mapping.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct BrowsedVariable {
+    /// Node id in text form, usable as an OPC UA tag mapping address.
+    pub node_id: String,
+    /// Browse names from the start node, joined by `/`.
+    pub browse_path: String,
+    pub display_name: String,
+    /// Built-in type name, e.g. `Double`, or the data type's node id.
+    pub data_type: Option<String>,
+    pub tag_mapping: TagMapping,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct BrowseOutcome {
+    pub variables: Vec<BrowsedVariable>,
+    /// Objects that were browsed.
+    pub nodes_browsed: usize,
+    /// Whether the node limit cut the walk short.
+    pub truncated: bool,
+}
+
+/// Walks the address space below `start` breadth first, down to `max_depth`
+/// object levels, browsing at most `max_nodes` nodes.
+pub async fn browse(
+    endpoint: &str,
+    start: &NodeId,
+    max_depth: usize,
+    max_nodes: usize,
+    timeout: Duration,
+) -> Result<BrowseOutcome> {
+    let mut session = Session::connect(endpoint, timeout).await?;
+    let outcome = walk(&mut session, start, max_depth, max_nodes).await;
+    session.close().await;
+    outcome
+}
+
+async fn walk(
+    session: &mut Session,
+    start: &NodeId,
+    max_depth: usize,
+    max_nodes: usize,
+) -> Result<BrowseOutcome> {
+    let mut seen: HashSet<NodeId> = HashSet::from([start.clone()]);
+    let mut queue: VecDeque<(NodeId, String, usize)> =
+        VecDeque::from([(start.clone(), String::new(), 0)]);
+    let mut variables: Vec<(NodeId, BrowsedVariable)> = Vec::new();
+    let mut nodes_browsed = 0;
+    let mut truncated = false;
+
+    while !queue.is_empty() {
+        let budget = max_nodes.saturating_sub(nodes_browsed).min(BATCH_SIZE);
+        if budget == 0 {
+            truncated = true;
+            break;
+        }
+        let batch: Vec<(NodeId, String, usize)> = queue.drain(..budget.min(queue.len())).collect();
+        let ids: Vec<NodeId> = batch.iter().map(|(id, _, _)| id.clone()).collect();
+        nodes_browsed += ids.len();
+        let results = session.browse(&ids).await?;
+        for ((_, path, depth), references) in batch.into_iter().zip(results) {
+            for reference in references {
+                if !seen.insert(reference.node_id.clone()) {
+                    continue;
+                }
+                let child_path = if path.is_empty() {
+                    reference.browse_name.clone()
+                } else {
+                    format!("{}/{}", path, reference.browse_name)
+                };
+                match reference.node_class {
+                    NODE_CLASS_VARIABLE => {
+                        let node_id = match &reference.namespace_uri {
+                            Some(uri) => {
+                                let local = NodeId {
+                                    namespace: 0,
+                                    ..reference.node_id.clone()
+                                };
+                                format!("nsu={};{}", uri, local)
+                            }
+                            None => reference.node_id.to_string(),
+                        };
+                        variables.push((
+                            reference.node_id,
+                            BrowsedVariable {
+                                node_id: node_id.clone(),
+                                display_name: reference
+                                    .display_name
+                                    .unwrap_or_else(|| reference.browse_name.clone()),
+                                browse_path: child_path,
+                                data_type: None,
+                                tag_mapping: TagMapping {
+                                    protocol: ProtocolType::OpcUa,
+                                    address: node_id,
+                                },
+                            },
+                        ));
+                    }
+                    NODE_CLASS_OBJECT if depth + 1 < max_depth => {
+                        queue.push_back((reference.node_id, child_path, depth + 1));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // Data types are a convenience; a server that refuses the Read still
+    // yields the variables.
+    for chunk in variables.chunks_mut(BATCH_SIZE) {
+        let ids: Vec<NodeId> = chunk.iter().map(|(id, _)| id.clone()).collect();
+        let Ok(types) = session.data_types(&ids).await else {
+            break;
+        };
+        for ((_, variable), data_type) in chunk.iter_mut().zip(types) {
+            variable.data_type = data_type.as_ref().map(data_type_name);
+        }
+    }
+
+    Ok(BrowseOutcome {
+        variables: variables
+            .into_iter()
+            .map(|(_, variable)| variable)
+            .collect(),
+        nodes_browsed,
+        truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_ids_round_trip_through_text_and_binary() {
+        for text in [
+            "i=85",
+            "ns=2;s=Dosing.FIC101.SP",
+            "ns=300;i=70000",
+            "ns=1;g=72962b91-fa75-4ae6-8d28-b404dc7daf63",
+            "ns=3;b=AQID",
+        ] {
+            let node_id = NodeId::parse(text).unwrap();
+            assert_eq!(node_id.to_string(), text);
+            let mut writer = Writer::default();
+            writer.node_id(&node_id);
+            assert_eq!(Reader::new(&writer.buf).node_id().unwrap(), node_id);
+        }
+        let mut writer = Writer::default();
+        writer.node_id(&NodeId::numeric(0, OBJECTS_FOLDER));
+        assert_eq!(writer.buf, vec![0x00, 85]);
+        assert!(NodeId::parse("ns=x;i=1").is_none());
+        assert_eq!(
+            endpoint_address("opc.tcp://dosing/UA/Server").unwrap(),
+            "dosing:4840"
+        );
+        assert!(endpoint_address("http://dosing:4840").is_err());
+    }
+
+    #[test]
+    fn browse_results_keep_local_references_and_continuation_points() {
+        let mut writer = Writer::default();
+        writer.i32(1).u32(0).bytes(Some(b"cp")).i32(2);
+        for (node_id, server_index) in [
+            (NodeId::parse("ns=2;s=FIC101.PV").unwrap(), 0),
+            (NodeId::numeric(0, 1), 1),
+        ] {
+            writer.node_id(&NodeId::numeric(0, 47)).bool(true);
+            if server_index == 0 {
+                writer.node_id(&node_id);
+            } else {
+                writer.u8(0x40).u8(1).u32(server_index);
+            }
+            writer
+                .u16(2)
+                .string(Some("PV"))
+                .localized_text("Flow")
+                .u32(NODE_CLASS_VARIABLE)
+                .node_id(&NodeId::numeric(0, 63));
+        }
+        let results = browse_results(&mut Reader::new(&writer.buf)).unwrap();
+        assert_eq!(results[0].continuation_point.as_deref(), Some(&b"cp"[..]));
+        assert_eq!(results[0].references.len(), 1);
+        let reference = &results[0].references[0];
+        assert_eq!(reference.node_id.to_string(), "ns=2;s=FIC101.PV");
+        assert_eq!(
+            (
+                reference.browse_name.as_str(),
+                reference.display_name.as_deref()
+            ),
+            ("PV", Some("Flow"))
+        );
+        assert_eq!(data_type_name(&NodeId::numeric(0, 11)), "Double");
+    }
+}
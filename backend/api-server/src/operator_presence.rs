@@ -0,0 +1,434 @@
+use actix_web::{HttpRequest, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use shared::domain::authority::ActorClass;
+use shared::mtp::topics;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::warn;
+use zenoh::Session;
+
+use crate::alarm_grouping;
+use crate::request_context::CallerContext;
+use crate::state::AppState;
+
+/// Seconds a claim survives without a heartbeat.
+pub const DEFAULT_TTL_S: i64 = 60;
+
+/// What an operator can take ownership of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AreaKind {
+    /// A single PEA: its lifecycle, service commands and alarms.
+    Pea,
+    /// Alarms whose source contains the target, like a blackout scope.
+    Alarms,
+}
+
+impl AreaKind {
+    fn prefix(self) -> &'static str {
+        match self {
+            AreaKind::Pea => "pea",
+            AreaKind::Alarms => "alarms",
+        }
+    }
+}
+
+/// Splits an area id such as `pea:reactor-1` or `alarms:boiler-house`.
+pub fn parse_area(area: &str) -> Result<(AreaKind, String), String> {
+    let (kind, target) = area
+        .split_once(':')
+        .ok_or_else(|| "area must look like pea:<id> or alarms:<scope>".to_string())?;
+    let kind = match kind {
+        "pea" => AreaKind::Pea,
+        "alarms" => AreaKind::Alarms,
+        other => return Err(format!("Unknown area kind '{}'", other)),
+    };
+    let target = target.trim();
+    if target.is_empty() {
+        return Err("area target is required".to_string());
+    }
+    Ok((kind, target.to_string()))
+}
+
+/// An operator's ownership of an area, kept alive by heartbeats.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Claim {
+    pub area: String,
+    pub kind: AreaKind,
+    pub target: String,
+    pub actor_id: String,
+    pub actor_class: Option<ActorClass>,
+    pub claimed_at: String,
+    pub heartbeat_at: String,
+    pub expires_at: String,
+    /// Previous owner when the area was taken over rather than claimed free.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub taken_over_from: Option<String>,
+}
+
+impl Claim {
+    fn expires(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.expires_at)
+            .ok()
+            .map(|time| time.with_timezone(&Utc))
+    }
+
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.expires().is_some_and(|expires| expires > now)
+    }
+
+    /// Whether the claim covers alarms raised by `source`.
+    pub fn covers_alarm(&self, source: &str) -> bool {
+        match self.kind {
+            AreaKind::Pea => alarm_grouping::pea_of(source) == self.target,
+            AreaKind::Alarms => source.contains(&self.target),
+        }
+    }
+
+    fn topic(&self) -> String {
+        topics::operator_presence(self.kind.prefix(), &self.target)
+    }
+}
+
+#[derive(Debug)]
+pub enum PresenceError {
+    MissingActor,
+    InvalidArea(String),
+    NotClaimed,
+    /// Someone else owns the area.
+    Held(Box<Claim>),
+}
+
+impl PresenceError {
+    pub fn response(&self) -> HttpResponse {
+        match self {
+            PresenceError::MissingActor => HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Presence requires an actor id (X-Actor-Id)"
+            })),
+            PresenceError::InvalidArea(e) => {
+                HttpResponse::BadRequest().json(serde_json::json!({"error": e}))
+            }
+            PresenceError::NotClaimed => {
+                HttpResponse::NotFound().json(serde_json::json!({"error": "Area is not claimed"}))
+            }
+            PresenceError::Held(claim) => HttpResponse::Conflict().json(serde_json::json!({
+                "error": format!("{} is owned by {}", claim.area, claim.actor_id),
+                "claim": claim,
+            })),
+        }
+    }
+}
+
+/// Operator ownership of PEAs and alarm areas.
+///
+/// Claims live in memory only: they are meaningful while their owner keeps
+/// heartbeating, and lapse after `ttl` otherwise.
+#[derive(Clone)]
+pub struct Presence {
+    claims: Arc<RwLock<HashMap<String, Claim>>>,
+    ttl: Duration,
+}
+
+impl Presence {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            claims: Arc::new(RwLock::new(HashMap::new())),
+            ttl,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let ttl_s = std::env::var("PRESENCE_TTL_S")
+            .ok()
+            .and_then(|value| value.parse::<i64>().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(DEFAULT_TTL_S);
+        Self::new(Duration::seconds(ttl_s))
+    }
+
+    /// Active claims, oldest first.
+    pub async fn list(&self, now: DateTime<Utc>) -> Vec<Claim> {
+        let mut claims: Vec<Claim> = self
+            .claims
+            .read()
+            .await
+            .values()
+            .filter(|claim| claim.is_active(now))
+            .cloned()
+            .collect();
+        claims.sort_by(|a, b| a.claimed_at.cmp(&b.claimed_at));
+        claims
+    }
+
+    /// Claims `area` for the caller. An area held by someone else is only
+    /// handed over when `takeover` is set; claiming one's own area renews it.
+    pub async fn claim(
+        &self,
+        area: &str,
+        caller: &CallerContext,
+        takeover: bool,
+        now: DateTime<Utc>,
+    ) -> Result<Claim, PresenceError> {
+        let actor_id = caller.actor_id.clone().ok_or(PresenceError::MissingActor)?;
+        let (kind, target) = parse_area(area).map_err(PresenceError::InvalidArea)?;
+        let area = format!("{}:{}", kind.prefix(), target);
+        let mut claims = self.claims.write().await;
+        let previous = claims
+            .get(&area)
+            .filter(|claim| claim.is_active(now))
+            .cloned();
+        let taken_over_from = match previous {
+            Some(previous) if previous.actor_id == actor_id => previous.taken_over_from,
+            Some(previous) if takeover => Some(previous.actor_id),
+            Some(previous) => return Err(PresenceError::Held(Box::new(previous))),
+            None => None,
+        };
+        let claimed_at = claims
+            .get(&area)
+            .filter(|claim| claim.is_active(now) && claim.actor_id == actor_id)
+            .map(|claim| claim.claimed_at.clone())
+            .unwrap_or_else(|| now.to_rfc3339());
+        let claim = Claim {
+            area: area.clone(),
+            kind,
+            target,
+            actor_id,
+            actor_class: caller.actor_class.clone(),
+            claimed_at,
+            heartbeat_at: now.to_rfc3339(),
+            expires_at: (now + self.ttl).to_rfc3339(),
+            taken_over_from,
+        };
+        claims.insert(area, claim.clone());
+        Ok(claim)
+    }
+
+    /// Extends the caller's claim on `area`.
+    pub async fn heartbeat(
+        &self,
+        area: &str,
+        caller: &CallerContext,
+        now: DateTime<Utc>,
+    ) -> Result<Claim, PresenceError> {
+        let actor_id = caller
+            .actor_id
+            .as_deref()
+            .ok_or(PresenceError::MissingActor)?;
+        let mut claims = self.claims.write().await;
+        let claim = claims
+            .get_mut(area)
+            .filter(|claim| claim.is_active(now))
+            .ok_or(PresenceError::NotClaimed)?;
+        if claim.actor_id != actor_id {
+            return Err(PresenceError::Held(Box::new(claim.clone())));
+        }
+        claim.heartbeat_at = now.to_rfc3339();
+        claim.expires_at = (now + self.ttl).to_rfc3339();
+        Ok(claim.clone())
+    }
+
+    /// Gives up the caller's claim on `area`.
+    pub async fn release(
+        &self,
+        area: &str,
+        caller: &CallerContext,
+        now: DateTime<Utc>,
+    ) -> Result<Claim, PresenceError> {
+        let actor_id = caller
+            .actor_id
+            .as_deref()
+            .ok_or(PresenceError::MissingActor)?;
+        let mut claims = self.claims.write().await;
+        let claim = claims
+            .get(area)
+            .filter(|claim| claim.is_active(now))
+            .cloned()
+            .ok_or(PresenceError::NotClaimed)?;
+        if claim.actor_id != actor_id {
+            return Err(PresenceError::Held(Box::new(claim)));
+        }
+        claims.remove(area);
+        Ok(claim)
+    }
+
+    /// Drops lapsed claims and returns them.
+    pub async fn expire(&self, now: DateTime<Utc>) -> Vec<Claim> {
+        let mut claims = self.claims.write().await;
+        let expired: Vec<String> = claims
+            .iter()
+            .filter(|(_, claim)| !claim.is_active(now))
+            .map(|(area, _)| area.clone())
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|area| claims.remove(&area))
+            .collect()
+    }
+
+    /// The claim on `pea_id` when it belongs to someone other than `actor_id`.
+    pub async fn foreign_pea_claim(
+        &self,
+        pea_id: &str,
+        actor_id: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Option<Claim> {
+        self.claims
+            .read()
+            .await
+            .get(&format!("{}:{}", AreaKind::Pea.prefix(), pea_id))
+            .filter(|claim| claim.is_active(now) && Some(claim.actor_id.as_str()) != actor_id)
+            .cloned()
+    }
+
+    /// A claim covering alarms from `source` that belongs to someone other than `actor_id`.
+    pub async fn foreign_alarm_claim(
+        &self,
+        source: &str,
+        actor_id: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Option<Claim> {
+        self.claims
+            .read()
+            .await
+            .values()
+            .find(|claim| {
+                claim.is_active(now)
+                    && claim.covers_alarm(source)
+                    && Some(claim.actor_id.as_str()) != actor_id
+            })
+            .cloned()
+    }
+}
+
+/// Publishes the claim on `entmoot/presence/{kind}/{target}` for UI display.
+pub async fn publish(session: &Session, claim: &Claim) {
+    let payload = serde_json::to_string(claim).unwrap_or_else(|_| "{}".to_string());
+    if let Err(e) = session.put(claim.topic(), payload).await {
+        warn!("Failed to publish presence for {}: {}", claim.area, e);
+    }
+}
+
+/// Announces that an area is no longer owned.
+pub async fn publish_released(session: &Session, claim: &Claim) {
+    if let Err(e) = session.delete(claim.topic()).await {
+        warn!("Failed to publish release of {}: {}", claim.area, e);
+    }
+}
+
+/// Rejects control actions on a PEA claimed by another operator.
+pub async fn reject_non_owner_pea(
+    state: &AppState,
+    req: &HttpRequest,
+    pea_id: &str,
+) -> Option<HttpResponse> {
+    let caller = CallerContext::from_request(req);
+    state
+        .presence
+        .foreign_pea_claim(pea_id, caller.actor_id.as_deref(), Utc::now())
+        .await
+        .map(|claim| PresenceError::Held(Box::new(claim)).response())
+}
+
+/// Rejects actions on an alarm whose PEA or alarm area is claimed by another operator.
+pub async fn reject_non_owner_alarm(
+    state: &AppState,
+    req: &HttpRequest,
+    alarm_id: &str,
+) -> Option<HttpResponse> {
+    let source = state.alarms.read().await.get(alarm_id)?.source.clone();
+    let caller = CallerContext::from_request(req);
+    state
+        .presence
+        .foreign_alarm_claim(&source, caller.actor_id.as_deref(), Utc::now())
+        .await
+        .map(|claim| PresenceError::Held(Box::new(claim)).response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caller(actor_id: &str) -> CallerContext {
+        CallerContext {
+            actor_id: Some(actor_id.to_string()),
+            actor_class: Some(ActorClass::Operator),
+            roles: Vec::new(),
+        }
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn held_areas_need_an_explicit_takeover() {
+        let presence = Presence::new(Duration::seconds(60));
+        let now = at("2026-05-01T10:00:00Z");
+        presence
+            .claim("pea:p1", &caller("alice"), false, now)
+            .await
+            .unwrap();
+
+        let err = presence
+            .claim("pea:p1", &caller("bob"), false, now)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PresenceError::Held(claim) if claim.actor_id == "alice"));
+        assert!(presence
+            .foreign_pea_claim("p1", Some("bob"), now)
+            .await
+            .is_some());
+        assert!(presence
+            .foreign_pea_claim("p1", Some("alice"), now)
+            .await
+            .is_none());
+
+        let claim = presence
+            .claim("pea:p1", &caller("bob"), true, now)
+            .await
+            .unwrap();
+        assert_eq!(claim.actor_id, "bob");
+        assert_eq!(claim.taken_over_from.as_deref(), Some("alice"));
+        assert!(presence
+            .foreign_alarm_claim(
+                "entmoot/habitat/nodes/n1/pea/p1/swimlane/alarm",
+                Some("alice"),
+                now,
+            )
+            .await
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn claims_lapse_without_heartbeats() {
+        let presence = Presence::new(Duration::seconds(60));
+        let now = at("2026-05-01T10:00:00Z");
+        presence
+            .claim("alarms:boiler", &caller("alice"), false, now)
+            .await
+            .unwrap();
+        presence
+            .heartbeat(
+                "alarms:boiler",
+                &caller("alice"),
+                at("2026-05-01T10:00:50Z"),
+            )
+            .await
+            .unwrap();
+
+        let later = at("2026-05-01T10:01:30Z");
+        assert_eq!(presence.list(later).await.len(), 1);
+        assert!(presence.expire(later).await.is_empty());
+
+        let lapsed = at("2026-05-01T10:02:00Z");
+        assert!(presence.list(lapsed).await.is_empty());
+        assert_eq!(presence.expire(lapsed).await.len(), 1);
+        assert!(presence
+            .claim("alarms:boiler", &caller("bob"), false, lapsed)
+            .await
+            .is_ok());
+        assert!(parse_area("boiler").is_err());
+    }
+}
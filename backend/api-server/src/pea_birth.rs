@@ -0,0 +1,261 @@
+use crate::runtime_store;
+use chrono::Utc;
+use shared::mtp::{topics, PeaConfig, PeaMode};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+use tracing::{error, info, warn};
+use zenoh::liveliness::LivelinessToken;
+use zenoh::Session;
+
+pub const DEATH_UNDEPLOYED: &str = "undeployed";
+pub const DEATH_DELETED: &str = "deleted";
+
+/// Sparkplug-style birth/death certificates for deployed PEAs.
+///
+/// Zenoh has no retained messages, so the latest birth of every deployed PEA is
+/// kept here, saved to `births_path` and served by a queryable on the birth keys.
+/// Births survive a restart with their `seq`, so deaths still match them.
+///
+/// Each deployed PEA holds a liveliness token, whose deletion tells consumers of
+/// its death when it drops off the bus without publishing one. The runtime that
+/// hosts a PEA declares the token; this server only holds the tokens of simulated
+/// PEAs, which it hosts itself.
+#[derive(Clone)]
+pub struct PeaCertificates {
+    births: Arc<RwLock<HashMap<String, serde_json::Value>>>,
+    tokens: Arc<Mutex<HashMap<String, LivelinessToken>>>,
+    seq: Arc<AtomicU64>,
+    births_path: String,
+}
+
+impl PeaCertificates {
+    pub fn from_env() -> Self {
+        let births_path = std::env::var("PEA_BIRTHS_PATH")
+            .unwrap_or_else(|_| "./data/pea-births.json".to_string());
+        Self::load(births_path)
+    }
+
+    fn load(births_path: String) -> Self {
+        let births: HashMap<String, serde_json::Value> =
+            runtime_store::load_json(&births_path).unwrap_or_default();
+        let next_seq = births
+            .values()
+            .filter_map(|birth| birth.get("seq").and_then(|seq| seq.as_u64()))
+            .max()
+            .map_or(0, |seq| seq + 1);
+        Self {
+            births: Arc::new(RwLock::new(births)),
+            tokens: Arc::new(Mutex::new(HashMap::new())),
+            seq: Arc::new(AtomicU64::new(next_seq)),
+            births_path,
+        }
+    }
+
+    /// Declares the liveliness tokens of simulated PEAs whose births were
+    /// restored at startup.
+    pub async fn resume(&self, session: &Session, configs: &HashMap<String, PeaConfig>) {
+        let pea_ids: Vec<String> = self.births.read().await.keys().cloned().collect();
+        for pea_id in pea_ids {
+            match configs.get(&pea_id) {
+                Some(config) => self.declare_liveliness(session, config).await,
+                None => warn!("Restored birth of unknown PEA {}", pea_id),
+            }
+        }
+    }
+
+    pub async fn birth(&self, pea_id: &str) -> Option<serde_json::Value> {
+        self.births.read().await.get(pea_id).cloned()
+    }
+
+    /// Records and publishes the birth certificate of a freshly deployed PEA and
+    /// declares its liveliness token.
+    pub async fn publish_birth(&self, session: &Session, config: &PeaConfig) {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        let birth = birth_certificate(config, seq);
+        {
+            let mut births = self.births.write().await;
+            births.insert(config.id.clone(), birth.clone());
+            runtime_store::persist_json_file(&self.births_path, &*births);
+        }
+
+        if let Err(e) = session
+            .put(topics::pea_birth(&config.id), birth.to_string())
+            .await
+        {
+            warn!("Failed to publish birth of PEA {}: {}", config.id, e);
+        }
+
+        self.declare_liveliness(session, config).await;
+    }
+
+    async fn declare_liveliness(&self, session: &Session, config: &PeaConfig) {
+        if config.mode != PeaMode::Simulated {
+            return;
+        }
+        // Re-deploying replaces the token; the old one is undeclared on drop.
+        match session
+            .liveliness()
+            .declare_token(topics::pea_liveliness(&config.id))
+            .await
+        {
+            Ok(token) => {
+                self.tokens.lock().await.insert(config.id.clone(), token);
+            }
+            Err(e) => warn!("Failed to declare liveliness for PEA {}: {}", config.id, e),
+        }
+    }
+
+    /// Withdraws the birth certificate and publishes the death. Does nothing for
+    /// PEAs without a birth, so a death is only ever sent once.
+    pub async fn publish_death(&self, session: &Session, pea_id: &str, reason: &str) {
+        let birth = {
+            let mut births = self.births.write().await;
+            let Some(birth) = births.remove(pea_id) else {
+                return;
+            };
+            runtime_store::persist_json_file(&self.births_path, &*births);
+            birth
+        };
+        if let Some(token) = self.tokens.lock().await.remove(pea_id) {
+            if let Err(e) = token.undeclare().await {
+                warn!("Failed to undeclare liveliness for PEA {}: {}", pea_id, e);
+            }
+        }
+
+        let death = serde_json::json!({
+            "pea_id": pea_id,
+            "seq": birth.get("seq").cloned().unwrap_or_default(),
+            "reason": reason,
+            "timestamp": Utc::now().to_rfc3339(),
+        });
+        if let Err(e) = session
+            .put(topics::pea_death(pea_id), death.to_string())
+            .await
+        {
+            warn!("Failed to publish death of PEA {}: {}", pea_id, e);
+        }
+        info!("PEA {} death published ({})", pea_id, reason);
+    }
+
+    /// Answers birth queries from the retained certificates.
+    pub async fn serve(self, session: Arc<Session>) {
+        let queryable = match session.declare_queryable(topics::PEA_BIRTH_WILDCARD).await {
+            Ok(queryable) => queryable,
+            Err(e) => {
+                error!("Failed to declare PEA birth queryable: {}", e);
+                return;
+            }
+        };
+
+        while let Ok(query) = queryable.recv_async().await {
+            let births = self.births.read().await;
+            for (pea_id, birth) in births.iter() {
+                let key = topics::pea_birth(pea_id);
+                let matches = zenoh::key_expr::KeyExpr::try_from(key.as_str())
+                    .is_ok_and(|key_expr| query.key_expr().intersects(&key_expr));
+                if !matches {
+                    continue;
+                }
+                if let Err(e) = query.reply(key.as_str(), birth.to_string()).await {
+                    warn!("Failed to reply to birth query: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Config summary and capabilities announced when a PEA is deployed.
+pub fn birth_certificate(config: &PeaConfig, seq: u64) -> serde_json::Value {
+    let services: Vec<serde_json::Value> = config
+        .services
+        .iter()
+        .map(|service| {
+            serde_json::json!({
+                "tag": service.tag,
+                "name": service.name,
+                "config_parameters": service.config_parameters.len(),
+                "procedures": service.procedures.iter().map(|procedure| serde_json::json!({
+                    "id": procedure.id,
+                    "name": procedure.name,
+                    "is_default": procedure.is_default,
+                    "is_self_completing": procedure.is_self_completing,
+                })).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    let active_elements: Vec<serde_json::Value> = config
+        .active_elements
+        .iter()
+        .filter_map(|element| {
+            let value = serde_json::to_value(element).ok()?;
+            Some(serde_json::json!({
+                "tag": value.get("tag")?,
+                "element_type": value.get("element_type")?,
+            }))
+        })
+        .collect();
+
+    serde_json::json!({
+        "pea_id": config.id,
+        "seq": seq,
+        "name": config.name,
+        "version": config.version,
+        "description": config.description,
+        "writer": config.writer,
+        "opcua_endpoint": config.opcua_config.endpoint,
+        "capabilities": {
+            "services": services,
+            "active_elements": active_elements,
+        },
+        "topics": {
+            "status": topics::pea_status(&config.id),
+            "death": topics::pea_death(&config.id),
+            "liveliness": topics::pea_liveliness(&config.id),
+        },
+        "updated_at": config.updated_at.to_rfc3339(),
+        "timestamp": Utc::now().to_rfc3339(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{pea_config, SERVICE_TAG};
+
+    #[test]
+    fn birth_certificate_summarises_services_and_procedures() {
+        let config = PeaConfig {
+            version: "2.1.0".to_string(),
+            ..pea_config("pea-1")
+        };
+
+        let birth = birth_certificate(&config, 7);
+        assert_eq!(birth["seq"], 7);
+        assert_eq!(birth["version"], "2.1.0");
+        let service = &birth["capabilities"]["services"][0];
+        assert_eq!(service["tag"], SERVICE_TAG);
+        assert_eq!(service["procedures"][0]["is_default"], true);
+        assert!(birth["topics"]["death"]
+            .as_str()
+            .unwrap()
+            .ends_with("/pea/pea-1/death"));
+    }
+
+    #[tokio::test]
+    async fn restored_births_keep_their_seq() {
+        let path = std::env::temp_dir().join(format!("pea-births-{}.json", uuid::Uuid::new_v4()));
+        let path = path.to_string_lossy().to_string();
+        let births = HashMap::from([(
+            "pea-1".to_string(),
+            serde_json::json!({"pea_id": "pea-1", "seq": 4}),
+        )]);
+        runtime_store::persist_json_file(&path, &births);
+
+        let certificates = PeaCertificates::load(path.clone());
+        assert_eq!(certificates.birth("pea-1").await.unwrap()["seq"], 4);
+        assert_eq!(certificates.seq.load(Ordering::Relaxed), 5);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,145 @@
+use actix_web::HttpResponse;
+use serde::Serialize;
+use shared::mtp::{ActiveElement, PeaConfig};
+
+/// Upper bound on PEAs acted on by one bulk request.
+pub const MAX_BULK_PEAS: usize = 200;
+
+/// One criterion of a `?filter=` selector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Criterion {
+    /// PEA name starts with this, ignoring case.
+    NamePrefix(String),
+    /// A service or active element of the PEA has this tag, ignoring case.
+    Tag(String),
+}
+
+/// Selects PEAs for a bulk request: comma-separated `name:<prefix>` and
+/// `tag:<tag>` criteria that must all match. A criterion without a key is a
+/// name prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeaFilter {
+    criteria: Vec<Criterion>,
+}
+
+impl PeaFilter {
+    pub fn parse(selector: &str) -> Result<Self, String> {
+        let criteria = selector
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(|part| {
+                let (key, value) = part.split_once(':').unwrap_or(("name", part));
+                let value = value.trim().to_lowercase();
+                if value.is_empty() {
+                    return Err(format!("Filter '{}' has no value", part));
+                }
+                match key.trim() {
+                    "name" => Ok(Criterion::NamePrefix(value)),
+                    "tag" => Ok(Criterion::Tag(value)),
+                    other => Err(format!(
+                        "Unknown filter '{}'; use name:<prefix> or tag:<tag>",
+                        other
+                    )),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if criteria.is_empty() {
+            return Err("filter must not be empty".to_string());
+        }
+        Ok(Self { criteria })
+    }
+
+    pub fn matches(&self, config: &PeaConfig) -> bool {
+        self.criteria.iter().all(|criterion| match criterion {
+            Criterion::NamePrefix(prefix) => config.name.to_lowercase().starts_with(prefix),
+            Criterion::Tag(tag) => config
+                .services
+                .iter()
+                .map(|service| service.tag.as_str())
+                .chain(config.active_elements.iter().map(element_tag))
+                .any(|candidate| candidate.eq_ignore_ascii_case(tag)),
+        })
+    }
+}
+
+fn element_tag(element: &ActiveElement) -> &str {
+    match element {
+        ActiveElement::BinVlv(v) => &v.tag,
+        ActiveElement::BinMon(v) => &v.tag,
+        ActiveElement::AnaVlv(v) => &v.tag,
+        ActiveElement::BinDrv(v) => &v.tag,
+        ActiveElement::AnaDrv(v) => &v.tag,
+        ActiveElement::DIntDrv(v) => &v.tag,
+        ActiveElement::DIntMon(v) => &v.tag,
+        ActiveElement::PIDCtrl(v) => &v.tag,
+    }
+}
+
+/// Outcome for one PEA of a bulk request.
+#[derive(Debug, Serialize)]
+pub struct BulkResult {
+    pub pea_id: String,
+    /// HTTP status the single-PEA endpoint would have answered with.
+    pub status: u16,
+    pub ok: bool,
+    /// That endpoint's response body.
+    pub body: serde_json::Value,
+}
+
+impl BulkResult {
+    pub async fn from_response(pea_id: String, response: HttpResponse) -> Self {
+        let status = response.status();
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or(serde_json::Value::Null);
+        Self {
+            pea_id,
+            status: status.as_u16(),
+            ok: status.is_success(),
+            body,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{bin_mon, pea_config};
+
+    fn pea(name: &str) -> PeaConfig {
+        PeaConfig {
+            name: name.to_string(),
+            active_elements: vec![bin_mon("P1_RUN")],
+            ..pea_config("p1")
+        }
+    }
+
+    #[test]
+    fn filters_match_name_prefixes_and_tags() {
+        let filter = PeaFilter::parse("Feed").unwrap();
+        assert!(filter.matches(&pea("feed pump 1")));
+        assert!(!filter.matches(&pea("Dosing")));
+
+        let filter = PeaFilter::parse("name:feed, tag:p1_run").unwrap();
+        assert!(filter.matches(&pea("Feed Pump")));
+        assert!(!PeaFilter::parse("tag:P2_RUN")
+            .unwrap()
+            .matches(&pea("Feed Pump")));
+
+        assert!(PeaFilter::parse("").is_err());
+        assert!(PeaFilter::parse("tag:").is_err());
+        assert!(PeaFilter::parse("mode:simulated").is_err());
+    }
+
+    #[actix_web::test]
+    async fn results_carry_the_single_pea_response() {
+        let response = HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"}));
+        let result = BulkResult::from_response("p1".to_string(), response).await;
+        assert_eq!(result.status, 404);
+        assert!(!result.ok);
+        assert_eq!(result.body["error"], "PEA not found");
+    }
+}
@@ -0,0 +1,242 @@
+use serde::Serialize;
+use shared::mtp::units;
+use shared::mtp::{
+    DisplayHints, PeaConfig, PeaMode, ServiceCommand, ServiceParameter, ServiceState,
+};
+use std::collections::BTreeMap;
+
+use crate::state::TimeSeriesStore;
+use crate::state_analytics;
+
+/// What a PEA offers and what it accepts right now: the services, procedures
+/// and parameter ranges of its config, with each service's live state.
+#[derive(Debug, Serialize)]
+pub struct Capabilities {
+    pub pea_id: String,
+    pub name: String,
+    pub mode: PeaMode,
+    pub services: Vec<ServiceCapabilities>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServiceCapabilities {
+    pub tag: String,
+    pub name: String,
+    pub description: String,
+    /// `None` until the PEA has reported the service.
+    pub current_state: Option<ServiceState>,
+    /// Commands the PackML state machine allows from `current_state`; `None`
+    /// while the state is unknown, when commands are not checked.
+    pub allowed_commands: Option<Vec<ServiceCommand>>,
+    pub config_parameters: Vec<ParameterCapabilities>,
+    pub procedures: Vec<ProcedureCapabilities>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProcedureCapabilities {
+    pub id: u32,
+    pub name: String,
+    pub is_default: bool,
+    pub is_self_completing: bool,
+    pub parameters: Vec<ParameterCapabilities>,
+}
+
+/// A parameter as configured, plus how HMIs show it.
+#[derive(Debug, Serialize)]
+pub struct ParameterCapabilities {
+    #[serde(flatten)]
+    pub parameter: ServiceParameter,
+    /// `None` for binary and string parameters.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_display: Option<ResolvedDisplay>,
+}
+
+/// A numeric parameter's display hints applied: its limits and default in the
+/// display unit, and the conversion from stored values,
+/// `shown = value * factor + offset`.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ResolvedDisplay {
+    pub unit: String,
+    pub factor: f64,
+    pub offset: f64,
+    pub decimals: Option<u32>,
+    pub v_min: f64,
+    pub v_max: f64,
+    pub v_default: f64,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<i64, String>,
+}
+
+fn parameter(parameter: &ServiceParameter) -> ParameterCapabilities {
+    let resolved_display = match parameter {
+        ServiceParameter::Analog(p) => Some(resolve(
+            &p.unit,
+            p.display.as_ref(),
+            [p.v_min, p.v_max, p.v_default],
+            None,
+        )),
+        ServiceParameter::DInt(p) => Some(resolve(
+            &p.unit,
+            p.display.as_ref(),
+            [p.v_min as f64, p.v_max as f64, p.v_default as f64],
+            Some(0),
+        )),
+        ServiceParameter::Binary(_) | ServiceParameter::StringParam(_) => None,
+    };
+    ParameterCapabilities {
+        parameter: parameter.clone(),
+        resolved_display,
+    }
+}
+
+/// Applies `hints` to a parameter stored in `unit`. A display unit that
+/// cannot be converted to, as in configs stored before it was validated,
+/// falls back to the stored unit.
+fn resolve(
+    unit: &str,
+    hints: Option<&DisplayHints>,
+    [v_min, v_max, v_default]: [f64; 3],
+    default_decimals: Option<u32>,
+) -> ResolvedDisplay {
+    let hints = hints.cloned().unwrap_or_default();
+    let (unit, (factor, offset)) = hints
+        .unit
+        .as_deref()
+        .and_then(|shown| Some((shown, units::conversion(unit, shown)?)))
+        .unwrap_or((unit, (1.0, 0.0)));
+    let show = |value: f64| value * factor + offset;
+    ResolvedDisplay {
+        unit: unit.to_string(),
+        factor,
+        offset,
+        decimals: hints.decimals.or(default_decimals),
+        v_min: show(v_min),
+        v_max: show(v_max),
+        v_default: show(v_default),
+        labels: hints.labels,
+    }
+}
+
+/// Merges `config` with the latest status the PEA published.
+pub fn capabilities(config: &PeaConfig, timeseries: &TimeSeriesStore) -> Capabilities {
+    let services = config
+        .services
+        .iter()
+        .map(|service| {
+            let current_state =
+                state_analytics::current_service_state(timeseries, &config.id, &service.tag);
+            ServiceCapabilities {
+                tag: service.tag.clone(),
+                name: service.name.clone(),
+                description: service.description.clone(),
+                current_state,
+                allowed_commands: current_state.map(|state| state.allowed_commands()),
+                config_parameters: service.config_parameters.iter().map(parameter).collect(),
+                procedures: service
+                    .procedures
+                    .iter()
+                    .map(|procedure| ProcedureCapabilities {
+                        id: procedure.id,
+                        name: procedure.name.clone(),
+                        is_default: procedure.is_default,
+                        is_self_completing: procedure.is_self_completing,
+                        parameters: procedure.parameters.iter().map(parameter).collect(),
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    Capabilities {
+        pea_id: config.id.clone(),
+        name: config.name.clone(),
+        mode: config.mode,
+        services,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::TimeSeriesPoint;
+    use shared::mtp::{AnalogParameter, ProcedureConfig, ServiceConfig};
+
+    fn service(tag: &str) -> ServiceConfig {
+        ServiceConfig {
+            tag: tag.to_string(),
+            name: tag.to_string(),
+            description: String::new(),
+            config_parameters: Vec::new(),
+            procedures: vec![ProcedureConfig {
+                id: 1,
+                name: "Heat".to_string(),
+                is_self_completing: false,
+                is_default: true,
+                parameters: vec![ServiceParameter::Analog(AnalogParameter {
+                    tag: "setpoint".to_string(),
+                    name: "Setpoint".to_string(),
+                    unit: "degC".to_string(),
+                    v_scl_min: 0.0,
+                    v_scl_max: 200.0,
+                    v_min: 20.0,
+                    v_max: 150.0,
+                    v_default: 60.0,
+                    tag_mapping: None,
+                    display: Some(DisplayHints {
+                        decimals: Some(1),
+                        unit: Some("degF".to_string()),
+                        labels: BTreeMap::new(),
+                    }),
+                })],
+                process_value_outs: Vec::new(),
+                report_values: Vec::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn live_state_gates_the_commands_of_reported_services() {
+        let mut config: PeaConfig = serde_json::from_value(serde_json::json!({
+            "id": "reactor",
+            "name": "Reactor",
+            "version": "1",
+            "description": "",
+            "writer": {"name": "", "version": "", "vendor": ""},
+            "services": [],
+            "active_elements": [],
+            "opcua_config": {"endpoint": "", "namespace_uri": "", "security_policy": ""},
+            "created_at": "2026-01-01T00:00:00Z",
+            "updated_at": "2026-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        config.services = vec![service("heating"), service("dosing")];
+        let mut timeseries = TimeSeriesStore::new(10);
+        timeseries.insert_point(
+            shared::mtp::topics::pea_status("reactor"),
+            TimeSeriesPoint {
+                producer: None,
+                timestamp_ms: 1,
+                value: serde_json::json!({"services": [{"tag": "heating", "state": "Idle"}]}),
+            },
+        );
+
+        let capabilities = capabilities(&config, &timeseries);
+
+        let heating = &capabilities.services[0];
+        assert_eq!(heating.current_state, Some(ServiceState::Idle));
+        assert_eq!(
+            heating.allowed_commands,
+            Some(vec![ServiceCommand::Start, ServiceCommand::Abort])
+        );
+        let setpoint = heating.procedures[0].parameters[0]
+            .resolved_display
+            .as_ref()
+            .unwrap();
+        assert_eq!(setpoint.unit, "degF");
+        assert_eq!(setpoint.decimals, Some(1));
+        assert!((setpoint.v_default - 140.0).abs() < 1e-9);
+        assert!((setpoint.v_max - 302.0).abs() < 1e-9);
+        let dosing = &capabilities.services[1];
+        assert_eq!(dosing.current_state, None);
+        assert_eq!(dosing.allowed_commands, None);
+    }
+}
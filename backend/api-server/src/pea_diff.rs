@@ -0,0 +1,305 @@
+use serde::Serialize;
+use shared::mtp::PeaConfig;
+
+/// PEA fields that are not compared: identity, bookkeeping and the parts
+/// diffed item by item.
+const SKIPPED_PEA_FIELDS: &[&str] = &[
+    "id",
+    "created_at",
+    "updated_at",
+    "tenant_id",
+    "archived_at",
+    "services",
+    "active_elements",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServiceDiff {
+    pub tag: String,
+    pub change: Change,
+    pub fields: Vec<FieldChange>,
+    pub config_parameters: Vec<ParameterDiff>,
+    pub procedures: Vec<ProcedureDiff>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProcedureDiff {
+    pub id: u64,
+    pub change: Change,
+    pub fields: Vec<FieldChange>,
+    pub parameters: Vec<ParameterDiff>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParameterDiff {
+    pub tag: String,
+    pub change: Change,
+    /// Changed fields, such as `v_max` or `tag_mapping`; empty when the
+    /// parameter was added or removed.
+    pub fields: Vec<FieldChange>,
+}
+
+/// Differences from `before` to `after`, leaving out what is unchanged.
+#[derive(Debug, Serialize)]
+pub struct ConfigDiff {
+    pub fields: Vec<FieldChange>,
+    pub services: Vec<ServiceDiff>,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty() && self.services.is_empty()
+    }
+}
+
+/// Compares two PEA configs. Services and parameters are matched by tag and
+/// procedures by id, so reordering alone is not a change.
+pub fn diff(before: &PeaConfig, after: &PeaConfig) -> ConfigDiff {
+    let before = serde_json::to_value(before).unwrap_or_default();
+    let after = serde_json::to_value(after).unwrap_or_default();
+    let services = pair(&before["services"], &after["services"], |s| {
+        s["tag"].as_str().map(str::to_string)
+    })
+    .into_iter()
+    .filter_map(|(tag, old, new)| {
+        let (change, old, new) = classify(old, new);
+        let config_parameters =
+            parameter_diffs(&old["config_parameters"], &new["config_parameters"]);
+        let procedures: Vec<ProcedureDiff> = pair(&old["procedures"], &new["procedures"], |p| {
+            p["id"].as_u64().map(|id| id.to_string())
+        })
+        .into_iter()
+        .filter_map(|(_, old, new)| {
+            let (change, old, new) = classify(old, new);
+            let id = old["id"]
+                .as_u64()
+                .or(new["id"].as_u64())
+                .unwrap_or_default();
+            let fields = changed_fields(&old, &new, &["id", "parameters"]);
+            let parameters = parameter_diffs(&old["parameters"], &new["parameters"]);
+            (change != Change::Changed || !fields.is_empty() || !parameters.is_empty()).then_some(
+                ProcedureDiff {
+                    id,
+                    change,
+                    fields: only_if_changed(change, fields),
+                    parameters,
+                },
+            )
+        })
+        .collect();
+        let fields = changed_fields(&old, &new, &["tag", "config_parameters", "procedures"]);
+        let unchanged = change == Change::Changed
+            && fields.is_empty()
+            && config_parameters.is_empty()
+            && procedures.is_empty();
+        (!unchanged).then_some(ServiceDiff {
+            tag,
+            change,
+            fields: only_if_changed(change, fields),
+            config_parameters,
+            procedures,
+        })
+    })
+    .collect();
+
+    ConfigDiff {
+        fields: changed_fields(&before, &after, SKIPPED_PEA_FIELDS),
+        services,
+    }
+}
+
+fn parameter_diffs(before: &serde_json::Value, after: &serde_json::Value) -> Vec<ParameterDiff> {
+    pair(before, after, |p| p["tag"].as_str().map(str::to_string))
+        .into_iter()
+        .filter_map(|(tag, old, new)| {
+            let (change, old, new) = classify(old, new);
+            let fields = changed_fields(&old, &new, &["tag"]);
+            (change != Change::Changed || !fields.is_empty()).then_some(ParameterDiff {
+                tag,
+                change,
+                fields: only_if_changed(change, fields),
+            })
+        })
+        .collect()
+}
+
+/// Items of two JSON arrays matched by key: those of `after` in order, then
+/// the ones only in `before`.
+fn pair<'a>(
+    before: &'a serde_json::Value,
+    after: &'a serde_json::Value,
+    key: impl Fn(&serde_json::Value) -> Option<String>,
+) -> Vec<(
+    String,
+    Option<&'a serde_json::Value>,
+    Option<&'a serde_json::Value>,
+)> {
+    let keyed = |items: &'a serde_json::Value| {
+        let items = items.as_array().map(Vec::as_slice).unwrap_or_default();
+        items
+            .iter()
+            .filter_map(|item| key(item).map(|k| (k, item)))
+            .collect::<Vec<_>>()
+    };
+    let (before, after) = (keyed(before), keyed(after));
+    let find = |items: &[(String, &'a serde_json::Value)], k: &str| {
+        items
+            .iter()
+            .find(|(key, _)| key == k)
+            .map(|(_, item)| *item)
+    };
+    let mut pairs: Vec<_> = after
+        .iter()
+        .map(|(k, item)| (k.clone(), find(&before, k), Some(*item)))
+        .collect();
+    pairs.extend(
+        before
+            .iter()
+            .filter(|(k, _)| find(&after, k).is_none())
+            .map(|(k, item)| (k.clone(), Some(*item), None)),
+    );
+    pairs
+}
+
+fn classify(
+    old: Option<&serde_json::Value>,
+    new: Option<&serde_json::Value>,
+) -> (Change, serde_json::Value, serde_json::Value) {
+    let change = match (old, new) {
+        (None, _) => Change::Added,
+        (_, None) => Change::Removed,
+        _ => Change::Changed,
+    };
+    (
+        change,
+        old.cloned().unwrap_or_default(),
+        new.cloned().unwrap_or_default(),
+    )
+}
+
+/// Top-level fields of two JSON objects that differ.
+fn changed_fields(
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+    skip: &[&str],
+) -> Vec<FieldChange> {
+    let mut fields: Vec<&String> = before
+        .as_object()
+        .into_iter()
+        .chain(after.as_object())
+        .flat_map(|object| object.keys())
+        .filter(|field| !skip.contains(&field.as_str()))
+        .collect();
+    fields.sort();
+    fields.dedup();
+    fields
+        .into_iter()
+        .filter(|field| before[field.as_str()] != after[field.as_str()])
+        .map(|field| FieldChange {
+            field: field.clone(),
+            before: before[field.as_str()].clone(),
+            after: after[field.as_str()].clone(),
+        })
+        .collect()
+}
+
+/// Field changes are only listed for items present on both sides.
+fn only_if_changed(change: Change, fields: Vec<FieldChange>) -> Vec<FieldChange> {
+    if change == Change::Changed {
+        fields
+    } else {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::pea_config;
+
+    fn config(services: serde_json::Value) -> PeaConfig {
+        PeaConfig {
+            services: serde_json::from_value(services).unwrap(),
+            ..pea_config("reactor")
+        }
+    }
+
+    fn service(tag: &str, v_max: f64, procedures: &[u32]) -> serde_json::Value {
+        serde_json::json!({
+            "tag": tag,
+            "name": tag,
+            "description": "",
+            "config_parameters": [],
+            "procedures": procedures.iter().map(|id| serde_json::json!({
+                "id": id, "name": format!("P{}", id),
+                "is_self_completing": false, "is_default": *id == 1,
+                "parameters": [{
+                    "type": "Analog", "tag": "SP", "name": "Setpoint", "unit": "°C",
+                    "v_scl_min": 0.0, "v_scl_max": 150.0, "v_min": 0.0, "v_max": v_max,
+                    "v_default": 50.0, "tag_mapping": null
+                }],
+                "process_value_outs": [], "report_values": []
+            })).collect::<Vec<_>>()
+        })
+    }
+
+    #[test]
+    fn changes_are_matched_by_tag_and_procedure_id() {
+        let before = config(serde_json::json!([
+            service("Heat", 95.0, &[1, 2]),
+            service("Mix", 95.0, &[1])
+        ]));
+        let mut after = config(serde_json::json!([
+            service("Mix", 95.0, &[1]),
+            service("Heat", 120.0, &[1]),
+            service("Dose", 95.0, &[1])
+        ]));
+        after.version = "1.1.0".to_string();
+        after.updated_at = chrono::Utc::now();
+
+        let diff = diff(&before, &after);
+        assert_eq!(
+            diff.fields,
+            vec![FieldChange {
+                field: "version".to_string(),
+                before: serde_json::json!("1.0.0"),
+                after: serde_json::json!("1.1.0"),
+            }]
+        );
+        let services: Vec<(&str, Change)> = diff
+            .services
+            .iter()
+            .map(|s| (s.tag.as_str(), s.change))
+            .collect();
+        assert_eq!(
+            services,
+            vec![("Heat", Change::Changed), ("Dose", Change::Added)]
+        );
+
+        let heat = &diff.services[0];
+        let procedures: Vec<(u64, Change)> =
+            heat.procedures.iter().map(|p| (p.id, p.change)).collect();
+        assert_eq!(procedures, vec![(1, Change::Changed), (2, Change::Removed)]);
+        let setpoint = &heat.procedures[0].parameters[0];
+        assert_eq!(setpoint.tag, "SP");
+        assert_eq!(setpoint.fields[0].field, "v_max");
+        assert_eq!(setpoint.fields[0].after, serde_json::json!(120.0));
+
+        assert!(super::diff(&before, &before).is_empty());
+    }
+}
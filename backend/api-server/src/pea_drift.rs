@@ -0,0 +1,209 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use tracing::info;
+
+use crate::pea_handlers;
+use crate::pea_lifecycle::Phase;
+use crate::state::AppState;
+use crate::tenancy::TenantScope;
+
+pub const DRIFT_NO_STATUS: &str = "no_status";
+pub const DRIFT_NOT_DEPLOYED: &str = "not_deployed";
+pub const DRIFT_NOT_RUNNING: &str = "not_running";
+pub const DRIFT_UNEXPECTEDLY_DEPLOYED: &str = "unexpectedly_deployed";
+pub const DRIFT_UNEXPECTEDLY_RUNNING: &str = "unexpectedly_running";
+
+/// A PEA whose observed state differs from the state last requested for it.
+#[derive(Clone, Debug, Serialize)]
+pub struct PeaDrift {
+    pub pea_id: String,
+    pub kind: String,
+    pub desired: Phase,
+    /// `None` when no status has been seen since the server started.
+    pub actual: Option<Phase>,
+    pub observed_at_ms: Option<i64>,
+}
+
+/// What was done about one drifted PEA.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReconcileAction {
+    pub pea_id: String,
+    pub kind: String,
+    /// Commands re-issued, e.g. `["deploy", "start"]`; empty when the drift
+    /// needs an operator.
+    pub commands: Vec<String>,
+}
+
+/// Outcome of the last reconciliation run.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReconcileReport {
+    /// `startup`, `api` or `desired_state`.
+    pub trigger: String,
+    pub at: String,
+    pub actions: Vec<ReconcileAction>,
+}
+
+/// How `actual` differs from `desired`, if it does.
+pub fn compare(desired: Phase, actual: Option<Phase>) -> Option<&'static str> {
+    let Some(actual) = actual else {
+        return desired.deployed.then_some(DRIFT_NO_STATUS);
+    };
+    if desired.deployed && !actual.deployed {
+        Some(DRIFT_NOT_DEPLOYED)
+    } else if desired.running && !actual.running {
+        Some(DRIFT_NOT_RUNNING)
+    } else if !desired.deployed && actual.deployed {
+        Some(DRIFT_UNEXPECTEDLY_DEPLOYED)
+    } else if !desired.running && actual.running {
+        Some(DRIFT_UNEXPECTEDLY_RUNNING)
+    } else {
+        None
+    }
+}
+
+/// Commands that bring a PEA from `actual` towards `desired`. Deploy and start
+/// are always re-issued; stop and undeploy only when `converge` is set, otherwise
+/// PEAs running against intent are left to an operator.
+pub fn commands_for(desired: Phase, actual: Option<Phase>, converge: bool) -> Vec<&'static str> {
+    let actual = actual.unwrap_or_default();
+    let mut commands = Vec::new();
+    if desired.deployed && !actual.deployed {
+        commands.push("deploy");
+    }
+    if desired.running && !actual.running {
+        commands.push("start");
+    }
+    if converge && !desired.running && actual.running {
+        commands.push("stop");
+    }
+    if converge && !desired.deployed && actual.deployed {
+        commands.push("undeploy");
+    }
+    commands
+}
+
+/// Compares the desired phase of every PEA visible in `scope` with its latest
+/// status in the time-series store. PEAs missing from `desired` are compared
+/// against `default`, or skipped when it is `None`.
+pub async fn detect(
+    state: &AppState,
+    scope: &TenantScope,
+    desired: &HashMap<String, Phase>,
+    default: Option<Phase>,
+) -> Vec<PeaDrift> {
+    let pea_ids: Vec<String> = state
+        .pea_configs
+        .read()
+        .await
+        .values()
+        .filter(|config| scope.allows(config.tenant_id.as_deref()))
+        .map(|config| config.id.clone())
+        .collect();
+
+    let store = state.timeseries.read().await;
+    let mut drifts = Vec::new();
+    for pea_id in pea_ids {
+        let suffix = format!("/pea/{}/status", pea_id);
+        let latest = store
+            .data
+            .iter()
+            .filter(|(key, _)| key.ends_with(&suffix))
+            .filter_map(|(_, buf)| buf.back())
+            .max_by_key(|point| point.timestamp_ms);
+        let actual = latest.and_then(|point| Phase::from_status(&point.value));
+        let Some(desired) = desired.get(&pea_id).copied().or(default) else {
+            continue;
+        };
+        if let Some(kind) = compare(desired, actual) {
+            drifts.push(PeaDrift {
+                pea_id,
+                kind: kind.to_string(),
+                desired,
+                actual,
+                observed_at_ms: latest.map(|point| point.timestamp_ms),
+            });
+        }
+    }
+    drifts.sort_by(|a, b| a.pea_id.cmp(&b.pea_id));
+    drifts
+}
+
+/// Re-issues commands for the given drifts (see [`commands_for`]) and keeps the
+/// report for `GET /pea/drift`.
+pub async fn reconcile(
+    state: &AppState,
+    drifts: &[PeaDrift],
+    trigger: &str,
+    converge: bool,
+) -> ReconcileReport {
+    let mut actions = Vec::new();
+    for drift in drifts {
+        let commands = commands_for(drift.desired, drift.actual, converge);
+        let config = state.pea_configs.read().await.get(&drift.pea_id).cloned();
+        if let Some(config) = config.filter(|_| !commands.is_empty()) {
+            for command in &commands {
+                match *command {
+                    "deploy" => pea_handlers::publish_deploy_command(state, &config).await,
+                    "undeploy" => pea_handlers::publish_undeploy_command(state, &config.id).await,
+                    _ => pea_handlers::publish_lifecycle_command(state, &config.id, command).await,
+                }
+            }
+            info!(
+                "Reconciled PEA {} ({}): re-issued {}",
+                drift.pea_id,
+                drift.kind,
+                commands.join(", ")
+            );
+        }
+        actions.push(ReconcileAction {
+            pea_id: drift.pea_id.clone(),
+            kind: drift.kind.clone(),
+            commands: commands.into_iter().map(str::to_string).collect(),
+        });
+    }
+    let report = ReconcileReport {
+        trigger: trigger.to_string(),
+        at: chrono::Utc::now().to_rfc3339(),
+        actions,
+    };
+    *state.pea_reconcile_report.write().await = Some(report.clone());
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drift_kinds_and_reconcile_commands() {
+        assert_eq!(compare(Phase::RUNNING, None), Some(DRIFT_NO_STATUS));
+        assert_eq!(compare(Phase::UNDEPLOYED, None), None);
+        assert_eq!(
+            compare(Phase::RUNNING, Some(Phase::UNDEPLOYED)),
+            Some(DRIFT_NOT_DEPLOYED)
+        );
+        assert_eq!(
+            compare(Phase::RUNNING, Some(Phase::IDLE)),
+            Some(DRIFT_NOT_RUNNING)
+        );
+        assert_eq!(
+            compare(Phase::IDLE, Some(Phase::RUNNING)),
+            Some(DRIFT_UNEXPECTEDLY_RUNNING)
+        );
+        assert_eq!(compare(Phase::IDLE, Some(Phase::IDLE)), None);
+
+        assert_eq!(
+            commands_for(Phase::RUNNING, None, false),
+            vec!["deploy", "start"]
+        );
+        assert_eq!(
+            commands_for(Phase::RUNNING, Some(Phase::IDLE), false),
+            vec!["start"]
+        );
+        assert!(commands_for(Phase::UNDEPLOYED, Some(Phase::RUNNING), false).is_empty());
+        assert_eq!(
+            commands_for(Phase::UNDEPLOYED, Some(Phase::RUNNING), true),
+            vec!["stop", "undeploy"]
+        );
+    }
+}
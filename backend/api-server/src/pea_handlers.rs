@@ -1,93 +1,1073 @@
+use crate::approval_handlers::{self, hold_for_approval};
+use crate::approval_service::GuardedAction;
+use crate::audit;
+use crate::config_store;
+use crate::interlock_service;
+use crate::opcua;
+use crate::operator_presence::reject_non_owner_pea;
+use crate::pea_bulk;
+use crate::pea_capabilities;
+use crate::pea_diff;
+use crate::pea_impact;
+use crate::pea_importer;
+use crate::pea_lifecycle::{self, Phase};
+use crate::pea_registry::RegisteredPea;
+use crate::pea_revisions;
+use crate::pea_tag_mappings::{self, TagMappingUpdate};
+use crate::pea_templates::{self, StampRequest};
+use crate::pea_validation;
+use crate::recipe_campaign::{self, Campaign, CampaignRequest};
+use crate::recipe_triggers;
+use crate::request_context::CallerContext;
+use crate::service_command::{self, CommandSource};
+use crate::service_parameters;
 use crate::state::AppState;
-use actix_web::{web, HttpResponse, Responder};
+use crate::state_analytics;
+use crate::tenancy::{self, TenantScope};
+use crate::webhook_service;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use chrono::Utc;
 use serde::Deserialize;
-use shared::mtp::{PeaConfig, Recipe, ServiceCommand, ServiceState};
-use std::time::Duration;
+use shared::mtp::{PeaConfig, PeaMode, Recipe, RecipeStep, ServiceCommand};
+use std::collections::BTreeMap;
 use tracing::{error, info};
 use uuid::Uuid;
 
 // ─── PEA Configuration CRUD ─────────────────────────────────────────────────
 
-pub async fn list_peas(state: web::Data<AppState>) -> impl Responder {
-    let configs = state.pea_configs.read().await;
-    let peas: Vec<&PeaConfig> = configs.values().collect();
-    HttpResponse::Ok().json(peas)
+const DEFAULT_PEA_PAGE_SIZE: usize = 50;
+const MAX_PEA_PAGE_SIZE: usize = 500;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListPeasQuery {
+    /// List deleted PEAs that can be restored instead of the active ones.
+    #[serde(default)]
+    pub archived: bool,
+    /// 1-based page number (default: 1)
+    pub page: Option<usize>,
+    /// PEAs per page (default: 50, at most 500)
+    pub page_size: Option<usize>,
+    /// Only PEAs whose name contains this text, ignoring case.
+    pub name_contains: Option<String>,
+    /// Only PEAs offering a service with this tag.
+    pub service_tag: Option<String>,
+    /// `name`, `id`, `created_at` or `updated_at`; a leading `-` sorts
+    /// descending (default: `name`).
+    pub sort_by: Option<String>,
 }
 
-pub async fn get_pea(state: web::Data<AppState>, pea_id: web::Path<String>) -> impl Responder {
-    let configs = state.pea_configs.read().await;
-    match configs.get(pea_id.as_str()) {
-        Some(config) => HttpResponse::Ok().json(config),
-        None => HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"})),
+impl ListPeasQuery {
+    fn matches(&self, config: &PeaConfig) -> bool {
+        let name_matches = self
+            .name_contains
+            .as_deref()
+            .is_none_or(|text| config.name.to_lowercase().contains(&text.to_lowercase()));
+        let service_matches = self
+            .service_tag
+            .as_deref()
+            .is_none_or(|tag| config.services.iter().any(|s| s.tag == tag));
+        name_matches && service_matches
+    }
+
+    /// Filters, sorts and pages `peas` into the list envelope.
+    fn page<T: serde::Serialize>(
+        &self,
+        mut peas: Vec<T>,
+        config: fn(&T) -> &PeaConfig,
+    ) -> Result<serde_json::Value, String> {
+        let sort_by = self.sort_by.as_deref().unwrap_or("name");
+        let (field, descending) = match sort_by.strip_prefix('-') {
+            Some(field) => (field, true),
+            None => (sort_by, false),
+        };
+        let order: fn(&PeaConfig, &PeaConfig) -> std::cmp::Ordering = match field {
+            "name" => |a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            "id" => |a, b| a.id.cmp(&b.id),
+            "created_at" => |a, b| a.created_at.cmp(&b.created_at),
+            "updated_at" => |a, b| a.updated_at.cmp(&b.updated_at),
+            _ => return Err(format!("Cannot sort by '{}'", field)),
+        };
+        peas.retain(|pea| self.matches(config(pea)));
+        peas.sort_by(|a, b| {
+            let ordering =
+                order(config(a), config(b)).then_with(|| config(a).id.cmp(&config(b).id));
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        let page = self.page.unwrap_or(1).max(1);
+        let page_size = self
+            .page_size
+            .unwrap_or(DEFAULT_PEA_PAGE_SIZE)
+            .clamp(1, MAX_PEA_PAGE_SIZE);
+        let total = peas.len();
+        let items: Vec<T> = peas
+            .into_iter()
+            .skip((page - 1).saturating_mul(page_size))
+            .take(page_size)
+            .collect();
+        Ok(serde_json::json!({
+            "total": total,
+            "page": page,
+            "page_size": page_size,
+            "items": items,
+        }))
+    }
+}
+
+/// GET /pea — one page of the PEAs the caller can see, filtered and sorted
+pub async fn list_peas(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<ListPeasQuery>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let page = if query.archived {
+        let configs = state.archived_pea_configs.read().await;
+        let peas: Vec<&PeaConfig> = configs
+            .values()
+            .filter(|config| scope.allows(config.tenant_id.as_deref()))
+            .collect();
+        query.page(peas, |config| config)
+    } else {
+        let peas: Vec<RegisteredPea> = state
+            .pea_registry
+            .list()
+            .await
+            .into_iter()
+            .filter(|pea| scope.allows(pea.config.tenant_id.as_deref()))
+            .collect();
+        query.page(peas, |pea| &pea.config)
+    };
+    match page {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({"error": e})),
+    }
+}
+
+pub async fn get_pea(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    match state.pea_registry.get(&pea_id).await {
+        Some(pea) if scope.allows(pea.config.tenant_id.as_deref()) => HttpResponse::Ok().json(pea),
+        _ => HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"})),
     }
 }
 
-pub async fn create_pea(state: web::Data<AppState>, body: web::Json<PeaConfig>) -> impl Responder {
+pub async fn create_pea(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<PeaConfig>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
     let mut config = body.into_inner();
+    if let Some(response) = reject_invalid_config(&config) {
+        return response;
+    }
     if config.id.is_empty() {
         config.id = Uuid::new_v4().to_string();
     }
+    config.archived_at = None;
+    if let TenantScope::Tenant(tenant) = &scope {
+        if state.pea_configs.read().await.contains_key(&config.id) {
+            return HttpResponse::Conflict()
+                .json(serde_json::json!({"error": "PEA id is already in use"}));
+        }
+        let (used, _) = crate::tenant_handlers::usage_counts(&state, &tenant.id).await;
+        if let Some(response) = tenancy::check_quota(tenant.quotas.max_peas, used, 1, "PEAs") {
+            return response;
+        }
+        config.tenant_id = Some(tenant.id.clone());
+    }
     config.created_at = Utc::now();
     config.updated_at = Utc::now();
 
     let id = config.id.clone();
-    persist_pea_config(&state.pea_config_dir, &config);
+    config_store::save_pea_config(state.config_store.as_ref(), &config).await;
 
     let mut configs = state.pea_configs.write().await;
     configs.insert(id, config.clone());
+    drop(configs);
+    let actor_id = CallerContext::from_request(&req).actor_id;
+    state
+        .pea_revisions
+        .record(&config, pea_revisions::REASON_CREATE, None, actor_id)
+        .await;
 
     info!("Created PEA config: {} ({})", config.name, config.id);
     HttpResponse::Created().json(config)
 }
 
+/// 422 listing every field problem of a submitted config, if it has any.
+pub fn reject_invalid_config(config: &PeaConfig) -> Option<HttpResponse> {
+    let errors = pea_validation::validate_pea_config(config);
+    (!errors.is_empty()).then(|| {
+        HttpResponse::UnprocessableEntity().json(serde_json::json!({
+            "error": "PEA config is invalid",
+            "errors": errors,
+        }))
+    })
+}
+
 pub async fn update_pea(
+    req: HttpRequest,
     state: web::Data<AppState>,
     pea_id: web::Path<String>,
     body: web::Json<PeaConfig>,
 ) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let (existing_tenant, existing_mode) = state
+        .pea_configs
+        .read()
+        .await
+        .get(pea_id.as_str())
+        .map(|config| (config.tenant_id.clone(), config.mode))
+        .unzip();
+    if let TenantScope::Tenant(tenant) = &scope {
+        if existing_tenant
+            .as_ref()
+            .is_some_and(|owner| owner.as_deref() != Some(tenant.id.as_str()))
+        {
+            return HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"}));
+        }
+    }
+
     let mut config = body.into_inner();
+    if let Some(response) = reject_invalid_config(&config) {
+        return response;
+    }
+    if let Some(response) =
+        reject_deployed_mode_change(&state, &pea_id, existing_mode, config.mode).await
+    {
+        return response;
+    }
     config.id = pea_id.to_string();
     config.updated_at = Utc::now();
+    config.archived_at = None;
+    config.tenant_id = match scope.tenant_id() {
+        Some(tenant_id) => Some(tenant_id.to_string()),
+        None => config.tenant_id.or(existing_tenant.flatten()),
+    };
 
-    persist_pea_config(&state.pea_config_dir, &config);
+    config_store::save_pea_config(state.config_store.as_ref(), &config).await;
 
     let mut configs = state.pea_configs.write().await;
     configs.insert(pea_id.to_string(), config.clone());
+    drop(configs);
+    let actor_id = CallerContext::from_request(&req).actor_id;
+    state
+        .pea_revisions
+        .record(&config, pea_revisions::REASON_UPDATE, None, actor_id)
+        .await;
 
     info!("Updated PEA config: {} ({})", config.name, config.id);
     HttpResponse::Ok().json(config)
 }
 
-pub async fn delete_pea(state: web::Data<AppState>, pea_id: web::Path<String>) -> impl Responder {
+/// Switching a deployed PEA between simulator and real device would mix
+/// simulated and real telemetry under the same keys.
+async fn reject_deployed_mode_change(
+    state: &AppState,
+    pea_id: &str,
+    existing_mode: Option<PeaMode>,
+    mode: PeaMode,
+) -> Option<HttpResponse> {
+    let deployed = state
+        .pea_lifecycle
+        .desired()
+        .await
+        .get(pea_id)
+        .is_some_and(|phase| phase.deployed);
+    (existing_mode.is_some_and(|existing| existing != mode) && deployed).then(|| {
+        HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Undeploy the PEA before changing its mode",
+        }))
+    })
+}
+
+// ─── Cloning and Templates ───────────────────────────────────────────────────
+
+/// POST /pea/{id}/clone — copies of a PEA's services, procedures and tags as new PEAs
+pub async fn clone_pea(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+    body: Option<web::Json<StampRequest>>,
+) -> impl Responder {
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    let Some(source) = state.pea_configs.read().await.get(pea_id.as_str()).cloned() else {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"}));
+    };
+    let mut request = body.map(web::Json::into_inner).unwrap_or_default();
+    request
+        .name
+        .get_or_insert_with(|| format!("{} (copy)", source.name));
+    match pea_templates::stamp(&source, &request) {
+        Ok(peas) => create_stamped_peas(&req, &state, peas).await,
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({"error": e})),
+    }
+}
+
+/// GET /pea/templates — templates found in `PEA_TEMPLATE_DIR`
+pub async fn list_pea_templates(state: web::Data<AppState>) -> impl Responder {
+    let templates = pea_templates::load(&state.pea_template_dir);
+    HttpResponse::Ok().json(
+        templates
+            .iter()
+            .map(|(id, config)| pea_templates::summary(id, config))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// POST /pea/from-template/{template_id} — new PEAs stamped out of a template
+pub async fn create_pea_from_template(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    template_id: web::Path<String>,
+    body: Option<web::Json<StampRequest>>,
+) -> impl Responder {
+    let Some(template) = pea_templates::get(&state.pea_template_dir, &template_id) else {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Template not found"}));
+    };
+    let request = body.map(web::Json::into_inner).unwrap_or_default();
+    match pea_templates::stamp(&template, &request) {
+        Ok(peas) => create_stamped_peas(&req, &state, peas).await,
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({"error": e})),
+    }
+}
+
+/// Saves stamped PEAs for the caller's tenant; none is saved unless all are
+/// valid, unused and within the tenant's quota.
+async fn create_stamped_peas(
+    req: &HttpRequest,
+    state: &AppState,
+    mut peas: Vec<PeaConfig>,
+) -> HttpResponse {
+    let scope = match tenancy::scope_for(state, req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    if let Some(response) = peas.iter().find_map(reject_invalid_config) {
+        return response;
+    }
+    {
+        let configs = state.pea_configs.read().await;
+        if let Some(taken) = peas.iter().find(|config| configs.contains_key(&config.id)) {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": format!("PEA id '{}' is already in use", taken.id)
+            }));
+        }
+    }
+    if let TenantScope::Tenant(tenant) = &scope {
+        let (used, _) = crate::tenant_handlers::usage_counts(state, &tenant.id).await;
+        let limit = tenant.quotas.max_peas;
+        if let Some(response) = tenancy::check_quota(limit, used, peas.len(), "PEAs") {
+            return response;
+        }
+    }
+
+    let now = Utc::now();
+    let actor_id = CallerContext::from_request(req).actor_id;
+    for config in &mut peas {
+        config.tenant_id = scope.tenant_id().map(str::to_string);
+        config.created_at = now;
+        config.updated_at = now;
+        config_store::save_pea_config(state.config_store.as_ref(), config).await;
+        state
+            .pea_configs
+            .write()
+            .await
+            .insert(config.id.clone(), config.clone());
+        state
+            .pea_revisions
+            .record(config, pea_revisions::REASON_CREATE, None, actor_id.clone())
+            .await;
+        info!("Created PEA config: {} ({})", config.name, config.id);
+    }
+    HttpResponse::Created().json(peas)
+}
+
+// ─── PEA Config Revisions ────────────────────────────────────────────────────
+
+/// GET /pea/{id}/revisions — every saved revision, oldest first, without configs
+pub async fn list_pea_revisions(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    match state.pea_revisions.list(&pea_id).await {
+        Ok(revisions) => HttpResponse::Ok().json(
+            revisions
+                .iter()
+                .map(pea_revisions::PeaRevision::summary)
+                .collect::<Vec<_>>(),
+        ),
+        Err(e) => {
+            error!("Failed to list revisions of PEA {}: {:#}", pea_id, e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({"error": "Failed to list revisions"}))
+        }
+    }
+}
+
+/// GET /pea/{id}/revisions/{n} — one revision with its full config
+pub async fn get_pea_revision(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, u32)>,
+) -> impl Responder {
+    let (pea_id, revision) = path.into_inner();
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    match load_revision(&state, &pea_id, revision).await {
+        Ok(revision) => HttpResponse::Ok().json(revision),
+        Err(response) => response,
+    }
+}
+
+/// POST /pea/{id}/rollback/{n} — makes revision n the current config again,
+/// recorded as a new revision so the rollback itself can be undone.
+pub async fn rollback_pea(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, u32)>,
+) -> impl Responder {
+    let (pea_id, revision) = path.into_inner();
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    let restored = match load_revision(&state, &pea_id, revision).await {
+        Ok(restored) => restored,
+        Err(response) => return response,
+    };
+    let (existing_tenant, existing_mode) = state
+        .pea_configs
+        .read()
+        .await
+        .get(&pea_id)
+        .map(|config| (config.tenant_id.clone(), config.mode))
+        .unzip();
+
+    let mut config = restored.config;
+    if let Some(response) =
+        reject_deployed_mode_change(&state, &pea_id, existing_mode, config.mode).await
+    {
+        return response;
+    }
+    config.updated_at = Utc::now();
+    if let Some(tenant_id) = existing_tenant {
+        config.tenant_id = tenant_id;
+    }
+
+    config_store::save_pea_config(state.config_store.as_ref(), &config).await;
+    state
+        .pea_configs
+        .write()
+        .await
+        .insert(pea_id.clone(), config.clone());
+    let actor_id = CallerContext::from_request(&req).actor_id;
+    let recorded = state
+        .pea_revisions
+        .record(
+            &config,
+            pea_revisions::REASON_ROLLBACK,
+            Some(revision),
+            actor_id,
+        )
+        .await;
+
+    info!("Rolled back PEA config {} to revision {}", pea_id, revision);
+    HttpResponse::Ok().json(serde_json::json!({
+        "revision": recorded.map(|recorded| recorded.revision),
+        "restored_from": revision,
+        "config": config,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PeaDiffQuery {
+    /// Another PEA's id, or a revision number of this PEA.
+    pub against: String,
+}
+
+/// GET /pea/{id}/diff?against= — services, procedures and parameters that
+/// differ between this PEA and another PEA or one of its own revisions
+pub async fn diff_pea(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+    query: web::Query<PeaDiffQuery>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let (current, other) = {
+        let configs = state.pea_configs.read().await;
+        let visible = |id: &str| {
+            configs
+                .get(id)
+                .filter(|config| scope.allows(config.tenant_id.as_deref()))
+                .cloned()
+        };
+        (visible(&pea_id), visible(&query.against))
+    };
+    let Some(current) = current else {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"}));
+    };
+
+    // A PEA with that id wins over a revision number.
+    let (against, base) = match (other, query.against.parse::<u32>()) {
+        (Some(other), _) => (serde_json::json!({"pea_id": other.id}), other),
+        (None, Ok(revision)) => match load_revision(&state, &pea_id, revision).await {
+            Ok(loaded) => (serde_json::json!({"revision": revision}), loaded.config),
+            Err(response) => return response,
+        },
+        (None, Err(_)) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "error": format!("No PEA or revision '{}' to compare against", query.against)
+            }));
+        }
+    };
+    let diff = pea_diff::diff(&base, &current);
+    HttpResponse::Ok().json(serde_json::json!({
+        "pea_id": current.id,
+        "against": against,
+        "identical": diff.is_empty(),
+        "fields": diff.fields,
+        "services": diff.services,
+    }))
+}
+
+async fn load_revision(
+    state: &AppState,
+    pea_id: &str,
+    revision: u32,
+) -> Result<pea_revisions::PeaRevision, HttpResponse> {
+    match state.pea_revisions.get(pea_id, revision).await {
+        Ok(Some(revision)) => Ok(revision),
+        Ok(None) => Err(HttpResponse::NotFound()
+            .json(serde_json::json!({"error": "Revision not found"}))),
+        Err(e) => {
+            error!("Failed to load revision {} of PEA {}: {:#}", revision, pea_id, e);
+            Err(HttpResponse::InternalServerError()
+                .json(serde_json::json!({"error": "Failed to load revision"})))
+        }
+    }
+}
+
+pub async fn delete_pea(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    let mut configs = state.pea_configs.write().await;
+    if let Some(mut config) = configs.remove(pea_id.as_str()) {
+        config.archived_at = Some(Utc::now());
+        config_store::archive_pea_config(state.config_store.as_ref(), &config).await;
+        state
+            .archived_pea_configs
+            .write()
+            .await
+            .insert(config.id.clone(), config);
+    }
+    drop(configs);
+    state.pea_status.remove(&pea_id).await;
+    state
+        .pea_certificates
+        .publish_death(
+            &state.zenoh_session,
+            &pea_id,
+            crate::pea_birth::DEATH_DELETED,
+        )
+        .await;
+
+    info!("Archived PEA config: {}", pea_id);
+    HttpResponse::NoContent().finish()
+}
+
+/// Archived config of `pea_id` if the caller's tenant owns it.
+async fn visible_archived_pea(
+    state: &AppState,
+    req: &HttpRequest,
+    pea_id: &str,
+) -> Result<PeaConfig, HttpResponse> {
+    let scope = tenancy::scope_for(state, req)
+        .await
+        .map_err(|e| e.response())?;
+    state
+        .archived_pea_configs
+        .read()
+        .await
+        .get(pea_id)
+        .filter(|config| scope.allows(config.tenant_id.as_deref()))
+        .cloned()
+        .ok_or_else(|| {
+            HttpResponse::NotFound().json(serde_json::json!({"error": "Archived PEA not found"}))
+        })
+}
+
+/// POST /pea/{id}/restore — brings a deleted PEA back, undeployed
+pub async fn restore_pea(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+) -> impl Responder {
+    let mut config = match visible_archived_pea(&state, &req, &pea_id).await {
+        Ok(config) => config,
+        Err(response) => return response,
+    };
     let mut configs = state.pea_configs.write().await;
-    configs.remove(pea_id.as_str());
-    delete_pea_file(&state.pea_config_dir, &pea_id);
+    if configs.contains_key(pea_id.as_str()) {
+        return HttpResponse::Conflict()
+            .json(serde_json::json!({"error": "A PEA with this id exists again"}));
+    }
+    if let Some(tenant_id) = &config.tenant_id {
+        let used = configs
+            .values()
+            .filter(|pea| pea.tenant_id.as_ref() == Some(tenant_id))
+            .count();
+        let limit = state
+            .tenants
+            .read()
+            .await
+            .get(tenant_id)
+            .and_then(|tenant| tenant.quotas.max_peas);
+        if let Some(response) = tenancy::check_quota(limit, used, 1, "PEAs") {
+            return response;
+        }
+    }
+    config.archived_at = None;
+    config_store::restore_pea_config(state.config_store.as_ref(), &config).await;
+    state
+        .archived_pea_configs
+        .write()
+        .await
+        .remove(pea_id.as_str());
+    configs.insert(config.id.clone(), config.clone());
+    drop(configs);
+
+    info!("Restored PEA config: {}", pea_id);
+    HttpResponse::Ok().json(config)
+}
 
-    info!("Deleted PEA config: {}", pea_id);
+/// DELETE /pea/{id}/archive — removes an archived PEA and its attachments for good
+pub async fn purge_archived_pea(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+) -> impl Responder {
+    if let Err(response) = visible_archived_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    state
+        .archived_pea_configs
+        .write()
+        .await
+        .remove(pea_id.as_str());
+    config_store::delete_archived_pea_config(state.config_store.as_ref(), &pea_id).await;
+    crate::attachment_handlers::purge_pea_attachments(&state, &pea_id).await;
+
+    info!("Purged archived PEA config: {}", pea_id);
     HttpResponse::NoContent().finish()
 }
 
+#[derive(Deserialize)]
+pub struct PeaImportQuery {
+    /// Store the generated configs; without it the import is a dry run.
+    pub persist: Option<bool>,
+}
+
+/// POST /pea/import-csv — build PEA configs from a CSV/XLSX sheet of services,
+/// parameters and OPC UA node addresses, returning them with a validation report.
+pub async fn import_pea_sheet(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<PeaImportQuery>,
+    mut payload: actix_multipart::Multipart,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+
+    let (filename, file_bytes) = read_upload(&mut payload, "unknown.csv").await;
+    if file_bytes.is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": "No file uploaded"}));
+    }
+
+    let rows = match pea_importer::read_sheet(&filename, &file_bytes) {
+        Ok(rows) => rows,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("Failed to read spreadsheet: {}", e)
+            }));
+        }
+    };
+    let default_name = filename
+        .rsplit_once('.')
+        .map(|(stem, _)| stem)
+        .unwrap_or(&filename)
+        .to_string();
+    let (mut peas, report) = pea_importer::build_pea_configs(&rows, &default_name);
+    for config in &mut peas {
+        config.tenant_id = scope.tenant_id().map(str::to_string);
+    }
+
+    let persist = query.persist.unwrap_or(false);
+    if persist && !report.errors.is_empty() {
+        return HttpResponse::UnprocessableEntity().json(serde_json::json!({
+            "error": "Spreadsheet has validation errors; nothing was imported",
+            "report": report,
+        }));
+    }
+    if let (true, TenantScope::Tenant(tenant)) = (persist, &scope) {
+        let (used, _) = crate::tenant_handlers::usage_counts(&state, &tenant.id).await;
+        let limit = tenant.quotas.max_peas;
+        if let Some(response) = tenancy::check_quota(limit, used, peas.len(), "PEAs") {
+            return response;
+        }
+    }
+    if persist {
+        let mut configs = state.pea_configs.write().await;
+        for config in &peas {
+            config_store::save_pea_config(state.config_store.as_ref(), config).await;
+            configs.insert(config.id.clone(), config.clone());
+            info!("Imported PEA config: {} ({})", config.name, config.id);
+        }
+        drop(configs);
+        let actor_id = CallerContext::from_request(&req).actor_id;
+        for config in &peas {
+            state
+                .pea_revisions
+                .record(config, pea_revisions::REASON_IMPORT, None, actor_id.clone())
+                .await;
+        }
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "persisted": persist,
+        "peas": peas,
+        "report": report,
+    }))
+}
+
+/// POST /pea/import — build a PEA config from an MTP package (`.mtp`) or a
+/// bare AML document, returning it with the parts that could not be carried over.
+pub async fn import_pea_mtp(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<PeaImportQuery>,
+    mut payload: actix_multipart::Multipart,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+
+    let (filename, file_bytes) = read_upload(&mut payload, "unknown.mtp").await;
+    if file_bytes.is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": "No file uploaded"}));
+    }
+    let import = match shared::mtp::aml::import_package(&file_bytes) {
+        Ok(import) => import,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("Failed to import {}: {}", filename, e)
+            }));
+        }
+    };
+    let mut config = import.config;
+    config.tenant_id = scope.tenant_id().map(str::to_string);
+
+    let persist = query.persist.unwrap_or(false);
+    if let (true, TenantScope::Tenant(tenant)) = (persist, &scope) {
+        let (used, _) = crate::tenant_handlers::usage_counts(&state, &tenant.id).await;
+        let limit = tenant.quotas.max_peas;
+        if let Some(response) = tenancy::check_quota(limit, used, 1, "PEAs") {
+            return response;
+        }
+    }
+    if persist {
+        config_store::save_pea_config(state.config_store.as_ref(), &config).await;
+        state
+            .pea_configs
+            .write()
+            .await
+            .insert(config.id.clone(), config.clone());
+        state
+            .pea_revisions
+            .record(
+                &config,
+                pea_revisions::REASON_IMPORT,
+                None,
+                CallerContext::from_request(&req).actor_id,
+            )
+            .await;
+        info!(
+            "Imported PEA config from MTP {}: {} ({})",
+            filename, config.name, config.id
+        );
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "persisted": persist,
+        "pea": config,
+        "warnings": import.warnings,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OpcUaBrowseRequest {
+    /// `opc.tcp://host[:port][/path]` of the PEA's server.
+    pub endpoint: String,
+    /// Node to browse from; the Objects folder by default.
+    pub start_node: Option<String>,
+    pub max_depth: Option<usize>,
+    pub max_nodes: Option<usize>,
+    pub timeout_ms: Option<u64>,
+}
+
+/// POST /pea/opcua/browse — walk an OPC UA server's address space and list
+/// its variables as candidate tag mappings, to build a PEA config from a live
+/// server instead of from its nodeset XML.
+pub async fn browse_opcua(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<OpcUaBrowseRequest>,
+) -> impl Responder {
+    if let Err(e) = tenancy::scope_for(&state, &req).await {
+        return e.response();
+    }
+    let start = match body.start_node.as_deref() {
+        Some(text) => match opcua::NodeId::parse(text) {
+            Some(node_id) => node_id,
+            None => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": format!("Invalid start node '{}'", text)
+                }));
+            }
+        },
+        None => opcua::NodeId::numeric(0, opcua::OBJECTS_FOLDER),
+    };
+    let max_depth = body.max_depth.unwrap_or(4).clamp(1, 16);
+    let max_nodes = body.max_nodes.unwrap_or(1000).clamp(1, 10_000);
+    let timeout =
+        std::time::Duration::from_millis(body.timeout_ms.unwrap_or(5000).clamp(100, 60_000));
+
+    match opcua::browse(&body.endpoint, &start, max_depth, max_nodes, timeout).await {
+        Ok(outcome) => HttpResponse::Ok().json(outcome),
+        Err(e) => {
+            error!("OPC UA browse of {} failed: {:#}", body.endpoint, e);
+            HttpResponse::BadGateway().json(serde_json::json!({
+                "error": format!("Failed to browse {}: {:#}", body.endpoint, e)
+            }))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PeaExportQuery {
+    /// Export format; only `aml` (an MTP package) is supported.
+    pub format: Option<String>,
+}
+
+/// GET /pea/{id}/export?format=aml — the PEA config as an MTP package, for
+/// taking configs edited here into other process orchestration tooling.
+pub async fn export_pea(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+    query: web::Query<PeaExportQuery>,
+) -> impl Responder {
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    let format = query.format.as_deref().unwrap_or("aml");
+    if !format.eq_ignore_ascii_case("aml") {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Unsupported export format '{}'; expected 'aml'", format)
+        }));
+    }
+    let Some(config) = state.pea_configs.read().await.get(pea_id.as_str()).cloned() else {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"}));
+    };
+
+    match shared::mtp::aml::export_package(&config) {
+        Ok(export) => {
+            for warning in &export.warnings {
+                info!("MTP export of PEA {}: {}", config.id, warning);
+            }
+            HttpResponse::Ok()
+                .content_type("application/zip")
+                .insert_header((
+                    "Content-Disposition",
+                    format!("attachment; filename=\"{}\"", export.file_name),
+                ))
+                .body(export.package)
+        }
+        Err(e) => {
+            error!("Failed to export PEA {} as MTP: {}", config.id, e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({"error": "Failed to export PEA"}))
+        }
+    }
+}
+
+/// Name and content of the first file of a multipart upload.
+async fn read_upload(
+    payload: &mut actix_multipart::Multipart,
+    default_name: &str,
+) -> (String, Vec<u8>) {
+    use futures_util::{StreamExt, TryStreamExt};
+
+    let mut file_bytes: Vec<u8> = Vec::new();
+    let mut filename = default_name.to_string();
+
+    if let Ok(Some(mut field)) = payload.try_next().await {
+        if let Some(disposition) = field.content_disposition() {
+            if let Some(name) = disposition.get_filename() {
+                filename = name.to_string();
+            }
+        }
+        while let Some(Ok(chunk)) = field.next().await {
+            file_bytes.extend_from_slice(&chunk);
+        }
+    }
+    (filename, file_bytes)
+}
+
 // ─── PEA Lifecycle ───────────────────────────────────────────────────────────
 
-pub async fn deploy_pea(state: web::Data<AppState>, pea_id: web::Path<String>) -> impl Responder {
+pub async fn deploy_pea(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+) -> impl Responder {
+    run_lifecycle_action(&state, &req, &pea_id, LifecycleAction::Deploy, false).await
+}
+
+/// A lifecycle change requested for one PEA, alone or in bulk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleAction {
+    Deploy,
+    Undeploy,
+    Start,
+    Stop,
+}
+
+/// Runs `action` on a PEA with the checks of its single-PEA endpoint: tenant
+/// ownership, operator claims and approval policies.
+async fn run_lifecycle_action(
+    state: &AppState,
+    req: &HttpRequest,
+    pea_id: &str,
+    action: LifecycleAction,
+    force: bool,
+) -> HttpResponse {
+    if let Some(response) = reject_foreign_pea(state, req, pea_id).await {
+        return response;
+    }
+    if let Some(response) = reject_non_owner_pea(state, req, pea_id).await {
+        return response;
+    }
+    if action == LifecycleAction::Undeploy && !force {
+        if let Some(response) = reject_with_dependents(state, req, pea_id).await {
+            return response;
+        }
+    }
+    let guarded = match action {
+        LifecycleAction::Deploy => Some(GuardedAction::Deploy),
+        LifecycleAction::Undeploy => Some(GuardedAction::Undeploy),
+        LifecycleAction::Stop => Some(GuardedAction::Stop),
+        LifecycleAction::Start => None,
+    };
+    if let Some(guarded) = guarded {
+        if let Some(response) = hold_for_approval(state, req, pea_id, None, None, guarded).await {
+            return response;
+        }
+    }
+    let actor_id = CallerContext::from_request(req).actor_id;
+    match action {
+        LifecycleAction::Deploy => perform_deploy(state, actor_id, pea_id).await,
+        LifecycleAction::Undeploy => perform_undeploy(state, actor_id, pea_id).await,
+        LifecycleAction::Start => perform_start(state, actor_id, pea_id).await,
+        LifecycleAction::Stop => perform_stop(state, actor_id, pea_id).await,
+    }
+}
+
+/// Refuses to undeploy a PEA that downstream PEAs or running recipes depend on.
+async fn reject_with_dependents(
+    state: &AppState,
+    req: &HttpRequest,
+    pea_id: &str,
+) -> Option<HttpResponse> {
+    let scope = match tenancy::scope_for(state, req).await {
+        Ok(scope) => scope,
+        Err(e) => return Some(e.response()),
+    };
+    let impact = pea_impact::analyze(state, &scope, pea_id).await;
+    impact.has_dependents.then(|| {
+        HttpResponse::Conflict().json(serde_json::json!({
+            "error": "PEA has dependents; pass force=true to undeploy anyway",
+            "impact": impact,
+        }))
+    })
+}
+
+/// Deploys a PEA; shared by the API and confirmed approvals.
+pub(crate) async fn perform_deploy(
+    state: &AppState,
+    actor_id: Option<String>,
+    pea_id: &str,
+) -> HttpResponse {
     let configs = state.pea_configs.read().await;
-    match configs.get(pea_id.as_str()) {
+    match configs.get(pea_id) {
         Some(config) => {
-            // Publish deploy command on the runtime topic family.
-            let deploy_msg = serde_json::json!({
-                "action": "deploy",
-                "pea_config": config
-            });
-            let runtime_topic = shared::mtp::topics::runtime_pea_deploy(&pea_id);
-            let _ = state
-                .zenoh_session
-                .put(&runtime_topic, deploy_msg.to_string())
-                .await;
+            record_lifecycle(state, actor_id.clone(), pea_id, Phase::IDLE).await;
+            audit::record(
+                state,
+                actor_id,
+                audit::ENTITY_PEA,
+                pea_id,
+                audit::ACTION_DEPLOY,
+                serde_json::json!({"version": config.version, "mode": config.mode}),
+            )
+            .await;
+
+            publish_deploy_command(state, config).await;
 
             // Publish deployed status directly so frontend gets immediate feedback
             let status = serde_json::json!({
-                "pea_id": pea_id.as_str(),
+                "pea_id": pea_id,
                 "deployed": true,
                 "running": false,
                 "services": config.services.iter().map(|s| serde_json::json!({
@@ -99,50 +1079,382 @@ pub async fn deploy_pea(state: web::Data<AppState>, pea_id: web::Path<String>) -
                 })).collect::<Vec<_>>(),
                 "last_updated": chrono::Utc::now().to_rfc3339(),
             });
-            let status_topic = shared::mtp::topics::pea_status(&pea_id);
-            let _ = state
-                .zenoh_session
-                .put(&status_topic, status.to_string())
+            publish_simulated_status(state, config, status).await;
+
+            state
+                .pea_certificates
+                .publish_birth(&state.zenoh_session, config)
                 .await;
 
             info!("PEA deployed: {} ({})", config.name, pea_id);
+            state
+                .webhooks
+                .emit(
+                    webhook_service::EVENT_PEA_DEPLOYED,
+                    serde_json::json!({ "pea_id": pea_id, "name": config.name }),
+                )
+                .await;
             HttpResponse::Accepted().json(serde_json::json!({
                 "status": "deployed",
-                "pea_id": pea_id.as_str()
+                "pea_id": pea_id
             }))
         }
         None => HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"})),
     }
 }
 
-pub async fn undeploy_pea(state: web::Data<AppState>, pea_id: web::Path<String>) -> impl Responder {
-    let pea_id_str = pea_id.into_inner();
-    let exists = {
-        let configs = state.pea_configs.read().await;
-        configs.contains_key(&pea_id_str)
+/// GET /pea/{id}/birth — the current birth certificate of a deployed PEA.
+pub async fn get_pea_birth(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    match state.pea_certificates.birth(&pea_id).await {
+        Some(birth) => HttpResponse::Ok().json(birth),
+        None => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "PEA has no birth certificate; it is not deployed"
+        })),
+    }
+}
+
+/// Default and maximum number of sparkline points per key in `GET /pea/{id}/status`.
+const DEFAULT_SPARKLINE_POINTS: usize = 30;
+const MAX_SPARKLINE_POINTS: usize = 500;
+
+#[derive(Deserialize)]
+pub struct PeaStatusQuery {
+    /// Points per key; defaults to [`DEFAULT_SPARKLINE_POINTS`].
+    pub points: Option<usize>,
+    /// Comma-separated key expressions; defaults to the PEA's bound tags and data keys.
+    pub keys: Option<String>,
+}
+
+/// GET /pea/{id}/status — deployment state, latest status and recent values for a PEA card
+pub async fn get_pea_status(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+    query: web::Query<PeaStatusQuery>,
+) -> impl Responder {
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    let name = match state.pea_configs.read().await.get(pea_id.as_str()) {
+        Some(config) => config.name.clone(),
+        None => {
+            return HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"}))
+        }
     };
-    if !exists {
+    let points = query
+        .points
+        .unwrap_or(DEFAULT_SPARKLINE_POINTS)
+        .clamp(1, MAX_SPARKLINE_POINTS);
+    let pea_segment = format!("/pea/{}/", pea_id);
+
+    // key -> label shown on the card
+    let mut keys: Vec<(String, String)> = Vec::new();
+    match query.keys.as_deref() {
+        Some(requested) => {
+            for key in requested
+                .split(',')
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+            {
+                // Only keys of this PEA, so the tenant check above covers them.
+                if key.contains(&pea_segment) {
+                    keys.push((
+                        key.to_string(),
+                        key.rsplit('/').next().unwrap_or(key).to_string(),
+                    ));
+                }
+            }
+        }
+        None => {
+            let bindings = state.pea_bindings.read().await;
+            if let Some(binding) = bindings.values().find(|b| b.pea_id == *pea_id) {
+                for mapping in &binding.mappings {
+                    keys.push((
+                        crate::binding_handlers::binding_value_topic(binding, mapping),
+                        mapping.canonical_tag.clone(),
+                    ));
+                }
+            }
+        }
+    }
+
+    let birth = state.pea_certificates.birth(&pea_id).await;
+    let cached = state.pea_status.get(&pea_id).await;
+    let store = state.timeseries.read().await;
+    if query.keys.is_none() {
+        let data_segment = format!("{}data/", pea_segment);
+        let mut data_keys: Vec<(String, String)> = store
+            .data
+            .keys()
+            .filter_map(|key| {
+                let (_, tag) = key.split_once(&data_segment)?;
+                Some((key.clone(), tag.to_string()))
+            })
+            .collect();
+        data_keys.sort();
+        keys.extend(data_keys);
+    }
+
+    let mut status = serde_json::Value::Null;
+    let mut services = serde_json::Map::new();
+    for (key, buf) in &store.data {
+        let Some((_, rest)) = key.split_once(&pea_segment) else {
+            continue;
+        };
+        let Some(latest) = buf.back() else {
+            continue;
+        };
+        if rest == "status" && cached.is_none() {
+            status = latest.value.clone();
+        } else if let Some(tag) = rest
+            .strip_prefix("services/")
+            .and_then(|rest| rest.strip_suffix("/state"))
+        {
+            services.insert(tag.to_string(), latest.value.clone());
+        }
+    }
+
+    let sparklines: Vec<serde_json::Value> = keys
+        .into_iter()
+        .map(|(key, label)| {
+            let buf = store.data.get(&key);
+            let latest = buf.and_then(|buf| buf.back());
+            serde_json::json!({
+                "key": key,
+                "label": label,
+                "latest": latest.map(|p| p.value.clone()),
+                "latest_ts": latest.map(|p| p.timestamp_ms),
+                "points": buf.map(|buf| sparkline(buf, points)).unwrap_or_default(),
+            })
+        })
+        .collect();
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "pea_id": pea_id.as_str(),
+        "name": name,
+        "deployed": birth.is_some(),
+        "status": cached
+            .as_ref()
+            .map(|cached| serde_json::json!(cached.status))
+            .unwrap_or(status),
+        "node_id": cached.as_ref().map(|cached| &cached.node_id),
+        "status_received_at": cached.as_ref().map(|cached| cached.received_at),
+        "services": services,
+        "sparklines": sparklines,
+    }))
+}
+
+/// GET /pea/status — last status every visible PEA published, `null` for
+/// PEAs not heard from since the server started
+pub async fn list_pea_statuses(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut statuses = state.pea_status.all().await;
+    let configs = state.pea_configs.read().await;
+    let mut list: Vec<serde_json::Value> = configs
+        .values()
+        .filter(|config| scope.allows(config.tenant_id.as_deref()))
+        .map(|config| {
+            let cached = statuses.remove(&config.id);
+            serde_json::json!({
+                "pea_id": config.id,
+                "name": config.name,
+                "node_id": cached.as_ref().map(|cached| &cached.node_id),
+                "received_at": cached.as_ref().map(|cached| cached.received_at),
+                "status": cached.map(|cached| cached.status),
+            })
+        })
+        .collect();
+    list.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+    HttpResponse::Ok().json(list)
+}
+
+/// The last `count` numeric points of a buffer, oldest first, as `{t, v}` pairs.
+fn sparkline(
+    buf: &std::collections::VecDeque<crate::state::TimeSeriesPoint>,
+    count: usize,
+) -> Vec<serde_json::Value> {
+    let mut points: Vec<serde_json::Value> = buf
+        .iter()
+        .rev()
+        .filter_map(|point| {
+            crate::timeseries_handlers::extract_numeric_value(&point.value)
+                .map(|v| serde_json::json!({"t": point.timestamp_ms, "v": v}))
+        })
+        .take(count)
+        .collect();
+    points.reverse();
+    points
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UndeployQuery {
+    /// Undeploy even when downstream PEAs or running recipes depend on the PEA.
+    #[serde(default)]
+    pub force: bool,
+}
+
+pub async fn undeploy_pea(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+    query: web::Query<UndeployQuery>,
+) -> impl Responder {
+    run_lifecycle_action(&state, &req, &pea_id, LifecycleAction::Undeploy, query.force).await
+}
+
+/// GET /pea/{id}/impact — what depends on the PEA, before stopping or undeploying it
+pub async fn get_pea_impact(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let visible = state
+        .pea_configs
+        .read()
+        .await
+        .get(pea_id.as_str())
+        .is_some_and(|config| scope.allows(config.tenant_id.as_deref()));
+    if !visible {
         return HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"}));
     }
+    HttpResponse::Ok().json(pea_impact::analyze(&state, &scope, &pea_id).await)
+}
 
-    let undeploy_msg = serde_json::json!({ "action": "undeploy" });
-    let runtime_topic = shared::mtp::topics::runtime_pea_deploy(&pea_id_str);
-    let _ = state
-        .zenoh_session
-        .put(&runtime_topic, undeploy_msg.to_string())
+/// GET /pea/{id}/capabilities — services, procedures and parameter ranges with
+/// the commands each service accepts in its live state
+pub async fn get_pea_capabilities(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    let Some(config) = state.pea_configs.read().await.get(pea_id.as_str()).cloned() else {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"}));
+    };
+    let capabilities = pea_capabilities::capabilities(&config, &*state.timeseries.read().await);
+    HttpResponse::Ok().json(capabilities)
+}
+
+/// GET /pea/{id}/tag-mappings — every tag mapping of a PEA as one flat table
+pub async fn get_pea_tag_mappings(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    let Some(config) = state.pea_configs.read().await.get(pea_id.as_str()).cloned() else {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"}));
+    };
+    HttpResponse::Ok().json(serde_json::json!({
+        "pea_id": config.id,
+        "rows": pea_tag_mappings::rows(&config),
+    }))
+}
+
+/// PUT /pea/{id}/tag-mappings — sets the mappings of the listed rows and
+/// keeps the others, saved as a new revision
+pub async fn update_pea_tag_mappings(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+    body: web::Json<TagMappingUpdate>,
+) -> impl Responder {
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    let Some(mut config) = state.pea_configs.read().await.get(pea_id.as_str()).cloned() else {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"}));
+    };
+    if let Err(errors) = pea_tag_mappings::apply(&mut config, body.into_inner().rows) {
+        return HttpResponse::UnprocessableEntity().json(serde_json::json!({
+            "error": "Tag mapping rows are invalid",
+            "errors": errors,
+        }));
+    }
+    if let Some(response) = reject_invalid_config(&config) {
+        return response;
+    }
+    config.updated_at = Utc::now();
+
+    config_store::save_pea_config(state.config_store.as_ref(), &config).await;
+    state
+        .pea_configs
+        .write()
+        .await
+        .insert(pea_id.to_string(), config.clone());
+    let actor_id = CallerContext::from_request(&req).actor_id;
+    state
+        .pea_revisions
+        .record(&config, pea_revisions::REASON_UPDATE, None, actor_id)
         .await;
 
+    info!(
+        "Updated tag mappings of PEA config: {} ({})",
+        config.name, config.id
+    );
+    HttpResponse::Ok().json(serde_json::json!({
+        "pea_id": config.id,
+        "rows": pea_tag_mappings::rows(&config),
+    }))
+}
+
+/// Undeploys a PEA; shared by the API and confirmed approvals.
+pub(crate) async fn perform_undeploy(
+    state: &AppState,
+    actor_id: Option<String>,
+    pea_id_str: &str,
+) -> HttpResponse {
+    let config = match state.pea_configs.read().await.get(pea_id_str) {
+        Some(config) => config.clone(),
+        None => return HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"})),
+    };
+
+    record_lifecycle(state, actor_id.clone(), pea_id_str, Phase::UNDEPLOYED).await;
+    audit::record(
+        state,
+        actor_id,
+        audit::ENTITY_PEA,
+        pea_id_str,
+        audit::ACTION_UNDEPLOY,
+        serde_json::json!({}),
+    )
+    .await;
+
+    publish_undeploy_command(state, pea_id_str).await;
+
     let status = serde_json::json!({
-        "pea_id": &pea_id_str,
+        "pea_id": pea_id_str,
         "deployed": false,
         "running": false,
         "services": [],
         "last_updated": chrono::Utc::now().to_rfc3339(),
     });
-    let status_topic = shared::mtp::topics::pea_status(&pea_id_str);
-    let _ = state
-        .zenoh_session
-        .put(&status_topic, status.to_string())
+    publish_simulated_status(state, &config, status).await;
+    state
+        .pea_certificates
+        .publish_death(
+            &state.zenoh_session,
+            pea_id_str,
+            crate::pea_birth::DEATH_UNDEPLOYED,
+        )
         .await;
 
     HttpResponse::Accepted().json(serde_json::json!({
@@ -155,54 +1467,177 @@ pub async fn undeploy_pea(state: web::Data<AppState>, pea_id: web::Path<String>)
 pub struct ServiceCommandRequest {
     pub command: ServiceCommand,
     pub procedure_id: Option<u32>,
+    pub interlock_override: Option<InterlockOverrideRequest>,
+}
+
+/// Asks to pass the interlocks blocking a command. Who overrides comes from the
+/// caller's token; only the reason is taken from the body.
+#[derive(Debug, Deserialize)]
+pub struct InterlockOverrideRequest {
+    pub reason: Option<String>,
 }
 
 pub async fn command_service(
+    http_req: HttpRequest,
     state: web::Data<AppState>,
     path: web::Path<(String, String)>,
     body: web::Json<ServiceCommandRequest>,
 ) -> impl Responder {
     let (pea_id, service_tag) = path.into_inner();
+    if let Some(response) = reject_foreign_pea(&state, &http_req, &pea_id).await {
+        return response;
+    }
     let req = body.into_inner();
-
-    let exists = {
-        let configs = state.pea_configs.read().await;
-        configs
-            .get(&pea_id)
-            .is_some_and(|c| c.services.iter().any(|s| s.tag == service_tag))
+    let interlock_override = match req.interlock_override {
+        Some(request) => {
+            let caller = tenancy::authenticated_caller(&state, &http_req).await;
+            match caller.and_then(|caller| interlock_service::override_for(&caller, request.reason))
+            {
+                Some(interlock_override) => Some(interlock_override),
+                None => {
+                    return HttpResponse::Forbidden().json(serde_json::json!({
+                        "error": "Interlock overrides require a token with an actor class"
+                    }))
+                }
+            }
+        }
+        None => None,
     };
-    if !exists {
+    let guarded = GuardedAction::ServiceCommand {
+        command: req.command,
+    };
+    let actor_id = match approval_handlers::authenticated_requester(
+        &state,
+        &http_req,
+        &pea_id,
+        Some(&service_tag),
+        &guarded,
+    )
+    .await
+    {
+        Ok(actor_id) => actor_id,
+        Err(response) => return response,
+    };
+    let source = CommandSource {
+        actor_id,
+        origin: "command_service",
+        interlock_override: interlock_override.as_ref(),
+    };
+    match service_command::send(
+        &state,
+        source,
+        &pea_id,
+        &service_tag,
+        req.command,
+        req.procedure_id,
+        None,
+    )
+    .await
+    {
+        Ok(outcome) => service_command::accepted(outcome, &pea_id, &service_tag),
+        Err(refusal) => refusal.response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServiceParametersRequest {
+    /// Procedure whose parameters are set; the default procedure when unset.
+    pub procedure_id: Option<u32>,
+    pub values: BTreeMap<String, serde_json::Value>,
+}
+
+/// POST /pea/{id}/services/{service_tag}/parameters — validates procedure
+/// parameter values and publishes them for the PEA's connector to write
+pub async fn write_service_parameters(
+    http_req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    body: web::Json<ServiceParametersRequest>,
+) -> impl Responder {
+    let (pea_id, service_tag) = path.into_inner();
+    if let Some(response) = reject_foreign_pea(&state, &http_req, &pea_id).await {
+        return response;
+    }
+    if let Some(response) = reject_non_owner_pea(&state, &http_req, &pea_id).await {
+        return response;
+    }
+    let req = body.into_inner();
+    if req.values.is_empty() {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": "values must not be empty"}));
+    }
+
+    let service = state.pea_configs.read().await.get(&pea_id).and_then(|config| {
+        config
+            .services
+            .iter()
+            .find(|s| s.tag == service_tag)
+            .cloned()
+    });
+    let Some(service) = service else {
         return HttpResponse::NotFound().json(serde_json::json!({
             "error": "PEA or service not found"
         }));
-    }
+    };
+    let procedure_id = req
+        .procedure_id
+        .or_else(|| service.procedures.iter().find(|p| p.is_default).map(|p| p.id));
+    let parameters = match service_parameters::validate(&service, req.procedure_id, &req.values) {
+        Ok(parameters) => parameters,
+        Err(errors) => {
+            return HttpResponse::UnprocessableEntity().json(serde_json::json!({
+                "error": "Parameter values are invalid",
+                "errors": errors,
+            }))
+        }
+    };
 
     let payload = serde_json::json!({
-        "command": req.command,
-        "command_code": req.command.code(),
-        "procedure_id": req.procedure_id,
-        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "procedure_id": procedure_id,
+        "parameters": parameters,
+        "timestamp": Utc::now().to_rfc3339(),
     });
-    let topic = shared::mtp::topics::pea_service_command(&pea_id, &service_tag);
-    match state.zenoh_session.put(&topic, payload.to_string()).await {
-        Ok(_) => HttpResponse::Accepted().json(serde_json::json!({
-            "status": "command_sent",
-            "pea_id": pea_id,
-            "service_tag": service_tag,
-        })),
+    let topic = shared::mtp::topics::pea_service_parameters(&pea_id, &service_tag);
+    match state
+        .chaos
+        .put(&state.zenoh_session, &topic, payload.to_string())
+        .await
+    {
+        Ok(_) => {
+            info!(
+                "Parameters {:?} sent to {}/{}",
+                req.values.keys().collect::<Vec<_>>(),
+                pea_id,
+                service_tag
+            );
+            HttpResponse::Accepted().json(serde_json::json!({
+                "status": "parameters_sent",
+                "pea_id": pea_id,
+                "service_tag": service_tag,
+                "procedure_id": procedure_id,
+                "parameters": parameters,
+            }))
+        }
         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": format!("Failed to publish command: {}", e),
+            "error": format!("Failed to publish parameters: {}", e),
         })),
     }
 }
 
-pub async fn start_pea(state: web::Data<AppState>, pea_id: web::Path<String>) -> impl Responder {
-    let pea_id_str = pea_id.into_inner();
+pub async fn start_pea(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+) -> impl Responder {
+    run_lifecycle_action(&state, &req, &pea_id, LifecycleAction::Start, false).await
+}
 
+/// Starts a PEA.
+async fn perform_start(state: &AppState, actor_id: Option<String>, pea_id_str: &str) -> HttpResponse {
     // Check PEA exists
     let config_name = {
         let configs = state.pea_configs.read().await;
-        match configs.get(&pea_id_str) {
+        match configs.get(pea_id_str) {
             Some(c) => c.name.clone(),
             None => {
                 return HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"}))
@@ -210,20 +1645,25 @@ pub async fn start_pea(state: web::Data<AppState>, pea_id: web::Path<String>) ->
         }
     };
 
-    // Publish lifecycle command on the runtime topic family.
-    let cmd = serde_json::json!({"action": "start"});
-    let runtime_topic = shared::mtp::topics::runtime_pea_lifecycle(&pea_id_str);
-    let _ = state
-        .zenoh_session
-        .put(&runtime_topic, cmd.to_string())
-        .await;
+    record_lifecycle(state, actor_id.clone(), pea_id_str, Phase::RUNNING).await;
+    audit::record(
+        state,
+        actor_id,
+        audit::ENTITY_PEA,
+        pea_id_str,
+        audit::ACTION_START,
+        serde_json::json!({}),
+    )
+    .await;
+
+    publish_lifecycle_command(state, pea_id_str, "start").await;
 
     // Publish running status directly
     {
         let configs = state.pea_configs.read().await;
-        if let Some(config) = configs.get(&pea_id_str) {
+        if let Some(config) = configs.get(pea_id_str) {
             let status = serde_json::json!({
-                "pea_id": &pea_id_str,
+                "pea_id": pea_id_str,
                 "deployed": true,
                 "running": true,
                 "services": config.services.iter().map(|s| serde_json::json!({
@@ -235,38 +1675,129 @@ pub async fn start_pea(state: web::Data<AppState>, pea_id: web::Path<String>) ->
                 })).collect::<Vec<_>>(),
                 "last_updated": chrono::Utc::now().to_rfc3339(),
             });
-            let status_topic = shared::mtp::topics::pea_status(&pea_id_str);
-            let _ = state
-                .zenoh_session
-                .put(&status_topic, status.to_string())
-                .await;
+            publish_simulated_status(state, config, status).await;
+        }
+    }
+
+    info!("PEA started: {} ({})", config_name, pea_id_str);
+    HttpResponse::Accepted().json(serde_json::json!({
+        "status": "running",
+        "pea_id": pea_id_str,
+    }))
+}
+
+pub async fn stop_pea(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+) -> impl Responder {
+    run_lifecycle_action(&state, &req, &pea_id, LifecycleAction::Stop, false).await
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkQuery {
+    /// Selector such as `name:Pump,tag:Dosing`; see `pea_bulk::PeaFilter`.
+    pub filter: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkRequest {
+    pub action: LifecycleAction,
+    /// PEAs to act on; with `?filter=`, only those that also match.
+    pub pea_ids: Option<Vec<String>>,
+    /// Undeploy PEAs even when others depend on them.
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// POST /pea/bulk — runs one lifecycle action on many PEAs, reporting each
+/// PEA's outcome as its single-PEA endpoint would have answered.
+pub async fn bulk_pea_action(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<BulkQuery>,
+    body: web::Json<BulkRequest>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let BulkRequest {
+        action,
+        pea_ids,
+        force,
+    } = body.into_inner();
+    let filter = match query.filter.as_deref().map(pea_bulk::PeaFilter::parse) {
+        Some(Ok(filter)) => Some(filter),
+        Some(Err(e)) => return HttpResponse::BadRequest().json(serde_json::json!({"error": e})),
+        None => None,
+    };
+    let targets: Vec<String> = match (pea_ids, &filter) {
+        (None, None) => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({"error": "Give pea_ids, a filter or both"}))
+        }
+        (Some(ids), None) => ids,
+        (ids, Some(filter)) => {
+            let configs = state.pea_configs.read().await;
+            let mut matched: Vec<&PeaConfig> = configs
+                .values()
+                .filter(|config| scope.allows(config.tenant_id.as_deref()))
+                .filter(|config| filter.matches(config))
+                .filter(|config| ids.as_ref().is_none_or(|ids| ids.contains(&config.id)))
+                .collect();
+            matched.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+            matched.into_iter().map(|config| config.id.clone()).collect()
         }
+    };
+    if targets.len() > pea_bulk::MAX_BULK_PEAS {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("At most {} PEAs per bulk request", pea_bulk::MAX_BULK_PEAS)
+        }));
     }
 
-    info!("PEA started: {} ({})", config_name, pea_id_str);
-    HttpResponse::Accepted().json(serde_json::json!({
-        "status": "running",
-        "pea_id": &pea_id_str,
+    let mut results = Vec::with_capacity(targets.len());
+    for pea_id in targets {
+        let response = run_lifecycle_action(&state, &req, &pea_id, action, force).await;
+        results.push(pea_bulk::BulkResult::from_response(pea_id, response).await);
+    }
+    let succeeded = results.iter().filter(|result| result.ok).count();
+    HttpResponse::Ok().json(serde_json::json!({
+        "action": action,
+        "requested": results.len(),
+        "succeeded": succeeded,
+        "failed": results.len() - succeeded,
+        "results": results,
     }))
 }
 
-pub async fn stop_pea(state: web::Data<AppState>, pea_id: web::Path<String>) -> impl Responder {
-    let pea_id_str = pea_id.into_inner();
-
-    // Publish lifecycle command on the runtime topic family.
-    let cmd = serde_json::json!({"action": "stop"});
-    let runtime_topic = shared::mtp::topics::runtime_pea_lifecycle(&pea_id_str);
-    let _ = state
-        .zenoh_session
-        .put(&runtime_topic, cmd.to_string())
+/// Stops a PEA; shared by the API and confirmed approvals.
+pub(crate) async fn perform_stop(
+    state: &AppState,
+    actor_id: Option<String>,
+    pea_id_str: &str,
+) -> HttpResponse {
+    if state.pea_configs.read().await.contains_key(pea_id_str) {
+        record_lifecycle(state, actor_id.clone(), pea_id_str, Phase::IDLE).await;
+        audit::record(
+            state,
+            actor_id,
+            audit::ENTITY_PEA,
+            pea_id_str,
+            audit::ACTION_STOP,
+            serde_json::json!({}),
+        )
         .await;
+    }
+
+    publish_lifecycle_command(state, pea_id_str, "stop").await;
 
     // Publish idle status directly
     {
         let configs = state.pea_configs.read().await;
-        if let Some(config) = configs.get(&pea_id_str) {
+        if let Some(config) = configs.get(pea_id_str) {
             let status = serde_json::json!({
-                "pea_id": &pea_id_str,
+                "pea_id": pea_id_str,
                 "deployed": true,
                 "running": false,
                 "services": config.services.iter().map(|s| serde_json::json!({
@@ -278,38 +1809,354 @@ pub async fn stop_pea(state: web::Data<AppState>, pea_id: web::Path<String>) ->
                 })).collect::<Vec<_>>(),
                 "last_updated": chrono::Utc::now().to_rfc3339(),
             });
-            let status_topic = shared::mtp::topics::pea_status(&pea_id_str);
-            let _ = state
-                .zenoh_session
-                .put(&status_topic, status.to_string())
-                .await;
+            publish_simulated_status(state, config, status).await;
         }
     }
 
     info!("PEA stopped: {}", pea_id_str);
     HttpResponse::Accepted().json(serde_json::json!({
         "status": "stopped",
-        "pea_id": &pea_id_str,
+        "pea_id": pea_id_str,
+    }))
+}
+
+/// Publishes the status a simulated PEA reports after a lifecycle command,
+/// labelled with its origin. PEAs in external mode report their own status from
+/// the real device, so nothing is published for them.
+async fn publish_simulated_status(
+    state: &AppState,
+    config: &PeaConfig,
+    mut status: serde_json::Value,
+) {
+    if config.mode != PeaMode::Simulated {
+        return;
+    }
+    status["origin"] = serde_json::json!(config.mode.origin());
+    let status_topic = shared::mtp::topics::pea_status(&config.id);
+    let _ = state
+        .zenoh_session
+        .put(&status_topic, status.to_string())
+        .await;
+}
+
+/// Publishes the deploy command on the runtime topic family.
+pub(crate) async fn publish_deploy_command(state: &AppState, config: &PeaConfig) {
+    let deploy_msg = serde_json::json!({
+        "action": "deploy",
+        "pea_config": config,
+        "liveliness": shared::mtp::topics::pea_liveliness(&config.id),
+    });
+    let runtime_topic = shared::mtp::topics::runtime_pea_deploy(&config.id);
+    let _ = state
+        .chaos
+        .put(&state.zenoh_session, &runtime_topic, deploy_msg.to_string())
+        .await;
+}
+
+/// Publishes the undeploy command on the runtime topic family.
+pub(crate) async fn publish_undeploy_command(state: &AppState, pea_id: &str) {
+    let undeploy_msg = serde_json::json!({ "action": "undeploy" });
+    let runtime_topic = shared::mtp::topics::runtime_pea_deploy(pea_id);
+    let _ = state
+        .chaos
+        .put(
+            &state.zenoh_session,
+            &runtime_topic,
+            undeploy_msg.to_string(),
+        )
+        .await;
+}
+
+/// Publishes a start/stop command on the runtime topic family.
+pub(crate) async fn publish_lifecycle_command(state: &AppState, pea_id: &str, action: &str) {
+    let cmd = serde_json::json!({ "action": action });
+    let runtime_topic = shared::mtp::topics::runtime_pea_lifecycle(pea_id);
+    let _ = state
+        .chaos
+        .put(&state.zenoh_session, &runtime_topic, cmd.to_string())
+        .await;
+}
+
+/// GET /pea/drift — PEAs whose observed state differs from the state last requested for them
+pub async fn get_pea_drift(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let desired = state.pea_lifecycle.desired().await;
+    let drifts = crate::pea_drift::detect(&state, &scope, &desired, Some(Phase::UNDEPLOYED)).await;
+    // The last run may cover other tenants' PEAs.
+    let last_reconcile = match scope {
+        TenantScope::Deployment => state.pea_reconcile_report.read().await.clone(),
+        TenantScope::Tenant(_) => None,
+    };
+    HttpResponse::Ok().json(serde_json::json!({
+        "count": drifts.len(),
+        "drifts": drifts,
+        "last_reconcile": last_reconcile,
+    }))
+}
+
+/// POST /pea/drift/reconcile — re-issue deploy/start commands for drifted PEAs
+pub async fn reconcile_pea_drift(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let desired = state.pea_lifecycle.desired().await;
+    let drifts = crate::pea_drift::detect(&state, &scope, &desired, Some(Phase::UNDEPLOYED)).await;
+    let report = crate::pea_drift::reconcile(&state, &drifts, "api", false).await;
+    HttpResponse::Ok().json(report)
+}
+
+async fn record_lifecycle(state: &AppState, actor_id: Option<String>, pea_id: &str, next: Phase) {
+    state
+        .pea_lifecycle
+        .record(
+            &state.db_client,
+            pea_id,
+            next,
+            pea_lifecycle::CAUSE_API,
+            actor_id,
+        )
+        .await;
+}
+
+/// GET /pea/{id}/lifecycle-history — deploy/start/stop/undeploy events and uptime in a window
+pub async fn get_pea_lifecycle_history(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    pea_id: web::Path<String>,
+    query: web::Query<StateWindowQuery>,
+) -> impl Responder {
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    let (start_ms, end_ms) = query.resolve();
+    let (Some(start), Some(end)) = (
+        chrono::DateTime::from_timestamp_millis(start_ms),
+        chrono::DateTime::from_timestamp_millis(end_ms),
+    ) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": "Invalid window"}));
+    };
+    let history = async {
+        let initial = crate::db::lifecycle_phase_at(&state.db_client, &pea_id, start).await?;
+        let events =
+            crate::db::list_lifecycle_events(&state.db_client, &pea_id, start, end).await?;
+        anyhow::Ok((initial, events))
+    };
+    let (initial, events) = match history.await {
+        Ok(history) => history,
+        Err(e) => {
+            error!("Failed to load lifecycle history for PEA {}: {}", pea_id, e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({"error": "Failed to load lifecycle history"}));
+        }
+    };
+    let (deployed_ms, running_ms) = pea_lifecycle::uptime(initial, &events, start, end);
+    let window_ms = (end_ms - start_ms).max(0);
+    let running_ratio = if window_ms > 0 {
+        running_ms as f64 / window_ms as f64
+    } else {
+        0.0
+    };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "pea_id": pea_id.as_str(),
+        "start_ms": start_ms,
+        "end_ms": end_ms,
+        "deployed_at_start": initial.deployed,
+        "running_at_start": initial.running,
+        "uptime": {
+            "deployed_ms": deployed_ms,
+            "running_ms": running_ms,
+            "running_ratio": running_ratio,
+        },
+        "events": events,
+    }))
+}
+
+// ─── Service State Machine ───────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct StateWindowQuery {
+    /// Start of the history window as Unix milliseconds (default: 24h ago)
+    pub start_ms: Option<i64>,
+    /// End of the history window as Unix milliseconds (default: now)
+    pub end_ms: Option<i64>,
+    /// Window length used when `start_ms` is omitted (default: 24h)
+    pub window_ms: Option<i64>,
+}
+
+impl StateWindowQuery {
+    fn resolve(&self) -> (i64, i64) {
+        let end_ms = self.end_ms.unwrap_or_else(|| Utc::now().timestamp_millis());
+        let window_ms = self
+            .window_ms
+            .filter(|w| *w > 0)
+            .unwrap_or(24 * 60 * 60 * 1000);
+        (self.start_ms.unwrap_or(end_ms - window_ms), end_ms)
+    }
+}
+
+async fn service_exists(state: &AppState, pea_id: &str, service_tag: &str) -> bool {
+    let configs = state.pea_configs.read().await;
+    configs
+        .get(pea_id)
+        .is_some_and(|c| c.services.iter().any(|s| s.tag == service_tag))
+}
+
+async fn service_state_history(
+    state: &AppState,
+    pea_id: &str,
+    service_tag: &str,
+    start_ms: i64,
+    end_ms: i64,
+) -> Vec<state_analytics::StateSample> {
+    let ts = state.timeseries.read().await;
+    let points = ts.query(&shared::mtp::topics::pea_status(pea_id), start_ms, end_ms);
+    state_analytics::service_state_history(&points, service_tag)
+}
+
+/// GET /pea/{id}/services/{tag}/state-machine — PackML graph with live state and dwell history.
+pub async fn get_service_state_machine(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    query: web::Query<StateWindowQuery>,
+) -> impl Responder {
+    let (pea_id, service_tag) = path.into_inner();
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    if !service_exists(&state, &pea_id, &service_tag).await {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "PEA or service not found"
+        }));
+    }
+
+    let (start_ms, end_ms) = query.resolve();
+    let history = service_state_history(&state, &pea_id, &service_tag, start_ms, end_ms).await;
+    let current = history.last().map(|sample| sample.state);
+    let dwell = state_analytics::dwell_by_state(&history, end_ms);
+
+    let mut model = state_analytics::state_machine_model(current, &dwell);
+    model["pea_id"] = serde_json::json!(pea_id);
+    model["service_tag"] = serde_json::json!(service_tag);
+    model["start_ms"] = serde_json::json!(start_ms);
+    model["end_ms"] = serde_json::json!(end_ms);
+    HttpResponse::Ok().json(model)
+}
+
+/// GET /pea/{id}/services/{tag}/state-stats — dwell time, transitions and abnormal ratio per state.
+pub async fn get_service_state_stats(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    query: web::Query<StateWindowQuery>,
+) -> impl Responder {
+    let (pea_id, service_tag) = path.into_inner();
+    if let Some(response) = reject_foreign_pea(&state, &req, &pea_id).await {
+        return response;
+    }
+    if !service_exists(&state, &pea_id, &service_tag).await {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "PEA or service not found"
+        }));
+    }
+
+    let (start_ms, end_ms) = query.resolve();
+    let history = service_state_history(&state, &pea_id, &service_tag, start_ms, end_ms).await;
+    let stats = state_analytics::state_stats(&history, end_ms);
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "pea_id": pea_id,
+        "service_tag": service_tag,
+        "start_ms": start_ms,
+        "end_ms": end_ms,
+        "sample_count": history.len(),
+        "stats": stats,
     }))
 }
 
+/// Tenant-scoped callers only reach PEAs their tenant owns; anything else looks missing.
+pub async fn reject_foreign_pea(
+    state: &AppState,
+    req: &HttpRequest,
+    pea_id: &str,
+) -> Option<HttpResponse> {
+    let scope = match tenancy::scope_for(state, req).await {
+        Ok(scope) => scope,
+        Err(e) => return Some(e.response()),
+    };
+    if let TenantScope::Deployment = scope {
+        return None;
+    }
+    let owned = state
+        .pea_configs
+        .read()
+        .await
+        .get(pea_id)
+        .is_some_and(|config| scope.allows(config.tenant_id.as_deref()));
+    (!owned).then(|| HttpResponse::NotFound().json(serde_json::json!({"error": "PEA not found"})))
+}
+
 // ─── Recipe CRUD ─────────────────────────────────────────────────────────────
 
-pub async fn list_recipes(state: web::Data<AppState>) -> impl Responder {
+pub async fn list_recipes(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
     let recipes = state.recipes.read().await;
-    let list: Vec<&Recipe> = recipes.values().collect();
+    let list: Vec<&Recipe> = recipes
+        .values()
+        .filter(|recipe| scope.allows(recipe.tenant_id.as_deref()))
+        .collect();
     HttpResponse::Ok().json(list)
 }
 
-pub async fn create_recipe(state: web::Data<AppState>, body: web::Json<Recipe>) -> impl Responder {
+pub async fn create_recipe(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<Recipe>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
     let mut recipe = body.into_inner();
     if recipe.id.is_empty() {
         recipe.id = Uuid::new_v4().to_string();
     }
     recipe.created_at = Utc::now();
+    if let Some(tenant_id) = scope.tenant_id() {
+        recipe.tenant_id = Some(tenant_id.to_string());
+    }
+    let steps = match expanded_steps(&state, &recipe).await {
+        Ok(steps) => steps,
+        Err(response) => return response,
+    };
+    if let Some(response) = reject_invalid_triggers(&state, &recipe).await {
+        return response;
+    }
+    if let TenantScope::Tenant(tenant) = &scope {
+        if state.recipes.read().await.contains_key(&recipe.id) {
+            return HttpResponse::Conflict()
+                .json(serde_json::json!({"error": "Recipe id is already in use"}));
+        }
+        if let Some(response) = reject_foreign_steps(&state, &scope, &steps).await {
+            return response;
+        }
+        let (_, used) = crate::tenant_handlers::usage_counts(&state, &tenant.id).await;
+        if let Some(response) = tenancy::check_quota(tenant.quotas.max_recipes, used, 1, "recipes")
+        {
+            return response;
+        }
+    }
 
     let id = recipe.id.clone();
-    persist_recipe(&state.recipe_dir, &recipe);
+    config_store::save_recipe(state.config_store.as_ref(), &recipe).await;
 
     let mut recipes = state.recipes.write().await;
     recipes.insert(id, recipe.clone());
@@ -319,13 +2166,45 @@ pub async fn create_recipe(state: web::Data<AppState>, body: web::Json<Recipe>)
 }
 
 pub async fn update_recipe(
+    req: HttpRequest,
     state: web::Data<AppState>,
     recipe_id: web::Path<String>,
     body: web::Json<Recipe>,
 ) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let existing_tenant = state
+        .recipes
+        .read()
+        .await
+        .get(recipe_id.as_str())
+        .map(|recipe| recipe.tenant_id.clone());
+    if existing_tenant
+        .as_ref()
+        .is_some_and(|owner| !scope.allows(owner.as_deref()))
+    {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Recipe not found"}));
+    }
+
     let mut recipe = body.into_inner();
     recipe.id = recipe_id.to_string();
-    persist_recipe(&state.recipe_dir, &recipe);
+    recipe.tenant_id = match scope.tenant_id() {
+        Some(tenant_id) => Some(tenant_id.to_string()),
+        None => recipe.tenant_id.or(existing_tenant.flatten()),
+    };
+    let steps = match expanded_steps(&state, &recipe).await {
+        Ok(steps) => steps,
+        Err(response) => return response,
+    };
+    if let Some(response) = reject_invalid_triggers(&state, &recipe).await {
+        return response;
+    }
+    if let Some(response) = reject_foreign_steps(&state, &scope, &steps).await {
+        return response;
+    }
+    config_store::save_recipe(state.config_store.as_ref(), &recipe).await;
 
     let mut recipes = state.recipes.write().await;
     recipes.insert(recipe.id.clone(), recipe.clone());
@@ -333,19 +2212,34 @@ pub async fn update_recipe(
 }
 
 pub async fn delete_recipe(
+    req: HttpRequest,
     state: web::Data<AppState>,
     recipe_id: web::Path<String>,
 ) -> impl Responder {
+    if let Some(response) = reject_foreign_recipe(&state, &req, &recipe_id).await {
+        return response;
+    }
     let mut recipes = state.recipes.write().await;
     recipes.remove(recipe_id.as_str());
-    delete_recipe_file(&state.recipe_dir, recipe_id.as_str());
+    config_store::delete_recipe(state.config_store.as_ref(), recipe_id.as_str()).await;
     HttpResponse::NoContent().finish()
 }
 
+#[derive(Debug, Default, Deserialize)]
+pub struct ExecuteQuery {
+    /// Execution environment whose bindings the run uses; empty for none.
+    pub environment: Option<String>,
+}
+
 pub async fn execute_recipe(
+    req: HttpRequest,
     state: web::Data<AppState>,
     recipe_id: web::Path<String>,
+    query: web::Query<ExecuteQuery>,
 ) -> impl Responder {
+    if let Some(response) = reject_foreign_recipe(&state, &req, &recipe_id).await {
+        return response;
+    }
     let recipe = {
         let recipes = state.recipes.read().await;
         match recipes.get(recipe_id.as_str()) {
@@ -357,437 +2251,362 @@ pub async fn execute_recipe(
         }
     };
 
-    let execution_id = Uuid::new_v4().to_string();
-    let mut steps = recipe.steps.clone();
-    steps.sort_by_key(|s| s.order);
-    let total_steps = steps.len();
-
-    // Enforce orchestration topology: each cross-PEA transition must follow an edge.
-    {
-        let topology = state.topology.read().await;
-        if steps.len() > 1 {
-            for pair in steps.windows(2) {
-                let prev = &pair[0];
-                let next = &pair[1];
-                if prev.pea_id == next.pea_id {
-                    continue;
-                }
-                if topology.edges.is_empty() {
-                    return HttpResponse::BadRequest().json(serde_json::json!({
-                        "error": "Topology is empty. Define PEA connections before executing cross-PEA recipes."
-                    }));
-                }
-                let allowed = topology
-                    .edges
-                    .iter()
-                    .any(|e| e.from == prev.pea_id && e.to == next.pea_id);
-                if !allowed {
-                    return HttpResponse::BadRequest().json(serde_json::json!({
-                        "error": format!(
-                            "Topology violation: no connection from '{}' to '{}' for recipe step transition.",
-                            prev.pea_id, next.pea_id
-                        )
-                    }));
-                }
-            }
-        }
-    }
-
+    let environment = crate::environments::select(&state, query.environment.as_deref());
+    let execution_id = match crate::recipe_executor::start(
+        &state,
+        &recipe,
+        crate::recipe_executor::ORIGIN_API,
+        environment,
+    )
+    .await
     {
-        let mut execs = state.recipe_executions.write().await;
-        execs.insert(
-            execution_id.clone(),
-            serde_json::json!({
-                "execution_id": execution_id,
-                "recipe_id": recipe.id,
-                "recipe_name": recipe.name,
-                "current_step": 0,
-                "total_steps": total_steps,
-                "step_statuses": vec!["pending"; total_steps],
-                "state": "running",
-                "started_at": chrono::Utc::now().to_rfc3339(),
-                "updated_at": chrono::Utc::now().to_rfc3339(),
-            }),
-        );
-    }
-
-    let zenoh = state.zenoh_session.clone();
-    let executions = state.recipe_executions.clone();
-    let timeseries = state.timeseries.clone();
-    let execution_id_task = execution_id.clone();
-    tokio::spawn(async move {
-        let mut step_statuses = vec!["pending".to_string(); total_steps];
-
-        for (idx, step) in steps.iter().enumerate() {
-            step_statuses[idx] = "executing".to_string();
-            update_exec_status(
-                &executions,
-                &execution_id_task,
-                idx + 1,
-                total_steps,
-                &step_statuses,
-                "running",
-            )
-            .await;
-
-            let topic = shared::mtp::topics::pea_service_command(&step.pea_id, &step.service_tag);
-            let payload = serde_json::json!({
-                "command": step.command,
-                "command_code": step.command.code(),
-                "procedure_id": step.procedure_id,
-                "parameters": step.parameters,
-                "timestamp": chrono::Utc::now().to_rfc3339(),
-            });
-
-            if let Err(e) = zenoh.put(&topic, payload.to_string()).await {
-                error!("Recipe step publish failed for {}: {}", topic, e);
-                step_statuses[idx] = "failed".to_string();
-                update_exec_status(
-                    &executions,
-                    &execution_id_task,
-                    idx + 1,
-                    total_steps,
-                    &step_statuses,
-                    "failed",
-                )
-                .await;
-                return;
-            }
-
-            if let Some(wait_state) = step.wait_for_state {
-                let timeout_ms = step.timeout_ms.unwrap_or(30000);
-                let deadline = std::time::Instant::now() + Duration::from_millis(timeout_ms);
-                let status_key = shared::mtp::topics::pea_status(&step.pea_id);
-                let mut reached = false;
-
-                while std::time::Instant::now() < deadline {
-                    {
-                        let ts = timeseries.read().await;
-                        if let Some(buf) = ts.data.get(&status_key) {
-                            if let Some(last) = buf.back() {
-                                if let Some(services) =
-                                    last.value.get("services").and_then(|v| v.as_array())
-                                {
-                                    if services.iter().any(|svc| {
-                                        svc.get("tag").and_then(|t| t.as_str())
-                                            == Some(step.service_tag.as_str())
-                                            && svc.get("state").and_then(|s| s.as_str())
-                                                == Some(service_state_name(wait_state))
-                                    }) {
-                                        reached = true;
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    tokio::time::sleep(Duration::from_millis(500)).await;
-                }
-
-                if !reached {
-                    step_statuses[idx] = "failed".to_string();
-                    update_exec_status(
-                        &executions,
-                        &execution_id_task,
-                        idx + 1,
-                        total_steps,
-                        &step_statuses,
-                        "failed",
-                    )
-                    .await;
-                    return;
-                }
-            }
-
-            step_statuses[idx] = "completed".to_string();
-            update_exec_status(
-                &executions,
-                &execution_id_task,
-                idx + 1,
-                total_steps,
-                &step_statuses,
-                "running",
-            )
-            .await;
-        }
-
-        update_exec_status(
-            &executions,
-            &execution_id_task,
-            total_steps,
-            total_steps,
-            &step_statuses,
-            "completed",
-        )
-        .await;
-    });
+        Ok(execution_id) => execution_id,
+        Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({"error": e})),
+    };
 
     HttpResponse::Accepted().json(serde_json::json!({
         "status": "executing",
         "execution_id": execution_id,
         "recipe_id": recipe_id.as_str(),
+        "environment": environment,
     }))
 }
 
-pub async fn list_recipe_executions(state: web::Data<AppState>) -> impl Responder {
+/// POST /recipes/{id}/campaign — runs the recipe several times in a row,
+/// each run with its own parameter values
+pub async fn start_recipe_campaign(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    recipe_id: web::Path<String>,
+    body: web::Json<CampaignRequest>,
+) -> impl Responder {
+    if let Some(response) = reject_foreign_recipe(&state, &req, &recipe_id).await {
+        return response;
+    }
+    let Some(recipe) = state.recipes.read().await.get(recipe_id.as_str()).cloned() else {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Recipe not found"}));
+    };
+    let request = body.into_inner();
+    let environment =
+        crate::environments::select(&state, request.environment.as_deref()).map(str::to_string);
+    match recipe_campaign::start(state.clone(), recipe, request, environment).await {
+        Ok(campaign) => HttpResponse::Accepted().json(campaign),
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({"error": e})),
+    }
+}
+
+pub async fn list_recipe_campaigns(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let campaigns = state.recipe_campaigns.read().await;
+    let mut list: Vec<&Campaign> = campaigns
+        .values()
+        .filter(|campaign| scope.allows(campaign.tenant_id.as_deref()))
+        .collect();
+    list.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    HttpResponse::Ok().json(list)
+}
+
+pub async fn get_recipe_campaign(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    campaign_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    match state
+        .recipe_campaigns
+        .read()
+        .await
+        .get(campaign_id.as_str())
+        .filter(|campaign| scope.allows(campaign.tenant_id.as_deref()))
+    {
+        Some(campaign) => HttpResponse::Ok().json(campaign),
+        None => HttpResponse::NotFound().json(serde_json::json!({"error": "Campaign not found"})),
+    }
+}
+
+/// POST /recipes/campaigns/{id}/abort — aborts the current run and skips the rest
+pub async fn abort_recipe_campaign(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    campaign_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let visible = state
+        .recipe_campaigns
+        .read()
+        .await
+        .get(campaign_id.as_str())
+        .is_some_and(|campaign| scope.allows(campaign.tenant_id.as_deref()));
+    if !visible {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Campaign not found"}));
+    }
+    match recipe_campaign::request_abort(&state, &campaign_id).await {
+        Ok(()) => HttpResponse::Accepted().json(serde_json::json!({
+            "status": "aborting",
+            "campaign_id": campaign_id.as_str(),
+        })),
+        Err(e) => HttpResponse::Conflict().json(serde_json::json!({"error": e})),
+    }
+}
+
+pub async fn list_recipe_executions(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let visible = visible_recipe_ids(&state, &scope).await;
     let execs = state.recipe_executions.read().await;
-    let list: Vec<serde_json::Value> = execs.values().cloned().collect();
+    let list: Vec<serde_json::Value> = execs
+        .values()
+        .filter(|exec| execution_visible(exec, visible.as_ref()))
+        .cloned()
+        .collect();
     HttpResponse::Ok().json(list)
 }
 
 pub async fn get_recipe_execution(
+    req: HttpRequest,
     state: web::Data<AppState>,
     execution_id: web::Path<String>,
 ) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let visible = visible_recipe_ids(&state, &scope).await;
     let execs = state.recipe_executions.read().await;
-    match execs.get(execution_id.as_str()) {
+    match execs
+        .get(execution_id.as_str())
+        .filter(|exec| execution_visible(exec, visible.as_ref()))
+    {
         Some(status) => HttpResponse::Ok().json(status),
         None => HttpResponse::NotFound().json(serde_json::json!({"error": "Execution not found"})),
     }
 }
 
-// ─── Persistence Helpers ─────────────────────────────────────────────────────
-
-fn persist_pea_config(dir: &str, config: &PeaConfig) {
-    if let Err(e) = std::fs::create_dir_all(dir) {
-        error!("Failed to create PEA config dir {}: {}", dir, e);
-        return;
+/// POST /recipes/executions/{id}/abort — stops a running execution before its next step.
+pub async fn abort_recipe_execution(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    execution_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let visible = visible_recipe_ids(&state, &scope).await;
+    let exists = state
+        .recipe_executions
+        .read()
+        .await
+        .get(execution_id.as_str())
+        .is_some_and(|exec| execution_visible(exec, visible.as_ref()));
+    if !exists {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Execution not found"}));
     }
-    let path = format!("{}/{}.json", dir, config.id);
-    match serde_json::to_string_pretty(config) {
-        Ok(json) => {
-            if let Err(e) = std::fs::write(&path, json) {
-                error!("Failed to persist PEA config to {}: {}", path, e);
-            }
-        }
-        Err(e) => error!("Failed to serialize PEA config: {}", e),
+    match crate::recipe_executor::request_abort(&state.recipe_executions, &execution_id).await {
+        Ok(()) => HttpResponse::Accepted().json(serde_json::json!({
+            "status": "aborting",
+            "execution_id": execution_id.as_str(),
+        })),
+        Err(e) => HttpResponse::Conflict().json(serde_json::json!({"error": e})),
     }
 }
 
-fn delete_pea_file(dir: &str, pea_id: &str) {
-    let path = format!("{}/{}.json", dir, pea_id);
-    if let Err(e) = std::fs::remove_file(&path) {
-        if e.kind() != std::io::ErrorKind::NotFound {
-            error!("Failed to delete PEA config file {}: {}", path, e);
-        }
-    }
+/// Recipe ids visible to a tenant; `None` at deployment level, where everything is.
+pub(crate) async fn visible_recipe_ids(
+    state: &AppState,
+    scope: &TenantScope,
+) -> Option<std::collections::HashSet<String>> {
+    scope.tenant_id()?;
+    let recipes = state.recipes.read().await;
+    Some(
+        recipes
+            .values()
+            .filter(|recipe| scope.allows(recipe.tenant_id.as_deref()))
+            .map(|recipe| recipe.id.clone())
+            .collect(),
+    )
 }
 
-fn persist_recipe(dir: &str, recipe: &Recipe) {
-    if let Err(e) = std::fs::create_dir_all(dir) {
-        error!("Failed to create recipe dir {}: {}", dir, e);
-        return;
-    }
-    let path = format!("{}/{}.json", dir, recipe.id);
-    match serde_json::to_string_pretty(recipe) {
-        Ok(json) => {
-            if let Err(e) = std::fs::write(&path, json) {
-                error!("Failed to persist recipe to {}: {}", path, e);
-            }
-        }
-        Err(e) => error!("Failed to serialize recipe: {}", e),
-    }
+pub(crate) fn execution_visible(
+    exec: &serde_json::Value,
+    visible: Option<&std::collections::HashSet<String>>,
+) -> bool {
+    visible.is_none_or(|ids| {
+        exec.get("recipe_id")
+            .and_then(|id| id.as_str())
+            .is_some_and(|id| ids.contains(id))
+    })
 }
 
-fn delete_recipe_file(dir: &str, recipe_id: &str) {
-    let path = format!("{}/{}.json", dir, recipe_id);
-    if let Err(e) = std::fs::remove_file(&path) {
-        if e.kind() != std::io::ErrorKind::NotFound {
-            error!("Failed to delete recipe file {}: {}", path, e);
-        }
+async fn reject_foreign_recipe(
+    state: &AppState,
+    req: &HttpRequest,
+    recipe_id: &str,
+) -> Option<HttpResponse> {
+    let scope = match tenancy::scope_for(state, req).await {
+        Ok(scope) => scope,
+        Err(e) => return Some(e.response()),
+    };
+    if let TenantScope::Deployment = scope {
+        return None;
     }
+    let owned = state
+        .recipes
+        .read()
+        .await
+        .get(recipe_id)
+        .is_some_and(|recipe| scope.allows(recipe.tenant_id.as_deref()));
+    (!owned)
+        .then(|| HttpResponse::NotFound().json(serde_json::json!({"error": "Recipe not found"})))
 }
 
-async fn update_exec_status(
-    executions: &tokio::sync::RwLock<std::collections::HashMap<String, serde_json::Value>>,
-    execution_id: &str,
-    current_step: usize,
-    total_steps: usize,
-    step_statuses: &[String],
-    state: &str,
-) {
-    let mut execs = executions.write().await;
-    let mut base = execs
-        .get(execution_id)
-        .cloned()
-        .unwrap_or_else(|| serde_json::json!({}));
-    base["execution_id"] = serde_json::json!(execution_id);
-    base["current_step"] = serde_json::json!(current_step);
-    base["total_steps"] = serde_json::json!(total_steps);
-    base["step_statuses"] = serde_json::json!(step_statuses);
-    base["state"] = serde_json::json!(state);
-    base["updated_at"] = serde_json::json!(chrono::Utc::now().to_rfc3339());
-    execs.insert(execution_id.to_string(), base);
-}
-
-fn service_state_name(state: ServiceState) -> &'static str {
-    match state {
-        ServiceState::Idle => "Idle",
-        ServiceState::Starting => "Starting",
-        ServiceState::Execute => "Execute",
-        ServiceState::Completing => "Completing",
-        ServiceState::Completed => "Completed",
-        ServiceState::Pausing => "Pausing",
-        ServiceState::Paused => "Paused",
-        ServiceState::Resuming => "Resuming",
-        ServiceState::Holding => "Holding",
-        ServiceState::Held => "Held",
-        ServiceState::Unholding => "Unholding",
-        ServiceState::Stopping => "Stopping",
-        ServiceState::Stopped => "Stopped",
-        ServiceState::Aborting => "Aborting",
-        ServiceState::Aborted => "Aborted",
-        ServiceState::Resetting => "Resetting",
-    }
-}
-
-pub fn load_recipes(dir: &str) -> std::collections::HashMap<String, Recipe> {
-    let mut recipes = std::collections::HashMap::new();
-
-    let entries = match std::fs::read_dir(dir) {
-        Ok(entries) => entries,
-        Err(e) => {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                if let Err(create_err) = std::fs::create_dir_all(dir) {
-                    error!("Failed to create recipe dir {}: {}", dir, create_err);
-                }
-            }
-            return recipes;
-        }
-    };
-
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if path.extension().is_some_and(|ext| ext == "json") {
-            match std::fs::read_to_string(&path) {
-                Ok(content) => match serde_json::from_str::<Recipe>(&content) {
-                    Ok(recipe) => {
-                        info!("Loaded recipe: {} ({})", recipe.name, recipe.id);
-                        recipes.insert(recipe.id.clone(), recipe);
-                    }
-                    Err(e) => error!("Failed to parse recipe {:?}: {}", path, e),
-                },
-                Err(e) => error!("Failed to read recipe {:?}: {}", path, e),
-            }
-        }
-    }
-
-    info!("Loaded {} recipes", recipes.len());
-    recipes
+/// The steps a recipe runs with its library steps expanded, or why they cannot be.
+async fn expanded_steps(
+    state: &AppState,
+    recipe: &Recipe,
+) -> Result<Vec<RecipeStep>, HttpResponse> {
+    crate::step_library::expand(recipe, &*state.step_library.read().await)
+        .map_err(|e| HttpResponse::BadRequest().json(serde_json::json!({"error": e})))
 }
 
-pub fn load_pea_configs(dir: &str) -> std::collections::HashMap<String, PeaConfig> {
-    let mut configs = std::collections::HashMap::new();
-
-    let entries = match std::fs::read_dir(dir) {
-        Ok(entries) => entries,
-        Err(e) => {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                if let Err(create_err) = std::fs::create_dir_all(dir) {
-                    error!("Failed to create PEA config dir {}: {}", dir, create_err);
-                }
-            } else {
-                error!("Failed to read PEA config dir {}: {}", dir, e);
-            }
-            return configs;
-        }
-    };
-
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if path.extension().is_some_and(|ext| ext == "json") {
-            match std::fs::read_to_string(&path) {
-                Ok(content) => match serde_json::from_str::<PeaConfig>(&content) {
-                    Ok(config) => {
-                        info!("Loaded PEA config: {} ({})", config.name, config.id);
-                        configs.insert(config.id.clone(), config);
-                    }
-                    Err(e) => error!("Failed to parse PEA config {:?}: {}", path, e),
-                },
-                Err(e) => error!("Failed to read PEA config {:?}: {}", path, e),
-            }
-        }
-    }
+async fn reject_invalid_triggers(state: &AppState, recipe: &Recipe) -> Option<HttpResponse> {
+    let recipes = state.recipes.read().await;
+    let configs = state.pea_configs.read().await;
+    recipe_triggers::validate(recipe, &recipes, &configs)
+        .err()
+        .map(|e| HttpResponse::BadRequest().json(serde_json::json!({"error": e})))
+}
 
-    info!("Loaded {} PEA configurations", configs.len());
-    configs
+/// A tenant's recipe may only drive PEAs owned by the same tenant.
+async fn reject_foreign_steps(
+    state: &AppState,
+    scope: &TenantScope,
+    steps: &[RecipeStep],
+) -> Option<HttpResponse> {
+    scope.tenant_id()?;
+    let configs = state.pea_configs.read().await;
+    let foreign: Vec<&str> = steps
+        .iter()
+        .filter(|step| {
+            !configs
+                .get(&step.pea_id)
+                .is_some_and(|config| scope.allows(config.tenant_id.as_deref()))
+        })
+        .map(|step| step.pea_id.as_str())
+        .collect();
+    (!foreign.is_empty()).then(|| {
+        HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Recipe steps reference PEAs outside the tenant",
+            "pea_ids": foreign,
+        }))
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use shared::mtp::{OpcUaConfig, ServiceConfig, WriterInfo};
-
-    fn unique_temp_dir(name: &str) -> String {
-        let dir = std::env::temp_dir().join(format!(
-            "fendtastic-pea-handlers-{name}-{}",
-            Uuid::new_v4()
-        ));
-        dir.to_string_lossy().to_string()
-    }
-
-    fn sample_pea_config(id: &str, name: &str) -> PeaConfig {
-        PeaConfig {
-            id: id.to_string(),
-            name: name.to_string(),
-            version: "1.0.0".to_string(),
-            description: "test pea".to_string(),
-            writer: WriterInfo {
-                name: "test-writer".to_string(),
-                version: "1.0.0".to_string(),
-                vendor: "tests".to_string(),
-            },
-            services: vec![ServiceConfig {
-                tag: "svc.main".to_string(),
-                name: "Main Service".to_string(),
-                description: "primary service".to_string(),
-                config_parameters: vec![],
-                procedures: vec![],
-            }],
-            active_elements: vec![],
-            opcua_config: OpcUaConfig {
-                endpoint: "opc.tcp://127.0.0.1:4841/test".to_string(),
-                namespace_uri: "urn:fendtastic:test".to_string(),
-                security_policy: "Basic256Sha256".to_string(),
-            },
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        }
-    }
 
     #[test]
-    fn load_pea_configs_reads_local_json_files() {
-        let dir = unique_temp_dir("load-peas");
-        let config = sample_pea_config("pea-1", "Test PEA");
-        persist_pea_config(&dir, &config);
-
-        let configs = load_pea_configs(&dir);
-
-        assert_eq!(configs.len(), 1);
-        let loaded = configs.get("pea-1").expect("missing persisted pea");
-        assert_eq!(loaded.name, "Test PEA");
-
-        let _ = std::fs::remove_dir_all(&dir);
+    fn sparkline_keeps_the_latest_numeric_points_in_order() {
+        let buf: std::collections::VecDeque<crate::state::TimeSeriesPoint> = [
+            (1, serde_json::json!(1.0)),
+            (2, serde_json::json!({"result": {"value": 2.5}})),
+            (3, serde_json::json!("offline")),
+            (4, serde_json::json!({"v": 4})),
+        ]
+        .into_iter()
+        .map(|(timestamp_ms, value)| crate::state::TimeSeriesPoint {
+            timestamp_ms,
+            value,
+            producer: None,
+        })
+        .collect();
+
+        assert_eq!(
+            sparkline(&buf, 2),
+            vec![
+                serde_json::json!({"t": 2, "v": 2.5}),
+                serde_json::json!({"t": 4, "v": 4.0}),
+            ]
+        );
+        assert_eq!(sparkline(&buf, 10).len(), 3);
     }
 
     #[test]
-    fn load_recipes_reads_local_json_files() {
-        let dir = unique_temp_dir("load-recipes");
-        let recipe = Recipe {
-            id: "recipe-1".to_string(),
-            name: "Test Recipe".to_string(),
-            description: "test recipe".to_string(),
-            steps: vec![],
-            created_at: Utc::now(),
+    fn pea_list_is_filtered_sorted_and_paged() {
+        let pea = |id: &str, name: &str, service: &str| -> PeaConfig {
+            serde_json::from_value(serde_json::json!({
+                "id": id,
+                "name": name,
+                "version": "1",
+                "description": "",
+                "writer": {"name": "", "version": "", "vendor": ""},
+                "services": [{
+                    "tag": service,
+                    "name": service,
+                    "description": "",
+                    "config_parameters": [],
+                    "procedures": [],
+                }],
+                "active_elements": [],
+                "opcua_config": {"endpoint": "", "namespace_uri": "", "security_policy": ""},
+                "created_at": "2026-01-01T00:00:00Z",
+                "updated_at": "2026-01-01T00:00:00Z",
+            }))
+            .unwrap()
+        };
+        let peas = vec![
+            pea("r2", "Reactor B", "Heat"),
+            pea("d1", "Dosing", "Dose"),
+            pea("r1", "reactor A", "Heat"),
+            pea("r3", "Reactor C", "Cool"),
+        ];
+        let ids = |page: &serde_json::Value| -> Vec<String> {
+            page["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["id"].as_str().unwrap().to_string())
+                .collect()
         };
-        persist_recipe(&dir, &recipe);
-
-        let recipes = load_recipes(&dir);
 
-        assert_eq!(recipes.len(), 1);
-        let loaded = recipes.get("recipe-1").expect("missing persisted recipe");
-        assert_eq!(loaded.name, "Test Recipe");
+        let query = ListPeasQuery {
+            name_contains: Some("REACTOR".to_string()),
+            service_tag: Some("Heat".to_string()),
+            sort_by: Some("-name".to_string()),
+            ..Default::default()
+        };
+        let page = query.page(peas.clone(), |config| config).unwrap();
+        assert_eq!(page["total"], 2);
+        assert_eq!(ids(&page), vec!["r2", "r1"]);
+
+        let query = ListPeasQuery {
+            page: Some(2),
+            page_size: Some(3),
+            ..Default::default()
+        };
+        let page = query.page(peas.clone(), |config| config).unwrap();
+        assert_eq!(page["total"], 4);
+        assert_eq!(ids(&page), vec!["r3"]);
 
-        let _ = std::fs::remove_dir_all(&dir);
+        let query = ListPeasQuery {
+            sort_by: Some("colour".to_string()),
+            ..Default::default()
+        };
+        assert!(query.page(peas, |config| config).is_err());
     }
 }
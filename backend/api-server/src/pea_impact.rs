@@ -0,0 +1,164 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+use crate::alarm_grouping;
+use crate::recipe_executor;
+use crate::state::{AppState, PolTopology};
+use crate::tenancy::TenantScope;
+
+/// What depends on a PEA: the PEAs downstream of it in the topology, recipe
+/// executions driving it and its open alarms.
+#[derive(Debug, Serialize)]
+pub struct Impact {
+    pub pea_id: String,
+    pub downstream_peas: Vec<DownstreamPea>,
+    pub running_recipes: Vec<RunningRecipe>,
+    pub open_alarms: Vec<OpenAlarm>,
+    /// Downstream PEAs or running recipes would break without this PEA.
+    pub has_dependents: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DownstreamPea {
+    pub pea_id: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunningRecipe {
+    pub execution_id: String,
+    pub recipe_id: String,
+    pub recipe_name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OpenAlarm {
+    pub id: String,
+    pub severity: String,
+    pub event: String,
+    pub status: String,
+}
+
+/// Every PEA downstream of `pea` in the topology, following edges transitively.
+pub fn downstream_peas(topology: &PolTopology, pea: &str) -> BTreeSet<String> {
+    let mut found = BTreeSet::new();
+    let mut frontier = vec![pea.to_string()];
+    while let Some(current) = frontier.pop() {
+        for edge in topology.edges.iter().filter(|edge| edge.from == current) {
+            if edge.to != pea && found.insert(edge.to.clone()) {
+                frontier.push(edge.to.clone());
+            }
+        }
+    }
+    found
+}
+
+/// Impact of taking `pea_id` away, limited to what `scope` may see.
+pub async fn analyze(state: &AppState, scope: &TenantScope, pea_id: &str) -> Impact {
+    let downstream_peas: Vec<DownstreamPea> = {
+        let downstream = downstream_peas(&*state.topology.read().await, pea_id);
+        let configs = state.pea_configs.read().await;
+        downstream
+            .into_iter()
+            .filter_map(|id| match configs.get(&id) {
+                Some(config) if !scope.allows(config.tenant_id.as_deref()) => None,
+                config => Some(DownstreamPea {
+                    name: config.map(|config| config.name.clone()),
+                    pea_id: id,
+                }),
+            })
+            .collect()
+    };
+
+    // Executions are matched on the steps they run, after library expansion
+    // and environment binding.
+    let running: Vec<(String, String, Option<String>)> = state
+        .recipe_executions
+        .read()
+        .await
+        .values()
+        .filter(|exec| exec["state"] == "running")
+        .filter_map(|exec| {
+            Some((
+                exec["execution_id"].as_str()?.to_string(),
+                exec["recipe_id"].as_str()?.to_string(),
+                exec["environment"].as_str().map(str::to_string),
+            ))
+        })
+        .collect();
+    let mut running_recipes = Vec::new();
+    for (execution_id, recipe_id, environment) in running {
+        let Some(recipe) = state.recipes.read().await.get(&recipe_id).cloned() else {
+            continue;
+        };
+        if !scope.allows(recipe.tenant_id.as_deref()) {
+            continue;
+        }
+        let drives_pea = recipe_executor::resolve_steps(state, &recipe, environment.as_deref())
+            .await
+            .is_ok_and(|steps| steps.iter().any(|step| step.pea_id == pea_id));
+        if drives_pea {
+            running_recipes.push(RunningRecipe {
+                execution_id,
+                recipe_id,
+                recipe_name: recipe.name,
+            });
+        }
+    }
+    running_recipes.sort_by(|a, b| a.execution_id.cmp(&b.execution_id));
+
+    let mut open_alarms: Vec<OpenAlarm> = state
+        .alarms
+        .read()
+        .await
+        .values()
+        .filter(|alarm| alarm_grouping::is_active(alarm))
+        .filter(|alarm| alarm_grouping::pea_of(&alarm.source) == pea_id)
+        .map(|alarm| OpenAlarm {
+            id: alarm.id.clone(),
+            severity: alarm.severity.clone(),
+            event: alarm.event.clone(),
+            status: alarm.status.clone(),
+        })
+        .collect();
+    open_alarms.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Impact {
+        pea_id: pea_id.to_string(),
+        has_dependents: !downstream_peas.is_empty() || !running_recipes.is_empty(),
+        downstream_peas,
+        running_recipes,
+        open_alarms,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::PolEdge;
+
+    #[test]
+    fn downstream_follows_edges_transitively_and_survives_cycles() {
+        let topology = PolTopology {
+            edges: [
+                ("feed", "reactor"),
+                ("reactor", "dryer"),
+                ("dryer", "packer"),
+                ("packer", "reactor"),
+                ("cip", "feed"),
+            ]
+            .into_iter()
+            .map(|(from, to)| PolEdge {
+                from: from.to_string(),
+                to: to.to_string(),
+            })
+            .collect(),
+            updated_at: String::new(),
+        };
+
+        let downstream: Vec<String> = downstream_peas(&topology, "reactor").into_iter().collect();
+        assert_eq!(downstream, vec!["dryer", "packer"]);
+        assert_eq!(downstream_peas(&topology, "cip").len(), 4);
+        assert!(downstream_peas(&topology, "unknown").is_empty());
+    }
+}
@@ -0,0 +1,628 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::Serialize;
+use shared::mtp::{
+    AnaViewConfig, AnalogParameter, BinViewConfig, BinaryParameter, DIntParameter, DIntViewConfig,
+    IndicatorElement, OpcUaConfig, PeaConfig, PeaMode, ProcedureConfig, ProtocolType,
+    ServiceConfig, ServiceParameter, StringParameter, StringViewConfig, TagMapping, WriterInfo,
+};
+use std::collections::HashMap;
+use std::io::Cursor;
+
+/// One spreadsheet row keyed by lower-cased column header, with its 1-based
+/// line number in the source file (header = row 1).
+#[derive(Debug, Clone)]
+pub struct SheetRow {
+    pub row: usize,
+    pub cells: HashMap<String, String>,
+}
+
+impl SheetRow {
+    fn get(&self, aliases: &[&str]) -> Option<&str> {
+        aliases
+            .iter()
+            .find_map(|alias| self.cells.get(*alias))
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportIssue {
+    pub row: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub rows_total: usize,
+    pub rows_imported: usize,
+    pub errors: Vec<ImportIssue>,
+    pub warnings: Vec<ImportIssue>,
+}
+
+impl ImportReport {
+    fn error(&mut self, row: usize, message: impl Into<String>) {
+        self.errors.push(ImportIssue {
+            row,
+            message: message.into(),
+        });
+    }
+
+    fn warn(&mut self, row: usize, message: impl Into<String>) {
+        self.warnings.push(ImportIssue {
+            row,
+            message: message.into(),
+        });
+    }
+}
+
+const PEA_COLUMNS: [&str; 3] = ["pea", "pea name", "pea_name"];
+const SERVICE_COLUMNS: [&str; 3] = ["service", "service tag", "service_tag"];
+const TAG_COLUMNS: [&str; 3] = ["tag", "element tag", "element_tag"];
+const ADDRESS_COLUMNS: [&str; 5] = ["address", "node id", "node_id", "nodeid", "opc ua node"];
+
+/// Auto-detect format by filename extension and read all rows.
+pub fn read_sheet(filename: &str, content: &[u8]) -> Result<Vec<SheetRow>> {
+    let lower = filename.to_lowercase();
+    if lower.ends_with(".xlsx") {
+        read_workbook::<calamine::Xlsx<_>>(content, "XLSX")
+    } else if lower.ends_with(".xls") {
+        read_workbook::<calamine::Xls<_>>(content, "XLS")
+    } else {
+        read_csv(content)
+    }
+}
+
+/// Reads the first sheet of an Excel workbook; `kind` names the format in errors.
+fn read_workbook<'a, W>(content: &'a [u8], kind: &str) -> Result<Vec<SheetRow>>
+where
+    W: calamine::Reader<Cursor<&'a [u8]>>,
+    W::Error: std::fmt::Display,
+{
+    let mut workbook: W = calamine::open_workbook_from_rs(Cursor::new(content))
+        .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", kind, e))?;
+    let sheet_name = workbook
+        .sheet_names()
+        .first()
+        .cloned()
+        .with_context(|| format!("{} file has no sheets", kind))?;
+    let range = workbook
+        .worksheet_range(&sheet_name)
+        .map_err(|e| anyhow::anyhow!("Failed to read sheet '{}': {}", sheet_name, e))?;
+
+    let mut rows = range.rows();
+    let header: Vec<String> = rows
+        .next()
+        .with_context(|| format!("{} sheet is empty", kind))?
+        .iter()
+        .map(|c| c.to_string().trim().to_lowercase())
+        .collect();
+
+    Ok(rows
+        .enumerate()
+        .map(|(idx, row)| SheetRow {
+            row: idx + 2,
+            cells: header
+                .iter()
+                .cloned()
+                .zip(row.iter().map(|c| c.to_string()))
+                .collect(),
+        })
+        .filter(|row| row.cells.values().any(|v| !v.trim().is_empty()))
+        .collect())
+}
+
+fn read_csv(content: &[u8]) -> Result<Vec<SheetRow>> {
+    let text = String::from_utf8_lossy(content);
+    let mut lines = text.lines().enumerate();
+    let (_, header) = lines.next().context("CSV file is empty")?;
+
+    let delimiter = if header.contains('\t') {
+        '\t'
+    } else if header.contains(';') {
+        ';'
+    } else {
+        ','
+    };
+    let columns: Vec<String> = split_csv_line(header, delimiter)
+        .into_iter()
+        .map(|c| c.trim().to_lowercase())
+        .collect();
+
+    Ok(lines
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| SheetRow {
+            row: idx + 1,
+            cells: columns
+                .iter()
+                .cloned()
+                .zip(split_csv_line(line, delimiter))
+                .collect(),
+        })
+        .collect())
+}
+
+/// Splits one CSV line, honouring double quotes so OPC UA node ids such as
+/// `"ns=2;s=Tank.Level"` survive a `;` or `,` delimiter.
+fn split_csv_line(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => fields.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+/// Builds PEA configs from spreadsheet rows.
+///
+/// Each row describes a service (only `pea` + `service` set) or one element of
+/// it. `element` selects where the element goes: `config` (service config
+/// parameter), `parameter` (procedure parameter, the default), `process_value`
+/// or `report_value`. Elements without a `procedure` land in a default procedure.
+pub fn build_pea_configs(
+    rows: &[SheetRow],
+    default_pea_name: &str,
+) -> (Vec<PeaConfig>, ImportReport) {
+    let mut report = ImportReport {
+        rows_total: rows.len(),
+        ..ImportReport::default()
+    };
+    let mut peas: Vec<PeaConfig> = Vec::new();
+
+    for row in rows {
+        let pea_name = row.get(&PEA_COLUMNS).unwrap_or(default_pea_name);
+        let Some(service_tag) = row.get(&SERVICE_COLUMNS) else {
+            report.error(row.row, "Missing service tag");
+            continue;
+        };
+
+        let pea_idx = match peas.iter().position(|p| p.name == pea_name) {
+            Some(idx) => idx,
+            None => {
+                peas.push(new_pea(pea_name, row));
+                peas.len() - 1
+            }
+        };
+        let pea = &mut peas[pea_idx];
+        if let Some(endpoint) = row.get(&["endpoint", "opc ua endpoint", "opcua_endpoint"]) {
+            pea.opcua_config.endpoint = endpoint.to_string();
+        }
+
+        let service = match pea.services.iter().position(|s| s.tag == service_tag) {
+            Some(idx) => &mut pea.services[idx],
+            None => {
+                pea.services.push(ServiceConfig {
+                    tag: service_tag.to_string(),
+                    name: row
+                        .get(&["service name", "service_name"])
+                        .unwrap_or(service_tag)
+                        .to_string(),
+                    description: String::new(),
+                    config_parameters: Vec::new(),
+                    procedures: Vec::new(),
+                });
+                pea.services.last_mut().expect("service was just pushed")
+            }
+        };
+
+        let Some(tag) = row.get(&TAG_COLUMNS) else {
+            report.rows_imported += 1;
+            continue;
+        };
+        if service_has_tag(service, tag) {
+            report.error(
+                row.row,
+                format!(
+                    "Duplicate element tag '{}' in service '{}'",
+                    tag, service.tag
+                ),
+            );
+            continue;
+        }
+
+        let tag_mapping = match parse_tag_mapping(row, &mut report) {
+            Ok(mapping) => mapping,
+            Err(message) => {
+                report.error(row.row, message);
+                continue;
+            }
+        };
+        let element = row.get(&["element", "kind", "role"]).unwrap_or("parameter");
+        let result = match element.to_lowercase().as_str() {
+            "config" | "config_parameter" | "config parameter" => {
+                parse_parameter(row, tag, tag_mapping, &mut report)
+                    .map(|param| service.config_parameters.push(param))
+            }
+            "parameter" | "param" | "procedure_parameter" => {
+                parse_parameter(row, tag, tag_mapping, &mut report).map(|param| {
+                    procedure_for(service, row).parameters.push(param);
+                })
+            }
+            "process_value" | "process value" | "pv" => {
+                parse_indicator(row, tag, tag_mapping).map(|view| {
+                    procedure_for(service, row).process_value_outs.push(view);
+                })
+            }
+            "report_value" | "report value" | "report" => parse_indicator(row, tag, tag_mapping)
+                .map(|view| {
+                    procedure_for(service, row).report_values.push(view);
+                }),
+            other => Err(format!("Unknown element kind '{}'", other)),
+        };
+
+        match result {
+            Ok(()) => report.rows_imported += 1,
+            Err(message) => report.error(row.row, message),
+        }
+    }
+
+    for pea in &peas {
+        if pea.opcua_config.endpoint.is_empty() {
+            report.warn(0, format!("PEA '{}' has no OPC UA endpoint", pea.name));
+        }
+    }
+
+    (peas, report)
+}
+
+fn new_pea(name: &str, row: &SheetRow) -> PeaConfig {
+    let now = Utc::now();
+    PeaConfig {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        version: row
+            .get(&["version", "pea version"])
+            .unwrap_or("1.0.0")
+            .to_string(),
+        description: format!("Imported from spreadsheet on {}", now.format("%Y-%m-%d")),
+        writer: WriterInfo {
+            name: "spreadsheet-import".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            vendor: row.get(&["vendor"]).unwrap_or_default().to_string(),
+        },
+        services: Vec::new(),
+        active_elements: Vec::new(),
+        opcua_config: OpcUaConfig {
+            endpoint: String::new(),
+            namespace_uri: row
+                .get(&["namespace", "namespace uri", "namespace_uri"])
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("urn:fendtastic:{}", name)),
+            security_policy: row
+                .get(&["security policy", "security_policy"])
+                .unwrap_or("None")
+                .to_string(),
+        },
+        created_at: now,
+        updated_at: now,
+        tenant_id: None,
+        mode: PeaMode::Simulated,
+        archived_at: None,
+    }
+}
+
+fn procedure_for<'a>(service: &'a mut ServiceConfig, row: &SheetRow) -> &'a mut ProcedureConfig {
+    let name = row
+        .get(&["procedure", "procedure name"])
+        .unwrap_or("Default");
+    if let Some(idx) = service.procedures.iter().position(|p| p.name == name) {
+        return &mut service.procedures[idx];
+    }
+    let id = row
+        .get(&["procedure id", "procedure_id"])
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(service.procedures.len() as u32 + 1);
+    service.procedures.push(ProcedureConfig {
+        id,
+        name: name.to_string(),
+        is_self_completing: false,
+        is_default: service.procedures.is_empty(),
+        parameters: Vec::new(),
+        process_value_outs: Vec::new(),
+        report_values: Vec::new(),
+    });
+    service
+        .procedures
+        .last_mut()
+        .expect("procedure was just pushed")
+}
+
+fn service_has_tag(service: &ServiceConfig, tag: &str) -> bool {
+    let param_tag = |p: &ServiceParameter| match p {
+        ServiceParameter::Analog(p) => p.tag == tag,
+        ServiceParameter::Binary(p) => p.tag == tag,
+        ServiceParameter::DInt(p) => p.tag == tag,
+        ServiceParameter::StringParam(p) => p.tag == tag,
+    };
+    let view_tag = |v: &IndicatorElement| match v {
+        IndicatorElement::AnaView(v) => v.tag == tag,
+        IndicatorElement::BinView(v) => v.tag == tag,
+        IndicatorElement::BinStringView(v) => v.tag == tag,
+        IndicatorElement::DIntView(v) => v.tag == tag,
+        IndicatorElement::DIntStringView(v) => v.tag == tag,
+        IndicatorElement::StringView(v) => v.tag == tag,
+    };
+    service.config_parameters.iter().any(param_tag)
+        || service.procedures.iter().any(|p| {
+            p.parameters.iter().any(param_tag)
+                || p.process_value_outs.iter().any(view_tag)
+                || p.report_values.iter().any(view_tag)
+        })
+}
+
+fn parse_tag_mapping(
+    row: &SheetRow,
+    report: &mut ImportReport,
+) -> Result<Option<TagMapping>, String> {
+    let Some(address) = row.get(&ADDRESS_COLUMNS) else {
+        report.warn(row.row, "No address; element is left unmapped");
+        return Ok(None);
+    };
+    let protocol = match row.get(&["protocol"]).map(str::to_lowercase).as_deref() {
+        None | Some("opcua") | Some("opc ua") | Some("opc-ua") => ProtocolType::OpcUa,
+        Some("modbus") => ProtocolType::Modbus,
+        Some("zenoh") => ProtocolType::Zenoh,
+        Some(other) => return Err(format!("Unknown protocol '{}'", other)),
+    };
+    if protocol == ProtocolType::OpcUa && !is_opcua_node_id(address) {
+        report.warn(
+            row.row,
+            format!("'{}' does not look like an OPC UA node id", address),
+        );
+    }
+    Ok(Some(TagMapping {
+        protocol,
+        address: address.to_string(),
+    }))
+}
+
+/// Accepts `ns=<n>;<i|s|g|b>=<id>`, `nsu=<uri>;<i|s|g|b>=<id>` and namespace-0
+/// `<i|s|g|b>=<id>` node ids.
+pub fn is_opcua_node_id(address: &str) -> bool {
+    let identifier = if let Some(rest) = address.strip_prefix("ns=") {
+        match rest.split_once(';') {
+            Some((ns, identifier)) if ns.parse::<u16>().is_ok() => identifier,
+            _ => return false,
+        }
+    } else if let Some(rest) = address.strip_prefix("nsu=") {
+        match rest.split_once(';') {
+            Some((uri, identifier)) if !uri.is_empty() => identifier,
+            _ => return false,
+        }
+    } else {
+        address
+    };
+    ["i=", "s=", "g=", "b="]
+        .iter()
+        .any(|prefix| identifier.len() > 2 && identifier.starts_with(prefix))
+}
+
+fn parse_number<T: std::str::FromStr>(row: &SheetRow, column: &str) -> Result<Option<T>, String> {
+    match row.get(&[column]) {
+        Some(raw) => raw
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("Column '{}' is not a number: '{}'", column, raw)),
+        None => Ok(None),
+    }
+}
+
+fn parse_parameter(
+    row: &SheetRow,
+    tag: &str,
+    tag_mapping: Option<TagMapping>,
+    report: &mut ImportReport,
+) -> Result<ServiceParameter, String> {
+    let name = row.get(&["name", "description"]).unwrap_or(tag).to_string();
+    let unit = row.get(&["unit"]).unwrap_or_default().to_string();
+    let data_type = row
+        .get(&["type", "data type", "data_type"])
+        .unwrap_or("analog");
+
+    match data_type.to_lowercase().as_str() {
+        "analog" | "real" | "float" | "lreal" => {
+            let min = parse_number::<f64>(row, "min")?.unwrap_or(0.0);
+            let max = parse_number::<f64>(row, "max")?.unwrap_or(100.0);
+            let default = parse_number::<f64>(row, "default")?.unwrap_or(min);
+            check_range(row, report, min, max, default)?;
+            Ok(ServiceParameter::Analog(AnalogParameter {
+                tag: tag.to_string(),
+                name,
+                unit,
+                v_scl_min: min,
+                v_scl_max: max,
+                v_min: min,
+                v_max: max,
+                v_default: default,
+                tag_mapping,
+                display: None,
+            }))
+        }
+        "dint" | "int" | "integer" => {
+            let min = parse_number::<i64>(row, "min")?.unwrap_or(0);
+            let max = parse_number::<i64>(row, "max")?.unwrap_or(100);
+            let default = parse_number::<i64>(row, "default")?.unwrap_or(min);
+            check_range(row, report, min, max, default)?;
+            Ok(ServiceParameter::DInt(DIntParameter {
+                tag: tag.to_string(),
+                name,
+                unit,
+                v_scl_min: min,
+                v_scl_max: max,
+                v_min: min,
+                v_max: max,
+                v_default: default,
+                tag_mapping,
+                display: None,
+            }))
+        }
+        "binary" | "bool" | "boolean" => Ok(ServiceParameter::Binary(BinaryParameter {
+            tag: tag.to_string(),
+            name,
+            v_state0: row
+                .get(&["state0", "false text"])
+                .unwrap_or("Off")
+                .to_string(),
+            v_state1: row
+                .get(&["state1", "true text"])
+                .unwrap_or("On")
+                .to_string(),
+            v_default: row
+                .get(&["default"])
+                .is_some_and(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes" | "on")),
+            tag_mapping,
+        })),
+        "string" | "text" => Ok(ServiceParameter::StringParam(StringParameter {
+            tag: tag.to_string(),
+            name,
+            v_default: row.get(&["default"]).unwrap_or_default().to_string(),
+            tag_mapping,
+        })),
+        other => Err(format!("Unknown parameter type '{}'", other)),
+    }
+}
+
+fn check_range<T: PartialOrd + std::fmt::Display>(
+    row: &SheetRow,
+    report: &mut ImportReport,
+    min: T,
+    max: T,
+    default: T,
+) -> Result<(), String> {
+    if min > max {
+        return Err(format!("Minimum {} is greater than maximum {}", min, max));
+    }
+    if default < min || default > max {
+        report.warn(
+            row.row,
+            format!("Default {} is outside [{}, {}]", default, min, max),
+        );
+    }
+    Ok(())
+}
+
+fn parse_indicator(
+    row: &SheetRow,
+    tag: &str,
+    tag_mapping: Option<TagMapping>,
+) -> Result<IndicatorElement, String> {
+    let name = row.get(&["name", "description"]).unwrap_or(tag).to_string();
+    let data_type = row
+        .get(&["type", "data type", "data_type"])
+        .unwrap_or("analog");
+
+    match data_type.to_lowercase().as_str() {
+        "analog" | "real" | "float" | "lreal" => Ok(IndicatorElement::AnaView(AnaViewConfig {
+            tag: tag.to_string(),
+            name,
+            unit: row.get(&["unit"]).unwrap_or_default().to_string(),
+            v_scl_min: parse_number(row, "min")?.unwrap_or(0.0),
+            v_scl_max: parse_number(row, "max")?.unwrap_or(100.0),
+            tag_mapping,
+        })),
+        "dint" | "int" | "integer" => Ok(IndicatorElement::DIntView(DIntViewConfig {
+            tag: tag.to_string(),
+            name,
+            unit: row.get(&["unit"]).unwrap_or_default().to_string(),
+            v_scl_min: parse_number(row, "min")?.unwrap_or(0),
+            v_scl_max: parse_number(row, "max")?.unwrap_or(100),
+            tag_mapping,
+        })),
+        "binary" | "bool" | "boolean" => Ok(IndicatorElement::BinView(BinViewConfig {
+            tag: tag.to_string(),
+            name,
+            v_state0: row
+                .get(&["state0", "false text"])
+                .unwrap_or("Off")
+                .to_string(),
+            v_state1: row
+                .get(&["state1", "true text"])
+                .unwrap_or("On")
+                .to_string(),
+            tag_mapping,
+        })),
+        "string" | "text" => Ok(IndicatorElement::StringView(StringViewConfig {
+            tag: tag.to_string(),
+            name,
+            tag_mapping,
+        })),
+        other => Err(format!("Unknown indicator type '{}'", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = "\
+PEA,Service,Procedure,Element,Tag,Name,Type,Unit,Min,Max,Default,Address
+Reactor,Dosing,,,,,,,,,,
+Reactor,Dosing,Fill,parameter,SP_Level,Level setpoint,analog,%,0,100,50,\"ns=2;s=Dosing.SP_Level\"
+Reactor,Dosing,Fill,process_value,PV_Level,Level,analog,%,0,100,,ns=2;s=Dosing.PV_Level
+Reactor,Dosing,,config,MaxRate,Max rate,dint,l/min,10,5,,ns=2;s=Dosing.MaxRate
+Reactor,Dosing,Fill,parameter,SP_Level,Duplicate,analog,%,0,100,50,ns=2;s=Dup
+";
+
+    #[test]
+    fn excel_formats_use_their_own_reader() {
+        let not_excel = b"PEA,Service\nreactor,Dose\n";
+        let xls = read_sheet("plant.XLS", not_excel).unwrap_err();
+        assert!(xls.to_string().starts_with("Failed to open XLS:"));
+        let xlsx = read_sheet("plant.xlsx", not_excel).unwrap_err();
+        assert!(xlsx.to_string().starts_with("Failed to open XLSX:"));
+        assert_eq!(read_sheet("plant.csv", not_excel).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn quoted_fields_keep_delimiters() {
+        assert_eq!(
+            split_csv_line("a,\"ns=2;s=x,y\",c", ','),
+            vec!["a", "ns=2;s=x,y", "c"]
+        );
+    }
+
+    #[test]
+    fn builds_services_procedures_and_report() {
+        let rows = read_sheet("peas.csv", SHEET.as_bytes()).unwrap();
+        let (peas, report) = build_pea_configs(&rows, "Imported PEA");
+
+        assert_eq!(peas.len(), 1);
+        let service = &peas[0].services[0];
+        assert_eq!(service.tag, "Dosing");
+        assert_eq!(service.procedures.len(), 1);
+        assert_eq!(service.procedures[0].parameters.len(), 1);
+        assert_eq!(service.procedures[0].process_value_outs.len(), 1);
+        assert!(service.config_parameters.is_empty());
+
+        assert_eq!(report.rows_total, 5);
+        assert_eq!(report.rows_imported, 3);
+        let error_rows: Vec<usize> = report.errors.iter().map(|e| e.row).collect();
+        assert_eq!(error_rows, vec![5, 6]);
+        assert!(report
+            .warnings
+            .iter()
+            .any(|w| w.message.contains("endpoint")));
+    }
+
+    #[test]
+    fn recognises_opcua_node_ids() {
+        assert!(is_opcua_node_id("ns=2;s=Tank.Level"));
+        assert!(is_opcua_node_id("i=2258"));
+        assert!(is_opcua_node_id("nsu=urn:dosing;s=FIC100.SP"));
+        assert!(!is_opcua_node_id("DB1.DBW0"));
+        assert!(!is_opcua_node_id("ns=x;s=Tank"));
+    }
+}
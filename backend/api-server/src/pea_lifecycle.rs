@@ -0,0 +1,274 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio_postgres::Client;
+use tracing::{error, info};
+use zenoh::Session;
+
+use crate::db;
+
+pub const TRANSITION_DEPLOYED: &str = "deployed";
+pub const TRANSITION_STARTED: &str = "started";
+pub const TRANSITION_STOPPED: &str = "stopped";
+pub const TRANSITION_UNDEPLOYED: &str = "undeployed";
+
+/// Transition requested through the REST API.
+pub const CAUSE_API: &str = "api";
+/// Transition seen on a PEA status topic without a matching API call.
+pub const CAUSE_OBSERVED: &str = "observed";
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct LifecycleEvent {
+    pub id: String,
+    pub pea_id: String,
+    pub transition: String,
+    pub cause: String,
+    pub actor: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct Phase {
+    pub deployed: bool,
+    pub running: bool,
+}
+
+impl Phase {
+    pub const UNDEPLOYED: Phase = Phase {
+        deployed: false,
+        running: false,
+    };
+    pub const IDLE: Phase = Phase {
+        deployed: true,
+        running: false,
+    };
+    pub const RUNNING: Phase = Phase {
+        deployed: true,
+        running: true,
+    };
+
+    /// Reads the `deployed`/`running` flags of a PEA status payload.
+    pub fn from_status(status: &serde_json::Value) -> Option<Phase> {
+        let deployed = status.get("deployed").and_then(|v| v.as_bool())?;
+        let running = status
+            .get("running")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        Some(Phase {
+            deployed: deployed || running,
+            running,
+        })
+    }
+
+    /// The phase a PEA is in right after `transition`.
+    pub fn after(transition: &str) -> Phase {
+        match transition {
+            TRANSITION_STARTED => Phase::RUNNING,
+            TRANSITION_DEPLOYED | TRANSITION_STOPPED => Phase::IDLE,
+            _ => Phase::UNDEPLOYED,
+        }
+    }
+}
+
+/// Transitions between two phases, in the order they happen.
+pub fn transitions(previous: Phase, next: Phase) -> Vec<&'static str> {
+    let mut out = Vec::new();
+    if !previous.deployed && next.deployed {
+        out.push(TRANSITION_DEPLOYED);
+    }
+    if !previous.running && next.running {
+        out.push(TRANSITION_STARTED);
+    }
+    if previous.running && !next.running {
+        out.push(TRANSITION_STOPPED);
+    }
+    if previous.deployed && !next.deployed {
+        out.push(TRANSITION_UNDEPLOYED);
+    }
+    out
+}
+
+/// Time spent deployed and running within `[start, end]`, given the phase at
+/// `start` and the events of the window in time order.
+pub fn uptime(
+    initial: Phase,
+    events: &[LifecycleEvent],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> (i64, i64) {
+    let (mut deployed_ms, mut running_ms) = (0, 0);
+    let mut phase = initial;
+    let mut since = start;
+    let mut account = |phase: Phase, from: DateTime<Utc>, to: DateTime<Utc>| {
+        let ms = (to - from).num_milliseconds().max(0);
+        if phase.deployed {
+            deployed_ms += ms;
+        }
+        if phase.running {
+            running_ms += ms;
+        }
+    };
+    for event in events {
+        let at = event.occurred_at.clamp(start, end);
+        account(phase, since, at);
+        phase = Phase::after(&event.transition);
+        since = at;
+    }
+    account(phase, since, end);
+    (deployed_ms, running_ms)
+}
+
+/// Records PEA lifecycle transitions in `pea_lifecycle_events`. Keeps the last
+/// known phase of each PEA, so the status echo of an API call is not logged a
+/// second time as an observed change, and the last phase requested through the
+/// API, which is the phase the PEA is meant to be in.
+#[derive(Clone, Default)]
+pub struct PeaLifecycle {
+    phases: Arc<RwLock<HashMap<String, Phase>>>,
+    desired: Arc<RwLock<HashMap<String, Phase>>>,
+}
+
+impl PeaLifecycle {
+    pub fn new(phases: HashMap<String, Phase>, desired: HashMap<String, Phase>) -> Self {
+        Self {
+            phases: Arc::new(RwLock::new(phases)),
+            desired: Arc::new(RwLock::new(desired)),
+        }
+    }
+
+    /// Phase last requested through the API for each PEA.
+    pub async fn desired(&self) -> HashMap<String, Phase> {
+        self.desired.read().await.clone()
+    }
+
+    /// Moves a PEA to `next`, storing one event per transition.
+    pub async fn record(
+        &self,
+        client: &Client,
+        pea_id: &str,
+        next: Phase,
+        cause: &str,
+        actor: Option<String>,
+    ) -> Vec<LifecycleEvent> {
+        if cause == CAUSE_API {
+            self.desired.write().await.insert(pea_id.to_string(), next);
+        }
+        let previous = {
+            let mut phases = self.phases.write().await;
+            phases.insert(pea_id.to_string(), next).unwrap_or_default()
+        };
+        let now = Utc::now();
+        let mut events = Vec::new();
+        // One microsecond apart, so transitions of one change keep their order in the table.
+        for (offset, transition) in (0..).zip(transitions(previous, next)) {
+            let event = LifecycleEvent {
+                id: uuid::Uuid::new_v4().to_string(),
+                pea_id: pea_id.to_string(),
+                transition: transition.to_string(),
+                cause: cause.to_string(),
+                actor: actor.clone(),
+                occurred_at: now + chrono::Duration::microseconds(offset),
+            };
+            if let Err(e) = db::insert_lifecycle_event(client, &event).await {
+                error!("Failed to record lifecycle event for PEA {}: {}", pea_id, e);
+            }
+            info!("PEA {} {} ({})", pea_id, transition, cause);
+            events.push(event);
+        }
+        events
+    }
+
+    /// Records transitions seen on PEA status topics.
+    pub async fn observe(self, session: Arc<Session>, client: Arc<Client>) {
+        let subscriber = match session
+            .declare_subscriber(shared::mtp::topics::PEA_STATUS_WILDCARD)
+            .await
+        {
+            Ok(subscriber) => subscriber,
+            Err(e) => {
+                error!(
+                    "Failed to subscribe to PEA status for lifecycle events: {}",
+                    e
+                );
+                return;
+            }
+        };
+        while let Ok(sample) = subscriber.recv_async().await {
+            let key = sample.key_expr().as_str().to_string();
+            let Ok(status) =
+                serde_json::from_slice::<serde_json::Value>(&sample.payload().to_bytes())
+            else {
+                continue;
+            };
+            let Some(pea_id) = status
+                .get("pea_id")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .or_else(|| pea_id_from_status_key(&key))
+            else {
+                continue;
+            };
+            let Some(next) = Phase::from_status(&status) else {
+                continue;
+            };
+            self.record(&client, &pea_id, next, CAUSE_OBSERVED, None)
+                .await;
+        }
+    }
+}
+
+fn pea_id_from_status_key(key: &str) -> Option<String> {
+    let rest = key.strip_suffix("/status")?;
+    let (_, pea_id) = rest.rsplit_once("/pea/")?;
+    (!pea_id.is_empty() && !pea_id.contains('/')).then(|| pea_id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(transition: &str, at: DateTime<Utc>) -> LifecycleEvent {
+        LifecycleEvent {
+            id: String::new(),
+            pea_id: "p1".to_string(),
+            transition: transition.to_string(),
+            cause: CAUSE_API.to_string(),
+            actor: None,
+            occurred_at: at,
+        }
+    }
+
+    #[test]
+    fn phase_changes_become_ordered_transitions() {
+        assert_eq!(
+            transitions(Phase::UNDEPLOYED, Phase::RUNNING),
+            vec![TRANSITION_DEPLOYED, TRANSITION_STARTED]
+        );
+        assert_eq!(
+            transitions(Phase::RUNNING, Phase::UNDEPLOYED),
+            vec![TRANSITION_STOPPED, TRANSITION_UNDEPLOYED]
+        );
+        assert!(transitions(Phase::IDLE, Phase::IDLE).is_empty());
+        assert_eq!(
+            pea_id_from_status_key("entmoot/habitat/nodes/n1/pea/p1/status").as_deref(),
+            Some("p1")
+        );
+    }
+
+    #[test]
+    fn uptime_counts_deployed_and_running_time_in_the_window() {
+        let start: DateTime<Utc> = "2026-01-01T00:00:00Z".parse().unwrap();
+        let minutes = |m: i64| start + chrono::Duration::minutes(m);
+        let events = [
+            event(TRANSITION_STARTED, minutes(10)),
+            event(TRANSITION_STOPPED, minutes(40)),
+            event(TRANSITION_UNDEPLOYED, minutes(50)),
+        ];
+
+        let (deployed_ms, running_ms) = uptime(Phase::IDLE, &events, start, minutes(60));
+        assert_eq!(deployed_ms, 50 * 60_000);
+        assert_eq!(running_ms, 30 * 60_000);
+    }
+}
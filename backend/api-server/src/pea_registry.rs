@@ -0,0 +1,261 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use shared::mtp::{topics, PeaConfig};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, error, info};
+use zenoh::sample::SampleKind;
+use zenoh::Session;
+
+use crate::task_registry::TaskHandle;
+
+/// Where a PEA served by the registry comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PeaSource {
+    /// Stored through this API.
+    Local,
+    /// Announced on the bus by a node, read-only here.
+    Announced,
+}
+
+/// A PEA config tagged with its source; serializes as the config plus
+/// `source`, and for announced PEAs the node and time of the announcement.
+#[derive(Clone, Debug, Serialize)]
+pub struct RegisteredPea {
+    pub source: PeaSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub announced_at: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub config: PeaConfig,
+}
+
+#[derive(Clone, Debug)]
+struct Announcement {
+    node_id: String,
+    received_at: DateTime<Utc>,
+    config: PeaConfig,
+}
+
+/// Every PEA the server knows of: the locally stored configs, and the
+/// configs nodes announce on `PEA_ANNOUNCE_WILDCARD`, kept by the
+/// `pea-announcements` subscriber. A local config shadows an announced one
+/// with the same id.
+#[derive(Clone)]
+pub struct PeaRegistry {
+    local: Arc<RwLock<HashMap<String, PeaConfig>>>,
+    announced: Arc<RwLock<HashMap<String, Announcement>>>,
+}
+
+impl PeaRegistry {
+    pub fn new(local: Arc<RwLock<HashMap<String, PeaConfig>>>) -> Self {
+        Self {
+            local,
+            announced: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Local PEAs, then announced ones not stored locally, each by id.
+    pub async fn list(&self) -> Vec<RegisteredPea> {
+        let local = self.local.read().await;
+        let announced = self.announced.read().await;
+        let mut peas: Vec<RegisteredPea> = local.values().map(local_pea).collect();
+        peas.sort_by(|a, b| a.config.id.cmp(&b.config.id));
+        let mut remote: Vec<RegisteredPea> = announced
+            .iter()
+            .filter(|(id, _)| !local.contains_key(*id))
+            .map(|(_, announcement)| announced_pea(announcement))
+            .collect();
+        remote.sort_by(|a, b| a.config.id.cmp(&b.config.id));
+        peas.extend(remote);
+        peas
+    }
+
+    pub async fn get(&self, pea_id: &str) -> Option<RegisteredPea> {
+        if let Some(config) = self.local.read().await.get(pea_id) {
+            return Some(local_pea(config));
+        }
+        self.announced.read().await.get(pea_id).map(announced_pea)
+    }
+
+    /// Stores an announced config; returns the PEA id, or `None` when the key
+    /// or payload is not a PEA announcement. A config without an id takes the
+    /// one in the key.
+    pub async fn update(&self, key: &str, payload: &[u8]) -> Option<String> {
+        let (node_id, key_pea_id) = announce_key(key)?;
+        let mut config: PeaConfig = match serde_json::from_slice(payload) {
+            Ok(config) => config,
+            Err(e) => {
+                debug!(
+                    "Ignoring announcement on {} that does not parse: {}",
+                    key, e
+                );
+                return None;
+            }
+        };
+        if config.id.is_empty() {
+            config.id = key_pea_id.to_string();
+        }
+        let pea_id = config.id.clone();
+        self.announced.write().await.insert(
+            pea_id.clone(),
+            Announcement {
+                node_id: node_id.to_string(),
+                received_at: Utc::now(),
+                config,
+            },
+        );
+        Some(pea_id)
+    }
+
+    /// Drops the announcement a node withdrew by deleting its key.
+    pub async fn withdraw(&self, key: &str) {
+        let Some((node_id, pea_id)) = announce_key(key) else {
+            return;
+        };
+        let mut announced = self.announced.write().await;
+        if announced
+            .get(pea_id)
+            .is_some_and(|announcement| announcement.node_id == node_id)
+        {
+            announced.remove(pea_id);
+        }
+    }
+
+    /// Follows the PEA announcement topics until the session closes.
+    pub async fn observe(self, session: Arc<Session>, task: TaskHandle) {
+        let subscriber = match session
+            .declare_subscriber(topics::PEA_ANNOUNCE_WILDCARD)
+            .await
+        {
+            Ok(subscriber) => subscriber,
+            Err(e) => {
+                error!("Failed to subscribe to PEA announcements: {}", e);
+                task.fail(format!("Failed to subscribe to PEA announcements: {}", e));
+                return;
+            }
+        };
+        info!(
+            "PEA registry: subscribed to {}",
+            topics::PEA_ANNOUNCE_WILDCARD
+        );
+        while let Ok(sample) = subscriber.recv_async().await {
+            task.beat();
+            let key = sample.key_expr().as_str();
+            match sample.kind() {
+                SampleKind::Put => {
+                    self.update(key, &sample.payload().to_bytes()).await;
+                }
+                SampleKind::Delete => self.withdraw(key).await,
+            }
+        }
+    }
+}
+
+fn local_pea(config: &PeaConfig) -> RegisteredPea {
+    RegisteredPea {
+        source: PeaSource::Local,
+        node_id: None,
+        announced_at: None,
+        config: config.clone(),
+    }
+}
+
+fn announced_pea(announcement: &Announcement) -> RegisteredPea {
+    RegisteredPea {
+        source: PeaSource::Announced,
+        node_id: Some(announcement.node_id.clone()),
+        announced_at: Some(announcement.received_at),
+        config: announcement.config.clone(),
+    }
+}
+
+/// Node and PEA of a `.../nodes/<node>/pea/<pea>/announce` key.
+fn announce_key(key: &str) -> Option<(&str, &str)> {
+    let rest = key.strip_suffix("/announce")?;
+    let (prefix, pea_id) = rest.rsplit_once("/pea/")?;
+    let (_, node_id) = prefix.rsplit_once("/nodes/")?;
+    (!pea_id.is_empty() && !pea_id.contains('/') && !node_id.contains('/'))
+        .then_some((node_id, pea_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(id: &str, name: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "name": name,
+            "version": "1.0.0",
+            "description": "",
+            "writer": {"name": "tests", "version": "1.0.0", "vendor": "tests"},
+            "services": [],
+            "active_elements": [],
+            "opcua_config": {
+                "endpoint": "opc.tcp://127.0.0.1:4840",
+                "namespace_uri": "urn:tests",
+                "security_policy": "None"
+            },
+            "created_at": "2026-01-01T00:00:00Z",
+            "updated_at": "2026-01-01T00:00:00Z"
+        })
+    }
+
+    #[tokio::test]
+    async fn local_configs_shadow_announced_ones() {
+        let local: PeaConfig = serde_json::from_value(config("mixer", "Local mixer")).unwrap();
+        let registry = PeaRegistry::new(Arc::new(RwLock::new(HashMap::from([(
+            "mixer".to_string(),
+            local,
+        )]))));
+        let key = |pea: &str| format!("entmoot/habitat/nodes/edge-1/pea/{}/announce", pea);
+
+        let announced = config("mixer", "Announced mixer").to_string();
+        registry.update(&key("mixer"), announced.as_bytes()).await;
+        let unnamed = config("", "Dosing skid").to_string();
+        assert_eq!(
+            registry
+                .update(&key("doser"), unnamed.as_bytes())
+                .await
+                .as_deref(),
+            Some("doser")
+        );
+        assert!(registry.update(&key("x"), b"not json").await.is_none());
+
+        let listed: Vec<(String, PeaSource, String)> = registry
+            .list()
+            .await
+            .into_iter()
+            .map(|pea| (pea.config.id, pea.source, pea.config.name))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                (
+                    "mixer".to_string(),
+                    PeaSource::Local,
+                    "Local mixer".to_string()
+                ),
+                (
+                    "doser".to_string(),
+                    PeaSource::Announced,
+                    "Dosing skid".to_string()
+                ),
+            ]
+        );
+        let doser = serde_json::to_value(registry.get("doser").await.unwrap()).unwrap();
+        assert_eq!(doser["source"], "announced");
+        assert_eq!(doser["node_id"], "edge-1");
+
+        registry
+            .withdraw("entmoot/habitat/nodes/edge-2/pea/doser/announce")
+            .await;
+        assert!(registry.get("doser").await.is_some(), "other node's key");
+        registry.withdraw(&key("doser")).await;
+        assert!(registry.get("doser").await.is_none());
+    }
+}
@@ -0,0 +1,274 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use shared::mtp::PeaConfig;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+use tracing::{error, info};
+
+use crate::db;
+
+pub const REASON_CREATE: &str = "create";
+pub const REASON_UPDATE: &str = "update";
+pub const REASON_IMPORT: &str = "import";
+pub const REASON_ROLLBACK: &str = "rollback";
+/// Loaded from a file changed outside the API, see `config_watch`.
+pub const REASON_FILE: &str = "file";
+
+/// One saved state of a PEA config. Revisions are numbered from 1 per PEA
+/// and never rewritten; a rollback adds a new revision.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeaRevision {
+    pub pea_id: String,
+    pub revision: u32,
+    /// `create`, `update`, `import`, `rollback` or `file`.
+    pub reason: String,
+    /// Revision restored by a rollback.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restored_from: Option<u32>,
+    pub actor: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub config: PeaConfig,
+}
+
+impl PeaRevision {
+    /// The revision without its config, as listed by GET /pea/{id}/revisions.
+    pub fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "pea_id": self.pea_id,
+            "revision": self.revision,
+            "reason": self.reason,
+            "restored_from": self.restored_from,
+            "actor": self.actor,
+            "created_at": self.created_at,
+            "name": self.config.name,
+            "version": self.config.version,
+        })
+    }
+}
+
+enum Backend {
+    /// `<dir>/<pea id>/v<n>.json`, beside the PEA config files.
+    Files(PathBuf),
+    /// The `pea_config_revisions` table.
+    Postgres(Arc<Client>),
+}
+
+/// History of every PEA config written through the API. Revisions live in
+/// Postgres when `CONFIG_STORE=postgres`, otherwise in files under the PEA
+/// config directory.
+pub struct PeaRevisions {
+    backend: Backend,
+    /// Serializes numbering so concurrent writes get distinct revisions.
+    lock: Mutex<()>,
+}
+
+impl PeaRevisions {
+    pub fn files(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            backend: Backend::Files(dir.into()),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn postgres(client: Arc<Client>) -> Self {
+        Self {
+            backend: Backend::Postgres(client),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Stores `config` as the PEA's next revision. Failures are logged; the
+    /// config itself has already been saved.
+    pub async fn record(
+        &self,
+        config: &PeaConfig,
+        reason: &str,
+        restored_from: Option<u32>,
+        actor: Option<String>,
+    ) -> Option<PeaRevision> {
+        let _guard = self.lock.lock().await;
+        let result = async {
+            let latest = self.latest(&config.id).await?;
+            let revision = PeaRevision {
+                pea_id: config.id.clone(),
+                revision: latest + 1,
+                reason: reason.to_string(),
+                restored_from,
+                actor,
+                created_at: Utc::now(),
+                config: config.clone(),
+            };
+            self.write(&revision).await?;
+            anyhow::Ok(revision)
+        };
+        match result.await {
+            Ok(revision) => {
+                info!(
+                    "Recorded revision {} of PEA {} ({})",
+                    revision.revision, revision.pea_id, reason
+                );
+                Some(revision)
+            }
+            Err(e) => {
+                error!("Failed to record revision of PEA {}: {:#}", config.id, e);
+                None
+            }
+        }
+    }
+
+    /// Every revision of a PEA, oldest first.
+    pub async fn list(&self, pea_id: &str) -> Result<Vec<PeaRevision>> {
+        match &self.backend {
+            Backend::Postgres(client) => db::list_pea_config_revisions(client, pea_id).await,
+            Backend::Files(dir) => {
+                let mut revisions = Vec::new();
+                for revision in revision_numbers(&pea_dir(dir, pea_id)?).await? {
+                    revisions.push(read_revision(&revision_path(dir, pea_id, revision)?).await?);
+                }
+                Ok(revisions)
+            }
+        }
+    }
+
+    pub async fn get(&self, pea_id: &str, revision: u32) -> Result<Option<PeaRevision>> {
+        match &self.backend {
+            Backend::Postgres(client) => {
+                db::get_pea_config_revision(client, pea_id, revision).await
+            }
+            Backend::Files(dir) => {
+                let path = revision_path(dir, pea_id, revision)?;
+                if !tokio::fs::try_exists(&path).await? {
+                    return Ok(None);
+                }
+                read_revision(&path).await.map(Some)
+            }
+        }
+    }
+
+    async fn latest(&self, pea_id: &str) -> Result<u32> {
+        match &self.backend {
+            Backend::Postgres(client) => db::latest_pea_config_revision(client, pea_id).await,
+            Backend::Files(dir) => Ok(revision_numbers(&pea_dir(dir, pea_id)?)
+                .await?
+                .last()
+                .copied()
+                .unwrap_or(0)),
+        }
+    }
+
+    async fn write(&self, revision: &PeaRevision) -> Result<()> {
+        match &self.backend {
+            Backend::Postgres(client) => db::insert_pea_config_revision(client, revision).await,
+            Backend::Files(dir) => {
+                tokio::fs::create_dir_all(pea_dir(dir, &revision.pea_id)?).await?;
+                let path = revision_path(dir, &revision.pea_id, revision.revision)?;
+                tokio::fs::write(&path, serde_json::to_string_pretty(revision)?)
+                    .await
+                    .with_context(|| format!("Failed to write {}", path.display()))
+            }
+        }
+    }
+}
+
+fn pea_dir(dir: &Path, pea_id: &str) -> Result<PathBuf> {
+    if pea_id.is_empty() || pea_id.contains(['/', '\\']) || pea_id == "." || pea_id == ".." {
+        anyhow::bail!("Invalid PEA id '{}'", pea_id);
+    }
+    Ok(dir.join(pea_id))
+}
+
+fn revision_path(dir: &Path, pea_id: &str, revision: u32) -> Result<PathBuf> {
+    Ok(pea_dir(dir, pea_id)?.join(format!("v{}.json", revision)))
+}
+
+/// Revision numbers present in a PEA's revision directory, ascending.
+async fn revision_numbers(dir: &Path) -> Result<Vec<u32>> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut numbers = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        if let Some(number) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.strip_prefix('v'))
+            .and_then(|name| name.strip_suffix(".json"))
+            .and_then(|number| number.parse::<u32>().ok())
+        {
+            numbers.push(number);
+        }
+    }
+    numbers.sort_unstable();
+    Ok(numbers)
+}
+
+async fn read_revision(path: &Path) -> Result<PeaRevision> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::pea_config;
+
+    fn config(name: &str) -> PeaConfig {
+        PeaConfig {
+            name: name.to_string(),
+            ..pea_config("pea-1")
+        }
+    }
+
+    #[tokio::test]
+    async fn revisions_are_numbered_and_kept_per_pea() {
+        let dir =
+            std::env::temp_dir().join(format!("fendtastic-pea-revisions-{}", uuid::Uuid::new_v4()));
+        let revisions = PeaRevisions::files(&dir);
+
+        revisions
+            .record(&config("first"), REASON_CREATE, None, None)
+            .await
+            .unwrap();
+        revisions
+            .record(
+                &config("second"),
+                REASON_UPDATE,
+                None,
+                Some("op-1".to_string()),
+            )
+            .await
+            .unwrap();
+        let rollback = revisions
+            .record(&config("first"), REASON_ROLLBACK, Some(1), None)
+            .await
+            .unwrap();
+        assert_eq!(rollback.revision, 3);
+
+        let listed = revisions.list("pea-1").await.unwrap();
+        let names: Vec<&str> = listed.iter().map(|r| r.config.name.as_str()).collect();
+        assert_eq!(names, vec!["first", "second", "first"]);
+        assert_eq!(listed[1].actor.as_deref(), Some("op-1"));
+        assert_eq!(
+            revisions
+                .get("pea-1", 2)
+                .await
+                .unwrap()
+                .unwrap()
+                .config
+                .name,
+            "second"
+        );
+        assert!(revisions.get("pea-1", 9).await.unwrap().is_none());
+        assert!(revisions.list("other").await.unwrap().is_empty());
+        assert!(revisions.list("../x").await.is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use shared::mtp::{topics, PeaInstanceStatus};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, error, info};
+use zenoh::Session;
+
+use crate::task_registry::TaskHandle;
+
+/// The last status a PEA published, with where and when it was seen.
+#[derive(Clone, Debug, Serialize)]
+pub struct CachedStatus {
+    pub node_id: String,
+    pub received_at: DateTime<Utc>,
+    pub status: PeaInstanceStatus,
+}
+
+/// Last `PeaInstanceStatus` of every PEA, kept by the `pea-status-cache`
+/// subscriber on the PEA status topics.
+#[derive(Clone, Default)]
+pub struct PeaStatusCache {
+    statuses: Arc<RwLock<HashMap<String, CachedStatus>>>,
+}
+
+impl PeaStatusCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn get(&self, pea_id: &str) -> Option<CachedStatus> {
+        self.statuses.read().await.get(pea_id).cloned()
+    }
+
+    pub async fn all(&self) -> HashMap<String, CachedStatus> {
+        self.statuses.read().await.clone()
+    }
+
+    pub async fn remove(&self, pea_id: &str) {
+        self.statuses.write().await.remove(pea_id);
+    }
+
+    /// Stores a status sample; returns the PEA id, or `None` when the key or
+    /// payload is not a PEA status. The PEA id comes from the payload, the
+    /// node from the key.
+    pub async fn update(&self, key: &str, payload: &[u8]) -> Option<String> {
+        let node_id = status_key_node(key)?;
+        let status: PeaInstanceStatus = match serde_json::from_slice(payload) {
+            Ok(status) => status,
+            Err(e) => {
+                debug!("Ignoring status on {} that does not parse: {}", key, e);
+                return None;
+            }
+        };
+        let pea_id = status.pea_id.clone();
+        self.statuses.write().await.insert(
+            pea_id.clone(),
+            CachedStatus {
+                node_id: node_id.to_string(),
+                received_at: Utc::now(),
+                status,
+            },
+        );
+        Some(pea_id)
+    }
+
+    /// Follows the PEA status topics until the session closes.
+    pub async fn observe(self, session: Arc<Session>, task: TaskHandle) {
+        let subscriber = match session
+            .declare_subscriber(topics::PEA_STATUS_WILDCARD)
+            .await
+        {
+            Ok(subscriber) => subscriber,
+            Err(e) => {
+                error!(
+                    "Failed to subscribe to PEA status for the status cache: {}",
+                    e
+                );
+                task.fail(format!("Failed to subscribe to PEA status: {}", e));
+                return;
+            }
+        };
+        info!(
+            "PEA status cache: subscribed to {}",
+            topics::PEA_STATUS_WILDCARD
+        );
+        while let Ok(sample) = subscriber.recv_async().await {
+            task.beat();
+            self.update(sample.key_expr().as_str(), &sample.payload().to_bytes())
+                .await;
+        }
+    }
+}
+
+/// Node of a `.../nodes/<node>/pea/<pea>/status` key.
+fn status_key_node(key: &str) -> Option<&str> {
+    let rest = key.strip_suffix("/status")?;
+    let (prefix, pea_id) = rest.rsplit_once("/pea/")?;
+    let (_, node_id) = prefix.rsplit_once("/nodes/")?;
+    (!pea_id.is_empty() && !pea_id.contains('/') && !node_id.contains('/')).then_some(node_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn keeps_the_latest_parsed_status_per_pea() {
+        let cache = PeaStatusCache::new();
+        let key = "entmoot/habitat/nodes/edge-1/pea/p1/status";
+        let status = |running: bool| {
+            serde_json::json!({
+                "pea_id": "p1",
+                "deployed": true,
+                "running": running,
+                "services": [{
+                    "tag": "Dose",
+                    "state": "Execute",
+                    "state_code": 64,
+                    "current_procedure_id": null,
+                    "operation_mode": "Automatic",
+                    "source_mode": "External"
+                }],
+                "last_updated": "2026-05-01T10:00:00Z",
+                "origin": "simulated"
+            })
+            .to_string()
+        };
+
+        assert_eq!(
+            cache.update(key, status(false).as_bytes()).await.as_deref(),
+            Some("p1")
+        );
+        cache.update(key, status(true).as_bytes()).await;
+        assert!(cache.update(key, b"not json").await.is_none());
+        assert!(cache
+            .update(
+                "entmoot/habitat/nodes/edge-1/pea/p1/data/flow",
+                status(false).as_bytes()
+            )
+            .await
+            .is_none());
+
+        let cached = cache.get("p1").await.unwrap();
+        assert_eq!(cached.node_id, "edge-1");
+        assert!(cached.status.running);
+        assert_eq!(cached.status.services[0].tag, "Dose");
+
+        cache.remove("p1").await;
+        assert!(cache.all().await.is_empty());
+    }
+}
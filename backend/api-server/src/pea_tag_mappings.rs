@@ -0,0 +1,318 @@
+use serde::{Deserialize, Serialize};
+use shared::mtp::{ActiveElement, IndicatorElement, PeaConfig, ServiceParameter, TagMapping};
+use std::collections::{HashMap, HashSet};
+
+use crate::pea_validation::FieldError;
+
+/// One place in a PEA config that takes a tag mapping, mapped or not. `path`
+/// locates it the way validation errors do, e.g.
+/// `services[0].procedures[1].parameters[2].tag_mapping`.
+#[derive(Debug, Serialize)]
+pub struct TagMappingRow {
+    pub path: String,
+    /// `None` for active elements.
+    pub service: Option<String>,
+    pub procedure: Option<u32>,
+    /// Tag of the parameter, indicator or active element.
+    pub tag: String,
+    pub mapping: Option<TagMapping>,
+}
+
+/// A submitted row. Only `path` and `mapping` are read, so rows from
+/// `GET` can be sent back as they are.
+#[derive(Debug, Deserialize)]
+pub struct TagMappingEdit {
+    pub path: String,
+    pub mapping: Option<TagMapping>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TagMappingUpdate {
+    pub rows: Vec<TagMappingEdit>,
+}
+
+struct Slot<'a> {
+    path: String,
+    service: Option<String>,
+    procedure: Option<u32>,
+    tag: String,
+    mapping: &'a mut Option<TagMapping>,
+}
+
+/// Every tag mapping of `config` as a flat table, in document order.
+pub fn rows(config: &PeaConfig) -> Vec<TagMappingRow> {
+    let mut config = config.clone();
+    slots(&mut config)
+        .into_iter()
+        .map(|slot| TagMappingRow {
+            path: slot.path,
+            service: slot.service,
+            procedure: slot.procedure,
+            tag: slot.tag,
+            mapping: slot.mapping.clone(),
+        })
+        .collect()
+}
+
+/// Sets the mapping of each edited row; rows not listed keep theirs. A path
+/// that names no mapping, or is listed twice, is reported at `rows[i].path`
+/// and nothing is applied.
+pub fn apply(config: &mut PeaConfig, edits: Vec<TagMappingEdit>) -> Result<(), Vec<FieldError>> {
+    let mut errors = Vec::new();
+    let mut seen = HashSet::new();
+    for (i, edit) in edits.iter().enumerate() {
+        if !seen.insert(edit.path.as_str()) {
+            errors.push(FieldError {
+                path: format!("rows[{}].path", i),
+                message: format!("'{}' is listed more than once", edit.path),
+            });
+        }
+    }
+
+    let mut slots: HashMap<String, &mut Option<TagMapping>> = slots(config)
+        .into_iter()
+        .map(|slot| (slot.path, slot.mapping))
+        .collect();
+    for (i, edit) in edits.iter().enumerate() {
+        if !slots.contains_key(&edit.path) {
+            errors.push(FieldError {
+                path: format!("rows[{}].path", i),
+                message: format!("No tag mapping at '{}'", edit.path),
+            });
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    for edit in edits {
+        if let Some(mapping) = slots.get_mut(&edit.path) {
+            **mapping = edit.mapping;
+        }
+    }
+    Ok(())
+}
+
+fn slots(config: &mut PeaConfig) -> Vec<Slot<'_>> {
+    let mut slots = Vec::new();
+    for (s, service) in config.services.iter_mut().enumerate() {
+        let path = format!("services[{}]", s);
+        let service_tag = service.tag.clone();
+        for (i, parameter) in service.config_parameters.iter_mut().enumerate() {
+            let (tag, mapping) = parameter_mapping(parameter);
+            slots.push(Slot {
+                path: format!("{}.config_parameters[{}].tag_mapping", path, i),
+                service: Some(service_tag.clone()),
+                procedure: None,
+                tag,
+                mapping,
+            });
+        }
+        for (p, procedure) in service.procedures.iter_mut().enumerate() {
+            let path = format!("{}.procedures[{}]", path, p);
+            let mut push = |field: String, (tag, mapping)| {
+                slots.push(Slot {
+                    path: format!("{}.{}.tag_mapping", path, field),
+                    service: Some(service_tag.clone()),
+                    procedure: Some(procedure.id),
+                    tag,
+                    mapping,
+                })
+            };
+            for (i, parameter) in procedure.parameters.iter_mut().enumerate() {
+                push(format!("parameters[{}]", i), parameter_mapping(parameter));
+            }
+            for (i, view) in procedure.process_value_outs.iter_mut().enumerate() {
+                push(
+                    format!("process_value_outs[{}]", i),
+                    indicator_mapping(view),
+                );
+            }
+            for (i, view) in procedure.report_values.iter_mut().enumerate() {
+                push(format!("report_values[{}]", i), indicator_mapping(view));
+            }
+        }
+    }
+
+    for (e, element) in config.active_elements.iter_mut().enumerate() {
+        let (tag, mappings) = element_mappings(element);
+        for (field, mapping) in mappings {
+            slots.push(Slot {
+                path: format!("active_elements[{}].{}", e, field),
+                service: None,
+                procedure: None,
+                tag: tag.clone(),
+                mapping,
+            });
+        }
+    }
+    slots
+}
+
+fn parameter_mapping(parameter: &mut ServiceParameter) -> (String, &mut Option<TagMapping>) {
+    match parameter {
+        ServiceParameter::Analog(p) => (p.tag.clone(), &mut p.tag_mapping),
+        ServiceParameter::Binary(p) => (p.tag.clone(), &mut p.tag_mapping),
+        ServiceParameter::DInt(p) => (p.tag.clone(), &mut p.tag_mapping),
+        ServiceParameter::StringParam(p) => (p.tag.clone(), &mut p.tag_mapping),
+    }
+}
+
+fn indicator_mapping(view: &mut IndicatorElement) -> (String, &mut Option<TagMapping>) {
+    match view {
+        IndicatorElement::AnaView(v) => (v.tag.clone(), &mut v.tag_mapping),
+        IndicatorElement::BinView(v) => (v.tag.clone(), &mut v.tag_mapping),
+        IndicatorElement::BinStringView(v) => (v.tag.clone(), &mut v.tag_mapping),
+        IndicatorElement::DIntView(v) => (v.tag.clone(), &mut v.tag_mapping),
+        IndicatorElement::DIntStringView(v) => (v.tag.clone(), &mut v.tag_mapping),
+        IndicatorElement::StringView(v) => (v.tag.clone(), &mut v.tag_mapping),
+    }
+}
+
+type ElementMappings<'a> = (String, Vec<(&'static str, &'a mut Option<TagMapping>)>);
+
+fn element_mappings(element: &mut ActiveElement) -> ElementMappings<'_> {
+    match element {
+        ActiveElement::BinVlv(v) => (
+            v.tag.clone(),
+            vec![
+                ("open_fbk_tag", &mut v.open_fbk_tag),
+                ("close_fbk_tag", &mut v.close_fbk_tag),
+                ("open_cmd_tag", &mut v.open_cmd_tag),
+                ("close_cmd_tag", &mut v.close_cmd_tag),
+            ],
+        ),
+        ActiveElement::BinMon(v) => (v.tag.clone(), vec![("fbk_tag", &mut v.fbk_tag)]),
+        ActiveElement::AnaVlv(v) => (
+            v.tag.clone(),
+            vec![
+                ("pos_fbk_tag", &mut v.pos_fbk_tag),
+                ("pos_sp_tag", &mut v.pos_sp_tag),
+            ],
+        ),
+        ActiveElement::BinDrv(v) => (
+            v.tag.clone(),
+            vec![
+                ("fwd_fbk_tag", &mut v.fwd_fbk_tag),
+                ("rev_fbk_tag", &mut v.rev_fbk_tag),
+                ("fwd_cmd_tag", &mut v.fwd_cmd_tag),
+                ("rev_cmd_tag", &mut v.rev_cmd_tag),
+                ("stop_cmd_tag", &mut v.stop_cmd_tag),
+            ],
+        ),
+        ActiveElement::AnaDrv(v) => (
+            v.tag.clone(),
+            vec![
+                ("rpm_fbk_tag", &mut v.rpm_fbk_tag),
+                ("rpm_sp_tag", &mut v.rpm_sp_tag),
+                ("fwd_cmd_tag", &mut v.fwd_cmd_tag),
+                ("rev_cmd_tag", &mut v.rev_cmd_tag),
+                ("stop_cmd_tag", &mut v.stop_cmd_tag),
+            ],
+        ),
+        ActiveElement::DIntDrv(v) => (
+            v.tag.clone(),
+            vec![
+                ("rpm_fbk_tag", &mut v.rpm_fbk_tag),
+                ("rpm_sp_tag", &mut v.rpm_sp_tag),
+                ("fwd_cmd_tag", &mut v.fwd_cmd_tag),
+                ("rev_cmd_tag", &mut v.rev_cmd_tag),
+                ("stop_cmd_tag", &mut v.stop_cmd_tag),
+            ],
+        ),
+        ActiveElement::DIntMon(v) => (v.tag.clone(), vec![("fbk_tag", &mut v.fbk_tag)]),
+        ActiveElement::PIDCtrl(v) => (
+            v.tag.clone(),
+            vec![
+                ("pv_tag", &mut v.pv_tag),
+                ("sp_tag", &mut v.sp_tag),
+                ("mv_tag", &mut v.mv_tag),
+            ],
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{bin_mon, pea_config};
+    use shared::mtp::ProtocolType;
+
+    fn config() -> PeaConfig {
+        let mut config = pea_config("reactor");
+        config.services[0].procedures[0].parameters =
+            vec![serde_json::from_value(serde_json::json!({
+                "type": "Binary",
+                "tag": "Enable",
+                "name": "Enable",
+                "v_state0": "Off",
+                "v_state1": "On",
+                "v_default": false,
+                "tag_mapping": {"protocol": "OpcUa", "address": "ns=2;s=Enable"},
+            }))
+            .unwrap()];
+        config.active_elements = vec![bin_mon("LS1")];
+        config
+    }
+
+    fn paths(config: &PeaConfig) -> Vec<(String, Option<TagMapping>)> {
+        rows(config)
+            .into_iter()
+            .map(|row| (row.path, row.mapping))
+            .collect()
+    }
+
+    #[test]
+    fn edits_set_the_listed_mappings_only() {
+        let mut config = config();
+        let level = TagMapping {
+            protocol: ProtocolType::Modbus,
+            address: "1:10001".to_string(),
+        };
+        let enable = rows(&config)[0].mapping.clone();
+
+        apply(
+            &mut config,
+            vec![TagMappingEdit {
+                path: "active_elements[0].fbk_tag".to_string(),
+                mapping: Some(level.clone()),
+            }],
+        )
+        .unwrap();
+
+        assert_eq!(
+            paths(&config),
+            vec![
+                (
+                    "services[0].procedures[0].parameters[0].tag_mapping".to_string(),
+                    enable
+                ),
+                ("active_elements[0].fbk_tag".to_string(), Some(level)),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_and_repeated_paths_apply_nothing() {
+        let mut config = config();
+        let before = paths(&config);
+        let clear = |path: &str| TagMappingEdit {
+            path: path.to_string(),
+            mapping: None,
+        };
+
+        let errors = apply(
+            &mut config,
+            vec![
+                clear("services[0].procedures[0].parameters[0].tag_mapping"),
+                clear("services[0].procedures[0].parameters[0].tag_mapping"),
+                clear("active_elements[0].open_fbk_tag"),
+            ],
+        )
+        .unwrap_err();
+
+        let error_paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(error_paths, vec!["rows[1].path", "rows[2].path"]);
+        assert_eq!(paths(&config), before);
+    }
+}
@@ -0,0 +1,205 @@
+use chrono::Utc;
+use serde::Deserialize;
+use shared::mtp::PeaConfig;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tracing::error;
+
+/// Replaced by the instance number (1, 2, ...) in every string of a stamped PEA.
+pub const INSTANCE_PLACEHOLDER: &str = "{n}";
+
+/// Upper bound on PEAs stamped out by one request.
+pub const MAX_STAMP_COUNT: u32 = 100;
+
+/// How to stamp PEAs out of a template or an existing PEA.
+#[derive(Debug, Default, Deserialize)]
+pub struct StampRequest {
+    /// Id of the new PEA; with `count` above 1, the base of `<id>-<n>`. Random when omitted.
+    pub id: Option<String>,
+    /// Name of the new PEAs; ` <n>` is appended when stamping several and it has no `{n}`.
+    pub name: Option<String>,
+    /// Number of PEAs to create (default 1).
+    pub count: Option<u32>,
+}
+
+/// Templates under `dir`, keyed by file stem. A template is a PEA config
+/// whose `id` and timestamps may be omitted.
+pub fn load(dir: &str) -> BTreeMap<String, PeaConfig> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return BTreeMap::new(),
+        Err(e) => {
+            error!("Failed to read PEA template dir {}: {}", dir, e);
+            return BTreeMap::new();
+        }
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let id = path.file_stem()?.to_str()?.to_string();
+            (path.extension()? == "json")
+                .then(|| read(&path, &id))
+                .flatten()
+                .map(|config| (id, config))
+        })
+        .collect()
+}
+
+/// One template, or `None` when it does not exist or does not parse.
+pub fn get(dir: &str, template_id: &str) -> Option<PeaConfig> {
+    if template_id.is_empty() || template_id.contains(['/', '\\', '.']) {
+        return None;
+    }
+    let path = Path::new(dir).join(format!("{}.json", template_id));
+    path.exists().then(|| read(&path, template_id)).flatten()
+}
+
+fn read(path: &Path, template_id: &str) -> Option<PeaConfig> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| error!("Failed to read {}: {}", path.display(), e))
+        .ok()?;
+    let mut document: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| error!("Failed to parse {}: {}", path.display(), e))
+        .ok()?;
+    if let Some(object) = document.as_object_mut() {
+        let now = serde_json::json!(Utc::now());
+        object.entry("id").or_insert_with(|| template_id.into());
+        object.entry("created_at").or_insert_with(|| now.clone());
+        object.entry("updated_at").or_insert(now);
+    }
+    serde_json::from_value(document)
+        .map_err(|e| error!("Invalid PEA template {}: {}", path.display(), e))
+        .ok()
+}
+
+/// The template as listed by GET /pea/templates, without its structure.
+pub fn summary(template_id: &str, config: &PeaConfig) -> serde_json::Value {
+    serde_json::json!({
+        "id": template_id,
+        "name": config.name,
+        "description": config.description,
+        "version": config.version,
+        "mode": config.mode,
+        "services": config.services.iter().map(|s| &s.tag).collect::<Vec<_>>(),
+        "active_elements": config.active_elements.len(),
+    })
+}
+
+/// New PEA configs copied from `source`, numbered from 1. Ownership and
+/// timestamps are left for the caller to set.
+pub fn stamp(source: &PeaConfig, request: &StampRequest) -> Result<Vec<PeaConfig>, String> {
+    let count = request.count.unwrap_or(1);
+    if count == 0 || count > MAX_STAMP_COUNT {
+        return Err(format!("count must be between 1 and {}", MAX_STAMP_COUNT));
+    }
+    let name = request.name.as_deref().unwrap_or(&source.name).trim();
+    if name.is_empty() {
+        return Err("name must not be empty".to_string());
+    }
+    let template = serde_json::to_value(source).map_err(|e| e.to_string())?;
+
+    (1..=count)
+        .map(|n| {
+            let mut config: PeaConfig =
+                serde_json::from_value(substitute(template.clone(), &n.to_string()))
+                    .map_err(|e| e.to_string())?;
+            config.id = match request.id.as_deref() {
+                Some(id) if count == 1 => id.to_string(),
+                Some(id) => format!("{}-{}", id, n),
+                None => uuid::Uuid::new_v4().to_string(),
+            };
+            config.name = if count > 1 && !name.contains(INSTANCE_PLACEHOLDER) {
+                format!("{} {}", name, n)
+            } else {
+                name.replace(INSTANCE_PLACEHOLDER, &n.to_string())
+            };
+            config.tenant_id = None;
+            Ok(config)
+        })
+        .collect()
+}
+
+fn substitute(value: serde_json::Value, instance: &str) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(text) => {
+            serde_json::Value::String(text.replace(INSTANCE_PLACEHOLDER, instance))
+        }
+        serde_json::Value::Array(items) => items
+            .into_iter()
+            .map(|item| substitute(item, instance))
+            .collect(),
+        serde_json::Value::Object(fields) => fields
+            .into_iter()
+            .map(|(key, item)| (key, substitute(item, instance)))
+            .collect(),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{bin_mon, pea_config};
+
+    fn template() -> PeaConfig {
+        PeaConfig {
+            name: "Pump".to_string(),
+            description: "Feed pump {n}".to_string(),
+            active_elements: vec![bin_mon("P{n}_RUN")],
+            tenant_id: Some("t1".to_string()),
+            ..pea_config("pump")
+        }
+    }
+
+    #[test]
+    fn stamping_numbers_ids_names_and_placeholders() {
+        let request = StampRequest {
+            id: Some("pump".to_string()),
+            name: Some("Feed Pump".to_string()),
+            count: Some(3),
+        };
+        let peas = stamp(&template(), &request).unwrap();
+
+        let ids: Vec<&str> = peas.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["pump-1", "pump-2", "pump-3"]);
+        assert_eq!(peas[1].name, "Feed Pump 2");
+        assert_eq!(peas[1].description, "Feed pump 2");
+        let element = serde_json::to_value(&peas[2].active_elements[0]).unwrap();
+        assert_eq!(element["tag"], "P3_RUN");
+        assert!(peas.iter().all(|p| p.tenant_id.is_none()));
+
+        let single = stamp(&template(), &StampRequest::default()).unwrap();
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].name, "Pump");
+        assert_ne!(single[0].id, "pump");
+
+        let too_many = StampRequest {
+            count: Some(MAX_STAMP_COUNT + 1),
+            ..StampRequest::default()
+        };
+        assert!(stamp(&template(), &too_many).is_err());
+    }
+
+    #[test]
+    fn templates_may_omit_id_and_timestamps() {
+        let dir =
+            std::env::temp_dir().join(format!("fendtastic-pea-templates-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut document = serde_json::to_value(template()).unwrap();
+        for field in ["id", "created_at", "updated_at"] {
+            document.as_object_mut().unwrap().remove(field);
+        }
+        fs::write(dir.join("pump.json"), document.to_string()).unwrap();
+        fs::write(dir.join("broken.json"), "{").unwrap();
+
+        let dir = dir.to_string_lossy().to_string();
+        let templates = load(&dir);
+        assert_eq!(templates.keys().collect::<Vec<_>>(), vec!["pump"]);
+        assert_eq!(get(&dir, "pump").unwrap().id, "pump");
+        assert!(get(&dir, "../pump").is_none());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
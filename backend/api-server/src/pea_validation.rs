@@ -0,0 +1,467 @@
+use serde::Serialize;
+use shared::mtp::units;
+use shared::mtp::{
+    ActiveElement, DisplayHints, IndicatorElement, PeaConfig, ProtocolType, ServiceParameter,
+    TagMapping,
+};
+use std::collections::HashSet;
+
+use crate::pea_importer::is_opcua_node_id;
+
+/// A problem with one field of a PEA config, located by its path in the
+/// submitted document, e.g. `services[0].procedures[1].parameters[2].v_default`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FieldError {
+    pub path: String,
+    pub message: String,
+}
+
+/// Checks a submitted PEA config beyond what deserialization enforces:
+/// unique service tags and procedure ids, parameter defaults within their
+/// limits, display units their values convert to, and tag mapping addresses
+/// that fit their protocol.
+pub fn validate_pea_config(config: &PeaConfig) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+
+    let mut service_tags = HashSet::new();
+    for (s, service) in config.services.iter().enumerate() {
+        let path = format!("services[{}]", s);
+        if service.tag.trim().is_empty() {
+            push(
+                &mut errors,
+                format!("{}.tag", path),
+                "Service tag is required",
+            );
+        } else if !service_tags.insert(service.tag.as_str()) {
+            push(
+                &mut errors,
+                format!("{}.tag", path),
+                format!("Duplicate service tag '{}'", service.tag),
+            );
+        }
+
+        for (i, parameter) in service.config_parameters.iter().enumerate() {
+            check_parameter(
+                &mut errors,
+                &format!("{}.config_parameters[{}]", path, i),
+                parameter,
+            );
+        }
+
+        let mut procedure_ids = HashSet::new();
+        for (p, procedure) in service.procedures.iter().enumerate() {
+            let path = format!("{}.procedures[{}]", path, p);
+            if !procedure_ids.insert(procedure.id) {
+                push(
+                    &mut errors,
+                    format!("{}.id", path),
+                    format!(
+                        "Duplicate procedure id {} in service '{}'",
+                        procedure.id, service.tag
+                    ),
+                );
+            }
+            for (i, parameter) in procedure.parameters.iter().enumerate() {
+                check_parameter(
+                    &mut errors,
+                    &format!("{}.parameters[{}]", path, i),
+                    parameter,
+                );
+            }
+            for (i, view) in procedure.process_value_outs.iter().enumerate() {
+                check_mapping(
+                    &mut errors,
+                    &format!("{}.process_value_outs[{}].tag_mapping", path, i),
+                    indicator_mapping(view),
+                );
+            }
+            for (i, view) in procedure.report_values.iter().enumerate() {
+                check_mapping(
+                    &mut errors,
+                    &format!("{}.report_values[{}].tag_mapping", path, i),
+                    indicator_mapping(view),
+                );
+            }
+        }
+    }
+
+    for (e, element) in config.active_elements.iter().enumerate() {
+        for (field, mapping) in element_mappings(element) {
+            check_mapping(
+                &mut errors,
+                &format!("active_elements[{}].{}", e, field),
+                mapping.as_ref(),
+            );
+        }
+    }
+
+    errors
+}
+
+fn push(errors: &mut Vec<FieldError>, path: String, message: impl Into<String>) {
+    errors.push(FieldError {
+        path,
+        message: message.into(),
+    });
+}
+
+fn check_parameter(errors: &mut Vec<FieldError>, path: &str, parameter: &ServiceParameter) {
+    match parameter {
+        ServiceParameter::Analog(p) => {
+            check_bounds(errors, path, p.v_min, p.v_default, p.v_max);
+            if let Some(display) = &p.display {
+                check_display(errors, path, &p.unit, display);
+                if !display.labels.is_empty() {
+                    push(
+                        errors,
+                        format!("{}.display.labels", path),
+                        "Labels are only shown for DInt parameters",
+                    );
+                }
+            }
+            check_mapping(
+                errors,
+                &format!("{}.tag_mapping", path),
+                p.tag_mapping.as_ref(),
+            );
+        }
+        ServiceParameter::DInt(p) => {
+            check_bounds(errors, path, p.v_min, p.v_default, p.v_max);
+            if let Some(display) = &p.display {
+                check_display(errors, path, &p.unit, display);
+            }
+            check_mapping(
+                errors,
+                &format!("{}.tag_mapping", path),
+                p.tag_mapping.as_ref(),
+            );
+        }
+        ServiceParameter::Binary(p) => {
+            check_mapping(
+                errors,
+                &format!("{}.tag_mapping", path),
+                p.tag_mapping.as_ref(),
+            );
+        }
+        ServiceParameter::StringParam(p) => {
+            check_mapping(
+                errors,
+                &format!("{}.tag_mapping", path),
+                p.tag_mapping.as_ref(),
+            );
+        }
+    }
+}
+
+fn check_bounds<T: PartialOrd + std::fmt::Display>(
+    errors: &mut Vec<FieldError>,
+    path: &str,
+    min: T,
+    default: T,
+    max: T,
+) {
+    if min > max {
+        push(
+            errors,
+            format!("{}.v_min", path),
+            format!("v_min {} is greater than v_max {}", min, max),
+        );
+    } else if default < min || default > max {
+        push(
+            errors,
+            format!("{}.v_default", path),
+            format!("v_default {} is outside [{}, {}]", default, min, max),
+        );
+    }
+}
+
+fn check_display(errors: &mut Vec<FieldError>, path: &str, unit: &str, display: &DisplayHints) {
+    if let Some(shown) = &display.unit {
+        if units::conversion(unit, shown).is_none() {
+            push(
+                errors,
+                format!("{}.display.unit", path),
+                format!("Values in '{}' cannot be shown in '{}'", unit, shown),
+            );
+        }
+    }
+    if display.decimals.is_some_and(|decimals| decimals > 9) {
+        push(
+            errors,
+            format!("{}.display.decimals", path),
+            "At most 9 decimal places are shown",
+        );
+    }
+}
+
+fn check_mapping(errors: &mut Vec<FieldError>, path: &str, mapping: Option<&TagMapping>) {
+    let Some(mapping) = mapping else {
+        return;
+    };
+    let address = mapping.address.trim();
+    let path = format!("{}.address", path);
+    if address.is_empty() {
+        push(errors, path, "Address is required");
+        return;
+    }
+    let problem = match mapping.protocol {
+        ProtocolType::OpcUa => (!is_opcua_node_id(address))
+            .then(|| format!("'{}' is not an OPC UA node id such as ns=2;s=Tag", address)),
+        ProtocolType::Modbus => (!is_modbus_address(address)).then(|| {
+            format!(
+                "'{}' is not a Modbus register such as 40001 or 1:40001",
+                address
+            )
+        }),
+        ProtocolType::Zenoh => zenoh::key_expr::KeyExpr::try_from(address)
+            .err()
+            .map(|e| format!("'{}' is not a Zenoh key expression: {}", address, e)),
+    };
+    if let Some(message) = problem {
+        push(errors, path, message);
+    }
+}
+
+/// Accepts a register number, optionally prefixed with a unit id: `40001`, `1:40001`.
+fn is_modbus_address(address: &str) -> bool {
+    let register = match address.split_once(':') {
+        Some((unit, register)) if unit.parse::<u8>().is_ok() => register,
+        Some(_) => return false,
+        None => address,
+    };
+    register.parse::<u32>().is_ok()
+}
+
+fn indicator_mapping(view: &IndicatorElement) -> Option<&TagMapping> {
+    match view {
+        IndicatorElement::AnaView(v) => v.tag_mapping.as_ref(),
+        IndicatorElement::BinView(v) => v.tag_mapping.as_ref(),
+        IndicatorElement::BinStringView(v) => v.tag_mapping.as_ref(),
+        IndicatorElement::DIntView(v) => v.tag_mapping.as_ref(),
+        IndicatorElement::DIntStringView(v) => v.tag_mapping.as_ref(),
+        IndicatorElement::StringView(v) => v.tag_mapping.as_ref(),
+    }
+}
+
+fn element_mappings(element: &ActiveElement) -> Vec<(&'static str, &Option<TagMapping>)> {
+    match element {
+        ActiveElement::BinVlv(v) => vec![
+            ("open_fbk_tag", &v.open_fbk_tag),
+            ("close_fbk_tag", &v.close_fbk_tag),
+            ("open_cmd_tag", &v.open_cmd_tag),
+            ("close_cmd_tag", &v.close_cmd_tag),
+        ],
+        ActiveElement::BinMon(v) => vec![("fbk_tag", &v.fbk_tag)],
+        ActiveElement::AnaVlv(v) => vec![
+            ("pos_fbk_tag", &v.pos_fbk_tag),
+            ("pos_sp_tag", &v.pos_sp_tag),
+        ],
+        ActiveElement::BinDrv(v) => vec![
+            ("fwd_fbk_tag", &v.fwd_fbk_tag),
+            ("rev_fbk_tag", &v.rev_fbk_tag),
+            ("fwd_cmd_tag", &v.fwd_cmd_tag),
+            ("rev_cmd_tag", &v.rev_cmd_tag),
+            ("stop_cmd_tag", &v.stop_cmd_tag),
+        ],
+        ActiveElement::AnaDrv(v) => vec![
+            ("rpm_fbk_tag", &v.rpm_fbk_tag),
+            ("rpm_sp_tag", &v.rpm_sp_tag),
+            ("fwd_cmd_tag", &v.fwd_cmd_tag),
+            ("rev_cmd_tag", &v.rev_cmd_tag),
+            ("stop_cmd_tag", &v.stop_cmd_tag),
+        ],
+        ActiveElement::DIntDrv(v) => vec![
+            ("rpm_fbk_tag", &v.rpm_fbk_tag),
+            ("rpm_sp_tag", &v.rpm_sp_tag),
+            ("fwd_cmd_tag", &v.fwd_cmd_tag),
+            ("rev_cmd_tag", &v.rev_cmd_tag),
+            ("stop_cmd_tag", &v.stop_cmd_tag),
+        ],
+        ActiveElement::DIntMon(v) => vec![("fbk_tag", &v.fbk_tag)],
+        ActiveElement::PIDCtrl(v) => vec![
+            ("pv_tag", &v.pv_tag),
+            ("sp_tag", &v.sp_tag),
+            ("mv_tag", &v.mv_tag),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::pea_config;
+    use shared::mtp::{AnalogParameter, BinMonConfig, ProcedureConfig, ServiceConfig};
+
+    fn analog(
+        v_min: f64,
+        v_default: f64,
+        v_max: f64,
+        mapping: Option<TagMapping>,
+    ) -> ServiceParameter {
+        ServiceParameter::Analog(AnalogParameter {
+            tag: "SP".to_string(),
+            name: "Setpoint".to_string(),
+            unit: "l/h".to_string(),
+            v_scl_min: 0.0,
+            v_scl_max: 100.0,
+            v_min,
+            v_max,
+            v_default,
+            tag_mapping: mapping,
+            display: None,
+        })
+    }
+
+    fn procedure(id: u32, parameters: Vec<ServiceParameter>) -> ProcedureConfig {
+        ProcedureConfig {
+            id,
+            name: format!("Procedure {}", id),
+            is_self_completing: false,
+            is_default: id == 1,
+            parameters,
+            process_value_outs: Vec::new(),
+            report_values: Vec::new(),
+        }
+    }
+
+    fn service(tag: &str, procedures: Vec<ProcedureConfig>) -> ServiceConfig {
+        ServiceConfig {
+            tag: tag.to_string(),
+            name: tag.to_string(),
+            description: String::new(),
+            config_parameters: Vec::new(),
+            procedures,
+        }
+    }
+
+    fn config(services: Vec<ServiceConfig>, active_elements: Vec<ActiveElement>) -> PeaConfig {
+        PeaConfig {
+            services,
+            active_elements,
+            ..pea_config("pea-1")
+        }
+    }
+
+    fn mapping(protocol: ProtocolType, address: &str) -> Option<TagMapping> {
+        Some(TagMapping {
+            protocol,
+            address: address.to_string(),
+        })
+    }
+
+    #[test]
+    fn valid_config_has_no_errors() {
+        let config = config(
+            vec![service(
+                "Dose",
+                vec![
+                    procedure(
+                        1,
+                        vec![analog(
+                            0.0,
+                            10.0,
+                            50.0,
+                            mapping(ProtocolType::OpcUa, "ns=2;s=FIC100.SP"),
+                        )],
+                    ),
+                    procedure(
+                        2,
+                        vec![analog(
+                            0.0,
+                            0.0,
+                            0.0,
+                            mapping(ProtocolType::Modbus, "1:40001"),
+                        )],
+                    ),
+                ],
+            )],
+            vec![ActiveElement::BinMon(BinMonConfig {
+                tag: "LS100".to_string(),
+                name: "Level switch".to_string(),
+                fbk_tag: mapping(ProtocolType::Zenoh, "dosing/ls100/fbk"),
+            })],
+        );
+        assert_eq!(validate_pea_config(&config), Vec::new());
+    }
+
+    #[test]
+    fn reports_each_problem_with_its_path() {
+        let config = config(
+            vec![
+                service(
+                    "Dose",
+                    vec![
+                        procedure(1, vec![analog(0.0, 80.0, 50.0, None)]),
+                        procedure(
+                            1,
+                            vec![analog(0.0, 1.0, 5.0, mapping(ProtocolType::OpcUa, " "))],
+                        ),
+                    ],
+                ),
+                service("Dose", Vec::new()),
+            ],
+            vec![ActiveElement::BinMon(BinMonConfig {
+                tag: "LS100".to_string(),
+                name: "Level switch".to_string(),
+                fbk_tag: mapping(ProtocolType::Modbus, "ns=2;s=LS100"),
+            })],
+        );
+        let paths: Vec<String> = validate_pea_config(&config)
+            .into_iter()
+            .map(|error| error.path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "services[0].procedures[0].parameters[0].v_default",
+                "services[0].procedures[1].id",
+                "services[0].procedures[1].parameters[0].tag_mapping.address",
+                "services[1].tag",
+                "active_elements[0].fbk_tag.address",
+            ]
+        );
+    }
+
+    #[test]
+    fn display_units_must_convert_from_the_stored_unit() {
+        let with_display = |unit: &str, labels: &[(i64, &str)]| {
+            let mut parameter = analog(0.0, 1.0, 5.0, None);
+            if let ServiceParameter::Analog(p) = &mut parameter {
+                p.display = Some(DisplayHints {
+                    decimals: Some(2),
+                    unit: Some(unit.to_string()),
+                    labels: labels
+                        .iter()
+                        .map(|(value, label)| (*value, label.to_string()))
+                        .collect(),
+                });
+            }
+            parameter
+        };
+        let config = config(
+            vec![service(
+                "Dose",
+                vec![procedure(
+                    1,
+                    vec![
+                        with_display("l/min", &[]),
+                        with_display("bar", &[]),
+                        with_display("l/h", &[(0, "Off")]),
+                    ],
+                )],
+            )],
+            Vec::new(),
+        );
+        let paths: Vec<String> = validate_pea_config(&config)
+            .into_iter()
+            .map(|error| error.path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "services[0].procedures[0].parameters[1].display.unit",
+                "services[0].procedures[0].parameters[2].display.labels",
+            ]
+        );
+    }
+}
@@ -0,0 +1,682 @@
+use crate::db;
+use crate::state::{AppState, TimeSeriesStore};
+use crate::tenancy::{self, TenantScope};
+use crate::ts_historian;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tracing::{error, info};
+use zenoh::key_expr::keyexpr;
+
+/// How long a new playback waits for a WebSocket client to attach before streaming anyway.
+const ATTACH_GRACE: Duration = Duration::from_secs(10);
+const MAX_SPEED: f64 = 1000.0;
+/// Frames between progress updates of a session's info.
+const PROGRESS_EVERY: usize = 50;
+
+/// A historical sample replayed to WebSocket clients.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybackFrame {
+    pub playback_id: String,
+    pub key: String,
+    pub payload: serde_json::Value,
+    pub timestamp_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackInfo {
+    pub id: String,
+    pub keys: Vec<String>,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub speed: f64,
+    pub state: String,
+    pub position_ms: Option<i64>,
+    pub frames_total: usize,
+    pub frames_sent: usize,
+    pub started_at: String,
+    /// Last start, control or running moment; sessions idle for longer than
+    /// `PLAYBACK_SESSION_TTL_S` are dropped.
+    #[serde(default = "Utc::now")]
+    pub last_active_at: DateTime<Utc>,
+    /// Tenant that started the playback; only its keys are replayed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+}
+
+/// Operator controls of a playback, applied by its streaming task.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PlaybackControl {
+    Pause,
+    Resume,
+    /// Continue from the first frame at or after this time.
+    Seek(i64),
+}
+
+pub struct PlaybackSession {
+    pub info: PlaybackInfo,
+    pub sender: broadcast::Sender<PlaybackFrame>,
+    controls: mpsc::UnboundedSender<PlaybackControl>,
+    task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl Drop for PlaybackSession {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+pub type PlaybackSessions = Arc<RwLock<HashMap<String, PlaybackSession>>>;
+
+#[derive(Deserialize)]
+pub struct PlaybackStartRequest {
+    /// Exact keys or Zenoh key expressions (e.g. "entmoot/habitat/nodes/*/pea/pea-1/**")
+    pub keys: Vec<String>,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    /// Replay speed multiplier (1.0 = real time)
+    pub speed: Option<f64>,
+}
+
+#[derive(Deserialize)]
+pub struct PlaybackSeekRequest {
+    pub position_ms: i64,
+}
+
+/// POST /playback/start — replay stored history over the WebSocket channel.
+/// Frames are redacted per attached connection, like live subscriptions, and
+/// limited to the keys of the caller's tenant.
+pub async fn start_playback(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<PlaybackStartRequest>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let req = body.into_inner();
+    if req.keys.is_empty() {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": "At least one key is required"}));
+    }
+    if req.end_ms <= req.start_ms {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": "end_ms must be after start_ms"}));
+    }
+    let speed = req.speed.unwrap_or(1.0);
+    if !(speed > 0.0 && speed <= MAX_SPEED) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("speed must be within (0, {}]", MAX_SPEED)
+        }));
+    }
+    if let Some(bad) = req
+        .keys
+        .iter()
+        .find(|key| keyexpr::new(key.as_str()).is_err())
+    {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Invalid key expression: {}", bad)
+        }));
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let frames = collect_frames(&state, &scope, &id, &req.keys, req.start_ms, req.end_ms).await;
+
+    let info = PlaybackInfo {
+        id: id.clone(),
+        keys: req.keys,
+        start_ms: req.start_ms,
+        end_ms: req.end_ms,
+        speed,
+        state: "pending".to_string(),
+        position_ms: None,
+        frames_total: frames.len(),
+        frames_sent: 0,
+        started_at: Utc::now().to_rfc3339(),
+        last_active_at: Utc::now(),
+        tenant_id: scope.tenant_id().map(str::to_string),
+    };
+    let session = open_session(&state.playback_sessions, info.clone(), frames, None);
+    state
+        .playback_sessions
+        .write()
+        .await
+        .insert(id.clone(), session);
+    save(&state, &info).await;
+
+    info!("Playback {} created with {} frames", id, info.frames_total);
+    HttpResponse::Accepted().json(info)
+}
+
+/// Reopens the playbacks saved before a restart, paused where they were.
+/// Their frames are collected again from the time-series store.
+pub async fn restore(state: &AppState, ttl: Duration) {
+    let since = idle_cutoff(Utc::now(), ttl);
+    let infos = match db::load_playback_sessions(&state.db_client, since).await {
+        Ok(infos) => infos,
+        Err(e) => {
+            error!("Failed to load playback sessions: {}", e);
+            return;
+        }
+    };
+    for value in infos {
+        let mut info: PlaybackInfo = match serde_json::from_value(value) {
+            Ok(info) => info,
+            Err(e) => {
+                error!("Skipping unreadable playback session: {}", e);
+                continue;
+            }
+        };
+        let scope = match info.tenant_id.as_deref() {
+            None => TenantScope::Deployment,
+            Some(tenant_id) => match state.tenants.read().await.get(tenant_id) {
+                Some(tenant) => TenantScope::Tenant(tenant.clone()),
+                None => {
+                    error!(
+                        "Skipping playback {} of unknown tenant {}",
+                        info.id, tenant_id
+                    );
+                    continue;
+                }
+            },
+        };
+        let frames = collect_frames(
+            state,
+            &scope,
+            &info.id,
+            &info.keys,
+            info.start_ms,
+            info.end_ms,
+        )
+        .await;
+        let position_ms = info.position_ms.unwrap_or(info.start_ms);
+        info.frames_total = frames.len();
+        info.frames_sent = frames.partition_point(|frame| frame.timestamp_ms < position_ms);
+        info.state = "paused".to_string();
+        let session = open_session(
+            &state.playback_sessions,
+            info.clone(),
+            frames,
+            Some(position_ms),
+        );
+        state
+            .playback_sessions
+            .write()
+            .await
+            .insert(info.id.clone(), session);
+        info!("Playback {} restored at {}", info.id, position_ms);
+    }
+}
+
+/// Drops playbacks idle for longer than `ttl` and saves the others, so they
+/// survive a restart. Running playbacks count as active.
+pub async fn sweep(state: &AppState, ttl: Duration) {
+    let now = Utc::now();
+    let cutoff = idle_cutoff(now, ttl);
+    let kept: Vec<PlaybackInfo> = {
+        let mut sessions = state.playback_sessions.write().await;
+        sessions.retain(|id, session| {
+            if session.info.state == "running" {
+                session.info.last_active_at = now;
+            }
+            let keep = session.info.last_active_at >= cutoff;
+            if !keep {
+                info!("Playback {} expired", id);
+            }
+            keep
+        });
+        sessions
+            .values()
+            .map(|session| session.info.clone())
+            .collect()
+    };
+    for info in &kept {
+        save(state, info).await;
+    }
+    if let Err(e) = db::delete_idle_playback_sessions(&state.db_client, cutoff).await {
+        error!("Failed to delete expired playback sessions: {}", e);
+    }
+}
+
+/// Playbacks last active before this are expired. A `ttl` reaching back past
+/// the epoch expires none.
+fn idle_cutoff(now: DateTime<Utc>, ttl: Duration) -> DateTime<Utc> {
+    chrono::Duration::from_std(ttl)
+        .ok()
+        .and_then(|ttl| now.checked_sub_signed(ttl))
+        .filter(|cutoff| *cutoff > DateTime::UNIX_EPOCH)
+        .unwrap_or(DateTime::UNIX_EPOCH)
+}
+
+async fn save(state: &AppState, info: &PlaybackInfo) {
+    if let Err(e) = db::upsert_playback_session(&state.db_client, info).await {
+        error!("Failed to save playback {}: {}", info.id, e);
+    }
+}
+
+/// Spawns the streaming task of a new session. A session resumed at a
+/// position starts paused there; a new one waits for a client to attach.
+fn open_session(
+    sessions: &PlaybackSessions,
+    info: PlaybackInfo,
+    frames: Vec<PlaybackFrame>,
+    resume_at: Option<i64>,
+) -> PlaybackSession {
+    let (sender, _) = broadcast::channel(1024);
+    let (controls, receiver) = mpsc::unbounded_channel();
+    let player = Player {
+        id: info.id.clone(),
+        sender: sender.clone(),
+        next: resume_at.map_or(0, |position_ms| {
+            frames.partition_point(|frame| frame.timestamp_ms < position_ms)
+        }),
+        frames,
+        speed: info.speed,
+        controls: receiver,
+        paused: resume_at.is_some(),
+    };
+    PlaybackSession {
+        info,
+        sender,
+        controls,
+        task: Some(tokio::spawn(player.run(sessions.clone()))),
+    }
+}
+
+pub async fn list_playbacks(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let sessions = state.playback_sessions.read().await;
+    let list: Vec<&PlaybackInfo> = sessions
+        .values()
+        .map(|s| &s.info)
+        .filter(|info| scope.allows(info.tenant_id.as_deref()))
+        .collect();
+    HttpResponse::Ok().json(list)
+}
+
+pub async fn get_playback(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    playback_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let sessions = state.playback_sessions.read().await;
+    match sessions
+        .get(playback_id.as_str())
+        .filter(|session| scope.allows(session.info.tenant_id.as_deref()))
+    {
+        Some(session) => HttpResponse::Ok().json(&session.info),
+        None => playback_not_found(),
+    }
+}
+
+/// POST /playback/{id}/pause — hold the playback at its current frame.
+pub async fn pause_playback(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    playback_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    control(&state, &scope, &playback_id, PlaybackControl::Pause).await
+}
+
+/// POST /playback/{id}/resume — continue a paused playback.
+pub async fn resume_playback(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    playback_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    control(&state, &scope, &playback_id, PlaybackControl::Resume).await
+}
+
+/// POST /playback/{id}/seek — continue from `position_ms` within the window,
+/// backwards or forwards; a paused playback stays paused there.
+pub async fn seek_playback(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    playback_id: web::Path<String>,
+    body: web::Json<PlaybackSeekRequest>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let window = state
+        .playback_sessions
+        .read()
+        .await
+        .get(playback_id.as_str())
+        .filter(|session| scope.allows(session.info.tenant_id.as_deref()))
+        .map(|session| (session.info.start_ms, session.info.end_ms));
+    if let Some((start_ms, end_ms)) = window {
+        if !(start_ms..=end_ms).contains(&body.position_ms) {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("position_ms must be within [{}, {}]", start_ms, end_ms)
+            }));
+        }
+    }
+    control(
+        &state,
+        &scope,
+        &playback_id,
+        PlaybackControl::Seek(body.position_ms),
+    )
+    .await
+}
+
+async fn control(
+    state: &AppState,
+    scope: &TenantScope,
+    playback_id: &str,
+    control: PlaybackControl,
+) -> HttpResponse {
+    let mut sessions = state.playback_sessions.write().await;
+    let Some(session) = sessions
+        .get_mut(playback_id)
+        .filter(|session| scope.allows(session.info.tenant_id.as_deref()))
+    else {
+        return playback_not_found();
+    };
+    if session.controls.send(control).is_err() {
+        return HttpResponse::Conflict()
+            .json(serde_json::json!({"error": "Playback is no longer streaming"}));
+    }
+    session.info.last_active_at = Utc::now();
+    HttpResponse::Accepted().json(&session.info)
+}
+
+/// POST /playback/{id}/stop — abort a running playback and drop the session.
+pub async fn stop_playback(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    playback_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let removed = {
+        let mut sessions = state.playback_sessions.write().await;
+        match sessions.get(playback_id.as_str()) {
+            Some(session) if scope.allows(session.info.tenant_id.as_deref()) => {
+                sessions.remove(playback_id.as_str())
+            }
+            _ => None,
+        }
+    };
+    match removed {
+        Some(session) => {
+            if let Err(e) = db::delete_playback_session(&state.db_client, &playback_id).await {
+                error!("Failed to delete playback {}: {}", playback_id, e);
+            }
+            let mut info = session.info.clone();
+            info.state = "stopped".to_string();
+            HttpResponse::Ok().json(info)
+        }
+        None => playback_not_found(),
+    }
+}
+
+fn playback_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({"error": "Playback not found"}))
+}
+
+/// Keys of the caller's scope matching one of the playback's patterns.
+fn matching_keys(store: &TimeSeriesStore, scope: &TenantScope, patterns: &[String]) -> Vec<String> {
+    let patterns: Vec<&keyexpr> = patterns
+        .iter()
+        .filter_map(|p| keyexpr::new(p.as_str()).ok())
+        .collect();
+    store
+        .keys()
+        .into_iter()
+        .filter(|key| scope.allows_key(key))
+        .filter(|key| {
+            keyexpr::new(key.as_str())
+                .is_ok_and(|key_expr| patterns.iter().any(|p| p.intersects(key_expr)))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Frames of every matching key within the window, in time order. Points are
+/// read through the historian, so windows older than memory still play.
+async fn collect_frames(
+    state: &AppState,
+    scope: &TenantScope,
+    playback_id: &str,
+    patterns: &[String],
+    start_ms: i64,
+    end_ms: i64,
+) -> Vec<PlaybackFrame> {
+    let keys = matching_keys(&*state.timeseries.read().await, scope, patterns);
+    let mut frames: Vec<PlaybackFrame> = Vec::new();
+    for key in keys {
+        let points = ts_historian::points(state, &key, start_ms, end_ms).await;
+        frames.extend(points.into_iter().map(|point| PlaybackFrame {
+            playback_id: playback_id.to_string(),
+            key: key.clone(),
+            payload: point.value,
+            timestamp_ms: point.timestamp_ms,
+        }));
+    }
+    frames.sort_by_key(|frame| frame.timestamp_ms);
+    frames
+}
+
+/// The streaming task of a session: sends frames at their recorded pace,
+/// scaled by `speed`, and applies controls between them. It outlives the
+/// last frame so a completed playback can be sought back into.
+struct Player {
+    id: String,
+    sender: broadcast::Sender<PlaybackFrame>,
+    frames: Vec<PlaybackFrame>,
+    speed: f64,
+    controls: mpsc::UnboundedReceiver<PlaybackControl>,
+    /// Index of the next frame to send.
+    next: usize,
+    paused: bool,
+}
+
+impl Player {
+    async fn run(mut self, sessions: PlaybackSessions) {
+        if !self.paused {
+            let deadline = tokio::time::Instant::now() + ATTACH_GRACE;
+            while self.sender.receiver_count() == 0 && tokio::time::Instant::now() < deadline {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+            update_info(&sessions, &self.id, |info| {
+                info.state = "running".to_string()
+            })
+            .await;
+        }
+
+        let mut previous_ms: Option<i64> = None;
+        loop {
+            let wait = match self.frames.get(self.next) {
+                Some(frame) if !self.paused => Some(previous_ms.map_or(Duration::ZERO, |prev| {
+                    let gap_ms = ((frame.timestamp_ms - prev) as f64 / self.speed).max(0.0);
+                    Duration::from_millis(gap_ms as u64)
+                })),
+                _ => None,
+            };
+            tokio::select! {
+                control = self.controls.recv() => {
+                    // The session is gone once its control sender is dropped.
+                    let Some(control) = control else {
+                        break;
+                    };
+                    previous_ms = None;
+                    self.apply(control, &sessions).await;
+                }
+                _ = tokio::time::sleep(wait.unwrap_or_default()), if wait.is_some() => {
+                    let frame = self.frames[self.next].clone();
+                    previous_ms = Some(frame.timestamp_ms);
+                    let position_ms = frame.timestamp_ms;
+                    let _ = self.sender.send(frame);
+                    self.next += 1;
+                    let sent = self.next;
+
+                    if sent == self.frames.len() {
+                        update_info(&sessions, &self.id, |info| {
+                            info.frames_sent = sent;
+                            info.position_ms = Some(info.end_ms);
+                            info.state = "completed".to_string();
+                        })
+                        .await;
+                        info!("Playback {} completed", self.id);
+                    } else if sent % PROGRESS_EVERY == 1 {
+                        // Throttled to keep lock contention low on dense histories.
+                        update_info(&sessions, &self.id, |info| {
+                            info.position_ms = Some(position_ms);
+                            info.frames_sent = sent;
+                        })
+                        .await;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn apply(&mut self, control: PlaybackControl, sessions: &PlaybackSessions) {
+        let position_ms = match control {
+            PlaybackControl::Pause => {
+                self.paused = true;
+                self.last_sent_ms()
+            }
+            PlaybackControl::Resume => {
+                self.paused = false;
+                self.last_sent_ms()
+            }
+            PlaybackControl::Seek(position_ms) => {
+                self.next = self
+                    .frames
+                    .partition_point(|frame| frame.timestamp_ms < position_ms);
+                Some(position_ms)
+            }
+        };
+        let state = if self.paused {
+            "paused"
+        } else if self.next == self.frames.len() {
+            "completed"
+        } else {
+            "running"
+        };
+        let sent = self.next;
+        update_info(sessions, &self.id, |info| {
+            if position_ms.is_some() {
+                info.position_ms = position_ms;
+            }
+            info.frames_sent = sent;
+            info.state = state.to_string();
+        })
+        .await;
+    }
+
+    fn last_sent_ms(&self) -> Option<i64> {
+        let last = self.next.checked_sub(1)?;
+        Some(self.frames[last].timestamp_ms)
+    }
+}
+
+async fn update_info(sessions: &PlaybackSessions, id: &str, apply: impl FnOnce(&mut PlaybackInfo)) {
+    if let Some(session) = sessions.write().await.get_mut(id) {
+        apply(&mut session.info);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn playbacks_cover_the_keys_matching_their_patterns() {
+        let mut store = TimeSeriesStore::new(100);
+        store.insert("entmoot/a/value".to_string(), serde_json::json!(1), 30);
+        store.insert("entmoot/b/value".to_string(), serde_json::json!(2), 10);
+        store.insert("other/c/value".to_string(), serde_json::json!(4), 15);
+
+        let mut keys = matching_keys(
+            &store,
+            &TenantScope::Deployment,
+            &["entmoot/**".to_string()],
+        );
+        keys.sort();
+        assert_eq!(keys, vec!["entmoot/a/value", "entmoot/b/value"]);
+    }
+
+    #[test]
+    fn idle_cutoff_never_underflows() {
+        let now = Utc::now();
+        assert_eq!(
+            idle_cutoff(now, Duration::from_secs(60)),
+            now - chrono::Duration::seconds(60)
+        );
+        assert_eq!(idle_cutoff(now, Duration::MAX), DateTime::UNIX_EPOCH);
+    }
+
+    #[tokio::test]
+    async fn seeking_continues_from_the_first_frame_at_the_position() {
+        let frames: Vec<PlaybackFrame> = (0..10)
+            .map(|i| PlaybackFrame {
+                playback_id: "pb-1".to_string(),
+                key: "entmoot/a/value".to_string(),
+                payload: serde_json::json!(i),
+                timestamp_ms: i * 10,
+            })
+            .collect();
+        let info = PlaybackInfo {
+            id: "pb-1".to_string(),
+            keys: vec!["entmoot/**".to_string()],
+            start_ms: 0,
+            end_ms: 100,
+            speed: MAX_SPEED,
+            state: "paused".to_string(),
+            position_ms: Some(20),
+            frames_total: frames.len(),
+            frames_sent: 2,
+            started_at: Utc::now().to_rfc3339(),
+            last_active_at: Utc::now(),
+            tenant_id: None,
+        };
+        let sessions: PlaybackSessions = Arc::new(RwLock::new(HashMap::new()));
+        let session = open_session(&sessions, info, frames, Some(20));
+        let mut receiver = session.sender.subscribe();
+        let controls = session.controls.clone();
+        sessions.write().await.insert("pb-1".to_string(), session);
+
+        controls.send(PlaybackControl::Seek(65)).unwrap();
+        controls.send(PlaybackControl::Resume).unwrap();
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            received.push(receiver.recv().await.unwrap().timestamp_ms);
+        }
+        assert_eq!(received, vec![70, 80, 90]);
+
+        // A completed playback can be sought back into.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(sessions.read().await["pb-1"].info.state, "completed");
+        controls.send(PlaybackControl::Seek(0)).unwrap();
+        assert_eq!(receiver.recv().await.unwrap().timestamp_ms, 0);
+    }
+}
@@ -1,8 +1,17 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
 use tracing::error;
 
+use crate::alarm_bus;
+use crate::alarm_grouping;
+use crate::alarm_sla::{self, AlarmSla};
+use crate::config_replication;
+use crate::ical;
+use crate::operator_presence::reject_non_owner_alarm;
+use crate::runtime_store;
 use crate::state::{AlarmRule, AppState, BlackoutWindow, PolEdge, PolTopology};
+use crate::tenancy::{self, TenantScope};
 
 const ALARMS_FILE: &str = "alarms.json";
 const TOPOLOGY_FILE: &str = "topology.json";
@@ -36,27 +45,78 @@ pub struct BlackoutPayload {
     pub scope: Option<String>,
 }
 
-pub async fn get_topology(state: web::Data<AppState>) -> impl Responder {
+pub async fn get_topology(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let owned_peas = tenancy::owned_pea_ids(&state, &scope).await;
     let topology = state.topology.read().await;
-    HttpResponse::Ok().json(&*topology)
+    if let TenantScope::Deployment = scope {
+        return HttpResponse::Ok().json(&*topology);
+    }
+    HttpResponse::Ok().json(PolTopology {
+        edges: topology
+            .edges
+            .iter()
+            .filter(|edge| edge_in_scope(&scope, &owned_peas, edge))
+            .cloned()
+            .collect(),
+        updated_at: topology.updated_at.clone(),
+    })
 }
 
+/// Tenants replace only the edges between their own nodes; other edges are kept.
 pub async fn put_topology(
+    req: HttpRequest,
     state: web::Data<AppState>,
     body: web::Json<TopologyPayload>,
 ) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let owned_peas = tenancy::owned_pea_ids(&state, &scope).await;
     let payload = body.into_inner();
+    if payload
+        .edges
+        .iter()
+        .any(|edge| !edge_in_scope(&scope, &owned_peas, edge))
+    {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Topology edges must connect nodes owned by the tenant"
+        }));
+    }
+    let mut edges = match scope {
+        TenantScope::Deployment => Vec::new(),
+        TenantScope::Tenant(_) => state
+            .topology
+            .read()
+            .await
+            .edges
+            .iter()
+            .filter(|edge| !edge_in_scope(&scope, &owned_peas, edge))
+            .cloned()
+            .collect(),
+    };
+    edges.extend(payload.edges);
     let topology = PolTopology {
-        edges: payload.edges,
+        edges,
         updated_at: Utc::now().to_rfc3339(),
     };
+    store_topology(&state, &topology).await;
 
+    HttpResponse::Ok().json(topology)
+}
+
+/// Replaces the topology, persists it and announces it on the bus.
+pub(crate) async fn store_topology(state: &AppState, topology: &PolTopology) {
     {
         let mut stored = state.topology.write().await;
         *stored = topology.clone();
     }
-    persist_topology(&state.pol_db_dir, &topology);
-    if let Err(e) = upsert_topology_db(&state.db_client, &topology).await {
+    persist_topology(&state.pol_db_dir, topology);
+    if let Err(e) = upsert_topology_db(&state.db_client, topology).await {
         error!("Failed to persist topology in Postgres: {}", e);
     }
 
@@ -68,33 +128,70 @@ pub async fn put_topology(
         .zenoh_session
         .put(POL_TOPOLOGY_TOPIC, bus_msg.to_string())
         .await;
+}
 
-    HttpResponse::Ok().json(topology)
+fn edge_in_scope(
+    scope: &TenantScope,
+    owned_peas: &std::collections::HashSet<String>,
+    edge: &PolEdge,
+) -> bool {
+    scope.allows_source(&edge.from, owned_peas) && scope.allows_source(&edge.to, owned_peas)
 }
 
-pub async fn ack_alarm(state: web::Data<AppState>, alarm_id: web::Path<String>) -> impl Responder {
+pub async fn ack_alarm(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    alarm_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = reject_foreign_alarm(&state, &req, &alarm_id).await {
+        return response;
+    }
+    if let Some(response) = reject_non_owner_alarm(&state, &req, &alarm_id).await {
+        return response;
+    }
     handle_alarm_action(state, alarm_id.into_inner(), "acknowledged").await
 }
 
 pub async fn shelve_alarm(
+    req: HttpRequest,
     state: web::Data<AppState>,
     alarm_id: web::Path<String>,
 ) -> impl Responder {
+    if let Some(response) = reject_foreign_alarm(&state, &req, &alarm_id).await {
+        return response;
+    }
+    if let Some(response) = reject_non_owner_alarm(&state, &req, &alarm_id).await {
+        return response;
+    }
     handle_alarm_action(state, alarm_id.into_inner(), "shelved").await
 }
 
 pub async fn action_alarm(
+    req: HttpRequest,
     state: web::Data<AppState>,
     alarm_id: web::Path<String>,
     body: web::Json<AlarmActionPayload>,
 ) -> impl Responder {
+    if let Some(response) = reject_foreign_alarm(&state, &req, &alarm_id).await {
+        return response;
+    }
+    if let Some(response) = reject_non_owner_alarm(&state, &req, &alarm_id).await {
+        return response;
+    }
     handle_alarm_action(state, alarm_id.into_inner(), &body.action).await
 }
 
 pub async fn delete_alarm(
+    req: HttpRequest,
     state: web::Data<AppState>,
     alarm_id: web::Path<String>,
 ) -> impl Responder {
+    if let Some(response) = reject_foreign_alarm(&state, &req, &alarm_id).await {
+        return response;
+    }
+    if let Some(response) = reject_non_owner_alarm(&state, &req, &alarm_id).await {
+        return response;
+    }
     let id = alarm_id.into_inner();
     {
         let mut alarms = state.alarms.write().await;
@@ -104,6 +201,7 @@ pub async fn delete_alarm(
     if let Err(e) = delete_alarm_db(&state.db_client, &id).await {
         error!("Failed to delete alarm {} in Postgres: {}", id, e);
     }
+    alarm_bus::publish_removed(&state.zenoh_session, &id).await;
     let _ = state
         .zenoh_session
         .put(
@@ -119,18 +217,54 @@ pub async fn delete_alarm(
     HttpResponse::NoContent().finish()
 }
 
+/// Tenant-scoped callers may only act on alarms raised by their own sources.
+async fn reject_foreign_alarm(
+    state: &AppState,
+    req: &HttpRequest,
+    alarm_id: &str,
+) -> Option<HttpResponse> {
+    let scope = match tenancy::scope_for(state, req).await {
+        Ok(scope) => scope,
+        Err(e) => return Some(e.response()),
+    };
+    if let TenantScope::Deployment = scope {
+        return None;
+    }
+    let owned_peas = tenancy::owned_pea_ids(state, &scope).await;
+    let visible = state
+        .alarms
+        .read()
+        .await
+        .get(alarm_id)
+        .is_some_and(|alarm| scope.allows_source(&alarm.source, &owned_peas));
+    (!visible)
+        .then(|| HttpResponse::NotFound().json(serde_json::json!({"error": "Alarm not found"})))
+}
+
 async fn handle_alarm_action(
     state: web::Data<AppState>,
     alarm_id: String,
     status: &str,
 ) -> HttpResponse {
-    let updated = {
+    let (updated, children) = {
         let mut alarms = state.alarms.write().await;
         if let Some(alarm) = alarms.get_mut(&alarm_id) {
-            alarm.status = status.to_string();
-            Some(alarm.clone())
+            alarm_sla::record_status(alarm, status, Utc::now());
+            let updated = alarm.clone();
+            // Alarms grouped under a primary cause follow it.
+            let child_ids: Vec<String> = alarm_grouping::active_children(&alarms, &alarm_id)
+                .map(|child| child.id.clone())
+                .collect();
+            let mut children = Vec::with_capacity(child_ids.len());
+            for id in child_ids {
+                if let Some(child) = alarms.get_mut(&id) {
+                    alarm_sla::record_status(child, status, Utc::now());
+                    children.push(child.clone());
+                }
+            }
+            (Some(updated), children)
         } else {
-            None
+            (None, Vec::new())
         }
     };
 
@@ -140,21 +274,24 @@ async fn handle_alarm_action(
                 let alarms = state.alarms.read().await;
                 persist_alarms(&state.pol_db_dir, &alarms);
             }
-            if let Err(e) = upsert_alarm_db(&state.db_client, &alarm).await {
-                error!("Failed to persist alarm in Postgres: {}", e);
+            for changed in std::iter::once(&alarm).chain(&children) {
+                if let Err(e) = upsert_alarm_db(&state.db_client, changed).await {
+                    error!("Failed to persist alarm in Postgres: {}", e);
+                }
+                alarm_bus::publish(&state.zenoh_session, &state.chaos, changed).await;
+                let _ = state
+                    .zenoh_session
+                    .put(
+                        POL_ALARM_ACTION_TOPIC,
+                        serde_json::json!({
+                            "alarm_id": changed.id,
+                            "action": status,
+                            "timestamp": Utc::now().to_rfc3339(),
+                        })
+                        .to_string(),
+                    )
+                    .await;
             }
-            let _ = state
-                .zenoh_session
-                .put(
-                    POL_ALARM_ACTION_TOPIC,
-                    serde_json::json!({
-                        "alarm_id": alarm_id,
-                        "action": status,
-                        "timestamp": Utc::now().to_rfc3339(),
-                    })
-                    .to_string(),
-                )
-                .await;
             HttpResponse::Ok().json(alarm)
         }
         None => HttpResponse::NotFound().json(serde_json::json!({"error": "Alarm not found"})),
@@ -189,6 +326,7 @@ pub async fn create_alarm_rule(
     if let Err(e) = upsert_alarm_rule_db(&state.db_client, &rule).await {
         error!("Failed to persist alarm rule in Postgres: {}", e);
     }
+    config_replication::replicate_alarm_rule(&state, &rule.id, Some(&rule)).await;
     HttpResponse::Created().json(rule)
 }
 
@@ -217,6 +355,7 @@ pub async fn update_alarm_rule(
             if let Err(e) = upsert_alarm_rule_db(&state.db_client, &rule).await {
                 error!("Failed to persist alarm rule in Postgres: {}", e);
             }
+            config_replication::replicate_alarm_rule(&state, &rule.id, Some(&rule)).await;
             HttpResponse::Ok().json(rule)
         }
         None => HttpResponse::NotFound().json(serde_json::json!({"error": "Rule not found"})),
@@ -235,9 +374,234 @@ pub async fn delete_alarm_rule(
     if let Err(e) = delete_alarm_rule_db(&state.db_client, &id).await {
         error!("Failed to delete alarm rule from Postgres: {}", e);
     }
+    config_replication::replicate_alarm_rule(&state, &id, None).await;
+    HttpResponse::NoContent().finish()
+}
+
+/// GET /alarm-slas — response-time targets per severity
+pub async fn list_alarm_slas(state: web::Data<AppState>) -> impl Responder {
+    let slas = state.alarm_slas.read().await;
+    let mut list: Vec<AlarmSla> = slas.values().cloned().collect();
+    let severities = state.severity_profile.read().await;
+    list.sort_by_key(|sla| std::cmp::Reverse(severities.priority(&sla.severity)));
+    HttpResponse::Ok().json(list)
+}
+
+/// PUT /alarm-slas/{severity} — set the response-time targets of a severity
+pub async fn put_alarm_sla(
+    state: web::Data<AppState>,
+    severity: web::Path<String>,
+    body: web::Json<AlarmSla>,
+) -> impl Responder {
+    let mut sla = body.into_inner();
+    sla.severity = severity.trim().to_ascii_lowercase();
+    if let Err(e) = sla.validate() {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    sla.updated_at = Utc::now().to_rfc3339();
+    runtime_store::persist_json(&state.alarm_sla_dir, &sla.severity, &sla);
+    state
+        .alarm_slas
+        .write()
+        .await
+        .insert(sla.severity.clone(), sla.clone());
+    HttpResponse::Ok().json(sla)
+}
+
+/// DELETE /alarm-slas/{severity} — stop tracking a severity
+pub async fn delete_alarm_sla(
+    state: web::Data<AppState>,
+    severity: web::Path<String>,
+) -> impl Responder {
+    let severity = severity.trim().to_ascii_lowercase();
+    if state.alarm_slas.write().await.remove(&severity).is_none() {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "SLA not found"}));
+    }
+    runtime_store::delete_json(&state.alarm_sla_dir, &severity);
     HttpResponse::NoContent().finish()
 }
 
+#[derive(serde::Deserialize)]
+pub struct SlaReportQuery {
+    /// Start of the window alarms were raised in, as Unix milliseconds (default: 24h ago)
+    pub start_ms: Option<i64>,
+    /// End of the window as Unix milliseconds (default: now)
+    pub end_ms: Option<i64>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct SlaAtRiskQuery {
+    /// Share of a target after which a pending alarm is at risk (default: 0.8)
+    pub ratio: Option<f64>,
+}
+
+#[derive(Default, serde::Serialize)]
+struct SeverityCompliance {
+    alarms: usize,
+    ack_breaches: usize,
+    clear_breaches: usize,
+}
+
+/// SLA compliance of the alarms visible to the caller that have an SLA.
+async fn visible_compliance(
+    state: &AppState,
+    req: &HttpRequest,
+) -> Result<Vec<alarm_sla::SlaCompliance>, HttpResponse> {
+    let scope = tenancy::scope_for(state, req)
+        .await
+        .map_err(|e| e.response())?;
+    let owned_peas = tenancy::owned_pea_ids(state, &scope).await;
+    let slas = state.alarm_slas.read().await;
+    let now = Utc::now();
+    Ok(state
+        .alarms
+        .read()
+        .await
+        .values()
+        .filter(|alarm| scope.allows_source(&alarm.source, &owned_peas))
+        .filter_map(|alarm| {
+            let sla = slas.get(&alarm.severity.to_ascii_lowercase())?;
+            alarm_sla::evaluate(alarm, sla, now)
+        })
+        .collect())
+}
+
+/// GET /alarms/sla — SLA compliance per severity and the breaching alarms,
+/// for alarms raised in a window
+pub async fn get_alarm_sla_report(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<SlaReportQuery>,
+) -> impl Responder {
+    let end_ms = query
+        .end_ms
+        .unwrap_or_else(|| Utc::now().timestamp_millis());
+    let start_ms = query.start_ms.unwrap_or(end_ms - 24 * 60 * 60 * 1000);
+    let compliance = match visible_compliance(&state, &req).await {
+        Ok(compliance) => compliance,
+        Err(response) => return response,
+    };
+    let mut in_window: Vec<_> = compliance
+        .into_iter()
+        .filter(|c| {
+            DateTime::parse_from_rfc3339(&c.raised_at)
+                .is_ok_and(|raised| (start_ms..=end_ms).contains(&raised.timestamp_millis()))
+        })
+        .collect();
+    in_window.sort_by(|a, b| a.raised_at.cmp(&b.raised_at));
+
+    let mut by_severity: BTreeMap<String, SeverityCompliance> = BTreeMap::new();
+    for c in &in_window {
+        let entry = by_severity
+            .entry(c.severity.to_ascii_lowercase())
+            .or_default();
+        entry.alarms += 1;
+        entry.ack_breaches += usize::from(c.ack.as_ref().is_some_and(|stage| stage.breached));
+        entry.clear_breaches += usize::from(c.clear.as_ref().is_some_and(|stage| stage.breached));
+    }
+    let total = in_window.len();
+    let breaches: Vec<_> = in_window.into_iter().filter(|c| c.breached()).collect();
+    let compliance_ratio = if total > 0 {
+        (total - breaches.len()) as f64 / total as f64
+    } else {
+        1.0
+    };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "start_ms": start_ms,
+        "end_ms": end_ms,
+        "total": total,
+        "breached": breaches.len(),
+        "compliance": compliance_ratio,
+        "by_severity": by_severity,
+        "breaches": breaches,
+    }))
+}
+
+/// GET /alarms/sla/at-risk — active alarms close to or past an SLA target, soonest due first
+pub async fn get_alarms_sla_at_risk(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<SlaAtRiskQuery>,
+) -> impl Responder {
+    let ratio = query
+        .ratio
+        .filter(|ratio| *ratio >= 0.0)
+        .unwrap_or(alarm_sla::DEFAULT_AT_RISK_RATIO);
+    let compliance = match visible_compliance(&state, &req).await {
+        Ok(compliance) => compliance,
+        Err(response) => return response,
+    };
+    let due_at = |c: &alarm_sla::SlaCompliance| {
+        c.ack
+            .iter()
+            .chain(&c.clear)
+            .filter(|stage| !stage.completed)
+            .map(|stage| stage.due_at.clone())
+            .min()
+    };
+    let mut at_risk: Vec<_> = compliance
+        .into_iter()
+        .filter(|c| c.status == "open" || c.status == "acknowledged")
+        .filter(|c| c.at_risk(ratio))
+        .collect();
+    at_risk.sort_by_key(|c| due_at(c));
+    HttpResponse::Ok().json(serde_json::json!({
+        "ratio": ratio,
+        "count": at_risk.len(),
+        "alarms": at_risk,
+    }))
+}
+
+#[derive(serde::Deserialize)]
+pub struct SoeListQuery {
+    /// Only records of this PEA
+    pub pea_id: Option<String>,
+}
+
+/// GET /soe — sequence-of-events records captured at Critical trips, newest first
+pub async fn list_soe_records(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<SoeListQuery>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let owned_peas = tenancy::owned_pea_ids(&state, &scope).await;
+    let records: Vec<serde_json::Value> = state
+        .soe
+        .list()
+        .await
+        .iter()
+        .filter(|record| query.pea_id.as_ref().is_none_or(|pea| &record.pea_id == pea))
+        .filter(|record| scope.allows_source(&record.source, &owned_peas))
+        .map(|record| record.summary())
+        .collect();
+    HttpResponse::Ok().json(records)
+}
+
+/// GET /alarms/{id}/soe — keys of the alarm's PEA in the seconds before it tripped
+pub async fn get_alarm_soe(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    alarm_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let owned_peas = tenancy::owned_pea_ids(&state, &scope).await;
+    match state.soe.get(&alarm_id).await {
+        Some(record) if scope.allows_source(&record.source, &owned_peas) => {
+            HttpResponse::Ok().json(record)
+        }
+        _ => HttpResponse::NotFound()
+            .json(serde_json::json!({"error": "No sequence of events for this alarm"})),
+    }
+}
+
 pub async fn list_blackouts(state: web::Data<AppState>) -> impl Responder {
     let windows = state.blackout_windows.read().await;
     let list: Vec<BlackoutWindow> = windows.values().cloned().collect();
@@ -300,6 +664,69 @@ pub async fn delete_blackout(
     HttpResponse::NoContent().finish()
 }
 
+/// GET /calendar.ics — blackout windows as an iCal feed. Windows scoped to a PEA or
+/// source are exported as maintenance events.
+pub async fn export_calendar(state: web::Data<AppState>) -> impl Responder {
+    let mut windows: Vec<BlackoutWindow> = state
+        .blackout_windows
+        .read()
+        .await
+        .values()
+        .cloned()
+        .collect();
+    windows.sort_by(|a, b| a.starts_at.cmp(&b.starts_at));
+    HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .insert_header(("Content-Disposition", "inline; filename=\"calendar.ics\""))
+        .body(ical::export(&windows, Utc::now()))
+}
+
+/// POST /calendar.ics — creates or updates blackout windows from an iCal body.
+/// Events exported by `GET /calendar.ics` update their original window.
+pub async fn import_calendar(state: web::Data<AppState>, body: String) -> impl Responder {
+    let (imported, errors) = ical::import(&body);
+    if imported.is_empty() && !errors.is_empty() {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": "No usable events", "errors": errors}));
+    }
+
+    let mut created = 0;
+    let mut updated = 0;
+    for window in imported {
+        let blackout = {
+            let mut windows = state.blackout_windows.write().await;
+            let created_at = match windows.get(&window.id) {
+                Some(existing) => {
+                    updated += 1;
+                    existing.created_at.clone()
+                }
+                None => {
+                    created += 1;
+                    Utc::now().to_rfc3339()
+                }
+            };
+            let blackout = BlackoutWindow {
+                id: window.id,
+                name: window.name,
+                starts_at: window.starts_at.to_rfc3339(),
+                ends_at: window.ends_at.to_rfc3339(),
+                scope: window.scope,
+                created_at,
+            };
+            windows.insert(blackout.id.clone(), blackout.clone());
+            blackout
+        };
+        if let Err(e) = upsert_blackout_db(&state.db_client, &blackout).await {
+            error!("Failed to persist imported blackout in Postgres: {}", e);
+        }
+    }
+    HttpResponse::Ok().json(serde_json::json!({
+        "created": created,
+        "updated": updated,
+        "errors": errors,
+    }))
+}
+
 pub fn persist_alarms(
     dir: &str,
     alarms: &std::collections::HashMap<String, crate::state::AlarmRecord>,
@@ -342,8 +769,8 @@ pub async fn upsert_alarm_db(
     let ts = DateTime::parse_from_rfc3339(&alarm.timestamp)?.with_timezone(&Utc);
     client
         .execute(
-            "INSERT INTO alarms (id, severity, status, source, event, value, description, timestamp, duplicate_count)
-             VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9)
+            "INSERT INTO alarms (id, severity, status, source, event, value, description, timestamp, duplicate_count, parent_id, raised_at, acknowledged_at, cleared_at)
+             VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11,$12,$13)
              ON CONFLICT (id) DO UPDATE SET
                severity=EXCLUDED.severity,
                status=EXCLUDED.status,
@@ -352,7 +779,11 @@ pub async fn upsert_alarm_db(
                value=EXCLUDED.value,
                description=EXCLUDED.description,
                timestamp=EXCLUDED.timestamp,
-               duplicate_count=EXCLUDED.duplicate_count",
+               duplicate_count=EXCLUDED.duplicate_count,
+               parent_id=EXCLUDED.parent_id,
+               raised_at=EXCLUDED.raised_at,
+               acknowledged_at=EXCLUDED.acknowledged_at,
+               cleared_at=EXCLUDED.cleared_at",
             &[
                 &alarm.id,
                 &alarm.severity,
@@ -363,12 +794,23 @@ pub async fn upsert_alarm_db(
                 &alarm.description,
                 &ts,
                 &(alarm.duplicate_count as i32),
+                &alarm.parent_id,
+                &parse_optional_time(alarm.raised_at.as_deref())?,
+                &parse_optional_time(alarm.acknowledged_at.as_deref())?,
+                &parse_optional_time(alarm.cleared_at.as_deref())?,
             ],
         )
         .await?;
     Ok(())
 }
 
+fn parse_optional_time(value: Option<&str>) -> anyhow::Result<Option<DateTime<Utc>>> {
+    Ok(match value {
+        Some(value) => Some(DateTime::parse_from_rfc3339(value)?.with_timezone(&Utc)),
+        None => None,
+    })
+}
+
 pub async fn delete_alarm_db(
     client: &tokio_postgres::Client,
     alarm_id: &str,
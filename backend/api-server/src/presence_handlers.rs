@@ -0,0 +1,97 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use tracing::info;
+
+use crate::operator_presence;
+use crate::request_context::CallerContext;
+use crate::state::AppState;
+
+#[derive(serde::Deserialize)]
+pub struct PresencePayload {
+    /// Area id, e.g. `pea:reactor-1` or `alarms:boiler-house`
+    pub area: String,
+}
+
+/// GET /presence — areas currently owned by an operator
+pub async fn list_presence(state: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(state.presence.list(Utc::now()).await)
+}
+
+/// POST /presence/claim — take ownership of a free area, or renew one's own
+pub async fn claim_area(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<PresencePayload>,
+) -> impl Responder {
+    claim(&state, &req, &body.area, false).await
+}
+
+/// POST /presence/takeover — take ownership of an area held by another operator
+pub async fn take_over_area(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<PresencePayload>,
+) -> impl Responder {
+    claim(&state, &req, &body.area, true).await
+}
+
+async fn claim(state: &AppState, req: &HttpRequest, area: &str, takeover: bool) -> HttpResponse {
+    let caller = CallerContext::from_request(req);
+    match state
+        .presence
+        .claim(area, &caller, takeover, Utc::now())
+        .await
+    {
+        Ok(claim) => {
+            if let Some(previous) = &claim.taken_over_from {
+                info!(
+                    "{} took over {} from {}",
+                    claim.actor_id, claim.area, previous
+                );
+            }
+            operator_presence::publish(&state.zenoh_session, &claim).await;
+            HttpResponse::Ok().json(claim)
+        }
+        Err(e) => e.response(),
+    }
+}
+
+/// POST /presence/heartbeat — keep one's claim on an area alive
+pub async fn heartbeat_area(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<PresencePayload>,
+) -> impl Responder {
+    let caller = CallerContext::from_request(&req);
+    match state
+        .presence
+        .heartbeat(&body.area, &caller, Utc::now())
+        .await
+    {
+        Ok(claim) => {
+            operator_presence::publish(&state.zenoh_session, &claim).await;
+            HttpResponse::Ok().json(claim)
+        }
+        Err(e) => e.response(),
+    }
+}
+
+/// POST /presence/release — give up one's claim on an area
+pub async fn release_area(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<PresencePayload>,
+) -> impl Responder {
+    let caller = CallerContext::from_request(&req);
+    match state
+        .presence
+        .release(&body.area, &caller, Utc::now())
+        .await
+    {
+        Ok(claim) => {
+            operator_presence::publish_released(&state.zenoh_session, &claim).await;
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => e.response(),
+    }
+}
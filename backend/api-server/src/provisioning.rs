@@ -0,0 +1,195 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Prefix of node download tokens, distinct from tenant tokens.
+pub const TOKEN_PREFIX: &str = "fdn_";
+pub const DEFAULT_TOKEN_TTL_S: i64 = 24 * 3600;
+
+pub const STATUS_PLANNED: &str = "planned";
+pub const STATUS_TOKEN_ISSUED: &str = "token_issued";
+pub const STATUS_FETCHED: &str = "fetched";
+pub const STATUS_ACTIVATED: &str = "activated";
+
+/// A node planned for the mesh, with its generated Zenoh config and the state of
+/// its provisioning: planned → token_issued → fetched → activated.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlannedNode {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Options the config was generated from; see `mesh_handlers::build_node_config`.
+    pub options: serde_json::Value,
+    pub config: serde_json::Value,
+    pub status: String,
+    #[serde(default)]
+    pub token_hash: Option<String>,
+    pub token_expires_at: Option<String>,
+    pub fetched_at: Option<String>,
+    pub fetched_by: Option<String>,
+    pub activated_at: Option<String>,
+    /// Zenoh id reported by the node on activation.
+    pub zenoh_id: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TokenError {
+    Unknown,
+    Expired,
+    AlreadyUsed,
+    NotFetched,
+}
+
+impl TokenError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            TokenError::Unknown => "Unknown provisioning token",
+            TokenError::Expired => "Provisioning token has expired",
+            TokenError::AlreadyUsed => "Provisioning token has already been used",
+            TokenError::NotFetched => "Config must be downloaded before activation",
+        }
+    }
+}
+
+pub fn generate_token() -> String {
+    format!("{}{}", TOKEN_PREFIX, uuid::Uuid::new_v4().simple())
+}
+
+impl PlannedNode {
+    /// Node as returned by the API; the token hash is never exposed.
+    pub fn public_view(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(object) = value.as_object_mut() {
+            object.remove("token_hash");
+        }
+        value
+    }
+
+    /// Issues a fresh download token, invalidating any earlier one and restarting
+    /// the fetch/activation tracking. Returns the plain token.
+    pub fn issue_token(&mut self, ttl_s: i64, now: DateTime<Utc>) -> String {
+        let token = generate_token();
+        self.token_hash = Some(crate::tenancy::hash_token(&token));
+        self.token_expires_at = Some((now + Duration::seconds(ttl_s)).to_rfc3339());
+        self.status = STATUS_TOKEN_ISSUED.to_string();
+        self.fetched_at = None;
+        self.fetched_by = None;
+        self.activated_at = None;
+        self.zenoh_id = None;
+        self.updated_at = now.to_rfc3339();
+        token
+    }
+
+    pub fn holds_token(&self, token: &str) -> bool {
+        self.token_hash.as_deref() == Some(crate::tenancy::hash_token(token).as_str())
+    }
+
+    /// The token allows exactly one download before it expires.
+    pub fn redeem_download(
+        &mut self,
+        fetched_by: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), TokenError> {
+        if self.status != STATUS_TOKEN_ISSUED {
+            return Err(TokenError::AlreadyUsed);
+        }
+        let expired = self
+            .token_expires_at
+            .as_deref()
+            .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+            .is_none_or(|at| now > at);
+        if expired {
+            return Err(TokenError::Expired);
+        }
+        self.status = STATUS_FETCHED.to_string();
+        self.fetched_at = Some(now.to_rfc3339());
+        self.fetched_by = Some(fetched_by.to_string());
+        self.updated_at = now.to_rfc3339();
+        Ok(())
+    }
+
+    /// After the download, the same token confirms activation once; it is then
+    /// discarded.
+    pub fn activate(
+        &mut self,
+        zenoh_id: Option<String>,
+        now: DateTime<Utc>,
+    ) -> Result<(), TokenError> {
+        match self.status.as_str() {
+            STATUS_FETCHED => {}
+            STATUS_TOKEN_ISSUED => return Err(TokenError::NotFetched),
+            _ => return Err(TokenError::AlreadyUsed),
+        }
+        self.status = STATUS_ACTIVATED.to_string();
+        self.activated_at = Some(now.to_rfc3339());
+        self.zenoh_id = zenoh_id;
+        self.token_hash = None;
+        self.token_expires_at = None;
+        self.updated_at = now.to_rfc3339();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn planned() -> PlannedNode {
+        PlannedNode {
+            id: "edge-1".to_string(),
+            name: "Edge 1".to_string(),
+            description: String::new(),
+            options: serde_json::json!({"mode": "client"}),
+            config: serde_json::json!({"mode": "client"}),
+            status: STATUS_PLANNED.to_string(),
+            token_hash: None,
+            token_expires_at: None,
+            fetched_at: None,
+            fetched_by: None,
+            activated_at: None,
+            zenoh_id: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn token_allows_one_download_then_one_activation() {
+        let now = Utc::now();
+        let mut node = planned();
+        let token = node.issue_token(3600, now);
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert!(node.holds_token(&token));
+        assert!(!node.holds_token("fdn_other"));
+
+        assert_eq!(node.activate(None, now), Err(TokenError::NotFetched));
+        assert!(node.redeem_download("10.0.0.5", now).is_ok());
+        assert_eq!(
+            node.redeem_download("10.0.0.5", now),
+            Err(TokenError::AlreadyUsed)
+        );
+        assert!(node.activate(Some("zid-1".to_string()), now).is_ok());
+        assert_eq!(node.status, STATUS_ACTIVATED);
+        assert!(!node.holds_token(&token));
+    }
+
+    #[test]
+    fn expired_tokens_are_rejected_and_reissue_resets_tracking() {
+        let now = Utc::now();
+        let mut node = planned();
+        node.issue_token(60, now);
+        assert_eq!(
+            node.redeem_download("host", now + Duration::seconds(61)),
+            Err(TokenError::Expired)
+        );
+
+        let token = node.issue_token(60, now);
+        assert!(node.redeem_download("host", now).is_ok());
+        node.issue_token(60, now);
+        assert!(!node.holds_token(&token));
+        assert_eq!(node.status, STATUS_TOKEN_ISSUED);
+        assert!(node.fetched_at.is_none());
+    }
+}
@@ -0,0 +1,255 @@
+use crate::mesh_handlers;
+use crate::provisioning::{self, PlannedNode, TokenError};
+use crate::runtime_store;
+use crate::state::AppState;
+use crate::tenancy;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use tracing::info;
+
+#[derive(serde::Deserialize)]
+pub struct PlannedNodePayload {
+    pub name: String,
+    pub description: Option<String>,
+    /// Generation options, as accepted by `POST /mesh/generate-config`.
+    #[serde(default)]
+    pub options: serde_json::Value,
+}
+
+#[derive(serde::Deserialize)]
+pub struct TokenPayload {
+    pub ttl_s: Option<i64>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct ActivationPayload {
+    pub zenoh_id: Option<String>,
+}
+
+/// GET /provisioning/nodes
+pub async fn list_planned_nodes(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    let nodes = state.planned_nodes.read().await;
+    let mut list: Vec<&PlannedNode> = nodes.values().collect();
+    list.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    let list: Vec<serde_json::Value> = list.into_iter().map(PlannedNode::public_view).collect();
+    HttpResponse::Ok().json(list)
+}
+
+/// POST /provisioning/nodes — plan a node and store its generated Zenoh config.
+pub async fn create_planned_node(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<PlannedNodePayload>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    let payload = body.into_inner();
+    if payload.name.trim().is_empty() {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": "Node name is required"}));
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let node = PlannedNode {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: payload.name.trim().to_string(),
+        description: payload.description.unwrap_or_default(),
+        config: mesh_handlers::build_node_config(&payload.options),
+        options: payload.options,
+        status: provisioning::STATUS_PLANNED.to_string(),
+        token_hash: None,
+        token_expires_at: None,
+        fetched_at: None,
+        fetched_by: None,
+        activated_at: None,
+        zenoh_id: None,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    runtime_store::persist_json(&state.provisioning_dir, &node.id, &node);
+    state
+        .planned_nodes
+        .write()
+        .await
+        .insert(node.id.clone(), node.clone());
+
+    info!("Planned node {} ({})", node.name, node.id);
+    HttpResponse::Created().json(node.public_view())
+}
+
+/// GET /provisioning/nodes/{id}
+pub async fn get_planned_node(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    node_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    match state.planned_nodes.read().await.get(node_id.as_str()) {
+        Some(node) => HttpResponse::Ok().json(node.public_view()),
+        None => node_not_found(),
+    }
+}
+
+/// PUT /provisioning/nodes/{id} — regenerate the config. A node that already
+/// fetched its config has to be issued a new token to pick up the change.
+pub async fn update_planned_node(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    node_id: web::Path<String>,
+    body: web::Json<PlannedNodePayload>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    let payload = body.into_inner();
+    let mut nodes = state.planned_nodes.write().await;
+    let Some(node) = nodes.get_mut(node_id.as_str()) else {
+        return node_not_found();
+    };
+    if !payload.name.trim().is_empty() {
+        node.name = payload.name.trim().to_string();
+    }
+    if let Some(description) = payload.description {
+        node.description = description;
+    }
+    node.config = mesh_handlers::build_node_config(&payload.options);
+    node.options = payload.options;
+    node.updated_at = Utc::now().to_rfc3339();
+
+    runtime_store::persist_json(&state.provisioning_dir, &node.id, &*node);
+    HttpResponse::Ok().json(node.public_view())
+}
+
+pub async fn delete_planned_node(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    node_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    if state
+        .planned_nodes
+        .write()
+        .await
+        .remove(node_id.as_str())
+        .is_none()
+    {
+        return node_not_found();
+    }
+    runtime_store::delete_json(&state.provisioning_dir, node_id.as_str());
+    HttpResponse::NoContent().finish()
+}
+
+/// POST /provisioning/nodes/{id}/token — issue a one-time download token,
+/// replacing any earlier one. The plain token is only returned here.
+pub async fn issue_node_token(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    node_id: web::Path<String>,
+    body: Option<web::Json<TokenPayload>>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    let ttl_s = body
+        .and_then(|body| body.ttl_s)
+        .filter(|ttl| *ttl > 0)
+        .unwrap_or(provisioning::DEFAULT_TOKEN_TTL_S);
+
+    let mut nodes = state.planned_nodes.write().await;
+    let Some(node) = nodes.get_mut(node_id.as_str()) else {
+        return node_not_found();
+    };
+    let token = node.issue_token(ttl_s, Utc::now());
+    runtime_store::persist_json(&state.provisioning_dir, &node.id, &*node);
+
+    info!("Issued provisioning token for node {}", node.id);
+    HttpResponse::Created().json(serde_json::json!({
+        "node_id": node.id,
+        "token": token,
+        "expires_at": node.token_expires_at,
+        "download_path": format!("/provisioning/download/{}", token),
+        "activate_path": format!("/provisioning/activate/{}", token),
+    }))
+}
+
+/// GET /provisioning/download/{token} — the node's config bundle. Needs no other
+/// credentials; the token works for a single download.
+pub async fn download_node_config(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    token: web::Path<String>,
+) -> impl Responder {
+    let fetched_by = req
+        .connection_info()
+        .realip_remote_addr()
+        .unwrap_or("unknown")
+        .to_string();
+
+    let mut nodes = state.planned_nodes.write().await;
+    let Some(node) = nodes.values_mut().find(|node| node.holds_token(&token)) else {
+        return token_rejected(&TokenError::Unknown);
+    };
+    if let Err(e) = node.redeem_download(&fetched_by, Utc::now()) {
+        return token_rejected(&e);
+    }
+    runtime_store::persist_json(&state.provisioning_dir, &node.id, &*node);
+
+    info!("Node {} fetched its config from {}", node.id, fetched_by);
+    HttpResponse::Ok().json(serde_json::json!({
+        "node_id": node.id,
+        "name": node.name,
+        "config": node.config,
+        "activate_path": format!("/provisioning/activate/{}", token.as_str()),
+    }))
+}
+
+/// POST /provisioning/activate/{token} — called by the node once it runs with the
+/// downloaded config.
+pub async fn activate_node(
+    state: web::Data<AppState>,
+    token: web::Path<String>,
+    body: Option<web::Json<ActivationPayload>>,
+) -> impl Responder {
+    let zenoh_id = body.and_then(|body| body.into_inner().zenoh_id);
+    let mut nodes = state.planned_nodes.write().await;
+    let Some(node) = nodes.values_mut().find(|node| node.holds_token(&token)) else {
+        return token_rejected(&TokenError::Unknown);
+    };
+    if let Err(e) = node.activate(zenoh_id, Utc::now()) {
+        return token_rejected(&e);
+    }
+    runtime_store::persist_json(&state.provisioning_dir, &node.id, &*node);
+
+    info!("Node {} activated", node.id);
+    HttpResponse::Ok().json(node.public_view())
+}
+
+fn node_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({"error": "Planned node not found"}))
+}
+
+fn token_rejected(error: &TokenError) -> HttpResponse {
+    let body = serde_json::json!({"error": error.message()});
+    match error {
+        TokenError::Unknown => HttpResponse::NotFound().json(body),
+        TokenError::Expired | TokenError::AlreadyUsed => HttpResponse::Gone().json(body),
+        TokenError::NotFetched => HttpResponse::Conflict().json(body),
+    }
+}
+
+fn reject_unless_elevated(state: &AppState, req: &HttpRequest) -> Option<HttpResponse> {
+    if tenancy::is_deployment_admin(state, req) {
+        return None;
+    }
+    Some(HttpResponse::Forbidden().json(serde_json::json!({
+        "error": "Node provisioning requires the deployment admin token"
+    })))
+}
@@ -0,0 +1,427 @@
+use actix_web::web;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use shared::mtp::{Recipe, RecipeStep};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::recipe_executor;
+use crate::state::AppState;
+use crate::step_library;
+use crate::task_registry::{self, TaskHandle};
+
+/// Upper bound on runs in one campaign.
+pub const MAX_CAMPAIGN_RUNS: u32 = 500;
+
+/// How often a campaign checks whether its current run has finished.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub const ORIGIN_CAMPAIGN: &str = "campaign";
+
+/// Body of POST /recipes/{id}/campaign.
+#[derive(Debug, Default, Deserialize)]
+pub struct CampaignRequest {
+    /// Number of runs; defaults to the number of variations, or 1.
+    pub count: Option<u32>,
+    /// Parameter values of each run, by `parameter_tag`. They replace the
+    /// values of every step that sets the tag.
+    #[serde(default)]
+    pub variations: Vec<BTreeMap<String, serde_json::Value>>,
+    /// Execution environment of every run; see `environments::select`.
+    pub environment: Option<String>,
+    /// Failed runs after which the remaining runs are skipped (default 1).
+    pub max_failures: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CampaignRun {
+    /// 1-based position in the campaign.
+    pub run: u32,
+    pub parameters: BTreeMap<String, serde_json::Value>,
+    pub execution_id: Option<String>,
+    /// `pending`, `running`, `completed`, `failed`, `aborted` or `skipped`.
+    pub state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Sequential runs of one recipe, each with its own parameter values.
+#[derive(Clone, Debug, Serialize)]
+pub struct Campaign {
+    pub id: String,
+    pub recipe_id: String,
+    pub recipe_name: String,
+    pub environment: Option<String>,
+    pub max_failures: u32,
+    /// `running`, `completed`, `failed` (failure threshold reached) or `aborted`.
+    pub state: String,
+    pub completed: u32,
+    pub failed: u32,
+    pub runs: Vec<CampaignRun>,
+    #[serde(skip)]
+    pub abort_requested: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Parameter values of each run, checked against the tags the steps set.
+pub fn plan(
+    steps: &[RecipeStep],
+    request: &CampaignRequest,
+) -> Result<Vec<BTreeMap<String, serde_json::Value>>, String> {
+    let variations = request.variations.len() as u32;
+    let count = request.count.unwrap_or(variations.max(1));
+    if count == 0 || count > MAX_CAMPAIGN_RUNS {
+        return Err(format!("count must be between 1 and {}", MAX_CAMPAIGN_RUNS));
+    }
+    if variations > 0 && variations != count {
+        return Err(format!(
+            "{} variations given for {} runs; give one per run",
+            variations, count
+        ));
+    }
+    if request.max_failures == Some(0) {
+        return Err("max_failures must be at least 1".to_string());
+    }
+    for tag in request
+        .variations
+        .iter()
+        .flat_map(|variation| variation.keys())
+    {
+        let set = steps
+            .iter()
+            .any(|step| step.parameters.iter().any(|p| &p.parameter_tag == tag));
+        if !set {
+            return Err(format!("No step of the recipe sets parameter '{}'", tag));
+        }
+    }
+    Ok((0..count as usize)
+        .map(|run| request.variations.get(run).cloned().unwrap_or_default())
+        .collect())
+}
+
+/// `steps` with the parameter values of one run.
+pub fn vary(
+    steps: &[RecipeStep],
+    parameters: &BTreeMap<String, serde_json::Value>,
+) -> Vec<RecipeStep> {
+    steps
+        .iter()
+        .cloned()
+        .map(|mut step| {
+            for parameter in &mut step.parameters {
+                if let Some(value) = parameters.get(&parameter.parameter_tag) {
+                    parameter.value = value.clone();
+                }
+            }
+            step
+        })
+        .collect()
+}
+
+/// Records a campaign for `recipe` and runs it in the background.
+pub async fn start(
+    state: web::Data<AppState>,
+    recipe: Recipe,
+    request: CampaignRequest,
+    environment: Option<String>,
+) -> Result<Campaign, String> {
+    let steps = step_library::expand(&recipe, &*state.step_library.read().await)?;
+    let runs = plan(&steps, &request)?;
+    // Checks the environment and topology before anything runs.
+    let bound = recipe_executor::resolve_steps(&state, &recipe, environment.as_deref()).await?;
+    recipe_executor::check_topology(&bound, &*state.topology.read().await)?;
+
+    let now = Utc::now().to_rfc3339();
+    let campaign = Campaign {
+        id: uuid::Uuid::new_v4().to_string(),
+        recipe_id: recipe.id.clone(),
+        recipe_name: recipe.name.clone(),
+        environment,
+        max_failures: request.max_failures.unwrap_or(1),
+        state: "running".to_string(),
+        completed: 0,
+        failed: 0,
+        runs: (1..)
+            .zip(runs)
+            .map(|(run, parameters)| CampaignRun {
+                run,
+                parameters,
+                execution_id: None,
+                state: "pending".to_string(),
+                error: None,
+            })
+            .collect(),
+        abort_requested: false,
+        tenant_id: recipe.tenant_id.clone(),
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    state
+        .recipe_campaigns
+        .write()
+        .await
+        .insert(campaign.id.clone(), campaign.clone());
+
+    // Library steps are expanded once, so every run uses the same sequence.
+    let recipe = Recipe {
+        steps,
+        library_steps: Vec::new(),
+        ..recipe
+    };
+    let campaign_id = campaign.id.clone();
+    let runner_state = state.clone();
+    state.tasks.spawn(
+        format!("recipe-campaign:{}", campaign_id),
+        task_registry::KIND_RECIPE,
+        |task| run(runner_state, campaign_id, recipe, task),
+    );
+    info!(
+        "Campaign {} of recipe {} started with {} runs",
+        campaign.id,
+        campaign.recipe_id,
+        campaign.runs.len()
+    );
+    Ok(campaign)
+}
+
+/// Stops a running campaign: the current run is aborted and the rest skipped.
+pub async fn request_abort(state: &AppState, campaign_id: &str) -> Result<(), String> {
+    let execution_id = {
+        let mut campaigns = state.recipe_campaigns.write().await;
+        let Some(campaign) = campaigns.get_mut(campaign_id) else {
+            return Err(format!("Campaign '{}' not found", campaign_id));
+        };
+        if campaign.state != "running" {
+            return Err(format!("Campaign '{}' is not running", campaign_id));
+        }
+        campaign.abort_requested = true;
+        campaign
+            .runs
+            .iter()
+            .find(|run| run.state == "running")
+            .and_then(|run| run.execution_id.clone())
+    };
+    if let Some(execution_id) = execution_id {
+        // The run may have finished in the meantime; then there is nothing to abort.
+        let _ = recipe_executor::request_abort(&state.recipe_executions, &execution_id).await;
+    }
+    Ok(())
+}
+
+async fn run(state: web::Data<AppState>, campaign_id: String, recipe: Recipe, task: TaskHandle) {
+    let Some(campaign) = state
+        .recipe_campaigns
+        .read()
+        .await
+        .get(&campaign_id)
+        .cloned()
+    else {
+        return;
+    };
+    let base_steps = recipe.steps.clone();
+    for index in 0..campaign.runs.len() {
+        if aborted(&state, &campaign_id).await {
+            finish(&state, &campaign_id, "aborted").await;
+            return;
+        }
+        let parameters = campaign.runs[index].parameters.clone();
+        let variant = Recipe {
+            steps: vary(&base_steps, &parameters),
+            ..recipe.clone()
+        };
+        let started = recipe_executor::start(
+            &state,
+            &variant,
+            ORIGIN_CAMPAIGN,
+            campaign.environment.as_deref(),
+        )
+        .await;
+        let outcome = match started {
+            Ok(execution_id) => {
+                if let Some(exec) = state.recipe_executions.write().await.get_mut(&execution_id) {
+                    exec["campaign_id"] = serde_json::json!(campaign_id);
+                    exec["campaign_run"] = serde_json::json!(index + 1);
+                }
+                update_run(&state, &campaign_id, index, |run| {
+                    run.execution_id = Some(execution_id.clone());
+                    run.state = "running".to_string();
+                })
+                .await;
+                wait_for_execution(&state, &execution_id, &task).await
+            }
+            Err(e) => {
+                warn!(
+                    "Campaign {} run {} did not start: {}",
+                    campaign_id,
+                    index + 1,
+                    e
+                );
+                update_run(&state, &campaign_id, index, |run| run.error = Some(e)).await;
+                "failed".to_string()
+            }
+        };
+        update_run(&state, &campaign_id, index, |run| {
+            run.state = outcome.clone()
+        })
+        .await;
+
+        let threshold_reached = {
+            let mut campaigns = state.recipe_campaigns.write().await;
+            let Some(campaign) = campaigns.get_mut(&campaign_id) else {
+                return;
+            };
+            if outcome == "completed" {
+                campaign.completed += 1;
+            } else {
+                campaign.failed += 1;
+            }
+            campaign.failed >= campaign.max_failures
+        };
+        if aborted(&state, &campaign_id).await {
+            finish(&state, &campaign_id, "aborted").await;
+            return;
+        }
+        if threshold_reached {
+            finish(&state, &campaign_id, "failed").await;
+            return;
+        }
+    }
+    finish(&state, &campaign_id, "completed").await;
+}
+
+/// Terminal state of an execution, polled until it leaves `running`.
+async fn wait_for_execution(state: &AppState, execution_id: &str, task: &TaskHandle) -> String {
+    loop {
+        let current = state
+            .recipe_executions
+            .read()
+            .await
+            .get(execution_id)
+            .and_then(|exec| {
+                exec.get("state")
+                    .and_then(|s| s.as_str())
+                    .map(str::to_string)
+            });
+        match current.as_deref() {
+            Some("running") => {}
+            Some(terminal) => return terminal.to_string(),
+            None => return "failed".to_string(),
+        }
+        task.beat();
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn aborted(state: &AppState, campaign_id: &str) -> bool {
+    state
+        .recipe_campaigns
+        .read()
+        .await
+        .get(campaign_id)
+        .is_none_or(|campaign| campaign.abort_requested)
+}
+
+async fn update_run(
+    state: &AppState,
+    campaign_id: &str,
+    index: usize,
+    change: impl FnOnce(&mut CampaignRun),
+) {
+    let mut campaigns = state.recipe_campaigns.write().await;
+    if let Some(campaign) = campaigns.get_mut(campaign_id) {
+        if let Some(run) = campaign.runs.get_mut(index) {
+            change(run);
+        }
+        campaign.updated_at = Utc::now().to_rfc3339();
+    }
+}
+
+/// Records the campaign's final state; runs that never started are skipped.
+async fn finish(state: &AppState, campaign_id: &str, final_state: &str) {
+    let mut campaigns = state.recipe_campaigns.write().await;
+    let Some(campaign) = campaigns.get_mut(campaign_id) else {
+        return;
+    };
+    for run in campaign
+        .runs
+        .iter_mut()
+        .filter(|run| run.state == "pending")
+    {
+        run.state = "skipped".to_string();
+    }
+    campaign.state = final_state.to_string();
+    campaign.updated_at = Utc::now().to_rfc3339();
+    info!(
+        "Campaign {} {}: {} completed, {} failed",
+        campaign_id, final_state, campaign.completed, campaign.failed
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steps() -> Vec<RecipeStep> {
+        serde_json::from_value(serde_json::json!([
+            {
+                "order": 1, "pea_id": "heater", "service_tag": "Heat",
+                "command": "Start", "procedure_id": 1,
+                "parameters": [{"parameter_tag": "SP_Temp", "value": 60.0}],
+                "wait_for_state": null, "timeout_ms": null
+            },
+            {
+                "order": 2, "pea_id": "mixer", "service_tag": "Mix",
+                "command": "Start", "procedure_id": null,
+                "parameters": [{"parameter_tag": "Speed", "value": 100}],
+                "wait_for_state": null, "timeout_ms": null
+            }
+        ]))
+        .unwrap()
+    }
+
+    fn variation(tag: &str, value: serde_json::Value) -> BTreeMap<String, serde_json::Value> {
+        BTreeMap::from([(tag.to_string(), value)])
+    }
+
+    #[test]
+    fn runs_follow_count_and_variations() {
+        let repeated = CampaignRequest {
+            count: Some(3),
+            ..CampaignRequest::default()
+        };
+        assert_eq!(plan(&steps(), &repeated).unwrap().len(), 3);
+
+        let varied = CampaignRequest {
+            variations: vec![
+                variation("SP_Temp", serde_json::json!(65.0)),
+                variation("SP_Temp", serde_json::json!(70.0)),
+            ],
+            ..CampaignRequest::default()
+        };
+        let runs = plan(&steps(), &varied).unwrap();
+        assert_eq!(runs.len(), 2);
+        let second = vary(&steps(), &runs[1]);
+        assert_eq!(second[0].parameters[0].value, serde_json::json!(70.0));
+        assert_eq!(second[1].parameters[0].value, serde_json::json!(100));
+
+        let mismatched = CampaignRequest {
+            count: Some(3),
+            ..varied
+        };
+        assert!(plan(&steps(), &mismatched).is_err());
+        let unknown = CampaignRequest {
+            variations: vec![variation("Pressure", serde_json::json!(2))],
+            ..CampaignRequest::default()
+        };
+        assert!(plan(&steps(), &unknown).unwrap_err().contains("'Pressure'"));
+        let too_many = CampaignRequest {
+            count: Some(MAX_CAMPAIGN_RUNS + 1),
+            ..CampaignRequest::default()
+        };
+        assert!(plan(&steps(), &too_many).is_err());
+    }
+}
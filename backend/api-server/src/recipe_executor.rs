@@ -0,0 +1,640 @@
+use crate::approval_handlers;
+use crate::approval_service::ApprovalStatus;
+use crate::environments;
+use crate::service_command::{self, CommandOutcome, CommandRefusal, CommandSource};
+use crate::state::{AppState, PolTopology};
+use crate::step_library;
+use crate::task_registry;
+use crate::webhook_service;
+use actix_web::web;
+use serde::Deserialize;
+use shared::mtp::{topics, Recipe, RecipeStep, ServiceCommand, ServiceState};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+pub const ORIGIN_API: &str = "api";
+pub const ORIGIN_BUS: &str = "bus";
+pub const ORIGIN_AUTOMATION: &str = "automation";
+pub const ORIGIN_TRIGGER: &str = "trigger";
+
+/// Source recorded for the commands and interlock violations of recipe steps.
+const COMMAND_ORIGIN: &str = "recipe_executor";
+
+type Executions = Arc<RwLock<HashMap<String, serde_json::Value>>>;
+
+/// Command accepted on `POL_RECIPES_COMMAND`.
+#[derive(Debug, Deserialize)]
+pub struct RecipeBusCommand {
+    /// `execute` or `abort`.
+    pub action: String,
+    pub recipe_id: Option<String>,
+    pub execution_id: Option<String>,
+    /// Execution environment for `execute`; see `environments::select`.
+    pub environment: Option<String>,
+    /// Echoed in the acknowledgement so callers can correlate it.
+    pub request_id: Option<String>,
+}
+
+/// Runs an execution in the background, detached from the request that started it.
+#[derive(Clone)]
+struct Executor {
+    state: web::Data<AppState>,
+}
+
+/// The steps a recipe runs: library steps expanded and, in an execution
+/// environment, roles bound to that environment's PEAs.
+pub async fn resolve_steps(
+    state: &AppState,
+    recipe: &Recipe,
+    environment: Option<&str>,
+) -> Result<Vec<RecipeStep>, String> {
+    let steps = step_library::expand(recipe, &*state.step_library.read().await)?;
+    match environment {
+        Some(environment_id) => environments::bind_steps(state, recipe, steps, environment_id).await,
+        None => Ok(steps),
+    }
+}
+
+/// Validates the recipe against the topology, records a new execution and runs it
+/// in the background. Returns the execution id, or why the recipe cannot run.
+pub async fn start(
+    state: &web::Data<AppState>,
+    recipe: &Recipe,
+    origin: &str,
+    environment: Option<&str>,
+) -> Result<String, String> {
+    let steps = resolve_steps(state, recipe, environment).await?;
+    check_topology(&steps, &*state.topology.read().await)?;
+
+    let execution_id = Uuid::new_v4().to_string();
+    let total_steps = steps.len();
+    let record = serde_json::json!({
+        "execution_id": execution_id,
+        "recipe_id": recipe.id,
+        "recipe_name": recipe.name,
+        "origin": origin,
+        "environment": environment,
+        "current_step": 0,
+        "total_steps": total_steps,
+        "step_statuses": vec!["pending"; total_steps],
+        "step_timings": steps.iter().map(|step| serde_json::json!({
+            "pea_id": step.pea_id,
+            "service_tag": step.service_tag,
+            "started_at": null,
+            "finished_at": null,
+        })).collect::<Vec<_>>(),
+        "state": "running",
+        "started_at": chrono::Utc::now().to_rfc3339(),
+        "updated_at": chrono::Utc::now().to_rfc3339(),
+    });
+    state
+        .recipe_executions
+        .write()
+        .await
+        .insert(execution_id.clone(), record.clone());
+
+    let executor = Executor {
+        state: state.clone(),
+    };
+    executor.publish_status(&record).await;
+    state.tasks.spawn(
+        format!("recipe-execution:{}", execution_id),
+        task_registry::KIND_RECIPE,
+        |_| executor.run(execution_id.clone(), steps),
+    );
+    info!(
+        "Recipe {} started as execution {} ({})",
+        recipe.id, execution_id, origin
+    );
+    Ok(execution_id)
+}
+
+/// Asks a running execution to stop before its next step. A step still waiting
+/// for its `wait_for_state` is sent an Abort command by the executor; steps
+/// without one are complete once their command is sent and are not aborted.
+pub async fn request_abort(executions: &Executions, execution_id: &str) -> Result<(), String> {
+    let mut execs = executions.write().await;
+    let Some(exec) = execs.get_mut(execution_id) else {
+        return Err(format!("Execution '{}' not found", execution_id));
+    };
+    if exec.get("state").and_then(|s| s.as_str()) != Some("running") {
+        return Err(format!("Execution '{}' is not running", execution_id));
+    }
+    exec["abort_requested"] = serde_json::json!(true);
+    Ok(())
+}
+
+/// Each cross-PEA transition must follow an edge of the orchestration topology.
+pub fn check_topology(steps: &[RecipeStep], topology: &PolTopology) -> Result<(), String> {
+    for pair in steps.windows(2) {
+        let prev = &pair[0];
+        let next = &pair[1];
+        if prev.pea_id == next.pea_id {
+            continue;
+        }
+        if topology.edges.is_empty() {
+            return Err(
+                "Topology is empty. Define PEA connections before executing cross-PEA recipes."
+                    .to_string(),
+            );
+        }
+        let allowed = topology
+            .edges
+            .iter()
+            .any(|e| e.from == prev.pea_id && e.to == next.pea_id);
+        if !allowed {
+            return Err(format!(
+                "Topology violation: no connection from '{}' to '{}' for recipe step transition.",
+                prev.pea_id, next.pea_id
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Subscribes to `POL_RECIPES_COMMAND` so external orchestrators can execute and
+/// abort recipes; progress and acknowledgements go out on `POL_RECIPES_STATUS`.
+pub async fn serve_bus_commands(state: web::Data<AppState>) {
+    let subscriber = match state
+        .zenoh_session
+        .declare_subscriber(topics::POL_RECIPES_COMMAND)
+        .await
+    {
+        Ok(subscriber) => subscriber,
+        Err(e) => {
+            error!(
+                "Failed to subscribe to {}: {}",
+                topics::POL_RECIPES_COMMAND,
+                e
+            );
+            return;
+        }
+    };
+
+    while let Ok(sample) = subscriber.recv_async().await {
+        let payload = sample.payload().to_bytes();
+        let ack = match serde_json::from_slice::<RecipeBusCommand>(&payload) {
+            Ok(command) => handle_bus_command(&state, &command).await,
+            Err(e) => serde_json::json!({
+                "type": "ack",
+                "accepted": false,
+                "error": format!("Invalid recipe command: {}", e),
+            }),
+        };
+        if let Err(e) = state
+            .zenoh_session
+            .put(topics::POL_RECIPES_STATUS, ack.to_string())
+            .await
+        {
+            warn!("Failed to acknowledge recipe command: {}", e);
+        }
+    }
+}
+
+async fn handle_bus_command(
+    state: &web::Data<AppState>,
+    command: &RecipeBusCommand,
+) -> serde_json::Value {
+    let result = match command.action.as_str() {
+        "execute" => match command.recipe_id.as_deref() {
+            Some(recipe_id) => {
+                let recipe = state.recipes.read().await.get(recipe_id).cloned();
+                match recipe {
+                    Some(recipe) => {
+                        let environment =
+                            environments::select(state, command.environment.as_deref());
+                        start(state, &recipe, ORIGIN_BUS, environment).await
+                    }
+                    None => Err(format!("Recipe '{}' not found", recipe_id)),
+                }
+            }
+            None => Err("execute requires recipe_id".to_string()),
+        },
+        "abort" => match command.execution_id.as_deref() {
+            Some(execution_id) => request_abort(&state.recipe_executions, execution_id)
+                .await
+                .map(|_| execution_id.to_string()),
+            None => Err("abort requires execution_id".to_string()),
+        },
+        other => Err(format!("Unknown recipe action '{}'", other)),
+    };
+
+    let mut ack = serde_json::json!({
+        "type": "ack",
+        "action": command.action,
+        "request_id": command.request_id,
+        "recipe_id": command.recipe_id,
+        "accepted": result.is_ok(),
+    });
+    match result {
+        Ok(execution_id) => ack["execution_id"] = serde_json::json!(execution_id),
+        Err(e) => {
+            warn!("Rejected recipe command from bus: {}", e);
+            ack["error"] = serde_json::json!(e);
+        }
+    }
+    ack
+}
+
+impl Executor {
+    async fn run(self, execution_id: String, steps: Vec<RecipeStep>) {
+        let total_steps = steps.len();
+        let mut step_statuses = vec!["pending".to_string(); total_steps];
+
+        for (idx, step) in steps.iter().enumerate() {
+            if self.abort_requested(&execution_id).await {
+                self.finish(&execution_id, idx, total_steps, &step_statuses, "aborted")
+                    .await;
+                return;
+            }
+            step_statuses[idx] = "executing".to_string();
+            self.update(
+                &execution_id,
+                idx + 1,
+                total_steps,
+                &step_statuses,
+                "running",
+            )
+            .await;
+
+            let source = CommandSource {
+                actor_id: None,
+                origin: COMMAND_ORIGIN,
+                interlock_override: None,
+            };
+            let sent = service_command::send(
+                &self.state,
+                source,
+                &step.pea_id,
+                &step.service_tag,
+                step.command,
+                step.procedure_id,
+                Some(&step.parameters),
+            )
+            .await;
+            let refused = match sent {
+                Ok(CommandOutcome::Sent) => None,
+                Ok(CommandOutcome::PendingApproval(approval)) => {
+                    step_statuses[idx] = "awaiting_approval".to_string();
+                    self.update(
+                        &execution_id,
+                        idx + 1,
+                        total_steps,
+                        &step_statuses,
+                        "running",
+                    )
+                    .await;
+                    match self.wait_for_approval(&execution_id, &approval.id).await {
+                        WaitOutcome::Reached => None,
+                        WaitOutcome::TimedOut => Some("rejected"),
+                        WaitOutcome::Aborted => {
+                            approval_handlers::withdraw(
+                                &self.state,
+                                &approval.id,
+                                COMMAND_ORIGIN,
+                                "Recipe execution aborted",
+                            )
+                            .await;
+                            step_statuses[idx] = "aborted".to_string();
+                            self.finish(
+                                &execution_id,
+                                idx + 1,
+                                total_steps,
+                                &step_statuses,
+                                "aborted",
+                            )
+                            .await;
+                            return;
+                        }
+                    }
+                }
+                Err(refusal) => {
+                    error!(
+                        "Recipe step {}/{} not sent: {}",
+                        step.pea_id,
+                        step.service_tag,
+                        refusal.message()
+                    );
+                    Some(match refusal {
+                        CommandRefusal::NotAllowed { .. } => "not_allowed",
+                        CommandRefusal::Interlocked(_) => "interlocked",
+                        _ => "failed",
+                    })
+                }
+            };
+            if let Some(status) = refused {
+                step_statuses[idx] = status.to_string();
+                self.finish(
+                    &execution_id,
+                    idx + 1,
+                    total_steps,
+                    &step_statuses,
+                    "failed",
+                )
+                .await;
+                return;
+            }
+
+            if let Some(wait_state) = step.wait_for_state {
+                let timeout_ms = step.timeout_ms.unwrap_or(30000);
+                match self
+                    .wait_for_state(&execution_id, step, wait_state, timeout_ms)
+                    .await
+                {
+                    WaitOutcome::Reached => {}
+                    WaitOutcome::TimedOut => {
+                        step_statuses[idx] = "failed".to_string();
+                        self.finish(
+                            &execution_id,
+                            idx + 1,
+                            total_steps,
+                            &step_statuses,
+                            "failed",
+                        )
+                        .await;
+                        return;
+                    }
+                    WaitOutcome::Aborted => {
+                        self.abort_step(step).await;
+                        step_statuses[idx] = "aborted".to_string();
+                        self.finish(
+                            &execution_id,
+                            idx + 1,
+                            total_steps,
+                            &step_statuses,
+                            "aborted",
+                        )
+                        .await;
+                        return;
+                    }
+                }
+            }
+
+            step_statuses[idx] = "completed".to_string();
+            self.update(
+                &execution_id,
+                idx + 1,
+                total_steps,
+                &step_statuses,
+                "running",
+            )
+            .await;
+        }
+
+        self.finish(
+            &execution_id,
+            total_steps,
+            total_steps,
+            &step_statuses,
+            "completed",
+        )
+        .await;
+    }
+
+    async fn wait_for_state(
+        &self,
+        execution_id: &str,
+        step: &RecipeStep,
+        wait_state: ServiceState,
+        timeout_ms: u64,
+    ) -> WaitOutcome {
+        let deadline = std::time::Instant::now() + Duration::from_millis(timeout_ms);
+        let status_key = topics::pea_status(&step.pea_id);
+
+        while std::time::Instant::now() < deadline {
+            if self.abort_requested(execution_id).await {
+                return WaitOutcome::Aborted;
+            }
+            {
+                let ts = self.state.timeseries.read().await;
+                let reached = ts
+                    .data
+                    .get(&status_key)
+                    .and_then(|buf| buf.back())
+                    .and_then(|last| last.value.get("services"))
+                    .and_then(|v| v.as_array())
+                    .is_some_and(|services| {
+                        services.iter().any(|svc| {
+                            svc.get("tag").and_then(|t| t.as_str())
+                                == Some(step.service_tag.as_str())
+                                && svc.get("state").and_then(|s| s.as_str())
+                                    == Some(service_state_name(wait_state))
+                        })
+                    });
+                if reached {
+                    return WaitOutcome::Reached;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        WaitOutcome::TimedOut
+    }
+
+    /// Waits until a step's command held for approval is decided: `Reached`
+    /// once approved and sent, `TimedOut` when rejected or expired.
+    async fn wait_for_approval(&self, execution_id: &str, approval_id: &str) -> WaitOutcome {
+        loop {
+            if self.abort_requested(execution_id).await {
+                return WaitOutcome::Aborted;
+            }
+            let status = self
+                .state
+                .approvals
+                .read()
+                .await
+                .get(approval_id)
+                .map(|approval| {
+                    let mut approval = approval.clone();
+                    approval.lapse(chrono::Utc::now());
+                    approval.status
+                });
+            match status {
+                Some(ApprovalStatus::Pending) => {}
+                Some(ApprovalStatus::Approved) => return WaitOutcome::Reached,
+                _ => return WaitOutcome::TimedOut,
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    /// Sends Abort to the service an aborted execution was waiting on, through
+    /// the same checks as any other command.
+    async fn abort_step(&self, step: &RecipeStep) {
+        let source = CommandSource {
+            actor_id: None,
+            origin: COMMAND_ORIGIN,
+            interlock_override: None,
+        };
+        match service_command::send(
+            &self.state,
+            source,
+            &step.pea_id,
+            &step.service_tag,
+            ServiceCommand::Abort,
+            None,
+            None,
+        )
+        .await
+        {
+            Ok(CommandOutcome::Sent) => {}
+            Ok(CommandOutcome::PendingApproval(approval)) => info!(
+                "Abort of service {}/{} awaits approval {}",
+                step.pea_id, step.service_tag, approval.id
+            ),
+            Err(refusal) => error!(
+                "Failed to abort service {}/{}: {}",
+                step.pea_id,
+                step.service_tag,
+                refusal.message()
+            ),
+        }
+    }
+
+    async fn abort_requested(&self, execution_id: &str) -> bool {
+        self.state
+            .recipe_executions
+            .read()
+            .await
+            .get(execution_id)
+            .and_then(|exec| exec.get("abort_requested"))
+            .and_then(|flag| flag.as_bool())
+            .unwrap_or(false)
+    }
+
+    async fn update(
+        &self,
+        execution_id: &str,
+        current_step: usize,
+        total_steps: usize,
+        step_statuses: &[String],
+        state: &str,
+    ) -> serde_json::Value {
+        let snapshot = {
+            let mut execs = self.state.recipe_executions.write().await;
+            let mut base = execs
+                .get(execution_id)
+                .cloned()
+                .unwrap_or_else(|| serde_json::json!({}));
+            base["execution_id"] = serde_json::json!(execution_id);
+            base["current_step"] = serde_json::json!(current_step);
+            base["total_steps"] = serde_json::json!(total_steps);
+            base["step_statuses"] = serde_json::json!(step_statuses);
+            base["state"] = serde_json::json!(state);
+            let now = chrono::Utc::now().to_rfc3339();
+            // Steps are timed from when they start executing until they settle.
+            if let Some(timings) = base["step_timings"].as_array_mut() {
+                for (timing, status) in timings.iter_mut().zip(step_statuses) {
+                    match status.as_str() {
+                        "pending" => {}
+                        "executing" if timing["started_at"].is_null() => {
+                            timing["started_at"] = serde_json::json!(now);
+                        }
+                        "executing" | "awaiting_approval" => {}
+                        _ if timing["finished_at"].is_null() && !timing["started_at"].is_null() => {
+                            timing["finished_at"] = serde_json::json!(now);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            base["updated_at"] = serde_json::json!(now);
+            execs.insert(execution_id.to_string(), base.clone());
+            base
+        };
+        self.publish_status(&snapshot).await;
+        snapshot
+    }
+
+    /// Records the terminal execution state and notifies webhook subscribers.
+    async fn finish(
+        &self,
+        execution_id: &str,
+        current_step: usize,
+        total_steps: usize,
+        step_statuses: &[String],
+        state: &str,
+    ) {
+        let snapshot = self
+            .update(
+                execution_id,
+                current_step,
+                total_steps,
+                step_statuses,
+                state,
+            )
+            .await;
+        let event = match state {
+            "completed" => webhook_service::EVENT_RECIPE_COMPLETED,
+            "failed" => webhook_service::EVENT_RECIPE_FAILED,
+            _ => return,
+        };
+        self.state.webhooks.emit(event, snapshot).await;
+    }
+
+    async fn publish_status(&self, execution: &serde_json::Value) {
+        let mut status = execution.clone();
+        status["type"] = serde_json::json!("execution");
+        if let Err(e) = self
+            .state
+            .zenoh_session
+            .put(topics::POL_RECIPES_STATUS, status.to_string())
+            .await
+        {
+            warn!("Failed to publish recipe status: {}", e);
+        }
+    }
+}
+
+enum WaitOutcome {
+    Reached,
+    TimedOut,
+    Aborted,
+}
+
+fn service_state_name(state: ServiceState) -> &'static str {
+    match state {
+        ServiceState::Idle => "Idle",
+        ServiceState::Starting => "Starting",
+        ServiceState::Execute => "Execute",
+        ServiceState::Completing => "Completing",
+        ServiceState::Completed => "Completed",
+        ServiceState::Pausing => "Pausing",
+        ServiceState::Paused => "Paused",
+        ServiceState::Resuming => "Resuming",
+        ServiceState::Holding => "Holding",
+        ServiceState::Held => "Held",
+        ServiceState::Unholding => "Unholding",
+        ServiceState::Stopping => "Stopping",
+        ServiceState::Stopped => "Stopped",
+        ServiceState::Aborting => "Aborting",
+        ServiceState::Aborted => "Aborted",
+        ServiceState::Resetting => "Resetting",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn abort_is_only_accepted_for_running_executions() {
+        let executions: Executions = Arc::new(RwLock::new(HashMap::new()));
+        executions.write().await.insert(
+            "exec-1".to_string(),
+            serde_json::json!({"execution_id": "exec-1", "state": "running"}),
+        );
+        executions.write().await.insert(
+            "exec-2".to_string(),
+            serde_json::json!({"execution_id": "exec-2", "state": "completed"}),
+        );
+
+        assert!(request_abort(&executions, "exec-1").await.is_ok());
+        assert_eq!(executions.read().await["exec-1"]["abort_requested"], true);
+        assert!(request_abort(&executions, "exec-2").await.is_err());
+        assert!(request_abort(&executions, "missing").await.is_err());
+    }
+}
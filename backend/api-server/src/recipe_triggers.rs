@@ -0,0 +1,374 @@
+use actix_web::web;
+use shared::mtp::{topics, PeaConfig, PeaInstanceStatus, Recipe, RecipeTrigger, ServiceState};
+use std::collections::{HashMap, HashSet, VecDeque};
+use tracing::{error, info, warn};
+
+use crate::environments;
+use crate::recipe_executor;
+use crate::state::AppState;
+use crate::task_registry::TaskHandle;
+
+/// Completed executions remembered so a repeated status message fires once.
+const SEEN_EXECUTIONS: usize = 1000;
+
+/// Checks the triggers of `recipe` against the other recipes and PEAs: they
+/// must name recipes and PEAs of the recipe's tenant, and completion
+/// triggers must not chain back to the recipe.
+pub fn validate(
+    recipe: &Recipe,
+    recipes: &HashMap<String, Recipe>,
+    configs: &HashMap<String, PeaConfig>,
+) -> Result<(), String> {
+    for trigger in &recipe.triggers {
+        match trigger {
+            RecipeTrigger::RecipeCompleted { recipe_id } => {
+                let known = recipe_id == &recipe.id
+                    || recipes
+                        .get(recipe_id)
+                        .is_some_and(|other| other.tenant_id == recipe.tenant_id);
+                if !known {
+                    return Err(format!("Trigger names unknown recipe '{}'", recipe_id));
+                }
+            }
+            RecipeTrigger::ServiceState {
+                pea_id,
+                service_tag,
+                ..
+            } => {
+                let Some(config) = configs
+                    .get(pea_id)
+                    .filter(|config| config.tenant_id == recipe.tenant_id)
+                else {
+                    return Err(format!("Trigger names unknown PEA '{}'", pea_id));
+                };
+                if let Some(tag) = service_tag {
+                    if !config.services.iter().any(|s| &s.tag == tag) {
+                        return Err(format!("PEA '{}' has no service '{}'", pea_id, tag));
+                    }
+                }
+            }
+        }
+    }
+
+    // Follow "completion of X starts Y" from the recipe; reaching it again is a loop.
+    let mut recipes = recipes.clone();
+    recipes.insert(recipe.id.clone(), recipe.clone());
+    let mut pending = vec![recipe.id.as_str()];
+    let mut visited = HashSet::new();
+    while let Some(current) = pending.pop() {
+        for next in started_by_completion(&recipes, current) {
+            if next.id == recipe.id {
+                return Err(format!(
+                    "Completion triggers of '{}' would start it again",
+                    recipe.id
+                ));
+            }
+            if visited.insert(next.id.as_str()) {
+                pending.push(next.id.as_str());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Recipes started when an execution of `recipe_id` completes.
+fn started_by_completion<'a>(
+    recipes: &'a HashMap<String, Recipe>,
+    recipe_id: &str,
+) -> Vec<&'a Recipe> {
+    recipes
+        .values()
+        .filter(|recipe| {
+            recipe.triggers.iter().any(|trigger| {
+                matches!(trigger, RecipeTrigger::RecipeCompleted { recipe_id: id } if id == recipe_id)
+            })
+        })
+        .collect()
+}
+
+/// Recipes started when a service of `pea_id` enters `state`.
+fn started_by_state<'a>(
+    recipes: &'a HashMap<String, Recipe>,
+    pea_id: &str,
+    service: &str,
+    entered: ServiceState,
+) -> Vec<&'a Recipe> {
+    recipes
+        .values()
+        .filter(|recipe| {
+            recipe.triggers.iter().any(|trigger| match trigger {
+                RecipeTrigger::ServiceState {
+                    pea_id: id,
+                    service_tag,
+                    state,
+                } => {
+                    id == pea_id
+                        && *state == entered
+                        && service_tag.as_deref().is_none_or(|tag| tag == service)
+                }
+                RecipeTrigger::RecipeCompleted { .. } => false,
+            })
+        })
+        .collect()
+}
+
+/// Last service states seen per PEA, to tell when a service enters a state.
+#[derive(Default)]
+pub struct ServiceTransitions {
+    states: HashMap<String, HashMap<String, ServiceState>>,
+}
+
+impl ServiceTransitions {
+    /// Services of the PEA whose state changed since its previous status. The
+    /// first status of a PEA only sets the baseline, so a restart does not
+    /// replay states the services were already in.
+    pub fn entered(&mut self, status: &PeaInstanceStatus) -> Vec<(String, ServiceState)> {
+        let current: HashMap<String, ServiceState> = status
+            .services
+            .iter()
+            .map(|service| (service.tag.clone(), service.state))
+            .collect();
+        let Some(previous) = self.states.insert(status.pea_id.clone(), current.clone()) else {
+            return Vec::new();
+        };
+        let mut entered: Vec<(String, ServiceState)> = current
+            .into_iter()
+            .filter(|(tag, state)| previous.get(tag).is_some_and(|before| before != state))
+            .collect();
+        entered.sort_by(|a, b| a.0.cmp(&b.0));
+        entered
+    }
+}
+
+/// Starts recipes whose triggers fire, following recipe executions on
+/// `POL_RECIPES_STATUS` and service states on the PEA status topics. Only
+/// the leader acts, so a chain runs once across instances.
+pub async fn watch(state: web::Data<AppState>, task: TaskHandle) {
+    let session = state.zenoh_session.clone();
+    let (executions, statuses) = match (
+        session.declare_subscriber(topics::POL_RECIPES_STATUS).await,
+        session
+            .declare_subscriber(topics::PEA_STATUS_WILDCARD)
+            .await,
+    ) {
+        (Ok(executions), Ok(statuses)) => (executions, statuses),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to subscribe for recipe triggers: {}", e);
+            task.fail(format!("Failed to subscribe: {}", e));
+            return;
+        }
+    };
+    info!("Recipe triggers: watching recipe executions and PEA status");
+
+    let mut transitions = ServiceTransitions::default();
+    let mut completed: VecDeque<String> = VecDeque::new();
+    loop {
+        tokio::select! {
+            sample = executions.recv_async() => {
+                let Ok(sample) = sample else { break };
+                task.beat();
+                let Ok(message) = serde_json::from_slice::<serde_json::Value>(
+                    &sample.payload().to_bytes(),
+                ) else {
+                    continue;
+                };
+                if message["type"] != "execution" || message["state"] != "completed" {
+                    continue;
+                }
+                let (Some(execution_id), Some(recipe_id)) = (
+                    message["execution_id"].as_str(),
+                    message["recipe_id"].as_str(),
+                ) else {
+                    continue;
+                };
+                if completed.iter().any(|id| id == execution_id) {
+                    continue;
+                }
+                completed.push_back(execution_id.to_string());
+                if completed.len() > SEEN_EXECUTIONS {
+                    completed.pop_front();
+                }
+                let recipes = state.recipes.read().await;
+                let Some(source) = recipes.get(recipe_id) else {
+                    continue;
+                };
+                let targets: Vec<Recipe> = started_by_completion(&recipes, recipe_id)
+                    .into_iter()
+                    .filter(|recipe| recipe.tenant_id == source.tenant_id)
+                    .cloned()
+                    .collect();
+                drop(recipes);
+                let cause = format!("recipe {} completed (execution {})", recipe_id, execution_id);
+                for recipe in targets {
+                    fire(&state, &recipe, &cause).await;
+                }
+            }
+            sample = statuses.recv_async() => {
+                let Ok(sample) = sample else { break };
+                task.beat();
+                let Ok(status) = serde_json::from_slice::<PeaInstanceStatus>(
+                    &sample.payload().to_bytes(),
+                ) else {
+                    continue;
+                };
+                for (service, entered) in transitions.entered(&status) {
+                    let targets: Vec<Recipe> = {
+                        let recipes = state.recipes.read().await;
+                        let owner = state
+                            .pea_configs
+                            .read()
+                            .await
+                            .get(&status.pea_id)
+                            .map(|config| config.tenant_id.clone());
+                        started_by_state(&recipes, &status.pea_id, &service, entered)
+                            .into_iter()
+                            .filter(|recipe| owner.as_ref() == Some(&recipe.tenant_id))
+                            .cloned()
+                            .collect()
+                    };
+                    let cause = format!("{}/{} entered {:?}", status.pea_id, service, entered);
+                    for recipe in targets {
+                        fire(&state, &recipe, &cause).await;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Starts a triggered recipe unless an execution of it is still running.
+async fn fire(state: &web::Data<AppState>, recipe: &Recipe, cause: &str) {
+    if !state.leadership.is_leader() {
+        return;
+    }
+    let running = state
+        .recipe_executions
+        .read()
+        .await
+        .values()
+        .any(|exec| exec["recipe_id"] == recipe.id.as_str() && exec["state"] == "running");
+    if running {
+        warn!(
+            "Recipe {} not triggered by {}: an execution is still running",
+            recipe.id, cause
+        );
+        return;
+    }
+    let environment = environments::select(state, None);
+    match recipe_executor::start(state, recipe, recipe_executor::ORIGIN_TRIGGER, environment).await
+    {
+        Ok(execution_id) => {
+            if let Some(exec) = state.recipe_executions.write().await.get_mut(&execution_id) {
+                exec["triggered_by"] = serde_json::json!(cause);
+            }
+            info!(
+                "Recipe {} triggered by {} as execution {}",
+                recipe.id, cause, execution_id
+            );
+        }
+        Err(e) => warn!(
+            "Recipe {} triggered by {} did not start: {}",
+            recipe.id, cause, e
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipe(id: &str, triggers: serde_json::Value) -> Recipe {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": id,
+            "description": "",
+            "steps": [],
+            "triggers": triggers,
+            "created_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap()
+    }
+
+    fn status(services: &[(&str, &str)]) -> PeaInstanceStatus {
+        serde_json::from_value(serde_json::json!({
+            "pea_id": "reactor",
+            "deployed": true,
+            "running": true,
+            "services": services.iter().map(|(tag, state)| serde_json::json!({
+                "tag": tag,
+                "state": state,
+                "current_procedure_id": null,
+                "operation_mode": "Automatic",
+                "source_mode": "Internal"
+            })).collect::<Vec<_>>(),
+            "last_updated": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn completion_chains_may_not_loop() {
+        let recipes = HashMap::from([
+            ("a".to_string(), recipe("a", serde_json::json!([]))),
+            (
+                "b".to_string(),
+                recipe(
+                    "b",
+                    serde_json::json!([{"type": "recipe_completed", "recipe_id": "a"}]),
+                ),
+            ),
+        ]);
+        let configs = HashMap::new();
+
+        let c = recipe(
+            "c",
+            serde_json::json!([{"type": "recipe_completed", "recipe_id": "b"}]),
+        );
+        assert!(validate(&c, &recipes, &configs).is_ok());
+
+        let looping_a = recipe(
+            "a",
+            serde_json::json!([{"type": "recipe_completed", "recipe_id": "b"}]),
+        );
+        assert!(validate(&looping_a, &recipes, &configs)
+            .unwrap_err()
+            .contains("start it again"));
+
+        let unknown = recipe(
+            "d",
+            serde_json::json!([{"type": "service_state", "pea_id": "reactor", "state": "Completed"}]),
+        );
+        assert!(validate(&unknown, &recipes, &configs).is_err());
+    }
+
+    #[test]
+    fn only_state_changes_after_the_first_status_are_reported() {
+        let mut transitions = ServiceTransitions::default();
+        assert!(transitions
+            .entered(&status(&[("Heat", "Completed"), ("Mix", "Idle")]))
+            .is_empty());
+        assert_eq!(
+            transitions.entered(&status(&[("Heat", "Idle"), ("Mix", "Execute")])),
+            vec![
+                ("Heat".to_string(), ServiceState::Idle),
+                ("Mix".to_string(), ServiceState::Execute)
+            ]
+        );
+        assert!(transitions
+            .entered(&status(&[("Heat", "Idle"), ("Mix", "Execute")]))
+            .is_empty());
+
+        let recipes = HashMap::from([(
+            "next".to_string(),
+            recipe(
+                "next",
+                serde_json::json!([{"type": "service_state", "pea_id": "reactor", "state": "Completed"}]),
+            ),
+        )]);
+        assert_eq!(
+            started_by_state(&recipes, "reactor", "Heat", ServiceState::Completed).len(),
+            1
+        );
+        assert!(started_by_state(&recipes, "reactor", "Heat", ServiceState::Idle).is_empty());
+    }
+}
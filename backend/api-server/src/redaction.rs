@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use zenoh::key_expr::keyexpr;
+
+/// Marks keys matching `key_pattern` as sensitive. With no `fields`, the whole
+/// key is hidden; otherwise only the listed payload fields (dot paths) are removed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionRule {
+    pub id: String,
+    pub name: String,
+    pub key_pattern: String,
+    pub fields: Vec<String>,
+    pub enabled: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// The enabled rules applied to a non-elevated caller.
+#[derive(Debug, Clone, Default)]
+pub struct RedactionPolicy {
+    rules: Vec<RedactionRule>,
+}
+
+impl RedactionPolicy {
+    pub fn new(rules: impl IntoIterator<Item = RedactionRule>) -> Self {
+        Self {
+            rules: rules
+                .into_iter()
+                .filter(|rule| rule.enabled && keyexpr::new(rule.key_pattern.as_str()).is_ok())
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    fn matching<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a RedactionRule> + 'a {
+        let key = keyexpr::new(key).ok();
+        self.rules.iter().filter(
+            move |rule| match (key, keyexpr::new(rule.key_pattern.as_str())) {
+                (Some(key), Ok(pattern)) => pattern.intersects(key),
+                _ => false,
+            },
+        )
+    }
+
+    /// True when the key itself must not be disclosed.
+    pub fn hides_key(&self, key: &str) -> bool {
+        self.matching(key).any(|rule| rule.fields.is_empty())
+    }
+
+    /// Returns the payload with sensitive fields removed, or `None` when the key is hidden.
+    pub fn apply(&self, key: &str, mut value: serde_json::Value) -> Option<serde_json::Value> {
+        if self.rules.is_empty() {
+            return Some(value);
+        }
+        if self.hides_key(key) {
+            return None;
+        }
+        for rule in self.matching(key) {
+            for field in &rule.fields {
+                remove_path(&mut value, field);
+            }
+        }
+        Some(value)
+    }
+}
+
+fn remove_path(value: &mut serde_json::Value, path: &str) {
+    let mut segments: Vec<&str> = path.split('.').filter(|s| !s.is_empty()).collect();
+    let Some(last) = segments.pop() else {
+        return;
+    };
+    let mut current = value;
+    for segment in segments {
+        match current.get_mut(segment) {
+            Some(next) => current = next,
+            None => return,
+        }
+    }
+    if let Some(object) = current.as_object_mut() {
+        object.remove(last);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(key_pattern: &str, fields: &[&str]) -> RedactionRule {
+        RedactionRule {
+            id: "rule".to_string(),
+            name: "rule".to_string(),
+            key_pattern: key_pattern.to_string(),
+            fields: fields.iter().map(|f| f.to_string()).collect(),
+            enabled: true,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn whole_key_rules_hide_matching_keys() {
+        let policy = RedactionPolicy::new([rule("entmoot/secrets/**", &[])]);
+        assert!(policy.hides_key("entmoot/secrets/plc/password"));
+        assert!(!policy.hides_key("entmoot/habitat/nodes/local/pea/p1/status"));
+        assert!(policy
+            .apply("entmoot/secrets/plc/password", serde_json::json!("x"))
+            .is_none());
+    }
+
+    #[test]
+    fn field_rules_strip_nested_fields() {
+        let policy =
+            RedactionPolicy::new([rule("entmoot/runtime/**", &["auth.token", "password"])]);
+        let value = serde_json::json!({
+            "host": "10.0.0.1",
+            "password": "secret",
+            "auth": { "token": "abc", "user": "ops" },
+        });
+        let redacted = policy
+            .apply("entmoot/runtime/nodes/n1/status", value)
+            .expect("key should stay visible");
+        assert!(redacted.get("password").is_none());
+        assert!(redacted["auth"].get("token").is_none());
+        assert_eq!(redacted["auth"]["user"], "ops");
+    }
+
+    #[test]
+    fn disabled_rules_are_ignored() {
+        let mut disabled = rule("entmoot/**", &[]);
+        disabled.enabled = false;
+        assert!(RedactionPolicy::new([disabled]).is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+use crate::redaction::{RedactionPolicy, RedactionRule};
+use crate::request_context::CallerContext;
+use crate::runtime_store;
+use crate::state::AppState;
+use crate::tenancy;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use tracing::info;
+use zenoh::key_expr::keyexpr;
+
+#[derive(serde::Deserialize)]
+pub struct RedactionRulePayload {
+    pub name: String,
+    pub key_pattern: String,
+    pub fields: Option<Vec<String>>,
+    pub enabled: Option<bool>,
+}
+
+/// Redaction policy for the caller; empty for the deployment admin.
+pub async fn policy_for(state: &AppState, req: &HttpRequest) -> RedactionPolicy {
+    if tenancy::is_deployment_admin(state, req) {
+        return RedactionPolicy::default();
+    }
+    let rules = state.redaction_rules.read().await;
+    RedactionPolicy::new(rules.values().cloned())
+}
+
+pub async fn list_redaction_rules(state: web::Data<AppState>) -> impl Responder {
+    let rules = state.redaction_rules.read().await;
+    let list: Vec<&RedactionRule> = rules.values().collect();
+    HttpResponse::Ok().json(list)
+}
+
+pub async fn create_redaction_rule(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<RedactionRulePayload>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    let payload = body.into_inner();
+    if let Err(err) = validate_payload(&payload) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": err}));
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let rule = RedactionRule {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: payload.name,
+        key_pattern: payload.key_pattern,
+        fields: payload.fields.unwrap_or_default(),
+        enabled: payload.enabled.unwrap_or(true),
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    runtime_store::persist_json(&state.redaction_dir, &rule.id, &rule);
+    state
+        .redaction_rules
+        .write()
+        .await
+        .insert(rule.id.clone(), rule.clone());
+    HttpResponse::Created().json(rule)
+}
+
+pub async fn update_redaction_rule(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    rule_id: web::Path<String>,
+    body: web::Json<RedactionRulePayload>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    let payload = body.into_inner();
+    if let Err(err) = validate_payload(&payload) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": err}));
+    }
+
+    let mut rules = state.redaction_rules.write().await;
+    let Some(rule) = rules.get_mut(rule_id.as_str()) else {
+        return HttpResponse::NotFound()
+            .json(serde_json::json!({"error": "Redaction rule not found"}));
+    };
+    rule.name = payload.name;
+    rule.key_pattern = payload.key_pattern;
+    rule.fields = payload.fields.unwrap_or_default();
+    rule.enabled = payload.enabled.unwrap_or(true);
+    rule.updated_at = Utc::now().to_rfc3339();
+
+    runtime_store::persist_json(&state.redaction_dir, &rule.id, &*rule);
+    HttpResponse::Ok().json(rule.clone())
+}
+
+pub async fn delete_redaction_rule(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    rule_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    state.redaction_rules.write().await.remove(rule_id.as_str());
+    runtime_store::delete_json(&state.redaction_dir, rule_id.as_str());
+    HttpResponse::NoContent().finish()
+}
+
+fn reject_unless_elevated(state: &AppState, req: &HttpRequest) -> Option<HttpResponse> {
+    let ctx = CallerContext::from_request(req);
+    if tenancy::is_deployment_admin(state, req) {
+        info!(
+            "Redaction rules changed by {}",
+            ctx.actor_id.as_deref().unwrap_or("unknown actor")
+        );
+        return None;
+    }
+    Some(HttpResponse::Forbidden().json(serde_json::json!({
+        "error": "Managing redaction rules requires the deployment admin token"
+    })))
+}
+
+fn validate_payload(payload: &RedactionRulePayload) -> Result<(), String> {
+    if payload.name.trim().is_empty() {
+        return Err("Rule name is required".to_string());
+    }
+    keyexpr::new(payload.key_pattern.as_str())
+        .map(|_| ())
+        .map_err(|e| format!("Invalid key pattern: {}", e))
+}
@@ -0,0 +1,89 @@
+use actix_web::HttpRequest;
+use shared::domain::authority::ActorClass;
+
+pub const ACTOR_ID_HEADER: &str = "X-Actor-Id";
+pub const ACTOR_CLASS_HEADER: &str = "X-Actor-Class";
+
+/// Caller identity as asserted by the fronting proxy.
+///
+/// Only headers are read, and they never elevate: `X-Actor-Class: Admin` is
+/// ignored. Admin callers come from the deployment admin token, see
+/// `tenancy::authenticated_caller`.
+#[derive(Debug, Clone, Default)]
+pub struct CallerContext {
+    pub actor_id: Option<String>,
+    pub actor_class: Option<ActorClass>,
+    /// Roles such as `approver`. Never taken from headers; only an API token
+    /// grants them, see `tenancy::authenticated_caller`.
+    pub roles: Vec<String>,
+}
+
+impl CallerContext {
+    /// Identity from the proxy headers.
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let lookup = |header: &str| {
+            req.headers()
+                .get(header)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        Self {
+            actor_id: lookup(ACTOR_ID_HEADER),
+            actor_class: lookup(ACTOR_CLASS_HEADER)
+                .and_then(|value| parse_actor_class(&value))
+                .filter(|class| *class != ActorClass::Admin),
+            roles: Vec::new(),
+        }
+    }
+
+    /// Admins hold every role.
+    pub fn has_role(&self, role: &str) -> bool {
+        self.is_elevated() || self.roles.iter().any(|held| held == role)
+    }
+
+    /// Only contexts built from the deployment admin token are elevated.
+    pub fn is_elevated(&self) -> bool {
+        self.actor_class == Some(ActorClass::Admin)
+    }
+}
+
+pub fn parse_actor_class(value: &str) -> Option<ActorClass> {
+    serde_json::from_value(serde_json::Value::String(value.to_string())).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn headers_and_query_params_never_elevate() {
+        let query = TestRequest::default()
+            .uri("/ws?actor_class=Admin&actor_id=mallory")
+            .to_http_request();
+        let caller = CallerContext::from_request(&query);
+        assert!(!caller.is_elevated());
+        assert_eq!(caller.actor_id, None);
+
+        let proxied = TestRequest::default()
+            .uri("/api/v1/mesh/keys")
+            .insert_header((ACTOR_ID_HEADER, "mallory"))
+            .insert_header((ACTOR_CLASS_HEADER, "Admin"))
+            .insert_header(("X-Actor-Roles", "approver"))
+            .to_http_request();
+        let caller = CallerContext::from_request(&proxied);
+        assert!(!caller.is_elevated());
+        assert!(!caller.has_role("approver"));
+        assert_eq!(caller.actor_id.as_deref(), Some("mallory"));
+
+        let operator = TestRequest::default()
+            .insert_header((ACTOR_CLASS_HEADER, "Operator"))
+            .to_http_request();
+        assert_eq!(
+            CallerContext::from_request(&operator).actor_class,
+            Some(ActorClass::Operator)
+        );
+    }
+}
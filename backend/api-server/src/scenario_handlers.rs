@@ -8,6 +8,8 @@ use tracing::{error, info};
 use uuid::Uuid;
 
 use crate::state::AppState;
+use crate::task_registry;
+use crate::webhook_service;
 
 #[derive(Clone, Debug, Serialize)]
 pub struct RunningScenario {
@@ -102,7 +104,7 @@ fn built_in_scenarios() -> Vec<ScenarioInfo> {
 }
 
 fn compute_progress(started_at: &str, timeout_real_s: u32, status: &str) -> u32 {
-    if status == "completed" || status == "failed" {
+    if status == "completed" || status == "failed" || status == "stopped" {
         return 100;
     }
     let start = DateTime::parse_from_rfc3339(started_at)
@@ -186,36 +188,55 @@ pub async fn launch_scenario(
             }
 
             let runs = state.scenario_runs.clone();
+            let webhooks = state.webhooks.clone();
             let run_id_cloned = run_id.clone();
-            tokio::spawn(async move {
-                match child.wait().await {
-                    Ok(exit) => {
-                        let mut runs_guard = runs.write().await;
-                        if let Some(run) = runs_guard.get_mut(&run_id_cloned) {
-                            run["status"] = json!(if exit.success() {
-                                "completed"
-                            } else {
-                                "failed"
-                            });
-                            run["progress_percent"] = json!(100);
-                            run["message"] = if exit.success() {
-                                json!("Scenario completed successfully")
-                            } else {
-                                json!(format!("Scenario failed with status {:?}", exit.code()))
-                            };
+            let task_name = format!("scenario-run:{}", run_id);
+            state
+                .tasks
+                .spawn(task_name, task_registry::KIND_SCENARIO, |task| async move {
+                    match child.wait().await {
+                        Ok(exit) => {
+                            if !exit.success() {
+                                task.fail(format!("exited with status {:?}", exit.code()));
+                            }
+                            let mut runs_guard = runs.write().await;
+                            if let Some(run) = runs_guard.get_mut(&run_id_cloned) {
+                                let stopped = run["stop_requested"].as_bool().unwrap_or(false);
+                                run["status"] = json!(if exit.success() {
+                                    "completed"
+                                } else if stopped {
+                                    "stopped"
+                                } else {
+                                    "failed"
+                                });
+                                run["progress_percent"] = json!(100);
+                                run["message"] = if exit.success() {
+                                    json!("Scenario completed successfully")
+                                } else if stopped {
+                                    json!("Scenario stopped")
+                                } else {
+                                    json!(format!("Scenario failed with status {:?}", exit.code()))
+                                };
+                            }
                         }
-                    }
-                    Err(e) => {
-                        error!("Scenario wait failed for {}: {}", run_id_cloned, e);
-                        let mut runs_guard = runs.write().await;
-                        if let Some(run) = runs_guard.get_mut(&run_id_cloned) {
-                            run["status"] = json!("failed");
-                            run["progress_percent"] = json!(100);
-                            run["message"] = json!(format!("Scenario process error: {}", e));
+                        Err(e) => {
+                            error!("Scenario wait failed for {}: {}", run_id_cloned, e);
+                            task.fail(&e);
+                            let mut runs_guard = runs.write().await;
+                            if let Some(run) = runs_guard.get_mut(&run_id_cloned) {
+                                run["status"] = json!("failed");
+                                run["progress_percent"] = json!(100);
+                                run["message"] = json!(format!("Scenario process error: {}", e));
+                            }
                         }
                     }
-                }
-            });
+                    let finished = runs.read().await.get(&run_id_cloned).cloned();
+                    if let Some(run) = finished {
+                        webhooks
+                            .emit(webhook_service::EVENT_SCENARIO_FINISHED, run)
+                            .await;
+                    }
+                });
 
             HttpResponse::Accepted().json(LaunchScenarioResponse {
                 run_id,
@@ -281,3 +302,33 @@ pub async fn list_running_scenarios(state: web::Data<AppState>) -> impl Responde
         "count": list.len(),
     }))
 }
+
+/// Terminates running scenario processes: the given run, or all of them.
+/// Returns the run ids that were signalled.
+pub async fn stop_runs(state: &AppState, run_id: Option<&str>) -> Vec<String> {
+    let mut stopped = Vec::new();
+    let mut runs = state.scenario_runs.write().await;
+    for (id, run) in runs.iter_mut() {
+        if run_id.is_some_and(|wanted| wanted != id) || run["status"] != "running" {
+            continue;
+        }
+        let Some(pid) = run["pid"].as_u64().filter(|pid| *pid > 0) else {
+            continue;
+        };
+        match Command::new("kill")
+            .arg("-TERM")
+            .arg(pid.to_string())
+            .status()
+            .await
+        {
+            Ok(status) if status.success() => {
+                info!("Stopping scenario run {} (pid={})", id, pid);
+                run["stop_requested"] = json!(true);
+                stopped.push(id.clone());
+            }
+            Ok(status) => error!("kill {} exited with {:?}", pid, status.code()),
+            Err(e) => error!("Failed to stop scenario run {}: {}", id, e),
+        }
+    }
+    stopped
+}
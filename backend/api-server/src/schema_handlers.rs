@@ -0,0 +1,117 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::request_context::CallerContext;
+use crate::runtime_store;
+use crate::schema_registry::{self, TopicSchema};
+use crate::state::AppState;
+use crate::tenancy;
+
+#[derive(Deserialize)]
+pub struct SchemaQuery {
+    /// Only the schema that applies to this key
+    pub key: Option<String>,
+}
+
+/// GET /schemas?key=... — payload schemas per topic family
+pub async fn list_schemas(
+    state: web::Data<AppState>,
+    query: web::Query<SchemaQuery>,
+) -> impl Responder {
+    match &query.key {
+        Some(key) => {
+            let list: Vec<TopicSchema> = state.schemas.schema_for(key).await.into_iter().collect();
+            HttpResponse::Ok().json(list)
+        }
+        None => HttpResponse::Ok().json(state.schemas.list().await),
+    }
+}
+
+pub async fn get_schema(state: web::Data<AppState>, family: web::Path<String>) -> impl Responder {
+    match state.schemas.get(&family).await {
+        Some(schema) => HttpResponse::Ok().json(schema),
+        None => HttpResponse::NotFound().json(serde_json::json!({"error": "Schema not found"})),
+    }
+}
+
+/// PUT /schemas/{family} — registers a schema, replacing a built-in one of the same family
+pub async fn put_schema(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    family: web::Path<String>,
+    body: web::Json<TopicSchema>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    let mut schema = body.into_inner();
+    schema.family = family.trim().to_string();
+    schema.builtin = false;
+    if let Err(e) = schema.validate() {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    schema.updated_at = Utc::now().to_rfc3339();
+    runtime_store::persist_json(&state.schema_dir, &schema.family, &schema);
+    state.schemas.register(schema.clone()).await;
+    HttpResponse::Ok().json(schema)
+}
+
+/// DELETE /schemas/{family} — removes a registered schema; a built-in one applies again
+pub async fn delete_schema(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    family: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    if !state.schemas.unregister(&family).await {
+        return HttpResponse::NotFound()
+            .json(serde_json::json!({"error": "No registered schema for this family"}));
+    }
+    runtime_store::delete_json(&state.schema_dir, &family);
+    HttpResponse::NoContent().finish()
+}
+
+/// POST /schemas/{family}/validate — checks a sample payload against the family's schema
+pub async fn validate_payload(
+    state: web::Data<AppState>,
+    family: web::Path<String>,
+    body: web::Json<serde_json::Value>,
+) -> impl Responder {
+    let Some(schema) = state.schemas.get(&family).await else {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Schema not found"}));
+    };
+    let errors = schema_registry::validate(&schema.schema, &body);
+    HttpResponse::Ok().json(serde_json::json!({
+        "family": schema.family,
+        "valid": errors.is_empty(),
+        "errors": errors,
+    }))
+}
+
+/// GET /schemas/violations — ingested payloads that broke their schema
+pub async fn get_violations(state: web::Data<AppState>) -> impl Responder {
+    let (counts, recent) = state.schemas.violations();
+    HttpResponse::Ok().json(serde_json::json!({
+        "validating": state.schemas.validating(),
+        "counts": counts,
+        "recent": recent,
+    }))
+}
+
+fn reject_unless_elevated(state: &AppState, req: &HttpRequest) -> Option<HttpResponse> {
+    let ctx = CallerContext::from_request(req);
+    if tenancy::is_deployment_admin(state, req) {
+        info!(
+            "Payload schemas changed by {}",
+            ctx.actor_id.as_deref().unwrap_or("unknown actor")
+        );
+        return None;
+    }
+    Some(HttpResponse::Forbidden().json(serde_json::json!({
+        "error": "Registering payload schemas requires the deployment admin token"
+    })))
+}
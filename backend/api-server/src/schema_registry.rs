@@ -0,0 +1,462 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use shared::mtp::ServiceState;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+use tracing::warn;
+use zenoh::key_expr::KeyExpr;
+
+use crate::ts_bands::key_expr_includes;
+
+/// Violations kept for `GET /schemas/violations`.
+pub const MAX_VIOLATIONS: usize = 200;
+
+/// JSON Schema for the payloads of one topic family.
+///
+/// Schemas use the draft-07 keywords `type`, `enum`, `required`, `properties`,
+/// `additionalProperties` (as a boolean), `items`, `minimum` and `maximum`;
+/// other keywords are accepted and ignored.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TopicSchema {
+    #[serde(default)]
+    pub family: String,
+    /// Key expression of the family, e.g. `entmoot/habitat/nodes/*/pea/*/status`
+    pub key_expr: String,
+    #[serde(default)]
+    pub description: String,
+    pub schema: Value,
+    /// Shipped with the server; a registered schema of the same family replaces it.
+    #[serde(default)]
+    pub builtin: bool,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl TopicSchema {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.family.trim().is_empty() {
+            return Err("family is required".to_string());
+        }
+        // Families double as file names in the schema directory.
+        if self.family.contains(['/', '\\']) || self.family.starts_with('.') {
+            return Err("family may not contain path separators or start with '.'".to_string());
+        }
+        if KeyExpr::try_from(self.key_expr.as_str()).is_err() {
+            return Err(format!("Invalid key expression '{}'", self.key_expr));
+        }
+        check_schema(&self.schema, "")
+    }
+}
+
+const TYPES: [&str; 7] = [
+    "string", "number", "integer", "boolean", "object", "array", "null",
+];
+
+fn check_schema(schema: &Value, path: &str) -> Result<(), String> {
+    let Some(schema) = schema.as_object() else {
+        return Err(format!("schema{}: must be an object", path));
+    };
+    let types: Vec<&Value> = match schema.get("type") {
+        Some(Value::Array(types)) => types.iter().collect(),
+        Some(single) => vec![single],
+        None => Vec::new(),
+    };
+    if let Some(unknown) = types
+        .iter()
+        .find(|t| !t.as_str().is_some_and(|t| TYPES.contains(&t)))
+    {
+        return Err(format!("schema{}: unknown type {}", path, unknown));
+    }
+    if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        for (name, property) in properties {
+            check_schema(property, &format!("{}/properties/{}", path, name))?;
+        }
+    }
+    if let Some(items) = schema.get("items") {
+        check_schema(items, &format!("{}/items", path))?;
+    }
+    Ok(())
+}
+
+fn type_matches(name: &str, value: &Value) -> bool {
+    match name {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
+
+/// Where `value` breaks `schema`, as `/pointer: problem` lines; empty when it conforms.
+pub fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    check(schema, value, "", &mut errors);
+    errors
+}
+
+fn check(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let at = if path.is_empty() { "/" } else { path };
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(single)) => vec![single.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if !types.is_empty() && !types.iter().any(|t| type_matches(t, value)) {
+        errors.push(format!("{}: expected {}", at, types.join(" or ")));
+        return;
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            errors.push(format!("{}: {} is not an allowed value", at, value));
+        }
+    }
+    if let Some(n) = value.as_f64() {
+        if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+            if n < min {
+                errors.push(format!("{}: {} is below {}", at, n, min));
+            }
+        }
+        if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+            if n > max {
+                errors.push(format!("{}: {} is above {}", at, n, max));
+            }
+        }
+    }
+    if let Value::Object(fields) = value {
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !fields.contains_key(name) {
+                    errors.push(format!("{}/{}: is required", path, name));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, field) in fields {
+            match properties.and_then(|properties| properties.get(name)) {
+                Some(property) => check(property, field, &format!("{}/{}", path, name), errors),
+                None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                    errors.push(format!("{}/{}: is not allowed", path, name));
+                }
+                None => {}
+            }
+        }
+    }
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (index, item) in items.iter().enumerate() {
+            check(item_schema, item, &format!("{}/{}", path, index), errors);
+        }
+    }
+}
+
+/// Schemas of the topic families the platform itself publishes.
+pub fn builtin_schemas() -> Vec<TopicSchema> {
+    let states: Vec<Value> = ServiceState::all()
+        .iter()
+        .map(|state| serde_json::to_value(state).unwrap_or_default())
+        .collect();
+    let commands = json!([
+        "Reset", "Start", "Stop", "Hold", "Unhold", "Pause", "Resume", "Abort", "Restart",
+        "Complete"
+    ]);
+    let schema = |family: &str, key_expr: &str, description: &str, schema: Value| TopicSchema {
+        family: family.to_string(),
+        key_expr: key_expr.to_string(),
+        description: description.to_string(),
+        schema,
+        builtin: true,
+        updated_at: String::new(),
+    };
+    vec![
+        schema(
+            "pea-status",
+            "entmoot/habitat/nodes/*/pea/*/status",
+            "Runtime status of a PEA and its services",
+            json!({
+                "type": "object",
+                "required": ["pea_id", "deployed", "running"],
+                "properties": {
+                    "pea_id": {"type": "string"},
+                    "deployed": {"type": "boolean"},
+                    "running": {"type": "boolean"},
+                    "services": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["tag", "state"],
+                            "properties": {
+                                "tag": {"type": "string"},
+                                "state": {"enum": states},
+                                "state_code": {"type": "integer", "minimum": 0},
+                            },
+                        },
+                    },
+                    "last_updated": {"type": "string"},
+                },
+            }),
+        ),
+        schema(
+            "pea-service-state",
+            "entmoot/habitat/nodes/*/pea/*/services/*/state",
+            "State of one PEA service",
+            json!({
+                "type": "object",
+                "required": ["state"],
+                "properties": {
+                    "state": {"enum": states},
+                    "state_code": {"type": "integer", "minimum": 0},
+                },
+            }),
+        ),
+        schema(
+            "pea-service-command",
+            "entmoot/habitat/nodes/*/pea/*/services/*/command",
+            "Command for one PEA service",
+            json!({
+                "type": "object",
+                "required": ["command"],
+                "properties": {
+                    "command": {"enum": commands},
+                    "command_code": {"type": "integer", "minimum": 0},
+                    "procedure_id": {"type": ["integer", "null"], "minimum": 0},
+                    "timestamp": {"type": "string"},
+                },
+            }),
+        ),
+        schema(
+            "pea-alarm",
+            "entmoot/habitat/nodes/*/pea/*/swimlane/alarm",
+            "Alarm raised or cleared by a PEA",
+            json!({
+                "type": "object",
+                "required": ["active", "alarm"],
+                "properties": {
+                    "active": {"type": "boolean"},
+                    "alarm": {"type": "string"},
+                    "severity": {"type": "string"},
+                },
+            }),
+        ),
+        schema(
+            "runtime-pea-deploy",
+            "entmoot/runtime/nodes/*/pea/*/deploy",
+            "Deploy or undeploy a PEA on a runtime node",
+            json!({
+                "type": "object",
+                "required": ["action"],
+                "properties": {
+                    "action": {"enum": ["deploy", "undeploy"]},
+                    "pea_config": {"type": "object"},
+                },
+            }),
+        ),
+        schema(
+            "runtime-pea-lifecycle",
+            "entmoot/runtime/nodes/*/pea/*/lifecycle",
+            "Start or stop a deployed PEA",
+            json!({
+                "type": "object",
+                "required": ["action"],
+                "properties": {
+                    "action": {"enum": ["start", "stop"]},
+                },
+            }),
+        ),
+    ]
+}
+
+/// A payload that did not conform to its family's schema.
+#[derive(Clone, Debug, Serialize)]
+pub struct SchemaViolation {
+    pub family: String,
+    pub key: String,
+    pub errors: Vec<String>,
+    pub observed_at: String,
+}
+
+#[derive(Default)]
+struct ViolationLog {
+    recent: VecDeque<SchemaViolation>,
+    counts: HashMap<String, u64>,
+}
+
+/// Registered schemas and, with `SCHEMA_VALIDATION=true`, the record of
+/// ingested payloads that broke them.
+#[derive(Clone)]
+pub struct SchemaRegistry {
+    validating: bool,
+    registered: Arc<RwLock<HashMap<String, TopicSchema>>>,
+    violations: Arc<Mutex<ViolationLog>>,
+}
+
+impl SchemaRegistry {
+    pub fn from_env(registered: HashMap<String, TopicSchema>) -> Self {
+        let validating = std::env::var("SCHEMA_VALIDATION")
+            .map(|value| matches!(value.as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+        Self {
+            validating,
+            registered: Arc::new(RwLock::new(registered)),
+            violations: Arc::new(Mutex::new(ViolationLog::default())),
+        }
+    }
+
+    pub fn validating(&self) -> bool {
+        self.validating
+    }
+
+    /// Built-in schemas not replaced by a registered one, then the registered ones.
+    pub async fn list(&self) -> Vec<TopicSchema> {
+        let registered = self.registered.read().await;
+        let mut list: Vec<TopicSchema> = builtin_schemas()
+            .into_iter()
+            .filter(|schema| !registered.contains_key(&schema.family))
+            .collect();
+        let mut custom: Vec<TopicSchema> = registered.values().cloned().collect();
+        custom.sort_by(|a, b| a.family.cmp(&b.family));
+        list.extend(custom);
+        list
+    }
+
+    pub async fn get(&self, family: &str) -> Option<TopicSchema> {
+        self.list()
+            .await
+            .into_iter()
+            .find(|schema| schema.family == family)
+    }
+
+    /// The schema payloads on `key` must follow; registered schemas win.
+    pub async fn schema_for(&self, key: &str) -> Option<TopicSchema> {
+        let list = self.list().await;
+        list.iter()
+            .find(|schema| !schema.builtin && key_expr_includes(&schema.key_expr, key))
+            .or_else(|| {
+                list.iter()
+                    .find(|schema| key_expr_includes(&schema.key_expr, key))
+            })
+            .cloned()
+    }
+
+    pub async fn register(&self, schema: TopicSchema) {
+        self.registered
+            .write()
+            .await
+            .insert(schema.family.clone(), schema);
+    }
+
+    pub async fn unregister(&self, family: &str) -> bool {
+        self.registered.write().await.remove(family).is_some()
+    }
+
+    /// Flags an ingested payload that breaks its family's schema.
+    pub async fn check(&self, key: &str, value: &Value) {
+        if !self.validating {
+            return;
+        }
+        let Some(schema) = self.schema_for(key).await else {
+            return;
+        };
+        let errors = validate(&schema.schema, value);
+        if errors.is_empty() {
+            return;
+        }
+        let mut log = self.violations.lock().unwrap_or_else(|e| e.into_inner());
+        let count = log.counts.entry(schema.family.clone()).or_insert(0);
+        *count += 1;
+        // Log the first violation of a family and then every hundredth.
+        if *count % 100 == 1 {
+            warn!(
+                "Payload on {} breaks the {} schema ({} so far): {}",
+                key,
+                schema.family,
+                count,
+                errors.join("; ")
+            );
+        }
+        if log.recent.len() == MAX_VIOLATIONS {
+            log.recent.pop_front();
+        }
+        log.recent.push_back(SchemaViolation {
+            family: schema.family,
+            key: key.to_string(),
+            errors,
+            observed_at: Utc::now().to_rfc3339(),
+        });
+    }
+
+    /// Violation counts per family and the most recent violations, newest first.
+    pub fn violations(&self) -> (HashMap<String, u64>, Vec<SchemaViolation>) {
+        let log = self.violations.lock().unwrap_or_else(|e| e.into_inner());
+        (
+            log.counts.clone(),
+            log.recent.iter().rev().cloned().collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation_reports_each_nonconforming_field() {
+        let schema = json!({
+            "type": "object",
+            "required": ["pea_id", "running"],
+            "additionalProperties": false,
+            "properties": {
+                "pea_id": {"type": "string"},
+                "running": {"type": "boolean"},
+                "level": {"type": "number", "minimum": 0, "maximum": 100},
+                "tags": {"type": "array", "items": {"enum": ["a", "b"]}},
+            },
+        });
+        assert!(validate(&schema, &json!({"pea_id": "p1", "running": true})).is_empty());
+
+        let mut errors = validate(
+            &schema,
+            &json!({"pea_id": 7, "level": 120, "tags": ["a", "c"], "extra": 1}),
+        );
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                "/extra: is not allowed",
+                "/level: 120 is above 100",
+                "/pea_id: expected string",
+                "/running: is required",
+                "/tags/1: \"c\" is not an allowed value",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn builtin_status_schema_accepts_simulated_status() {
+        let registry = SchemaRegistry::from_env(HashMap::new());
+        let key = "entmoot/habitat/nodes/local/pea/reactor/status";
+        let schema = registry.schema_for(key).await.unwrap();
+        assert_eq!(schema.family, "pea-status");
+        for schema in builtin_schemas() {
+            assert!(schema.validate().is_ok(), "{}", schema.family);
+        }
+
+        let status = json!({
+            "pea_id": "reactor",
+            "deployed": true,
+            "running": false,
+            "services": [{"tag": "Dose", "state": "Idle", "state_code": 16}],
+            "last_updated": "2026-01-01T00:00:00Z",
+        });
+        assert!(validate(&schema.schema, &status).is_empty());
+        let broken = json!({"pea_id": "reactor", "deployed": "yes", "running": false});
+        assert_eq!(
+            validate(&schema.schema, &broken),
+            vec!["/deployed: expected boolean"]
+        );
+    }
+}
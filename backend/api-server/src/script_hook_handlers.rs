@@ -0,0 +1,128 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use tracing::info;
+
+use crate::request_context::CallerContext;
+use crate::runtime_store;
+use crate::script_hooks::ScriptHook;
+use crate::state::AppState;
+use crate::tenancy;
+
+/// GET /scripts — uploaded script hooks, without their modules
+pub async fn list_script_hooks(state: web::Data<AppState>) -> impl Responder {
+    let hooks: Vec<serde_json::Value> = state
+        .scripts
+        .list()
+        .await
+        .into_iter()
+        .map(|hook| summary(&state, hook))
+        .collect();
+    HttpResponse::Ok().json(hooks)
+}
+
+/// GET /scripts/{id} — a script hook with its module and run counters
+pub async fn get_script_hook(
+    state: web::Data<AppState>,
+    hook_id: web::Path<String>,
+) -> impl Responder {
+    match state.scripts.get(&hook_id).await {
+        Some(hook) => {
+            let stats = state.scripts.stats(&hook.id);
+            let mut body = serde_json::json!(hook);
+            body["stats"] = serde_json::json!(stats);
+            HttpResponse::Ok().json(body)
+        }
+        None => hook_not_found(),
+    }
+}
+
+/// POST /scripts — upload a WebAssembly module bound to a hook
+pub async fn create_script_hook(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<ScriptHook>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    let now = Utc::now().to_rfc3339();
+    let mut hook = body.into_inner();
+    hook.id = uuid::Uuid::new_v4().to_string();
+    hook.name = hook.name.trim().to_string();
+    hook.created_at = now.clone();
+    hook.updated_at = now;
+    if let Err(e) = state.scripts.upsert(hook.clone()).await {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    runtime_store::persist_json(&state.script_dir, &hook.id, &hook);
+    HttpResponse::Created().json(summary(&state, hook))
+}
+
+/// PUT /scripts/{id} — replace a script hook; runs already started finish
+/// with the old module
+pub async fn update_script_hook(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    hook_id: web::Path<String>,
+    body: web::Json<ScriptHook>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    let Some(existing) = state.scripts.get(&hook_id).await else {
+        return hook_not_found();
+    };
+    let mut hook = body.into_inner();
+    hook.id = existing.id;
+    hook.name = hook.name.trim().to_string();
+    hook.created_at = existing.created_at;
+    hook.updated_at = Utc::now().to_rfc3339();
+    if let Err(e) = state.scripts.upsert(hook.clone()).await {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    runtime_store::persist_json(&state.script_dir, &hook.id, &hook);
+    HttpResponse::Ok().json(summary(&state, hook))
+}
+
+pub async fn delete_script_hook(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    hook_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    if !state.scripts.remove(&hook_id).await {
+        return hook_not_found();
+    }
+    runtime_store::delete_json(&state.script_dir, &hook_id);
+    HttpResponse::NoContent().finish()
+}
+
+/// The hook without its module, which can be large, plus its run counters.
+fn summary(state: &AppState, mut hook: ScriptHook) -> serde_json::Value {
+    hook.wasm = None;
+    hook.wat = None;
+    let stats = state.scripts.stats(&hook.id);
+    let mut body = serde_json::json!(hook);
+    body["stats"] = serde_json::json!(stats);
+    body
+}
+
+fn reject_unless_elevated(state: &AppState, req: &HttpRequest) -> Option<HttpResponse> {
+    let ctx = CallerContext::from_request(req);
+    if tenancy::is_deployment_admin(state, req) {
+        info!(
+            "Script hooks changed by {}",
+            ctx.actor_id.as_deref().unwrap_or("unknown actor")
+        );
+        return None;
+    }
+    Some(HttpResponse::Forbidden().json(serde_json::json!({
+        "error": "Uploading script hooks requires the deployment admin token"
+    })))
+}
+
+fn hook_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({"error": "Script hook not found"}))
+}
@@ -0,0 +1,838 @@
+use actix_web::web;
+use base64::Engine as _;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use shared::mtp::ServiceCommand;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, RwLock, Semaphore};
+use tracing::{error, info, warn};
+use wasmtime::{
+    Caller, Config, Engine, Extern, Instance, Linker, Module, Store, StoreLimits,
+    StoreLimitsBuilder,
+};
+
+use crate::redaction::{RedactionPolicy, RedactionRule};
+use crate::state::{AlarmRecord, AppState, TimeSeriesStore};
+use crate::ts_bands::key_expr_includes;
+use crate::{alarm_bus, automation, pol_handlers, soe, webhook_service};
+
+/// Largest module accepted, as uploaded.
+pub const MAX_MODULE_BYTES: usize = 1024 * 1024;
+/// Linear memory a script may grow to.
+const MAX_MEMORY_BYTES: usize = 16 * 1024 * 1024;
+/// Publishes and alarms one run may queue; further ones fail.
+const MAX_EFFECTS_PER_RUN: usize = 16;
+/// Runs of sample and alarm hooks at a time; samples arriving while all are
+/// busy skip their hooks instead of queueing.
+const MAX_CONCURRENT_RUNS: usize = 4;
+/// Scripts may only publish below this prefix, so they cannot command PEAs
+/// past interlocks and approvals.
+pub const PUBLISH_PREFIX: &str = "entmoot/scripts";
+const ALARM_SOURCE_PREFIX: &str = "entmoot/scripts/hooks";
+
+/// When a script runs, and the export it runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Hook {
+    /// After an alarm is raised; `key_pattern` matches the alarm source.
+    OnAlarm,
+    /// After a sample is ingested; `key_pattern` matches the key.
+    OnSample,
+    /// Before a service command is sent; `key_pattern` matches
+    /// `<pea_id>/<service_tag>`. A non-zero result rejects the command.
+    PreCommand,
+}
+
+impl Hook {
+    fn export(self) -> &'static str {
+        match self {
+            Hook::OnAlarm => "on_alarm",
+            Hook::OnSample => "on_sample",
+            Hook::PreCommand => "pre_command",
+        }
+    }
+}
+
+/// A WebAssembly module bound to a hook.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScriptHook {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub hook: Hook,
+    /// Key expression limiting what the hook runs for; every event without it.
+    #[serde(default)]
+    pub key_pattern: Option<String>,
+    /// The module as base64 of a `.wasm` binary.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wasm: Option<String>,
+    /// The module in WebAssembly text format, instead of `wasm`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wat: Option<String>,
+    /// Instructions one run may execute, roughly; `SCRIPT_FUEL` by default and
+    /// at most.
+    #[serde(default)]
+    pub fuel: Option<u64>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl ScriptHook {
+    fn module_bytes(&self) -> Result<Vec<u8>, String> {
+        let bytes = match (&self.wasm, &self.wat) {
+            (Some(wasm), None) => base64::engine::general_purpose::STANDARD
+                .decode(wasm.trim())
+                .map_err(|e| format!("wasm is not valid base64: {}", e))?,
+            (None, Some(wat)) => wat.as_bytes().to_vec(),
+            _ => return Err("Exactly one of wasm and wat is required".to_string()),
+        };
+        if bytes.len() > MAX_MODULE_BYTES {
+            return Err(format!("Module exceeds {} bytes", MAX_MODULE_BYTES));
+        }
+        Ok(bytes)
+    }
+
+    fn matches(&self, subject: &str) -> bool {
+        self.enabled
+            && self
+                .key_pattern
+                .as_deref()
+                .is_none_or(|pattern| key_expr_includes(pattern, subject))
+    }
+}
+
+/// Run counters of a hook, since the server started or the hook changed.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct HookStats {
+    pub runs: u64,
+    pub failures: u64,
+    /// Sample and alarm events passed over while all runners were busy.
+    pub skipped: u64,
+    pub last_error: Option<String>,
+    pub last_run_at: Option<String>,
+}
+
+/// What a run asked the host to do; applied by `apply_effects`.
+#[derive(Debug)]
+enum Effect {
+    Publish {
+        key: String,
+        payload: String,
+    },
+    RaiseAlarm {
+        hook: Box<ScriptHook>,
+        severity: String,
+        message: String,
+    },
+}
+
+struct Loaded {
+    hook: ScriptHook,
+    module: Module,
+}
+
+/// Per-run host state.
+struct Host {
+    hook: ScriptHook,
+    timeseries: Arc<RwLock<TimeSeriesStore>>,
+    /// Scripts are not elevated callers: `read_state` sees redacted values.
+    redaction: RedactionPolicy,
+    effects: Vec<Effect>,
+    rejection: Option<String>,
+    limits: StoreLimits,
+}
+
+/// Uploaded scripts, run in a sandbox at their hooks. The host API is the
+/// `entmoot` import module: `read_state`, `publish`, `raise_alarm`, `reject`
+/// and `log`.
+#[derive(Clone)]
+pub struct ScriptHooks {
+    engine: Engine,
+    linker: Arc<Linker<Host>>,
+    /// `SCRIPT_FUEL`: fuel of hooks that set none, and the most any may set.
+    default_fuel: u64,
+    hooks: Arc<RwLock<HashMap<String, Loaded>>>,
+    stats: Arc<Mutex<HashMap<String, HookStats>>>,
+    timeseries: Arc<RwLock<TimeSeriesStore>>,
+    redaction_rules: Arc<RwLock<HashMap<String, RedactionRule>>>,
+    permits: Arc<Semaphore>,
+    effects: mpsc::UnboundedSender<Effect>,
+    receiver: Arc<Mutex<Option<mpsc::UnboundedReceiver<Effect>>>>,
+}
+
+impl ScriptHooks {
+    pub fn from_env(
+        hooks: HashMap<String, ScriptHook>,
+        timeseries: Arc<RwLock<TimeSeriesStore>>,
+        redaction_rules: Arc<RwLock<HashMap<String, RedactionRule>>>,
+    ) -> anyhow::Result<Self> {
+        let default_fuel = std::env::var("SCRIPT_FUEL")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(10_000_000);
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let linker = host_api(&engine)?;
+        let (effects, receiver) = mpsc::unbounded_channel();
+        let mut scripts = Self {
+            engine,
+            linker: Arc::new(linker),
+            default_fuel,
+            hooks: Arc::new(RwLock::new(HashMap::new())),
+            stats: Arc::new(Mutex::new(HashMap::new())),
+            timeseries,
+            redaction_rules,
+            permits: Arc::new(Semaphore::new(MAX_CONCURRENT_RUNS)),
+            effects,
+            receiver: Arc::new(Mutex::new(Some(receiver))),
+        };
+        let mut loaded = HashMap::new();
+        for (id, hook) in hooks {
+            match scripts.compile(&hook) {
+                Ok(module) => {
+                    loaded.insert(id, Loaded { hook, module });
+                }
+                Err(e) => warn!("Skipping script hook {}: {}", id, e),
+            }
+        }
+        scripts.hooks = Arc::new(RwLock::new(loaded));
+        Ok(scripts)
+    }
+
+    /// Compiles a hook's module and checks it exports what the hook runs.
+    fn compile(&self, hook: &ScriptHook) -> Result<Module, String> {
+        if hook.name.trim().is_empty() {
+            return Err("name is required".to_string());
+        }
+        if let Some(pattern) = &hook.key_pattern {
+            if zenoh::key_expr::KeyExpr::try_from(pattern.as_str()).is_err() {
+                return Err(format!("Invalid key expression '{}'", pattern));
+            }
+        }
+        let module = Module::new(&self.engine, hook.module_bytes()?)
+            .map_err(|e| format!("Invalid module: {:#}", e))?;
+        for export in ["memory", "alloc", hook.hook.export()] {
+            if module.get_export(export).is_none() {
+                return Err(format!("Module does not export '{}'", export));
+            }
+        }
+        Ok(module)
+    }
+
+    pub async fn list(&self) -> Vec<ScriptHook> {
+        let mut hooks: Vec<ScriptHook> = self
+            .hooks
+            .read()
+            .await
+            .values()
+            .map(|loaded| loaded.hook.clone())
+            .collect();
+        hooks.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+        hooks
+    }
+
+    pub async fn get(&self, id: &str) -> Option<ScriptHook> {
+        self.hooks
+            .read()
+            .await
+            .get(id)
+            .map(|loaded| loaded.hook.clone())
+    }
+
+    pub fn stats(&self, id: &str) -> HookStats {
+        let stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        stats.get(id).cloned().unwrap_or_default()
+    }
+
+    pub async fn upsert(&self, hook: ScriptHook) -> Result<(), String> {
+        if hook
+            .fuel
+            .is_some_and(|fuel| fuel == 0 || fuel > self.default_fuel)
+        {
+            return Err(format!(
+                "fuel must be between 1 and {} (SCRIPT_FUEL)",
+                self.default_fuel
+            ));
+        }
+        let module = self.compile(&hook)?;
+        let id = hook.id.clone();
+        self.hooks
+            .write()
+            .await
+            .insert(id.clone(), Loaded { hook, module });
+        self.stats
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&id);
+        Ok(())
+    }
+
+    pub async fn remove(&self, id: &str) -> bool {
+        self.stats
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(id);
+        self.hooks.write().await.remove(id).is_some()
+    }
+
+    /// Runs the sample hooks matching `key`, in the background. Keys scripts
+    /// publish do not run hooks, so a script cannot feed itself.
+    pub async fn on_sample(&self, key: &str, value: &Value, timestamp_ms: i64) {
+        if key.starts_with(PUBLISH_PREFIX) {
+            return;
+        }
+        let event = json!({"key": key, "value": value, "timestamp_ms": timestamp_ms});
+        self.spawn_runs(Hook::OnSample, key, event).await;
+    }
+
+    /// Runs the alarm hooks matching the alarm's source, in the background.
+    /// Alarms scripts raised do not run hooks.
+    pub async fn on_alarm(&self, alarm: &AlarmRecord) {
+        if alarm.source.starts_with(ALARM_SOURCE_PREFIX) {
+            return;
+        }
+        self.spawn_runs(Hook::OnAlarm, &alarm.source, json!(alarm))
+            .await;
+    }
+
+    async fn spawn_runs(&self, hook: Hook, subject: &str, event: Value) {
+        let matching = self.matching(hook, subject).await;
+        if matching.is_empty() {
+            return;
+        }
+        let event = event.to_string();
+        for (script, module) in matching {
+            let Ok(permit) = self.permits.clone().try_acquire_owned() else {
+                self.count(&script.id, |stats| stats.skipped += 1);
+                continue;
+            };
+            let scripts = self.clone();
+            let event = event.clone();
+            tokio::task::spawn_blocking(move || {
+                let _permit = permit;
+                let _ = scripts.run_blocking(script, module, &event);
+            });
+        }
+    }
+
+    /// Runs the pre-command hooks for a service command; `Err` carries the
+    /// reason the first rejecting script gave. A script that fails rejects
+    /// the command too.
+    pub async fn pre_command(
+        &self,
+        pea_id: &str,
+        service_tag: &str,
+        command: ServiceCommand,
+        procedure_id: Option<u32>,
+    ) -> Result<(), String> {
+        let subject = format!("{}/{}", pea_id, service_tag);
+        let matching = self.matching(Hook::PreCommand, &subject).await;
+        if matching.is_empty() {
+            return Ok(());
+        }
+        let event = json!({
+            "pea_id": pea_id,
+            "service_tag": service_tag,
+            "command": command,
+            "procedure_id": procedure_id,
+        })
+        .to_string();
+        for (script, module) in matching {
+            let name = script.name.clone();
+            let scripts = self.clone();
+            let event = event.clone();
+            let verdict =
+                tokio::task::spawn_blocking(move || scripts.run_blocking(script, module, &event))
+                    .await
+                    .map_err(|e| format!("Script {} did not finish: {}", name, e))?;
+            match verdict {
+                Ok((0, _)) => {}
+                Ok((_, reason)) => {
+                    return Err(
+                        reason.unwrap_or_else(|| format!("Rejected by script hook {}", name))
+                    )
+                }
+                Err(e) => return Err(format!("Script hook {} failed: {}", name, e)),
+            }
+        }
+        Ok(())
+    }
+
+    async fn matching(&self, hook: Hook, subject: &str) -> Vec<(ScriptHook, Module)> {
+        let mut matching: Vec<(ScriptHook, Module)> = self
+            .hooks
+            .read()
+            .await
+            .values()
+            .filter(|loaded| loaded.hook.hook == hook && loaded.hook.matches(subject))
+            .map(|loaded| (loaded.hook.clone(), loaded.module.clone()))
+            .collect();
+        matching.sort_by(|a, b| a.0.name.cmp(&b.0.name).then_with(|| a.0.id.cmp(&b.0.id)));
+        matching
+    }
+
+    /// Runs a script's hook export on a blocking thread, queueing its effects;
+    /// returns the export's result and any rejection reason.
+    fn run_blocking(
+        &self,
+        script: ScriptHook,
+        module: Module,
+        event: &str,
+    ) -> Result<(i32, Option<String>), String> {
+        let id = script.id.clone();
+        // Hooks stored before SCRIPT_FUEL was lowered are held to the new limit.
+        let fuel = script
+            .fuel
+            .unwrap_or(self.default_fuel)
+            .min(self.default_fuel);
+        let export = script.hook.export();
+        let host = Host {
+            hook: script,
+            timeseries: self.timeseries.clone(),
+            redaction: RedactionPolicy::new(self.redaction_rules.blocking_read().values().cloned()),
+            effects: Vec::new(),
+            rejection: None,
+            limits: StoreLimitsBuilder::new()
+                .memory_size(MAX_MEMORY_BYTES)
+                .instances(1)
+                .build(),
+        };
+        let mut store = Store::new(&self.engine, host);
+        store.limiter(|host| &mut host.limits);
+        let result = store
+            .set_fuel(fuel)
+            .and_then(|_| self.linker.instantiate(&mut store, &module))
+            .and_then(|instance| call_hook(&mut store, &instance, export, event))
+            .map_err(|e| format!("{:#}", e));
+
+        self.count(&id, |stats| {
+            stats.runs += 1;
+            stats.last_run_at = Some(Utc::now().to_rfc3339());
+            if let Err(e) = &result {
+                stats.failures += 1;
+                stats.last_error = Some(e.clone());
+            }
+        });
+        let host = store.into_data();
+        match &result {
+            // A failed run's effects are dropped with it.
+            Ok(_) => {
+                for effect in host.effects {
+                    let _ = self.effects.send(effect);
+                }
+            }
+            Err(e) => warn!("Script hook {} failed: {}", id, e),
+        }
+        result.map(|code| (code, host.rejection))
+    }
+
+    fn count(&self, id: &str, update: impl FnOnce(&mut HookStats)) {
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        update(stats.entry(id.to_string()).or_default());
+    }
+
+    fn take_receiver(&self) -> Option<mpsc::UnboundedReceiver<Effect>> {
+        self.receiver.lock().ok()?.take()
+    }
+}
+
+/// Copies the event into the script's memory and calls `export(ptr, len)`.
+fn call_hook(
+    store: &mut Store<Host>,
+    instance: &Instance,
+    export: &str,
+    event: &str,
+) -> wasmtime::Result<i32> {
+    let alloc = instance.get_typed_func::<i32, i32>(&mut *store, "alloc")?;
+    let entry = instance.get_typed_func::<(i32, i32), i32>(&mut *store, export)?;
+    let memory = instance
+        .get_memory(&mut *store, "memory")
+        .ok_or_else(|| wasmtime::Error::msg("Module does not export a memory"))?;
+    let len = i32::try_from(event.len())?;
+    let ptr = alloc.call(&mut *store, len)?;
+    memory.write(&mut *store, usize::try_from(ptr)?, event.as_bytes())?;
+    entry.call(&mut *store, (ptr, len))
+}
+
+/// Copies `len` bytes at `ptr` out of the script's memory. The range is checked
+/// against the memory before anything is allocated for it.
+fn read_guest(caller: &mut Caller<'_, Host>, ptr: i32, len: i32) -> wasmtime::Result<String> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmtime::Error::msg("Module does not export a memory"))?;
+    let ptr = usize::try_from(ptr)?;
+    let len = usize::try_from(len)?;
+    let in_bounds = ptr
+        .checked_add(len)
+        .is_some_and(|end| end <= memory.data_size(&*caller));
+    if len > MAX_MEMORY_BYTES || !in_bounds {
+        return Err(wasmtime::Error::msg(format!(
+            "Read of {} bytes at {} is outside the script's memory",
+            len, ptr
+        )));
+    }
+    let mut buf = vec![0u8; len];
+    memory.read(&*caller, ptr, &mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// The `entmoot` imports. Calls return 0 on success and a negative value
+/// when refused.
+fn host_api(engine: &Engine) -> wasmtime::Result<Linker<Host>> {
+    let mut linker = Linker::new(engine);
+    // read_state(key, out, cap) -> len: the latest value of a key as JSON,
+    // redacted as for any non-elevated caller. Returns -1 for an unknown or
+    // hidden key; a length above `cap` means nothing was
+    // written and the script should retry with a larger buffer.
+    linker.func_wrap(
+        "entmoot",
+        "read_state",
+        |mut caller: Caller<'_, Host>,
+         key_ptr: i32,
+         key_len: i32,
+         out_ptr: i32,
+         out_cap: i32|
+         -> wasmtime::Result<i32> {
+            let key = read_guest(&mut caller, key_ptr, key_len)?;
+            let host = caller.data();
+            let latest = host
+                .timeseries
+                .blocking_read()
+                .data
+                .get(&key)
+                .and_then(|buf| buf.back())
+                .and_then(|point| host.redaction.apply(&key, point.value.clone()))
+                .map(|value| value.to_string());
+            let Some(latest) = latest else {
+                return Ok(-1);
+            };
+            let len = i32::try_from(latest.len())?;
+            if len <= out_cap {
+                let memory = caller
+                    .get_export("memory")
+                    .and_then(Extern::into_memory)
+                    .ok_or_else(|| wasmtime::Error::msg("Module does not export a memory"))?;
+                memory.write(&mut caller, usize::try_from(out_ptr)?, latest.as_bytes())?;
+            }
+            Ok(len)
+        },
+    )?;
+    // publish(key, payload): only below PUBLISH_PREFIX.
+    linker.func_wrap(
+        "entmoot",
+        "publish",
+        |mut caller: Caller<'_, Host>,
+         key_ptr: i32,
+         key_len: i32,
+         payload_ptr: i32,
+         payload_len: i32|
+         -> wasmtime::Result<i32> {
+            let key = read_guest(&mut caller, key_ptr, key_len)?;
+            let payload = read_guest(&mut caller, payload_ptr, payload_len)?;
+            let allowed = key_expr_includes(&format!("{}/**", PUBLISH_PREFIX), &key)
+                && !key.contains(['*', '$']);
+            if !allowed || caller.data().effects.len() >= MAX_EFFECTS_PER_RUN {
+                return Ok(-1);
+            }
+            caller
+                .data_mut()
+                .effects
+                .push(Effect::Publish { key, payload });
+            Ok(0)
+        },
+    )?;
+    // raise_alarm(severity, message)
+    linker.func_wrap(
+        "entmoot",
+        "raise_alarm",
+        |mut caller: Caller<'_, Host>,
+         severity_ptr: i32,
+         severity_len: i32,
+         message_ptr: i32,
+         message_len: i32|
+         -> wasmtime::Result<i32> {
+            let severity = read_guest(&mut caller, severity_ptr, severity_len)?;
+            let message = read_guest(&mut caller, message_ptr, message_len)?;
+            if message.trim().is_empty() || caller.data().effects.len() >= MAX_EFFECTS_PER_RUN {
+                return Ok(-1);
+            }
+            let host = caller.data_mut();
+            let hook = Box::new(host.hook.clone());
+            host.effects.push(Effect::RaiseAlarm {
+                hook,
+                severity,
+                message,
+            });
+            Ok(0)
+        },
+    )?;
+    // reject(reason): the reason a pre-command hook gives for rejecting.
+    linker.func_wrap(
+        "entmoot",
+        "reject",
+        |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> wasmtime::Result<()> {
+            let reason = read_guest(&mut caller, ptr, len)?;
+            caller.data_mut().rejection = Some(reason);
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "entmoot",
+        "log",
+        |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> wasmtime::Result<()> {
+            let line = read_guest(&mut caller, ptr, len)?;
+            info!("Script hook {}: {}", caller.data().hook.id, line);
+            Ok(())
+        },
+    )?;
+    Ok(linker)
+}
+
+/// Applies what scripts asked for: publishes, and alarms raised like those
+/// of computed alarm rules.
+pub async fn apply_effects(state: web::Data<AppState>) {
+    let Some(mut receiver) = state.scripts.take_receiver() else {
+        error!("Script hook effects are already being applied");
+        return;
+    };
+    while let Some(effect) = receiver.recv().await {
+        match effect {
+            Effect::Publish { key, payload } => {
+                if let Err(e) = state.chaos.put(&state.zenoh_session, &key, payload).await {
+                    warn!("Failed to publish script output on {}: {}", key, e);
+                }
+            }
+            Effect::RaiseAlarm {
+                hook,
+                severity,
+                message,
+            } => {
+                // Followers mirror the leader's records instead.
+                if state.leadership.is_leader() {
+                    raise_alarm(&state, &hook, severity, message).await;
+                }
+            }
+        }
+    }
+}
+
+async fn raise_alarm(
+    state: &web::Data<AppState>,
+    hook: &ScriptHook,
+    severity: String,
+    message: String,
+) {
+    let source = format!("{}/{}", ALARM_SOURCE_PREFIX, hook.id);
+    let now = Utc::now().to_rfc3339();
+    let (changed, newly_raised) = {
+        let mut alarms = state.alarms.write().await;
+        let existing = alarms
+            .values_mut()
+            .find(|a| a.source == source && a.event == message && a.status != "cleared");
+        let changed = match existing {
+            Some(existing) => {
+                existing.duplicate_count += 1;
+                existing.timestamp = now;
+                (existing.clone(), false)
+            }
+            None => {
+                let alarm = AlarmRecord {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    severity: if severity.trim().is_empty() {
+                        "warning".to_string()
+                    } else {
+                        severity
+                    },
+                    status: "open".to_string(),
+                    source,
+                    event: message,
+                    value: String::new(),
+                    description: format!("Raised by script hook {}", hook.name),
+                    timestamp: now.clone(),
+                    duplicate_count: 1,
+                    parent_id: None,
+                    raised_at: Some(now),
+                    acknowledged_at: None,
+                    cleared_at: None,
+                };
+                info!("Script hook {} raised alarm {}", hook.name, alarm.id);
+                (alarm, true)
+            }
+        };
+        alarms.insert(changed.0.id.clone(), changed.0.clone());
+        pol_handlers::persist_alarms(&state.pol_db_dir, &alarms);
+        changed
+    };
+    alarm_bus::publish(&state.zenoh_session, &state.chaos, &changed).await;
+    if let Err(e) = pol_handlers::upsert_alarm_db(&state.db_client, &changed).await {
+        error!("Failed to persist alarm in Postgres: {}", e);
+    }
+    if newly_raised {
+        state
+            .webhooks
+            .emit(webhook_service::EVENT_ALARM_RAISED, json!(changed))
+            .await;
+        automation::on_alarm_raised(state, &changed).await;
+        soe::capture(state, &changed).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::TimeSeriesPoint;
+
+    const SCRIPT: &str = r#"(module
+      (import "entmoot" "read_state" (func $read_state (param i32 i32 i32 i32) (result i32)))
+      (import "entmoot" "publish" (func $publish (param i32 i32 i32 i32) (result i32)))
+      (import "entmoot" "reject" (func $reject (param i32 i32)))
+      (memory (export "memory") 1)
+      (data (i32.const 0) "plant/tank/level")
+      (data (i32.const 32) "entmoot/scripts/tank")
+      (data (i32.const 64) "entmoot/habitat/nodes/n1/pea/p1/services/Fill/command")
+      (data (i32.const 128) "Tank too full")
+      (func (export "alloc") (param i32) (result i32) i32.const 1024)
+      ;; Publishes the level read back, and tries a command topic.
+      (func (export "on_sample") (param i32 i32) (result i32)
+        (drop (call $read_state (i32.const 0) (i32.const 16) (i32.const 512) (i32.const 64)))
+        (drop (call $publish (i32.const 32) (i32.const 20) (i32.const 512) (i32.const 2)))
+        (call $publish (i32.const 64) (i32.const 54) (i32.const 512) (i32.const 2)))
+      ;; Rejects while the level reads back as two digits or more.
+      (func (export "pre_command") (param i32 i32) (result i32)
+        (if (i32.ge_s (call $read_state (i32.const 0) (i32.const 16) (i32.const 512) (i32.const 64)) (i32.const 2))
+          (then (call $reject (i32.const 128) (i32.const 13)) (return (i32.const 1))))
+        (i32.const 0))
+      (func (export "on_alarm") (param i32 i32) (result i32) (loop $spin (br $spin)) (i32.const 0)))"#;
+
+    fn hook(hook: Hook, key_pattern: Option<&str>) -> ScriptHook {
+        ScriptHook {
+            id: format!("{:?}", hook),
+            name: format!("{:?}", hook),
+            hook,
+            key_pattern: key_pattern.map(str::to_string),
+            wasm: None,
+            wat: Some(SCRIPT.to_string()),
+            fuel: Some(100_000),
+            enabled: true,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn scripts_run_sandboxed_at_their_hooks() {
+        let timeseries = Arc::new(RwLock::new(TimeSeriesStore::new(10)));
+        let redaction_rules = Arc::new(RwLock::new(HashMap::new()));
+        let scripts =
+            ScriptHooks::from_env(HashMap::new(), timeseries.clone(), redaction_rules.clone())
+                .unwrap();
+        let mut effects = scripts.take_receiver().unwrap();
+        for script in [
+            hook(Hook::OnSample, Some("plant/**")),
+            hook(Hook::PreCommand, Some("p1/*")),
+            hook(Hook::OnAlarm, None),
+        ] {
+            scripts.upsert(script).await.unwrap();
+        }
+        let mut broken = hook(Hook::OnSample, None);
+        broken.wat = Some("(module)".to_string());
+        assert_eq!(
+            scripts.upsert(broken).await.unwrap_err(),
+            "Module does not export 'memory'"
+        );
+        let mut greedy = hook(Hook::OnSample, None);
+        greedy.fuel = Some(u64::MAX);
+        assert!(scripts.upsert(greedy).await.is_err());
+
+        timeseries.write().await.insert_point(
+            "plant/tank/level".to_string(),
+            TimeSeriesPoint {
+                producer: None,
+                timestamp_ms: 0,
+                value: json!(42),
+            },
+        );
+        scripts.on_sample("plant/tank/level", &json!(42), 0).await;
+        match effects.recv().await.unwrap() {
+            Effect::Publish { key, payload } => {
+                assert_eq!(
+                    (key.as_str(), payload.as_str()),
+                    ("entmoot/scripts/tank", "42")
+                );
+            }
+            other => panic!("unexpected effect {:?}", other),
+        }
+        // The command topic is outside the publish prefix.
+        assert!(effects.try_recv().is_err());
+        assert_eq!(scripts.stats("OnSample").runs, 1);
+
+        assert_eq!(
+            scripts
+                .pre_command("p1", "Fill", ServiceCommand::Start, None)
+                .await,
+            Err("Tank too full".to_string())
+        );
+        assert!(scripts
+            .pre_command("p2", "Fill", ServiceCommand::Start, None)
+            .await
+            .is_ok());
+
+        // Keys hidden by a redaction rule read back as unknown.
+        redaction_rules.write().await.insert(
+            "r1".to_string(),
+            RedactionRule {
+                id: "r1".to_string(),
+                name: "Tank levels".to_string(),
+                key_pattern: "plant/tank/**".to_string(),
+                fields: Vec::new(),
+                enabled: true,
+                created_at: String::new(),
+                updated_at: String::new(),
+            },
+        );
+        assert!(scripts
+            .pre_command("p1", "Fill", ServiceCommand::Start, None)
+            .await
+            .is_ok());
+
+        // A script that never returns runs out of fuel.
+        let looping = scripts.get("OnAlarm").await.unwrap();
+        let module = scripts.compile(&looping).unwrap();
+        let result = tokio::task::block_in_place(|| scripts.run_blocking(looping, module, "{}"));
+        assert!(result.is_err());
+        assert_eq!(scripts.stats("OnAlarm").failures, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reads_outside_guest_memory_fail_the_run() {
+        let timeseries = Arc::new(RwLock::new(TimeSeriesStore::new(10)));
+        let scripts =
+            ScriptHooks::from_env(HashMap::new(), timeseries, Default::default()).unwrap();
+        let mut oversized = hook(Hook::OnSample, None);
+        oversized.wat = Some(
+            r#"(module
+              (import "entmoot" "publish" (func $publish (param i32 i32 i32 i32) (result i32)))
+              (memory (export "memory") 1)
+              (func (export "alloc") (param i32) (result i32) i32.const 1024)
+              (func (export "on_sample") (param i32 i32) (result i32)
+                (call $publish (i32.const 0) (i32.const 2147483647) (i32.const 0) (i32.const 1))))"#
+                .to_string(),
+        );
+        let module = scripts.compile(&oversized).unwrap();
+        let error = tokio::task::block_in_place(|| scripts.run_blocking(oversized, module, "{}"))
+            .unwrap_err();
+        assert!(error.contains("outside the script's memory"), "{}", error);
+    }
+}
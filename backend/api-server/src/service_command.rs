@@ -0,0 +1,378 @@
+use actix_web::HttpResponse;
+use chrono::Utc;
+use shared::domain::interlock::{InterlockOverride, InterlockViolation};
+use shared::mtp::{RecipeParameterValue, ServiceCommand, ServiceState};
+use tokio::sync::RwLock;
+use tracing::info;
+use zenoh::Session;
+
+use crate::approval_handlers;
+use crate::approval_service::{GuardedAction, PendingApproval};
+use crate::audit;
+use crate::chaos::Chaos;
+use crate::interlock_service;
+use crate::operator_presence::Claim;
+use crate::state::{AppState, TimeSeriesStore};
+use crate::state_analytics;
+
+/// Who sends a service command, for the checks and records it goes through.
+pub struct CommandSource<'a> {
+    pub actor_id: Option<String>,
+    /// Recorded as the source of interlock violations, e.g. `command_service`.
+    pub origin: &'a str,
+    /// Lets a permitted actor pass the interlocks that would block the command.
+    pub interlock_override: Option<&'a InterlockOverride>,
+}
+
+pub enum CommandOutcome {
+    Sent,
+    /// Guarded by an approval policy; sent once a second user confirms it.
+    PendingApproval(Box<PendingApproval>),
+}
+
+/// Why a service command was not sent.
+pub enum CommandRefusal {
+    NotFound,
+    /// The PEA is claimed by another operator.
+    Claimed(Box<Claim>),
+    /// The PackML state machine does not allow the command from the last
+    /// reported state.
+    NotAllowed {
+        command: ServiceCommand,
+        current: ServiceState,
+        allowed: Vec<ServiceCommand>,
+    },
+    Interlocked(Vec<InterlockViolation>),
+    ScriptRejected(String),
+    PublishFailed(String),
+}
+
+impl CommandRefusal {
+    pub fn message(&self) -> String {
+        match self {
+            CommandRefusal::NotFound => "PEA or service not found".to_string(),
+            CommandRefusal::Claimed(claim) => {
+                format!("{} is owned by {}", claim.area, claim.actor_id)
+            }
+            CommandRefusal::NotAllowed {
+                command, current, ..
+            } => format!("{:?} is not allowed in state {:?}", command, current),
+            CommandRefusal::Interlocked(_) => "Command blocked by interlock".to_string(),
+            CommandRefusal::ScriptRejected(reason) => {
+                format!("Command rejected by script hook: {}", reason)
+            }
+            CommandRefusal::PublishFailed(e) => format!("Failed to publish command: {}", e),
+        }
+    }
+
+    pub fn response(&self) -> HttpResponse {
+        match self {
+            CommandRefusal::NotFound => {
+                HttpResponse::NotFound().json(serde_json::json!({"error": self.message()}))
+            }
+            CommandRefusal::Claimed(claim) => HttpResponse::Conflict().json(serde_json::json!({
+                "error": self.message(),
+                "claim": claim,
+            })),
+            CommandRefusal::NotAllowed {
+                current, allowed, ..
+            } => HttpResponse::Conflict().json(serde_json::json!({
+                "error": self.message(),
+                "current_state": current,
+                "allowed_commands": allowed,
+            })),
+            CommandRefusal::Interlocked(violations) => {
+                HttpResponse::Conflict().json(serde_json::json!({
+                    "error": self.message(),
+                    "violations": violations,
+                }))
+            }
+            CommandRefusal::ScriptRejected(reason) => {
+                HttpResponse::Conflict().json(serde_json::json!({
+                    "error": "Command rejected by script hook",
+                    "reason": reason,
+                }))
+            }
+            CommandRefusal::PublishFailed(_) => HttpResponse::InternalServerError()
+                .json(serde_json::json!({"error": self.message()})),
+        }
+    }
+}
+
+/// Sends a service command after every check a command must pass: operator
+/// claims, interlocks, script hooks and approval policies, then the PackML
+/// state machine in `put`. The API, automation and recipe steps all go
+/// through here.
+pub async fn send(
+    state: &AppState,
+    source: CommandSource<'_>,
+    pea_id: &str,
+    service_tag: &str,
+    command: ServiceCommand,
+    procedure_id: Option<u32>,
+    parameters: Option<&[RecipeParameterValue]>,
+) -> Result<CommandOutcome, CommandRefusal> {
+    let exists = state
+        .pea_configs
+        .read()
+        .await
+        .get(pea_id)
+        .is_some_and(|config| config.services.iter().any(|s| s.tag == service_tag));
+    if !exists {
+        return Err(CommandRefusal::NotFound);
+    }
+
+    if let Some(claim) = state
+        .presence
+        .foreign_pea_claim(pea_id, source.actor_id.as_deref(), Utc::now())
+        .await
+    {
+        return Err(CommandRefusal::Claimed(Box::new(claim)));
+    }
+
+    let hits = interlock_service::check_command(
+        &state.interlocks,
+        &state.timeseries,
+        pea_id,
+        service_tag,
+        command,
+    )
+    .await;
+    if !hits.is_empty() {
+        let override_request = source
+            .interlock_override
+            .filter(|o| interlock_service::override_permitted(&hits, &o.actor_class));
+        let violations = interlock_service::violation_records(
+            &hits,
+            pea_id,
+            service_tag,
+            command,
+            source.origin,
+            override_request,
+        );
+        interlock_service::record_violations(
+            &state.interlock_violations,
+            &state.zenoh_session,
+            violations.clone(),
+        )
+        .await;
+
+        if override_request.is_none() {
+            return Err(CommandRefusal::Interlocked(violations));
+        }
+        info!(
+            "Interlock override for {}/{} {:?} by {:?}",
+            pea_id,
+            service_tag,
+            command,
+            override_request.map(|o| &o.actor_id)
+        );
+    }
+
+    state
+        .scripts
+        .pre_command(pea_id, service_tag, command, procedure_id)
+        .await
+        .map_err(CommandRefusal::ScriptRejected)?;
+
+    if let Some(approval) = approval_handlers::park_for_approval(
+        state,
+        source.actor_id.clone(),
+        pea_id,
+        Some(service_tag),
+        procedure_id,
+        parameters,
+        GuardedAction::ServiceCommand { command },
+    )
+    .await
+    {
+        return Ok(CommandOutcome::PendingApproval(Box::new(approval)));
+    }
+
+    publish(
+        state,
+        source.actor_id,
+        source.origin,
+        pea_id,
+        service_tag,
+        command,
+        procedure_id,
+        parameters,
+    )
+    .await?;
+    Ok(CommandOutcome::Sent)
+}
+
+/// Publishes a service command through `put` and records it in the audit log
+/// with its `origin`. Confirmed approvals come straight here, as `send`
+/// checked them when they were made.
+#[allow(clippy::too_many_arguments)]
+pub async fn publish(
+    state: &AppState,
+    actor_id: Option<String>,
+    origin: &str,
+    pea_id: &str,
+    service_tag: &str,
+    command: ServiceCommand,
+    procedure_id: Option<u32>,
+    parameters: Option<&[RecipeParameterValue]>,
+) -> Result<(), CommandRefusal> {
+    put(
+        &state.chaos,
+        &state.zenoh_session,
+        &state.timeseries,
+        pea_id,
+        service_tag,
+        command,
+        procedure_id,
+        parameters,
+    )
+    .await?;
+    audit::record(
+        state,
+        actor_id,
+        audit::ENTITY_PEA,
+        pea_id,
+        audit::ACTION_COMMAND,
+        serde_json::json!({
+            "service_tag": service_tag,
+            "command": command,
+            "procedure_id": procedure_id,
+            "cause": origin,
+        }),
+    )
+    .await;
+    Ok(())
+}
+
+/// Refuses a command the PackML state machine does not allow from the
+/// service's last reported state. Services that have not reported a state
+/// yet are not checked.
+pub fn check_transition(
+    timeseries: &TimeSeriesStore,
+    pea_id: &str,
+    service_tag: &str,
+    command: ServiceCommand,
+) -> Result<(), CommandRefusal> {
+    let Some(current) = state_analytics::current_service_state(timeseries, pea_id, service_tag)
+    else {
+        return Ok(());
+    };
+    let allowed = current.allowed_commands();
+    if allowed.contains(&command) {
+        return Ok(());
+    }
+    Err(CommandRefusal::NotAllowed {
+        command,
+        current,
+        allowed,
+    })
+}
+
+/// Publishes a service command on the PEA's command topic, unless the PackML
+/// state machine forbids it. Every command ends here, including recipe steps.
+#[allow(clippy::too_many_arguments)]
+pub async fn put(
+    chaos: &Chaos,
+    session: &Session,
+    timeseries: &RwLock<TimeSeriesStore>,
+    pea_id: &str,
+    service_tag: &str,
+    command: ServiceCommand,
+    procedure_id: Option<u32>,
+    parameters: Option<&[RecipeParameterValue]>,
+) -> Result<(), CommandRefusal> {
+    check_transition(&*timeseries.read().await, pea_id, service_tag, command)?;
+    let mut payload = serde_json::json!({
+        "command": command,
+        "command_code": command.code(),
+        "procedure_id": procedure_id,
+        "timestamp": Utc::now().to_rfc3339(),
+    });
+    if let Some(parameters) = parameters {
+        payload["parameters"] = serde_json::json!(parameters);
+    }
+    let topic = shared::mtp::topics::pea_service_command(pea_id, service_tag);
+    chaos
+        .put(session, &topic, payload.to_string())
+        .await
+        .map_err(|e| CommandRefusal::PublishFailed(e.to_string()))
+}
+
+/// The API's answer to a sent or parked command.
+pub fn accepted(outcome: CommandOutcome, pea_id: &str, service_tag: &str) -> HttpResponse {
+    match outcome {
+        CommandOutcome::Sent => HttpResponse::Accepted().json(serde_json::json!({
+            "status": "command_sent",
+            "pea_id": pea_id,
+            "service_tag": service_tag,
+        })),
+        CommandOutcome::PendingApproval(approval) => approval_handlers::pending_response(&approval),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{status_message, SERVICE_TAG};
+
+    fn reported(state: ServiceState) -> RwLock<TimeSeriesStore> {
+        let mut timeseries = TimeSeriesStore::new(10);
+        timeseries.insert(
+            shared::mtp::topics::pea_status("reactor"),
+            status_message("reactor", &[(SERVICE_TAG, state)]),
+            0,
+        );
+        RwLock::new(timeseries)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn illegal_transitions_are_never_published() {
+        let mut config = zenoh::Config::default();
+        config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .unwrap();
+        let session = zenoh::open(config).await.unwrap();
+        let subscriber = session
+            .declare_subscriber(shared::mtp::topics::pea_service_command(
+                "reactor",
+                SERVICE_TAG,
+            ))
+            .await
+            .unwrap();
+        let chaos = Chaos::from_env();
+        let timeseries = reported(ServiceState::Idle);
+
+        // As a recipe step or an approval would send it, without the API.
+        let refused = put(
+            &chaos,
+            &session,
+            &timeseries,
+            "reactor",
+            SERVICE_TAG,
+            ServiceCommand::Complete,
+            None,
+            None,
+        )
+        .await;
+        assert!(matches!(refused, Err(CommandRefusal::NotAllowed { .. })));
+        assert!(subscriber.try_recv().unwrap().is_none());
+
+        let sent = put(
+            &chaos,
+            &session,
+            &timeseries,
+            "reactor",
+            SERVICE_TAG,
+            ServiceCommand::Start,
+            None,
+            None,
+        )
+        .await;
+        assert!(sent.is_ok());
+        let sample = subscriber.recv_async().await.unwrap();
+        let payload: serde_json::Value =
+            serde_json::from_slice(&sample.payload().to_bytes()).unwrap();
+        assert_eq!(payload["command"], serde_json::json!(ServiceCommand::Start));
+    }
+}
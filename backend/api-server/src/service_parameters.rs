@@ -0,0 +1,213 @@
+use serde::Serialize;
+use shared::mtp::{ServiceConfig, ServiceParameter, TagMapping};
+use std::collections::BTreeMap;
+
+use crate::pea_validation::FieldError;
+
+/// One checked parameter value, as published on `pea_service_parameters`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ParameterWrite {
+    pub tag: String,
+    pub value: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag_mapping: Option<TagMapping>,
+}
+
+/// Checks `values` against the parameters a service offers for a procedure:
+/// the service's configuration parameters plus those of the procedure, or of
+/// the default procedure when none is given. Binary parameters also accept
+/// their state texts; values are returned in their wire form.
+pub fn validate(
+    service: &ServiceConfig,
+    procedure_id: Option<u32>,
+    values: &BTreeMap<String, serde_json::Value>,
+) -> Result<Vec<ParameterWrite>, Vec<FieldError>> {
+    let procedure = match procedure_id {
+        Some(id) => match service.procedures.iter().find(|p| p.id == id) {
+            Some(procedure) => Some(procedure),
+            None => {
+                return Err(vec![FieldError {
+                    path: "procedure_id".to_string(),
+                    message: format!("Service '{}' has no procedure {}", service.tag, id),
+                }])
+            }
+        },
+        None => service.procedures.iter().find(|p| p.is_default),
+    };
+    let offered: Vec<&ServiceParameter> = service
+        .config_parameters
+        .iter()
+        .chain(procedure.into_iter().flat_map(|p| p.parameters.iter()))
+        .collect();
+
+    let mut writes = Vec::new();
+    let mut errors = Vec::new();
+    for (tag, value) in values {
+        let path = format!("values.{}", tag);
+        let Some(parameter) = offered.iter().find(|p| parameter_tag(p) == tag) else {
+            errors.push(FieldError {
+                path,
+                message: format!("Unknown parameter '{}'", tag),
+            });
+            continue;
+        };
+        match check_value(parameter, value) {
+            Ok(value) => writes.push(ParameterWrite {
+                tag: tag.clone(),
+                value,
+                tag_mapping: parameter_mapping(parameter).cloned(),
+            }),
+            Err(message) => errors.push(FieldError { path, message }),
+        }
+    }
+    if errors.is_empty() {
+        Ok(writes)
+    } else {
+        Err(errors)
+    }
+}
+
+fn check_value(
+    parameter: &ServiceParameter,
+    value: &serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    match parameter {
+        ServiceParameter::Analog(p) => {
+            let v = value.as_f64().ok_or("Expected a number")?;
+            if v < p.v_min || v > p.v_max {
+                return Err(format!("{} is outside {}..{}", v, p.v_min, p.v_max));
+            }
+            Ok(serde_json::json!(v))
+        }
+        ServiceParameter::DInt(p) => {
+            let v = value.as_i64().ok_or("Expected an integer")?;
+            if v < p.v_min || v > p.v_max {
+                return Err(format!("{} is outside {}..{}", v, p.v_min, p.v_max));
+            }
+            Ok(serde_json::json!(v))
+        }
+        ServiceParameter::Binary(p) => match value {
+            serde_json::Value::Bool(v) => Ok(serde_json::json!(v)),
+            serde_json::Value::String(text) if *text == p.v_state0 => Ok(serde_json::json!(false)),
+            serde_json::Value::String(text) if *text == p.v_state1 => Ok(serde_json::json!(true)),
+            _ => Err(format!(
+                "Expected true, false, '{}' or '{}'",
+                p.v_state0, p.v_state1
+            )),
+        },
+        ServiceParameter::StringParam(_) => match value {
+            serde_json::Value::String(_) => Ok(value.clone()),
+            _ => Err("Expected a string".to_string()),
+        },
+    }
+}
+
+fn parameter_tag(parameter: &ServiceParameter) -> &str {
+    match parameter {
+        ServiceParameter::Analog(p) => &p.tag,
+        ServiceParameter::Binary(p) => &p.tag,
+        ServiceParameter::DInt(p) => &p.tag,
+        ServiceParameter::StringParam(p) => &p.tag,
+    }
+}
+
+fn parameter_mapping(parameter: &ServiceParameter) -> Option<&TagMapping> {
+    match parameter {
+        ServiceParameter::Analog(p) => p.tag_mapping.as_ref(),
+        ServiceParameter::Binary(p) => p.tag_mapping.as_ref(),
+        ServiceParameter::DInt(p) => p.tag_mapping.as_ref(),
+        ServiceParameter::StringParam(p) => p.tag_mapping.as_ref(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> ServiceConfig {
+        serde_json::from_value(serde_json::json!({
+            "tag": "Heat",
+            "name": "Heat",
+            "description": "",
+            "config_parameters": [{
+                "type": "StringParam", "tag": "Batch", "name": "Batch",
+                "v_default": "", "tag_mapping": null
+            }],
+            "procedures": [{
+                "id": 1, "name": "Ramp", "is_self_completing": false, "is_default": true,
+                "parameters": [
+                    {
+                        "type": "Analog", "tag": "TIC_SP", "name": "Setpoint", "unit": "°C",
+                        "v_scl_min": 0.0, "v_scl_max": 150.0, "v_min": 20.0, "v_max": 95.0,
+                        "v_default": 60.0, "tag_mapping": null
+                    },
+                    {
+                        "type": "Binary", "tag": "Stir", "name": "Stirrer",
+                        "v_state0": "Off", "v_state1": "On", "v_default": false,
+                        "tag_mapping": null
+                    }
+                ],
+                "process_value_outs": [], "report_values": []
+            }, {
+                "id": 2, "name": "Hold", "is_self_completing": false, "is_default": false,
+                "parameters": [{
+                    "type": "DInt", "tag": "Minutes", "name": "Minutes", "unit": "min",
+                    "v_scl_min": 0, "v_scl_max": 600, "v_min": 1, "v_max": 240,
+                    "v_default": 30, "tag_mapping": null
+                }],
+                "process_value_outs": [], "report_values": []
+            }]
+        }))
+        .unwrap()
+    }
+
+    fn values(values: serde_json::Value) -> BTreeMap<String, serde_json::Value> {
+        serde_json::from_value(values).unwrap()
+    }
+
+    #[test]
+    fn values_are_checked_against_the_procedure_parameters() {
+        let writes = validate(
+            &service(),
+            None,
+            &values(serde_json::json!({"TIC_SP": 72.5, "Stir": "On", "Batch": "B-17"})),
+        )
+        .unwrap();
+        let written: Vec<(&str, &serde_json::Value)> =
+            writes.iter().map(|w| (w.tag.as_str(), &w.value)).collect();
+        assert_eq!(
+            written,
+            vec![
+                ("Batch", &serde_json::json!("B-17")),
+                ("Stir", &serde_json::json!(true)),
+                ("TIC_SP", &serde_json::json!(72.5)),
+            ]
+        );
+
+        let errors = validate(
+            &service(),
+            None,
+            &values(serde_json::json!({"TIC_SP": 120, "Stir": "Fast", "Minutes": 10})),
+        )
+        .unwrap_err();
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["values.Minutes", "values.Stir", "values.TIC_SP"]
+        );
+
+        assert!(validate(
+            &service(),
+            Some(2),
+            &values(serde_json::json!({"Minutes": 10}))
+        )
+        .is_ok());
+        assert!(validate(
+            &service(),
+            Some(2),
+            &values(serde_json::json!({"Minutes": 2.5}))
+        )
+        .is_err());
+        assert!(validate(&service(), Some(9), &BTreeMap::new()).is_err());
+    }
+}
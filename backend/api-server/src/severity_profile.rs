@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// How clients present one alarm severity.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SeverityLevel {
+    /// Severity string as carried by alarms, e.g. "critical"
+    pub name: String,
+    #[serde(default)]
+    pub label: String,
+    /// Display color as `#rrggbb`
+    pub color: String,
+    /// Whether clients sound an annunciator for new alarms of this severity
+    #[serde(default)]
+    pub audible: bool,
+    /// Other severity strings shown as this level, e.g. "medium" for "warning"
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+/// Severities of this deployment, least urgent first.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SeverityProfile {
+    pub name: String,
+    pub levels: Vec<SeverityLevel>,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl Default for SeverityProfile {
+    /// The severities used until a profile is saved.
+    fn default() -> Self {
+        let level =
+            |name: &str, label: &str, color: &str, audible: bool, aliases: &[&str]| SeverityLevel {
+                name: name.to_string(),
+                label: label.to_string(),
+                color: color.to_string(),
+                audible,
+                aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+            };
+        Self {
+            name: "default".to_string(),
+            levels: vec![
+                level("info", "Info", "#3b82f6", false, &[]),
+                level("low", "Low", "#22c55e", false, &[]),
+                level("warning", "Warning", "#f59e0b", false, &["medium"]),
+                level("high", "High", "#f97316", true, &[]),
+                level("critical", "Critical", "#dc2626", true, &[]),
+            ],
+            updated_at: String::new(),
+        }
+    }
+}
+
+impl SeverityProfile {
+    /// Lowercases names and aliases and fills in missing labels.
+    pub fn normalize(&mut self) {
+        for level in &mut self.levels {
+            level.name = level.name.trim().to_ascii_lowercase();
+            level.aliases = level
+                .aliases
+                .iter()
+                .map(|alias| alias.trim().to_ascii_lowercase())
+                .filter(|alias| !alias.is_empty())
+                .collect();
+            if level.label.trim().is_empty() {
+                level.label = level.name.clone();
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name is required".to_string());
+        }
+        if self.levels.is_empty() {
+            return Err("At least one severity level is required".to_string());
+        }
+        let mut seen = HashSet::new();
+        for level in &self.levels {
+            if level.name.is_empty() {
+                return Err("Severity levels need a name".to_string());
+            }
+            if !is_hex_color(&level.color) {
+                return Err(format!(
+                    "Color of '{}' must be #rrggbb, got '{}'",
+                    level.name, level.color
+                ));
+            }
+            for name in std::iter::once(&level.name).chain(&level.aliases) {
+                if !seen.insert(name.as_str()) {
+                    return Err(format!("Severity '{}' is defined twice", name));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The level `severity` is shown as, by name or alias.
+    pub fn level(&self, severity: &str) -> Option<&SeverityLevel> {
+        let severity = severity.to_ascii_lowercase();
+        self.levels
+            .iter()
+            .find(|level| level.name == severity || level.aliases.contains(&severity))
+    }
+
+    /// Position of `severity` in the profile, 0 being least urgent;
+    /// unknown severities rank lowest.
+    pub fn priority(&self, severity: &str) -> usize {
+        let severity = severity.to_ascii_lowercase();
+        self.levels
+            .iter()
+            .position(|level| level.name == severity || level.aliases.contains(&severity))
+            .unwrap_or(0)
+    }
+
+    /// Whether `severity` ranks at or above `min`.
+    pub fn at_least(&self, severity: &str, min: &str) -> bool {
+        self.priority(severity) >= self.priority(min)
+    }
+
+    /// Whether `severity` is the profile's most urgent level. Such alarms trip
+    /// captures, group downstream alarms and bypass notification delays.
+    pub fn is_critical(&self, severity: &str) -> bool {
+        self.level(severity).is_some() && self.priority(severity) + 1 == self.levels.len()
+    }
+}
+
+fn is_hex_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_profile_ranks_from_info_to_critical() {
+        let profile = SeverityProfile::default();
+        assert!(profile.validate().is_ok());
+        for (rank, severity) in ["info", "low", "warning", "high", "critical"]
+            .into_iter()
+            .enumerate()
+        {
+            assert_eq!(profile.priority(severity), rank, "{}", severity);
+        }
+        assert_eq!(profile.priority("medium"), profile.priority("warning"));
+        assert_eq!(profile.priority("unknown"), 0);
+        assert!(profile.at_least("CRITICAL", "high"));
+        assert!(!profile.at_least("low", "warning"));
+        assert!(profile.is_critical("critical"));
+        assert!(!profile.is_critical("high"));
+        assert_eq!(
+            profile.level("MEDIUM").map(|l| l.name.as_str()),
+            Some("warning")
+        );
+    }
+
+    #[test]
+    fn custom_profiles_decide_the_ranking() {
+        let mut profile = SeverityProfile::default();
+        profile.levels.truncate(3);
+        for (level, name) in profile.levels.iter_mut().zip(["minor", "major", "fatal"]) {
+            level.name = name.to_string();
+            level.aliases.clear();
+        }
+        assert!(profile.at_least("fatal", "major"));
+        assert!(profile.is_critical("FATAL"));
+        assert!(!profile.is_critical("critical"));
+        assert!(profile.level("critical").is_none());
+    }
+
+    #[test]
+    fn profiles_reject_bad_colors_and_duplicates() {
+        let mut profile = SeverityProfile::default();
+        profile.levels[0].color = "blue".to_string();
+        assert!(profile.validate().is_err());
+
+        let mut profile = SeverityProfile::default();
+        profile.levels[1].aliases.push("critical".to_string());
+        assert!(profile.validate().is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use tracing::{info, warn};
+
+use crate::request_context::CallerContext;
+use crate::runtime_store;
+use crate::severity_profile::SeverityProfile;
+use crate::state::AppState;
+use crate::tenancy;
+
+/// Clients subscribe here to pick up profile changes without polling.
+pub const SEVERITY_PROFILE_TOPIC: &str = "entmoot/pol/severity-profile";
+
+/// GET /severity-profile — severities, their order, colors and annunciation
+pub async fn get_severity_profile(state: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(&*state.severity_profile.read().await)
+}
+
+/// PUT /severity-profile — replaces the deployment's severity profile
+pub async fn put_severity_profile(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<SeverityProfile>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    let mut profile = body.into_inner();
+    profile.normalize();
+    if let Err(err) = profile.validate() {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": err}));
+    }
+    profile.updated_at = Utc::now().to_rfc3339();
+    runtime_store::persist_json_file(&state.severity_profile_path, &profile);
+    *state.severity_profile.write().await = profile.clone();
+    publish(&state, &profile).await;
+    HttpResponse::Ok().json(profile)
+}
+
+/// DELETE /severity-profile — back to the built-in severities
+pub async fn reset_severity_profile(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_elevated(&state, &req) {
+        return response;
+    }
+    if let Err(err) = std::fs::remove_file(&state.severity_profile_path) {
+        if err.kind() != std::io::ErrorKind::NotFound {
+            tracing::error!("Failed to delete {}: {}", state.severity_profile_path, err);
+        }
+    }
+    let profile = SeverityProfile::default();
+    *state.severity_profile.write().await = profile.clone();
+    publish(&state, &profile).await;
+    HttpResponse::Ok().json(profile)
+}
+
+async fn publish(state: &AppState, profile: &SeverityProfile) {
+    let payload = serde_json::to_string(profile).unwrap_or_else(|_| "{}".to_string());
+    if let Err(e) = state
+        .zenoh_session
+        .put(SEVERITY_PROFILE_TOPIC, payload)
+        .await
+    {
+        warn!("Failed to publish severity profile: {}", e);
+    }
+}
+
+fn reject_unless_elevated(state: &AppState, req: &HttpRequest) -> Option<HttpResponse> {
+    let ctx = CallerContext::from_request(req);
+    if tenancy::is_deployment_admin(state, req) {
+        info!(
+            "Severity profile changed by {}",
+            ctx.actor_id.as_deref().unwrap_or("unknown actor")
+        );
+        return None;
+    }
+    Some(HttpResponse::Forbidden().json(serde_json::json!({
+        "error": "Changing the severity profile requires the deployment admin token"
+    })))
+}
@@ -0,0 +1,294 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::info;
+
+use crate::alarm_grouping;
+use crate::runtime_store;
+use crate::state::{AlarmRecord, AppState, TimeSeriesStore};
+
+/// Seconds of history captured before a Critical alarm.
+pub const DEFAULT_WINDOW_S: i64 = 30;
+
+/// Upper bound on sequence-of-events records kept; the oldest are dropped first.
+pub const MAX_RECORDS: usize = 500;
+
+/// One raw point of a related key inside the capture window.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SoeSample {
+    pub timestamp_ms: i64,
+    pub value: serde_json::Value,
+}
+
+/// A related key changing value inside the capture window.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SoeEvent {
+    pub key: String,
+    pub timestamp_ms: i64,
+    pub value: serde_json::Value,
+    /// Value before the change; `None` when the key had no earlier point.
+    pub previous: Option<serde_json::Value>,
+}
+
+/// Keys of a PEA as they stood in the seconds before one of its alarms
+/// tripped, keyed by the alarm id.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SoeRecord {
+    pub alarm_id: String,
+    pub pea_id: String,
+    pub source: String,
+    pub event: String,
+    pub severity: String,
+    pub tripped_at: String,
+    pub window_s: i64,
+    /// The earliest change in the window: the signal that moved first.
+    pub first_out: Option<SoeEvent>,
+    /// Value changes of every related key, in time order.
+    pub events: Vec<SoeEvent>,
+    /// Every point of every related key in the window, at full resolution.
+    pub samples: BTreeMap<String, Vec<SoeSample>>,
+    pub captured_at: String,
+}
+
+impl SoeRecord {
+    /// The record without its raw samples, as listed by GET /soe.
+    pub fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "alarm_id": self.alarm_id,
+            "pea_id": self.pea_id,
+            "source": self.source,
+            "event": self.event,
+            "severity": self.severity,
+            "tripped_at": self.tripped_at,
+            "window_s": self.window_s,
+            "first_out": self.first_out,
+            "event_count": self.events.len(),
+            "keys": self.samples.keys().collect::<Vec<_>>(),
+            "captured_at": self.captured_at,
+        })
+    }
+}
+
+/// Builds the record for an alarm of `pea_id` tripping at `tripped_at` from
+/// the points buffered for that PEA's keys; `None` when no key of the PEA has
+/// points in the window.
+pub fn build_record(
+    alarm: &AlarmRecord,
+    pea_id: &str,
+    timeseries: &TimeSeriesStore,
+    tripped_at: DateTime<Utc>,
+    window: Duration,
+) -> Option<SoeRecord> {
+    let end_ms = tripped_at.timestamp_millis();
+    let start_ms = end_ms - window.num_milliseconds();
+
+    let mut samples = BTreeMap::new();
+    let mut events = Vec::new();
+    for (key, buf) in &timeseries.data {
+        if alarm_grouping::pea_of(key) != pea_id || key == &alarm.source {
+            continue;
+        }
+        let points: Vec<SoeSample> = buf
+            .iter()
+            .filter(|p| p.timestamp_ms >= start_ms && p.timestamp_ms <= end_ms)
+            .map(|p| SoeSample {
+                timestamp_ms: p.timestamp_ms,
+                value: p.value.clone(),
+            })
+            .collect();
+        if points.is_empty() {
+            continue;
+        }
+        // The last point before the window is the baseline a first change is measured against.
+        let mut previous = buf
+            .iter()
+            .take_while(|p| p.timestamp_ms < start_ms)
+            .last()
+            .map(|p| p.value.clone());
+        for point in &points {
+            if previous.as_ref() != Some(&point.value) {
+                events.push(SoeEvent {
+                    key: key.clone(),
+                    timestamp_ms: point.timestamp_ms,
+                    value: point.value.clone(),
+                    previous: previous.clone(),
+                });
+            }
+            previous = Some(point.value.clone());
+        }
+        samples.insert(key.clone(), points);
+    }
+    if samples.is_empty() {
+        return None;
+    }
+    events.sort_by(|a, b| {
+        a.timestamp_ms
+            .cmp(&b.timestamp_ms)
+            .then_with(|| a.key.cmp(&b.key))
+    });
+
+    Some(SoeRecord {
+        alarm_id: alarm.id.clone(),
+        pea_id: pea_id.to_string(),
+        source: alarm.source.clone(),
+        event: alarm.event.clone(),
+        severity: alarm.severity.clone(),
+        tripped_at: tripped_at.to_rfc3339(),
+        window_s: window.num_seconds(),
+        first_out: events.first().cloned(),
+        events,
+        samples,
+        captured_at: Utc::now().to_rfc3339(),
+    })
+}
+
+/// Sequence-of-events records captured for tripped alarms, persisted one file per alarm.
+#[derive(Clone)]
+pub struct SequenceOfEvents {
+    records: Arc<RwLock<HashMap<String, SoeRecord>>>,
+    window: Duration,
+    dir: String,
+}
+
+impl SequenceOfEvents {
+    /// Loads the records under `dir`; the window comes from `SOE_WINDOW_S`.
+    pub fn load(dir: String) -> Self {
+        let window_s = std::env::var("SOE_WINDOW_S")
+            .ok()
+            .and_then(|value| value.parse::<i64>().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(DEFAULT_WINDOW_S);
+        Self {
+            records: Arc::new(RwLock::new(runtime_store::load_map(&dir))),
+            window: Duration::seconds(window_s),
+            dir,
+        }
+    }
+
+    /// Records, newest trip first.
+    pub async fn list(&self) -> Vec<SoeRecord> {
+        let mut records: Vec<SoeRecord> = self.records.read().await.values().cloned().collect();
+        records.sort_by(|a, b| b.tripped_at.cmp(&a.tripped_at));
+        records
+    }
+
+    pub async fn get(&self, alarm_id: &str) -> Option<SoeRecord> {
+        self.records.read().await.get(alarm_id).cloned()
+    }
+
+    async fn insert(&self, record: SoeRecord) {
+        let mut records = self.records.write().await;
+        runtime_store::persist_json(&self.dir, &record.alarm_id, &record);
+        records.insert(record.alarm_id.clone(), record);
+        while records.len() > MAX_RECORDS {
+            let Some(oldest) = records
+                .values()
+                .min_by(|a, b| a.captured_at.cmp(&b.captured_at))
+                .map(|record| record.alarm_id.clone())
+            else {
+                break;
+            };
+            records.remove(&oldest);
+            runtime_store::delete_json(&self.dir, &oldest);
+        }
+    }
+}
+
+/// Captures the preceding window of the alarm's PEA keys when an alarm of the
+/// most urgent severity in the profile trips.
+pub async fn capture(state: &AppState, alarm: &AlarmRecord) {
+    let tripped = state
+        .severity_profile
+        .read()
+        .await
+        .is_critical(&alarm.severity);
+    if !tripped {
+        return;
+    }
+    let pea_id = alarm_grouping::pea_of(&alarm.source);
+    let tripped_at = alarm
+        .raised_at
+        .as_deref()
+        .and_then(|raised| DateTime::parse_from_rfc3339(raised).ok())
+        .map(|raised| raised.with_timezone(&Utc))
+        .unwrap_or_else(Utc::now);
+    let record = build_record(
+        alarm,
+        pea_id,
+        &*state.timeseries.read().await,
+        tripped_at,
+        state.soe.window,
+    );
+    let Some(record) = record else {
+        return;
+    };
+    info!(
+        "Captured sequence of events for alarm {}: {} changes across {} keys",
+        alarm.id,
+        record.events.len(),
+        record.samples.len()
+    );
+    state.soe.insert(record).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::alarm_record;
+
+    #[test]
+    fn first_out_is_the_earliest_change_of_the_pea() {
+        let tripped_at: DateTime<Utc> = "2026-05-01T10:00:30Z".parse().unwrap();
+        let t = |s: i64| tripped_at.timestamp_millis() - s * 1000;
+        let mut ts = TimeSeriesStore::new(100);
+        let pressure = "entmoot/habitat/nodes/n1/pea/p1/data/pressure".to_string();
+        let flow = "entmoot/habitat/nodes/n1/pea/p1/data/flow".to_string();
+        ts.insert(pressure.clone(), serde_json::json!(5.0), t(60));
+        ts.insert(pressure.clone(), serde_json::json!(5.0), t(20));
+        ts.insert(pressure.clone(), serde_json::json!(7.5), t(4));
+        ts.insert(flow.clone(), serde_json::json!(10), t(12));
+        ts.insert(flow.clone(), serde_json::json!(2), t(8));
+        ts.insert(
+            "entmoot/habitat/nodes/n1/pea/p2/data/flow".to_string(),
+            serde_json::json!(1),
+            t(15),
+        );
+
+        let alarm = alarm_record("a1", "p1", "critical");
+        let record = build_record(&alarm, "p1", &ts, tripped_at, Duration::seconds(30)).unwrap();
+
+        assert_eq!(record.samples.len(), 2);
+        assert_eq!(record.samples[&pressure].len(), 2);
+        let first_out = record.first_out.unwrap();
+        assert_eq!(first_out.key, flow);
+        assert_eq!(first_out.previous, None);
+        let changes: Vec<(&str, i64)> = record
+            .events
+            .iter()
+            .map(|e| (e.key.as_str(), e.timestamp_ms))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (flow.as_str(), t(12)),
+                (flow.as_str(), t(8)),
+                (pressure.as_str(), t(4))
+            ]
+        );
+    }
+
+    #[test]
+    fn nothing_is_captured_without_points_in_the_window() {
+        let tripped_at = Utc::now();
+        let mut ts = TimeSeriesStore::new(100);
+        ts.insert(
+            "entmoot/habitat/nodes/n1/pea/p1/data/pressure".to_string(),
+            serde_json::json!(1),
+            tripped_at.timestamp_millis() - 120_000,
+        );
+        let alarm = alarm_record("a1", "p1", "critical");
+        assert!(build_record(&alarm, "p1", &ts, tripped_at, Duration::seconds(30)).is_none());
+    }
+}
@@ -1,6 +1,7 @@
 use shared::domain::authority::{AuthorityAuditRecord, AuthorityState};
 use shared::domain::binding::PeaBinding;
 use shared::domain::driver::{DriverCatalogEntry, DriverInstance, DriverStatusSnapshot};
+use shared::domain::interlock::{InterlockRule, InterlockViolation};
 use shared::domain::runtime::RuntimeNode;
 use shared::mtp::{PeaConfig, Recipe};
 use std::collections::{HashMap, VecDeque};
@@ -20,6 +21,16 @@ pub struct AlarmRecord {
     pub description: String,
     pub timestamp: String,
     pub duplicate_count: u32,
+    /// Primary alarm of an upstream PEA this alarm was grouped under.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    /// When the alarm was first raised; `timestamp` moves on with duplicates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raised_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acknowledged_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cleared_at: Option<String>,
 }
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
@@ -56,11 +67,30 @@ pub struct BlackoutWindow {
     pub created_at: String,
 }
 
+/// Metadata for a document attached to a PEA; the bytes live in the blob store.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct AttachmentRecord {
+    pub id: String,
+    pub pea_id: String,
+    pub filename: String,
+    pub content_type: String,
+    pub category: String,
+    pub description: String,
+    pub size_bytes: i64,
+    pub sha256: String,
+    pub storage_key: String,
+    pub uploaded_by: Option<String>,
+    pub created_at: String,
+}
+
 /// A single timestamped data point stored in the ring buffer.
 #[derive(Clone, serde::Serialize)]
 pub struct TimeSeriesPoint {
     pub timestamp_ms: i64,
     pub value: serde_json::Value,
+    /// Who published the point; `None` when it was not recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub producer: Option<crate::ts_provenance::Producer>,
 }
 
 /// Per-key ring buffer of historical data points.
@@ -69,6 +99,12 @@ pub struct TimeSeriesStore {
     pub data: HashMap<String, VecDeque<TimeSeriesPoint>>,
     /// Maximum points per key (older points are evicted)
     pub max_points_per_key: usize,
+    /// Keep evicted points in `evicted` until the archiver collects them
+    pub archive_evicted: bool,
+    /// key_expr -> points dropped by retention, oldest first
+    pub evicted: HashMap<String, Vec<TimeSeriesPoint>>,
+    /// key_expr -> raised point limit, e.g. while a capture trigger records it
+    pub retention_overrides: HashMap<String, usize>,
 }
 
 impl TimeSeriesStore {
@@ -76,18 +112,65 @@ impl TimeSeriesStore {
         Self {
             data: HashMap::new(),
             max_points_per_key,
+            archive_evicted: false,
+            evicted: HashMap::new(),
+            retention_overrides: HashMap::new(),
         }
     }
 
+    /// Points kept for `key`: the store's limit, or a higher override.
+    pub fn retention_for(&self, key: &str) -> usize {
+        self.retention_overrides
+            .get(key)
+            .map_or(self.max_points_per_key, |limit| {
+                (*limit).max(self.max_points_per_key)
+            })
+    }
+
+    /// Insert a point; late points (e.g. replayed by an edge agent) are placed
+    /// in time order rather than appended.
     pub fn insert(&mut self, key: String, value: serde_json::Value, timestamp_ms: i64) {
-        let buf = self.data.entry(key).or_insert_with(VecDeque::new);
-        buf.push_back(TimeSeriesPoint {
-            timestamp_ms,
-            value,
-        });
-        while buf.len() > self.max_points_per_key {
-            buf.pop_front();
+        self.insert_point(
+            key,
+            TimeSeriesPoint {
+                timestamp_ms,
+                value,
+                producer: None,
+            },
+        );
+    }
+
+    /// Insert a point together with its provenance.
+    pub fn insert_point(&mut self, key: String, point: TimeSeriesPoint) {
+        let timestamp_ms = point.timestamp_ms;
+        let buf = self.data.entry(key.clone()).or_insert_with(VecDeque::new);
+        if buf.back().is_none_or(|last| last.timestamp_ms <= timestamp_ms) {
+            buf.push_back(point);
+        } else {
+            let idx = buf.partition_point(|p| p.timestamp_ms <= timestamp_ms);
+            buf.insert(idx, point);
         }
+        self.evict(&key);
+    }
+
+    /// Drops the oldest points of `key` beyond its retention.
+    fn evict(&mut self, key: &str) {
+        let limit = self.retention_for(key);
+        let Some(buf) = self.data.get_mut(key) else {
+            return;
+        };
+        while buf.len() > limit {
+            if let Some(point) = buf.pop_front() {
+                if self.archive_evicted {
+                    self.evicted.entry(key.to_string()).or_default().push(point);
+                }
+            }
+        }
+    }
+
+    /// Drain the points evicted since the last call.
+    pub fn take_evicted(&mut self) -> HashMap<String, Vec<TimeSeriesPoint>> {
+        std::mem::take(&mut self.evicted)
     }
 
     /// Query points for a key within [start_ms, end_ms].
@@ -106,11 +189,37 @@ impl TimeSeriesStore {
         self.data.keys().collect()
     }
 
+    /// Moves the points of `from` into `to`, merging them in time order with
+    /// any points `to` already holds. Returns the number of points moved.
+    pub fn rename_key(&mut self, from: &str, to: &str) -> usize {
+        let Some(points) = self.data.remove(from) else {
+            return 0;
+        };
+        let moved = points.len();
+        for point in points {
+            self.insert_point(to.to_string(), point);
+        }
+        moved
+    }
+
     pub fn set_max_points_per_key(&mut self, max_points_per_key: usize) {
         self.max_points_per_key = max_points_per_key;
-        for buf in self.data.values_mut() {
-            while buf.len() > self.max_points_per_key {
-                buf.pop_front();
+        let keys: Vec<String> = self.data.keys().cloned().collect();
+        for key in keys {
+            self.evict(&key);
+        }
+    }
+
+    /// Raises the point limit of `key`, or with `None` returns it to the
+    /// store's limit and drops what no longer fits.
+    pub fn set_retention_override(&mut self, key: &str, limit: Option<usize>) {
+        match limit {
+            Some(limit) => {
+                self.retention_overrides.insert(key.to_string(), limit);
+            }
+            None => {
+                self.retention_overrides.remove(key);
+                self.evict(key);
             }
         }
     }
@@ -120,7 +229,15 @@ pub struct AppState {
     pub zenoh_session: Arc<Session>,
     pub native_s7_registry: Arc<crate::native_s7_backend::NativeS7Registry>,
     pub pea_configs: Arc<RwLock<HashMap<String, PeaConfig>>>,
+    /// Local and announced PEAs, as served by the PEA list and get endpoints.
+    pub pea_registry: crate::pea_registry::PeaRegistry,
+    /// Deleted PEA configs that can still be restored.
+    pub archived_pea_configs: Arc<RwLock<HashMap<String, PeaConfig>>>,
     pub recipes: Arc<RwLock<HashMap<String, Recipe>>>,
+    pub step_library: Arc<RwLock<HashMap<String, crate::step_library::LibraryStep>>>,
+    pub environments: Arc<RwLock<HashMap<String, crate::environments::ExecutionEnvironment>>>,
+    /// Environment of recipe runs that name none (`EXECUTION_ENVIRONMENT`).
+    pub default_environment: Option<String>,
     pub runtime_nodes: Arc<RwLock<HashMap<String, RuntimeNode>>>,
     pub driver_instances: Arc<RwLock<HashMap<String, DriverInstance>>>,
     pub driver_statuses: Arc<RwLock<HashMap<String, DriverStatusSnapshot>>>,
@@ -128,20 +245,93 @@ pub struct AppState {
     pub authority_states: Arc<RwLock<HashMap<String, AuthorityState>>>,
     pub authority_audit: Arc<RwLock<Vec<AuthorityAuditRecord>>>,
     pub driver_catalog: Arc<RwLock<Vec<DriverCatalogEntry>>>,
+    pub interlocks: Arc<RwLock<HashMap<String, InterlockRule>>>,
+    pub interlock_violations: Arc<RwLock<Vec<InterlockViolation>>>,
+    pub approval_policies: Arc<RwLock<HashMap<String, crate::approval_service::ApprovalPolicy>>>,
+    pub approvals: Arc<RwLock<HashMap<String, crate::approval_service::PendingApproval>>>,
     pub recipe_executions: Arc<RwLock<HashMap<String, serde_json::Value>>>,
+    pub recipe_campaigns: Arc<RwLock<HashMap<String, crate::recipe_campaign::Campaign>>>,
+    /// Golden run of each recipe, by recipe id.
+    pub golden_runs: Arc<RwLock<HashMap<String, crate::golden_runs::GoldenRun>>>,
     pub scenario_runs: Arc<RwLock<HashMap<String, serde_json::Value>>>,
+    pub playback_sessions: crate::playback_handlers::PlaybackSessions,
+    pub redaction_rules: Arc<RwLock<HashMap<String, crate::redaction::RedactionRule>>>,
+    pub webhooks: crate::webhook_service::Webhooks,
+    pub pea_certificates: crate::pea_birth::PeaCertificates,
+    pub pea_status: crate::pea_status::PeaStatusCache,
+    pub pea_lifecycle: crate::pea_lifecycle::PeaLifecycle,
+    pub presence: crate::operator_presence::Presence,
+    pub pea_reconcile_report: Arc<RwLock<Option<crate::pea_drift::ReconcileReport>>>,
+    pub desired_state: Arc<RwLock<Option<crate::desired_state::DesiredState>>>,
+    pub automation: crate::automation::Automation,
+    pub tenants: Arc<RwLock<HashMap<String, crate::tenancy::Tenant>>>,
+    pub planned_nodes: Arc<RwLock<HashMap<String, crate::provisioning::PlannedNode>>>,
+    pub mesh_config_changes: Arc<RwLock<HashMap<String, crate::mesh_admin::MeshConfigChange>>>,
+    pub ts_saved_queries: Arc<RwLock<HashMap<String, crate::ts_saved_query::SavedQuery>>>,
+    pub ts_bands: Arc<RwLock<HashMap<String, crate::ts_bands::ThresholdBand>>>,
+    pub ts_key_hints: Arc<RwLock<HashMap<String, crate::ts_counters::KeyTypeHint>>>,
+    pub ts_aliases: Arc<RwLock<HashMap<String, crate::ts_aliases::KeyAlias>>>,
     pub alarms: Arc<RwLock<HashMap<String, AlarmRecord>>>,
     pub alarm_rules: Arc<RwLock<HashMap<String, AlarmRule>>>,
+    pub alarm_slas: Arc<RwLock<HashMap<String, crate::alarm_sla::AlarmSla>>>,
+    pub soe: crate::soe::SequenceOfEvents,
+    pub on_call: crate::on_call::OnCall,
+    pub message_catalog: Arc<RwLock<HashMap<String, crate::message_catalog::MessageEntry>>>,
+    pub message_languages: crate::message_catalog::Languages,
+    pub severity_profile: Arc<RwLock<crate::severity_profile::SeverityProfile>>,
     pub blackout_windows: Arc<RwLock<HashMap<String, BlackoutWindow>>>,
     pub topology: Arc<RwLock<PolTopology>>,
     pub db_client: Arc<Client>,
-    pub pea_config_dir: String,
-    pub recipe_dir: String,
+    pub blob_store: Arc<dyn crate::blob_store::BlobStore>,
+    pub config_store: Arc<dyn crate::config_store::ConfigStore>,
+    pub pea_revisions: Arc<crate::pea_revisions::PeaRevisions>,
+    pub replication: Option<crate::config_replication::Replication>,
+    pub leadership: crate::leader::Leadership,
+    pub chaos: crate::chaos::Chaos,
+    pub schemas: crate::schema_registry::SchemaRegistry,
+    pub ingest_processors: crate::ingest_processors::IngestProcessors,
+    pub computed_alarms: crate::computed_alarms::ComputedAlarms,
+    pub captures: crate::ts_capture::Captures,
+    pub scripts: crate::script_hooks::ScriptHooks,
+    pub historian: crate::ts_historian::Historian,
+    pub pea_template_dir: String,
     pub pol_db_dir: String,
     pub runtime_node_dir: String,
     pub driver_dir: String,
     pub binding_dir: String,
     pub authority_dir: String,
+    pub interlock_dir: String,
+    pub approval_policy_dir: String,
+    pub redaction_dir: String,
+    pub webhook_dir: String,
+    pub notification_preference_dir: String,
+    pub on_call_dir: String,
+    pub automation_dir: String,
+    pub tenant_dir: String,
+    pub provisioning_dir: String,
+    pub mesh_change_dir: String,
+    pub ts_saved_query_dir: String,
+    pub ts_band_dir: String,
+    pub step_library_dir: String,
+    pub environment_dir: String,
+    pub golden_run_dir: String,
+    pub ts_key_hint_dir: String,
+    pub ts_alias_dir: String,
+    pub alarm_sla_dir: String,
+    pub message_catalog_dir: String,
     pub timeseries_config_path: String,
+    pub desired_state_path: String,
+    pub severity_profile_path: String,
+    pub schema_dir: String,
+    pub ingest_processor_dir: String,
+    pub script_dir: String,
+    pub computed_alarm_dir: String,
+    pub capture_trigger_dir: String,
+    pub incident_recording_dir: String,
     pub timeseries: Arc<RwLock<TimeSeriesStore>>,
+    pub tasks: crate::task_registry::TaskRegistry,
+    pub ws_limits: crate::websocket::WsLimits,
+    /// SHA-256 of `DEPLOYMENT_ADMIN_TOKEN`, the credential for deployment-level
+    /// access once tenants exist.
+    pub admin_token_hash: Option<String>,
 }
@@ -0,0 +1,363 @@
+use crate::state::{TimeSeriesPoint, TimeSeriesStore};
+use serde::Serialize;
+use shared::mtp::{ServiceCommand, ServiceState};
+use std::collections::HashMap;
+
+/// A service state change observed on the PEA status topic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StateSample {
+    pub timestamp_ms: i64,
+    pub state: ServiceState,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StateDwell {
+    pub total_ms: i64,
+    pub visits: u32,
+    pub mean_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StateShare {
+    pub state: ServiceState,
+    pub total_ms: i64,
+    pub visits: u32,
+    pub mean_ms: i64,
+    pub ratio: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransitionCount {
+    pub from: ServiceState,
+    pub to: ServiceState,
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StateStats {
+    pub observed_ms: i64,
+    pub states: Vec<StateShare>,
+    pub transitions: Vec<TransitionCount>,
+    pub transition_count: u32,
+    pub abnormal_ms: i64,
+    pub abnormal_ratio: f64,
+}
+
+/// States that indicate the service left its normal production path.
+pub const ABNORMAL_STATES: [ServiceState; 6] = [
+    ServiceState::Holding,
+    ServiceState::Held,
+    ServiceState::Stopping,
+    ServiceState::Stopped,
+    ServiceState::Aborting,
+    ServiceState::Aborted,
+];
+
+/// Extracts `(service_tag, state)` pairs from a PEA status payload as published
+/// on `topics::pea_status`. Falls back to `state_code` when the state name is
+/// missing or unknown.
+pub fn service_states_from_status(status: &serde_json::Value) -> Vec<(String, ServiceState)> {
+    let Some(services) = status.get("services").and_then(|v| v.as_array()) else {
+        return Vec::new();
+    };
+
+    services
+        .iter()
+        .filter_map(|svc| {
+            let tag = svc.get("tag").and_then(|t| t.as_str())?.to_string();
+            let state = svc
+                .get("state")
+                .and_then(|s| serde_json::from_value::<ServiceState>(s.clone()).ok())
+                .or_else(|| {
+                    svc.get("state_code")
+                        .and_then(|c| c.as_u64())
+                        .and_then(|c| ServiceState::from_code(c as u32))
+                })?;
+            Some((tag, state))
+        })
+        .collect()
+}
+
+/// Last known state of one service, read from the newest status sample
+/// buffered for its PEA. `None` until the PEA has reported that service.
+pub fn current_service_state(
+    timeseries: &TimeSeriesStore,
+    pea_id: &str,
+    service_tag: &str,
+) -> Option<ServiceState> {
+    let key = shared::mtp::topics::pea_status(pea_id);
+    let last = timeseries.data.get(&key).and_then(|buf| buf.back())?;
+    service_states_from_status(&last.value)
+        .into_iter()
+        .find(|(tag, _)| tag == service_tag)
+        .map(|(_, state)| state)
+}
+
+/// Reduces status history to the sequence of state changes for one service.
+pub fn service_state_history(points: &[&TimeSeriesPoint], service_tag: &str) -> Vec<StateSample> {
+    let mut history: Vec<StateSample> = Vec::new();
+    for point in points {
+        let Some(state) = service_states_from_status(&point.value)
+            .into_iter()
+            .find(|(tag, _)| tag == service_tag)
+            .map(|(_, state)| state)
+        else {
+            continue;
+        };
+        if history.last().is_some_and(|last| last.state == state) {
+            continue;
+        }
+        history.push(StateSample {
+            timestamp_ms: point.timestamp_ms,
+            state,
+        });
+    }
+    history
+}
+
+/// Time spent in each state, counting the last state as held until `end_ms`.
+pub fn dwell_by_state(history: &[StateSample], end_ms: i64) -> HashMap<ServiceState, StateDwell> {
+    let mut dwell: HashMap<ServiceState, StateDwell> = HashMap::new();
+    for (idx, sample) in history.iter().enumerate() {
+        let until = history
+            .get(idx + 1)
+            .map(|next| next.timestamp_ms)
+            .unwrap_or(end_ms);
+        let entry = dwell.entry(sample.state).or_default();
+        entry.total_ms += (until - sample.timestamp_ms).max(0);
+        entry.visits += 1;
+    }
+    for entry in dwell.values_mut() {
+        entry.mean_ms = entry.total_ms / i64::from(entry.visits.max(1));
+    }
+    dwell
+}
+
+pub fn transition_counts(history: &[StateSample]) -> Vec<TransitionCount> {
+    let mut counts: Vec<TransitionCount> = Vec::new();
+    for pair in history.windows(2) {
+        let (from, to) = (pair[0].state, pair[1].state);
+        match counts.iter_mut().find(|c| c.from == from && c.to == to) {
+            Some(existing) => existing.count += 1,
+            None => counts.push(TransitionCount { from, to, count: 1 }),
+        }
+    }
+    counts.sort_by_key(|c| std::cmp::Reverse(c.count));
+    counts
+}
+
+/// Dwell shares, transition counts and abnormal-state ratio over the observed
+/// part of the window (from the first sample to `end_ms`).
+pub fn state_stats(history: &[StateSample], end_ms: i64) -> StateStats {
+    let observed_ms = history
+        .first()
+        .map(|first| (end_ms - first.timestamp_ms).max(0))
+        .unwrap_or(0);
+    let ratio_of = |ms: i64| {
+        if observed_ms > 0 {
+            ms as f64 / observed_ms as f64
+        } else {
+            0.0
+        }
+    };
+
+    let dwell = dwell_by_state(history, end_ms);
+    let states: Vec<StateShare> = ServiceState::all()
+        .into_iter()
+        .filter_map(|state| {
+            dwell.get(&state).map(|d| StateShare {
+                state,
+                total_ms: d.total_ms,
+                visits: d.visits,
+                mean_ms: d.mean_ms,
+                ratio: ratio_of(d.total_ms),
+            })
+        })
+        .collect();
+
+    let abnormal_ms: i64 = states
+        .iter()
+        .filter(|share| ABNORMAL_STATES.contains(&share.state))
+        .map(|share| share.total_ms)
+        .sum();
+    let transitions = transition_counts(history);
+
+    StateStats {
+        observed_ms,
+        transition_count: transitions.iter().map(|t| t.count).sum(),
+        states,
+        transitions,
+        abnormal_ms,
+        abnormal_ratio: ratio_of(abnormal_ms),
+    }
+}
+
+/// PackML graph for one service, annotated with the live state and dwell history.
+pub fn state_machine_model(
+    current: Option<ServiceState>,
+    dwell: &HashMap<ServiceState, StateDwell>,
+) -> serde_json::Value {
+    let states: Vec<serde_json::Value> = ServiceState::all()
+        .iter()
+        .map(|state| {
+            serde_json::json!({
+                "state": state,
+                "code": state.code(),
+                "stable": state.is_stable(),
+                "current": current == Some(*state),
+                "dwell": dwell.get(state).cloned().unwrap_or_default(),
+            })
+        })
+        .collect();
+
+    let mut transitions = Vec::new();
+    for from in ServiceState::all() {
+        for command in from.allowed_commands() {
+            if let Some(to) = from.command_target(command) {
+                transitions.push(serde_json::json!({
+                    "from": from,
+                    "to": to,
+                    "trigger": "command",
+                    "command": command,
+                    "command_code": command.code(),
+                    "allowed_now": current == Some(from),
+                }));
+            }
+        }
+        if let Some(to) = from.completion_target() {
+            transitions.push(serde_json::json!({
+                "from": from,
+                "to": to,
+                "trigger": "state_complete",
+                "command": serde_json::Value::Null,
+                "command_code": serde_json::Value::Null,
+                "allowed_now": false,
+            }));
+        }
+    }
+
+    let allowed_commands: Vec<ServiceCommand> = current
+        .map(|state| state.allowed_commands())
+        .unwrap_or_default();
+
+    serde_json::json!({
+        "current_state": current,
+        "current_state_code": current.map(|state| state.code()),
+        "allowed_commands": allowed_commands,
+        "states": states,
+        "transitions": transitions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{point, status_message};
+
+    fn status(timestamp_ms: i64, state: ServiceState) -> TimeSeriesPoint {
+        point(
+            timestamp_ms,
+            status_message("pea-a", &[("svc.main", state)]),
+        )
+    }
+
+    #[test]
+    fn current_state_comes_from_the_newest_status() {
+        let mut ts = TimeSeriesStore::new(10);
+        let key = shared::mtp::topics::pea_status("pea-a");
+        assert_eq!(current_service_state(&ts, "pea-a", "svc.main"), None);
+        ts.insert(key.clone(), status(0, ServiceState::Idle).value, 0);
+        ts.insert(key, status(10, ServiceState::Execute).value, 10);
+        assert_eq!(
+            current_service_state(&ts, "pea-a", "svc.main"),
+            Some(ServiceState::Execute)
+        );
+        assert_eq!(current_service_state(&ts, "pea-a", "svc.other"), None);
+    }
+
+    #[test]
+    fn history_collapses_repeated_states() {
+        let points = [
+            status(0, ServiceState::Idle),
+            status(10, ServiceState::Idle),
+            status(20, ServiceState::Starting),
+            status(30, ServiceState::Execute),
+        ];
+        let refs: Vec<&TimeSeriesPoint> = points.iter().collect();
+        let history = service_state_history(&refs, "svc.main");
+        let states: Vec<ServiceState> = history.iter().map(|s| s.state).collect();
+        assert_eq!(
+            states,
+            vec![
+                ServiceState::Idle,
+                ServiceState::Starting,
+                ServiceState::Execute
+            ]
+        );
+    }
+
+    #[test]
+    fn dwell_counts_last_state_until_end() {
+        let history = [
+            StateSample {
+                timestamp_ms: 0,
+                state: ServiceState::Idle,
+            },
+            StateSample {
+                timestamp_ms: 100,
+                state: ServiceState::Execute,
+            },
+            StateSample {
+                timestamp_ms: 400,
+                state: ServiceState::Idle,
+            },
+        ];
+        let dwell = dwell_by_state(&history, 500);
+        assert_eq!(dwell[&ServiceState::Idle].total_ms, 200);
+        assert_eq!(dwell[&ServiceState::Idle].visits, 2);
+        assert_eq!(dwell[&ServiceState::Execute].total_ms, 300);
+    }
+
+    #[test]
+    fn stats_report_transitions_and_abnormal_ratio() {
+        let history = [
+            StateSample {
+                timestamp_ms: 0,
+                state: ServiceState::Execute,
+            },
+            StateSample {
+                timestamp_ms: 600,
+                state: ServiceState::Held,
+            },
+            StateSample {
+                timestamp_ms: 800,
+                state: ServiceState::Execute,
+            },
+            StateSample {
+                timestamp_ms: 900,
+                state: ServiceState::Held,
+            },
+        ];
+        let stats = state_stats(&history, 1000);
+        assert_eq!(stats.observed_ms, 1000);
+        assert_eq!(stats.transition_count, 3);
+        assert_eq!(stats.transitions[0].from, ServiceState::Execute);
+        assert_eq!(stats.transitions[0].count, 2);
+        assert_eq!(stats.abnormal_ms, 300);
+        assert!((stats.abnormal_ratio - 0.3).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn model_marks_current_state_transitions_as_allowed() {
+        let model = state_machine_model(Some(ServiceState::Idle), &HashMap::new());
+        let transitions = model["transitions"].as_array().unwrap();
+        let allowed: Vec<&serde_json::Value> = transitions
+            .iter()
+            .filter(|t| t["allowed_now"] == true)
+            .collect();
+        assert_eq!(allowed.len(), 2);
+        assert!(allowed
+            .iter()
+            .any(|t| t["command"] == "Start" && t["to"] == "Starting"));
+    }
+}
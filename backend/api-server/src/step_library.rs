@@ -0,0 +1,325 @@
+use serde::{Deserialize, Serialize};
+use shared::mtp::{Recipe, RecipeStep};
+use std::collections::{BTreeMap, HashMap};
+
+/// A named sequence of recipe steps, e.g. reset → start → wait for Execute,
+/// shared by every recipe that references it. String fields of its steps may
+/// hold `{{parameter}}` placeholders filled in by each reference.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LibraryStep {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub parameters: Vec<LibraryStepParameter>,
+    pub steps: Vec<RecipeStep>,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LibraryStepParameter {
+    pub name: String,
+    /// Used when a reference gives no value; without one the value is required.
+    #[serde(default)]
+    pub default: Option<serde_json::Value>,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl LibraryStep {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+        if self.steps.is_empty() {
+            return Err("A library step needs at least one step".to_string());
+        }
+        let mut declared = Vec::new();
+        for parameter in &self.parameters {
+            let name = parameter.name.as_str();
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(format!(
+                    "Parameter name '{}' may only use letters, digits and _",
+                    name
+                ));
+            }
+            if declared.contains(&name) {
+                return Err(format!("Parameter '{}' is declared twice", name));
+            }
+            declared.push(name);
+        }
+        for step in &self.steps {
+            let value = serde_json::to_value(step).map_err(|e| e.to_string())?;
+            if let Some(name) = used_placeholders(&value)
+                .into_iter()
+                .find(|name| !declared.contains(&name.as_str()))
+            {
+                return Err(format!(
+                    "Step {} uses undeclared parameter '{}'",
+                    step.order, name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The steps with placeholders replaced, in order. `arguments` override defaults.
+    pub fn instantiate(
+        &self,
+        arguments: &BTreeMap<String, serde_json::Value>,
+    ) -> Result<Vec<RecipeStep>, String> {
+        if let Some(unknown) = arguments
+            .keys()
+            .find(|name| !self.parameters.iter().any(|p| &p.name == *name))
+        {
+            return Err(format!(
+                "Library step '{}' has no parameter '{}'",
+                self.id, unknown
+            ));
+        }
+        let mut values = BTreeMap::new();
+        for parameter in &self.parameters {
+            let value = arguments
+                .get(&parameter.name)
+                .or(parameter.default.as_ref())
+                .ok_or_else(|| {
+                    format!(
+                        "Library step '{}' needs a value for '{}'",
+                        self.id, parameter.name
+                    )
+                })?;
+            values.insert(parameter.name.as_str(), value);
+        }
+
+        let mut steps = self.steps.clone();
+        steps.sort_by_key(|step| step.order);
+        steps
+            .iter()
+            .map(|step| {
+                let value = serde_json::to_value(step).map_err(|e| e.to_string())?;
+                serde_json::from_value(substitute(value, &values))
+                    .map_err(|e| format!("Library step '{}', step {}: {}", self.id, step.order, e))
+            })
+            .collect()
+    }
+}
+
+/// The steps a recipe runs: its own steps and the expanded library steps it
+/// references, numbered from 1 in execution order. A recipe may only use
+/// library steps of its own tenant.
+pub fn expand(
+    recipe: &Recipe,
+    library: &HashMap<String, LibraryStep>,
+) -> Result<Vec<RecipeStep>, String> {
+    let mut blocks: Vec<(u32, Vec<RecipeStep>)> = recipe
+        .steps
+        .iter()
+        .map(|step| (step.order, vec![step.clone()]))
+        .collect();
+    for reference in &recipe.library_steps {
+        let step = library
+            .get(&reference.step_id)
+            .filter(|step| step.tenant_id == recipe.tenant_id)
+            .ok_or_else(|| format!("Library step '{}' not found", reference.step_id))?;
+        blocks.push((reference.order, step.instantiate(&reference.arguments)?));
+    }
+    blocks.sort_by_key(|(order, _)| *order);
+    Ok(blocks
+        .into_iter()
+        .flat_map(|(_, steps)| steps)
+        .zip(1..)
+        .map(|(mut step, order)| {
+            step.order = order;
+            step
+        })
+        .collect())
+}
+
+/// Names inside `{{...}}` in `text`, trimmed.
+fn placeholders(text: &str) -> Vec<(usize, usize, &str)> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find("{{").map(|i| offset + i) {
+        let Some(len) = text[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + len + 2;
+        found.push((start, end, text[start + 2..start + 2 + len].trim()));
+        offset = end;
+    }
+    found
+}
+
+fn used_placeholders(value: &serde_json::Value) -> Vec<String> {
+    match value {
+        serde_json::Value::String(text) => placeholders(text)
+            .into_iter()
+            .map(|(_, _, name)| name.to_string())
+            .collect(),
+        serde_json::Value::Array(items) => items.iter().flat_map(used_placeholders).collect(),
+        serde_json::Value::Object(fields) => fields.values().flat_map(used_placeholders).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// A string that is one placeholder takes the argument as is, so numbers and
+/// objects keep their type; otherwise placeholders are replaced by text.
+fn substitute(
+    value: serde_json::Value,
+    values: &BTreeMap<&str, &serde_json::Value>,
+) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(text) => {
+            let found = placeholders(&text);
+            if let [(0, end, name)] = found.as_slice() {
+                if *end == text.len() {
+                    if let Some(value) = values.get(name) {
+                        return (*value).clone();
+                    }
+                }
+            }
+            let mut rendered = String::new();
+            let mut last = 0;
+            for (start, end, name) in found {
+                rendered.push_str(&text[last..start]);
+                match values.get(name) {
+                    Some(serde_json::Value::String(value)) => rendered.push_str(value),
+                    Some(value) => rendered.push_str(&value.to_string()),
+                    None => rendered.push_str(&text[start..end]),
+                }
+                last = end;
+            }
+            rendered.push_str(&text[last..]);
+            serde_json::Value::String(rendered)
+        }
+        serde_json::Value::Array(items) => items
+            .into_iter()
+            .map(|item| substitute(item, values))
+            .collect(),
+        serde_json::Value::Object(fields) => fields
+            .into_iter()
+            .map(|(key, item)| (key, substitute(item, values)))
+            .collect(),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::mtp::RecipeStepRef;
+
+    fn restart_sequence() -> LibraryStep {
+        serde_json::from_value(serde_json::json!({
+            "id": "restart",
+            "name": "Reset and start",
+            "parameters": [
+                {"name": "pea"},
+                {"name": "service"},
+                {"name": "setpoint", "default": 20.0}
+            ],
+            "steps": [
+                {
+                    "order": 2, "pea_id": "{{pea}}", "service_tag": "{{service}}",
+                    "command": "Start", "procedure_id": 1,
+                    "parameters": [{"parameter_tag": "{{service}}_SP", "value": "{{setpoint}}"}],
+                    "wait_for_state": "Execute", "timeout_ms": 30000
+                },
+                {
+                    "order": 1, "pea_id": "{{pea}}", "service_tag": "{{service}}",
+                    "command": "Reset", "procedure_id": null, "parameters": [],
+                    "wait_for_state": "Idle", "timeout_ms": null
+                }
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn recipe(library_steps: Vec<RecipeStepRef>) -> Recipe {
+        serde_json::from_value(serde_json::json!({
+            "id": "r1",
+            "name": "Batch",
+            "description": "",
+            "steps": [{
+                "order": 1, "pea_id": "mixer", "service_tag": "Mix",
+                "command": "Start", "procedure_id": null, "parameters": [],
+                "wait_for_state": null, "timeout_ms": null
+            }],
+            "library_steps": library_steps,
+            "created_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap()
+    }
+
+    fn reference(order: u32, arguments: serde_json::Value) -> RecipeStepRef {
+        RecipeStepRef {
+            order,
+            step_id: "restart".to_string(),
+            arguments: serde_json::from_value(arguments).unwrap(),
+        }
+    }
+
+    #[test]
+    fn references_expand_in_place_with_their_arguments() {
+        let library = HashMap::from([("restart".to_string(), restart_sequence())]);
+        let recipe = recipe(vec![
+            reference(0, serde_json::json!({"pea": "dosing", "service": "Dose"})),
+            reference(
+                1,
+                serde_json::json!({"pea": "heater", "service": "Heat", "setpoint": 65.5}),
+            ),
+        ]);
+
+        let steps = expand(&recipe, &library).unwrap();
+
+        let summary: Vec<(u32, &str, &str)> = steps
+            .iter()
+            .map(|s| (s.order, s.pea_id.as_str(), s.service_tag.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, "dosing", "Dose"),
+                (2, "dosing", "Dose"),
+                (3, "mixer", "Mix"),
+                (4, "heater", "Heat"),
+                (5, "heater", "Heat"),
+            ]
+        );
+        assert_eq!(steps[1].parameters[0].parameter_tag, "Dose_SP");
+        assert_eq!(steps[1].parameters[0].value, serde_json::json!(20.0));
+        assert_eq!(steps[4].parameters[0].value, serde_json::json!(65.5));
+    }
+
+    #[test]
+    fn expansion_rejects_missing_foreign_and_unknown_arguments() {
+        let library = HashMap::from([("restart".to_string(), restart_sequence())]);
+        let missing = recipe(vec![reference(0, serde_json::json!({"pea": "dosing"}))]);
+        assert!(expand(&missing, &library)
+            .unwrap_err()
+            .contains("'service'"));
+
+        let unknown = recipe(vec![reference(
+            0,
+            serde_json::json!({"pea": "p", "service": "s", "speed": 1}),
+        )]);
+        assert!(expand(&unknown, &library).unwrap_err().contains("'speed'"));
+
+        let mut foreign = recipe(vec![reference(
+            0,
+            serde_json::json!({"pea": "p", "service": "s"}),
+        )]);
+        foreign.tenant_id = Some("t1".to_string());
+        assert!(expand(&foreign, &library).is_err());
+
+        let mut undeclared = restart_sequence();
+        undeclared.parameters.remove(1);
+        assert!(undeclared.validate().unwrap_err().contains("'service'"));
+        assert!(restart_sequence().validate().is_ok());
+    }
+}
@@ -0,0 +1,199 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+
+use crate::environments;
+use crate::pea_handlers::ExecuteQuery;
+use crate::recipe_executor;
+use crate::runtime_store;
+use crate::state::AppState;
+use crate::step_library::{self, LibraryStep};
+use crate::tenancy;
+
+pub async fn list_library_steps(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let library = state.step_library.read().await;
+    let mut list: Vec<&LibraryStep> = library
+        .values()
+        .filter(|step| scope.allows(step.tenant_id.as_deref()))
+        .collect();
+    list.sort_by(|a, b| a.name.cmp(&b.name));
+    HttpResponse::Ok().json(list)
+}
+
+pub async fn get_library_step(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    step_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    match state
+        .step_library
+        .read()
+        .await
+        .get(step_id.as_str())
+        .filter(|step| scope.allows(step.tenant_id.as_deref()))
+    {
+        Some(step) => HttpResponse::Ok().json(step),
+        None => step_not_found(),
+    }
+}
+
+pub async fn create_library_step(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<LibraryStep>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut step = body.into_inner();
+    if let Err(e) = step.validate() {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    if step.id.is_empty() {
+        step.id = uuid::Uuid::new_v4().to_string();
+    }
+    let mut library = state.step_library.write().await;
+    if library.contains_key(&step.id) {
+        return HttpResponse::Conflict()
+            .json(serde_json::json!({"error": "Library step id is already in use"}));
+    }
+    step.tenant_id = scope.tenant_id().map(str::to_string);
+    step.updated_at = Utc::now().to_rfc3339();
+    runtime_store::persist_json(&state.step_library_dir, &step.id, &step);
+    library.insert(step.id.clone(), step.clone());
+    HttpResponse::Created().json(step)
+}
+
+/// PUT /step-library/{id} — replace a library step; refused while a recipe
+/// using it would no longer expand
+pub async fn update_library_step(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    step_id: web::Path<String>,
+    body: web::Json<LibraryStep>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut step = body.into_inner();
+    if let Err(e) = step.validate() {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    let mut library = state.step_library.write().await;
+    let Some(existing) = library
+        .get(step_id.as_str())
+        .filter(|existing| scope.allows(existing.tenant_id.as_deref()))
+    else {
+        return step_not_found();
+    };
+    step.id = existing.id.clone();
+    step.tenant_id = existing.tenant_id.clone();
+    step.updated_at = Utc::now().to_rfc3339();
+
+    let mut updated = library.clone();
+    updated.insert(step.id.clone(), step.clone());
+    let broken: Vec<serde_json::Value> = state
+        .recipes
+        .read()
+        .await
+        .values()
+        .filter(|recipe| recipe.library_steps.iter().any(|r| r.step_id == step.id))
+        .filter_map(|recipe| {
+            step_library::expand(recipe, &updated)
+                .err()
+                .map(|e| serde_json::json!({"recipe_id": recipe.id, "error": e}))
+        })
+        .collect();
+    if !broken.is_empty() {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Recipes using this library step would no longer expand",
+            "recipes": broken,
+        }));
+    }
+
+    runtime_store::persist_json(&state.step_library_dir, &step.id, &step);
+    library.insert(step.id.clone(), step.clone());
+    HttpResponse::Ok().json(step)
+}
+
+/// DELETE /step-library/{id} — refused while recipes reference the step
+pub async fn delete_library_step(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    step_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut library = state.step_library.write().await;
+    match library.get(step_id.as_str()) {
+        Some(step) if scope.allows(step.tenant_id.as_deref()) => {}
+        _ => return step_not_found(),
+    }
+    let mut users: Vec<String> = state
+        .recipes
+        .read()
+        .await
+        .values()
+        .filter(|recipe| {
+            recipe
+                .library_steps
+                .iter()
+                .any(|r| r.step_id == step_id.as_str())
+        })
+        .map(|recipe| recipe.id.clone())
+        .collect();
+    if !users.is_empty() {
+        users.sort();
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Library step is used by recipes",
+            "recipe_ids": users,
+        }));
+    }
+    library.remove(step_id.as_str());
+    runtime_store::delete_json(&state.step_library_dir, &step_id);
+    HttpResponse::NoContent().finish()
+}
+
+/// GET /recipes/{id}/steps — the steps a recipe runs, library steps expanded
+/// and bound to `?environment=`
+pub async fn get_expanded_recipe_steps(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    recipe_id: web::Path<String>,
+    query: web::Query<ExecuteQuery>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let Some(recipe) = state
+        .recipes
+        .read()
+        .await
+        .get(recipe_id.as_str())
+        .filter(|recipe| scope.allows(recipe.tenant_id.as_deref()))
+        .cloned()
+    else {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Recipe not found"}));
+    };
+    let environment = environments::select(&state, query.environment.as_deref());
+    match recipe_executor::resolve_steps(&state, &recipe, environment).await {
+        Ok(steps) => HttpResponse::Ok().json(steps),
+        Err(e) => HttpResponse::Conflict().json(serde_json::json!({"error": e})),
+    }
+}
+
+fn step_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({"error": "Library step not found"}))
+}
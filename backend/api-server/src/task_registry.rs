@@ -0,0 +1,241 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+
+pub const KIND_SUBSCRIBER: &str = "subscriber";
+pub const KIND_LOOP: &str = "loop";
+pub const KIND_RECIPE: &str = "recipe_execution";
+pub const KIND_SCENARIO: &str = "scenario_process";
+
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_RESTARTING: &str = "restarting";
+pub const STATUS_COMPLETED: &str = "completed";
+pub const STATUS_FAILED: &str = "failed";
+
+/// Finished tasks kept for inspection; older ones are dropped first.
+const MAX_FINISHED: usize = 200;
+const MAX_RESTART_BACKOFF_S: u64 = 60;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TaskInfo {
+    pub name: String,
+    pub kind: String,
+    pub status: String,
+    pub restart_count: u32,
+    pub started_at: DateTime<Utc>,
+    pub last_activity: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+type Tasks = Arc<Mutex<HashMap<String, TaskInfo>>>;
+
+/// Background tasks of the server with their health, fed by thin wrappers
+/// around `tokio::spawn`.
+#[derive(Clone, Default)]
+pub struct TaskRegistry {
+    tasks: Tasks,
+}
+
+/// Given to a task so it can report activity and errors.
+#[derive(Clone)]
+pub struct TaskHandle {
+    name: String,
+    tasks: Tasks,
+}
+
+impl TaskHandle {
+    /// Records that the task did some work just now.
+    pub fn beat(&self) {
+        self.update(|task| task.last_activity = Some(Utc::now()));
+    }
+
+    /// Records an error; the task is reported as failed once it ends.
+    pub fn fail(&self, error: impl std::fmt::Display) {
+        let error = error.to_string();
+        self.update(|task| task.last_error = Some(error));
+    }
+
+    fn update(&self, f: impl FnOnce(&mut TaskInfo)) {
+        if let Some(task) = lock(&self.tasks).get_mut(&self.name) {
+            f(task);
+        }
+    }
+
+    /// Marks the task finished. Failed when it panicked or reported an error.
+    fn finish(&self, panic: Option<String>) {
+        self.update(|task| {
+            task.finished_at = Some(Utc::now());
+            if let Some(panic) = panic {
+                task.last_error = Some(panic);
+            }
+            task.status = if task.last_error.is_some() {
+                STATUS_FAILED
+            } else {
+                STATUS_COMPLETED
+            }
+            .to_string();
+        });
+    }
+}
+
+impl TaskRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawns a task that runs once. A task registered earlier under the same
+    /// name is replaced.
+    pub fn spawn<F, Fut>(&self, name: impl Into<String>, kind: &str, make: F) -> JoinHandle<()>
+    where
+        F: FnOnce(TaskHandle) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let handle = self.register(name.into(), kind);
+        let inner = tokio::spawn(make(handle.clone()));
+        tokio::spawn(async move {
+            let panic = inner.await.err().map(|e| panic_message(&handle.name, e));
+            handle.finish(panic);
+        })
+    }
+
+    /// Spawns a long-running task that is started again, with backoff, whenever
+    /// it panics or returns.
+    pub fn supervise<F, Fut>(&self, name: impl Into<String>, kind: &str, make: F)
+    where
+        F: Fn(TaskHandle) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let handle = self.register(name.into(), kind);
+        tokio::spawn(async move {
+            loop {
+                let outcome = tokio::spawn(make(handle.clone())).await;
+                let reason = match outcome {
+                    Ok(()) => "returned".to_string(),
+                    Err(e) => panic_message(&handle.name, e),
+                };
+                let mut restarts = 0;
+                handle.update(|task| {
+                    task.restart_count += 1;
+                    task.status = STATUS_RESTARTING.to_string();
+                    task.last_error = Some(reason.clone());
+                    restarts = task.restart_count;
+                });
+                let backoff_s = 2u64.saturating_pow(restarts).min(MAX_RESTART_BACKOFF_S);
+                warn!(
+                    "Task {} {}; restarting in {}s",
+                    handle.name, reason, backoff_s
+                );
+                tokio::time::sleep(std::time::Duration::from_secs(backoff_s)).await;
+                handle.update(|task| task.status = STATUS_RUNNING.to_string());
+            }
+        });
+    }
+
+    /// All known tasks, running ones first.
+    pub fn snapshot(&self) -> Vec<TaskInfo> {
+        let mut list: Vec<TaskInfo> = lock(&self.tasks).values().cloned().collect();
+        list.sort_by(|a, b| {
+            (a.finished_at.is_some(), &a.kind, &a.name).cmp(&(
+                b.finished_at.is_some(),
+                &b.kind,
+                &b.name,
+            ))
+        });
+        list
+    }
+
+    fn register(&self, name: String, kind: &str) -> TaskHandle {
+        let mut tasks = lock(&self.tasks);
+        prune_finished(&mut tasks);
+        tasks.insert(
+            name.clone(),
+            TaskInfo {
+                name: name.clone(),
+                kind: kind.to_string(),
+                status: STATUS_RUNNING.to_string(),
+                restart_count: 0,
+                started_at: Utc::now(),
+                last_activity: None,
+                finished_at: None,
+                last_error: None,
+            },
+        );
+        TaskHandle {
+            name,
+            tasks: self.tasks.clone(),
+        }
+    }
+}
+
+fn lock(tasks: &Tasks) -> std::sync::MutexGuard<'_, HashMap<String, TaskInfo>> {
+    tasks
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn prune_finished(tasks: &mut HashMap<String, TaskInfo>) {
+    let mut finished: Vec<(DateTime<Utc>, String)> = tasks
+        .values()
+        .filter_map(|task| task.finished_at.map(|at| (at, task.name.clone())))
+        .collect();
+    if finished.len() < MAX_FINISHED {
+        return;
+    }
+    finished.sort();
+    let excess = finished.len() + 1 - MAX_FINISHED;
+    for (_, name) in finished.into_iter().take(excess) {
+        tasks.remove(&name);
+    }
+}
+
+fn panic_message(name: &str, error: tokio::task::JoinError) -> String {
+    if !error.is_panic() {
+        return error.to_string();
+    }
+    let payload = error.into_panic();
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    error!("Task {} panicked: {}", name, message);
+    format!("panicked: {}", message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tracks_completion_failures_and_restarts() {
+        let registry = TaskRegistry::new();
+
+        registry
+            .spawn("ok", KIND_LOOP, |task| async move { task.beat() })
+            .await
+            .unwrap();
+        registry
+            .spawn("boom", KIND_LOOP, |_| async move { panic!("boom") })
+            .await
+            .unwrap();
+        registry.supervise("flaky", KIND_LOOP, |_| async move {});
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        let tasks: HashMap<String, TaskInfo> = registry
+            .snapshot()
+            .into_iter()
+            .map(|task| (task.name.clone(), task))
+            .collect();
+        assert_eq!(tasks["ok"].status, STATUS_COMPLETED);
+        assert!(tasks["ok"].last_activity.is_some());
+        assert_eq!(tasks["boom"].status, STATUS_FAILED);
+        assert_eq!(tasks["boom"].last_error.as_deref(), Some("panicked: boom"));
+        assert_eq!(tasks["flaky"].status, STATUS_RESTARTING);
+        assert_eq!(tasks["flaky"].restart_count, 1);
+    }
+}
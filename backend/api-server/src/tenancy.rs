@@ -0,0 +1,442 @@
+use crate::alarm_grouping;
+use crate::request_context::CallerContext;
+use crate::state::AppState;
+use actix_web::{HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use shared::domain::authority::ActorClass;
+use shared::mtp::{PeaConfig, Recipe};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Prefix of issued tenant tokens, so they are recognisable in logs and configs.
+pub const TOKEN_PREFIX: &str = "fdt_";
+
+/// Actor id of whoever presents the deployment admin token.
+pub const DEPLOYMENT_ADMIN_ACTOR: &str = "deployment-admin";
+
+/// Per-tenant resource limits; `None` means unlimited.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TenantQuotas {
+    pub max_peas: Option<usize>,
+    pub max_recipes: Option<usize>,
+}
+
+/// An issued API token. Only the SHA-256 of the token is kept.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TenantToken {
+    pub id: String,
+    pub name: String,
+    pub token_hash: String,
+    /// Roles held by whoever presents the token, e.g. `approver`.
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Actor class of whoever presents the token; lets them override the
+    /// interlocks that name it. Never `Admin`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor_class: Option<ActorClass>,
+    pub created_at: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Tenant {
+    pub id: String,
+    pub name: String,
+    /// Zenoh key prefixes owned by the tenant; scopes time-series and alarm sources.
+    pub key_prefixes: Vec<String>,
+    #[serde(default)]
+    pub quotas: TenantQuotas,
+    #[serde(default)]
+    pub tokens: Vec<TenantToken>,
+    pub enabled: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Tenant {
+    /// Prefixes match whole key chunks: `plant-a` covers `plant-a/x` but not `plant-ab`.
+    pub fn owns_key(&self, key: &str) -> bool {
+        self.key_prefixes.iter().any(|prefix| {
+            let prefix = prefix.trim_end_matches('/');
+            !prefix.is_empty()
+                && key
+                    .strip_prefix(prefix)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+
+    /// Tenant as returned by the API; token hashes are never exposed.
+    pub fn public_view(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "name": self.name,
+            "key_prefixes": self.key_prefixes,
+            "quotas": self.quotas,
+            "tokens": self.tokens.iter().map(|token| serde_json::json!({
+                "id": token.id,
+                "name": token.name,
+                "roles": token.roles,
+                "created_at": token.created_at,
+            })).collect::<Vec<_>>(),
+            "enabled": self.enabled,
+            "created_at": self.created_at,
+            "updated_at": self.updated_at,
+        })
+    }
+}
+
+/// What the caller may see: the whole deployment, or a single tenant's slice of it.
+#[derive(Clone, Debug)]
+pub enum TenantScope {
+    Deployment,
+    Tenant(Tenant),
+}
+
+impl TenantScope {
+    pub fn tenant_id(&self) -> Option<&str> {
+        match self {
+            TenantScope::Deployment => None,
+            TenantScope::Tenant(tenant) => Some(tenant.id.as_str()),
+        }
+    }
+
+    /// Whether a resource owned by `owner` is visible in this scope.
+    pub fn allows(&self, owner: Option<&str>) -> bool {
+        match self {
+            TenantScope::Deployment => true,
+            TenantScope::Tenant(tenant) => owner == Some(tenant.id.as_str()),
+        }
+    }
+
+    /// Whether a Zenoh key (time-series key, alarm source) is visible in this scope.
+    pub fn allows_key(&self, key: &str) -> bool {
+        match self {
+            TenantScope::Deployment => true,
+            TenantScope::Tenant(tenant) => tenant.owns_key(key),
+        }
+    }
+
+    /// Alarm sources and topology nodes name either a PEA or a Zenoh key.
+    pub fn allows_source(&self, source: &str, owned_peas: &HashSet<String>) -> bool {
+        match self {
+            TenantScope::Deployment => true,
+            TenantScope::Tenant(tenant) => owned_peas.contains(source) || tenant.owns_key(source),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ScopeError {
+    MissingToken,
+    InvalidToken,
+    TenantDisabled,
+}
+
+impl ScopeError {
+    pub fn response(&self) -> HttpResponse {
+        match self {
+            ScopeError::MissingToken => HttpResponse::Unauthorized().json(
+                serde_json::json!({"error": "A tenant or deployment admin token is required"}),
+            ),
+            ScopeError::InvalidToken => HttpResponse::Unauthorized()
+                .json(serde_json::json!({"error": "Invalid tenant token"})),
+            ScopeError::TenantDisabled => {
+                HttpResponse::Forbidden().json(serde_json::json!({"error": "Tenant is disabled"}))
+            }
+        }
+    }
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub fn generate_token() -> String {
+    format!(
+        "{}{}{}",
+        TOKEN_PREFIX,
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| token.starts_with(TOKEN_PREFIX))
+}
+
+/// Finds the tenant that issued `token`.
+pub fn resolve<'a>(
+    tenants: impl IntoIterator<Item = &'a Tenant>,
+    token: &str,
+) -> Result<&'a Tenant, ScopeError> {
+    resolve_token(tenants, token).map(|(tenant, _)| tenant)
+}
+
+/// Finds the tenant that issued `token` and the token's record.
+pub fn resolve_token<'a>(
+    tenants: impl IntoIterator<Item = &'a Tenant>,
+    token: &str,
+) -> Result<(&'a Tenant, &'a TenantToken), ScopeError> {
+    let hash = hash_token(token);
+    let (tenant, record) = tenants
+        .into_iter()
+        .find_map(|tenant| {
+            tenant
+                .tokens
+                .iter()
+                .find(|t| t.token_hash == hash)
+                .map(|record| (tenant, record))
+        })
+        .ok_or(ScopeError::InvalidToken)?;
+    if !tenant.enabled {
+        return Err(ScopeError::TenantDisabled);
+    }
+    Ok((tenant, record))
+}
+
+/// Caller proven by an API token rather than asserted in headers: the token's
+/// name is the actor id and its roles are the caller's roles. The deployment
+/// admin token makes the caller an Admin named [`DEPLOYMENT_ADMIN_ACTOR`].
+pub async fn authenticated_caller(state: &AppState, req: &HttpRequest) -> Option<CallerContext> {
+    if is_deployment_admin(state, req) {
+        return Some(CallerContext {
+            actor_id: Some(DEPLOYMENT_ADMIN_ACTOR.to_string()),
+            actor_class: Some(ActorClass::Admin),
+            roles: Vec::new(),
+        });
+    }
+    let token = bearer_token(req)?;
+    let tenants = state.tenants.read().await;
+    let (_, record) = resolve_token(tenants.values(), token).ok()?;
+    Some(CallerContext {
+        actor_id: Some(record.name.clone()),
+        actor_class: record
+            .actor_class
+            .clone()
+            .filter(|class| *class != ActorClass::Admin),
+        roles: record.roles.clone(),
+    })
+}
+
+/// Whether the request presents `DEPLOYMENT_ADMIN_TOKEN` as its bearer token.
+pub fn is_deployment_admin(state: &AppState, req: &HttpRequest) -> bool {
+    let Some(expected) = state.admin_token_hash.as_deref() else {
+        return false;
+    };
+    req.headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| hash_token(token.trim()) == expected)
+}
+
+/// Scope of the request. The deployment admin token acts at deployment level.
+/// Requests without any token do too while no tenant exists, so single-plant
+/// clients keep working unchanged; once one does, they are rejected.
+pub async fn scope_for(state: &AppState, req: &HttpRequest) -> Result<TenantScope, ScopeError> {
+    if is_deployment_admin(state, req) {
+        return Ok(TenantScope::Deployment);
+    }
+    let tenants = state.tenants.read().await;
+    let Some(token) = bearer_token(req) else {
+        return anonymous_scope(tenants.is_empty());
+    };
+    resolve(tenants.values(), token).map(|tenant| TenantScope::Tenant(tenant.clone()))
+}
+
+fn anonymous_scope(no_tenants: bool) -> Result<TenantScope, ScopeError> {
+    if no_tenants {
+        Ok(TenantScope::Deployment)
+    } else {
+        Err(ScopeError::MissingToken)
+    }
+}
+
+/// PEA ids owned by the scope's tenant; empty at deployment level.
+pub async fn owned_pea_ids(state: &AppState, scope: &TenantScope) -> HashSet<String> {
+    let Some(tenant_id) = scope.tenant_id() else {
+        return HashSet::new();
+    };
+    state
+        .pea_configs
+        .read()
+        .await
+        .values()
+        .filter(|config| config.tenant_id.as_deref() == Some(tenant_id))
+        .map(|config| config.id.clone())
+        .collect()
+}
+
+/// The maps that tell which tenant a platform event belongs to, shared with `AppState`.
+#[derive(Clone, Default)]
+pub struct EventOwners {
+    pub tenants: Arc<RwLock<HashMap<String, Tenant>>>,
+    pub pea_configs: Arc<RwLock<HashMap<String, PeaConfig>>>,
+    pub recipes: Arc<RwLock<HashMap<String, Recipe>>>,
+}
+
+impl EventOwners {
+    pub async fn owner_of(&self, data: &serde_json::Value) -> Option<String> {
+        event_owner(
+            data,
+            &*self.tenants.read().await,
+            &*self.pea_configs.read().await,
+            &*self.recipes.read().await,
+        )
+    }
+}
+
+/// Tenant owning the PEA, recipe or alarm source an event names; `None` for
+/// deployment-level events.
+pub fn event_owner(
+    data: &serde_json::Value,
+    tenants: &HashMap<String, Tenant>,
+    pea_configs: &HashMap<String, PeaConfig>,
+    recipes: &HashMap<String, Recipe>,
+) -> Option<String> {
+    let field = |name: &str| data.get(name).and_then(|value| value.as_str());
+    if let Some(pea_id) = field("pea_id") {
+        return pea_configs.get(pea_id)?.tenant_id.clone();
+    }
+    if let Some(recipe_id) = field("recipe_id") {
+        return recipes.get(recipe_id)?.tenant_id.clone();
+    }
+    let source = field("source")?;
+    if let Some(config) = pea_configs.get(alarm_grouping::pea_of(source)) {
+        return config.tenant_id.clone();
+    }
+    tenants
+        .values()
+        .find(|tenant| tenant.owns_key(source))
+        .map(|tenant| tenant.id.clone())
+}
+
+/// Rejects adding `adding` more of `resource` when that would exceed the tenant's limit.
+pub fn check_quota(
+    limit: Option<usize>,
+    used: usize,
+    adding: usize,
+    resource: &str,
+) -> Option<HttpResponse> {
+    let limit = limit?;
+    (used + adding > limit).then(|| {
+        HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("Tenant quota exceeded: at most {} {}", limit, resource),
+            "limit": limit,
+            "used": used,
+        }))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tenant(id: &str, token: &str, enabled: bool) -> Tenant {
+        Tenant {
+            id: id.to_string(),
+            name: id.to_string(),
+            key_prefixes: vec![format!("tenants/{}", id)],
+            quotas: TenantQuotas::default(),
+            tokens: vec![TenantToken {
+                id: "t1".to_string(),
+                name: "ci".to_string(),
+                token_hash: hash_token(token),
+                roles: Vec::new(),
+                actor_class: None,
+                created_at: String::new(),
+            }],
+            enabled,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn tokens_resolve_to_their_tenant() {
+        let tenants = [
+            tenant("acme", "fdt_acme", true),
+            tenant("globex", "fdt_globex", true),
+            tenant("initech", "fdt_initech", false),
+        ];
+        assert_eq!(resolve(&tenants, "fdt_globex").unwrap().id, "globex");
+        let (_, record) = resolve_token(&tenants, "fdt_acme").unwrap();
+        assert_eq!(record.name, "ci");
+        assert_eq!(
+            resolve(&tenants, "fdt_unknown").unwrap_err(),
+            ScopeError::InvalidToken
+        );
+        assert_eq!(
+            resolve(&tenants, "fdt_initech").unwrap_err(),
+            ScopeError::TenantDisabled
+        );
+        assert!(generate_token().starts_with(TOKEN_PREFIX));
+    }
+
+    #[test]
+    fn anonymous_requests_need_a_token_once_tenants_exist() {
+        assert!(matches!(anonymous_scope(true), Ok(TenantScope::Deployment)));
+        assert_eq!(
+            anonymous_scope(false).unwrap_err(),
+            ScopeError::MissingToken
+        );
+    }
+
+    #[test]
+    fn tenant_scope_limits_resources_and_keys() {
+        let scope = TenantScope::Tenant(tenant("acme", "fdt_acme", true));
+        assert!(scope.allows(Some("acme")));
+        assert!(!scope.allows(Some("globex")));
+        assert!(!scope.allows(None));
+        assert!(scope.allows_key("tenants/acme/line1/temp"));
+        assert!(!scope.allows_key("tenants/acmeco/line1/temp"));
+
+        let owned: HashSet<String> = ["pea-1".to_string()].into_iter().collect();
+        assert!(scope.allows_source("pea-1", &owned));
+        assert!(!scope.allows_source("pea-2", &owned));
+
+        let deployment = TenantScope::Deployment;
+        assert!(deployment.allows(Some("globex")));
+        assert!(deployment.allows_key("entmoot/anything"));
+    }
+
+    #[test]
+    fn events_belong_to_the_tenant_of_their_pea_or_source() {
+        let tenants = HashMap::from([("acme".to_string(), tenant("acme", "fdt_acme", true))]);
+        let mut config = crate::test_fixtures::pea_config("p1");
+        config.tenant_id = Some("acme".to_string());
+        let configs = HashMap::from([("p1".to_string(), config)]);
+        let owner =
+            |data: serde_json::Value| event_owner(&data, &tenants, &configs, &HashMap::new());
+
+        assert_eq!(
+            owner(serde_json::json!({"pea_id": "p1"})).as_deref(),
+            Some("acme")
+        );
+        assert_eq!(
+            owner(serde_json::json!({"source": "entmoot/habitat/nodes/n1/pea/p1/swimlane/alarm"}))
+                .as_deref(),
+            Some("acme")
+        );
+        assert_eq!(
+            owner(serde_json::json!({"source": "tenants/acme/line1/temp"})).as_deref(),
+            Some("acme")
+        );
+        assert_eq!(owner(serde_json::json!({"source": "entmoot/other"})), None);
+        assert_eq!(owner(serde_json::json!({"recipe_id": "r1"})), None);
+    }
+
+    #[test]
+    fn quotas_reject_only_at_the_limit() {
+        assert!(check_quota(None, 100, 1, "PEAs").is_none());
+        assert!(check_quota(Some(2), 1, 1, "PEAs").is_none());
+        assert!(check_quota(Some(2), 2, 1, "PEAs").is_some());
+        assert!(check_quota(Some(5), 2, 4, "PEAs").is_some());
+    }
+}
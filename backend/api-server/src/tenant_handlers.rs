@@ -0,0 +1,320 @@
+use crate::request_context::CallerContext;
+use crate::runtime_store;
+use crate::state::AppState;
+use crate::tenancy::{self, Tenant, TenantQuotas, TenantScope, TenantToken};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use shared::domain::authority::ActorClass;
+use tracing::info;
+
+#[derive(serde::Deserialize)]
+pub struct TenantPayload {
+    pub name: String,
+    /// Defaults to `["tenants/<id>"]`.
+    pub key_prefixes: Option<Vec<String>>,
+    pub quotas: Option<TenantQuotas>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct TenantTokenPayload {
+    pub name: Option<String>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    pub actor_class: Option<ActorClass>,
+}
+
+/// GET /tenants — all tenants (deployment admins only).
+pub async fn list_tenants(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    if let Some(response) = reject_unless_admin(&state, &req).await {
+        return response;
+    }
+    let tenants = state.tenants.read().await;
+    let list: Vec<serde_json::Value> = tenants.values().map(Tenant::public_view).collect();
+    HttpResponse::Ok().json(list)
+}
+
+/// POST /tenants — create a tenant; `id` is derived from the name.
+pub async fn create_tenant(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<TenantPayload>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_admin(&state, &req).await {
+        return response;
+    }
+    let payload = body.into_inner();
+    let id = slugify(&payload.name);
+    if id.is_empty() {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": "Tenant name is required"}));
+    }
+
+    let mut tenants = state.tenants.write().await;
+    if tenants.contains_key(&id) {
+        return HttpResponse::Conflict()
+            .json(serde_json::json!({"error": format!("Tenant '{}' already exists", id)}));
+    }
+    let now = Utc::now().to_rfc3339();
+    let tenant = Tenant {
+        key_prefixes: payload
+            .key_prefixes
+            .unwrap_or_else(|| vec![format!("tenants/{}", id)]),
+        id: id.clone(),
+        name: payload.name.trim().to_string(),
+        quotas: payload.quotas.unwrap_or_default(),
+        tokens: Vec::new(),
+        enabled: payload.enabled.unwrap_or(true),
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    runtime_store::persist_json(&state.tenant_dir, &tenant.id, &tenant);
+    tenants.insert(id, tenant.clone());
+    info!("Created tenant {}", tenant.id);
+    HttpResponse::Created().json(tenant.public_view())
+}
+
+/// GET /tenants/{id}
+pub async fn get_tenant(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    tenant_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_admin(&state, &req).await {
+        return response;
+    }
+    match state.tenants.read().await.get(tenant_id.as_str()) {
+        Some(tenant) => HttpResponse::Ok().json(tenant.public_view()),
+        None => HttpResponse::NotFound().json(serde_json::json!({"error": "Tenant not found"})),
+    }
+}
+
+/// PUT /tenants/{id} — update name, key prefixes, quotas or the enabled flag.
+pub async fn update_tenant(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    tenant_id: web::Path<String>,
+    body: web::Json<TenantPayload>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_admin(&state, &req).await {
+        return response;
+    }
+    let payload = body.into_inner();
+    let mut tenants = state.tenants.write().await;
+    let Some(tenant) = tenants.get_mut(tenant_id.as_str()) else {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Tenant not found"}));
+    };
+    if !payload.name.trim().is_empty() {
+        tenant.name = payload.name.trim().to_string();
+    }
+    if let Some(prefixes) = payload.key_prefixes {
+        tenant.key_prefixes = prefixes;
+    }
+    if let Some(quotas) = payload.quotas {
+        tenant.quotas = quotas;
+    }
+    if let Some(enabled) = payload.enabled {
+        tenant.enabled = enabled;
+    }
+    tenant.updated_at = Utc::now().to_rfc3339();
+
+    runtime_store::persist_json(&state.tenant_dir, &tenant.id, &*tenant);
+    HttpResponse::Ok().json(tenant.public_view())
+}
+
+/// DELETE /tenants/{id} — only allowed once the tenant owns no PEAs or recipes.
+pub async fn delete_tenant(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    tenant_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_admin(&state, &req).await {
+        return response;
+    }
+    let (peas, recipes) = usage_counts(&state, &tenant_id).await;
+    if peas + recipes > 0 {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Tenant still owns resources",
+            "peas": peas,
+            "recipes": recipes,
+        }));
+    }
+    if state
+        .tenants
+        .write()
+        .await
+        .remove(tenant_id.as_str())
+        .is_none()
+    {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Tenant not found"}));
+    }
+    runtime_store::delete_json(&state.tenant_dir, tenant_id.as_str());
+    info!("Deleted tenant {}", tenant_id);
+    HttpResponse::NoContent().finish()
+}
+
+/// POST /tenants/{id}/tokens — issue a token. The plain token is only returned here.
+pub async fn create_tenant_token(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    tenant_id: web::Path<String>,
+    body: web::Json<TenantTokenPayload>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_admin(&state, &req).await {
+        return response;
+    }
+    let mut tenants = state.tenants.write().await;
+    let Some(tenant) = tenants.get_mut(tenant_id.as_str()) else {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Tenant not found"}));
+    };
+
+    let body = body.into_inner();
+    if body.actor_class == Some(ActorClass::Admin) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Tenant tokens cannot carry the Admin class"
+        }));
+    }
+    let token = tenancy::generate_token();
+    let record = TenantToken {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: body.name.unwrap_or_else(|| "default".to_string()),
+        token_hash: tenancy::hash_token(&token),
+        roles: body
+            .roles
+            .iter()
+            .map(|role| role.trim().to_ascii_lowercase())
+            .filter(|role| !role.is_empty())
+            .collect(),
+        actor_class: body.actor_class,
+        created_at: Utc::now().to_rfc3339(),
+    };
+    tenant.tokens.push(record.clone());
+    tenant.updated_at = Utc::now().to_rfc3339();
+    runtime_store::persist_json(&state.tenant_dir, &tenant.id, &*tenant);
+
+    info!("Issued token {} for tenant {}", record.id, tenant.id);
+    HttpResponse::Created().json(serde_json::json!({
+        "id": record.id,
+        "name": record.name,
+        "roles": record.roles,
+        "actor_class": record.actor_class,
+        "tenant_id": tenant.id,
+        "token": token,
+        "created_at": record.created_at,
+    }))
+}
+
+/// DELETE /tenants/{id}/tokens/{token_id} — revoke a token.
+pub async fn delete_tenant_token(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    if let Some(response) = reject_unless_admin(&state, &req).await {
+        return response;
+    }
+    let (tenant_id, token_id) = path.into_inner();
+    let mut tenants = state.tenants.write().await;
+    let Some(tenant) = tenants.get_mut(&tenant_id) else {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Tenant not found"}));
+    };
+    let before = tenant.tokens.len();
+    tenant.tokens.retain(|token| token.id != token_id);
+    if tenant.tokens.len() == before {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Token not found"}));
+    }
+    tenant.updated_at = Utc::now().to_rfc3339();
+    runtime_store::persist_json(&state.tenant_dir, &tenant.id, &*tenant);
+    HttpResponse::NoContent().finish()
+}
+
+/// GET /tenants/{id}/usage — resource counts against quotas. Tenants may read their own.
+pub async fn get_tenant_usage(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    tenant_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    match &scope {
+        TenantScope::Tenant(tenant) if tenant.id != *tenant_id => {
+            return HttpResponse::NotFound().json(serde_json::json!({"error": "Tenant not found"}));
+        }
+        TenantScope::Deployment => {
+            if let Some(response) = reject_unless_admin(&state, &req).await {
+                return response;
+            }
+        }
+        TenantScope::Tenant(_) => {}
+    }
+
+    let Some(tenant) = state.tenants.read().await.get(tenant_id.as_str()).cloned() else {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Tenant not found"}));
+    };
+    let (peas, recipes) = usage_counts(&state, &tenant.id).await;
+    let ts_keys = {
+        let store = state.timeseries.read().await;
+        store
+            .keys()
+            .into_iter()
+            .filter(|key| tenant.owns_key(key))
+            .count()
+    };
+    HttpResponse::Ok().json(serde_json::json!({
+        "tenant_id": tenant.id,
+        "peas": { "used": peas, "limit": tenant.quotas.max_peas },
+        "recipes": { "used": recipes, "limit": tenant.quotas.max_recipes },
+        "ts_keys": ts_keys,
+    }))
+}
+
+/// Number of PEAs and recipes owned by a tenant.
+pub async fn usage_counts(state: &AppState, tenant_id: &str) -> (usize, usize) {
+    let peas = state
+        .pea_configs
+        .read()
+        .await
+        .values()
+        .filter(|pea| pea.tenant_id.as_deref() == Some(tenant_id))
+        .count();
+    let recipes = state
+        .recipes
+        .read()
+        .await
+        .values()
+        .filter(|recipe| recipe.tenant_id.as_deref() == Some(tenant_id))
+        .count();
+    (peas, recipes)
+}
+
+/// Tenant management needs the deployment admin token; tenant tokens and
+/// header-asserted Admins are refused.
+async fn reject_unless_admin(state: &AppState, req: &HttpRequest) -> Option<HttpResponse> {
+    if tenancy::is_deployment_admin(state, req) {
+        info!(
+            "Tenant configuration accessed by {}",
+            CallerContext::from_request(req)
+                .actor_id
+                .as_deref()
+                .unwrap_or("unknown actor")
+        );
+        return None;
+    }
+    Some(HttpResponse::Forbidden().json(serde_json::json!({
+        "error": "Managing tenants requires the deployment admin token (DEPLOYMENT_ADMIN_TOKEN)"
+    })))
+}
+
+fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.trim().to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_string()
+}
@@ -0,0 +1,20 @@
+//! Fixtures for api-server types, built on the shared `test_support::fixtures`
+//! so unit tests and integration tests agree on what a PEA or alarm looks like.
+
+use crate::state::{AlarmRecord, TimeSeriesPoint};
+
+pub use test_support::fixtures::*;
+
+/// Open alarm of `pea_id`, see `test_support::fixtures::alarm`.
+pub fn alarm_record(id: &str, pea_id: &str, severity: &str) -> AlarmRecord {
+    serde_json::from_value(alarm(id, pea_id, severity)).expect("fixture alarm is an AlarmRecord")
+}
+
+/// Buffered point without a recorded producer.
+pub fn point(timestamp_ms: i64, value: impl Into<serde_json::Value>) -> TimeSeriesPoint {
+    TimeSeriesPoint {
+        timestamp_ms,
+        value: value.into(),
+        producer: None,
+    }
+}
@@ -1,8 +1,24 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use serde::Deserialize;
 
+use crate::db;
+use crate::redaction::RedactionPolicy;
+use crate::redaction_handlers;
+use crate::request_context::CallerContext;
 use crate::runtime_store;
 use crate::state::{AppState, TimeSeriesPoint};
+use crate::tenancy;
+use crate::ts_aliases::{self, KeyAlias};
+use crate::ts_archive;
+use crate::ts_bands::{self, ThresholdBand};
+use crate::ts_correlation;
+use crate::ts_counters::{self, KeyTypeHint};
+use crate::ts_distribution;
+use crate::ts_forecast::{self, Model};
+use crate::ts_historian;
+use crate::ts_provenance::{self, Producer};
+use crate::ts_saved_query::SavedQuery;
+use crate::ts_segments;
 
 #[derive(Deserialize)]
 pub struct TsQuery {
@@ -16,6 +32,78 @@ pub struct TsQuery {
     pub max_points: Option<usize>,
 }
 
+#[derive(Deserialize)]
+pub struct TsQueryParams {
+    /// Exact key to query; required unless `saved` is given
+    pub key: Option<String>,
+    /// Id of a saved query whose keys, window and sampling are used
+    pub saved: Option<String>,
+    /// Start of range as Unix milliseconds; overrides the saved window
+    pub start_ms: Option<i64>,
+    /// End of range as Unix milliseconds; overrides the saved window
+    pub end_ms: Option<i64>,
+    /// Optional max points to return after downsampling; overrides the saved setting
+    pub max_points: Option<usize>,
+    /// Comma-separated producers to keep, e.g. "eva-ics-connector,underhill"
+    pub producer: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TsCorrelateQuery {
+    /// Exactly two comma-separated keys, e.g. "rpm,vibration"
+    pub keys: String,
+    /// Length of the analysed window in milliseconds, ending at `end_ms`
+    pub window: Option<i64>,
+    /// End of the window as Unix milliseconds; defaults to now
+    pub end_ms: Option<i64>,
+    /// Largest lag tried in each direction, in milliseconds; defaults to a quarter of the window
+    pub max_lag_ms: Option<i64>,
+    /// Number of slots the window is resampled into
+    pub buckets: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct TsForecastQuery {
+    /// Exact key to project
+    pub key: String,
+    /// How far ahead to project, in milliseconds
+    pub horizon: i64,
+    /// Length of the history the model is fitted to, in milliseconds, ending now
+    pub window: Option<i64>,
+    /// `holt` (default) or `linear`
+    pub model: Option<String>,
+    /// Extra limit to report a crossing for, besides the key's threshold band
+    pub limit: Option<f64>,
+}
+
+#[derive(Deserialize)]
+pub struct TsDistributionQuery {
+    /// Exact key to analyse
+    pub key: String,
+    /// Start of range as Unix milliseconds; defaults to one hour before `to`
+    pub from: Option<i64>,
+    /// End of range as Unix milliseconds; defaults to now
+    pub to: Option<i64>,
+    /// Number of histogram buckets
+    pub buckets: Option<usize>,
+    /// Lower specification limit for Cp/Cpk
+    pub lsl: Option<f64>,
+    /// Upper specification limit for Cp/Cpk
+    pub usl: Option<f64>,
+    /// Key holding the machine state; the values are also summarized per state
+    pub segment_by: Option<String>,
+    /// Service whose state is used when `segment_by` is a PEA status key
+    pub service: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TsArchiveQuery {
+    /// Restrict the listing to one key
+    pub key: Option<String>,
+    pub start_ms: Option<i64>,
+    pub end_ms: Option<i64>,
+}
+
 #[derive(Deserialize)]
 pub struct TsConfigUpdateRequest {
     pub max_points_per_key: usize,
@@ -27,23 +115,41 @@ pub struct TimeSeriesConfigRecord {
 }
 
 /// GET /ts/keys — list all key expressions with stored time-series data.
-pub async fn get_ts_keys(state: web::Data<AppState>) -> impl Responder {
+pub async fn get_ts_keys(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let policy = redaction_handlers::policy_for(&state, &req).await;
     let store = state.timeseries.read().await;
-    let keys: Vec<&String> = store.keys();
+    let keys: Vec<&String> = store
+        .keys()
+        .into_iter()
+        .filter(|key| scope.allows_key(key) && !policy.hides_key(key))
+        .collect();
     HttpResponse::Ok().json(serde_json::json!({ "keys": keys }))
 }
 
 /// GET /ts/latest — return the most recent value for every stored key.
-pub async fn get_ts_latest(state: web::Data<AppState>) -> impl Responder {
+pub async fn get_ts_latest(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let policy = redaction_handlers::policy_for(&state, &req).await;
     let store = state.timeseries.read().await;
     let mut entries = serde_json::Map::new();
-    for (key, buf) in &store.data {
+    for (key, buf) in store.data.iter().filter(|(key, _)| scope.allows_key(key)) {
         if let Some(last) = buf.back() {
+            let Some(value) = policy.apply(key, last.value.clone()) else {
+                continue;
+            };
             entries.insert(
                 key.clone(),
                 serde_json::json!({
                     "t": last.timestamp_ms,
-                    "v": last.value,
+                    "v": value,
+                    "producer": last.producer,
                 }),
             );
         }
@@ -83,16 +189,1151 @@ pub async fn update_ts_config(
     }))
 }
 
-/// GET /ts/query?key=...&start_ms=...&end_ms=...&max_points=... — query historical data for a key.
+/// GET /ts/query?key=...&start_ms=...&end_ms=...&max_points=... — query historical data for a
+/// key, or for the keys of a saved query with `saved=<id>`.
 pub async fn query_timeseries(
+    req: HttpRequest,
     state: web::Data<AppState>,
-    query: web::Query<TsQuery>,
+    query: web::Query<TsQueryParams>,
 ) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let policy = redaction_handlers::policy_for(&state, &req).await;
+    let producers = match query.producer.as_deref().map(ts_provenance::parse_filter) {
+        Some(Ok(producers)) => producers,
+        Some(Err(e)) => return HttpResponse::BadRequest().json(serde_json::json!({"error": e})),
+        None => Vec::new(),
+    };
+
+    let Some(saved_id) = query.saved.as_deref() else {
+        let (Some(key), Some(start_ms), Some(end_ms)) =
+            (query.key.as_deref(), query.start_ms, query.end_ms)
+        else {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "key, start_ms and end_ms are required unless saved is given"
+            }));
+        };
+        if !scope.allows_key(key) {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Key not found",
+                "key": key,
+            }));
+        }
+        if policy.hides_key(key) {
+            return HttpResponse::Forbidden().json(serde_json::json!({
+                "error": "Key is marked sensitive",
+                "key": key,
+            }));
+        }
+        let annotations = KeyAnnotations::load(&state, &scope).await;
+        let points = aliased_points(&state, &annotations.aliases, key, start_ms, end_ms).await;
+        let points = from_producers(points, &producers);
+        let max_points = query.max_points.filter(|value| *value > 0);
+        return HttpResponse::Ok().json(key_series(
+            points,
+            &policy,
+            &annotations,
+            key,
+            start_ms,
+            end_ms,
+            max_points,
+        ));
+    };
+
+    if query.key.is_some() {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": "Give either key or saved, not both"}));
+    }
+    let saved = match state.ts_saved_queries.read().await.get(saved_id) {
+        Some(saved) if scope.allows(saved.tenant_id.as_deref()) => saved.clone(),
+        _ => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"error": "Saved query not found"}))
+        }
+    };
+    let (saved_start_ms, saved_end_ms) = saved.range(chrono::Utc::now().timestamp_millis());
+    let start_ms = query.start_ms.unwrap_or(saved_start_ms);
+    let end_ms = query.end_ms.unwrap_or(saved_end_ms);
+    let max_points = query
+        .max_points
+        .or(saved.max_points)
+        .filter(|value| *value > 0);
+
+    let annotations = KeyAnnotations::load(&state, &scope).await;
+    let mut series: Vec<serde_json::Value> = Vec::with_capacity(saved.keys.len());
+    for key in &saved.keys {
+        series.push(if !scope.allows_key(key) {
+            serde_json::json!({"key": key, "error": "Key not found"})
+        } else if policy.hides_key(key) {
+            serde_json::json!({"key": key, "error": "Key is marked sensitive"})
+        } else {
+            let points = aliased_points(&state, &annotations.aliases, key, start_ms, end_ms).await;
+            let points = from_producers(points, &producers);
+            key_series(
+                points,
+                &policy,
+                &annotations,
+                key,
+                start_ms,
+                end_ms,
+                max_points,
+            )
+        });
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "saved_query": saved,
+        "start_ms": start_ms,
+        "end_ms": end_ms,
+        "max_points": max_points,
+        "series": series,
+    }))
+}
+
+/// Threshold bands, type hints and key aliases visible to the caller.
+struct KeyAnnotations {
+    bands: Vec<ThresholdBand>,
+    hints: Vec<KeyTypeHint>,
+    aliases: Vec<KeyAlias>,
+}
+
+impl KeyAnnotations {
+    async fn load(state: &AppState, scope: &tenancy::TenantScope) -> Self {
+        let hints = state
+            .ts_key_hints
+            .read()
+            .await
+            .values()
+            .filter(|hint| scope.allows(hint.tenant_id.as_deref()))
+            .cloned()
+            .collect();
+        Self {
+            bands: visible_bands(state, scope).await,
+            hints,
+            aliases: visible_aliases(state, scope).await,
+        }
+    }
+}
+
+/// Points of `key` merged with those recorded under its earlier names; where
+/// two names hold a point at the same time, the newer name wins.
+async fn aliased_points(
+    state: &AppState,
+    aliases: &[KeyAlias],
+    key: &str,
+    start_ms: i64,
+    end_ms: i64,
+) -> Vec<TimeSeriesPoint> {
+    let mut points = ts_historian::points(state, key, start_ms, end_ms).await;
+    for old_key in ts_aliases::old_keys(aliases, key) {
+        let older = ts_historian::points(state, &old_key, start_ms, end_ms).await;
+        points = ts_historian::merge(older, points);
+    }
+    points
+}
+
+/// Points recorded by one of `producers`; all points when none are given.
+fn from_producers(points: Vec<TimeSeriesPoint>, producers: &[Producer]) -> Vec<TimeSeriesPoint> {
+    if producers.is_empty() {
+        return points;
+    }
+    points
+        .into_iter()
+        .filter(|point| point.producer.is_some_and(|p| producers.contains(&p)))
+        .collect()
+}
+
+/// Points of one key in `[start_ms, end_ms]`, redacted and downsampled, as returned by
+/// `/ts/query`, with the threshold band that applies to the key. Counter keys also get
+/// the increase per point and in total.
+fn key_series(
+    raw_points: Vec<TimeSeriesPoint>,
+    policy: &RedactionPolicy,
+    annotations: &KeyAnnotations,
+    key: &str,
+    start_ms: i64,
+    end_ms: i64,
+    max_points: Option<usize>,
+) -> serde_json::Value {
+    let points: Vec<TimeSeriesPoint> = if policy.is_empty() {
+        raw_points
+    } else {
+        raw_points
+            .into_iter()
+            .filter_map(|point| {
+                policy.apply(key, point.value).map(|value| TimeSeriesPoint {
+                    timestamp_ms: point.timestamp_ms,
+                    value,
+                    producer: point.producer,
+                })
+            })
+            .collect()
+    };
+    let points: Vec<&TimeSeriesPoint> = points.iter().collect();
+    let original_count = points.len();
+    let band = ts_bands::band_for(&annotations.bands, key);
+    let hint = ts_counters::hint_for(&annotations.hints, key).filter(|hint| hint.is_counter());
+
+    let Some(hint) = hint else {
+        let result = downsample_points(points, max_points);
+        return serde_json::json!({
+            "key": key,
+            "kind": ts_counters::KIND_GAUGE,
+            "start_ms": start_ms,
+            "end_ms": end_ms,
+            "count": result.len(),
+            "original_count": original_count,
+            "sampled": max_points.is_some_and(|limit| original_count > limit),
+            "max_points": max_points,
+            "points": result,
+            "bands": band,
+        });
+    };
+
+    let readings: Vec<(i64, f64)> = points
+        .iter()
+        .filter_map(|point| Some((point.timestamp_ms, extract_numeric_value(&point.value)?)))
+        .collect();
+    let counter = ts_counters::deltas(&readings, hint.rollover_at);
+    let result = downsample_counter(&counter, max_points);
+    serde_json::json!({
+        "key": key,
+        "kind": ts_counters::KIND_COUNTER,
+        "start_ms": start_ms,
+        "end_ms": end_ms,
+        "count": result.len(),
+        "original_count": original_count,
+        "sampled": max_points.is_some_and(|limit| counter.points.len() > limit),
+        "max_points": max_points,
+        "points": result,
+        "total": counter.total,
+        "rollovers": counter.rollovers,
+        "resets": counter.resets,
+        "rollover_at": hint.rollover_at,
+        "bands": band,
+    })
+}
+
+async fn visible_bands(state: &AppState, scope: &tenancy::TenantScope) -> Vec<ThresholdBand> {
+    state
+        .ts_bands
+        .read()
+        .await
+        .values()
+        .filter(|band| scope.allows(band.tenant_id.as_deref()))
+        .cloned()
+        .collect()
+}
+
+/// GET /ts/saved-queries — list saved explorer queries
+pub async fn list_saved_queries(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let saved = state.ts_saved_queries.read().await;
+    let mut list: Vec<&SavedQuery> = saved
+        .values()
+        .filter(|query| scope.allows(query.tenant_id.as_deref()))
+        .collect();
+    list.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+    HttpResponse::Ok().json(list)
+}
+
+/// GET /ts/saved-queries/{id} — fetch one saved query
+pub async fn get_saved_query(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    match state.ts_saved_queries.read().await.get(query_id.as_str()) {
+        Some(saved) if scope.allows(saved.tenant_id.as_deref()) => HttpResponse::Ok().json(saved),
+        _ => HttpResponse::NotFound().json(serde_json::json!({"error": "Saved query not found"})),
+    }
+}
+
+/// POST /ts/saved-queries — save a query for sharing
+pub async fn create_saved_query(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<SavedQuery>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut saved = body.into_inner();
+    if let Err(e) = check_saved_query(&saved, &scope) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    if saved.id.is_empty() {
+        saved.id = uuid::Uuid::new_v4().to_string();
+    }
+    let mut queries = state.ts_saved_queries.write().await;
+    if queries.contains_key(&saved.id) {
+        return HttpResponse::Conflict()
+            .json(serde_json::json!({"error": "Saved query id is already in use"}));
+    }
+    let now = chrono::Utc::now().to_rfc3339();
+    saved.tenant_id = scope.tenant_id().map(str::to_string);
+    saved.created_by = CallerContext::from_request(&req).actor_id;
+    saved.created_at = now.clone();
+    saved.updated_at = now;
+    runtime_store::persist_json(&state.ts_saved_query_dir, &saved.id, &saved);
+    queries.insert(saved.id.clone(), saved.clone());
+    HttpResponse::Created().json(saved)
+}
+
+/// PUT /ts/saved-queries/{id} — replace a saved query
+pub async fn update_saved_query(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query_id: web::Path<String>,
+    body: web::Json<SavedQuery>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut saved = body.into_inner();
+    if let Err(e) = check_saved_query(&saved, &scope) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    let mut queries = state.ts_saved_queries.write().await;
+    let existing = match queries.get(query_id.as_str()) {
+        Some(existing) if scope.allows(existing.tenant_id.as_deref()) => existing,
+        _ => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"error": "Saved query not found"}))
+        }
+    };
+    saved.id = existing.id.clone();
+    saved.tenant_id = existing.tenant_id.clone();
+    saved.created_by = existing.created_by.clone();
+    saved.created_at = existing.created_at.clone();
+    saved.updated_at = chrono::Utc::now().to_rfc3339();
+    runtime_store::persist_json(&state.ts_saved_query_dir, &saved.id, &saved);
+    queries.insert(saved.id.clone(), saved.clone());
+    HttpResponse::Ok().json(saved)
+}
+
+/// DELETE /ts/saved-queries/{id} — remove a saved query
+pub async fn delete_saved_query(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut queries = state.ts_saved_queries.write().await;
+    match queries.get(query_id.as_str()) {
+        Some(saved) if scope.allows(saved.tenant_id.as_deref()) => {}
+        _ => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"error": "Saved query not found"}))
+        }
+    }
+    queries.remove(query_id.as_str());
+    runtime_store::delete_json(&state.ts_saved_query_dir, &query_id);
+    HttpResponse::NoContent().finish()
+}
+
+const DEFAULT_CORRELATE_WINDOW_MS: i64 = 3_600_000;
+const DEFAULT_CORRELATE_BUCKETS: usize = 500;
+const MAX_CORRELATE_BUCKETS: usize = 5000;
+
+/// GET /ts/correlate?keys=a,b&window=...&max_lag_ms=... — lagged cross-correlation of two keys.
+pub async fn correlate_timeseries(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<TsCorrelateQuery>,
+) -> impl Responder {
+    let keys: Vec<&str> = query
+        .keys
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .collect();
+    let [key_a, key_b] = keys[..] else {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": "keys must name exactly two keys"}));
+    };
+    let window_ms = query.window.unwrap_or(DEFAULT_CORRELATE_WINDOW_MS);
+    let buckets = query
+        .buckets
+        .unwrap_or(DEFAULT_CORRELATE_BUCKETS)
+        .clamp(10, MAX_CORRELATE_BUCKETS);
+    if window_ms < buckets as i64 {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("window must be at least {} ms", buckets)
+        }));
+    }
+    let end_ms = query
+        .end_ms
+        .unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
+    let start_ms = end_ms - window_ms;
+    let step_ms = window_ms / buckets as i64;
+    let max_lag = (query.max_lag_ms.unwrap_or(window_ms / 4).max(0) / step_ms) as usize;
+    let max_lag = max_lag.min(buckets / 2);
+
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let policy = redaction_handlers::policy_for(&state, &req).await;
     let store = state.timeseries.read().await;
-    let points = store.query(&query.key, query.start_ms, query.end_ms);
+    let mut series = Vec::with_capacity(2);
+    for key in [key_a, key_b] {
+        if !scope.allows_key(key) {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Key not found",
+                "key": key,
+            }));
+        }
+        if policy.hides_key(key) {
+            return HttpResponse::Forbidden().json(serde_json::json!({
+                "error": "Key is marked sensitive",
+                "key": key,
+            }));
+        }
+        let points: Vec<(i64, f64)> = store
+            .query(key, start_ms, end_ms)
+            .into_iter()
+            .filter_map(|point| {
+                let value = policy.apply(key, point.value.clone())?;
+                Some((point.timestamp_ms, extract_numeric_value(&value)?))
+            })
+            .collect();
+        series.push((
+            points.len(),
+            ts_correlation::resample(&points, start_ms, step_ms, buckets),
+        ));
+    }
+    drop(store);
+
+    let lags: Vec<serde_json::Value> =
+        ts_correlation::cross_correlation(&series[0].1, &series[1].1, max_lag)
+            .into_iter()
+            .map(|(lag, r)| serde_json::json!({"lag": lag, "lag_ms": lag * step_ms, "r": r}))
+            .collect();
+    let best = lags
+        .iter()
+        .filter(|entry| entry["r"].is_number())
+        .max_by(|x, y| {
+            let r = |entry: &serde_json::Value| entry["r"].as_f64().unwrap_or_default().abs();
+            r(x).total_cmp(&r(y))
+        });
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "keys": [key_a, key_b],
+        "start_ms": start_ms,
+        "end_ms": end_ms,
+        "step_ms": step_ms,
+        "point_counts": [series[0].0, series[1].0],
+        "best": best,
+        "lags": lags,
+    }))
+}
+
+const DEFAULT_FORECAST_WINDOW_MS: i64 = 86_400_000;
+const FORECAST_BUCKETS: usize = 200;
+const FORECAST_POINTS: i64 = 50;
+
+/// GET /ts/forecast — projects a key's recent history over `horizon` ms and
+/// reports when the projection crosses the limits of its threshold band
+pub async fn forecast_timeseries(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<TsForecastQuery>,
+) -> impl Responder {
+    let Some(model) = Model::parse(query.model.as_deref().unwrap_or("holt")) else {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": "model must be holt or linear"}));
+    };
+    let window_ms = query.window.unwrap_or(DEFAULT_FORECAST_WINDOW_MS);
+    if query.horizon <= 0 || window_ms < FORECAST_BUCKETS as i64 {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!(
+                "horizon must be positive and window at least {} ms",
+                FORECAST_BUCKETS
+            )
+        }));
+    }
+    let end_ms = chrono::Utc::now().timestamp_millis();
+    let start_ms = end_ms - window_ms;
+
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    if !scope.allows_key(&query.key) {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Key not found"}));
+    }
+    let policy = redaction_handlers::policy_for(&state, &req).await;
+    if policy.hides_key(&query.key) {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({"error": "Key is marked sensitive"}));
+    }
+    let points: Vec<(i64, f64)> = state
+        .timeseries
+        .read()
+        .await
+        .query(&query.key, start_ms, end_ms)
+        .into_iter()
+        .filter_map(|point| {
+            let value = policy.apply(&query.key, point.value.clone())?;
+            Some((point.timestamp_ms, extract_numeric_value(&value)?))
+        })
+        .collect();
+
+    let trend = match model {
+        Model::Linear => ts_forecast::linear_trend(&points),
+        Model::Holt => {
+            let step_ms = window_ms / FORECAST_BUCKETS as i64;
+            let slots = ts_correlation::resample(&points, start_ms, step_ms, FORECAST_BUCKETS);
+            ts_forecast::holt_trend(&slots, start_ms, step_ms)
+        }
+    };
+    let Some(trend) = trend else {
+        return HttpResponse::UnprocessableEntity().json(serde_json::json!({
+            "error": "Not enough numeric history in the window to fit a trend",
+            "point_count": points.len(),
+        }));
+    };
+
+    let until_ms = end_ms + query.horizon;
+    let bands = visible_bands(&state, &scope).await;
+    let band = ts_bands::band_for(&bands, &query.key);
+    let step_ms = (until_ms - trend.at_ms) / FORECAST_POINTS;
+    let projection: Vec<serde_json::Value> = (1..=FORECAST_POINTS)
+        .map(|i| {
+            let t = trend.at_ms + i * step_ms;
+            serde_json::json!({"timestamp_ms": t, "value": trend.value_at(t)})
+        })
+        .collect();
+    HttpResponse::Ok().json(serde_json::json!({
+        "key": query.key,
+        "model": model,
+        "start_ms": start_ms,
+        "end_ms": end_ms,
+        "horizon_ms": query.horizon,
+        "point_count": points.len(),
+        "value": trend.value,
+        "value_at_ms": trend.at_ms,
+        "slope_per_hour": trend.slope_per_ms * 3_600_000.0,
+        "band": band,
+        "crossings": ts_forecast::crossings(&trend, band, query.limit, until_ms),
+        "projection": projection,
+    }))
+}
+
+const DEFAULT_DISTRIBUTION_RANGE_MS: i64 = 3_600_000;
+const DEFAULT_DISTRIBUTION_BUCKETS: usize = 20;
+const MAX_DISTRIBUTION_BUCKETS: usize = 500;
+
+/// GET /ts/stats/distribution — histogram, percentiles and basic statistics
+/// of a key's values over a range, with Cp/Cpk when specification limits are given
+pub async fn get_ts_distribution(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<TsDistributionQuery>,
+) -> impl Responder {
+    let end_ms = query
+        .to
+        .unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
+    let start_ms = query.from.unwrap_or(end_ms - DEFAULT_DISTRIBUTION_RANGE_MS);
+    if start_ms > end_ms {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": "from must not be after to"}));
+    }
+    if query.lsl.zip(query.usl).is_some_and(|(lsl, usl)| lsl >= usl) {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": "lsl must be below usl"}));
+    }
+    let buckets = query
+        .buckets
+        .unwrap_or(DEFAULT_DISTRIBUTION_BUCKETS)
+        .clamp(1, MAX_DISTRIBUTION_BUCKETS);
+
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    if !scope.allows_key(&query.key) {
+        return HttpResponse::NotFound().json(serde_json::json!({"error": "Key not found"}));
+    }
+    let policy = redaction_handlers::policy_for(&state, &req).await;
+    if policy.hides_key(&query.key) {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({"error": "Key is marked sensitive"}));
+    }
+    if let Some(state_key) = &query.segment_by {
+        if !scope.allows_key(state_key) || policy.hides_key(state_key) {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"error": "State key not found", "key": state_key}));
+        }
+    }
+    let aliases = visible_aliases(&state, &scope).await;
+    let points: Vec<(i64, f64)> = aliased_points(&state, &aliases, &query.key, start_ms, end_ms)
+        .await
+        .into_iter()
+        .filter_map(|point| {
+            let value = policy.apply(&query.key, point.value)?;
+            Some((point.timestamp_ms, extract_numeric_value(&value)?))
+        })
+        .collect();
+    let values: Vec<f64> = points.iter().map(|(_, v)| *v).collect();
+
+    let mut body = match ts_distribution::distribution(&values, buckets) {
+        Some(distribution) => {
+            let capability = (query.lsl.is_some() || query.usl.is_some())
+                .then(|| ts_distribution::capability(&distribution, query.lsl, query.usl));
+            serde_json::json!({
+                "key": query.key,
+                "start_ms": start_ms,
+                "end_ms": end_ms,
+                "count": distribution.count,
+                "min": distribution.min,
+                "max": distribution.max,
+                "mean": distribution.mean,
+                "std_dev": distribution.std_dev,
+                "percentiles": distribution.percentiles,
+                "histogram": distribution.histogram,
+                "capability": capability,
+            })
+        }
+        None => serde_json::json!({
+            "key": query.key,
+            "start_ms": start_ms,
+            "end_ms": end_ms,
+            "count": 0,
+        }),
+    };
+    if let Some(state_key) = &query.segment_by {
+        let lookback_ms = start_ms.saturating_sub(ts_segments::STATE_LOOKBACK_MS);
+        let samples: Vec<(i64, String)> =
+            aliased_points(&state, &aliases, state_key, lookback_ms, end_ms)
+                .await
+                .into_iter()
+                .filter_map(|point| {
+                    let value = policy.apply(state_key, point.value)?;
+                    let label = ts_segments::state_label(&value, query.service.as_deref())?;
+                    Some((point.timestamp_ms, label))
+                })
+                .collect();
+        let spans = ts_segments::spans(&samples, start_ms, end_ms);
+        let (segments, unsegmented) = ts_segments::segment(&points, &spans, buckets);
+        body["segment_by"] = serde_json::json!(state_key);
+        body["segments"] = serde_json::json!(segments);
+        body["unsegmented"] = serde_json::json!(unsegmented);
+    }
+    HttpResponse::Ok().json(body)
+}
+
+/// GET /ts/bands — list threshold bands
+pub async fn list_bands(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut bands = visible_bands(&state, &scope).await;
+    bands.sort_by(|a, b| a.key_expr.cmp(&b.key_expr).then_with(|| a.id.cmp(&b.id)));
+    HttpResponse::Ok().json(bands)
+}
+
+/// POST /ts/bands — attach warning/critical limits to keys
+pub async fn create_band(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<ThresholdBand>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut band = body.into_inner();
+    if let Err(e) = check_band(&band, &scope) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    if band.id.is_empty() {
+        band.id = uuid::Uuid::new_v4().to_string();
+    }
+    let mut bands = state.ts_bands.write().await;
+    if bands.contains_key(&band.id) {
+        return HttpResponse::Conflict()
+            .json(serde_json::json!({"error": "Band id is already in use"}));
+    }
+    band.tenant_id = scope.tenant_id().map(str::to_string);
+    band.updated_at = chrono::Utc::now().to_rfc3339();
+    runtime_store::persist_json(&state.ts_band_dir, &band.id, &band);
+    bands.insert(band.id.clone(), band.clone());
+    HttpResponse::Created().json(band)
+}
+
+/// PUT /ts/bands/{id} — replace a threshold band
+pub async fn update_band(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    band_id: web::Path<String>,
+    body: web::Json<ThresholdBand>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut band = body.into_inner();
+    if let Err(e) = check_band(&band, &scope) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    let mut bands = state.ts_bands.write().await;
+    let existing = match bands.get(band_id.as_str()) {
+        Some(existing) if scope.allows(existing.tenant_id.as_deref()) => existing,
+        _ => return HttpResponse::NotFound().json(serde_json::json!({"error": "Band not found"})),
+    };
+    band.id = existing.id.clone();
+    band.tenant_id = existing.tenant_id.clone();
+    band.updated_at = chrono::Utc::now().to_rfc3339();
+    runtime_store::persist_json(&state.ts_band_dir, &band.id, &band);
+    bands.insert(band.id.clone(), band.clone());
+    HttpResponse::Ok().json(band)
+}
+
+/// DELETE /ts/bands/{id} — remove a threshold band
+pub async fn delete_band(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    band_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut bands = state.ts_bands.write().await;
+    match bands.get(band_id.as_str()) {
+        Some(band) if scope.allows(band.tenant_id.as_deref()) => {}
+        _ => return HttpResponse::NotFound().json(serde_json::json!({"error": "Band not found"})),
+    }
+    bands.remove(band_id.as_str());
+    runtime_store::delete_json(&state.ts_band_dir, &band_id);
+    HttpResponse::NoContent().finish()
+}
+
+/// GET /ts/key-hints — list gauge/counter type hints
+pub async fn list_key_hints(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut hints = KeyAnnotations::load(&state, &scope).await.hints;
+    hints.sort_by(|a, b| a.key_expr.cmp(&b.key_expr).then_with(|| a.id.cmp(&b.id)));
+    HttpResponse::Ok().json(hints)
+}
+
+/// POST /ts/key-hints — mark keys as gauges or counters
+pub async fn create_key_hint(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<KeyTypeHint>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut hint = body.into_inner();
+    if let Err(e) = check_key_hint(&hint, &scope) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    if hint.id.is_empty() {
+        hint.id = uuid::Uuid::new_v4().to_string();
+    }
+    let mut hints = state.ts_key_hints.write().await;
+    if hints.contains_key(&hint.id) {
+        return HttpResponse::Conflict()
+            .json(serde_json::json!({"error": "Key hint id is already in use"}));
+    }
+    hint.tenant_id = scope.tenant_id().map(str::to_string);
+    hint.updated_at = chrono::Utc::now().to_rfc3339();
+    runtime_store::persist_json(&state.ts_key_hint_dir, &hint.id, &hint);
+    hints.insert(hint.id.clone(), hint.clone());
+    HttpResponse::Created().json(hint)
+}
+
+/// PUT /ts/key-hints/{id} — replace a type hint
+pub async fn update_key_hint(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    hint_id: web::Path<String>,
+    body: web::Json<KeyTypeHint>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut hint = body.into_inner();
+    if let Err(e) = check_key_hint(&hint, &scope) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    let mut hints = state.ts_key_hints.write().await;
+    let existing = match hints.get(hint_id.as_str()) {
+        Some(existing) if scope.allows(existing.tenant_id.as_deref()) => existing,
+        _ => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"error": "Key hint not found"}))
+        }
+    };
+    hint.id = existing.id.clone();
+    hint.tenant_id = existing.tenant_id.clone();
+    hint.updated_at = chrono::Utc::now().to_rfc3339();
+    runtime_store::persist_json(&state.ts_key_hint_dir, &hint.id, &hint);
+    hints.insert(hint.id.clone(), hint.clone());
+    HttpResponse::Ok().json(hint)
+}
+
+/// DELETE /ts/key-hints/{id} — remove a type hint
+pub async fn delete_key_hint(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    hint_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut hints = state.ts_key_hints.write().await;
+    match hints.get(hint_id.as_str()) {
+        Some(hint) if scope.allows(hint.tenant_id.as_deref()) => {}
+        _ => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"error": "Key hint not found"}))
+        }
+    }
+    hints.remove(hint_id.as_str());
+    runtime_store::delete_json(&state.ts_key_hint_dir, &hint_id);
+    HttpResponse::NoContent().finish()
+}
+
+fn check_key_hint(hint: &KeyTypeHint, scope: &tenancy::TenantScope) -> Result<(), String> {
+    hint.validate()?;
+    if !scope.allows_key(&hint.key_expr) {
+        return Err(format!("Key not found: {}", hint.key_expr));
+    }
+    Ok(())
+}
+
+async fn visible_aliases(state: &AppState, scope: &tenancy::TenantScope) -> Vec<KeyAlias> {
+    state
+        .ts_aliases
+        .read()
+        .await
+        .values()
+        .filter(|alias| scope.allows(alias.tenant_id.as_deref()))
+        .cloned()
+        .collect()
+}
+
+/// GET /ts/aliases — list key aliases
+pub async fn list_aliases(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut aliases = visible_aliases(&state, &scope).await;
+    aliases.sort_by(|a, b| {
+        a.new_prefix
+            .cmp(&b.new_prefix)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    HttpResponse::Ok().json(aliases)
+}
+
+/// POST /ts/aliases — query keys under an old prefix together with the new ones
+pub async fn create_alias(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<KeyAlias>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut alias = body.into_inner();
+    if let Err(e) = check_alias(&alias, &scope) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    if alias.id.is_empty() {
+        alias.id = uuid::Uuid::new_v4().to_string();
+    }
+    let mut aliases = state.ts_aliases.write().await;
+    if aliases.contains_key(&alias.id) {
+        return HttpResponse::Conflict()
+            .json(serde_json::json!({"error": "Alias id is already in use"}));
+    }
+    alias.tenant_id = scope.tenant_id().map(str::to_string);
+    alias.updated_at = chrono::Utc::now().to_rfc3339();
+    runtime_store::persist_json(&state.ts_alias_dir, &alias.id, &alias);
+    aliases.insert(alias.id.clone(), alias.clone());
+    HttpResponse::Created().json(alias)
+}
+
+/// PUT /ts/aliases/{id} — replace a key alias
+pub async fn update_alias(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    alias_id: web::Path<String>,
+    body: web::Json<KeyAlias>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut alias = body.into_inner();
+    if let Err(e) = check_alias(&alias, &scope) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+    }
+    let mut aliases = state.ts_aliases.write().await;
+    let existing = match aliases.get(alias_id.as_str()) {
+        Some(existing) if scope.allows(existing.tenant_id.as_deref()) => existing,
+        _ => return HttpResponse::NotFound().json(serde_json::json!({"error": "Alias not found"})),
+    };
+    alias.id = existing.id.clone();
+    alias.tenant_id = existing.tenant_id.clone();
+    alias.updated_at = chrono::Utc::now().to_rfc3339();
+    runtime_store::persist_json(&state.ts_alias_dir, &alias.id, &alias);
+    aliases.insert(alias.id.clone(), alias.clone());
+    HttpResponse::Ok().json(alias)
+}
+
+/// DELETE /ts/aliases/{id} — remove a key alias
+pub async fn delete_alias(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    alias_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut aliases = state.ts_aliases.write().await;
+    match aliases.get(alias_id.as_str()) {
+        Some(alias) if scope.allows(alias.tenant_id.as_deref()) => {}
+        _ => return HttpResponse::NotFound().json(serde_json::json!({"error": "Alias not found"})),
+    }
+    aliases.remove(alias_id.as_str());
+    runtime_store::delete_json(&state.ts_alias_dir, &alias_id);
+    HttpResponse::NoContent().finish()
+}
+
+/// POST /ts/aliases/{id}/migrate — move stored history from the old key names to
+/// the new ones, in memory and in the historian
+pub async fn migrate_alias(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    alias_id: web::Path<String>,
+) -> impl Responder {
+    if !tenancy::is_deployment_admin(&state, &req) {
+        return HttpResponse::Forbidden().json(
+            serde_json::json!({"error": "Migrating history requires the deployment admin token"}),
+        );
+    }
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let alias = match state.ts_aliases.read().await.get(alias_id.as_str()) {
+        Some(alias) if scope.allows(alias.tenant_id.as_deref()) => alias.clone(),
+        _ => return HttpResponse::NotFound().json(serde_json::json!({"error": "Alias not found"})),
+    };
+
+    let mut migrated = Vec::new();
+    {
+        let mut store = state.timeseries.write().await;
+        let mut renames: Vec<(String, String)> = store
+            .data
+            .keys()
+            .filter_map(|key| Some((key.clone(), alias.rename(key)?)))
+            .collect();
+        renames.sort();
+        for (old_key, new_key) in renames {
+            let points = store.rename_key(&old_key, &new_key);
+            migrated.push(serde_json::json!({
+                "from": old_key,
+                "to": new_key,
+                "points": points,
+            }));
+        }
+    }
+    let historian_points = if state.historian.enabled() {
+        match db::rename_ts_points(
+            &state.db_client,
+            alias.old_prefix.trim_matches('/'),
+            alias.new_prefix.trim_matches('/'),
+        )
+        .await
+        {
+            Ok(moved) => Some(moved),
+            Err(e) => {
+                tracing::error!(
+                    "Failed to migrate historian points for alias {}: {}",
+                    alias.id,
+                    e
+                );
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": "Failed to migrate historian points",
+                    "keys": migrated,
+                }));
+            }
+        }
+    } else {
+        None
+    };
+    tracing::info!(
+        "Migrated {} key(s) from {} to {}",
+        migrated.len(),
+        alias.old_prefix,
+        alias.new_prefix
+    );
+    HttpResponse::Ok().json(serde_json::json!({
+        "alias": alias,
+        "keys": migrated,
+        "historian_points": historian_points,
+    }))
+}
+
+fn check_alias(alias: &KeyAlias, scope: &tenancy::TenantScope) -> Result<(), String> {
+    alias.validate()?;
+    for prefix in [&alias.old_prefix, &alias.new_prefix] {
+        if !scope.allows_key(prefix.trim_matches('/')) {
+            return Err(format!("Key not found: {}", prefix));
+        }
+    }
+    Ok(())
+}
+
+fn check_band(band: &ThresholdBand, scope: &tenancy::TenantScope) -> Result<(), String> {
+    band.validate()?;
+    if !scope.allows_key(&band.key_expr) {
+        return Err(format!("Key not found: {}", band.key_expr));
+    }
+    Ok(())
+}
+
+/// Saved queries may only name keys the caller's tenant can read.
+fn check_saved_query(saved: &SavedQuery, scope: &tenancy::TenantScope) -> Result<(), String> {
+    saved.validate()?;
+    match saved.keys.iter().find(|key| !scope.allows_key(key)) {
+        Some(key) => Err(format!("Key not found: {}", key)),
+        None => Ok(()),
+    }
+}
+
+/// GET /ts/archive?key=...&start_ms=...&end_ms=... — list archived segments from the manifest.
+pub async fn list_ts_archive(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<TsArchiveQuery>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let policy = redaction_handlers::policy_for(&state, &req).await;
+    let manifest = match ts_archive::load_manifest(state.blob_store.as_ref()).await {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({"error": "Archive manifest is unreadable"}));
+        }
+    };
+    let segments: Vec<&ts_archive::ArchiveSegment> = manifest
+        .find(
+            query.key.as_deref(),
+            query.start_ms.unwrap_or(i64::MIN),
+            query.end_ms.unwrap_or(i64::MAX),
+        )
+        .into_iter()
+        .filter(|segment| scope.allows_key(&segment.key) && !policy.hides_key(&segment.key))
+        .collect();
+    HttpResponse::Ok().json(serde_json::json!({
+        "backend": state.blob_store.backend(),
+        "count": segments.len(),
+        "point_count": segments.iter().map(|segment| segment.point_count).sum::<usize>(),
+        "segments": segments,
+    }))
+}
+
+/// GET /ts/archive/restore?key=...&start_ms=...&end_ms=...&max_points=... — read archived
+/// points back for analysis, in the same shape as `/ts/query`.
+pub async fn restore_ts_archive(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<TsQuery>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    if !scope.allows_key(&query.key) {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Key not found",
+            "key": query.key,
+        }));
+    }
+    let policy = redaction_handlers::policy_for(&state, &req).await;
+    if policy.hides_key(&query.key) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Key is marked sensitive",
+            "key": query.key,
+        }));
+    }
+
+    let manifest = match ts_archive::load_manifest(state.blob_store.as_ref()).await {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({"error": "Archive manifest is unreadable"}));
+        }
+    };
+    let restored = match ts_archive::restore_range(
+        state.blob_store.as_ref(),
+        &manifest,
+        &query.key,
+        query.start_ms,
+        query.end_ms,
+    )
+    .await
+    {
+        Ok(points) => points,
+        Err(e) => {
+            tracing::error!("Failed to restore archive for {}: {}", query.key, e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({"error": "Failed to read archived segments"}));
+        }
+    };
+    let points: Vec<TimeSeriesPoint> = restored
+        .into_iter()
+        .filter_map(|point| {
+            policy
+                .apply(&query.key, point.value)
+                .map(|value| TimeSeriesPoint {
+                    timestamp_ms: point.timestamp_ms,
+                    value,
+                    producer: point.producer,
+                })
+        })
+        .collect();
     let original_count = points.len();
     let max_points = query.max_points.filter(|value| *value > 0);
-    let result = downsample_points(points, max_points);
+    let result = downsample_points(points.iter().collect(), max_points);
 
     HttpResponse::Ok().json(serde_json::json!({
         "key": query.key,
@@ -139,12 +1380,20 @@ fn downsample_points(
 
         if numeric_values.len() == bucket.len() {
             let average = numeric_values.iter().sum::<f64>() / numeric_values.len() as f64;
-            sampled.push(serde_json::json!({
+            let mut sample = serde_json::json!({
                 "t": bucket.last().map(|point| point.timestamp_ms).unwrap_or_default(),
                 "v": average,
                 "min": numeric_values.iter().fold(f64::INFINITY, |acc, value| acc.min(*value)),
                 "max": numeric_values.iter().fold(f64::NEG_INFINITY, |acc, value| acc.max(*value)),
-            }));
+            });
+            // A bucket is labelled only when a single producer recorded all of it.
+            if let Some(producer) = bucket[0]
+                .producer
+                .filter(|producer| bucket.iter().all(|point| point.producer == Some(*producer)))
+            {
+                sample["producer"] = serde_json::json!(producer);
+            }
+            sampled.push(sample);
         } else if let Some(last) = bucket.last() {
             sampled.push(point_to_json(last));
         }
@@ -153,14 +1402,36 @@ fn downsample_points(
     sampled
 }
 
+/// Counter points as `{t, v, delta}`; buckets keep the last reading and sum the increases.
+fn downsample_counter(
+    counter: &ts_counters::CounterDeltas,
+    max_points: Option<usize>,
+) -> Vec<serde_json::Value> {
+    let limit = max_points.unwrap_or(usize::MAX).max(1);
+    let bucket_size = counter.points.len().div_ceil(limit).max(1);
+    counter
+        .points
+        .chunks(bucket_size)
+        .filter_map(|bucket| {
+            let &(t, v, _) = bucket.last()?;
+            let delta: f64 = bucket.iter().map(|(_, _, delta)| delta).sum();
+            Some(serde_json::json!({"t": t, "v": v, "delta": delta}))
+        })
+        .collect()
+}
+
 fn point_to_json(point: &TimeSeriesPoint) -> serde_json::Value {
-    serde_json::json!({
+    let mut json = serde_json::json!({
         "t": point.timestamp_ms,
         "v": point.value,
-    })
+    });
+    if let Some(producer) = point.producer {
+        json["producer"] = serde_json::json!(producer);
+    }
+    json
 }
 
-fn extract_numeric_value(value: &serde_json::Value) -> Option<f64> {
+pub(crate) fn extract_numeric_value(value: &serde_json::Value) -> Option<f64> {
     value
         .get("result")
         .and_then(|result| result.get("value"))
@@ -173,9 +1444,21 @@ fn extract_numeric_value(value: &serde_json::Value) -> Option<f64> {
 mod tests {
     use super::*;
     use crate::state::TimeSeriesStore;
+    use crate::test_fixtures::point;
 
-    fn point(timestamp_ms: i64, value: serde_json::Value) -> TimeSeriesPoint {
-        TimeSeriesPoint { timestamp_ms, value }
+    #[test]
+    fn downsample_counter_sums_increases_per_bucket() {
+        let counter =
+            ts_counters::deltas(&[(1, 10.0), (2, 12.0), (3, 15.0), (4, 1.0), (5, 4.0)], None);
+        let sampled = downsample_counter(&counter, Some(2));
+        assert_eq!(
+            sampled,
+            vec![
+                serde_json::json!({"t": 3, "v": 15.0, "delta": 5.0}),
+                serde_json::json!({"t": 5, "v": 4.0, "delta": 4.0}),
+            ]
+        );
+        assert_eq!(downsample_counter(&counter, None).len(), 5);
     }
 
     #[test]
@@ -193,6 +1476,26 @@ mod tests {
         assert_eq!(sampled[1]["t"], 3);
     }
 
+    #[test]
+    fn producer_filter_keeps_only_labelled_points() {
+        let mut points = vec![
+            point(1, serde_json::json!(1.0)),
+            point(2, serde_json::json!(2.0)),
+            point(3, serde_json::json!(3.0)),
+        ];
+        points[0].producer = Some(Producer::Simulator);
+        points[1].producer = Some(Producer::EvaIcsConnector);
+
+        let kept = from_producers(points.clone(), &[Producer::EvaIcsConnector]);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(point_to_json(&kept[0])["producer"], "eva-ics-connector");
+        assert_eq!(from_producers(points.clone(), &[]).len(), 3);
+
+        let sampled = downsample_points(points.iter().collect(), Some(2));
+        assert_eq!(sampled[0].get("producer"), None);
+        assert_eq!(point_to_json(&points[2]).get("producer"), None);
+    }
+
     #[test]
     fn downsample_points_averages_numeric_buckets() {
         let points = vec![
@@ -211,6 +1514,18 @@ mod tests {
         assert_eq!(sampled[1]["v"], serde_json::json!(35.0));
     }
 
+    #[test]
+    fn late_points_are_inserted_in_time_order() {
+        let mut store = TimeSeriesStore::new(3);
+        store.insert("key".to_string(), serde_json::json!(1), 10);
+        store.insert("key".to_string(), serde_json::json!(3), 30);
+        store.insert("key".to_string(), serde_json::json!(2), 20);
+        store.insert("key".to_string(), serde_json::json!(0), 5);
+
+        let times: Vec<i64> = store.data["key"].iter().map(|p| p.timestamp_ms).collect();
+        assert_eq!(times, vec![10, 20, 30]);
+    }
+
     #[test]
     fn set_max_points_prunes_existing_buffers() {
         let mut store = TimeSeriesStore::new(10);
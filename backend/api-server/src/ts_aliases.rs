@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+use crate::key_tree::is_under;
+
+/// Maps keys that moved when a topic layout changed, e.g. `fendtastic` to
+/// `murph/habitat`, so history recorded under the old names is still found
+/// under the new ones.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyAlias {
+    #[serde(default)]
+    pub id: String,
+    /// Literal prefix the keys were recorded under.
+    pub old_prefix: String,
+    /// Literal prefix the keys are recorded under now.
+    pub new_prefix: String,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl KeyAlias {
+    pub fn validate(&self) -> Result<(), String> {
+        for prefix in [&self.old_prefix, &self.new_prefix] {
+            let trimmed = prefix.trim_matches('/');
+            if trimmed.is_empty() || trimmed.contains('*') || trimmed.contains('$') {
+                return Err(format!(
+                    "Prefix '{}' must be a non-empty key without wildcards",
+                    prefix
+                ));
+            }
+        }
+        let old = self.old_prefix.trim_matches('/');
+        let new = self.new_prefix.trim_matches('/');
+        if is_under(old, new) || is_under(new, old) {
+            return Err("old_prefix and new_prefix must not contain each other".to_string());
+        }
+        Ok(())
+    }
+
+    /// The new name of `key`, when it lies under `old_prefix`.
+    pub fn rename(&self, key: &str) -> Option<String> {
+        swap_prefix(key, &self.old_prefix, &self.new_prefix)
+    }
+
+    /// The old name of `key`, when it lies under `new_prefix`.
+    pub fn original(&self, key: &str) -> Option<String> {
+        swap_prefix(key, &self.new_prefix, &self.old_prefix)
+    }
+}
+
+fn swap_prefix(key: &str, from: &str, to: &str) -> Option<String> {
+    let from = from.trim_matches('/');
+    if !is_under(from, key) {
+        return None;
+    }
+    Some(format!("{}{}", to.trim_matches('/'), &key[from.len()..]))
+}
+
+/// Every earlier name of `key`, following chains of aliases (a → b → c), newest
+/// first. Cycles end the walk.
+pub fn old_keys<'a>(
+    aliases: impl IntoIterator<Item = &'a KeyAlias> + Clone,
+    key: &str,
+) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    let mut frontier = vec![key.to_string()];
+    while let Some(current) = frontier.pop() {
+        for alias in aliases.clone() {
+            let Some(old) = alias.original(&current) else {
+                continue;
+            };
+            if old != key && !found.contains(&old) {
+                found.push(old.clone());
+                frontier.push(old);
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alias(old_prefix: &str, new_prefix: &str) -> KeyAlias {
+        KeyAlias {
+            id: String::new(),
+            old_prefix: old_prefix.to_string(),
+            new_prefix: new_prefix.to_string(),
+            tenant_id: None,
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn old_keys_follow_alias_chains() {
+        let aliases = [
+            alias("fendtastic", "murph/habitat"),
+            alias("murph/habitat", "entmoot/habitat"),
+        ];
+
+        assert_eq!(
+            old_keys(&aliases, "entmoot/habitat/pea/p1/status"),
+            vec![
+                "murph/habitat/pea/p1/status".to_string(),
+                "fendtastic/pea/p1/status".to_string(),
+            ]
+        );
+        assert!(old_keys(&aliases, "entmoot/other/p1").is_empty());
+        assert_eq!(
+            aliases[0].rename("fendtastic/pea/p1").as_deref(),
+            Some("murph/habitat/pea/p1")
+        );
+        assert_eq!(aliases[0].rename("fendtastic2/pea/p1"), None);
+    }
+
+    #[test]
+    fn cyclic_aliases_terminate() {
+        let aliases = [alias("a", "b"), alias("b", "a")];
+
+        assert_eq!(old_keys(&aliases, "a/x"), vec!["b/x".to_string()]);
+        assert!(alias("a", "a/b").validate().is_err());
+        assert!(alias("a/*", "b").validate().is_err());
+        assert!(alias("fendtastic", "murph/habitat").validate().is_ok());
+    }
+}
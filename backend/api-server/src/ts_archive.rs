@@ -0,0 +1,305 @@
+use crate::blob_store::{self, BlobStore};
+use crate::state::{TimeSeriesPoint, TimeSeriesStore};
+use crate::ts_provenance::Producer;
+use anyhow::Result;
+use chrono::Utc;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use tokio::sync::RwLock;
+
+/// Object key of the manifest listing every archived segment.
+pub const MANIFEST_KEY: &str = "ts-archive/manifest.json";
+
+/// One gzip-compressed JSONL object holding a contiguous run of points for a key.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ArchiveSegment {
+    pub id: String,
+    pub key: String,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub point_count: usize,
+    pub size_bytes: usize,
+    pub storage_key: String,
+    pub created_at: String,
+}
+
+impl ArchiveSegment {
+    pub fn overlaps(&self, start_ms: i64, end_ms: i64) -> bool {
+        self.start_ms <= end_ms && self.end_ms >= start_ms
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub segments: Vec<ArchiveSegment>,
+}
+
+impl ArchiveManifest {
+    /// Segments for `key` (all keys when `None`) that overlap the range, oldest first.
+    pub fn find(&self, key: Option<&str>, start_ms: i64, end_ms: i64) -> Vec<&ArchiveSegment> {
+        let mut found: Vec<&ArchiveSegment> = self
+            .segments
+            .iter()
+            .filter(|segment| key.is_none_or(|key| segment.key == key))
+            .filter(|segment| segment.overlaps(start_ms, end_ms))
+            .collect();
+        found.sort_by(|a, b| a.key.cmp(&b.key).then(a.start_ms.cmp(&b.start_ms)));
+        found
+    }
+}
+
+/// Encodes points as gzip-compressed JSON lines of `{"t": ms, "v": value}`, with the
+/// producer as `"p"` when it is known.
+pub fn encode_segment(points: &[TimeSeriesPoint]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    for point in points {
+        let mut line = serde_json::json!({ "t": point.timestamp_ms, "v": point.value });
+        if let Some(producer) = point.producer {
+            line["p"] = serde_json::json!(producer);
+        }
+        serde_json::to_writer(&mut encoder, &line)?;
+        encoder.write_all(b"\n")?;
+    }
+    Ok(encoder.finish()?)
+}
+
+pub fn decode_segment(bytes: &[u8]) -> Result<Vec<TimeSeriesPoint>> {
+    let reader = BufReader::new(GzDecoder::new(bytes));
+    let mut points = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: serde_json::Value = serde_json::from_str(&line)?;
+        points.push(TimeSeriesPoint {
+            timestamp_ms: record.get("t").and_then(|t| t.as_i64()).unwrap_or_default(),
+            value: record.get("v").cloned().unwrap_or(serde_json::Value::Null),
+            producer: record
+                .get("p")
+                .and_then(|p| p.as_str())
+                .and_then(Producer::parse),
+        });
+    }
+    Ok(points)
+}
+
+fn segment_storage_key(key: &str, start_ms: i64, end_ms: i64, id: &str) -> String {
+    let key_path = key
+        .split('/')
+        .filter(|chunk| !chunk.is_empty())
+        .map(|chunk| if chunk == ".." { "_" } else { chunk })
+        .collect::<Vec<_>>()
+        .join("/");
+    format!(
+        "ts-archive/segments/{}/{}-{}-{}.jsonl.gz",
+        key_path, start_ms, end_ms, id
+    )
+}
+
+/// Loads the manifest; a missing manifest is an empty archive. A corrupt or
+/// unreachable one is an error so a flush never overwrites it.
+pub async fn load_manifest(store: &dyn BlobStore) -> Result<ArchiveManifest> {
+    match store.get(MANIFEST_KEY).await {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map_err(|e| anyhow::anyhow!("Archive manifest is unreadable: {}", e)),
+        Err(e) if blob_store::is_not_found(&e) => Ok(ArchiveManifest::default()),
+        Err(e) => Err(e.context("Failed to load the archive manifest")),
+    }
+}
+
+/// Writes the points evicted by retention since the last flush as one segment
+/// per key and records them in the manifest. Returns the number of new segments.
+pub async fn flush_evicted(
+    store: &dyn BlobStore,
+    timeseries: &RwLock<TimeSeriesStore>,
+) -> Result<usize> {
+    if timeseries.read().await.evicted.is_empty() {
+        return Ok(0);
+    }
+    let mut manifest = load_manifest(store).await?;
+    let evicted = timeseries.write().await.take_evicted();
+
+    let mut written = 0;
+    let mut failed: HashMap<String, Vec<TimeSeriesPoint>> = HashMap::new();
+    for (key, points) in evicted {
+        let (Some(first), Some(last)) = (points.first(), points.last()) else {
+            continue;
+        };
+        let id = uuid::Uuid::new_v4().to_string();
+        let (start_ms, end_ms) = (first.timestamp_ms, last.timestamp_ms);
+        let storage_key = segment_storage_key(&key, start_ms, end_ms, &id);
+        let stored = match encode_segment(&points) {
+            Ok(bytes) => {
+                let size_bytes = bytes.len();
+                store
+                    .put(&storage_key, bytes, "application/gzip")
+                    .await
+                    .map(|_| size_bytes)
+            }
+            Err(e) => Err(e),
+        };
+        match stored {
+            Ok(size_bytes) => {
+                manifest.segments.push(ArchiveSegment {
+                    id,
+                    key,
+                    start_ms,
+                    end_ms,
+                    point_count: points.len(),
+                    size_bytes,
+                    storage_key,
+                    created_at: Utc::now().to_rfc3339(),
+                });
+                written += 1;
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to archive {} points of {}: {}",
+                    points.len(),
+                    key,
+                    e
+                );
+                failed.insert(key, points);
+            }
+        }
+    }
+
+    // Hand failed batches back so the next flush retries them ahead of newer evictions.
+    if !failed.is_empty() {
+        let mut ts = timeseries.write().await;
+        for (key, mut points) in failed {
+            if let Some(newer) = ts.evicted.remove(&key) {
+                points.extend(newer);
+            }
+            ts.evicted.insert(key, points);
+        }
+    }
+
+    if written > 0 {
+        let body = serde_json::to_vec_pretty(&manifest)?;
+        store.put(MANIFEST_KEY, body, "application/json").await?;
+    }
+    Ok(written)
+}
+
+/// Reads the archived points of `key` within [start_ms, end_ms], oldest first.
+pub async fn restore_range(
+    store: &dyn BlobStore,
+    manifest: &ArchiveManifest,
+    key: &str,
+    start_ms: i64,
+    end_ms: i64,
+) -> Result<Vec<TimeSeriesPoint>> {
+    let mut points = Vec::new();
+    for segment in manifest.find(Some(key), start_ms, end_ms) {
+        let bytes = store.get(&segment.storage_key).await?;
+        points.extend(
+            decode_segment(&bytes)?
+                .into_iter()
+                .filter(|p| p.timestamp_ms >= start_ms && p.timestamp_ms <= end_ms),
+        );
+    }
+    points.sort_by_key(|p| p.timestamp_ms);
+    Ok(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blob_store::LocalBlobStore;
+    use crate::test_fixtures::point;
+
+    #[test]
+    fn segments_round_trip_through_gzip_jsonl() {
+        let mut points = vec![point(1, 1.5), point(2, 2.5), point(3, 3.5)];
+        points[1].producer = Some(Producer::Simulator);
+        let bytes = encode_segment(&points).unwrap();
+        assert_eq!(&bytes[..2], &[0x1f, 0x8b]);
+
+        let decoded = decode_segment(&bytes).unwrap();
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(decoded[1].timestamp_ms, 2);
+        assert_eq!(decoded[1].value, serde_json::json!(2.5));
+        assert_eq!(decoded[1].producer, Some(Producer::Simulator));
+        assert_eq!(decoded[0].producer, None);
+    }
+
+    #[test]
+    fn storage_keys_stay_inside_the_archive_prefix() {
+        let key = segment_storage_key("entmoot/../x//y", 1, 2, "id");
+        assert_eq!(key, "ts-archive/segments/entmoot/_/x/y/1-2-id.jsonl.gz");
+        assert!(crate::blob_store::validate_key(&key).is_ok());
+    }
+
+    #[tokio::test]
+    async fn evicted_points_are_archived_and_restorable() {
+        let root = std::env::temp_dir().join(format!("ts-archive-{}", uuid::Uuid::new_v4()));
+        let store = LocalBlobStore::new(root.to_str().unwrap());
+        let mut ts = TimeSeriesStore::new(2);
+        ts.archive_evicted = true;
+        for t in 1..=5 {
+            ts.insert("entmoot/a".to_string(), serde_json::json!(t), t);
+        }
+        let ts = RwLock::new(ts);
+
+        assert_eq!(flush_evicted(&store, &ts).await.unwrap(), 1);
+        assert_eq!(flush_evicted(&store, &ts).await.unwrap(), 0);
+
+        let manifest = load_manifest(&store).await.unwrap();
+        assert_eq!(manifest.segments.len(), 1);
+        assert_eq!(manifest.segments[0].point_count, 3);
+        assert_eq!(manifest.find(Some("entmoot/a"), 4, 10).len(), 0);
+
+        let restored = restore_range(&store, &manifest, "entmoot/a", 2, 10)
+            .await
+            .unwrap();
+        let times: Vec<i64> = restored.iter().map(|p| p.timestamp_ms).collect();
+        assert_eq!(times, vec![2, 3]);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    /// Store whose reads always fail, like an S3 endpoint that is down.
+    struct UnreachableStore;
+
+    #[async_trait::async_trait]
+    impl BlobStore for UnreachableStore {
+        fn backend(&self) -> &'static str {
+            "unreachable"
+        }
+
+        async fn put(&self, _key: &str, _bytes: Vec<u8>, _content_type: &str) -> Result<()> {
+            anyhow::bail!("connection refused")
+        }
+
+        async fn get(&self, _key: &str) -> Result<Vec<u8>> {
+            anyhow::bail!("connection refused")
+        }
+
+        async fn delete(&self, _key: &str) -> Result<()> {
+            anyhow::bail!("connection refused")
+        }
+
+        async fn list(&self, _prefix: &str) -> Result<Vec<String>> {
+            anyhow::bail!("connection refused")
+        }
+    }
+
+    #[tokio::test]
+    async fn unreachable_store_is_not_an_empty_archive() {
+        assert!(load_manifest(&UnreachableStore).await.is_err());
+
+        let mut ts = TimeSeriesStore::new(1);
+        ts.archive_evicted = true;
+        ts.insert("entmoot/a".to_string(), serde_json::json!(1), 1);
+        ts.insert("entmoot/a".to_string(), serde_json::json!(2), 2);
+        let ts = RwLock::new(ts);
+        assert!(flush_evicted(&UnreachableStore, &ts).await.is_err());
+        assert!(!ts.read().await.evicted.is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use zenoh::key_expr::KeyExpr;
+
+/// Warning and critical limits drawn as bands on charts of the keys matching
+/// `key_expr`. Any limit may be left open.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ThresholdBand {
+    #[serde(default)]
+    pub id: String,
+    /// Exact key or key expression, e.g. `entmoot/habitat/nodes/*/pea/r1/data/TT*`.
+    pub key_expr: String,
+    #[serde(default)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub warning_low: Option<f64>,
+    #[serde(default)]
+    pub warning_high: Option<f64>,
+    #[serde(default)]
+    pub critical_low: Option<f64>,
+    #[serde(default)]
+    pub critical_high: Option<f64>,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl ThresholdBand {
+    /// Limits must be ordered critical_low ≤ warning_low < warning_high ≤ critical_high.
+    pub fn validate(&self) -> Result<(), String> {
+        if KeyExpr::try_from(self.key_expr.as_str()).is_err() {
+            return Err(format!("Invalid key expression '{}'", self.key_expr));
+        }
+        let limits = [
+            ("critical_low", self.critical_low),
+            ("warning_low", self.warning_low),
+            ("warning_high", self.warning_high),
+            ("critical_high", self.critical_high),
+        ];
+        if limits.iter().all(|(_, limit)| limit.is_none()) {
+            return Err("Set at least one limit".to_string());
+        }
+        let set: Vec<(&str, f64)> = limits
+            .iter()
+            .filter_map(|(name, limit)| limit.map(|limit| (*name, limit)))
+            .collect();
+        for pair in set.windows(2) {
+            let ((lower, a), (upper, b)) = (pair[0], pair[1]);
+            let ordered = if lower.ends_with("_low") && upper.ends_with("_high") {
+                a < b
+            } else {
+                a <= b
+            };
+            if !ordered {
+                return Err(format!("{} must be below {}", lower, upper));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `key_expr` (an exact key or a pattern) matches `key`.
+pub fn key_expr_includes(key_expr: &str, key: &str) -> bool {
+    match (KeyExpr::try_from(key_expr), KeyExpr::try_from(key)) {
+        (Ok(key_expr), Ok(key)) => key_expr.includes(&key),
+        _ => false,
+    }
+}
+
+/// The band for `key`: an exact match, else the longest key expression that
+/// covers it.
+pub fn band_for<'a>(
+    bands: impl IntoIterator<Item = &'a ThresholdBand>,
+    key: &str,
+) -> Option<&'a ThresholdBand> {
+    bands
+        .into_iter()
+        .filter(|band| key_expr_includes(&band.key_expr, key))
+        .max_by_key(|band| (band.key_expr == key, band.key_expr.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn band(
+        key_expr: &str,
+        warning_high: Option<f64>,
+        critical_high: Option<f64>,
+    ) -> ThresholdBand {
+        ThresholdBand {
+            id: key_expr.to_string(),
+            key_expr: key_expr.to_string(),
+            unit: None,
+            warning_low: None,
+            warning_high,
+            critical_low: None,
+            critical_high,
+            tenant_id: None,
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn limits_must_be_ordered() {
+        assert!(band("entmoot/pea/r1/data/TT101", Some(80.0), Some(95.0))
+            .validate()
+            .is_ok());
+        assert!(band("entmoot/pea/r1/data/TT101", Some(95.0), Some(80.0))
+            .validate()
+            .is_err());
+        assert!(band("entmoot/pea/r1/data/TT101", None, None)
+            .validate()
+            .is_err());
+        assert!(band("entmoot//bad", Some(1.0), None).validate().is_err());
+
+        let mut inverted = band("entmoot/pea/r1/data/TT101", Some(10.0), None);
+        inverted.warning_low = Some(10.0);
+        assert!(inverted.validate().is_err());
+    }
+
+    #[test]
+    fn exact_keys_win_over_longer_and_shorter_patterns() {
+        let bands = [
+            band("entmoot/**", Some(1.0), None),
+            band("entmoot/pea/*/data/TT101", Some(2.0), None),
+            band("entmoot/pea/r1/data/TT101", Some(3.0), None),
+        ];
+        let pick = |key: &str| band_for(&bands, key).and_then(|band| band.warning_high);
+
+        assert_eq!(pick("entmoot/pea/r1/data/TT101"), Some(3.0));
+        assert_eq!(pick("entmoot/pea/r2/data/TT101"), Some(2.0));
+        assert_eq!(pick("entmoot/pea/r2/data/FT201"), Some(1.0));
+        assert_eq!(pick("other/key"), None);
+    }
+}
@@ -0,0 +1,462 @@
+use crate::alarm_expr::{Expr, ExprValue};
+use crate::state::TimeSeriesStore;
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Points kept per captured key while a capture runs, unless the trigger
+/// sets its own limit.
+pub const DEFAULT_CAPTURE_POINTS: usize = 500_000;
+
+fn default_window_secs() -> u64 {
+    60
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Records a set of keys around the moment a condition starts to hold, e.g.
+/// `vibration > 12` on a pump's bearing sensor. The collector already stores
+/// every sample, so while a capture runs the keys keep far more points than
+/// the ring buffer normally does; the window is then saved as an incident
+/// recording that outlives the buffer.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CaptureTrigger {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// Condition in the computed-alarm expression language.
+    pub expression: String,
+    /// Expression variable -> telemetry key it reads.
+    pub bindings: BTreeMap<String, String>,
+    /// Keys recorded while the capture runs.
+    pub keys: Vec<String>,
+    /// Seconds before the condition fired that the recording includes.
+    #[serde(default = "default_window_secs")]
+    pub pre_secs: u64,
+    /// Seconds the capture runs on after the condition last held.
+    #[serde(default = "default_window_secs")]
+    pub post_secs: u64,
+    /// Points kept per captured key while the capture runs.
+    #[serde(default)]
+    pub max_points_per_key: Option<usize>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl CaptureTrigger {
+    /// Parses the condition and checks that every variable is bound and that
+    /// there is something to record.
+    pub fn compile(&self) -> Result<Expr, String> {
+        if self.name.trim().is_empty() {
+            return Err("Trigger name is required".to_string());
+        }
+        if self.keys.is_empty() || self.keys.iter().any(|key| key.trim().is_empty()) {
+            return Err("At least one key to capture is required".to_string());
+        }
+        let expr = Expr::parse(&self.expression)?;
+        let unbound: Vec<String> = expr
+            .variables()
+            .into_iter()
+            .filter(|name| !self.bindings.contains_key(name))
+            .collect();
+        if !unbound.is_empty() {
+            return Err(format!("Unbound variables: {}", unbound.join(", ")));
+        }
+        Ok(expr)
+    }
+
+    fn retention(&self) -> usize {
+        self.max_points_per_key.unwrap_or(DEFAULT_CAPTURE_POINTS)
+    }
+}
+
+/// One captured point, in the archive's `{"t": ms, "v": value}` form.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CapturedPoint {
+    pub t: i64,
+    pub v: serde_json::Value,
+}
+
+/// The window recorded by one firing of a trigger.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IncidentRecording {
+    pub id: String,
+    pub trigger_id: String,
+    pub trigger_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    pub fired_at: String,
+    /// `name=value` of the bound variables when the condition fired.
+    pub values: String,
+    pub start_ms: i64,
+    /// Extended while the condition keeps holding.
+    pub end_ms: i64,
+    /// False while the capture is still running.
+    pub complete: bool,
+    pub keys: Vec<String>,
+    /// Captured points by key; filled in when the capture completes.
+    #[serde(default)]
+    pub points: BTreeMap<String, Vec<CapturedPoint>>,
+}
+
+struct CompiledTrigger {
+    trigger: CaptureTrigger,
+    expr: Expr,
+    /// Whether the condition held on the last evaluation, so a capture only
+    /// starts on its rising edge.
+    holding: bool,
+}
+
+struct ActiveCapture {
+    recording: IncidentRecording,
+    retention: usize,
+}
+
+/// Capture triggers, evaluated on ingest of any key they bind, and the
+/// captures they started.
+#[derive(Clone)]
+pub struct Captures {
+    triggers: Arc<RwLock<HashMap<String, CompiledTrigger>>>,
+    /// Latest value of every bound key.
+    latest: Arc<RwLock<HashMap<String, ExprValue>>>,
+    /// Running capture of each trigger, by trigger id.
+    active: Arc<RwLock<HashMap<String, ActiveCapture>>>,
+    recordings: Arc<RwLock<HashMap<String, IncidentRecording>>>,
+    store: Arc<RwLock<TimeSeriesStore>>,
+}
+
+impl Captures {
+    pub fn new(
+        triggers: HashMap<String, CaptureTrigger>,
+        recordings: HashMap<String, IncidentRecording>,
+        store: Arc<RwLock<TimeSeriesStore>>,
+    ) -> Self {
+        let compiled = triggers
+            .into_iter()
+            .filter_map(|(id, trigger)| match trigger.compile() {
+                Ok(expr) => Some((
+                    id,
+                    CompiledTrigger {
+                        trigger,
+                        expr,
+                        holding: false,
+                    },
+                )),
+                Err(e) => {
+                    warn!("Skipping capture trigger {}: {}", id, e);
+                    None
+                }
+            })
+            .collect();
+        Self {
+            triggers: Arc::new(RwLock::new(compiled)),
+            latest: Arc::new(RwLock::new(HashMap::new())),
+            active: Arc::new(RwLock::new(HashMap::new())),
+            recordings: Arc::new(RwLock::new(recordings)),
+            store,
+        }
+    }
+
+    pub async fn list(&self) -> Vec<CaptureTrigger> {
+        let mut triggers: Vec<CaptureTrigger> = self
+            .triggers
+            .read()
+            .await
+            .values()
+            .map(|compiled| compiled.trigger.clone())
+            .collect();
+        triggers.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+        triggers
+    }
+
+    pub async fn get(&self, id: &str) -> Option<CaptureTrigger> {
+        self.triggers
+            .read()
+            .await
+            .get(id)
+            .map(|compiled| compiled.trigger.clone())
+    }
+
+    /// Adds or replaces a trigger. A capture it already started runs on as
+    /// it was.
+    pub async fn upsert(&self, trigger: CaptureTrigger) -> Result<(), String> {
+        let expr = trigger.compile()?;
+        self.triggers.write().await.insert(
+            trigger.id.clone(),
+            CompiledTrigger {
+                trigger,
+                expr,
+                holding: false,
+            },
+        );
+        Ok(())
+    }
+
+    pub async fn remove(&self, id: &str) -> bool {
+        self.triggers.write().await.remove(id).is_some()
+    }
+
+    /// Running and completed recordings, newest first, without their points.
+    pub async fn recordings(&self) -> Vec<IncidentRecording> {
+        let active = self.active.read().await;
+        let recordings = self.recordings.read().await;
+        let mut list: Vec<IncidentRecording> = active
+            .values()
+            .map(|capture| &capture.recording)
+            .chain(recordings.values())
+            .map(|recording| IncidentRecording {
+                points: BTreeMap::new(),
+                ..recording.clone()
+            })
+            .collect();
+        list.sort_by(|a, b| b.start_ms.cmp(&a.start_ms).then_with(|| a.id.cmp(&b.id)));
+        list
+    }
+
+    pub async fn recording(&self, id: &str) -> Option<IncidentRecording> {
+        if let Some(recording) = self.recordings.read().await.get(id) {
+            return Some(recording.clone());
+        }
+        self.active
+            .read()
+            .await
+            .values()
+            .find(|capture| capture.recording.id == id)
+            .map(|capture| capture.recording.clone())
+    }
+
+    /// Deletes a completed recording; a running one cannot be deleted.
+    pub async fn remove_recording(&self, id: &str) -> bool {
+        self.recordings.write().await.remove(id).is_some()
+    }
+
+    /// Records a sample of `key` and evaluates the enabled triggers that bind it.
+    pub async fn on_sample(&self, key: &str, value: &serde_json::Value) {
+        self.record(key, value, Utc::now().timestamp_millis()).await;
+    }
+
+    async fn record(&self, key: &str, value: &serde_json::Value, now_ms: i64) {
+        let mut triggers = self.triggers.write().await;
+        let affected: Vec<&mut CompiledTrigger> = triggers
+            .values_mut()
+            .filter(|compiled| {
+                compiled.trigger.enabled && compiled.trigger.bindings.values().any(|k| k == key)
+            })
+            .collect();
+        if affected.is_empty() {
+            return;
+        }
+        let Some(value) = ExprValue::from_json(value) else {
+            return;
+        };
+        let mut latest = self.latest.write().await;
+        latest.insert(key.to_string(), value);
+
+        let mut active = self.active.write().await;
+        let mut started = Vec::new();
+        for compiled in affected {
+            let trigger = &compiled.trigger;
+            let holds = compiled
+                .expr
+                .eval(&|name| {
+                    trigger
+                        .bindings
+                        .get(name)
+                        .and_then(|key| latest.get(key))
+                        .copied()
+                })
+                .is_ok_and(|value| value == ExprValue::Bool(true));
+            let rising = holds && !compiled.holding;
+            compiled.holding = holds;
+            if !holds {
+                continue;
+            }
+            let end_ms = now_ms + trigger.post_secs as i64 * 1000;
+            if let Some(capture) = active.get_mut(&trigger.id) {
+                capture.recording.end_ms = capture.recording.end_ms.max(end_ms);
+            } else if rising {
+                let recording = IncidentRecording {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    trigger_id: trigger.id.clone(),
+                    trigger_name: trigger.name.clone(),
+                    tenant_id: trigger.tenant_id.clone(),
+                    fired_at: Utc
+                        .timestamp_millis_opt(now_ms)
+                        .single()
+                        .unwrap_or_else(Utc::now)
+                        .to_rfc3339(),
+                    values: snapshot(&trigger.bindings, &latest),
+                    start_ms: now_ms - trigger.pre_secs as i64 * 1000,
+                    end_ms,
+                    complete: false,
+                    keys: trigger.keys.clone(),
+                    points: BTreeMap::new(),
+                };
+                info!(
+                    "Capture trigger {} fired, recording {}",
+                    trigger.name, recording.id
+                );
+                started.extend(recording.keys.clone());
+                active.insert(
+                    trigger.id.clone(),
+                    ActiveCapture {
+                        recording,
+                        retention: trigger.retention(),
+                    },
+                );
+            }
+        }
+        if !started.is_empty() {
+            let mut store = self.store.write().await;
+            apply_retention(&mut store, &active, &started);
+        }
+    }
+
+    /// Completes the captures whose window has passed: their points are
+    /// copied out of the store and the raised retention is dropped. Returns
+    /// the completed recordings.
+    pub async fn finish_due(&self) -> Vec<IncidentRecording> {
+        self.finish_at(Utc::now().timestamp_millis()).await
+    }
+
+    async fn finish_at(&self, now_ms: i64) -> Vec<IncidentRecording> {
+        let mut active = self.active.write().await;
+        let due: Vec<String> = active
+            .iter()
+            .filter(|(_, capture)| capture.recording.end_ms <= now_ms)
+            .map(|(id, _)| id.clone())
+            .collect();
+        if due.is_empty() {
+            return Vec::new();
+        }
+        let mut store = self.store.write().await;
+        let mut finished = Vec::new();
+        for id in due {
+            let Some(capture) = active.remove(&id) else {
+                continue;
+            };
+            let mut recording = capture.recording;
+            for key in &recording.keys {
+                let points = store
+                    .query(key, recording.start_ms, recording.end_ms)
+                    .into_iter()
+                    .map(|point| CapturedPoint {
+                        t: point.timestamp_ms,
+                        v: point.value.clone(),
+                    })
+                    .collect();
+                recording.points.insert(key.clone(), points);
+            }
+            recording.complete = true;
+            apply_retention(&mut store, &active, &recording.keys);
+            finished.push(recording);
+        }
+        let mut recordings = self.recordings.write().await;
+        for recording in &finished {
+            recordings.insert(recording.id.clone(), recording.clone());
+        }
+        finished
+    }
+}
+
+/// Sets the retention of `keys` to the highest of the captures recording
+/// them, or back to the store's own once none does.
+fn apply_retention(
+    store: &mut TimeSeriesStore,
+    active: &HashMap<String, ActiveCapture>,
+    keys: &[String],
+) {
+    for key in keys {
+        let limit = active
+            .values()
+            .filter(|capture| capture.recording.keys.contains(key))
+            .map(|capture| capture.retention)
+            .max();
+        store.set_retention_override(key, limit);
+    }
+}
+
+/// `name=value` for every bound variable with a value.
+fn snapshot(bindings: &BTreeMap<String, String>, values: &HashMap<String, ExprValue>) -> String {
+    bindings
+        .iter()
+        .filter_map(|(name, key)| values.get(key).map(|value| format!("{}={}", name, value)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn vibration_spike_keeps_and_records_the_window() {
+        let store = Arc::new(RwLock::new(TimeSeriesStore::new(3)));
+        let trigger = CaptureTrigger {
+            id: "bearing".to_string(),
+            name: "Bearing vibration".to_string(),
+            expression: "vibration > 12".to_string(),
+            bindings: BTreeMap::from([("vibration".to_string(), "pump/vib".to_string())]),
+            keys: vec!["pump/vib".to_string()],
+            pre_secs: 2,
+            post_secs: 3,
+            max_points_per_key: Some(100),
+            enabled: true,
+            tenant_id: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+        let captures = Captures::new(
+            HashMap::from([("bearing".to_string(), trigger)]),
+            HashMap::new(),
+            store.clone(),
+        );
+        let sample = |t: i64, v: f64| {
+            let (captures, store) = (captures.clone(), store.clone());
+            async move {
+                let value = serde_json::json!(v);
+                captures.record("pump/vib", &value, t).await;
+                store.write().await.insert("pump/vib".to_string(), value, t);
+            }
+        };
+
+        for t in 0..5 {
+            sample(t * 1000, 4.0).await;
+        }
+        assert!(captures.recordings().await.is_empty());
+        sample(5000, 15.0).await;
+        sample(6000, 14.0).await;
+        for t in 7..12 {
+            sample(t * 1000, 5.0).await;
+        }
+        // While running, the key keeps more than the store's three points.
+        assert_eq!(store.read().await.retention_for("pump/vib"), 100);
+        assert_eq!(store.read().await.data["pump/vib"].len(), 10);
+
+        assert!(
+            captures.finish_at(8000).await.is_empty(),
+            "still held at 6s"
+        );
+        let finished = captures.finish_at(9000).await;
+        assert_eq!(finished.len(), 1);
+        let recording = &finished[0];
+        assert_eq!((recording.start_ms, recording.end_ms), (3000, 9000));
+        assert_eq!(recording.values, "vibration=15");
+        let times: Vec<i64> = recording.points["pump/vib"].iter().map(|p| p.t).collect();
+        assert_eq!(times, vec![3000, 4000, 5000, 6000, 7000, 8000, 9000]);
+
+        assert_eq!(store.read().await.data["pump/vib"].len(), 3);
+        assert!(captures.recording(&recording.id).await.unwrap().complete);
+    }
+}
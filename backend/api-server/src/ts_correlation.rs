@@ -0,0 +1,110 @@
+/// Averages `points` (time-ordered `(t, v)` pairs) into `buckets` equal slots
+/// starting at `start_ms`. Empty slots carry the previous slot's value; slots
+/// before the first point stay empty.
+pub fn resample(
+    points: &[(i64, f64)],
+    start_ms: i64,
+    step_ms: i64,
+    buckets: usize,
+) -> Vec<Option<f64>> {
+    let mut sums = vec![(0.0, 0usize); buckets];
+    for &(t, v) in points {
+        if t < start_ms || step_ms <= 0 {
+            continue;
+        }
+        let slot = ((t - start_ms) / step_ms) as usize;
+        if let Some((sum, count)) = sums.get_mut(slot) {
+            *sum += v;
+            *count += 1;
+        }
+    }
+    let mut last = None;
+    sums.into_iter()
+        .map(|(sum, count)| {
+            if count > 0 {
+                last = Some(sum / count as f64);
+            }
+            last
+        })
+        .collect()
+}
+
+/// Pearson correlation of `a[t]` with `b[t + lag]` for every lag in
+/// `-max_lag..=max_lag`; a positive lag means `b` follows `a`. `None` where
+/// fewer than three slots overlap or either side is constant.
+pub fn cross_correlation(
+    a: &[Option<f64>],
+    b: &[Option<f64>],
+    max_lag: usize,
+) -> Vec<(i64, Option<f64>)> {
+    let max_lag = max_lag as i64;
+    (-max_lag..=max_lag)
+        .map(|lag| {
+            let pairs: Vec<(f64, f64)> = (0..a.len() as i64)
+                .filter_map(|i| {
+                    let j = usize::try_from(i + lag).ok()?;
+                    Some(((*a.get(i as usize)?)?, (*b.get(j)?)?))
+                })
+                .collect();
+            (lag, pearson(&pairs))
+        })
+        .collect()
+}
+
+fn pearson(pairs: &[(f64, f64)]) -> Option<f64> {
+    if pairs.len() < 3 {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (x, y) in pairs {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+    if var_x <= f64::EPSILON || var_y <= f64::EPSILON {
+        return None;
+    }
+    Some(cov / (var_x * var_y).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resample_averages_slots_and_carries_gaps() {
+        let points = [(0, 1.0), (5, 3.0), (25, 4.0), (99, 9.0)];
+        assert_eq!(
+            resample(&points, 0, 10, 4),
+            vec![Some(2.0), Some(2.0), Some(4.0), Some(4.0)]
+        );
+        assert_eq!(
+            resample(&[(15, 1.0)], 0, 10, 3),
+            vec![None, Some(1.0), Some(1.0)]
+        );
+    }
+
+    #[test]
+    fn finds_the_lag_at_which_b_follows_a() {
+        let a: Vec<Option<f64>> = (0..60).map(|i| Some(((i * 7) % 11) as f64)).collect();
+        let mut b = vec![Some(0.0); 3];
+        b.extend(a.iter().take(57).copied());
+
+        let lags = cross_correlation(&a, &b, 5);
+        let (best_lag, best_r) = lags
+            .iter()
+            .filter_map(|(lag, r)| r.map(|r| (*lag, r)))
+            .max_by(|x, y| x.1.total_cmp(&y.1))
+            .unwrap();
+        assert_eq!(best_lag, 3);
+        assert!(best_r > 0.99);
+        assert_eq!(lags.len(), 11);
+        assert_eq!(
+            cross_correlation(&[Some(1.0); 5], &[Some(1.0); 5], 0),
+            vec![(0, None)]
+        );
+    }
+}
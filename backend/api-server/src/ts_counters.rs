@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+
+use crate::ts_bands::key_expr_includes;
+
+pub const KIND_GAUGE: &str = "gauge";
+pub const KIND_COUNTER: &str = "counter";
+
+/// How the values of the keys matching `key_expr` are to be aggregated. Keys
+/// without a hint are gauges.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyTypeHint {
+    #[serde(default)]
+    pub id: String,
+    /// Exact key or key expression, e.g. `entmoot/habitat/nodes/*/pea/*/data/*Count`.
+    pub key_expr: String,
+    /// `gauge` or `counter`.
+    pub kind: String,
+    /// Value at which the counter wraps to zero, e.g. 65536 for a 16-bit
+    /// counter. Without it, a drop is taken as a reset.
+    #[serde(default)]
+    pub rollover_at: Option<f64>,
+    #[serde(default)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl KeyTypeHint {
+    pub fn validate(&self) -> Result<(), String> {
+        if zenoh::key_expr::KeyExpr::try_from(self.key_expr.as_str()).is_err() {
+            return Err(format!("Invalid key expression '{}'", self.key_expr));
+        }
+        if self.kind != KIND_GAUGE && self.kind != KIND_COUNTER {
+            return Err(format!(
+                "kind must be '{}' or '{}'",
+                KIND_GAUGE, KIND_COUNTER
+            ));
+        }
+        if self.rollover_at.is_some_and(|at| at <= 0.0) {
+            return Err("rollover_at must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn is_counter(&self) -> bool {
+        self.kind == KIND_COUNTER
+    }
+}
+
+/// The hint for `key`: an exact match, else the longest key expression that
+/// covers it.
+pub fn hint_for<'a>(
+    hints: impl IntoIterator<Item = &'a KeyTypeHint>,
+    key: &str,
+) -> Option<&'a KeyTypeHint> {
+    hints
+        .into_iter()
+        .filter(|hint| key_expr_includes(&hint.key_expr, key))
+        .max_by_key(|hint| (hint.key_expr == key, hint.key_expr.len()))
+}
+
+/// Increase of a counter between consecutive readings.
+#[derive(Debug, Default, PartialEq)]
+pub struct CounterDeltas {
+    /// `(t, raw value, increase since the previous reading)`; the first
+    /// reading has no predecessor and an increase of zero.
+    pub points: Vec<(i64, f64, f64)>,
+    pub total: f64,
+    pub rollovers: usize,
+    pub resets: usize,
+}
+
+/// Turns counter readings into increases. A drop counts as a rollover when
+/// `rollover_at` is set (the counter ran up to it and restarted at zero), and
+/// as a reset to zero otherwise.
+pub fn deltas(readings: &[(i64, f64)], rollover_at: Option<f64>) -> CounterDeltas {
+    let mut out = CounterDeltas::default();
+    let mut previous: Option<f64> = None;
+    for &(t, value) in readings {
+        let delta = match previous {
+            None => 0.0,
+            Some(prev) if value >= prev => value - prev,
+            Some(prev) => match rollover_at {
+                Some(at) if prev <= at => {
+                    out.rollovers += 1;
+                    at - prev + value
+                }
+                _ => {
+                    out.resets += 1;
+                    value
+                }
+            },
+        };
+        out.total += delta;
+        out.points.push((t, value, delta));
+        previous = Some(value);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_are_rollovers_or_resets() {
+        let readings = [(1, 65530.0), (2, 65535.0), (3, 4.0), (4, 10.0)];
+
+        let wrapped = deltas(&readings, Some(65536.0));
+        assert_eq!(wrapped.total, 16.0);
+        assert_eq!(wrapped.rollovers, 1);
+        assert_eq!(wrapped.points[2], (3, 4.0, 5.0));
+
+        let reset = deltas(&readings, None);
+        assert_eq!(reset.total, 15.0);
+        assert_eq!(reset.resets, 1);
+        assert_eq!(reset.points[0], (1, 65530.0, 0.0));
+    }
+
+    #[test]
+    fn most_specific_hint_applies() {
+        let hint = |key_expr: &str, kind: &str| KeyTypeHint {
+            id: key_expr.to_string(),
+            key_expr: key_expr.to_string(),
+            kind: kind.to_string(),
+            rollover_at: None,
+            unit: None,
+            tenant_id: None,
+            updated_at: String::new(),
+        };
+        let hints = [
+            hint("entmoot/**", KIND_GAUGE),
+            hint("entmoot/pea/*/data/GoodCount", KIND_COUNTER),
+        ];
+        assert!(hint_for(&hints, "entmoot/pea/p1/data/GoodCount").is_some_and(|h| h.is_counter()));
+        assert!(hint_for(&hints, "entmoot/pea/p1/data/TT101").is_some_and(|h| !h.is_counter()));
+        assert!(hint("entmoot/x", "rate").validate().is_err());
+    }
+}
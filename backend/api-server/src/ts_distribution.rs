@@ -0,0 +1,142 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Percentiles reported with every distribution.
+const PERCENTILES: [f64; 7] = [1.0, 5.0, 25.0, 50.0, 75.0, 95.0, 99.0];
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Bucket {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Sample standard deviation; 0 for a single value.
+    pub std_dev: f64,
+    /// `p1` … `p99`, interpolated between the nearest values.
+    pub percentiles: BTreeMap<String, f64>,
+    pub histogram: Vec<Bucket>,
+}
+
+/// Statistics and a histogram of `buckets` equal-width buckets spanning
+/// min..max; the last bucket includes the maximum.
+pub fn distribution(values: &[f64], buckets: usize) -> Option<Distribution> {
+    let mut sorted: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    if sorted.is_empty() {
+        return None;
+    }
+    sorted.sort_by(f64::total_cmp);
+    let count = sorted.len();
+    let (min, max) = (sorted[0], sorted[count - 1]);
+    let mean = sorted.iter().sum::<f64>() / count as f64;
+    let std_dev = if count > 1 {
+        (sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (count - 1) as f64).sqrt()
+    } else {
+        0.0
+    };
+    let percentiles = PERCENTILES
+        .iter()
+        .map(|p| (format!("p{}", p), percentile(&sorted, *p)))
+        .collect();
+
+    let buckets = buckets.max(1);
+    let width = (max - min) / buckets as f64;
+    let mut histogram: Vec<Bucket> = (0..buckets)
+        .map(|i| Bucket {
+            lower: min + width * i as f64,
+            upper: if i + 1 == buckets {
+                max
+            } else {
+                min + width * (i + 1) as f64
+            },
+            count: 0,
+        })
+        .collect();
+    for value in &sorted {
+        let index = if width > 0.0 {
+            (((value - min) / width) as usize).min(buckets - 1)
+        } else {
+            0
+        };
+        histogram[index].count += 1;
+    }
+
+    Some(Distribution {
+        count,
+        min,
+        max,
+        mean,
+        std_dev,
+        percentiles,
+        histogram,
+    })
+}
+
+/// The `p`th percentile of ascending `sorted` values.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+/// Process capability against specification limits.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Capability {
+    /// Spread of the limits over six standard deviations; needs both limits.
+    pub cp: Option<f64>,
+    /// Distance of the mean to the nearer limit over three standard deviations.
+    pub cpk: Option<f64>,
+}
+
+pub fn capability(distribution: &Distribution, lsl: Option<f64>, usl: Option<f64>) -> Capability {
+    let sigma = distribution.std_dev;
+    if sigma <= f64::EPSILON {
+        return Capability {
+            cp: None,
+            cpk: None,
+        };
+    }
+    let mean = distribution.mean;
+    let cpl = lsl.map(|lsl| (mean - lsl) / (3.0 * sigma));
+    let cpu = usl.map(|usl| (usl - mean) / (3.0 * sigma));
+    Capability {
+        cp: lsl.zip(usl).map(|(lsl, usl)| (usl - lsl) / (6.0 * sigma)),
+        cpk: match (cpl, cpu) {
+            (Some(l), Some(u)) => Some(l.min(u)),
+            (one, other) => one.or(other),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statistics_histogram_and_capability() {
+        let values: Vec<f64> = (0..=100).map(f64::from).collect();
+        let d = distribution(&values, 4).unwrap();
+        assert_eq!((d.count, d.min, d.max, d.mean), (101, 0.0, 100.0, 50.0));
+        assert_eq!(d.percentiles["p50"], 50.0);
+        assert_eq!(d.percentiles["p95"], 95.0);
+        let counts: Vec<usize> = d.histogram.iter().map(|b| b.count).collect();
+        assert_eq!(counts, vec![25, 25, 25, 26]);
+        assert_eq!(d.histogram[3].upper, 100.0);
+
+        let sigma = d.std_dev;
+        let c = capability(&d, Some(50.0 - 6.0 * sigma), Some(50.0 + 3.0 * sigma));
+        assert!((c.cp.unwrap() - 1.5).abs() < 1e-9);
+        assert!((c.cpk.unwrap() - 1.0).abs() < 1e-9);
+        assert_eq!(capability(&d, None, None).cp, None);
+
+        let flat = distribution(&[7.0, 7.0, f64::NAN], 10).unwrap();
+        assert_eq!((flat.count, flat.histogram[0].count), (2, 2));
+        assert!(distribution(&[], 10).is_none());
+    }
+}
@@ -0,0 +1,206 @@
+use serde::Serialize;
+
+use crate::ts_bands::ThresholdBand;
+
+/// Smoothing factors of Holt's method for level and trend.
+const HOLT_ALPHA: f64 = 0.5;
+const HOLT_BETA: f64 = 0.3;
+
+/// How a forecast projects the history.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Model {
+    /// Least-squares line through every point.
+    Linear,
+    /// Holt's double exponential smoothing (Holt-Winters without a seasonal
+    /// component), which follows recent changes of the trend.
+    Holt,
+}
+
+impl Model {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "linear" => Some(Self::Linear),
+            "holt" | "holt_winters" => Some(Self::Holt),
+            _ => None,
+        }
+    }
+}
+
+/// A straight projection: `value` at `at_ms`, changing by `slope_per_ms`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Trend {
+    pub at_ms: i64,
+    pub value: f64,
+    pub slope_per_ms: f64,
+}
+
+impl Trend {
+    pub fn value_at(&self, t_ms: i64) -> f64 {
+        self.value + self.slope_per_ms * (t_ms - self.at_ms) as f64
+    }
+
+    /// When the projection reaches `limit`, if it moves towards it and gets
+    /// there by `until_ms`. A value already past the limit crosses at once.
+    pub fn crossing(&self, limit: f64, until_ms: i64) -> Option<i64> {
+        if self.value == limit {
+            return Some(self.at_ms);
+        }
+        if self.slope_per_ms == 0.0 {
+            return None;
+        }
+        let offset_ms = (limit - self.value) / self.slope_per_ms;
+        if offset_ms < 0.0 {
+            return None;
+        }
+        let at_ms = self.at_ms + offset_ms.ceil() as i64;
+        (at_ms <= until_ms).then_some(at_ms)
+    }
+}
+
+/// Least-squares line through time-ordered `(t, v)` points, anchored at the
+/// last point's time. Needs two points at different times.
+pub fn linear_trend(points: &[(i64, f64)]) -> Option<Trend> {
+    let &(last_ms, _) = points.last()?;
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    // Times relative to the last point keep the sums small.
+    let xs: Vec<f64> = points.iter().map(|(t, _)| (t - last_ms) as f64).collect();
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, v)| v).sum::<f64>() / n;
+    let (mut cov, mut var_x) = (0.0, 0.0);
+    for (x, (_, y)) in xs.iter().zip(points) {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+    }
+    if var_x <= f64::EPSILON {
+        return None;
+    }
+    let slope = cov / var_x;
+    Some(Trend {
+        at_ms: last_ms,
+        value: mean_y - slope * mean_x,
+        slope_per_ms: slope,
+    })
+}
+
+/// Holt's method over evenly spaced `slots` (as resampled from `start_ms`
+/// every `step_ms`), anchored at the last filled slot. Needs two filled slots.
+pub fn holt_trend(slots: &[Option<f64>], start_ms: i64, step_ms: i64) -> Option<Trend> {
+    let mut filled = slots
+        .iter()
+        .enumerate()
+        .filter_map(|(i, slot)| slot.map(|v| (i, v)));
+    let (first_index, first) = filled.next()?;
+    let (mut last_index, second) = filled.next()?;
+    let mut level = second;
+    let mut trend = (second - first) / (last_index - first_index) as f64;
+    for (index, value) in filled {
+        let gap = (index - last_index) as f64;
+        let previous = level;
+        level = HOLT_ALPHA * value + (1.0 - HOLT_ALPHA) * (level + trend * gap);
+        trend = HOLT_BETA * (level - previous) / gap + (1.0 - HOLT_BETA) * trend;
+        last_index = index;
+    }
+    if step_ms <= 0 {
+        return None;
+    }
+    Some(Trend {
+        at_ms: start_ms + last_index as i64 * step_ms,
+        value: level,
+        slope_per_ms: trend / step_ms as f64,
+    })
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct LimitCrossing {
+    /// `critical_low`, `warning_low`, `warning_high`, `critical_high` or `limit`.
+    pub limit: String,
+    pub value: f64,
+    /// When the projection reaches the limit; `None` if not within the horizon.
+    pub at_ms: Option<i64>,
+}
+
+/// Crossings of the band's limits and of an extra `limit`, in that order.
+pub fn crossings(
+    trend: &Trend,
+    band: Option<&ThresholdBand>,
+    limit: Option<f64>,
+    until_ms: i64,
+) -> Vec<LimitCrossing> {
+    let band_limits = band.into_iter().flat_map(|band| {
+        [
+            ("critical_low", band.critical_low),
+            ("warning_low", band.warning_low),
+            ("warning_high", band.warning_high),
+            ("critical_high", band.critical_high),
+        ]
+    });
+    band_limits
+        .chain([("limit", limit)])
+        .filter_map(|(name, value)| {
+            let value = value?;
+            Some(LimitCrossing {
+                limit: name.to_string(),
+                value,
+                at_ms: trend.crossing(value, until_ms),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ts_correlation;
+
+    #[test]
+    fn draining_tank_crosses_its_low_limits_in_order() {
+        // A tank losing 2 % per hour, sampled every 10 minutes for six hours.
+        let hour = 3_600_000;
+        let points: Vec<(i64, f64)> = (0..=36)
+            .map(|i| {
+                let t = i * hour / 6;
+                (t, 80.0 - 2.0 * t as f64 / hour as f64)
+            })
+            .collect();
+
+        let linear = linear_trend(&points).unwrap();
+        assert_eq!(linear.at_ms, 6 * hour);
+        assert!((linear.value - 68.0).abs() < 1e-9);
+        assert!((linear.value_at(7 * hour) - 66.0).abs() < 1e-9);
+
+        let slots = ts_correlation::resample(&points, 0, hour / 6, 37);
+        let holt = holt_trend(&slots, 0, hour / 6).unwrap();
+        assert!((holt.value - 68.0).abs() < 1e-6);
+        assert!((holt.slope_per_ms * hour as f64 + 2.0).abs() < 1e-6);
+
+        let band = ThresholdBand {
+            id: "tank".to_string(),
+            key_expr: "entmoot/tank/level".to_string(),
+            unit: Some("%".to_string()),
+            warning_low: Some(20.0),
+            warning_high: Some(95.0),
+            critical_low: Some(10.0),
+            critical_high: None,
+            tenant_id: None,
+            updated_at: String::new(),
+        };
+        let found = crossings(&linear, Some(&band), Some(60.0), 6 * hour + 26 * hour);
+        let at: Vec<(&str, Option<i64>)> = found
+            .iter()
+            .map(|c| (c.limit.as_str(), c.at_ms.map(|t| (t - 6 * hour) / hour)))
+            .collect();
+        assert_eq!(
+            at,
+            vec![
+                ("critical_low", None),
+                ("warning_low", Some(24)),
+                ("warning_high", None),
+                ("limit", Some(4)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::sync::{Arc, Mutex};
+use tracing::error;
+
+use crate::db;
+use crate::leader::Leadership;
+use crate::state::{AppState, TimeSeriesPoint};
+
+/// Shared Postgres historian behind `/ts/query`.
+///
+/// The leader writes every ingested point to the `ts_points` table in batches.
+/// Queries read the range before the oldest point still in memory from the
+/// table, and followers read the whole range from it, so every replica answers
+/// with the same points.
+#[derive(Clone)]
+pub struct Historian {
+    enabled: bool,
+    leadership: Leadership,
+    pending: Arc<Mutex<Vec<(String, TimeSeriesPoint)>>>,
+}
+
+impl Historian {
+    /// Enabled with `TS_HISTORIAN=true`.
+    pub fn from_env(leadership: Leadership) -> Self {
+        let enabled = std::env::var("TS_HISTORIAN")
+            .map(|value| matches!(value.as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+        Self {
+            enabled,
+            leadership,
+            pending: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Queues an ingested point for the next flush; only the leader writes.
+    pub fn record(&self, key: &str, point: &TimeSeriesPoint) {
+        if !self.enabled || !self.leadership.is_leader() {
+            return;
+        }
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((key.to_string(), point.clone()));
+    }
+
+    /// Writes the queued points. Points that fail to write are dropped rather
+    /// than retried, so a database outage cannot grow the queue without bound.
+    pub async fn flush(&self, client: &tokio_postgres::Client) {
+        let points = std::mem::take(&mut *self.pending.lock().unwrap_or_else(|e| e.into_inner()));
+        if points.is_empty() {
+            return;
+        }
+        if let Err(e) = db::insert_ts_points(client, &points).await {
+            error!(
+                "Failed to write {} point(s) to the time-series historian: {}",
+                points.len(),
+                e
+            );
+        }
+    }
+
+    /// The part of `[start_ms, end_ms]` to read from the table, given the
+    /// timestamp of the oldest point of the key held in memory.
+    pub fn db_range(
+        &self,
+        oldest_in_memory_ms: Option<i64>,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Option<(i64, i64)> {
+        if !self.enabled {
+            return None;
+        }
+        db_range(
+            self.leadership.is_leader(),
+            oldest_in_memory_ms,
+            start_ms,
+            end_ms,
+        )
+    }
+}
+
+pub fn db_range(
+    leader: bool,
+    oldest_in_memory_ms: Option<i64>,
+    start_ms: i64,
+    end_ms: i64,
+) -> Option<(i64, i64)> {
+    if !leader {
+        return Some((start_ms, end_ms));
+    }
+    match oldest_in_memory_ms {
+        None => Some((start_ms, end_ms)),
+        Some(oldest) if oldest > start_ms => Some((start_ms, end_ms.min(oldest - 1))),
+        Some(_) => None,
+    }
+}
+
+/// Points from the historian and from memory in time order; memory wins where
+/// both hold a point at the same time.
+pub fn merge(stored: Vec<TimeSeriesPoint>, memory: Vec<TimeSeriesPoint>) -> Vec<TimeSeriesPoint> {
+    if stored.is_empty() {
+        return memory;
+    }
+    let mut merged = Vec::with_capacity(stored.len() + memory.len());
+    let mut stored = stored.into_iter().peekable();
+    for point in memory {
+        while let Some(earlier) = stored.next_if(|p| p.timestamp_ms < point.timestamp_ms) {
+            merged.push(earlier);
+        }
+        while stored
+            .next_if(|p| p.timestamp_ms == point.timestamp_ms)
+            .is_some()
+        {}
+        merged.push(point);
+    }
+    merged.extend(stored);
+    merged
+}
+
+/// Points of `key` in `[start_ms, end_ms]` from memory, completed from the
+/// historian when it is enabled.
+pub async fn points(
+    state: &AppState,
+    key: &str,
+    start_ms: i64,
+    end_ms: i64,
+) -> Vec<TimeSeriesPoint> {
+    let (memory, oldest) = {
+        let store = state.timeseries.read().await;
+        let memory: Vec<TimeSeriesPoint> = store
+            .query(key, start_ms, end_ms)
+            .into_iter()
+            .cloned()
+            .collect();
+        let oldest = store
+            .data
+            .get(key)
+            .and_then(|buf| buf.front())
+            .map(|point| point.timestamp_ms);
+        (memory, oldest)
+    };
+    let Some((from_ms, to_ms)) = state.historian.db_range(oldest, start_ms, end_ms) else {
+        return memory;
+    };
+    match db::query_ts_points(&state.db_client, key, from_ms, to_ms).await {
+        Ok(stored) => merge(stored, memory),
+        Err(e) => {
+            error!(
+                "Failed to read {} from the time-series historian: {}",
+                key, e
+            );
+            memory
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::point;
+
+    #[test]
+    fn leader_reads_only_what_memory_no_longer_holds() {
+        assert_eq!(db_range(true, Some(500), 100, 900), Some((100, 499)));
+        assert_eq!(db_range(true, Some(500), 600, 900), None);
+        assert_eq!(db_range(true, Some(500), 100, 300), Some((100, 300)));
+        assert_eq!(db_range(true, None, 100, 900), Some((100, 900)));
+        assert_eq!(db_range(false, Some(500), 600, 900), Some((600, 900)));
+    }
+
+    #[test]
+    fn merge_orders_points_and_prefers_memory() {
+        let merged = merge(
+            vec![point(1, 10), point(3, 30), point(5, 50), point(7, 70)],
+            vec![point(3, 31), point(4, 41), point(5, 51)],
+        );
+        let pairs: Vec<(i64, serde_json::Value)> = merged
+            .into_iter()
+            .map(|p| (p.timestamp_ms, p.value))
+            .collect();
+
+        assert_eq!(
+            pairs,
+            vec![
+                (1, serde_json::json!(10)),
+                (3, serde_json::json!(31)),
+                (4, serde_json::json!(41)),
+                (5, serde_json::json!(51)),
+                (7, serde_json::json!(70)),
+            ]
+        );
+    }
+}
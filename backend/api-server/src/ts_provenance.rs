@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+
+use crate::key_tree::is_under;
+
+/// Zenoh attachment naming the producer of a sample, e.g. `producer=ws-publish`.
+pub const ATTACHMENT_PREFIX: &str = "producer=";
+
+/// Where a stored time-series point came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Producer {
+    Simulator,
+    EvaIcsConnector,
+    Underhill,
+    WsPublish,
+    External,
+}
+
+impl Producer {
+    pub const ALL: [Producer; 5] = [
+        Producer::Simulator,
+        Producer::EvaIcsConnector,
+        Producer::Underhill,
+        Producer::WsPublish,
+        Producer::External,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Simulator => "simulator",
+            Self::EvaIcsConnector => "eva-ics-connector",
+            Self::Underhill => "underhill",
+            Self::WsPublish => "ws-publish",
+            Self::External => "external",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|producer| producer.as_str() == name.trim())
+    }
+
+    /// The attachment a publisher sets to name itself as the producer.
+    pub fn attachment(self) -> String {
+        format!("{}{}", ATTACHMENT_PREFIX, self.as_str())
+    }
+}
+
+/// Derives the producer of ingested samples: from the sample attachment, then
+/// from `producer` or `origin` fields in the payload, then from the key prefix.
+/// Samples nothing else identifies count as external ingest.
+#[derive(Clone, Debug)]
+pub struct ProducerRules {
+    /// Literal key prefixes and their producer; the longest matching prefix wins.
+    prefixes: Vec<(String, Producer)>,
+}
+
+impl Default for ProducerRules {
+    fn default() -> Self {
+        Self {
+            prefixes: vec![
+                ("entmoot/eva".to_string(), Producer::EvaIcsConnector),
+                ("entmoot/habitat".to_string(), Producer::Underhill),
+                ("pea".to_string(), Producer::Simulator),
+            ],
+        }
+    }
+}
+
+impl ProducerRules {
+    /// Prefix rules from `TS_PROVENANCE_PREFIXES`, e.g.
+    /// `entmoot/eva=eva-ics-connector,pea=simulator`; the defaults otherwise.
+    pub fn from_env() -> Self {
+        match std::env::var("TS_PROVENANCE_PREFIXES") {
+            Ok(spec) => Self::parse(&spec),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn parse(spec: &str) -> Self {
+        let prefixes = spec
+            .split(',')
+            .filter_map(|rule| {
+                let (prefix, producer) = rule.split_once('=')?;
+                let prefix = prefix.trim().trim_matches('/');
+                match Producer::parse(producer) {
+                    Some(producer) if !prefix.is_empty() => Some((prefix.to_string(), producer)),
+                    _ => {
+                        tracing::warn!("Ignoring provenance rule '{}'", rule);
+                        None
+                    }
+                }
+            })
+            .collect();
+        Self { prefixes }
+    }
+
+    pub fn producer(
+        &self,
+        key: &str,
+        value: &serde_json::Value,
+        attachment: Option<&str>,
+    ) -> Producer {
+        if let Some(producer) = attachment
+            .and_then(|attachment| attachment.strip_prefix(ATTACHMENT_PREFIX))
+            .and_then(Producer::parse)
+        {
+            return producer;
+        }
+        if let Some(producer) = value
+            .get("producer")
+            .and_then(|producer| producer.as_str())
+            .and_then(Producer::parse)
+        {
+            return producer;
+        }
+        if value.get("origin").and_then(|origin| origin.as_str()) == Some("simulated") {
+            return Producer::Simulator;
+        }
+        self.prefixes
+            .iter()
+            .filter(|(prefix, _)| is_under(prefix, key))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, producer)| *producer)
+            .unwrap_or(Producer::External)
+    }
+}
+
+/// Producers named in a comma-separated `producer` query parameter.
+pub fn parse_filter(spec: &str) -> Result<Vec<Producer>, String> {
+    spec.split(',')
+        .filter(|name| !name.trim().is_empty())
+        .map(|name| Producer::parse(name).ok_or_else(|| format!("Unknown producer: {}", name)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attachment_and_payload_metadata_win_over_key_prefixes() {
+        let rules = ProducerRules::default();
+        let value = serde_json::json!({"v": 1.0});
+
+        assert_eq!(
+            rules.producer("entmoot/habitat/nodes/n1/pea/p1/status", &value, None),
+            Producer::Underhill
+        );
+        assert_eq!(
+            rules.producer("pea/p1/temp", &value, None),
+            Producer::Simulator
+        );
+        assert_eq!(
+            rules.producer("entmoot/sensors/t1", &value, None),
+            Producer::External
+        );
+        assert_eq!(
+            rules.producer(
+                "entmoot/habitat/nodes/n1/pea/p1/status",
+                &value,
+                Some("producer=ws-publish")
+            ),
+            Producer::WsPublish
+        );
+        assert_eq!(
+            rules.producer(
+                "entmoot/habitat/nodes/n1/pea/p1/status",
+                &serde_json::json!({"deployed": true, "origin": "simulated"}),
+                None
+            ),
+            Producer::Simulator
+        );
+        assert_eq!(
+            rules.producer(
+                "entmoot/sensors/t1",
+                &serde_json::json!({"v": 1, "producer": "eva-ics-connector"}),
+                None
+            ),
+            Producer::EvaIcsConnector
+        );
+    }
+
+    #[test]
+    fn prefix_rules_parse_from_spec() {
+        let rules = ProducerRules::parse("site/a=underhill, site/a/sim=simulator,bad=nobody");
+
+        let value = serde_json::Value::Null;
+        assert_eq!(
+            rules.producer("site/a/x", &value, None),
+            Producer::Underhill
+        );
+        assert_eq!(
+            rules.producer("site/a/sim/x", &value, None),
+            Producer::Simulator
+        );
+        assert_eq!(rules.producer("pea/p1", &value, None), Producer::External);
+        assert_eq!(
+            parse_filter("simulator,ws-publish"),
+            Ok(vec![Producer::Simulator, Producer::WsPublish])
+        );
+        assert!(parse_filter("simulator,robot").is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+/// Most keys a saved query may chart together.
+pub const MAX_KEYS: usize = 32;
+
+/// A shareable time-series explorer view: which keys, over which window, how
+/// they are sampled and how the chart is drawn.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedQuery {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub keys: Vec<String>,
+    /// Relative window ending now, e.g. 3600000 for the last hour. Takes
+    /// precedence over `start_ms`/`end_ms`.
+    #[serde(default)]
+    pub window_ms: Option<i64>,
+    #[serde(default)]
+    pub start_ms: Option<i64>,
+    #[serde(default)]
+    pub end_ms: Option<i64>,
+    /// Downsampling applied per key, as `max_points` on `/ts/query`.
+    #[serde(default)]
+    pub max_points: Option<usize>,
+    /// Chart preset for the explorer (chart type, axes, colours); stored as given.
+    #[serde(default)]
+    pub chart: serde_json::Value,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    #[serde(default)]
+    pub created_by: Option<String>,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl SavedQuery {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name is required".to_string());
+        }
+        if self.keys.is_empty() || self.keys.iter().any(|key| key.trim().is_empty()) {
+            return Err("keys must list at least one non-empty key".to_string());
+        }
+        if self.keys.len() > MAX_KEYS {
+            return Err(format!("keys may list at most {} keys", MAX_KEYS));
+        }
+        match (self.window_ms, self.start_ms, self.end_ms) {
+            (Some(window_ms), _, _) if window_ms <= 0 => {
+                Err("window_ms must be positive".to_string())
+            }
+            (Some(_), _, _) => Ok(()),
+            (None, Some(start_ms), Some(end_ms)) if start_ms < end_ms => Ok(()),
+            (None, Some(_), Some(_)) => Err("start_ms must be before end_ms".to_string()),
+            _ => Err("set window_ms, or both start_ms and end_ms".to_string()),
+        }
+    }
+
+    /// The time range the query covers when run at `now_ms`.
+    pub fn range(&self, now_ms: i64) -> (i64, i64) {
+        match self.window_ms {
+            Some(window_ms) => (now_ms - window_ms, now_ms),
+            None => (
+                self.start_ms.unwrap_or_default(),
+                self.end_ms.unwrap_or(now_ms),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(window_ms: Option<i64>, start_ms: Option<i64>, end_ms: Option<i64>) -> SavedQuery {
+        SavedQuery {
+            id: "q1".to_string(),
+            name: "Reactor temperatures".to_string(),
+            description: String::new(),
+            keys: vec!["entmoot/pea/r1/data/TT101".to_string()],
+            window_ms,
+            start_ms,
+            end_ms,
+            max_points: Some(500),
+            chart: serde_json::Value::Null,
+            tenant_id: None,
+            created_by: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn relative_windows_follow_now_and_fixed_ranges_do_not() {
+        let relative = query(Some(3_600_000), None, None);
+        assert!(relative.validate().is_ok());
+        assert_eq!(relative.range(10_000_000), (6_400_000, 10_000_000));
+
+        let fixed = query(None, Some(1_000), Some(2_000));
+        assert!(fixed.validate().is_ok());
+        assert_eq!(fixed.range(10_000_000), (1_000, 2_000));
+
+        assert!(query(None, Some(2_000), Some(1_000)).validate().is_err());
+        assert!(query(None, Some(1_000), None).validate().is_err());
+        assert!(query(Some(0), None, None).validate().is_err());
+
+        let mut no_keys = query(Some(1), None, None);
+        no_keys.keys.clear();
+        assert!(no_keys.validate().is_err());
+    }
+}
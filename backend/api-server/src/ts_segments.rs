@@ -0,0 +1,193 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::state_analytics;
+use crate::ts_distribution::{self, Distribution};
+
+/// How far before the range the state key is read, so the state in effect
+/// at its start is known.
+pub const STATE_LOOKBACK_MS: i64 = 86_400_000;
+
+/// A stretch of time during which the state key held one state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateSpan {
+    pub state: String,
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+/// Values of a telemetry key observed while the machine was in one state.
+#[derive(Debug, Serialize)]
+pub struct Segment {
+    pub state: String,
+    /// Time spent in the state within the range.
+    pub duration_ms: i64,
+    #[serde(flatten)]
+    pub distribution: Option<Distribution>,
+}
+
+/// State named by one sample of a state key. With `service`, the sample is a
+/// PEA status and the service's state is used. Otherwise a plain value, or
+/// the `state`, `status`, `mode`, `value` or `v` field of an object; a PEA
+/// status without those reads as `running`, `deployed` or `idle`, as on the
+/// swimlane.
+pub fn state_label(value: &serde_json::Value, service: Option<&str>) -> Option<String> {
+    if let Some(service) = service {
+        let (_, state) = state_analytics::service_states_from_status(value)
+            .into_iter()
+            .find(|(tag, _)| tag == service)?;
+        return serde_json::to_value(state)
+            .ok()?
+            .as_str()
+            .map(str::to_string);
+    }
+    let text = |value: &serde_json::Value| match value {
+        serde_json::Value::String(text) => Some(text.clone()),
+        serde_json::Value::Number(number) => Some(number.to_string()),
+        serde_json::Value::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    };
+    if let Some(label) = text(value) {
+        return Some(label);
+    }
+    let object = value.as_object()?;
+    if let Some(label) = ["state", "status", "mode", "value", "v"]
+        .iter()
+        .find_map(|field| object.get(*field).and_then(text))
+    {
+        return Some(label);
+    }
+    match (object.get("running"), object.get("deployed")) {
+        (Some(serde_json::Value::Bool(true)), _) => Some("running".to_string()),
+        (_, Some(serde_json::Value::Bool(true))) => Some("deployed".to_string()),
+        (Some(_), _) | (_, Some(_)) => Some("idle".to_string()),
+        _ => None,
+    }
+}
+
+/// Spans between time-ordered state samples, clipped to `[start_ms, end_ms]`.
+/// Each state holds until the next different one; the last until `end_ms`.
+pub fn spans(samples: &[(i64, String)], start_ms: i64, end_ms: i64) -> Vec<StateSpan> {
+    let mut changes: Vec<&(i64, String)> = Vec::new();
+    for sample in samples {
+        if changes.last().is_none_or(|last| last.1 != sample.1) {
+            changes.push(sample);
+        }
+    }
+    changes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (t, state))| {
+            let until = changes.get(i + 1).map_or(end_ms, |next| next.0);
+            let (from, to) = ((*t).max(start_ms), until.min(end_ms));
+            (from < to).then(|| StateSpan {
+                state: state.clone(),
+                start_ms: from,
+                end_ms: to,
+            })
+        })
+        .collect()
+}
+
+/// Splits time-ordered telemetry `points` by the span they fall in and
+/// summarizes each state. Returns the segments, by state name, and the
+/// number of points outside every span.
+pub fn segment(
+    points: &[(i64, f64)],
+    spans: &[StateSpan],
+    buckets: usize,
+) -> (Vec<Segment>, usize) {
+    let mut values: BTreeMap<&str, (i64, Vec<f64>)> = BTreeMap::new();
+    for span in spans {
+        values.entry(&span.state).or_default().0 += span.end_ms - span.start_ms;
+    }
+    let mut unsegmented = 0;
+    for (t, v) in points {
+        // The last span starting at or before t; spans are ordered and disjoint.
+        let index = spans.partition_point(|span| span.start_ms <= *t);
+        match index.checked_sub(1).map(|i| &spans[i]) {
+            Some(span) if *t < span.end_ms || (*t == span.end_ms && index == spans.len()) => {
+                values.entry(&span.state).or_default().1.push(*v);
+            }
+            _ => unsegmented += 1,
+        }
+    }
+    let segments = values
+        .into_iter()
+        .map(|(state, (duration_ms, values))| Segment {
+            state: state.to_string(),
+            duration_ms,
+            distribution: ts_distribution::distribution(&values, buckets),
+        })
+        .collect();
+    (segments, unsegmented)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vibration_is_split_by_the_machine_state_in_effect() {
+        let samples: Vec<(i64, String)> = [
+            (-500, serde_json::json!("OPERATING")),
+            (1000, serde_json::json!({"state": "OPERATING"})),
+            (2000, serde_json::json!({"v": "MAINTENANCE"})),
+            (3000, serde_json::json!("OPERATING")),
+        ]
+        .into_iter()
+        .filter_map(|(t, value)| Some((t, state_label(&value, None)?)))
+        .collect();
+        let spans = spans(&samples, 0, 4000);
+        assert_eq!(
+            spans,
+            vec![
+                StateSpan {
+                    state: "OPERATING".to_string(),
+                    start_ms: 0,
+                    end_ms: 2000
+                },
+                StateSpan {
+                    state: "MAINTENANCE".to_string(),
+                    start_ms: 2000,
+                    end_ms: 3000
+                },
+                StateSpan {
+                    state: "OPERATING".to_string(),
+                    start_ms: 3000,
+                    end_ms: 4000
+                },
+            ]
+        );
+
+        let points = [
+            (500, 2.0),
+            (1500, 4.0),
+            (2500, 0.5),
+            (3500, 6.0),
+            (4000, 8.0),
+        ];
+        let (segments, unsegmented) = segment(&points, &spans, 10);
+        assert_eq!(unsegmented, 0);
+        let summary: Vec<(&str, i64, usize, f64)> = segments
+            .iter()
+            .map(|s| {
+                let d = s.distribution.as_ref().unwrap();
+                (s.state.as_str(), s.duration_ms, d.count, d.mean)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("MAINTENANCE", 1000, 1, 0.5), ("OPERATING", 3000, 4, 5.0)]
+        );
+
+        let status = serde_json::json!({"deployed": true, "running": false, "services": [
+            {"tag": "Heat", "state": "Execute"}
+        ]});
+        assert_eq!(state_label(&status, None).as_deref(), Some("deployed"));
+        assert_eq!(
+            state_label(&status, Some("Heat")).as_deref(),
+            Some("Execute")
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use crate::request_context::CallerContext;
+use crate::runtime_store;
+use crate::severity_profile::SeverityProfile;
+use crate::state::AppState;
+use crate::tenancy::{self, TenantScope};
+use crate::webhook_service::{
+    NotificationPreferences, QuietHours, WebhookEvent, WebhookSubscription, EVENT_WEBHOOK_TEST,
+    SUPPORTED_EVENTS,
+};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use tracing::info;
+
+#[derive(serde::Deserialize)]
+pub struct WebhookPayload {
+    pub name: String,
+    pub url: String,
+    /// Signing secret; generated on create and kept on update when omitted.
+    pub secret: Option<String>,
+    pub events: Vec<String>,
+    pub min_alarm_severity: Option<String>,
+    pub enabled: Option<bool>,
+    pub digest_minutes: Option<u32>,
+    pub quiet_hours: Option<QuietHours>,
+    pub owner: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct DeliveryQuery {
+    pub limit: Option<usize>,
+}
+
+pub async fn list_webhooks(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let subscriptions = state.webhooks.subscriptions.read().await;
+    let list: Vec<serde_json::Value> = subscriptions
+        .values()
+        .filter(|subscription| scope.allows(subscription.tenant_id.as_deref()))
+        .map(WebhookSubscription::public_view)
+        .collect();
+    HttpResponse::Ok().json(list)
+}
+
+pub async fn get_webhook(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    webhook_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let Some(mut view) = state
+        .webhooks
+        .subscriptions
+        .read()
+        .await
+        .get(webhook_id.as_str())
+        .filter(|subscription| scope.allows(subscription.tenant_id.as_deref()))
+        .map(WebhookSubscription::public_view)
+    else {
+        return webhook_not_found();
+    };
+    view["pending_digest"] = state
+        .webhooks
+        .pending_digest(webhook_id.as_str())
+        .await
+        .into();
+    HttpResponse::Ok().json(view)
+}
+
+/// POST /webhooks — the response is the only place the signing secret is returned.
+pub async fn create_webhook(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<WebhookPayload>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let payload = body.into_inner();
+    if let Err(err) = validate_payload(&payload, &*state.severity_profile.read().await) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": err}));
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let subscription = WebhookSubscription {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: payload.name,
+        url: payload.url,
+        secret: payload
+            .secret
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string()),
+        events: payload.events,
+        min_alarm_severity: payload.min_alarm_severity,
+        enabled: payload.enabled.unwrap_or(true),
+        digest_minutes: payload.digest_minutes,
+        quiet_hours: payload.quiet_hours,
+        owner: payload.owner,
+        tenant_id: scope.tenant_id().map(str::to_string),
+        created_at: now.clone(),
+        updated_at: now,
+    };
+
+    runtime_store::persist_json(&state.webhook_dir, &subscription.id, &subscription);
+    state
+        .webhooks
+        .subscriptions
+        .write()
+        .await
+        .insert(subscription.id.clone(), subscription.clone());
+
+    info!(
+        "Created webhook: {} ({})",
+        subscription.name, subscription.id
+    );
+    HttpResponse::Created().json(subscription)
+}
+
+pub async fn update_webhook(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    webhook_id: web::Path<String>,
+    body: web::Json<WebhookPayload>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let payload = body.into_inner();
+    if let Err(err) = validate_payload(&payload, &*state.severity_profile.read().await) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": err}));
+    }
+
+    let mut subscriptions = state.webhooks.subscriptions.write().await;
+    let Some(subscription) = subscriptions
+        .get_mut(webhook_id.as_str())
+        .filter(|subscription| scope.allows(subscription.tenant_id.as_deref()))
+    else {
+        return webhook_not_found();
+    };
+
+    subscription.name = payload.name;
+    subscription.url = payload.url;
+    if let Some(secret) = payload.secret.filter(|s| !s.is_empty()) {
+        subscription.secret = secret;
+    }
+    subscription.events = payload.events;
+    subscription.min_alarm_severity = payload.min_alarm_severity;
+    subscription.enabled = payload.enabled.unwrap_or(true);
+    subscription.digest_minutes = payload.digest_minutes;
+    subscription.quiet_hours = payload.quiet_hours;
+    subscription.owner = payload.owner;
+    subscription.updated_at = Utc::now().to_rfc3339();
+
+    runtime_store::persist_json(&state.webhook_dir, &subscription.id, &*subscription);
+    HttpResponse::Ok().json(subscription.public_view())
+}
+
+pub async fn delete_webhook(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    webhook_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let mut subscriptions = state.webhooks.subscriptions.write().await;
+    match subscriptions.get(webhook_id.as_str()) {
+        Some(subscription) if scope.allows(subscription.tenant_id.as_deref()) => {}
+        _ => return webhook_not_found(),
+    }
+    subscriptions.remove(webhook_id.as_str());
+    runtime_store::delete_json(&state.webhook_dir, webhook_id.as_str());
+    HttpResponse::NoContent().finish()
+}
+
+/// GET /webhooks/{id}/deliveries — most recent delivery attempts first.
+pub async fn list_deliveries(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    webhook_id: web::Path<String>,
+    query: web::Query<DeliveryQuery>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    if visible_subscription(&state, &scope, &webhook_id)
+        .await
+        .is_none()
+    {
+        return webhook_not_found();
+    }
+    let deliveries = state
+        .webhooks
+        .deliveries_for(webhook_id.as_str(), query.limit.unwrap_or(100))
+        .await;
+    HttpResponse::Ok().json(deliveries)
+}
+
+/// POST /webhooks/{id}/test — sends a `webhook.test` event regardless of the subscribed events.
+pub async fn test_webhook(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    webhook_id: web::Path<String>,
+) -> impl Responder {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return e.response(),
+    };
+    let Some(subscription) = visible_subscription(&state, &scope, &webhook_id).await else {
+        return webhook_not_found();
+    };
+    let event = WebhookEvent::new(
+        EVENT_WEBHOOK_TEST,
+        serde_json::json!({ "webhook_id": subscription.id }),
+    );
+    let delivery = state.webhooks.deliver(subscription, event).await;
+    HttpResponse::Accepted().json(delivery)
+}
+
+async fn visible_subscription(
+    state: &AppState,
+    scope: &TenantScope,
+    webhook_id: &str,
+) -> Option<WebhookSubscription> {
+    state
+        .webhooks
+        .subscriptions
+        .read()
+        .await
+        .get(webhook_id)
+        .filter(|subscription| scope.allows(subscription.tenant_id.as_deref()))
+        .cloned()
+}
+
+fn webhook_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({"error": "Webhook not found"}))
+}
+
+fn validate_payload(payload: &WebhookPayload, severities: &SeverityProfile) -> Result<(), String> {
+    if payload.name.trim().is_empty() {
+        return Err("Webhook name is required".to_string());
+    }
+    let url = reqwest::Url::parse(&payload.url).map_err(|e| format!("Invalid URL: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("Webhook URL must use http or https".to_string());
+    }
+    if payload.events.is_empty() {
+        return Err("Webhook must subscribe to at least one event".to_string());
+    }
+    if let Some(unknown) = payload
+        .events
+        .iter()
+        .find(|e| !SUPPORTED_EVENTS.contains(&e.as_str()))
+    {
+        return Err(format!(
+            "Unknown event '{}'; expected one of {}",
+            unknown,
+            SUPPORTED_EVENTS.join(", ")
+        ));
+    }
+    validate_severity(payload.min_alarm_severity.as_deref(), severities)?;
+    if let Some(quiet_hours) = &payload.quiet_hours {
+        quiet_hours.validate()?;
+    }
+    Ok(())
+}
+
+fn validate_severity(severity: Option<&str>, severities: &SeverityProfile) -> Result<(), String> {
+    if let Some(severity) = severity {
+        if severities.level(severity).is_none() {
+            return Err(format!("Unknown alarm severity '{}'", severity));
+        }
+    }
+    Ok(())
+}
+
+pub async fn list_notification_preferences(state: web::Data<AppState>) -> impl Responder {
+    let preferences = state.webhooks.preferences.read().await;
+    let mut list: Vec<NotificationPreferences> = preferences.values().cloned().collect();
+    list.sort_by(|a, b| a.user_id.cmp(&b.user_id));
+    HttpResponse::Ok().json(list)
+}
+
+pub async fn get_notification_preferences(
+    state: web::Data<AppState>,
+    user_id: web::Path<String>,
+) -> impl Responder {
+    let preferences = state.webhooks.preferences.read().await;
+    match preferences.get(user_id.as_str()) {
+        Some(preferences) => HttpResponse::Ok().json(preferences),
+        None => HttpResponse::NotFound()
+            .json(serde_json::json!({"error": "No notification preferences for user"})),
+    }
+}
+
+/// PUT /notification-preferences/{user_id} — users edit their own; elevated callers edit anyone's.
+pub async fn put_notification_preferences(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    user_id: web::Path<String>,
+    body: web::Json<NotificationPreferences>,
+) -> impl Responder {
+    let user_id = user_id.into_inner();
+    if let Some(forbidden) = forbid_other_user(&state, &req, &user_id) {
+        return forbidden;
+    }
+    let mut preferences = body.into_inner();
+    if let Err(err) = validate_severity(
+        preferences.min_alarm_severity.as_deref(),
+        &*state.severity_profile.read().await,
+    ) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": err}));
+    }
+    if let Some(unknown) = preferences
+        .muted_events
+        .iter()
+        .find(|e| !SUPPORTED_EVENTS.contains(&e.as_str()))
+    {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": format!("Unknown event '{}'", unknown)}));
+    }
+    if let Some(Err(err)) = preferences.quiet_hours.as_ref().map(QuietHours::validate) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"error": err}));
+    }
+    preferences.user_id = user_id.clone();
+    preferences.updated_at = Utc::now().to_rfc3339();
+
+    runtime_store::persist_json(&state.notification_preference_dir, &user_id, &preferences);
+    state
+        .webhooks
+        .preferences
+        .write()
+        .await
+        .insert(user_id, preferences.clone());
+    HttpResponse::Ok().json(preferences)
+}
+
+pub async fn delete_notification_preferences(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    user_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(forbidden) = forbid_other_user(&state, &req, &user_id) {
+        return forbidden;
+    }
+    state
+        .webhooks
+        .preferences
+        .write()
+        .await
+        .remove(user_id.as_str());
+    runtime_store::delete_json(&state.notification_preference_dir, user_id.as_str());
+    HttpResponse::NoContent().finish()
+}
+
+fn forbid_other_user(state: &AppState, req: &HttpRequest, user_id: &str) -> Option<HttpResponse> {
+    let caller = CallerContext::from_request(req);
+    if tenancy::is_deployment_admin(state, req) || caller.actor_id.as_deref() == Some(user_id) {
+        return None;
+    }
+    Some(HttpResponse::Forbidden().json(serde_json::json!({
+        "error": "Only the user or an elevated caller may change these preferences"
+    })))
+}
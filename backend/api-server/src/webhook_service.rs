@@ -0,0 +1,807 @@
+use chrono::{DateTime, NaiveTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::severity_profile::SeverityProfile;
+use crate::tenancy::EventOwners;
+
+pub const EVENT_PEA_DEPLOYED: &str = "pea.deployed";
+pub const EVENT_RECIPE_COMPLETED: &str = "recipe.completed";
+pub const EVENT_RECIPE_FAILED: &str = "recipe.failed";
+pub const EVENT_ALARM_RAISED: &str = "alarm.raised";
+pub const EVENT_SCENARIO_FINISHED: &str = "scenario.finished";
+/// An on-call user paged for an unacknowledged alarm.
+pub const EVENT_ALARM_ESCALATED: &str = "alarm.escalated";
+pub const EVENT_WEBHOOK_TEST: &str = "webhook.test";
+/// Batch of held events, sent to a subscription instead of one request per event.
+pub const EVENT_NOTIFICATION_DIGEST: &str = "notification.digest";
+
+pub const SUPPORTED_EVENTS: [&str; 6] = [
+    EVENT_PEA_DEPLOYED,
+    EVENT_RECIPE_COMPLETED,
+    EVENT_RECIPE_FAILED,
+    EVENT_ALARM_RAISED,
+    EVENT_ALARM_ESCALATED,
+    EVENT_SCENARIO_FINISHED,
+];
+
+pub const SIGNATURE_HEADER: &str = "X-Fendtastic-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Fendtastic-Timestamp";
+pub const EVENT_HEADER: &str = "X-Fendtastic-Event";
+
+/// Delivery attempts per event before a delivery is marked failed.
+pub const MAX_ATTEMPTS: u32 = 5;
+/// Upper bound on delivery records kept in memory.
+pub const MAX_DELIVERIES: usize = 1000;
+/// Upper bound on events held for one subscription's next digest; the oldest are dropped.
+pub const MAX_DIGEST_EVENTS: usize = 500;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookSubscription {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+    /// Only alarms at or above this severity fire `alarm.raised`.
+    pub min_alarm_severity: Option<String>,
+    pub enabled: bool,
+    /// Batch non-critical events into one digest every this many minutes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest_minutes: Option<u32>,
+    /// Hold non-critical events during these hours and send them as a digest afterwards.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<QuietHours>,
+    /// User whose notification preferences apply to this subscription.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Owning tenant; only that tenant's events are sent. `None` receives every event.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Daily window, e.g. 22:00 to 06:00, in which only Critical alarms go out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuietHours {
+    /// Start as `HH:MM`.
+    pub start: String,
+    /// End as `HH:MM`; before `start` when the window spans midnight.
+    pub end: String,
+    /// Offset of the local clock the times are given in (default: UTC).
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+}
+
+impl QuietHours {
+    pub fn validate(&self) -> Result<(), String> {
+        for time in [&self.start, &self.end] {
+            NaiveTime::parse_from_str(time, "%H:%M")
+                .map_err(|_| format!("Quiet hours time '{}' must be HH:MM", time))?;
+        }
+        if self.utc_offset_minutes.abs() > 14 * 60 {
+            return Err("utc_offset_minutes must be within ±14 hours".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        let (Ok(start), Ok(end)) = (
+            NaiveTime::parse_from_str(&self.start, "%H:%M"),
+            NaiveTime::parse_from_str(&self.end, "%H:%M"),
+        ) else {
+            return false;
+        };
+        let local = (now + chrono::Duration::minutes(i64::from(self.utc_offset_minutes))).time();
+        if start <= end {
+            local >= start && local < end
+        } else {
+            local >= start || local < end
+        }
+    }
+}
+
+/// A user's defaults for the subscriptions they own; settings on a
+/// subscription take precedence.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationPreferences {
+    #[serde(default)]
+    pub user_id: String,
+    /// Alarms below this severity are not sent to the user's subscriptions.
+    #[serde(default)]
+    pub min_alarm_severity: Option<String>,
+    /// Events the user does not want; Critical alarms are sent regardless.
+    #[serde(default)]
+    pub muted_events: Vec<String>,
+    #[serde(default)]
+    pub digest_minutes: Option<u32>,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+/// What happens to an event for one subscription.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Routing {
+    Skip,
+    Immediate,
+    /// Held for the subscription's next digest.
+    Digest,
+}
+
+/// Alarms that bypass digests, quiet hours and mutes.
+pub fn is_critical(event: &WebhookEvent, severities: &SeverityProfile) -> bool {
+    event.event == EVENT_ALARM_RAISED
+        && event
+            .data
+            .get("severity")
+            .and_then(|s| s.as_str())
+            .is_some_and(|severity| severities.is_critical(severity))
+}
+
+/// Decides how `event` reaches `subscription`, given its owner's preferences.
+/// Alarm severities are ranked by the deployment's severity profile.
+pub fn route(
+    subscription: &WebhookSubscription,
+    preferences: Option<&NotificationPreferences>,
+    event: &WebhookEvent,
+    now: DateTime<Utc>,
+    severities: &SeverityProfile,
+) -> Routing {
+    if !subscription.wants(event, severities) {
+        return Routing::Skip;
+    }
+    // Pages are never held, and reach only the paged user's subscriptions or unowned ones.
+    if event.event == EVENT_ALARM_ESCALATED {
+        let paged = event.data.get("user").and_then(|u| u.as_str());
+        return match subscription.owner.as_deref() {
+            Some(owner) if Some(owner) != paged => Routing::Skip,
+            _ => Routing::Immediate,
+        };
+    }
+    if is_critical(event, severities) || event.event == EVENT_WEBHOOK_TEST {
+        return Routing::Immediate;
+    }
+    if let Some(preferences) = preferences {
+        if preferences.muted_events.contains(&event.event) {
+            return Routing::Skip;
+        }
+        if event.event == EVENT_ALARM_RAISED {
+            let severity = event
+                .data
+                .get("severity")
+                .and_then(|s| s.as_str())
+                .unwrap_or_default();
+            if preferences
+                .min_alarm_severity
+                .as_deref()
+                .is_some_and(|min| !severities.at_least(severity, min))
+            {
+                return Routing::Skip;
+            }
+        }
+    }
+    let quiet = subscription
+        .quiet_hours
+        .as_ref()
+        .or(preferences.and_then(|p| p.quiet_hours.as_ref()));
+    if quiet.is_some_and(|quiet| quiet.contains(now)) {
+        return Routing::Digest;
+    }
+    match digest_minutes(subscription, preferences) {
+        Some(_) => Routing::Digest,
+        None => Routing::Immediate,
+    }
+}
+
+fn digest_minutes(
+    subscription: &WebhookSubscription,
+    preferences: Option<&NotificationPreferences>,
+) -> Option<u32> {
+    subscription
+        .digest_minutes
+        .or(preferences.and_then(|p| p.digest_minutes))
+        .filter(|minutes| *minutes > 0)
+}
+
+impl WebhookSubscription {
+    pub fn wants(&self, event: &WebhookEvent, severities: &SeverityProfile) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if event.event == EVENT_WEBHOOK_TEST {
+            return true;
+        }
+        if !self.events.iter().any(|e| e == &event.event) {
+            return false;
+        }
+        if event.event != EVENT_ALARM_RAISED {
+            return true;
+        }
+        let Some(min) = self.min_alarm_severity.as_deref() else {
+            return true;
+        };
+        let severity = event
+            .data
+            .get("severity")
+            .and_then(|s| s.as_str())
+            .unwrap_or_default();
+        severities.at_least(severity, min)
+    }
+
+    /// Whether an event owned by `owner` may reach this subscription.
+    pub fn sees(&self, owner: Option<&str>) -> bool {
+        self.tenant_id.is_none() || self.tenant_id.as_deref() == owner
+    }
+
+    /// The subscription as returned by the API, without the signing secret.
+    pub fn public_view(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(object) = value.as_object_mut() {
+            object.remove("secret");
+        }
+        value
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct WebhookEvent {
+    pub id: String,
+    pub event: String,
+    pub occurred_at: String,
+    pub data: serde_json::Value,
+}
+
+impl WebhookEvent {
+    pub fn new(event: &str, data: serde_json::Value) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            event: event.to_string(),
+            occurred_at: Utc::now().to_rfc3339(),
+            data,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub webhook_id: String,
+    pub event_id: String,
+    pub event: String,
+    /// `pending`, `delivered` or `failed`.
+    pub status: String,
+    pub attempts: u32,
+    pub response_status: Option<u16>,
+    pub last_error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Webhook subscriptions plus the delivery log, shared by handlers and the
+/// background tasks that raise platform events.
+#[derive(Clone)]
+pub struct Webhooks {
+    pub subscriptions: Arc<RwLock<HashMap<String, WebhookSubscription>>>,
+    pub deliveries: Arc<RwLock<VecDeque<WebhookDelivery>>>,
+    pub preferences: Arc<RwLock<HashMap<String, NotificationPreferences>>>,
+    /// Events held per subscription until its next digest.
+    digests: Arc<RwLock<HashMap<String, PendingDigest>>>,
+    /// The deployment's severity profile, shared with `AppState`.
+    severities: Arc<RwLock<SeverityProfile>>,
+    owners: EventOwners,
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Default)]
+struct PendingDigest {
+    events: VecDeque<WebhookEvent>,
+    last_sent: Option<DateTime<Utc>>,
+}
+
+impl Webhooks {
+    pub fn new(
+        subscriptions: HashMap<String, WebhookSubscription>,
+        preferences: HashMap<String, NotificationPreferences>,
+        severities: Arc<RwLock<SeverityProfile>>,
+        owners: EventOwners,
+    ) -> Self {
+        Self {
+            subscriptions: Arc::new(RwLock::new(subscriptions)),
+            deliveries: Arc::new(RwLock::new(VecDeque::new())),
+            preferences: Arc::new(RwLock::new(preferences)),
+            digests: Arc::new(RwLock::new(HashMap::new())),
+            severities,
+            owners,
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Queues `event` for every subscription that wants it, or holds it for the
+    /// subscription's next digest. Deliveries run in the background so callers
+    /// never wait on remote endpoints.
+    pub async fn emit(&self, event: &str, data: serde_json::Value) {
+        let event = WebhookEvent::new(event, data);
+        let now = Utc::now();
+        let owner = self.owners.owner_of(&event.data).await;
+        let targets: Vec<(WebhookSubscription, Routing)> = {
+            let subscriptions = self.subscriptions.read().await;
+            let preferences = self.preferences.read().await;
+            let severities = self.severities.read().await;
+            subscriptions
+                .values()
+                .filter(|sub| sub.sees(owner.as_deref()))
+                .map(|sub| {
+                    let owner = sub.owner.as_ref().and_then(|owner| preferences.get(owner));
+                    (sub.clone(), route(sub, owner, &event, now, &severities))
+                })
+                .collect()
+        };
+        for (subscription, routing) in targets {
+            match routing {
+                Routing::Skip => {}
+                Routing::Immediate => {
+                    self.deliver(subscription, event.clone()).await;
+                }
+                Routing::Digest => self.hold(&subscription.id, event.clone()).await,
+            }
+        }
+    }
+
+    async fn hold(&self, subscription_id: &str, event: WebhookEvent) {
+        let mut digests = self.digests.write().await;
+        let pending = digests.entry(subscription_id.to_string()).or_default();
+        pending.events.push_back(event);
+        while pending.events.len() > MAX_DIGEST_EVENTS {
+            pending.events.pop_front();
+        }
+    }
+
+    /// Number of events held for a subscription's next digest.
+    pub async fn pending_digest(&self, subscription_id: &str) -> usize {
+        self.digests
+            .read()
+            .await
+            .get(subscription_id)
+            .map_or(0, |pending| pending.events.len())
+    }
+
+    /// Sends a digest to every subscription whose interval has passed and that
+    /// is outside its quiet hours. Events held only for quiet hours go out as
+    /// soon as the quiet hours end.
+    pub async fn flush_digests(&self, now: DateTime<Utc>) {
+        let subscriptions = self.subscriptions.read().await.clone();
+        let preferences = self.preferences.read().await.clone();
+        let mut due = Vec::new();
+        {
+            let mut digests = self.digests.write().await;
+            digests.retain(|id, _| subscriptions.contains_key(id));
+            for (id, pending) in digests.iter_mut() {
+                if pending.events.is_empty() {
+                    continue;
+                }
+                let subscription = &subscriptions[id];
+                let owner = subscription
+                    .owner
+                    .as_ref()
+                    .and_then(|owner| preferences.get(owner));
+                let quiet = subscription
+                    .quiet_hours
+                    .as_ref()
+                    .or(owner.and_then(|p| p.quiet_hours.as_ref()));
+                if quiet.is_some_and(|quiet| quiet.contains(now)) {
+                    continue;
+                }
+                let interval_passed = match (digest_minutes(subscription, owner), pending.last_sent)
+                {
+                    (Some(minutes), Some(last)) => {
+                        now - last >= chrono::Duration::minutes(i64::from(minutes))
+                    }
+                    _ => true,
+                };
+                if !interval_passed {
+                    continue;
+                }
+                let events: Vec<WebhookEvent> = pending.events.drain(..).collect();
+                pending.last_sent = Some(now);
+                due.push((subscription.clone(), events));
+            }
+        }
+        for (subscription, events) in due {
+            let digest = WebhookEvent::new(
+                EVENT_NOTIFICATION_DIGEST,
+                serde_json::json!({ "count": events.len(), "events": events }),
+            );
+            self.deliver(subscription, digest).await;
+        }
+    }
+
+    /// Records a pending delivery and spawns the retrying sender for it.
+    pub async fn deliver(
+        &self,
+        subscription: WebhookSubscription,
+        event: WebhookEvent,
+    ) -> WebhookDelivery {
+        let now = Utc::now().to_rfc3339();
+        let delivery = WebhookDelivery {
+            id: uuid::Uuid::new_v4().to_string(),
+            webhook_id: subscription.id.clone(),
+            event_id: event.id.clone(),
+            event: event.event.clone(),
+            status: "pending".to_string(),
+            attempts: 0,
+            response_status: None,
+            last_error: None,
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        {
+            let mut deliveries = self.deliveries.write().await;
+            deliveries.push_back(delivery.clone());
+            while deliveries.len() > MAX_DELIVERIES {
+                deliveries.pop_front();
+            }
+        }
+
+        let webhooks = self.clone();
+        let delivery_id = delivery.id.clone();
+        tokio::spawn(async move {
+            webhooks
+                .send_with_retries(&subscription, &event, &delivery_id)
+                .await;
+        });
+        delivery
+    }
+
+    pub async fn deliveries_for(&self, webhook_id: &str, limit: usize) -> Vec<WebhookDelivery> {
+        self.deliveries
+            .read()
+            .await
+            .iter()
+            .rev()
+            .filter(|d| d.webhook_id == webhook_id)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    async fn send_with_retries(
+        &self,
+        subscription: &WebhookSubscription,
+        event: &WebhookEvent,
+        delivery_id: &str,
+    ) {
+        let body = serde_json::to_string(event).unwrap_or_default();
+        for attempt in 1..=MAX_ATTEMPTS {
+            let timestamp = Utc::now().timestamp().to_string();
+            let signature = sign(&subscription.secret, &timestamp, &body);
+            let result = self
+                .client
+                .post(&subscription.url)
+                .header("Content-Type", "application/json")
+                .header(EVENT_HEADER, &event.event)
+                .header(TIMESTAMP_HEADER, &timestamp)
+                .header(SIGNATURE_HEADER, format!("sha256={}", signature))
+                .body(body.clone())
+                .send()
+                .await;
+
+            let (response_status, error) = match result {
+                Ok(response) if response.status().is_success() => {
+                    (Some(response.status().as_u16()), None)
+                }
+                Ok(response) => (
+                    Some(response.status().as_u16()),
+                    Some(format!("HTTP {}", response.status())),
+                ),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            let delivered = error.is_none();
+            let status = if delivered {
+                "delivered"
+            } else if attempt == MAX_ATTEMPTS {
+                "failed"
+            } else {
+                "pending"
+            };
+            self.update_delivery(delivery_id, |d| {
+                d.attempts = attempt;
+                d.status = status.to_string();
+                d.response_status = response_status;
+                d.last_error = error.clone();
+            })
+            .await;
+
+            if delivered {
+                info!(
+                    "Webhook {} delivered {} ({})",
+                    subscription.id, event.event, event.id
+                );
+                return;
+            }
+            warn!(
+                "Webhook {} attempt {}/{} for {} failed: {}",
+                subscription.id,
+                attempt,
+                MAX_ATTEMPTS,
+                event.event,
+                error.unwrap_or_default()
+            );
+            if attempt < MAX_ATTEMPTS {
+                tokio::time::sleep(retry_delay(attempt)).await;
+            }
+        }
+    }
+
+    async fn update_delivery(&self, delivery_id: &str, apply: impl FnOnce(&mut WebhookDelivery)) {
+        let mut deliveries = self.deliveries.write().await;
+        if let Some(delivery) = deliveries.iter_mut().find(|d| d.id == delivery_id) {
+            apply(delivery);
+            delivery.updated_at = Utc::now().to_rfc3339();
+        }
+    }
+}
+
+/// Hex HMAC-SHA256 over `"{timestamp}.{body}"`, so receivers can reject replays.
+pub fn sign(secret: &str, timestamp: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Exponential backoff: 1s, 2s, 4s, ...
+pub fn retry_delay(attempt: u32) -> Duration {
+    Duration::from_secs(1u64 << attempt.saturating_sub(1).min(6))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(events: &[&str], min_alarm_severity: Option<&str>) -> WebhookSubscription {
+        WebhookSubscription {
+            id: "wh-1".to_string(),
+            name: "ops".to_string(),
+            url: "http://localhost:9000/hook".to_string(),
+            secret: "s3cret".to_string(),
+            events: events.iter().map(|e| e.to_string()).collect(),
+            min_alarm_severity: min_alarm_severity.map(str::to_string),
+            enabled: true,
+            digest_minutes: None,
+            quiet_hours: None,
+            owner: None,
+            tenant_id: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    fn alarm(severity: &str) -> WebhookEvent {
+        WebhookEvent::new(
+            EVENT_ALARM_RAISED,
+            serde_json::json!({"severity": severity}),
+        )
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        format!("2026-05-01T{}:00Z", time).parse().unwrap()
+    }
+
+    #[test]
+    fn signature_matches_reference_hmac() {
+        assert_eq!(
+            sign("key", "1700000000", "{}"),
+            "9d713ed406bb7076d4123f0dc2c39d2df5c654ed4b0cd56b52c8b4c940bd63ae"
+        );
+        assert_ne!(sign("key", "1", "{}"), sign("key", "2", "{}"));
+    }
+
+    #[test]
+    fn alarm_events_respect_minimum_severity() {
+        let severities = SeverityProfile::default();
+        let sub = subscription(&[EVENT_ALARM_RAISED], Some("high"));
+        let warning = WebhookEvent::new(
+            EVENT_ALARM_RAISED,
+            serde_json::json!({"severity": "warning"}),
+        );
+        let critical = WebhookEvent::new(
+            EVENT_ALARM_RAISED,
+            serde_json::json!({"severity": "critical"}),
+        );
+        assert!(!sub.wants(&warning, &severities));
+        assert!(sub.wants(&critical, &severities));
+    }
+
+    #[test]
+    fn unsubscribed_or_disabled_hooks_are_skipped() {
+        let severities = SeverityProfile::default();
+        let deployed = WebhookEvent::new(EVENT_PEA_DEPLOYED, serde_json::json!({}));
+        assert!(!subscription(&[EVENT_RECIPE_FAILED], None).wants(&deployed, &severities));
+
+        let mut disabled = subscription(&[EVENT_PEA_DEPLOYED], None);
+        disabled.enabled = false;
+        assert!(!disabled.wants(&deployed, &severities));
+    }
+
+    #[test]
+    fn quiet_hours_span_midnight_in_local_time() {
+        let quiet = QuietHours {
+            start: "22:00".to_string(),
+            end: "06:00".to_string(),
+            utc_offset_minutes: 120,
+        };
+        assert!(quiet.validate().is_ok());
+        assert!(quiet.contains(at("21:30")));
+        assert!(quiet.contains(at("03:59")));
+        assert!(!quiet.contains(at("04:00")));
+        assert!(!quiet.contains(at("19:59")));
+    }
+
+    #[test]
+    fn critical_alarms_bypass_digests_quiet_hours_and_mutes() {
+        let severities = SeverityProfile::default();
+        let mut sub = subscription(&[EVENT_ALARM_RAISED, EVENT_PEA_DEPLOYED], None);
+        sub.digest_minutes = Some(15);
+        sub.quiet_hours = Some(QuietHours {
+            start: "00:00".to_string(),
+            end: "23:59".to_string(),
+            utc_offset_minutes: 0,
+        });
+        let preferences = NotificationPreferences {
+            user_id: "op-1".to_string(),
+            min_alarm_severity: Some("high".to_string()),
+            muted_events: vec![EVENT_ALARM_RAISED.to_string()],
+            digest_minutes: None,
+            quiet_hours: None,
+            updated_at: String::new(),
+        };
+        let now = at("12:00");
+        assert_eq!(
+            route(
+                &sub,
+                Some(&preferences),
+                &alarm("critical"),
+                now,
+                &severities
+            ),
+            Routing::Immediate
+        );
+        assert_eq!(
+            route(&sub, Some(&preferences), &alarm("high"), now, &severities),
+            Routing::Skip
+        );
+        let deployed = WebhookEvent::new(EVENT_PEA_DEPLOYED, serde_json::json!({}));
+        assert_eq!(
+            route(&sub, Some(&preferences), &deployed, now, &severities),
+            Routing::Digest
+        );
+    }
+
+    #[test]
+    fn owner_preferences_fill_in_unset_subscription_settings() {
+        let severities = SeverityProfile::default();
+        let sub = subscription(&[EVENT_ALARM_RAISED], None);
+        let mut preferences = NotificationPreferences {
+            user_id: "op-1".to_string(),
+            min_alarm_severity: Some("medium".to_string()),
+            muted_events: Vec::new(),
+            digest_minutes: None,
+            quiet_hours: None,
+            updated_at: String::new(),
+        };
+        let now = at("12:00");
+        assert_eq!(
+            route(&sub, None, &alarm("low"), now, &severities),
+            Routing::Immediate
+        );
+        assert_eq!(
+            route(&sub, Some(&preferences), &alarm("low"), now, &severities),
+            Routing::Skip
+        );
+        assert_eq!(
+            route(
+                &sub,
+                Some(&preferences),
+                &alarm("warning"),
+                now,
+                &severities
+            ),
+            Routing::Immediate
+        );
+        preferences.digest_minutes = Some(30);
+        assert_eq!(
+            route(
+                &sub,
+                Some(&preferences),
+                &alarm("warning"),
+                now,
+                &severities
+            ),
+            Routing::Digest
+        );
+    }
+
+    #[test]
+    fn pages_reach_only_the_paged_users_subscriptions() {
+        let severities = SeverityProfile::default();
+        let mut sub = subscription(&[EVENT_ALARM_ESCALATED], None);
+        sub.digest_minutes = Some(15);
+        let page = |user: &str| {
+            WebhookEvent::new(EVENT_ALARM_ESCALATED, serde_json::json!({"user": user}))
+        };
+        let now = at("12:00");
+        assert_eq!(
+            route(&sub, None, &page("ana"), now, &severities),
+            Routing::Immediate
+        );
+        sub.owner = Some("ana".to_string());
+        assert_eq!(
+            route(&sub, None, &page("ana"), now, &severities),
+            Routing::Immediate
+        );
+        assert_eq!(
+            route(&sub, None, &page("ben"), now, &severities),
+            Routing::Skip
+        );
+    }
+
+    #[tokio::test]
+    async fn digests_wait_for_their_interval() {
+        let mut sub = subscription(&[EVENT_PEA_DEPLOYED], None);
+        sub.digest_minutes = Some(10);
+        let webhooks = Webhooks::new(
+            HashMap::from([(sub.id.clone(), sub.clone())]),
+            HashMap::new(),
+            Arc::new(RwLock::new(SeverityProfile::default())),
+            EventOwners::default(),
+        );
+        let event = || WebhookEvent::new(EVENT_PEA_DEPLOYED, serde_json::json!({}));
+
+        webhooks.hold(&sub.id, event()).await;
+        webhooks.flush_digests(at("12:00")).await;
+        assert_eq!(webhooks.pending_digest(&sub.id).await, 0);
+        assert_eq!(
+            webhooks.deliveries_for(&sub.id, 10).await[0].event,
+            EVENT_NOTIFICATION_DIGEST
+        );
+
+        webhooks.hold(&sub.id, event()).await;
+        webhooks.hold(&sub.id, event()).await;
+        webhooks.flush_digests(at("12:05")).await;
+        assert_eq!(webhooks.pending_digest(&sub.id).await, 2);
+        webhooks.flush_digests(at("12:10")).await;
+        assert_eq!(webhooks.pending_digest(&sub.id).await, 0);
+        assert_eq!(webhooks.deliveries_for(&sub.id, 10).await.len(), 2);
+    }
+
+    #[test]
+    fn tenant_subscriptions_only_see_their_tenants_events() {
+        let mut sub = subscription(&[EVENT_ALARM_RAISED], None);
+        assert!(sub.sees(None) && sub.sees(Some("acme")));
+        sub.tenant_id = Some("acme".to_string());
+        assert!(sub.sees(Some("acme")));
+        assert!(!sub.sees(Some("globex")));
+        assert!(!sub.sees(None));
+    }
+
+    #[test]
+    fn retry_delay_backs_off_exponentially() {
+        assert_eq!(retry_delay(1), Duration::from_secs(1));
+        assert_eq!(retry_delay(3), Duration::from_secs(4));
+    }
+}
@@ -1,5 +1,5 @@
 use actix::prelude::*;
-use actix_web::{web, Error, HttpRequest, HttpResponse};
+use actix_web::{web, Error, HttpRequest, HttpResponse, Responder};
 use actix_web_actors::ws;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -7,7 +7,13 @@ use tracing::{error, info};
 use uuid::Uuid;
 use zenoh::Session;
 
+use crate::pea_handlers;
+use crate::playback_handlers::{PlaybackFrame, PlaybackSessions};
+use crate::pol_handlers;
+use crate::redaction::{RedactionPolicy, RedactionRule};
 use crate::state::AppState;
+use crate::tenancy::{self, TenantScope};
+use crate::ts_provenance::Producer;
 
 // ─── Actor Messages ──────────────────────────────────────────────────────────
 
@@ -19,13 +25,108 @@ struct ZenohUpdate {
     payload: String,
 }
 
+/// A historical frame from an attached playback session
+#[derive(Message)]
+#[rtype(result = "()")]
+struct PlaybackUpdate(PlaybackFrame);
+
+// ─── Subscription Limits ─────────────────────────────────────────────────────
+
+/// Guards against a single connection amplifying the whole bus to itself.
+#[derive(Clone, Copy, Debug)]
+pub struct WsLimits {
+    /// Subscriptions and playback attachments a connection may hold at once
+    pub max_subscriptions: usize,
+    /// Literal chunks a key expression must start with before any wildcard,
+    /// e.g. 1 rejects `**` and `*/pea/**` but allows `entmoot/**`
+    pub min_literal_prefix: usize,
+}
+
+impl Default for WsLimits {
+    fn default() -> Self {
+        Self {
+            max_subscriptions: 64,
+            min_literal_prefix: 1,
+        }
+    }
+}
+
+impl WsLimits {
+    /// Why another subscription may not be added to `active` existing ones.
+    fn check_capacity(&self, active: usize) -> Result<(), (&'static str, String)> {
+        if active >= self.max_subscriptions {
+            return Err((
+                "subscription_limit",
+                format!(
+                    "Connection already holds {} subscriptions (limit {})",
+                    active, self.max_subscriptions
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Why a subscription to `key` may not be added to `active` existing ones.
+    fn check(&self, key: &str, active: usize) -> Result<(), (&'static str, String)> {
+        self.check_capacity(active)?;
+        let literal_prefix = key
+            .split('/')
+            .take_while(|chunk| !chunk.contains('*') && !chunk.contains('$'))
+            .count();
+        let has_wildcard = literal_prefix < key.split('/').count();
+        if has_wildcard && literal_prefix < self.min_literal_prefix {
+            return Err((
+                "wildcard_too_broad",
+                format!(
+                    "Key expressions must start with at least {} literal chunk(s) before a wildcard, e.g. entmoot/**",
+                    self.min_literal_prefix
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
 // ─── WebSocket Connection Actor ──────────────────────────────────────────────
 
+/// Encoding of frames sent to the client, chosen per connection with the
+/// `format` field of a `subscribe` (or `set_format`) message.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum FrameFormat {
+    /// Text frames carrying JSON
+    #[default]
+    Json,
+    /// Binary frames carrying MessagePack
+    MessagePack,
+}
+
+impl FrameFormat {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(Self::Json),
+            "msgpack" | "messagepack" => Some(Self::MessagePack),
+            _ => None,
+        }
+    }
+}
+
 pub struct WsConnection {
     id: Uuid,
     zenoh_session: Arc<Session>,
     /// Active Zenoh subscriber tasks keyed by subscription key expression
     subscription_tasks: HashMap<String, tokio::task::JoinHandle<()>>,
+    playback_sessions: PlaybackSessions,
+    redaction_rules: RedactionRules,
+    /// Elevated connections bypass redaction rules
+    elevated: bool,
+    /// Tenant scope resolved at the upgrade; keys outside it are never forwarded
+    scope: TenantScope,
+    format: FrameFormat,
+    limits: WsLimits,
+    /// Upgrade request, replayed to REST handlers so commands run with the
+    /// connection's actor and tenant context
+    upgrade_request: HttpRequest,
+    state: web::Data<AppState>,
 }
 
 impl Actor for WsConnection {
@@ -58,7 +159,23 @@ impl Handler<ZenohUpdate> for WsConnection {
             "key": msg.key,
             "payload": payload_value
         });
-        ctx.text(envelope.to_string());
+        self.send(&envelope, ctx);
+    }
+}
+
+// Handle PlaybackUpdate → send JSON shaped like a live update, tagged with the playback id
+impl Handler<PlaybackUpdate> for WsConnection {
+    type Result = ();
+
+    fn handle(&mut self, msg: PlaybackUpdate, ctx: &mut Self::Context) {
+        let frame = msg.0;
+        let envelope = serde_json::json!({
+            "key": frame.key,
+            "payload": frame.payload,
+            "playback_id": frame.playback_id,
+            "timestamp_ms": frame.timestamp_ms,
+        });
+        self.send(&envelope, ctx);
     }
 }
 
@@ -68,9 +185,14 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsConnection {
         match msg {
             Ok(ws::Message::Ping(data)) => ctx.pong(&data),
             Ok(ws::Message::Pong(_)) => {}
-            Ok(ws::Message::Text(text)) => {
-                self.handle_client_message(&text, ctx);
-            }
+            Ok(ws::Message::Text(text)) => match serde_json::from_str(&text) {
+                Ok(msg) => self.handle_client_message(msg, ctx),
+                Err(e) => error!("WS {}: invalid JSON: {}", self.id, e),
+            },
+            Ok(ws::Message::Binary(bytes)) => match rmp_serde::from_slice(&bytes) {
+                Ok(msg) => self.handle_client_message(msg, ctx),
+                Err(e) => error!("WS {}: invalid MessagePack: {}", self.id, e),
+            },
             Ok(ws::Message::Close(reason)) => {
                 ctx.close(reason);
                 ctx.stop();
@@ -81,17 +203,66 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsConnection {
 }
 
 impl WsConnection {
-    fn handle_client_message(&mut self, text: &str, ctx: &mut ws::WebsocketContext<Self>) {
-        let msg: serde_json::Value = match serde_json::from_str(text) {
-            Ok(v) => v,
-            Err(e) => {
-                error!("WS {}: invalid JSON: {}", self.id, e);
-                return;
-            }
+    /// Sends a frame in the connection's negotiated format.
+    fn send(&self, envelope: &serde_json::Value, ctx: &mut ws::WebsocketContext<Self>) {
+        match self.format {
+            FrameFormat::Json => ctx.text(envelope.to_string()),
+            FrameFormat::MessagePack => match rmp_serde::to_vec_named(envelope) {
+                Ok(bytes) => ctx.binary(bytes),
+                Err(e) => error!("WS {}: MessagePack encoding failed: {}", self.id, e),
+            },
+        }
+    }
+
+    /// Applies a `format` field; unknown formats are reported to the client.
+    fn negotiate_format(&mut self, msg: &serde_json::Value, ctx: &mut ws::WebsocketContext<Self>) {
+        let Some(name) = msg["format"].as_str() else {
+            return;
         };
+        match FrameFormat::parse(name) {
+            Some(format) => {
+                if format != self.format {
+                    info!("WS {}: switching frames to {:?}", self.id, format);
+                }
+                self.format = format;
+            }
+            None => self.send_error(
+                "unsupported_format",
+                format!("Unsupported format '{}'; use json or msgpack", name),
+                None,
+                ctx,
+            ),
+        }
+    }
 
+    /// Sends an error frame, naming the key it concerns where there is one.
+    fn send_error(
+        &self,
+        code: &str,
+        error: String,
+        key: Option<&str>,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        self.send(
+            &serde_json::json!({
+                "type": "error",
+                "code": code,
+                "error": error,
+                "key": key,
+            }),
+            ctx,
+        );
+    }
+
+    fn handle_client_message(
+        &mut self,
+        msg: serde_json::Value,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
         match msg["type"].as_str().unwrap_or("") {
+            "set_format" => self.negotiate_format(&msg, ctx),
             "subscribe" => {
+                self.negotiate_format(&msg, ctx);
                 if let Some(key) = msg["key"].as_str() {
                     self.start_zenoh_subscription(key.to_string(), ctx);
                 }
@@ -103,24 +274,99 @@ impl WsConnection {
             }
             "publish" => {
                 if let (Some(key), Some(payload)) = (msg["key"].as_str(), msg.get("payload")) {
+                    if !self.scope.allows_key(key) {
+                        self.send_error(
+                            "forbidden_key",
+                            format!("'{}' is outside the tenant's keys", key),
+                            Some(key),
+                            ctx,
+                        );
+                        return;
+                    }
                     self.publish_to_zenoh(key.to_string(), payload.clone());
                 }
             }
+            "playback_attach" => {
+                if let Some(playback_id) = msg["playback_id"].as_str() {
+                    self.attach_playback(playback_id.to_string(), ctx);
+                }
+            }
+            "execute_recipe" | "abort_recipe" | "ack_alarm" => self.run_command(msg, ctx),
+            "playback_detach" => {
+                if let Some(playback_id) = msg["playback_id"].as_str() {
+                    self.stop_zenoh_subscription(&playback_task_key(playback_id));
+                }
+            }
             _ => {}
         }
     }
 
+    /// Runs a command through the matching REST handler and answers with a
+    /// `command_result` frame carrying its status and body.
+    fn run_command(&self, msg: serde_json::Value, ctx: &mut ws::WebsocketContext<Self>) {
+        let command = msg["type"].as_str().unwrap_or_default().to_string();
+        let request_id = msg.get("request_id").cloned();
+        let req = self.upgrade_request.clone();
+        let state = self.state.clone();
+        let ws_id = self.id;
+
+        let fut = async move {
+            let response = dispatch_command(&command, &msg, req, state).await;
+            match response {
+                Ok(response) => {
+                    let status = response.status();
+                    let body = actix_web::body::to_bytes(response.into_body())
+                        .await
+                        .ok()
+                        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+                        .unwrap_or(serde_json::Value::Null);
+                    info!("WS {}: {} -> {}", ws_id, command, status);
+                    serde_json::json!({
+                        "type": "command_result",
+                        "command": command,
+                        "request_id": request_id,
+                        "status": status.as_u16(),
+                        "ok": status.is_success(),
+                        "body": body,
+                    })
+                }
+                Err(error) => serde_json::json!({
+                    "type": "error",
+                    "code": "invalid_command",
+                    "command": command,
+                    "request_id": request_id,
+                    "error": error,
+                }),
+            }
+        };
+        ctx.spawn(
+            fut.into_actor(self)
+                .map(|frame, act, ctx| act.send(&frame, ctx)),
+        );
+    }
+
     fn start_zenoh_subscription(&mut self, key: String, ctx: &mut ws::WebsocketContext<Self>) {
         if self.subscription_tasks.contains_key(&key) {
             return;
         }
+        if let Err((code, error)) = self.limits.check(&key, self.subscription_tasks.len()) {
+            info!(
+                "WS {}: rejected subscription to '{}': {}",
+                self.id, key, error
+            );
+            self.send_error(code, error, Some(&key), ctx);
+            return;
+        }
 
         let session = self.zenoh_session.clone();
         let addr = ctx.address();
         let key_expr = key.clone();
         let ws_id = self.id;
+        let redaction_rules = self.redaction_rules();
+        let scope = self.scope.clone();
 
         let handle = tokio::spawn(async move {
+            let policy = redaction_policy(redaction_rules).await;
             let subscriber = match session.declare_subscriber(&key_expr).await {
                 Ok(sub) => sub,
                 Err(e) => {
@@ -134,11 +380,17 @@ impl WsConnection {
                 match subscriber.recv_async().await {
                     Ok(sample) => {
                         let k = sample.key_expr().as_str().to_string();
+                        if !scope.allows_key(&k) {
+                            continue;
+                        }
                         let p = sample
                             .payload()
                             .try_to_string()
                             .unwrap_or_else(|e| e.to_string().into())
                             .to_string();
+                        let Some(p) = redact_payload(&policy, &k, p) else {
+                            continue;
+                        };
                         if addr.try_send(ZenohUpdate { key: k, payload: p }).is_err() {
                             break; // actor gone
                         }
@@ -158,18 +410,150 @@ impl WsConnection {
         }
     }
 
+    fn attach_playback(&mut self, playback_id: String, ctx: &mut ws::WebsocketContext<Self>) {
+        let task_key = playback_task_key(&playback_id);
+        if self.subscription_tasks.contains_key(&task_key) {
+            return;
+        }
+        if let Err((code, error)) = self.limits.check_capacity(self.subscription_tasks.len()) {
+            self.send_error(code, error, Some(&task_key), ctx);
+            return;
+        }
+
+        let sessions = self.playback_sessions.clone();
+        let addr = ctx.address();
+        let ws_id = self.id;
+        let redaction_rules = self.redaction_rules();
+        let scope = self.scope.clone();
+
+        let handle = tokio::spawn(async move {
+            let policy = redaction_policy(redaction_rules).await;
+            let mut receiver = {
+                let guard = sessions.read().await;
+                match guard.get(&playback_id) {
+                    Some(session) => session.sender.subscribe(),
+                    None => {
+                        error!("WS {}: playback '{}' not found", ws_id, playback_id);
+                        return;
+                    }
+                }
+            };
+            info!("WS {}: attached to playback '{}'", ws_id, playback_id);
+
+            loop {
+                match receiver.recv().await {
+                    Ok(mut frame) => {
+                        if !scope.allows_key(&frame.key) {
+                            continue;
+                        }
+                        let Some(payload) = policy.apply(&frame.key, frame.payload) else {
+                            continue;
+                        };
+                        frame.payload = payload;
+                        if addr.try_send(PlaybackUpdate(frame)).is_err() {
+                            break; // actor gone
+                        }
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        error!("WS {}: playback lagged, skipped {} frames", ws_id, skipped);
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        self.subscription_tasks.insert(task_key, handle);
+    }
+
+    /// Rules applied to what this connection receives; `None` when elevated.
+    fn redaction_rules(&self) -> Option<RedactionRules> {
+        (!self.elevated).then(|| self.redaction_rules.clone())
+    }
+
     fn publish_to_zenoh(&self, key: String, payload: serde_json::Value) {
         let session = self.zenoh_session.clone();
         let ws_id = self.id;
         tokio::spawn(async move {
             let payload_str = payload.to_string();
-            if let Err(e) = session.put(&key, payload_str).await {
+            let put = session
+                .put(&key, payload_str)
+                .attachment(Producer::WsPublish.attachment());
+            if let Err(e) = put.await {
                 error!("WS {}: publish to '{}' failed: {}", ws_id, key, e);
             }
         });
     }
 }
 
+/// Maps a WebSocket command onto its REST handler.
+async fn dispatch_command(
+    command: &str,
+    msg: &serde_json::Value,
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, String> {
+    let field = |name: &str| {
+        msg[name]
+            .as_str()
+            .filter(|value| !value.is_empty())
+            .map(|value| web::Path::from(value.to_string()))
+            .ok_or_else(|| format!("{} requires '{}'", command, name))
+    };
+    let response = match command {
+        "execute_recipe" => {
+            let recipe_id = field("recipe_id")?;
+            let query = web::Query(pea_handlers::ExecuteQuery {
+                environment: msg["environment"].as_str().map(str::to_string),
+            });
+            pea_handlers::execute_recipe(req.clone(), state, recipe_id, query)
+                .await
+                .respond_to(&req)
+                .map_into_boxed_body()
+        }
+        "abort_recipe" => {
+            let execution_id = field("execution_id")?;
+            pea_handlers::abort_recipe_execution(req.clone(), state, execution_id)
+                .await
+                .respond_to(&req)
+                .map_into_boxed_body()
+        }
+        "ack_alarm" => {
+            let alarm_id = field("alarm_id")?;
+            pol_handlers::ack_alarm(req.clone(), state, alarm_id)
+                .await
+                .respond_to(&req)
+                .map_into_boxed_body()
+        }
+        other => return Err(format!("Unknown command '{}'", other)),
+    };
+    Ok(response)
+}
+
+type RedactionRules = Arc<tokio::sync::RwLock<HashMap<String, RedactionRule>>>;
+
+async fn redaction_policy(rules: Option<RedactionRules>) -> RedactionPolicy {
+    match rules {
+        Some(rules) => RedactionPolicy::new(rules.read().await.values().cloned()),
+        None => RedactionPolicy::default(),
+    }
+}
+
+fn redact_payload(policy: &RedactionPolicy, key: &str, payload: String) -> Option<String> {
+    if policy.is_empty() {
+        return Some(payload);
+    }
+    let value = serde_json::from_str::<serde_json::Value>(&payload)
+        .unwrap_or(serde_json::Value::String(payload));
+    policy.apply(key, value).map(|value| match value {
+        serde_json::Value::String(text) => text,
+        other => other.to_string(),
+    })
+}
+
+fn playback_task_key(playback_id: &str) -> String {
+    format!("playback:{}", playback_id)
+}
+
 // ─── HTTP Handler ────────────────────────────────────────────────────────────
 
 pub async fn ws_handler(
@@ -177,10 +561,72 @@ pub async fn ws_handler(
     stream: web::Payload,
     state: web::Data<AppState>,
 ) -> Result<HttpResponse, Error> {
+    let scope = match tenancy::scope_for(&state, &req).await {
+        Ok(scope) => scope,
+        Err(e) => return Ok(e.response()),
+    };
     let ws_conn = WsConnection {
         id: Uuid::new_v4(),
         zenoh_session: state.zenoh_session.clone(),
         subscription_tasks: HashMap::new(),
+        playback_sessions: state.playback_sessions.clone(),
+        redaction_rules: state.redaction_rules.clone(),
+        elevated: tenancy::is_deployment_admin(&state, &req),
+        scope,
+        format: FrameFormat::default(),
+        limits: state.ws_limits,
+        upgrade_request: req.clone(),
+        state: state.clone(),
     };
     ws::start(ws_conn, &req, stream)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_reject_broad_wildcards_and_excess_subscriptions() {
+        let limits = WsLimits {
+            max_subscriptions: 2,
+            min_literal_prefix: 1,
+        };
+        assert!(limits.check("entmoot/**", 0).is_ok());
+        assert!(limits.check("entmoot/pea/p1/status", 1).is_ok());
+        assert_eq!(limits.check("**", 0).unwrap_err().0, "wildcard_too_broad");
+        assert_eq!(
+            limits.check("*/pea/**", 0).unwrap_err().0,
+            "wildcard_too_broad"
+        );
+        assert_eq!(
+            limits.check("entmoot/**", 2).unwrap_err().0,
+            "subscription_limit"
+        );
+
+        let strict = WsLimits {
+            min_literal_prefix: 2,
+            ..limits
+        };
+        assert!(strict.check("entmoot/**", 0).is_err());
+        assert!(strict.check("entmoot/pol/**", 0).is_ok());
+    }
+
+    #[test]
+    fn msgpack_frames_round_trip_the_json_envelope() {
+        assert_eq!(
+            FrameFormat::parse("msgpack"),
+            Some(FrameFormat::MessagePack)
+        );
+        assert_eq!(FrameFormat::parse("json"), Some(FrameFormat::Json));
+        assert_eq!(FrameFormat::parse("cbor"), None);
+
+        let envelope = serde_json::json!({
+            "key": "entmoot/pea/p1/data/temp",
+            "payload": {"value": 21.5, "quality": "good"},
+        });
+        let bytes = rmp_serde::to_vec_named(&envelope).unwrap();
+        assert!(bytes.len() < envelope.to_string().len());
+        let decoded: serde_json::Value = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, envelope);
+    }
+}
@@ -0,0 +1,49 @@
+/// Edge agent settings, read from the environment.
+#[derive(Debug, Clone)]
+pub struct AgentConfig {
+    /// Site-local Zenoh endpoint the telemetry is published on; scouting is used when unset.
+    pub local_endpoint: Option<String>,
+    /// Central Zenoh router reached over the WAN.
+    pub upstream_router: String,
+    /// Key expressions forwarded upstream.
+    pub keys: Vec<String>,
+    pub spool_dir: String,
+    /// Oldest buffered samples are dropped beyond this size.
+    pub spool_max_bytes: u64,
+    /// Maximum buffered samples replayed per second once the WAN is back.
+    pub replay_rate: usize,
+}
+
+impl AgentConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let upstream_router = std::env::var("EDGE_UPSTREAM_ROUTER")
+            .map_err(|_| anyhow::anyhow!("EDGE_UPSTREAM_ROUTER is not set"))?;
+        let keys: Vec<String> = std::env::var("EDGE_KEYS")
+            .unwrap_or_else(|_| "entmoot/**,pea/**".to_string())
+            .split(',')
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+            .collect();
+        if keys.is_empty() {
+            anyhow::bail!("EDGE_KEYS defines no key expressions");
+        }
+
+        Ok(Self {
+            local_endpoint: std::env::var("EDGE_LOCAL_ENDPOINT").ok(),
+            upstream_router,
+            keys,
+            spool_dir: std::env::var("EDGE_SPOOL_DIR")
+                .unwrap_or_else(|_| "./data/edge-spool".to_string()),
+            spool_max_bytes: env_number("EDGE_SPOOL_MAX_MB", 512) * 1024 * 1024,
+            replay_rate: env_number("EDGE_REPLAY_RATE", 500) as usize,
+        })
+    }
+}
+
+fn env_number(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or(default)
+}
@@ -0,0 +1,198 @@
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::sync::{mpsc, Mutex};
+use tracing::{error, info, warn, Level};
+use zenoh::sample::Sample;
+use zenoh::time::{Timestamp, NTP64};
+
+mod config;
+mod spool;
+
+use config::AgentConfig;
+use spool::{Spool, SpooledSample};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt().with_max_level(Level::INFO).init();
+
+    let config = AgentConfig::from_env()?;
+    info!(
+        "Starting edge agent: forwarding {:?} to {}",
+        config.keys, config.upstream_router
+    );
+
+    let mut local_config = zenoh::Config::default();
+    if let Some(endpoint) = &config.local_endpoint {
+        info!("Connecting to local Zenoh endpoint: {}", endpoint);
+        local_config
+            .insert_json5("connect/endpoints", &format!(r#"["{}"]"#, endpoint))
+            .expect("Failed to configure local Zenoh endpoints");
+    }
+    let local = zenoh::open(local_config)
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+    // The upstream session is a client of the central router only, so the site
+    // network and the WAN stay separate and nothing loops back.
+    let mut upstream_config = zenoh::Config::default();
+    upstream_config
+        .insert_json5("mode", r#""client""#)
+        .expect("Failed to configure upstream Zenoh mode");
+    upstream_config
+        .insert_json5(
+            "connect/endpoints",
+            &format!(r#"["{}"]"#, config.upstream_router),
+        )
+        .expect("Failed to configure upstream Zenoh endpoints");
+    upstream_config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .expect("Failed to disable upstream scouting");
+    let upstream = zenoh::open(upstream_config)
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+    let spool = Arc::new(Mutex::new(Spool::open(
+        &config.spool_dir,
+        config.spool_max_bytes,
+    )?));
+    if !spool.lock().await.is_empty() {
+        info!("Resuming with buffered samples in {}", config.spool_dir);
+    }
+
+    let (tx, mut rx) = mpsc::channel::<Sample>(4096);
+    for key in &config.keys {
+        let subscriber = local
+            .declare_subscriber(key.as_str())
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to subscribe to {}: {}", key, e))?;
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            while let Ok(sample) = subscriber.recv_async().await {
+                if tx.send(sample).await.is_err() {
+                    break;
+                }
+            }
+        });
+        info!("Subscribed to local {}", key);
+    }
+
+    let mut replay = tokio::time::interval(Duration::from_secs(1));
+    let mut online = false;
+    loop {
+        tokio::select! {
+            Some(sample) = rx.recv() => {
+                let buffered = SpooledSample::new(
+                    sample.key_expr().as_str(),
+                    &sample.payload().to_bytes(),
+                    &sample.encoding().to_string(),
+                    sample_time_ms(&sample),
+                );
+                let mut spool = spool.lock().await;
+                // Live samples bypass the spool only when nothing older is waiting.
+                if online && spool.is_empty() && forward(&upstream, &buffered).await {
+                    continue;
+                }
+                if let Err(e) = spool.append(&buffered) {
+                    error!("Failed to buffer sample for {}: {}", buffered.key, e);
+                }
+            }
+            _ = replay.tick() => {
+                let connected = upstream_connected(&upstream).await;
+                if connected != online {
+                    online = connected;
+                    let spool = spool.lock().await;
+                    if online {
+                        info!("Upstream reachable, replaying {} buffered bytes", spool.size_bytes());
+                    } else {
+                        warn!("Upstream unreachable, buffering samples locally");
+                    }
+                }
+                if online {
+                    replay_buffered(&upstream, &spool, config.replay_rate).await;
+                }
+            }
+            _ = tokio::signal::ctrl_c() => {
+                info!("Received shutdown signal");
+                break;
+            }
+        }
+    }
+
+    let dropped = spool.lock().await.dropped();
+    if dropped > 0 {
+        warn!(
+            "{} samples were dropped because the spool was full",
+            dropped
+        );
+    }
+    local.close().await.map_err(|e| anyhow::anyhow!(e))?;
+    upstream.close().await.map_err(|e| anyhow::anyhow!(e))?;
+    info!("Edge agent shut down");
+    Ok(())
+}
+
+async fn upstream_connected(upstream: &zenoh::Session) -> bool {
+    upstream.info().routers_zid().await.next().is_some()
+}
+
+/// Source time of a sample: its Zenoh timestamp when the publisher set one,
+/// otherwise the time it reached the agent.
+fn sample_time_ms(sample: &Sample) -> i64 {
+    sample
+        .timestamp()
+        .and_then(|ts| {
+            ts.get_time()
+                .to_system_time()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+        })
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or_else(|| chrono::Utc::now().timestamp_millis())
+}
+
+/// Publishes upstream, carrying the original sample time as the Zenoh timestamp
+/// so late data lands in its proper time position.
+async fn forward(upstream: &zenoh::Session, sample: &SpooledSample) -> bool {
+    let source = upstream.new_timestamp();
+    let time = NTP64::from(Duration::from_millis(sample.timestamp_ms.max(0) as u64));
+    let result = upstream
+        .put(sample.key.as_str(), sample.payload_bytes())
+        .encoding(sample.encoding.as_str())
+        .timestamp(Timestamp::new(time, *source.get_id()))
+        .await;
+    if let Err(e) = &result {
+        warn!("Failed to forward {}: {}", sample.key, e);
+    }
+    result.is_ok()
+}
+
+async fn replay_buffered(upstream: &zenoh::Session, spool: &Mutex<Spool>, budget: usize) {
+    let mut remaining = budget;
+    while remaining > 0 {
+        let mut spool = spool.lock().await;
+        let batch = match spool.peek(remaining.min(100)) {
+            Ok(batch) if !batch.is_empty() => batch,
+            Ok(_) => return,
+            Err(e) => {
+                error!("Failed to read spool: {}", e);
+                return;
+            }
+        };
+
+        let mut delivered = 0;
+        for sample in &batch {
+            if !forward(upstream, sample).await {
+                break;
+            }
+            delivered += 1;
+        }
+        if let Err(e) = spool.ack(delivered) {
+            error!("Failed to acknowledge replayed samples: {}", e);
+            return;
+        }
+        if delivered < batch.len() {
+            return;
+        }
+        remaining -= delivered;
+    }
+}
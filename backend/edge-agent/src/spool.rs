@@ -0,0 +1,295 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Segments are closed once they reach this size.
+const SEGMENT_BYTES: u64 = 8 * 1024 * 1024;
+
+/// A telemetry sample held back while the upstream link is down.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpooledSample {
+    pub key: String,
+    /// UTF-8 payloads are stored as-is; anything else is hex encoded.
+    pub payload: String,
+    #[serde(default)]
+    pub hex: bool,
+    pub encoding: String,
+    /// Time the sample was produced at the site, in Unix milliseconds.
+    pub timestamp_ms: i64,
+}
+
+impl SpooledSample {
+    pub fn new(key: &str, payload: &[u8], encoding: &str, timestamp_ms: i64) -> Self {
+        let (payload, hex) = match std::str::from_utf8(payload) {
+            Ok(text) => (text.to_string(), false),
+            Err(_) => (hex::encode(payload), true),
+        };
+        Self {
+            key: key.to_string(),
+            payload,
+            hex,
+            encoding: encoding.to_string(),
+            timestamp_ms,
+        }
+    }
+
+    pub fn payload_bytes(&self) -> Vec<u8> {
+        if self.hex {
+            hex::decode(&self.payload).unwrap_or_default()
+        } else {
+            self.payload.as_bytes().to_vec()
+        }
+    }
+}
+
+/// Disk-backed FIFO of samples, stored as numbered JSONL segment files.
+///
+/// Samples handed out by [`Spool::peek`] stay on disk until [`Spool::ack`]
+/// consumes the whole segment, so a crash mid-replay re-sends rather than
+/// loses them.
+pub struct Spool {
+    dir: PathBuf,
+    max_bytes: u64,
+    /// (sequence, size in bytes) of every segment on disk, oldest first.
+    segments: VecDeque<(u64, u64)>,
+    /// Unacknowledged samples of the oldest segment.
+    pending: VecDeque<SpooledSample>,
+    reading: Option<u64>,
+    writer: Option<(u64, File)>,
+    dropped: u64,
+}
+
+impl Spool {
+    pub fn open(dir: &str, max_bytes: u64) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let mut segments: Vec<(u64, u64)> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                let seq = path
+                    .file_name()?
+                    .to_str()?
+                    .strip_suffix(".jsonl")?
+                    .parse()
+                    .ok()?;
+                Some((seq, entry.metadata().ok()?.len()))
+            })
+            .collect();
+        segments.sort();
+
+        Ok(Self {
+            dir: PathBuf::from(dir),
+            max_bytes,
+            segments: segments.into(),
+            pending: VecDeque::new(),
+            reading: None,
+            writer: None,
+            dropped: 0,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty() && self.segments.iter().all(|(_, size)| *size == 0)
+    }
+
+    pub fn size_bytes(&self) -> u64 {
+        self.segments.iter().map(|(_, size)| size).sum()
+    }
+
+    /// Samples discarded because the spool was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn append(&mut self, sample: &SpooledSample) -> Result<()> {
+        let mut line = serde_json::to_vec(sample)?;
+        line.push(b'\n');
+
+        let full = self
+            .writer
+            .as_ref()
+            .and_then(|(seq, _)| self.segments.iter().find(|(s, _)| s == seq))
+            .is_some_and(|(_, size)| *size >= SEGMENT_BYTES);
+        if self.writer.is_none() || full {
+            self.rotate()?;
+        }
+        let (seq, file) = self.writer.as_mut().expect("writer opened by rotate");
+        file.write_all(&line)?;
+        if let Some(entry) = self.segments.iter_mut().find(|(s, _)| s == seq) {
+            entry.1 += line.len() as u64;
+        }
+
+        self.enforce_limit()
+    }
+
+    /// Up to `max` of the oldest samples, without removing them.
+    pub fn peek(&mut self, max: usize) -> Result<Vec<SpooledSample>> {
+        if self.pending.is_empty() {
+            self.load_oldest()?;
+        }
+        Ok(self.pending.iter().take(max).cloned().collect())
+    }
+
+    /// Marks the first `count` peeked samples as delivered.
+    pub fn ack(&mut self, count: usize) -> Result<()> {
+        let count = count.min(self.pending.len());
+        self.pending.drain(..count);
+        if self.pending.is_empty() {
+            if let Some(seq) = self.reading.take() {
+                self.remove_segment(seq)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn segment_path(&self, seq: u64) -> PathBuf {
+        self.dir.join(format!("{:020}.jsonl", seq))
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        let seq = self.segments.back().map(|(seq, _)| seq + 1).unwrap_or(0);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.segment_path(seq))?;
+        self.segments.push_back((seq, 0));
+        self.writer = Some((seq, file));
+        Ok(())
+    }
+
+    fn load_oldest(&mut self) -> Result<()> {
+        let Some(&(seq, _)) = self.segments.front() else {
+            return Ok(());
+        };
+        // Never read the segment still being written; new samples go to a fresh one.
+        if self.writer.as_ref().is_some_and(|(w, _)| *w == seq) {
+            self.writer = None;
+        }
+        self.pending = read_segment(&self.segment_path(seq))?.into();
+        self.reading = Some(seq);
+        if self.pending.is_empty() {
+            self.reading = None;
+            self.remove_segment(seq)?;
+        }
+        Ok(())
+    }
+
+    fn remove_segment(&mut self, seq: u64) -> Result<()> {
+        self.segments.retain(|(s, _)| *s != seq);
+        if self.writer.as_ref().is_some_and(|(w, _)| *w == seq) {
+            self.writer = None;
+        }
+        match fs::remove_file(self.segment_path(seq)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn enforce_limit(&mut self) -> Result<()> {
+        while self.size_bytes() > self.max_bytes && self.segments.len() > 1 {
+            let (seq, _) = self.segments[0];
+            let lost = if self.reading == Some(seq) {
+                self.reading = None;
+                std::mem::take(&mut self.pending).len()
+            } else {
+                read_segment(&self.segment_path(seq))?.len()
+            };
+            self.dropped += lost as u64;
+            tracing::warn!(
+                "Spool over {} bytes, dropped {} oldest samples",
+                self.max_bytes,
+                lost
+            );
+            self.remove_segment(seq)?;
+        }
+        Ok(())
+    }
+}
+
+fn read_segment(path: &Path) -> Result<Vec<SpooledSample>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut samples = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        // A torn last line from a crash mid-write is skipped.
+        if let Ok(sample) = serde_json::from_str(&line) {
+            samples.push(sample);
+        }
+    }
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!(
+            "edge-spool-{}-{}",
+            name,
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        dir.to_string_lossy().to_string()
+    }
+
+    fn sample(n: i64) -> SpooledSample {
+        SpooledSample::new(
+            "entmoot/site/temp",
+            n.to_string().as_bytes(),
+            "text/plain",
+            n,
+        )
+    }
+
+    #[test]
+    fn samples_replay_in_order_and_survive_reopen() {
+        let dir = temp_dir("order");
+        let mut spool = Spool::open(&dir, u64::MAX).unwrap();
+        for n in 0..5 {
+            spool.append(&sample(n)).unwrap();
+        }
+
+        let first = spool.peek(2).unwrap();
+        assert_eq!(first, vec![sample(0), sample(1)]);
+        spool.append(&sample(5)).unwrap();
+        drop(spool);
+
+        // Unacknowledged samples are still there after a restart.
+        let mut spool = Spool::open(&dir, u64::MAX).unwrap();
+        let mut replayed = Vec::new();
+        while !spool.is_empty() {
+            let batch = spool.peek(4).unwrap();
+            replayed.extend(batch.iter().map(|s| s.timestamp_ms));
+            spool.ack(batch.len()).unwrap();
+        }
+        assert_eq!(replayed, vec![0, 1, 2, 3, 4, 5]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn binary_payloads_round_trip() {
+        let raw = [0xff, 0x00, 0x10];
+        let spooled = SpooledSample::new("k", &raw, "application/octet-stream", 1);
+        assert!(spooled.hex);
+        assert_eq!(spooled.payload_bytes(), raw.to_vec());
+    }
+
+    #[test]
+    fn oldest_segments_are_dropped_over_the_limit() {
+        let dir = temp_dir("limit");
+        let mut spool = Spool::open(&dir, 1).unwrap();
+        spool.append(&sample(0)).unwrap();
+        spool.peek(1).unwrap();
+        spool.append(&sample(1)).unwrap();
+
+        assert_eq!(spool.dropped(), 1);
+        let batch = spool.peek(10).unwrap();
+        assert_eq!(batch, vec![sample(1)]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,65 @@
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IngestConfig {
+    pub broker: BrokerConfig,
+    pub rules: Vec<MappingRule>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BrokerConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub keep_alive_s: Option<u64>,
+}
+
+/// Maps MQTT topics matching `mqtt_topic` (with `+` / `#` wildcards) onto a
+/// Zenoh key rendered from `zenoh_key`.
+///
+/// `{1}`, `{2}`, ... in the template expand to the topic levels captured by the
+/// wildcards, in order; `{topic}` expands to the full MQTT topic.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MappingRule {
+    pub mqtt_topic: String,
+    pub zenoh_key: String,
+    /// Dot path of the measurement inside a JSON payload; the whole payload when unset.
+    pub value_field: Option<String>,
+    /// Dot path of a source timestamp inside a JSON payload.
+    pub timestamp_field: Option<String>,
+    pub unit: Option<String>,
+    /// MQTT QoS for the subscription (0, 1 or 2).
+    pub qos: Option<u8>,
+}
+
+impl IngestConfig {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path, e))?;
+        let mut config: IngestConfig = serde_json::from_str(&raw)
+            .map_err(|e| anyhow::anyhow!("Invalid MQTT ingest config {}: {}", path, e))?;
+
+        if let Ok(host) = std::env::var("MQTT_BROKER_HOST") {
+            config.broker.host = host;
+        }
+        if let Some(port) = std::env::var("MQTT_BROKER_PORT")
+            .ok()
+            .and_then(|value| value.parse().ok())
+        {
+            config.broker.port = port;
+        }
+        if let Ok(username) = std::env::var("MQTT_USERNAME") {
+            config.broker.username = Some(username);
+        }
+        if let Ok(password) = std::env::var("MQTT_PASSWORD") {
+            config.broker.password = Some(password);
+        }
+
+        if config.rules.is_empty() {
+            anyhow::bail!("MQTT ingest config {} defines no mapping rules", path);
+        }
+        Ok(config)
+    }
+}
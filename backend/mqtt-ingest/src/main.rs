@@ -0,0 +1,105 @@
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use std::time::Duration;
+use tracing::{error, info, warn, Level};
+
+mod config;
+mod mapping;
+
+use config::{IngestConfig, MappingRule};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt().with_max_level(Level::INFO).init();
+
+    let config_path = std::env::var("MQTT_INGEST_CONFIG")
+        .unwrap_or_else(|_| "./config/mqtt-ingest.json".to_string());
+    let config = IngestConfig::load(&config_path)?;
+    info!(
+        "Starting MQTT ingest: {} rule(s) from {}",
+        config.rules.len(),
+        config_path
+    );
+
+    let mut zenoh_config = zenoh::Config::default();
+    if let Ok(endpoint) = std::env::var("ZENOH_ROUTER") {
+        info!("Connecting to Zenoh router: {}", endpoint);
+        zenoh_config
+            .insert_json5("connect/endpoints", &format!(r#"["{}"]"#, endpoint))
+            .expect("Failed to configure Zenoh endpoints");
+    }
+    let session = zenoh::open(zenoh_config)
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    info!("Zenoh session opened");
+
+    let broker = &config.broker;
+    let client_id = broker
+        .client_id
+        .clone()
+        .unwrap_or_else(|| "fendtastic-mqtt-ingest".to_string());
+    let mut options = MqttOptions::new(client_id, broker.host.clone(), broker.port);
+    options.set_keep_alive(Duration::from_secs(broker.keep_alive_s.unwrap_or(30)));
+    if let (Some(username), Some(password)) = (&broker.username, &broker.password) {
+        options.set_credentials(username.clone(), password.clone());
+    }
+
+    let (client, mut eventloop) = AsyncClient::new(options, 256);
+    info!("Connecting to MQTT broker {}:{}", broker.host, broker.port);
+
+    loop {
+        tokio::select! {
+            event = eventloop.poll() => match event {
+                // Subscriptions are (re)issued on every connect since the session is not persistent.
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    info!("Connected to MQTT broker");
+                    subscribe_all(&client, &config.rules).await;
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    forward(&session, &config.rules, &publish.topic, &publish.payload).await;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!("MQTT connection error: {}", e);
+                    tokio::time::sleep(Duration::from_secs(2)).await;
+                }
+            },
+            _ = tokio::signal::ctrl_c() => {
+                info!("Received shutdown signal");
+                break;
+            }
+        }
+    }
+
+    let _ = client.disconnect().await;
+    session.close().await.map_err(|e| anyhow::anyhow!(e))?;
+    info!("MQTT ingest shut down");
+    Ok(())
+}
+
+async fn subscribe_all(client: &AsyncClient, rules: &[MappingRule]) {
+    for rule in rules {
+        let qos = match rule.qos.unwrap_or(1) {
+            0 => QoS::AtMostOnce,
+            2 => QoS::ExactlyOnce,
+            _ => QoS::AtLeastOnce,
+        };
+        match client.subscribe(rule.mqtt_topic.clone(), qos).await {
+            Ok(()) => info!("Subscribed to MQTT topic {}", rule.mqtt_topic),
+            Err(e) => error!("Failed to subscribe to {}: {}", rule.mqtt_topic, e),
+        }
+    }
+}
+
+/// Publishes the sample under every rule whose filter matches the topic.
+async fn forward(session: &zenoh::Session, rules: &[MappingRule], topic: &str, payload: &[u8]) {
+    for rule in rules {
+        let Some(captures) = mapping::match_topic(&rule.mqtt_topic, topic) else {
+            continue;
+        };
+        let key = mapping::render_key(&rule.zenoh_key, topic, &captures);
+        let sample = mapping::normalize(rule, topic, payload);
+        if let Err(e) = session.put(&key, sample.to_string()).await {
+            warn!("Failed to publish {} -> {}: {}", topic, key, e);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use crate::config::MappingRule;
+use chrono::Utc;
+use serde_json::{json, Value};
+
+/// Matches an MQTT topic against a subscription filter, returning the levels
+/// captured by `+` and `#` wildcards (a `#` capture keeps its `/` separators).
+pub fn match_topic(filter: &str, topic: &str) -> Option<Vec<String>> {
+    let filter_levels: Vec<&str> = filter.split('/').collect();
+    let topic_levels: Vec<&str> = topic.split('/').collect();
+    let mut captures = Vec::new();
+
+    for (idx, level) in filter_levels.iter().enumerate() {
+        match *level {
+            "#" => {
+                captures.push(topic_levels.get(idx..)?.join("/"));
+                return Some(captures);
+            }
+            "+" => captures.push(topic_levels.get(idx)?.to_string()),
+            exact => {
+                if topic_levels.get(idx) != Some(&exact) {
+                    return None;
+                }
+            }
+        }
+    }
+
+    (filter_levels.len() == topic_levels.len()).then_some(captures)
+}
+
+/// Renders the Zenoh key for a matched topic. Characters that are reserved in
+/// Zenoh key expressions are replaced so a stray topic cannot widen the key.
+pub fn render_key(template: &str, topic: &str, captures: &[String]) -> String {
+    let mut key = template.replace("{topic}", &sanitize(topic));
+    for (idx, capture) in captures.iter().enumerate() {
+        key = key.replace(&format!("{{{}}}", idx + 1), &sanitize(capture));
+    }
+    key
+}
+
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '*' | '$' | '?' | '#' | '+' => '_',
+            other => other,
+        })
+        .collect()
+}
+
+/// Builds the normalized sample published on Zenoh.
+pub fn normalize(rule: &MappingRule, topic: &str, payload: &[u8]) -> Value {
+    let text = String::from_utf8_lossy(payload);
+    let parsed: Value = serde_json::from_str(text.trim()).unwrap_or_else(|_| json!(text.trim()));
+
+    let value = match rule.value_field.as_deref() {
+        Some(path) => lookup(&parsed, path).cloned().unwrap_or(Value::Null),
+        None => parsed.clone(),
+    };
+    let timestamp = rule
+        .timestamp_field
+        .as_deref()
+        .and_then(|path| lookup(&parsed, path))
+        .cloned()
+        .unwrap_or_else(|| json!(Utc::now().to_rfc3339()));
+
+    json!({
+        "value": value,
+        "unit": rule.unit,
+        "timestamp": timestamp,
+        "source": {
+            "protocol": "mqtt",
+            "topic": topic,
+        },
+    })
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |current, segment| current.get(segment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(value_field: Option<&str>) -> MappingRule {
+        MappingRule {
+            mqtt_topic: "plant/+/sensors/#".to_string(),
+            zenoh_key: "entmoot/sensors/{1}/{2}".to_string(),
+            value_field: value_field.map(str::to_string),
+            timestamp_field: Some("ts".to_string()),
+            unit: Some("celsius".to_string()),
+            qos: None,
+        }
+    }
+
+    #[test]
+    fn wildcards_capture_topic_levels() {
+        assert_eq!(
+            match_topic("plant/+/sensors/#", "plant/line1/sensors/tank/temp"),
+            Some(vec!["line1".to_string(), "tank/temp".to_string()])
+        );
+        assert_eq!(match_topic("plant/+/temp", "plant/line1/pressure"), None);
+        assert_eq!(match_topic("plant/+", "plant/line1/extra"), None);
+    }
+
+    #[test]
+    fn keys_are_rendered_from_captures() {
+        let captures = vec!["line1".to_string(), "tank/temp".to_string()];
+        assert_eq!(
+            render_key(
+                "entmoot/sensors/{1}/{2}",
+                "plant/line1/sensors/tank/temp",
+                &captures
+            ),
+            "entmoot/sensors/line1/tank/temp"
+        );
+        assert_eq!(
+            render_key("entmoot/sensors/{1}", "t", &["a*b".to_string()]),
+            "entmoot/sensors/a_b"
+        );
+    }
+
+    #[test]
+    fn json_payloads_are_normalized() {
+        let sample = normalize(
+            &rule(Some("reading.value")),
+            "plant/line1/sensors/temp",
+            br#"{"reading": {"value": 21.5}, "ts": "2024-01-01T00:00:00Z"}"#,
+        );
+        assert_eq!(sample["value"], 21.5);
+        assert_eq!(sample["unit"], "celsius");
+        assert_eq!(sample["timestamp"], "2024-01-01T00:00:00Z");
+        assert_eq!(sample["source"]["topic"], "plant/line1/sensors/temp");
+    }
+
+    #[test]
+    fn plain_payloads_keep_their_value() {
+        let sample = normalize(&rule(None), "plant/line1/sensors/temp", b"42");
+        assert_eq!(sample["value"], 42);
+
+        let sample = normalize(&rule(None), "plant/line1/sensors/state", b"RUNNING");
+        assert_eq!(sample["value"], "RUNNING");
+    }
+}
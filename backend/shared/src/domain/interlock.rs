@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::authority::ActorClass;
+use crate::mtp::{ServiceCommand, ServiceState};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterlockRule {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub target: InterlockTarget,
+    pub condition: InterlockCondition,
+    pub override_actor_classes: Vec<ActorClass>,
+    pub enabled: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// The command(s) an interlock blocks. A missing service tag applies the
+/// rule to every service of the PEA.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterlockTarget {
+    pub pea_id: String,
+    pub service_tag: Option<String>,
+    pub commands: Vec<ServiceCommand>,
+}
+
+/// The blocking condition: the rule trips while any matching service of
+/// `pea_id` reports one of `states`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterlockCondition {
+    pub pea_id: String,
+    pub service_tag: Option<String>,
+    pub states: Vec<ServiceState>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterlockOverride {
+    pub actor_id: String,
+    pub actor_class: ActorClass,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterlockViolation {
+    pub id: String,
+    pub rule_id: String,
+    pub rule_name: String,
+    pub pea_id: String,
+    pub service_tag: String,
+    pub command: ServiceCommand,
+    pub source: String,
+    pub blocking_pea_id: String,
+    pub blocking_service_tag: String,
+    pub blocking_state: ServiceState,
+    pub overridden: bool,
+    pub override_actor_id: Option<String>,
+    pub override_actor_class: Option<ActorClass>,
+    pub override_reason: Option<String>,
+    pub occurred_at: chrono::DateTime<chrono::Utc>,
+}
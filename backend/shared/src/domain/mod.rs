@@ -2,5 +2,6 @@ pub mod authority;
 pub mod binding;
 pub mod capability;
 pub mod driver;
+pub mod interlock;
 pub mod pea;
 pub mod runtime;
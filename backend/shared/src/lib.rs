@@ -43,10 +43,21 @@ pub struct TimeSeriesPoint {
     pub value: f64,
 }
 
+/// An action taken on the platform, as kept in the audit log.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserAction {
-    pub user_id: String,
+    #[serde(default)]
+    pub id: String,
+    /// Actor that took the action; `None` when the request named none.
+    pub user_id: Option<String>,
+    /// Kind of entity acted on, e.g. `pea`.
+    #[serde(default)]
+    pub entity: String,
+    /// Id of the entity acted on.
     pub machine_id: String,
     pub action: String,
+    /// What the action carried, e.g. the command and procedure.
+    #[serde(default)]
+    pub payload: serde_json::Value,
     pub timestamp: DateTime<Utc>,
 }
@@ -1,4 +1,8 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub mod aml;
+pub mod units;
 
 // ─── PEA Information Label ───────────────────────────────────────────────────
 
@@ -14,6 +18,37 @@ pub struct PeaConfig {
     pub opcua_config: OpcUaConfig,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Owning tenant; `None` for resources shared at the deployment level.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    /// Whether the api-server simulates the PEA or a connector serves the real device.
+    #[serde(default)]
+    pub mode: PeaMode,
+    /// When the PEA was deleted; archived configs are kept out of the active
+    /// set until restored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Where the telemetry of a PEA comes from.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PeaMode {
+    /// The api-server publishes simulated status for the PEA.
+    #[default]
+    Simulated,
+    /// A connector (e.g. EVA-ICS) serves the real device and publishes its status.
+    External,
+}
+
+impl PeaMode {
+    /// Origin label attached to data published for a PEA in this mode.
+    pub fn origin(self) -> &'static str {
+        match self {
+            Self::Simulated => "simulated",
+            Self::External => "external",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,6 +113,8 @@ pub struct AnalogParameter {
     pub v_max: f64,
     pub v_default: f64,
     pub tag_mapping: Option<TagMapping>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<DisplayHints>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -101,6 +138,23 @@ pub struct DIntParameter {
     pub v_max: i64,
     pub v_default: i64,
     pub tag_mapping: Option<TagMapping>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<DisplayHints>,
+}
+
+/// How HMIs show a numeric parameter. Values are still written and stored
+/// in the parameter's own `unit`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DisplayHints {
+    /// Decimal places to show.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u32>,
+    /// Unit to show values in, when it differs from the parameter's unit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    /// Labels for the values of a DInt parameter, e.g. `{"0": "Off", "1": "Low"}`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<i64, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -419,6 +473,59 @@ impl ServiceState {
             _ => vec![],
         }
     }
+
+    pub fn all() -> [ServiceState; 16] {
+        [
+            Self::Idle,
+            Self::Starting,
+            Self::Execute,
+            Self::Completing,
+            Self::Completed,
+            Self::Pausing,
+            Self::Paused,
+            Self::Resuming,
+            Self::Holding,
+            Self::Held,
+            Self::Unholding,
+            Self::Stopping,
+            Self::Stopped,
+            Self::Aborting,
+            Self::Aborted,
+            Self::Resetting,
+        ]
+    }
+
+    /// State entered when `command` is accepted in this state.
+    pub fn command_target(&self, command: ServiceCommand) -> Option<ServiceState> {
+        if !self.allowed_commands().contains(&command) {
+            return None;
+        }
+        match command {
+            ServiceCommand::Start | ServiceCommand::Restart => Some(Self::Starting),
+            ServiceCommand::Complete => Some(Self::Completing),
+            ServiceCommand::Hold => Some(Self::Holding),
+            ServiceCommand::Unhold => Some(Self::Unholding),
+            ServiceCommand::Pause => Some(Self::Pausing),
+            ServiceCommand::Resume => Some(Self::Resuming),
+            ServiceCommand::Stop => Some(Self::Stopping),
+            ServiceCommand::Abort => Some(Self::Aborting),
+            ServiceCommand::Reset => Some(Self::Resetting),
+        }
+    }
+
+    /// Stable state a transient state settles into once its action completes.
+    pub fn completion_target(&self) -> Option<ServiceState> {
+        match self {
+            Self::Starting | Self::Resuming | Self::Unholding => Some(Self::Execute),
+            Self::Completing => Some(Self::Completed),
+            Self::Pausing => Some(Self::Paused),
+            Self::Holding => Some(Self::Held),
+            Self::Stopping => Some(Self::Stopped),
+            Self::Aborting => Some(Self::Aborted),
+            Self::Resetting => Some(Self::Idle),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -496,7 +603,15 @@ pub struct Recipe {
     pub name: String,
     pub description: String,
     pub steps: Vec<RecipeStep>,
+    /// Library steps used by the recipe, expanded among `steps` by order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub library_steps: Vec<RecipeStepRef>,
+    /// Events that start the recipe on their own; see `RecipeTrigger`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub triggers: Vec<RecipeTrigger>,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -511,6 +626,32 @@ pub struct RecipeStep {
     pub timeout_ms: Option<u64>,
 }
 
+/// Use of a named step sequence from the step library. Its steps take the
+/// place of the reference; at equal `order`, the recipe's own step comes first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeStepRef {
+    pub order: u32,
+    pub step_id: String,
+    /// Values for the library step's parameters, by name.
+    #[serde(default)]
+    pub arguments: std::collections::BTreeMap<String, serde_json::Value>,
+}
+
+/// Starts a recipe without a caller, to chain production steps.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecipeTrigger {
+    /// An execution of another recipe completed.
+    RecipeCompleted { recipe_id: String },
+    /// A service of a PEA entered `state`; any service when `service_tag` is unset.
+    ServiceState {
+        pea_id: String,
+        #[serde(default)]
+        service_tag: Option<String>,
+        state: ServiceState,
+    },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecipeParameterValue {
     pub parameter_tag: String,
@@ -550,6 +691,16 @@ pub mod topics {
         )
     }
 
+    /// Validated procedure parameter values, written to the PEA by its connector.
+    pub fn pea_service_parameters(pea_id: &str, service_tag: &str) -> String {
+        format!(
+            "entmoot/habitat/nodes/{}/pea/{}/services/{}/parameters",
+            get_node_id(),
+            pea_id,
+            service_tag
+        )
+    }
+
     pub fn pea_data(pea_id: &str, data_tag: &str) -> String {
         format!(
             "entmoot/habitat/nodes/{}/pea/{}/data/{}",
@@ -563,6 +714,37 @@ pub mod topics {
         format!("entmoot/habitat/nodes/{}/pea/{}/config", get_node_id(), pea_id)
     }
 
+    /// Birth certificate: config summary and capabilities, published on deploy.
+    pub fn pea_birth(pea_id: &str) -> String {
+        format!("entmoot/habitat/nodes/{}/pea/{}/birth", get_node_id(), pea_id)
+    }
+
+    /// Death certificate, published on undeploy or when the PEA's liveliness is lost.
+    pub fn pea_death(pea_id: &str) -> String {
+        format!("entmoot/habitat/nodes/{}/pea/{}/death", get_node_id(), pea_id)
+    }
+
+    /// Liveliness token held while the PEA is deployed.
+    pub fn pea_liveliness(pea_id: &str) -> String {
+        format!("entmoot/habitat/nodes/{}/pea/{}/alive", get_node_id(), pea_id)
+    }
+
+    /// Queryable through which an api-server instance answers with every
+    /// replicated document it holds, so peers can catch up.
+    pub fn config_replication_snapshot(instance_id: &str) -> String {
+        format!("entmoot/config/replication/snapshot/{}", instance_id)
+    }
+
+    /// Normalized alarm record, republished on every change and removed on delete.
+    pub fn pol_alarm(alarm_id: &str) -> String {
+        format!("entmoot/pol/alarms/{}", alarm_id)
+    }
+
+    /// Operator ownership of a PEA or alarm area; removed when released or lapsed.
+    pub fn operator_presence(kind: &str, target: &str) -> String {
+        format!("entmoot/presence/{}/{}", kind, target)
+    }
+
     pub fn runtime_pea_deploy(pea_id: &str) -> String {
         format!("entmoot/runtime/nodes/{}/pea/{}/deploy", get_node_id(), pea_id)
     }
@@ -573,9 +755,19 @@ pub mod topics {
 
     pub const PEA_ANNOUNCE_WILDCARD: &str = "entmoot/habitat/nodes/*/pea/*/announce";
     pub const PEA_STATUS_WILDCARD: &str = "entmoot/habitat/nodes/*/pea/*/status";
+    pub const PEA_BIRTH_WILDCARD: &str = "entmoot/habitat/nodes/*/pea/*/birth";
+    pub const PEA_LIVELINESS_WILDCARD: &str = "entmoot/habitat/nodes/*/pea/*/alive";
     pub const RUNTIME_PEA_DEPLOY_WILDCARD: &str = "entmoot/runtime/nodes/*/pea/*/deploy";
     pub const RUNTIME_PEA_LIFECYCLE_WILDCARD: &str = "entmoot/runtime/nodes/*/pea/*/lifecycle";
     pub const PEA_SERVICE_COMMAND_WILDCARD: &str = "entmoot/habitat/nodes/*/pea/*/services/*/command";
+    pub const PEA_SERVICE_PARAMETERS_WILDCARD: &str =
+        "entmoot/habitat/nodes/*/pea/*/services/*/parameters";
     pub const POL_RECIPES_COMMAND: &str = "entmoot/pol/recipes/command";
     pub const POL_RECIPES_STATUS: &str = "entmoot/pol/recipes/status";
+    pub const POL_ALARMS_WILDCARD: &str = "entmoot/pol/alarms/*";
+    /// PEA configurations and recipes changed outside the API, e.g. files edited on disk.
+    pub const CONFIG_CHANGES: &str = "entmoot/config/changes";
+    /// Versioned configuration writes exchanged between api-server instances.
+    pub const CONFIG_REPLICATION: &str = "entmoot/config/replication";
+    pub const CONFIG_REPLICATION_SNAPSHOT_WILDCARD: &str = "entmoot/config/replication/snapshot/*";
 }
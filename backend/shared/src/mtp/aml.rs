@@ -0,0 +1,1623 @@
+//! Import and export of VDI/VDE/NAMUR 2658 Module Type Packages.
+//!
+//! An MTP is an AutomationML container: a zip holding the CAEX (`.aml`)
+//! documents, whose root document is named in `_rels/.rels`. A bare `.aml`
+//! document is accepted as well. Elements are recognised by the last segment
+//! of their `RefBaseSystemUnitPath` (e.g. `.../OperationElement/AnaServParam`),
+//! so vendor libraries that derive from the MTP classes import the same way.
+//!
+//! [`export_package`] writes a PEA config back into such a package, so configs
+//! edited here can be taken into other process orchestration tooling.
+
+use super::{
+    ActiveElement, AnaDrvConfig, AnaViewConfig, AnaVlvConfig, AnalogParameter, BinDrvConfig,
+    BinMonConfig, BinStringViewConfig, BinViewConfig, BinVlvConfig, BinaryParameter, DIntMonConfig,
+    DIntParameter, DIntStringViewConfig, DIntViewConfig, IndicatorElement, OpcUaConfig,
+    PIDCtrlConfig, PeaConfig, PeaMode, ProcedureConfig, ProtocolType, ServiceConfig,
+    ServiceParameter, StringParameter, StringViewConfig, TagMapping, WriterInfo,
+};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+
+/// Relationship type naming the root CAEX document of an AutomationML container.
+const ROOT_DOCUMENT_RELATIONSHIP: &str = "RootDocument";
+
+/// Largest document read out of a package; bounds what a small, highly
+/// compressed upload can expand to.
+pub const MAX_DOCUMENT_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AmlError {
+    #[error("Failed to read MTP package: {0}")]
+    Package(String),
+    #[error("Invalid AML document: {0}")]
+    Xml(String),
+    #[error("Not a CAEX document (root element is '{0}')")]
+    NotCaex(String),
+    #[error("No ModuleTypePackage found in the AML document")]
+    NoModuleTypePackage,
+}
+
+/// A PEA config built from an MTP, with what could not be carried over.
+#[derive(Debug, Clone)]
+pub struct MtpImport {
+    pub config: PeaConfig,
+    pub warnings: Vec<String>,
+}
+
+/// Builds a PEA config from an MTP package (zip) or a bare AML document.
+pub fn import_package(content: &[u8]) -> Result<MtpImport, AmlError> {
+    let xml = root_document(content)?;
+    let document = parse_document(&xml)?;
+    if document.name != "CAEXFile" {
+        return Err(AmlError::NotCaex(document.name));
+    }
+    let elements = internal_elements(&document);
+    let module = elements
+        .iter()
+        .copied()
+        .find(|ie| class_of(ie) == "ModuleTypePackage")
+        .ok_or(AmlError::NoModuleTypePackage)?;
+    Ok(Importer::new(&elements).build(&document, module))
+}
+
+// ─── Package and XML Reading ─────────────────────────────────────────────────
+
+fn root_document(content: &[u8]) -> Result<String, AmlError> {
+    if !content.starts_with(b"PK") {
+        return Ok(decode(content));
+    }
+    let package_error = |e: zip::result::ZipError| AmlError::Package(e.to_string());
+    let mut archive = zip::ZipArchive::new(Cursor::new(content)).map_err(package_error)?;
+    let root = root_document_name(&mut archive)
+        .or_else(|| {
+            archive
+                .file_names()
+                .find(|name| name.to_lowercase().ends_with(".aml"))
+                .map(str::to_string)
+        })
+        .ok_or_else(|| AmlError::Package("package contains no .aml document".to_string()))?;
+    let entry = archive.by_name(&root).map_err(package_error)?;
+    let bytes = read_entry(entry, &root, MAX_DOCUMENT_BYTES)?;
+    Ok(decode(&bytes))
+}
+
+/// Reads a package entry, refusing entries that expand beyond `limit` bytes.
+fn read_entry(entry: impl Read, name: &str, limit: u64) -> Result<Vec<u8>, AmlError> {
+    let mut bytes = Vec::new();
+    entry
+        .take(limit + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| AmlError::Package(e.to_string()))?;
+    if bytes.len() as u64 > limit {
+        return Err(AmlError::Package(format!(
+            "{} is larger than {} bytes",
+            name, limit
+        )));
+    }
+    Ok(bytes)
+}
+
+/// Target of the container's root document relationship, if it declares one.
+fn root_document_name(archive: &mut zip::ZipArchive<Cursor<&[u8]>>) -> Option<String> {
+    let entry = archive.by_name("_rels/.rels").ok()?;
+    let bytes = read_entry(entry, "_rels/.rels", MAX_DOCUMENT_BYTES).ok()?;
+    let relationships = parse_document(&decode(&bytes)).ok()?;
+    let target = relationships
+        .children_named("Relationship")
+        .find(|r| {
+            r.attr("Type")
+                .is_some_and(|t| t.ends_with(ROOT_DOCUMENT_RELATIONSHIP))
+        })
+        .and_then(|r| r.attr("Target"))
+        .map(|target| target.trim_start_matches('/').to_string());
+    target
+}
+
+fn decode(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_start_matches('\u{feff}')
+        .to_string()
+}
+
+/// An XML element with namespace prefixes stripped.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn attr(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+}
+
+fn parse_document(xml: &str) -> Result<Element, AmlError> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    // The bottom of the stack collects the document element.
+    let mut stack = vec![Element::default()];
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) => stack.push(element_from(e)?),
+            Ok(Event::Empty(ref e)) => {
+                let element = element_from(e)?;
+                stack
+                    .last_mut()
+                    .expect("stack holds the root")
+                    .children
+                    .push(element);
+            }
+            Ok(Event::End(_)) => {
+                let element = stack.pop().expect("stack holds the root");
+                let parent = stack
+                    .last_mut()
+                    .ok_or_else(|| AmlError::Xml("unbalanced end tag".to_string()))?;
+                parent.children.push(element);
+            }
+            Ok(Event::Text(ref e)) => {
+                let text = e.unescape().map_err(|e| AmlError::Xml(e.to_string()))?;
+                stack
+                    .last_mut()
+                    .expect("stack holds the root")
+                    .text
+                    .push_str(&text);
+            }
+            Ok(Event::CData(e)) => {
+                let text = String::from_utf8_lossy(&e.into_inner()).into_owned();
+                stack
+                    .last_mut()
+                    .expect("stack holds the root")
+                    .text
+                    .push_str(&text);
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => {
+                return Err(AmlError::Xml(format!(
+                    "{} at byte {}",
+                    e,
+                    reader.buffer_position()
+                )))
+            }
+            _ => {}
+        }
+    }
+
+    if stack.len() != 1 {
+        return Err(AmlError::Xml("unexpected end of document".to_string()));
+    }
+    stack
+        .pop()
+        .and_then(|root| root.children.into_iter().next())
+        .ok_or_else(|| AmlError::Xml("empty document".to_string()))
+}
+
+fn element_from(start: &BytesStart) -> Result<Element, AmlError> {
+    let mut element = Element {
+        name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+        ..Element::default()
+    };
+    for attr in start.attributes() {
+        let attr = attr.map_err(|e| AmlError::Xml(e.to_string()))?;
+        let value = attr
+            .unescape_value()
+            .map_err(|e| AmlError::Xml(e.to_string()))?;
+        element.attributes.push((
+            String::from_utf8_lossy(attr.key.local_name().as_ref()).into_owned(),
+            value.into_owned(),
+        ));
+    }
+    Ok(element)
+}
+
+// ─── CAEX Helpers ────────────────────────────────────────────────────────────
+
+/// All `InternalElement`s of the document, depth first.
+fn internal_elements(root: &Element) -> Vec<&Element> {
+    fn walk<'a>(element: &'a Element, out: &mut Vec<&'a Element>) {
+        for child in &element.children {
+            if child.name == "InternalElement" {
+                out.push(child);
+            }
+            walk(child, out);
+        }
+    }
+    let mut out = Vec::new();
+    walk(root, &mut out);
+    out
+}
+
+/// MTP class of an element: the last segment of its system unit class path.
+fn class_of(ie: &Element) -> &str {
+    ie.attr("RefBaseSystemUnitPath")
+        .and_then(|path| path.rsplit('/').next())
+        .unwrap_or_default()
+}
+
+/// Value of a CAEX `<Attribute Name="...">`, if set.
+fn value<'a>(ie: &'a Element, name: &str) -> Option<&'a str> {
+    ie.children_named("Attribute")
+        .find(|a| a.attr("Name") == Some(name))
+        .and_then(|a| a.child("Value"))
+        .map(|v| v.text.trim())
+        .filter(|v| !v.is_empty())
+}
+
+fn number<T: std::str::FromStr>(ie: &Element, name: &str) -> Option<T> {
+    value(ie, name).and_then(|v| v.parse().ok())
+}
+
+fn flag(ie: &Element, name: &str) -> Option<bool> {
+    match value(ie, name)?.to_lowercase().as_str() {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn tag_of(da: &Element) -> String {
+    value(da, "TagName")
+        .or(da.attr("Name"))
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn name_of(da: &Element) -> String {
+    value(da, "TagDescription")
+        .map(str::to_string)
+        .unwrap_or_else(|| tag_of(da))
+}
+
+fn unit_of(da: &Element, name: &str) -> String {
+    value(da, name).map(unit_symbol).unwrap_or_default()
+}
+
+/// IEC 61158 unit codes as used by `VUnit`, with their symbols.
+const UNITS: [(&str, &str); 22] = [
+    ("1000", "K"),
+    ("1001", "°C"),
+    ("1002", "°F"),
+    ("1010", "m"),
+    ("1013", "mm"),
+    ("1034", "m³"),
+    ("1038", "l"),
+    ("1054", "s"),
+    ("1058", "min"),
+    ("1059", "h"),
+    ("1088", "kg"),
+    ("1089", "g"),
+    ("1130", "Pa"),
+    ("1133", "kPa"),
+    ("1137", "bar"),
+    ("1138", "mbar"),
+    ("1342", "%"),
+    ("1347", "m³/s"),
+    ("1349", "m³/h"),
+    ("1351", "l/s"),
+    ("1352", "l/min"),
+    ("1353", "l/h"),
+];
+
+/// Symbol for a unit code; other values are kept as is.
+fn unit_symbol(code: &str) -> String {
+    UNITS
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map_or(code, |(_, symbol)| symbol)
+        .to_string()
+}
+
+/// Unit code for a symbol; symbols without a code are kept as is.
+fn unit_code(symbol: &str) -> String {
+    UNITS
+        .iter()
+        .find(|(_, s)| *s == symbol)
+        .map_or(symbol, |(code, _)| code)
+        .to_string()
+}
+
+/// OPC UA node id of an `OPCUAItem`; the namespace is given by URI, since its
+/// index is only known once connected.
+fn node_address(namespace: Option<&str>, identifier: &str) -> String {
+    if identifier.starts_with("ns=") || identifier.starts_with("nsu=") {
+        return identifier.to_string();
+    }
+    let id = if identifier.parse::<u32>().is_ok() {
+        format!("i={}", identifier)
+    } else if ["i=", "s=", "g=", "b="]
+        .iter()
+        .any(|prefix| identifier.starts_with(prefix))
+    {
+        identifier.to_string()
+    } else {
+        format!("s={}", identifier)
+    };
+    match namespace {
+        Some(uri) => format!("nsu={};{}", uri, id),
+        None => id,
+    }
+}
+
+// ─── Conversion ──────────────────────────────────────────────────────────────
+
+struct Importer<'a> {
+    by_id: HashMap<&'a str, &'a Element>,
+    items: HashMap<&'a str, TagMapping>,
+    warnings: Vec<String>,
+}
+
+impl<'a> Importer<'a> {
+    fn new(elements: &[&'a Element]) -> Self {
+        let by_id = elements
+            .iter()
+            .filter_map(|ie| ie.attr("ID").map(|id| (id, *ie)))
+            .collect();
+        let items = elements
+            .iter()
+            .filter(|ie| class_of(ie) == "OPCUAItem")
+            .filter_map(|ie| {
+                let id = ie.attr("ID")?;
+                let identifier = value(ie, "Identifier")?;
+                let mapping = TagMapping {
+                    protocol: ProtocolType::OpcUa,
+                    address: node_address(value(ie, "Namespace"), identifier),
+                };
+                Some((id, mapping))
+            })
+            .collect();
+        Self {
+            by_id,
+            items,
+            warnings: Vec::new(),
+        }
+    }
+
+    fn build(mut self, document: &'a Element, module: &'a Element) -> MtpImport {
+        let elements = internal_elements(module);
+        let name = module.attr("Name").unwrap_or("Imported PEA").to_string();
+        let now = chrono::Utc::now();
+
+        let server = elements.iter().find(|ie| class_of(ie) == "OPCUAServer");
+        let endpoint = server
+            .and_then(|s| value(s, "Endpoint"))
+            .unwrap_or_default()
+            .to_string();
+        if endpoint.is_empty() {
+            self.warn(format!("PEA '{}' has no OPC UA endpoint", name));
+        }
+        let namespace_uri = elements
+            .iter()
+            .filter(|ie| class_of(ie) == "OPCUAItem")
+            .find_map(|ie| value(ie, "Namespace"))
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("urn:fendtastic:{}", name));
+
+        let services: Vec<ServiceConfig> = elements
+            .iter()
+            .copied()
+            .filter(|ie| class_of(ie) == "Service")
+            .map(|ie| self.service(ie))
+            .collect();
+        if services.is_empty() {
+            self.warn("The package declares no services");
+        }
+        let active_elements = elements
+            .iter()
+            .filter_map(|ie| self.active_element(ie))
+            .collect();
+
+        let config = PeaConfig {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.clone(),
+            version: value(module, "Version").unwrap_or("1.0.0").to_string(),
+            description: value(module, "Description")
+                .map(str::to_string)
+                .unwrap_or_else(|| {
+                    format!("Imported from MTP package on {}", now.format("%Y-%m-%d"))
+                }),
+            writer: writer_info(document),
+            services,
+            active_elements,
+            opcua_config: OpcUaConfig {
+                endpoint,
+                namespace_uri,
+                security_policy: "None".to_string(),
+            },
+            created_at: now,
+            updated_at: now,
+            tenant_id: None,
+            mode: PeaMode::Simulated,
+            archived_at: None,
+        };
+        MtpImport {
+            config,
+            warnings: self.warnings,
+        }
+    }
+
+    fn warn(&mut self, message: impl Into<String>) {
+        self.warnings.push(message.into());
+    }
+
+    /// Data assembly an element points at through `RefID`, or the element
+    /// itself; `None` (with a warning) when the reference is dangling.
+    fn resolve(&mut self, ie: &'a Element) -> Option<&'a Element> {
+        let Some(id) = value(ie, "RefID") else {
+            return Some(ie);
+        };
+        let da = self.by_id.get(id).copied();
+        if da.is_none() {
+            self.warn(format!(
+                "'{}' references unknown data assembly '{}'",
+                ie.attr("Name").unwrap_or_default(),
+                id
+            ));
+        }
+        da
+    }
+
+    /// First of the given dynamic attributes linked to an OPC UA item.
+    fn mapping(&self, da: &Element, attributes: &[&str]) -> Option<TagMapping> {
+        attributes
+            .iter()
+            .find_map(|name| value(da, name).and_then(|id| self.items.get(id)))
+            .cloned()
+    }
+
+    fn service(&mut self, ie: &'a Element) -> ServiceConfig {
+        let control = self.resolve(ie);
+        let tag = ie.attr("Name").unwrap_or_default().to_string();
+        let config_parameters = ie
+            .children_named("InternalElement")
+            .filter(|child| class_of(child) == "ConfigurationParameter")
+            .filter_map(|child| self.parameter(child))
+            .collect();
+        let mut procedures: Vec<ProcedureConfig> = ie
+            .children_named("InternalElement")
+            .filter(|child| class_of(child) == "Procedure")
+            .enumerate()
+            .map(|(idx, child)| self.procedure(child, idx as u32 + 1))
+            .collect();
+        if !procedures.is_empty() && !procedures.iter().any(|p| p.is_default) {
+            procedures[0].is_default = true;
+        }
+        if procedures.is_empty() {
+            self.warn(format!("Service '{}' has no procedures", tag));
+        }
+        ServiceConfig {
+            name: tag.clone(),
+            description: control
+                .and_then(|c| value(c, "TagDescription"))
+                .unwrap_or_default()
+                .to_string(),
+            tag,
+            config_parameters,
+            procedures,
+        }
+    }
+
+    fn procedure(&mut self, ie: &'a Element, fallback_id: u32) -> ProcedureConfig {
+        let mut procedure = ProcedureConfig {
+            id: number(ie, "ProcedureId")
+                .or_else(|| number(ie, "ProcedureID"))
+                .unwrap_or(fallback_id),
+            name: ie.attr("Name").unwrap_or_default().to_string(),
+            is_self_completing: flag(ie, "IsSelfCompleting").unwrap_or(false),
+            is_default: flag(ie, "IsDefault").unwrap_or(false),
+            parameters: Vec::new(),
+            process_value_outs: Vec::new(),
+            report_values: Vec::new(),
+        };
+        for child in ie.children_named("InternalElement") {
+            match class_of(child) {
+                "ProcedureParameter" => {
+                    if let Some(param) = self.parameter(child) {
+                        procedure.parameters.push(param);
+                    }
+                }
+                "ProcessValueOut" => {
+                    if let Some(view) = self.indicator(child) {
+                        procedure.process_value_outs.push(view);
+                    }
+                }
+                "ReportValue" => {
+                    if let Some(view) = self.indicator(child) {
+                        procedure.report_values.push(view);
+                    }
+                }
+                "ProcessValueIn" => self.warn(format!(
+                    "Process value input '{}' of procedure '{}' is not supported",
+                    child.attr("Name").unwrap_or_default(),
+                    procedure.name
+                )),
+                _ => {}
+            }
+        }
+        procedure
+    }
+
+    /// Operation element behind a configuration or procedure parameter. MTP
+    /// carries no default values, so parameters default to their lower limit.
+    fn parameter(&mut self, ie: &'a Element) -> Option<ServiceParameter> {
+        let da = self.resolve(ie)?;
+        let tag_mapping = self.mapping(da, &["VOp", "VExt", "VOut"]);
+        let param = match class_of(da) {
+            "AnaServParam" => {
+                let v_min = number(da, "VMin").unwrap_or(0.0);
+                ServiceParameter::Analog(AnalogParameter {
+                    tag: tag_of(da),
+                    name: name_of(da),
+                    unit: unit_of(da, "VUnit"),
+                    v_scl_min: number(da, "VSclMin").unwrap_or(0.0),
+                    v_scl_max: number(da, "VSclMax").unwrap_or(0.0),
+                    v_min,
+                    v_max: number(da, "VMax").unwrap_or(0.0),
+                    v_default: v_min,
+                    tag_mapping,
+                    display: None,
+                })
+            }
+            "DIntServParam" => {
+                let v_min = number(da, "VMin").unwrap_or(0);
+                ServiceParameter::DInt(DIntParameter {
+                    tag: tag_of(da),
+                    name: name_of(da),
+                    unit: unit_of(da, "VUnit"),
+                    v_scl_min: number(da, "VSclMin").unwrap_or(0),
+                    v_scl_max: number(da, "VSclMax").unwrap_or(0),
+                    v_min,
+                    v_max: number(da, "VMax").unwrap_or(0),
+                    v_default: v_min,
+                    tag_mapping,
+                    display: None,
+                })
+            }
+            "BinServParam" => ServiceParameter::Binary(BinaryParameter {
+                tag: tag_of(da),
+                name: name_of(da),
+                v_state0: value(da, "VState0").unwrap_or("Off").to_string(),
+                v_state1: value(da, "VState1").unwrap_or("On").to_string(),
+                v_default: false,
+                tag_mapping,
+            }),
+            "StringServParam" => ServiceParameter::StringParam(StringParameter {
+                tag: tag_of(da),
+                name: name_of(da),
+                v_default: String::new(),
+                tag_mapping,
+            }),
+            other => {
+                self.warn(format!(
+                    "'{}' is not a service parameter ({}); skipped",
+                    ie.attr("Name").unwrap_or_default(),
+                    other
+                ));
+                return None;
+            }
+        };
+        Some(param)
+    }
+
+    fn indicator(&mut self, ie: &'a Element) -> Option<IndicatorElement> {
+        let da = self.resolve(ie)?;
+        let tag_mapping = self.mapping(da, &["V"]);
+        let view = match class_of(da) {
+            "AnaView" => IndicatorElement::AnaView(AnaViewConfig {
+                tag: tag_of(da),
+                name: name_of(da),
+                unit: unit_of(da, "VUnit"),
+                v_scl_min: number(da, "VSclMin").unwrap_or(0.0),
+                v_scl_max: number(da, "VSclMax").unwrap_or(0.0),
+                tag_mapping,
+            }),
+            "BinView" => IndicatorElement::BinView(BinViewConfig {
+                tag: tag_of(da),
+                name: name_of(da),
+                v_state0: value(da, "VState0").unwrap_or("Off").to_string(),
+                v_state1: value(da, "VState1").unwrap_or("On").to_string(),
+                tag_mapping,
+            }),
+            "BinStringView" => IndicatorElement::BinStringView(BinStringViewConfig {
+                tag: tag_of(da),
+                name: name_of(da),
+                v_state0: value(da, "VState0").unwrap_or("Off").to_string(),
+                v_state1: value(da, "VState1").unwrap_or("On").to_string(),
+                tag_mapping,
+            }),
+            "DIntView" => IndicatorElement::DIntView(DIntViewConfig {
+                tag: tag_of(da),
+                name: name_of(da),
+                unit: unit_of(da, "VUnit"),
+                v_scl_min: number(da, "VSclMin").unwrap_or(0),
+                v_scl_max: number(da, "VSclMax").unwrap_or(0),
+                tag_mapping,
+            }),
+            "DIntStringView" => IndicatorElement::DIntStringView(DIntStringViewConfig {
+                tag: tag_of(da),
+                name: name_of(da),
+                v_scl_min: number(da, "VSclMin").unwrap_or(0),
+                v_scl_max: number(da, "VSclMax").unwrap_or(0),
+                tag_mapping,
+            }),
+            "StringView" => IndicatorElement::StringView(StringViewConfig {
+                tag: tag_of(da),
+                name: name_of(da),
+                tag_mapping,
+            }),
+            other => {
+                self.warn(format!(
+                    "'{}' is not an indicator element ({}); skipped",
+                    ie.attr("Name").unwrap_or_default(),
+                    other
+                ));
+                return None;
+            }
+        };
+        Some(view)
+    }
+
+    fn active_element(&self, da: &Element) -> Option<ActiveElement> {
+        let element = match class_of(da) {
+            "BinVlv" => ActiveElement::BinVlv(BinVlvConfig {
+                tag: tag_of(da),
+                name: name_of(da),
+                safe_pos: flag(da, "SafePos").unwrap_or(false),
+                open_fbk_tag: self.mapping(da, &["OpenFbk", "PosFbk"]),
+                close_fbk_tag: self.mapping(da, &["CloseFbk"]),
+                open_cmd_tag: self.mapping(da, &["OpenOp", "OpenAut"]),
+                close_cmd_tag: self.mapping(da, &["CloseOp", "CloseAut"]),
+            }),
+            "BinMon" => ActiveElement::BinMon(BinMonConfig {
+                tag: tag_of(da),
+                name: name_of(da),
+                fbk_tag: self.mapping(da, &["V"]),
+            }),
+            "AnaVlv" => ActiveElement::AnaVlv(AnaVlvConfig {
+                tag: tag_of(da),
+                name: name_of(da),
+                safe_pos: number(da, "SafePos").unwrap_or(0.0),
+                pos_min: number(da, "PosMin").unwrap_or(0.0),
+                pos_max: number(da, "PosMax").unwrap_or(100.0),
+                pos_unit: unit_of(da, "PosUnit"),
+                pos_fbk_tag: self.mapping(da, &["PosFbk", "Pos"]),
+                pos_sp_tag: self.mapping(da, &["PosMan", "PosInt"]),
+            }),
+            "BinDrv" => ActiveElement::BinDrv(BinDrvConfig {
+                tag: tag_of(da),
+                name: name_of(da),
+                safe_pos: flag(da, "SafePos").unwrap_or(false),
+                fwd_fbk_tag: self.mapping(da, &["FwdFbk"]),
+                rev_fbk_tag: self.mapping(da, &["RevFbk"]),
+                fwd_cmd_tag: self.mapping(da, &["FwdOp", "FwdAut"]),
+                rev_cmd_tag: self.mapping(da, &["RevOp", "RevAut"]),
+                stop_cmd_tag: self.mapping(da, &["StopOp", "StopAut"]),
+            }),
+            "AnaDrv" => ActiveElement::AnaDrv(AnaDrvConfig {
+                tag: tag_of(da),
+                name: name_of(da),
+                safe_pos: number(da, "SafePos").unwrap_or(0.0),
+                rpm_min: number(da, "RpmMin").unwrap_or(0.0),
+                rpm_max: number(da, "RpmMax").unwrap_or(0.0),
+                rpm_unit: unit_of(da, "RpmUnit"),
+                rpm_fbk_tag: self.mapping(da, &["RpmFbk", "Rpm"]),
+                rpm_sp_tag: self.mapping(da, &["RpmMan", "RpmInt"]),
+                fwd_cmd_tag: self.mapping(da, &["FwdOp", "FwdAut"]),
+                rev_cmd_tag: self.mapping(da, &["RevOp", "RevAut"]),
+                stop_cmd_tag: self.mapping(da, &["StopOp", "StopAut"]),
+            }),
+            "DIntMon" => ActiveElement::DIntMon(DIntMonConfig {
+                tag: tag_of(da),
+                name: name_of(da),
+                unit: unit_of(da, "VUnit"),
+                v_scl_min: number(da, "VSclMin").unwrap_or(0),
+                v_scl_max: number(da, "VSclMax").unwrap_or(0),
+                fbk_tag: self.mapping(da, &["V"]),
+            }),
+            "PIDCtrl" => {
+                // MTP gives gain and reset/derivative times; convert to parallel gains.
+                let kp = number(da, "P").unwrap_or(1.0);
+                let ti: f64 = number(da, "Ti").unwrap_or(0.0);
+                let td: f64 = number(da, "Td").unwrap_or(0.0);
+                ActiveElement::PIDCtrl(PIDCtrlConfig {
+                    tag: tag_of(da),
+                    name: name_of(da),
+                    kp,
+                    ki: if ti > 0.0 { kp / ti } else { 0.0 },
+                    kd: kp * td,
+                    pv_unit: unit_of(da, "PVUnit"),
+                    pv_scl_min: number(da, "PVSclMin").unwrap_or(0.0),
+                    pv_scl_max: number(da, "PVSclMax").unwrap_or(0.0),
+                    sp_scl_min: number(da, "SPSclMin").unwrap_or(0.0),
+                    sp_scl_max: number(da, "SPSclMax").unwrap_or(0.0),
+                    mv_scl_min: number(da, "MVSclMin").unwrap_or(0.0),
+                    mv_scl_max: number(da, "MVSclMax").unwrap_or(0.0),
+                    pv_tag: self.mapping(da, &["PV"]),
+                    sp_tag: self.mapping(da, &["SPMan", "SP"]),
+                    mv_tag: self.mapping(da, &["MV"]),
+                })
+            }
+            _ => return None,
+        };
+        Some(element)
+    }
+}
+
+/// Writer of the document: CAEX 3.0 `SourceDocumentInformation`, or the
+/// CAEX 2.15 `WriterHeader`.
+fn writer_info(document: &Element) -> WriterInfo {
+    if let Some(info) = document.child("SourceDocumentInformation") {
+        return WriterInfo {
+            name: info.attr("OriginName").unwrap_or("mtp-import").to_string(),
+            version: info.attr("OriginVersion").unwrap_or_default().to_string(),
+            vendor: info.attr("OriginVendor").unwrap_or_default().to_string(),
+        };
+    }
+    let header = document
+        .children_named("AdditionalInformation")
+        .find_map(|info| info.child("WriterHeader"));
+    let field = |name: &str| {
+        header
+            .and_then(|h| h.child(name))
+            .map(|e| e.text.trim().to_string())
+            .unwrap_or_default()
+    };
+    let name = field("WriterName");
+    WriterInfo {
+        name: if name.is_empty() {
+            "mtp-import".to_string()
+        } else {
+            name
+        },
+        version: field("WriterVersion"),
+        vendor: field("WriterVendor"),
+    }
+}
+
+// ─── Export ──────────────────────────────────────────────────────────────────
+
+const MODULE_TYPE_PACKAGE: &str = "MTPSUCLib/ModuleTypePackage";
+const COMMUNICATION_SET: &str = "MTPSUCLib/CommunicationSet";
+const INSTANCE_LIST: &str = "MTPSUCLib/CommunicationSet/InstanceList";
+const SOURCE_LIST: &str = "MTPSUCLib/CommunicationSet/SourceList";
+const OPCUA_SERVER: &str = "MTPCommunicationSUCLib/ServerAssembly/OPCUAServer";
+const OPCUA_ITEM: &str = "MTPCommunicationSUCLib/ServerAssembly/OPCUAItem";
+const DATA_ASSEMBLY: &str = "MTPDataObjectSUCLib/DataAssembly";
+const SERVICE_SET: &str = "MTPServiceSUCLib/ServiceSet";
+const SERVICE: &str = "MTPServiceSUCLib/Service";
+const PROCEDURE: &str = "MTPServiceSUCLib/Procedure";
+const SERVICE_ELEMENT: &str = "MTPServiceSUCLib/ServiceElement";
+
+/// An MTP package built from a PEA config, with what could not be carried over.
+#[derive(Debug, Clone)]
+pub struct MtpExport {
+    /// Suggested name of the package file, e.g. `Dosing.mtp`.
+    pub file_name: String,
+    pub package: Vec<u8>,
+    pub warnings: Vec<String>,
+}
+
+/// Serializes a PEA config into an MTP package that [`import_package`] reads
+/// back. Parameter defaults, the PEA mode and the tenant have no place in an
+/// MTP and are dropped; tag mappings other than OPC UA are skipped.
+pub fn export_package(config: &PeaConfig) -> Result<MtpExport, AmlError> {
+    let stem = file_stem(&config.name);
+    let root = format!("{}.aml", stem);
+    let mut exporter = Exporter::default();
+    for service in &config.services {
+        exporter.service(service);
+    }
+    for element in &config.active_elements {
+        exporter.active_element(element);
+    }
+    let document = exporter.document(config, &root);
+    Ok(MtpExport {
+        file_name: format!("{}.mtp", stem),
+        package: write_package(&root, &document)?,
+        warnings: exporter.warnings,
+    })
+}
+
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if stem.is_empty() {
+        "pea".to_string()
+    } else {
+        stem
+    }
+}
+
+/// Zips the root document with the container parts naming it.
+fn write_package(root: &str, document: &str) -> Result<Vec<u8>, AmlError> {
+    let package_error = |e: zip::result::ZipError| AmlError::Package(e.to_string());
+    let relationships = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Type="http://schemas.automationml.org/container/relationship/{}" Target="/{}" Id="R1" />
+</Relationships>
+"#,
+        ROOT_DOCUMENT_RELATIONSHIP,
+        quick_xml::escape::escape(root)
+    );
+    let content_types = r#"<?xml version="1.0" encoding="utf-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+  <Default Extension="aml" ContentType="model/vnd.automationml+xml" />
+  <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml" />
+</Types>
+"#;
+
+    let mut package = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for (name, content) in [
+        ("[Content_Types].xml", content_types),
+        ("_rels/.rels", relationships.as_str()),
+        (root, document),
+    ] {
+        package.start_file(name, options).map_err(package_error)?;
+        package
+            .write_all(content.as_bytes())
+            .map_err(|e| AmlError::Package(e.to_string()))?;
+    }
+    Ok(package.finish().map_err(package_error)?.into_inner())
+}
+
+/// Indenting writer for CAEX elements.
+#[derive(Default)]
+struct Xml {
+    out: String,
+    depth: usize,
+}
+
+impl Xml {
+    fn at(depth: usize) -> Self {
+        Self {
+            out: String::new(),
+            depth,
+        }
+    }
+
+    fn open(&mut self, name: &str, attributes: &[(&str, &str)]) {
+        self.start(name, attributes, ">");
+        self.depth += 1;
+    }
+
+    fn empty(&mut self, name: &str, attributes: &[(&str, &str)]) {
+        self.start(name, attributes, " />");
+    }
+
+    fn close(&mut self, name: &str) {
+        self.depth -= 1;
+        self.indent();
+        self.out.push_str(&format!("</{}>\n", name));
+    }
+
+    fn start(&mut self, name: &str, attributes: &[(&str, &str)], end: &str) {
+        self.indent();
+        self.out.push('<');
+        self.out.push_str(name);
+        for (key, value) in attributes {
+            let value = quick_xml::escape::escape(*value);
+            self.out.push_str(&format!(" {}=\"{}\"", key, value));
+        }
+        self.out.push_str(end);
+        self.out.push('\n');
+    }
+
+    fn indent(&mut self) {
+        self.out.push_str(&"  ".repeat(self.depth));
+    }
+
+    /// CAEX `<Attribute>` with a value; empty values are left out.
+    fn attribute(&mut self, name: &str, data_type: &str, value: &str) {
+        if value.is_empty() {
+            return;
+        }
+        self.indent();
+        self.out.push_str(&format!(
+            "<Attribute Name=\"{}\" AttributeDataType=\"{}\"><Value>{}</Value></Attribute>\n",
+            name,
+            data_type,
+            quick_xml::escape::escape(value)
+        ));
+    }
+
+    fn internal_element(&mut self, name: &str, id: &str, class: &str) {
+        self.open(
+            "InternalElement",
+            &[("Name", name), ("ID", id), ("RefBaseSystemUnitPath", class)],
+        );
+    }
+
+    /// Service element pointing at a data assembly through `RefID`.
+    fn reference(&mut self, class: &str, (name, id): &(String, String)) {
+        let class = format!("{}/{}", SERVICE_ELEMENT, class);
+        self.internal_element(name, &new_id(), &class);
+        self.attribute("RefID", "xs:IDREF", id);
+        self.close("InternalElement");
+    }
+}
+
+fn new_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// Data assembly of the instance list.
+struct Assembly {
+    id: String,
+    tag: String,
+    class: String,
+    attributes: Vec<(&'static str, &'static str, String)>,
+}
+
+impl Assembly {
+    fn new(group: &str, class: &str, tag: &str, description: &str) -> Self {
+        let class = if group.is_empty() {
+            format!("{}/{}", DATA_ASSEMBLY, class)
+        } else {
+            format!("{}/{}/{}", DATA_ASSEMBLY, group, class)
+        };
+        Self {
+            id: new_id(),
+            tag: tag.to_string(),
+            class,
+            attributes: Vec::new(),
+        }
+        .attr("TagName", "xs:string", tag)
+        .attr("TagDescription", "xs:string", description)
+    }
+
+    fn attr(mut self, name: &'static str, data_type: &'static str, value: impl ToString) -> Self {
+        self.attributes.push((name, data_type, value.to_string()));
+        self
+    }
+
+    fn unit(self, name: &'static str, symbol: &str) -> Self {
+        let code = unit_code(symbol);
+        let data_type = if code.parse::<u32>().is_ok() {
+            "xs:unsignedInt"
+        } else {
+            "xs:string"
+        };
+        self.attr(name, data_type, code)
+    }
+}
+
+/// OPC UA item of the source list.
+struct Item {
+    id: String,
+    name: String,
+    namespace: Option<String>,
+    identifier: String,
+}
+
+struct Exporter {
+    assemblies: Vec<Assembly>,
+    items: Vec<Item>,
+    /// Service set content, written while walking the services.
+    services: Xml,
+    warnings: Vec<String>,
+}
+
+impl Default for Exporter {
+    fn default() -> Self {
+        Self {
+            assemblies: Vec::new(),
+            items: Vec::new(),
+            // CAEXFile > InstanceHierarchy > ModuleTypePackage > ServiceSet
+            services: Xml::at(4),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl Exporter {
+    /// Adds the assembly, with an OPC UA item for each mapped attribute.
+    fn push(
+        &mut self,
+        mut assembly: Assembly,
+        links: &[(&'static str, &Option<TagMapping>)],
+    ) -> String {
+        for &(attribute, mapping) in links {
+            let Some(mapping) = mapping else { continue };
+            if mapping.protocol != ProtocolType::OpcUa {
+                self.warnings.push(format!(
+                    "Tag mapping {}.{} uses {:?}; only OPC UA items are exported",
+                    assembly.tag, attribute, mapping.protocol
+                ));
+                continue;
+            }
+            // `nsu=<uri>;<id>` splits into the item's namespace and identifier.
+            let (namespace, identifier) = match mapping.address.strip_prefix("nsu=") {
+                Some(rest) => match rest.split_once(';') {
+                    Some((uri, id)) => (Some(uri.to_string()), id.to_string()),
+                    None => (None, mapping.address.clone()),
+                },
+                None => (None, mapping.address.clone()),
+            };
+            let item = Item {
+                id: new_id(),
+                name: format!("{}.{}", assembly.tag, attribute),
+                namespace,
+                identifier,
+            };
+            assembly = assembly.attr(attribute, "xs:IDREF", &item.id);
+            self.items.push(item);
+        }
+        let id = assembly.id.clone();
+        self.assemblies.push(assembly);
+        id
+    }
+
+    fn service(&mut self, service: &ServiceConfig) {
+        let control = self.push(
+            Assembly::new("", "ServiceControl", &service.tag, &service.description),
+            &[],
+        );
+        let config_parameters: Vec<(String, String)> = service
+            .config_parameters
+            .iter()
+            .map(|param| self.parameter(param))
+            .collect();
+        self.services
+            .internal_element(&service.tag, &new_id(), SERVICE);
+        self.services.attribute("RefID", "xs:IDREF", &control);
+        for param in &config_parameters {
+            self.services.reference("ConfigurationParameter", param);
+        }
+        for procedure in &service.procedures {
+            self.procedure(procedure);
+        }
+        self.services.close("InternalElement");
+    }
+
+    fn procedure(&mut self, procedure: &ProcedureConfig) {
+        let parameters: Vec<(String, String)> = procedure
+            .parameters
+            .iter()
+            .map(|param| self.parameter(param))
+            .collect();
+        let process_value_outs: Vec<(String, String)> = procedure
+            .process_value_outs
+            .iter()
+            .map(|view| self.indicator(view))
+            .collect();
+        let report_values: Vec<(String, String)> = procedure
+            .report_values
+            .iter()
+            .map(|view| self.indicator(view))
+            .collect();
+
+        let xml = &mut self.services;
+        xml.internal_element(&procedure.name, &new_id(), PROCEDURE);
+        xml.attribute("ProcedureId", "xs:unsignedInt", &procedure.id.to_string());
+        xml.attribute(
+            "IsSelfCompleting",
+            "xs:boolean",
+            &procedure.is_self_completing.to_string(),
+        );
+        xml.attribute("IsDefault", "xs:boolean", &procedure.is_default.to_string());
+        for param in &parameters {
+            xml.reference("ProcedureParameter", param);
+        }
+        for view in &process_value_outs {
+            xml.reference("ProcessValueOut", view);
+        }
+        for view in &report_values {
+            xml.reference("ReportValue", view);
+        }
+        xml.close("InternalElement");
+    }
+
+    /// Adds the operation element of a parameter; returns its tag and id.
+    fn parameter(&mut self, param: &ServiceParameter) -> (String, String) {
+        const GROUP: &str = "OperationElement/ServParam";
+        let (tag, assembly, mapping) = match param {
+            ServiceParameter::Analog(p) => (
+                &p.tag,
+                Assembly::new(GROUP, "AnaServParam", &p.tag, &p.name)
+                    .unit("VUnit", &p.unit)
+                    .attr("VSclMin", "xs:float", p.v_scl_min)
+                    .attr("VSclMax", "xs:float", p.v_scl_max)
+                    .attr("VMin", "xs:float", p.v_min)
+                    .attr("VMax", "xs:float", p.v_max),
+                &p.tag_mapping,
+            ),
+            ServiceParameter::DInt(p) => (
+                &p.tag,
+                Assembly::new(GROUP, "DIntServParam", &p.tag, &p.name)
+                    .unit("VUnit", &p.unit)
+                    .attr("VSclMin", "xs:int", p.v_scl_min)
+                    .attr("VSclMax", "xs:int", p.v_scl_max)
+                    .attr("VMin", "xs:int", p.v_min)
+                    .attr("VMax", "xs:int", p.v_max),
+                &p.tag_mapping,
+            ),
+            ServiceParameter::Binary(p) => (
+                &p.tag,
+                Assembly::new(GROUP, "BinServParam", &p.tag, &p.name)
+                    .attr("VState0", "xs:string", &p.v_state0)
+                    .attr("VState1", "xs:string", &p.v_state1),
+                &p.tag_mapping,
+            ),
+            ServiceParameter::StringParam(p) => (
+                &p.tag,
+                Assembly::new(GROUP, "StringServParam", &p.tag, &p.name),
+                &p.tag_mapping,
+            ),
+        };
+        let id = self.push(assembly, &[("VOp", mapping)]);
+        (tag.clone(), id)
+    }
+
+    /// Adds the indicator element of a view; returns its tag and id.
+    fn indicator(&mut self, view: &IndicatorElement) -> (String, String) {
+        const GROUP: &str = "IndicatorElement";
+        let (tag, assembly, mapping) = match view {
+            IndicatorElement::AnaView(v) => (
+                &v.tag,
+                Assembly::new(GROUP, "AnaView", &v.tag, &v.name)
+                    .unit("VUnit", &v.unit)
+                    .attr("VSclMin", "xs:float", v.v_scl_min)
+                    .attr("VSclMax", "xs:float", v.v_scl_max),
+                &v.tag_mapping,
+            ),
+            IndicatorElement::BinView(v) => (
+                &v.tag,
+                Assembly::new(GROUP, "BinView", &v.tag, &v.name)
+                    .attr("VState0", "xs:string", &v.v_state0)
+                    .attr("VState1", "xs:string", &v.v_state1),
+                &v.tag_mapping,
+            ),
+            IndicatorElement::BinStringView(v) => (
+                &v.tag,
+                Assembly::new(GROUP, "BinStringView", &v.tag, &v.name)
+                    .attr("VState0", "xs:string", &v.v_state0)
+                    .attr("VState1", "xs:string", &v.v_state1),
+                &v.tag_mapping,
+            ),
+            IndicatorElement::DIntView(v) => (
+                &v.tag,
+                Assembly::new(GROUP, "DIntView", &v.tag, &v.name)
+                    .unit("VUnit", &v.unit)
+                    .attr("VSclMin", "xs:int", v.v_scl_min)
+                    .attr("VSclMax", "xs:int", v.v_scl_max),
+                &v.tag_mapping,
+            ),
+            IndicatorElement::DIntStringView(v) => (
+                &v.tag,
+                Assembly::new(GROUP, "DIntStringView", &v.tag, &v.name)
+                    .attr("VSclMin", "xs:int", v.v_scl_min)
+                    .attr("VSclMax", "xs:int", v.v_scl_max),
+                &v.tag_mapping,
+            ),
+            IndicatorElement::StringView(v) => (
+                &v.tag,
+                Assembly::new(GROUP, "StringView", &v.tag, &v.name),
+                &v.tag_mapping,
+            ),
+        };
+        let id = self.push(assembly, &[("V", mapping)]);
+        (tag.clone(), id)
+    }
+
+    /// Adds an active element, linking each mapping through the attribute
+    /// the importer reads first.
+    fn active_element(&mut self, element: &ActiveElement) {
+        const GROUP: &str = "ActiveElement";
+        match element {
+            ActiveElement::BinVlv(e) => {
+                let assembly = Assembly::new(GROUP, "BinVlv", &e.tag, &e.name).attr(
+                    "SafePos",
+                    "xs:boolean",
+                    e.safe_pos,
+                );
+                let links = [
+                    ("OpenFbk", &e.open_fbk_tag),
+                    ("CloseFbk", &e.close_fbk_tag),
+                    ("OpenOp", &e.open_cmd_tag),
+                    ("CloseOp", &e.close_cmd_tag),
+                ];
+                self.push(assembly, &links);
+            }
+            ActiveElement::BinMon(e) => {
+                let assembly = Assembly::new("IndicatorElement", "BinMon", &e.tag, &e.name);
+                self.push(assembly, &[("V", &e.fbk_tag)]);
+            }
+            ActiveElement::AnaVlv(e) => {
+                let assembly = Assembly::new(GROUP, "AnaVlv", &e.tag, &e.name)
+                    .attr("SafePos", "xs:float", e.safe_pos)
+                    .attr("PosMin", "xs:float", e.pos_min)
+                    .attr("PosMax", "xs:float", e.pos_max)
+                    .unit("PosUnit", &e.pos_unit);
+                let links = [("PosFbk", &e.pos_fbk_tag), ("PosMan", &e.pos_sp_tag)];
+                self.push(assembly, &links);
+            }
+            ActiveElement::BinDrv(e) => {
+                let assembly = Assembly::new(GROUP, "BinDrv", &e.tag, &e.name).attr(
+                    "SafePos",
+                    "xs:boolean",
+                    e.safe_pos,
+                );
+                let links = [
+                    ("FwdFbk", &e.fwd_fbk_tag),
+                    ("RevFbk", &e.rev_fbk_tag),
+                    ("FwdOp", &e.fwd_cmd_tag),
+                    ("RevOp", &e.rev_cmd_tag),
+                    ("StopOp", &e.stop_cmd_tag),
+                ];
+                self.push(assembly, &links);
+            }
+            ActiveElement::AnaDrv(e) => {
+                let assembly = Assembly::new(GROUP, "AnaDrv", &e.tag, &e.name)
+                    .attr("SafePos", "xs:float", e.safe_pos)
+                    .attr("RpmMin", "xs:float", e.rpm_min)
+                    .attr("RpmMax", "xs:float", e.rpm_max)
+                    .unit("RpmUnit", &e.rpm_unit);
+                let links = [
+                    ("RpmFbk", &e.rpm_fbk_tag),
+                    ("RpmMan", &e.rpm_sp_tag),
+                    ("FwdOp", &e.fwd_cmd_tag),
+                    ("RevOp", &e.rev_cmd_tag),
+                    ("StopOp", &e.stop_cmd_tag),
+                ];
+                self.push(assembly, &links);
+            }
+            ActiveElement::DIntDrv(e) => self.warnings.push(format!(
+                "Drive '{}' is a DIntDrv, which has no MTP counterpart; skipped",
+                e.tag
+            )),
+            ActiveElement::DIntMon(e) => {
+                let assembly = Assembly::new("IndicatorElement", "DIntMon", &e.tag, &e.name)
+                    .unit("VUnit", &e.unit)
+                    .attr("VSclMin", "xs:int", e.v_scl_min)
+                    .attr("VSclMax", "xs:int", e.v_scl_max);
+                self.push(assembly, &[("V", &e.fbk_tag)]);
+            }
+            ActiveElement::PIDCtrl(e) => {
+                // Back from parallel gains to gain and reset/derivative times.
+                let ti = if e.ki != 0.0 { e.kp / e.ki } else { 0.0 };
+                let td = if e.kp != 0.0 { e.kd / e.kp } else { 0.0 };
+                let assembly = Assembly::new(GROUP, "PIDCtrl", &e.tag, &e.name)
+                    .attr("P", "xs:float", e.kp)
+                    .attr("Ti", "xs:float", ti)
+                    .attr("Td", "xs:float", td)
+                    .unit("PVUnit", &e.pv_unit)
+                    .attr("PVSclMin", "xs:float", e.pv_scl_min)
+                    .attr("PVSclMax", "xs:float", e.pv_scl_max)
+                    .attr("SPSclMin", "xs:float", e.sp_scl_min)
+                    .attr("SPSclMax", "xs:float", e.sp_scl_max)
+                    .attr("MVSclMin", "xs:float", e.mv_scl_min)
+                    .attr("MVSclMax", "xs:float", e.mv_scl_max);
+                let links = [("PV", &e.pv_tag), ("SPMan", &e.sp_tag), ("MV", &e.mv_tag)];
+                self.push(assembly, &links);
+            }
+        }
+    }
+
+    fn document(&self, config: &PeaConfig, file_name: &str) -> String {
+        let mut xml = Xml::default();
+        xml.out
+            .push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.open(
+            "CAEXFile",
+            &[
+                ("FileName", file_name),
+                ("SchemaVersion", "3.0"),
+                ("xmlns", "http://www.dke.de/CAEX"),
+            ],
+        );
+        xml.empty(
+            "SourceDocumentInformation",
+            &[
+                ("OriginName", config.writer.name.as_str()),
+                ("OriginVersion", config.writer.version.as_str()),
+                ("OriginVendor", config.writer.vendor.as_str()),
+                ("OriginID", config.id.as_str()),
+                ("OriginRelease", config.version.as_str()),
+                (
+                    "LastWritingDateTime",
+                    config.updated_at.to_rfc3339().as_str(),
+                ),
+            ],
+        );
+        xml.open("InstanceHierarchy", &[("Name", "ModuleTypePackage")]);
+        xml.internal_element(&config.name, &new_id(), MODULE_TYPE_PACKAGE);
+        xml.attribute("Version", "xs:string", &config.version);
+        xml.attribute("Description", "xs:string", &config.description);
+
+        xml.internal_element("CommunicationSet", &new_id(), COMMUNICATION_SET);
+        xml.internal_element("InstanceList", &new_id(), INSTANCE_LIST);
+        for assembly in &self.assemblies {
+            xml.internal_element(&assembly.tag, &assembly.id, &assembly.class);
+            for (name, data_type, value) in &assembly.attributes {
+                xml.attribute(name, data_type, value);
+            }
+            xml.close("InternalElement");
+        }
+        xml.close("InternalElement");
+        xml.internal_element("SourceList", &new_id(), SOURCE_LIST);
+        xml.internal_element(
+            &format!("{}Server", file_stem(&config.name)),
+            &new_id(),
+            OPCUA_SERVER,
+        );
+        xml.attribute("Endpoint", "xs:string", &config.opcua_config.endpoint);
+        for item in &self.items {
+            xml.internal_element(&item.name, &item.id, OPCUA_ITEM);
+            xml.attribute("Identifier", "xs:string", &item.identifier);
+            if let Some(namespace) = &item.namespace {
+                xml.attribute("Namespace", "xs:string", namespace);
+            }
+            xml.close("InternalElement");
+        }
+        xml.close("InternalElement");
+        xml.close("InternalElement");
+        xml.close("InternalElement");
+
+        xml.internal_element("Services", &new_id(), SERVICE_SET);
+        xml.out.push_str(&self.services.out);
+        xml.close("InternalElement");
+        xml.close("InternalElement");
+        xml.close("InstanceHierarchy");
+        xml.close("CAEXFile");
+        xml.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEA_AML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<CAEXFile FileName="Dosing.aml" SchemaVersion="3.0" xmlns="http://www.dke.de/CAEX">
+  <SourceDocumentInformation OriginName="PEA Designer" OriginVersion="2.1" OriginVendor="Acme" OriginID="x" OriginRelease="1" LastWritingDateTime="2024-01-01T00:00:00" />
+  <InstanceHierarchy Name="ModuleTypePackage">
+    <InternalElement Name="Dosing" ID="m1" RefBaseSystemUnitPath="MTPSUCLib/ModuleTypePackage">
+      <Attribute Name="Version"><Value>1.2.0</Value></Attribute>
+      <InternalElement Name="CommunicationSet" ID="cs" RefBaseSystemUnitPath="MTPSUCLib/CommunicationSet">
+        <InternalElement Name="InstanceList" ID="il" RefBaseSystemUnitPath="MTPSUCLib/CommunicationSet/InstanceList">
+          <InternalElement Name="DoseControl" ID="sc1" RefBaseSystemUnitPath="MTPDataObjectSUCLib/DataAssembly/ServiceControl">
+            <Attribute Name="TagDescription"><Value>Dose a quantity</Value></Attribute>
+          </InternalElement>
+          <InternalElement Name="Quantity" ID="p1" RefBaseSystemUnitPath="MTPDataObjectSUCLib/DataAssembly/OperationElement/ServParam/AnaServParam">
+            <Attribute Name="TagName"><Value>FIC100_SP</Value></Attribute>
+            <Attribute Name="TagDescription"><Value>Dosing quantity</Value></Attribute>
+            <Attribute Name="VUnit"><Value>1038</Value></Attribute>
+            <Attribute Name="VSclMin"><Value>0</Value></Attribute>
+            <Attribute Name="VSclMax"><Value>100</Value></Attribute>
+            <Attribute Name="VMin"><Value>5</Value></Attribute>
+            <Attribute Name="VMax"><Value>80</Value></Attribute>
+            <Attribute Name="VOp"><Value>i1</Value></Attribute>
+          </InternalElement>
+          <InternalElement Name="Flow" ID="v1" RefBaseSystemUnitPath="MTPDataObjectSUCLib/DataAssembly/IndicatorElement/AnaView">
+            <Attribute Name="VUnit"><Value>1352</Value></Attribute>
+            <Attribute Name="V"><Value>i2</Value></Attribute>
+          </InternalElement>
+          <InternalElement Name="V100" ID="bv1" RefBaseSystemUnitPath="MTPDataObjectSUCLib/DataAssembly/ActiveElement/BinVlv">
+            <Attribute Name="SafePos"><Value>false</Value></Attribute>
+            <Attribute Name="OpenFbk"><Value>i3</Value></Attribute>
+          </InternalElement>
+        </InternalElement>
+        <InternalElement Name="SourceList" ID="sl" RefBaseSystemUnitPath="MTPSUCLib/CommunicationSet/SourceList">
+          <InternalElement Name="Server" ID="s1" RefBaseSystemUnitPath="MTPCommunicationSUCLib/ServerAssembly/OPCUAServer">
+            <Attribute Name="Endpoint"><Value>opc.tcp://dosing:4840</Value></Attribute>
+            <InternalElement Name="QuantityOp" ID="i1" RefBaseSystemUnitPath="MTPCommunicationSUCLib/ServerAssembly/OPCUAItem">
+              <Attribute Name="Identifier"><Value>Dosing.FIC100.VOp</Value></Attribute>
+              <Attribute Name="Namespace"><Value>urn:acme:dosing</Value></Attribute>
+            </InternalElement>
+            <InternalElement Name="FlowV" ID="i2" RefBaseSystemUnitPath="MTPCommunicationSUCLib/ServerAssembly/OPCUAItem">
+              <Attribute Name="Identifier"><Value>Dosing.FI100.V</Value></Attribute>
+              <Attribute Name="Namespace"><Value>urn:acme:dosing</Value></Attribute>
+            </InternalElement>
+            <InternalElement Name="ValveOpen" ID="i3" RefBaseSystemUnitPath="MTPCommunicationSUCLib/ServerAssembly/OPCUAItem">
+              <Attribute Name="Identifier"><Value>6021</Value></Attribute>
+              <Attribute Name="Namespace"><Value>urn:acme:dosing</Value></Attribute>
+            </InternalElement>
+          </InternalElement>
+        </InternalElement>
+      </InternalElement>
+      <InternalElement Name="Services" ID="ss" RefBaseSystemUnitPath="MTPServiceSUCLib/ServiceSet">
+        <InternalElement Name="Dose" ID="svc1" RefBaseSystemUnitPath="MTPServiceSUCLib/Service">
+          <Attribute Name="RefID"><Value>sc1</Value></Attribute>
+          <InternalElement Name="Quantity" ID="pr1" RefBaseSystemUnitPath="MTPServiceSUCLib/Procedure">
+            <Attribute Name="ProcedureId"><Value>3</Value></Attribute>
+            <Attribute Name="IsSelfCompleting"><Value>true</Value></Attribute>
+            <InternalElement Name="Quantity" ID="pp1" RefBaseSystemUnitPath="MTPServiceSUCLib/ServiceElement/ProcedureParameter">
+              <Attribute Name="RefID"><Value>p1</Value></Attribute>
+            </InternalElement>
+            <InternalElement Name="Flow" ID="pv1" RefBaseSystemUnitPath="MTPServiceSUCLib/ServiceElement/ProcessValueOut">
+              <Attribute Name="RefID"><Value>v1</Value></Attribute>
+            </InternalElement>
+            <InternalElement Name="Total" ID="rv1" RefBaseSystemUnitPath="MTPServiceSUCLib/ServiceElement/ReportValue">
+              <Attribute Name="RefID"><Value>missing</Value></Attribute>
+            </InternalElement>
+          </InternalElement>
+        </InternalElement>
+      </InternalElement>
+    </InternalElement>
+  </InstanceHierarchy>
+</CAEXFile>"#;
+
+    #[test]
+    fn imports_services_parameters_and_tag_mappings() {
+        let import = import_package(PEA_AML.as_bytes()).unwrap();
+        let config = &import.config;
+        assert_eq!(config.name, "Dosing");
+        assert_eq!(config.version, "1.2.0");
+        assert_eq!(config.writer.vendor, "Acme");
+        assert_eq!(config.opcua_config.endpoint, "opc.tcp://dosing:4840");
+        assert_eq!(config.opcua_config.namespace_uri, "urn:acme:dosing");
+
+        let service = &config.services[0];
+        assert_eq!(service.tag, "Dose");
+        assert_eq!(service.description, "Dose a quantity");
+        let procedure = &service.procedures[0];
+        assert_eq!(procedure.id, 3);
+        assert!(procedure.is_self_completing && procedure.is_default);
+
+        let ServiceParameter::Analog(param) = &procedure.parameters[0] else {
+            panic!("expected an analog parameter");
+        };
+        assert_eq!(param.tag, "FIC100_SP");
+        assert_eq!(param.unit, "l");
+        assert_eq!(
+            (param.v_min, param.v_max, param.v_default),
+            (5.0, 80.0, 5.0)
+        );
+        assert_eq!(
+            param.tag_mapping.as_ref().unwrap().address,
+            "nsu=urn:acme:dosing;s=Dosing.FIC100.VOp"
+        );
+        let IndicatorElement::AnaView(view) = &procedure.process_value_outs[0] else {
+            panic!("expected an analog view");
+        };
+        assert_eq!(view.unit, "l/min");
+        assert!(view.tag_mapping.is_some());
+
+        let ActiveElement::BinVlv(valve) = &config.active_elements[0] else {
+            panic!("expected a binary valve");
+        };
+        assert_eq!(
+            valve.open_fbk_tag.as_ref().unwrap().address,
+            "nsu=urn:acme:dosing;i=6021"
+        );
+
+        // The report value points at a data assembly that does not exist.
+        assert!(procedure.report_values.is_empty());
+        assert_eq!(import.warnings.len(), 1);
+        assert!(import.warnings[0].contains("missing"));
+    }
+
+    #[test]
+    fn reads_the_root_document_of_a_package() {
+        let mut package = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        package
+            .start_file("Libraries/MTPSUCLib.aml", options)
+            .unwrap();
+        package
+            .write_all(br#"<CAEXFile FileName="MTPSUCLib.aml" />"#)
+            .unwrap();
+        package.start_file("Dosing.aml", options).unwrap();
+        package.write_all(PEA_AML.as_bytes()).unwrap();
+        package.start_file("_rels/.rels", options).unwrap();
+        package
+            .write_all(
+                br#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Type="http://schemas.automationml.org/container/relationship/RootDocument" Target="/Dosing.aml" Id="R1" />
+</Relationships>"#,
+            )
+            .unwrap();
+        let bytes = package.finish().unwrap().into_inner();
+
+        let import = import_package(&bytes).unwrap();
+        assert_eq!(import.config.name, "Dosing");
+        assert_eq!(import.config.services.len(), 1);
+    }
+
+    #[test]
+    fn oversized_entries_are_refused() {
+        assert_eq!(
+            read_entry(&b"<CAEXFile />"[..], "a.aml", 12).unwrap().len(),
+            12
+        );
+        let err = read_entry(&b"<CAEXFile />"[..], "a.aml", 11).unwrap_err();
+        assert!(err.to_string().contains("a.aml is larger than 11 bytes"));
+    }
+
+    #[test]
+    fn rejects_documents_without_a_module() {
+        let err = import_package(br#"<CAEXFile FileName="Empty.aml" />"#).unwrap_err();
+        assert!(matches!(err, AmlError::NoModuleTypePackage));
+        let err = import_package(b"<Recipe />").unwrap_err();
+        assert!(matches!(err, AmlError::NotCaex(_)));
+    }
+
+    #[test]
+    fn exported_packages_import_back() {
+        let mut config = import_package(PEA_AML.as_bytes()).unwrap().config;
+        let ServiceParameter::Analog(param) = &mut config.services[0].procedures[0].parameters[0]
+        else {
+            panic!("expected an analog parameter");
+        };
+        param.tag_mapping = Some(TagMapping {
+            protocol: ProtocolType::Zenoh,
+            address: "dosing/fic100/sp".to_string(),
+        });
+
+        let export = export_package(&config).unwrap();
+        assert_eq!(export.file_name, "Dosing.mtp");
+        assert_eq!(export.warnings.len(), 1);
+        assert!(export.warnings[0].contains("FIC100_SP.VOp"));
+
+        let import = import_package(&export.package).unwrap();
+        assert!(import.warnings.is_empty(), "{:?}", import.warnings);
+        let round_trip = &import.config;
+        assert_eq!(round_trip.name, "Dosing");
+        assert_eq!(round_trip.version, "1.2.0");
+        assert_eq!(round_trip.writer.name, "PEA Designer");
+        assert_eq!(round_trip.opcua_config.endpoint, "opc.tcp://dosing:4840");
+
+        let service = &round_trip.services[0];
+        assert_eq!(service.description, "Dose a quantity");
+        let procedure = &service.procedures[0];
+        assert_eq!((procedure.id, procedure.name.as_str()), (3, "Quantity"));
+        assert!(procedure.is_self_completing && procedure.is_default);
+        let ServiceParameter::Analog(param) = &procedure.parameters[0] else {
+            panic!("expected an analog parameter");
+        };
+        assert_eq!(
+            (param.tag.as_str(), param.name.as_str()),
+            ("FIC100_SP", "Dosing quantity")
+        );
+        assert_eq!(
+            (param.unit.as_str(), param.v_min, param.v_max),
+            ("l", 5.0, 80.0)
+        );
+        assert!(param.tag_mapping.is_none());
+        let IndicatorElement::AnaView(view) = &procedure.process_value_outs[0] else {
+            panic!("expected an analog view");
+        };
+        assert_eq!(view.unit, "l/min");
+        assert_eq!(
+            view.tag_mapping.as_ref().unwrap().address,
+            "nsu=urn:acme:dosing;s=Dosing.FI100.V"
+        );
+
+        let ActiveElement::BinVlv(valve) = &round_trip.active_elements[0] else {
+            panic!("expected a binary valve");
+        };
+        assert_eq!(
+            valve.open_fbk_tag.as_ref().unwrap().address,
+            "nsu=urn:acme:dosing;i=6021"
+        );
+    }
+}
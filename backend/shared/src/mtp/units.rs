@@ -0,0 +1,101 @@
+/// A unit as a linear map onto its dimension's base unit:
+/// `base = value * factor + offset`.
+struct Unit {
+    symbols: &'static [&'static str],
+    dimension: &'static str,
+    factor: f64,
+    offset: f64,
+}
+
+const fn unit(
+    symbols: &'static [&'static str],
+    dimension: &'static str,
+    factor: f64,
+    offset: f64,
+) -> Unit {
+    Unit {
+        symbols,
+        dimension,
+        factor,
+        offset,
+    }
+}
+
+/// The units parameters are converted between, with the symbols the AML
+/// importer produces first and common ASCII spellings after them.
+const UNITS: &[Unit] = &[
+    unit(&["K"], "temperature", 1.0, 0.0),
+    unit(&["°C", "degC", "C", "celsius"], "temperature", 1.0, 273.15),
+    unit(
+        &["°F", "degF", "F", "fahrenheit"],
+        "temperature",
+        5.0 / 9.0,
+        273.15 - 32.0 * 5.0 / 9.0,
+    ),
+    unit(&["m"], "length", 1.0, 0.0),
+    unit(&["cm"], "length", 0.01, 0.0),
+    unit(&["mm"], "length", 0.001, 0.0),
+    unit(&["m³", "m3"], "volume", 1.0, 0.0),
+    unit(&["l", "L"], "volume", 0.001, 0.0),
+    unit(&["ml", "mL"], "volume", 0.000_001, 0.0),
+    unit(&["s"], "time", 1.0, 0.0),
+    unit(&["min"], "time", 60.0, 0.0),
+    unit(&["h"], "time", 3600.0, 0.0),
+    unit(&["kg"], "mass", 1.0, 0.0),
+    unit(&["g"], "mass", 0.001, 0.0),
+    unit(&["t"], "mass", 1000.0, 0.0),
+    unit(&["Pa"], "pressure", 1.0, 0.0),
+    unit(&["kPa"], "pressure", 1000.0, 0.0),
+    unit(&["bar"], "pressure", 100_000.0, 0.0),
+    unit(&["mbar"], "pressure", 100.0, 0.0),
+    unit(&["psi"], "pressure", 6894.757, 0.0),
+    unit(&["m³/s", "m3/s"], "flow", 1.0, 0.0),
+    unit(&["m³/h", "m3/h"], "flow", 1.0 / 3600.0, 0.0),
+    unit(&["l/s", "L/s"], "flow", 0.001, 0.0),
+    unit(&["l/min", "L/min"], "flow", 0.001 / 60.0, 0.0),
+    unit(&["l/h", "L/h"], "flow", 0.001 / 3600.0, 0.0),
+    unit(&["%"], "ratio", 0.01, 0.0),
+    unit(&["‰"], "ratio", 0.001, 0.0),
+];
+
+fn lookup(symbol: &str) -> Option<&'static Unit> {
+    UNITS.iter().find(|unit| unit.symbols.contains(&symbol))
+}
+
+/// `(factor, offset)` turning a value in `from` into one in `to`, as
+/// `value * factor + offset`. `None` when either unit is unknown or they
+/// measure different things; a unit always converts to itself.
+pub fn conversion(from: &str, to: &str) -> Option<(f64, f64)> {
+    if from == to {
+        return Some((1.0, 0.0));
+    }
+    let (from, to) = (lookup(from)?, lookup(to)?);
+    if from.dimension != to.dimension {
+        return None;
+    }
+    Some((
+        from.factor / to.factor,
+        (from.offset - to.offset) / to.factor,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(value: f64, from: &str, to: &str) -> Option<f64> {
+        conversion(from, to).map(|(factor, offset)| value * factor + offset)
+    }
+
+    #[test]
+    fn converts_within_a_dimension_only() {
+        let close = |a: Option<f64>, b: f64| (a.unwrap() - b).abs() < 1e-9;
+        assert!(close(convert(100.0, "°C", "°F"), 212.0));
+        assert!(close(convert(32.0, "degF", "K"), 273.15));
+        assert!(close(convert(1.5, "bar", "kPa"), 150.0));
+        assert!(close(convert(60.0, "l/h", "l/min"), 1.0));
+        assert_eq!(convert(7.0, "furlong", "furlong"), Some(7.0));
+        assert_eq!(convert(1.0, "bar", "°C"), None);
+        assert_eq!(convert(1.0, "bar", "furlong"), None);
+    }
+}
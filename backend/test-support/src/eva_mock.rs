@@ -0,0 +1,298 @@
+use actix_web::dev::ServerHandle;
+use actix_web::{web, App, HttpResponse, HttpServer};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Token handed out by the mock's `login`.
+pub const MOCK_TOKEN: &str = "token:mock";
+
+/// EVA-ICS error codes the mock answers with.
+pub const ERR_NOT_FOUND: i64 = -32001;
+pub const ERR_ACCESS_DENIED: i64 = -32002;
+pub const ERR_METHOD_NOT_FOUND: i64 = -32601;
+/// Code of errors injected with `MockEva::set_error_rate`.
+pub const ERR_INJECTED: i64 = -32000;
+
+/// A JSON-RPC call the mock received, for assertions.
+#[derive(Clone, Debug)]
+pub struct JrpcCall {
+    pub method: String,
+    pub params: Value,
+}
+
+#[derive(Deserialize)]
+struct JrpcRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+struct EvaState {
+    api_key: String,
+    items: Mutex<BTreeMap<String, Value>>,
+    calls: Mutex<Vec<JrpcCall>>,
+    error_rate: Mutex<f64>,
+}
+
+impl EvaState {
+    /// Whether the `n`th call (0-based) fails: spreads failures evenly so that
+    /// exactly `rate` of the calls fail, which keeps tests deterministic.
+    fn injects_error(&self, n: usize) -> bool {
+        let rate = *self.error_rate.lock().unwrap();
+        ((n + 1) as f64 * rate).floor() > (n as f64 * rate).floor()
+    }
+}
+
+/// In-process stand-in for the EVA-ICS HMI JSON-RPC API (`POST /jrpc`).
+///
+/// Items are plain values keyed by OID; `item.state`, `lvar.set`, `action`,
+/// `login` and `test` are understood. Calls need the API key (`k`) or the
+/// token from `login`. A share of calls can be made to fail, to exercise
+/// connector error handling.
+pub struct MockEva {
+    url: String,
+    state: Arc<EvaState>,
+    handle: ServerHandle,
+}
+
+impl MockEva {
+    pub async fn start(api_key: &str) -> anyhow::Result<Self> {
+        let state = Arc::new(EvaState {
+            api_key: api_key.to_string(),
+            items: Mutex::new(BTreeMap::new()),
+            calls: Mutex::new(Vec::new()),
+            error_rate: Mutex::new(0.0),
+        });
+        let data = web::Data::from(state.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .route("/jrpc", web::post().to(handle_jrpc))
+        })
+        .workers(1)
+        .disable_signals()
+        .bind(("127.0.0.1", 0))?;
+        let addr = server
+            .addrs()
+            .first()
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("Mock EVA-ICS did not bind"))?;
+        let server = server.run();
+        let handle = server.handle();
+        tokio::spawn(server);
+        Ok(Self {
+            url: format!("http://{}", addr),
+            state,
+            handle,
+        })
+    }
+
+    /// Base URL, as configured under `[eva_ics] url`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn client(&self) -> EvaClient {
+        EvaClient::new(&self.url, &self.state.api_key)
+    }
+
+    /// Seeds or overwrites an item value.
+    pub fn set_item(&self, oid: &str, value: Value) {
+        self.state
+            .items
+            .lock()
+            .unwrap()
+            .insert(oid.to_string(), value);
+    }
+
+    pub fn item(&self, oid: &str) -> Option<Value> {
+        self.state.items.lock().unwrap().get(oid).cloned()
+    }
+
+    /// Share of calls (`0..=1`) answered with an `ERR_INJECTED` error.
+    pub fn set_error_rate(&self, rate: f64) {
+        *self.state.error_rate.lock().unwrap() = rate.clamp(0.0, 1.0);
+    }
+
+    pub fn calls(&self) -> Vec<JrpcCall> {
+        self.state.calls.lock().unwrap().clone()
+    }
+
+    pub async fn stop(self) {
+        self.handle.stop(false).await;
+    }
+}
+
+async fn handle_jrpc(state: web::Data<EvaState>, body: web::Json<JrpcRequest>) -> HttpResponse {
+    let request = body.into_inner();
+    let n = {
+        let mut calls = state.calls.lock().unwrap();
+        calls.push(JrpcCall {
+            method: request.method.clone(),
+            params: request.params.clone(),
+        });
+        calls.len() - 1
+    };
+    let result = if state.injects_error(n) {
+        Err((ERR_INJECTED, "injected fault".to_string()))
+    } else {
+        dispatch(&state, &request.method, &request.params)
+    };
+    let reply = match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": request.id, "result": result}),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "id": request.id,
+            "error": {"code": code, "message": message},
+        }),
+    };
+    HttpResponse::Ok().json(reply)
+}
+
+fn dispatch(state: &EvaState, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+    let authorized = params.get("k").and_then(Value::as_str) == Some(state.api_key.as_str())
+        || params.get("token").and_then(Value::as_str) == Some(MOCK_TOKEN);
+    if !authorized {
+        return Err((ERR_ACCESS_DENIED, "access denied".to_string()));
+    }
+    let oid = || {
+        params
+            .get("i")
+            .and_then(Value::as_str)
+            .ok_or((ERR_NOT_FOUND, "item not specified".to_string()))
+    };
+    match method {
+        "test" => Ok(json!({"system_name": "mock", "product_code": "eva4node"})),
+        "login" => Ok(json!({"token": MOCK_TOKEN})),
+        "item.state" => {
+            let items = state.items.lock().unwrap();
+            let mask = params.get("i").and_then(Value::as_str).unwrap_or("#");
+            let states: Vec<Value> = items
+                .iter()
+                .filter(|(oid, _)| oid_matches(mask, oid))
+                .map(|(oid, value)| json!({"oid": oid, "status": 1, "value": value}))
+                .collect();
+            Ok(Value::Array(states))
+        }
+        "lvar.set" => {
+            let oid = oid()?;
+            if !oid.starts_with("lvar:") {
+                return Err((ERR_NOT_FOUND, format!("{} is not an lvar", oid)));
+            }
+            let value = params.get("value").cloned().unwrap_or(Value::Null);
+            state.items.lock().unwrap().insert(oid.to_string(), value);
+            Ok(json!({}))
+        }
+        "action" => {
+            let oid = oid()?;
+            let value = params
+                .get("params")
+                .and_then(|p| p.get("value"))
+                .cloned()
+                .unwrap_or(Value::Null);
+            state.items.lock().unwrap().insert(oid.to_string(), value);
+            Ok(json!({"oid": oid, "status": "completed", "exitcode": 0}))
+        }
+        other => Err((ERR_METHOD_NOT_FOUND, format!("method not found: {}", other))),
+    }
+}
+
+/// EVA-ICS OID masks: `#` matches everything, a trailing `#` a prefix.
+fn oid_matches(mask: &str, oid: &str) -> bool {
+    match mask.strip_suffix('#') {
+        Some(prefix) => oid.starts_with(prefix),
+        None => mask == oid,
+    }
+}
+
+/// Minimal EVA-ICS JSON-RPC client, as a connector would use it.
+#[derive(Clone)]
+pub struct EvaClient {
+    url: String,
+    api_key: String,
+    http: reqwest::Client,
+    next_id: Arc<AtomicU64>,
+}
+
+impl EvaClient {
+    pub fn new(url: &str, api_key: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            http: reqwest::Client::new(),
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// Calls `method`, adding the API key to `params`; JSON-RPC errors become `Err`.
+    pub async fn call(&self, method: &str, params: Value) -> anyhow::Result<Value> {
+        let mut params = match params {
+            Value::Object(map) => map,
+            _ => serde_json::Map::new(),
+        };
+        params.insert("k".to_string(), json!(self.api_key));
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id.fetch_add(1, Ordering::Relaxed),
+            "method": method,
+            "params": params,
+        });
+        let reply: Value = self
+            .http
+            .post(format!("{}/jrpc", self.url))
+            .json(&request)
+            .send()
+            .await?
+            .json()
+            .await?;
+        if let Some(error) = reply.get("error") {
+            anyhow::bail!("EVA-ICS {} failed: {}", method, error);
+        }
+        Ok(reply.get("result").cloned().unwrap_or(Value::Null))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn mock_requires_the_api_key() {
+        let eva = MockEva::start("secret").await.unwrap();
+        eva.set_item("sensor:reactor/temp", json!(21.5));
+
+        let states = eva
+            .client()
+            .call("item.state", json!({"i": "sensor:reactor/#"}))
+            .await
+            .unwrap();
+        assert_eq!(states[0]["value"], json!(21.5));
+
+        let denied = EvaClient::new(eva.url(), "wrong")
+            .call("item.state", json!({}))
+            .await;
+        assert!(denied.is_err());
+        assert_eq!(eva.calls().len(), 2);
+        eva.stop().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn injected_errors_follow_the_rate() {
+        let eva = MockEva::start("secret").await.unwrap();
+        eva.set_error_rate(0.25);
+        let client = eva.client();
+        let mut failures = 0;
+        for _ in 0..8 {
+            if client.call("test", json!({})).await.is_err() {
+                failures += 1;
+            }
+        }
+        assert_eq!(failures, 2);
+        eva.stop().await;
+    }
+}
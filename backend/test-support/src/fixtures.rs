@@ -0,0 +1,131 @@
+use chrono::Utc;
+use serde_json::{json, Value};
+use shared::mtp::{
+    ActiveElement, BinMonConfig, OpcUaConfig, PeaConfig, PeaMode, ProcedureConfig, ServiceCommand,
+    ServiceConfig, ServiceState, WriterInfo,
+};
+
+/// Service every fixture PEA offers.
+pub const SERVICE_TAG: &str = "Dose";
+
+/// A PEA with one service and a default procedure, served by a connector.
+pub fn pea_config(pea_id: &str) -> PeaConfig {
+    let now = Utc::now();
+    PeaConfig {
+        id: pea_id.to_string(),
+        name: format!("Fixture {}", pea_id),
+        version: "1.0.0".to_string(),
+        description: "Integration test PEA".to_string(),
+        writer: WriterInfo {
+            name: "test-support".to_string(),
+            version: "1.0.0".to_string(),
+            vendor: "Fendtastic".to_string(),
+        },
+        services: vec![ServiceConfig {
+            tag: SERVICE_TAG.to_string(),
+            name: SERVICE_TAG.to_string(),
+            description: "Dosing".to_string(),
+            config_parameters: Vec::new(),
+            procedures: vec![ProcedureConfig {
+                id: 1,
+                name: "Default".to_string(),
+                is_self_completing: false,
+                is_default: true,
+                parameters: Vec::new(),
+                process_value_outs: Vec::new(),
+                report_values: Vec::new(),
+            }],
+        }],
+        active_elements: Vec::new(),
+        opcua_config: OpcUaConfig {
+            endpoint: "opc.tcp://127.0.0.1:4840".to_string(),
+            namespace_uri: format!("urn:fendtastic:pea:{}", pea_id),
+            security_policy: "None".to_string(),
+        },
+        created_at: now,
+        updated_at: now,
+        tenant_id: None,
+        mode: PeaMode::External,
+        archived_at: None,
+    }
+}
+
+/// A binary monitor without a feedback mapping.
+pub fn bin_mon(tag: &str) -> ActiveElement {
+    ActiveElement::BinMon(BinMonConfig {
+        tag: tag.to_string(),
+        name: tag.to_string(),
+        fbk_tag: None,
+    })
+}
+
+/// PEA status as a runtime publishes it on `pea_status`, with each service in
+/// the given state.
+pub fn status_message(pea_id: &str, services: &[(&str, ServiceState)]) -> Value {
+    json!({
+        "pea_id": pea_id,
+        "deployed": true,
+        "running": true,
+        "services": services
+            .iter()
+            .map(|(tag, state)| json!({ "tag": tag, "state": state, "state_code": state.code() }))
+            .collect::<Vec<_>>(),
+        "last_updated": Utc::now().to_rfc3339(),
+    })
+}
+
+/// Open alarm record raised by a PEA, as published on `entmoot/pol/alarms/*`.
+pub fn alarm(id: &str, pea_id: &str, severity: &str) -> Value {
+    json!({
+        "id": id,
+        "severity": severity,
+        "status": "open",
+        "source": format!("entmoot/habitat/nodes/n1/pea/{}/swimlane/alarm", pea_id),
+        "event": "TRIP",
+        "value": "",
+        "description": "",
+        "timestamp": Utc::now().to_rfc3339(),
+        "duplicate_count": 1,
+    })
+}
+
+/// Deploy message the api-server publishes on `runtime_pea_deploy`.
+pub fn deploy_message(config: &PeaConfig) -> Value {
+    json!({
+        "action": "deploy",
+        "pea_config": config,
+        "liveliness": shared::mtp::topics::pea_liveliness(&config.id),
+    })
+}
+
+/// Undeploy message the api-server publishes on `runtime_pea_deploy`.
+pub fn undeploy_message() -> Value {
+    json!({ "action": "undeploy" })
+}
+
+/// Start/stop message the api-server publishes on `runtime_pea_lifecycle`.
+pub fn lifecycle_message(action: &str) -> Value {
+    json!({ "action": action })
+}
+
+/// Procedure parameters the api-server publishes on `pea_service_parameters`.
+pub fn parameters_message(procedure_id: Option<u32>, values: &[(&str, Value)]) -> Value {
+    json!({
+        "procedure_id": procedure_id,
+        "parameters": values
+            .iter()
+            .map(|(tag, value)| json!({ "tag": tag, "value": value }))
+            .collect::<Vec<_>>(),
+        "timestamp": Utc::now().to_rfc3339(),
+    })
+}
+
+/// Service command the api-server publishes on `pea_service_command`.
+pub fn command_message(command: ServiceCommand, procedure_id: Option<u32>) -> Value {
+    json!({
+        "command": command,
+        "command_code": command.code(),
+        "procedure_id": procedure_id,
+        "timestamp": Utc::now().to_rfc3339(),
+    })
+}
@@ -0,0 +1,63 @@
+//! Test support for cross-service integration tests: an in-process Zenoh
+//! router, a mock EVA-ICS JSON-RPC server, a stand-in PEA runtime and PEA
+//! fixtures. Payloads follow what the api-server publishes, so tests written
+//! against this crate exercise the same message contracts.
+
+pub mod eva_mock;
+pub mod fixtures;
+pub mod router;
+pub mod runtime;
+
+use std::time::Duration;
+use zenoh::handlers::FifoChannelHandler;
+use zenoh::pubsub::Subscriber;
+use zenoh::sample::Sample;
+
+pub use eva_mock::{EvaClient, MockEva};
+pub use router::ZenohRouter;
+pub use runtime::MockPeaRuntime;
+
+/// Subscription used to wait for a payload published under a key expression.
+pub struct Probe {
+    subscriber: Subscriber<FifoChannelHandler<Sample>>,
+}
+
+impl Probe {
+    /// Subscribes before anything is published, so no sample is missed.
+    pub async fn subscribe(session: &zenoh::Session, key_expr: &str) -> anyhow::Result<Self> {
+        let subscriber = session
+            .declare_subscriber(key_expr.to_string())
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to subscribe to {}: {}", key_expr, e))?;
+        Ok(Self { subscriber })
+    }
+
+    /// The first JSON payload matching `predicate` within `timeout`.
+    pub async fn wait_for(
+        &self,
+        timeout: Duration,
+        predicate: impl Fn(&serde_json::Value) -> bool,
+    ) -> anyhow::Result<serde_json::Value> {
+        let wait = async {
+            while let Ok(sample) = self.subscriber.recv_async().await {
+                let Ok(value) =
+                    serde_json::from_slice::<serde_json::Value>(&sample.payload().to_bytes())
+                else {
+                    continue;
+                };
+                if predicate(&value) {
+                    return Ok(value);
+                }
+            }
+            Err(anyhow::anyhow!("Subscription closed"))
+        };
+        tokio::time::timeout(timeout, wait)
+            .await
+            .map_err(|_| anyhow::anyhow!("No matching payload within {:?}", timeout))?
+    }
+}
+
+/// Gives subscriber declarations time to reach the router before publishing.
+pub async fn settle() {
+    tokio::time::sleep(Duration::from_millis(250)).await;
+}
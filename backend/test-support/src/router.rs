@@ -0,0 +1,60 @@
+use anyhow::anyhow;
+use std::net::TcpListener;
+
+/// Zenoh router listening on a free loopback port for the lifetime of a test.
+///
+/// Multicast scouting is off, so parallel tests and a developer's local
+/// router never see each other.
+pub struct ZenohRouter {
+    session: zenoh::Session,
+    endpoint: String,
+}
+
+impl ZenohRouter {
+    pub async fn start() -> anyhow::Result<Self> {
+        let port = free_port()?;
+        let endpoint = format!("tcp/127.0.0.1:{}", port);
+        let mut config = zenoh::Config::default();
+        config
+            .insert_json5("mode", r#""router""#)
+            .map_err(|e| anyhow!(e))?;
+        config
+            .insert_json5("listen/endpoints", &format!(r#"["{}"]"#, endpoint))
+            .map_err(|e| anyhow!(e))?;
+        config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .map_err(|e| anyhow!(e))?;
+        let session = zenoh::open(config).await.map_err(|e| anyhow!(e))?;
+        Ok(Self { session, endpoint })
+    }
+
+    /// Endpoint to pass as `ZENOH_ROUTER` or `connect/endpoints`.
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// The router's own session, e.g. to publish as if from another node.
+    pub fn session(&self) -> &zenoh::Session {
+        &self.session
+    }
+
+    /// A client session connected to this router only.
+    pub async fn client(&self) -> anyhow::Result<zenoh::Session> {
+        let mut config = zenoh::Config::default();
+        config
+            .insert_json5("mode", r#""client""#)
+            .map_err(|e| anyhow!(e))?;
+        config
+            .insert_json5("connect/endpoints", &format!(r#"["{}"]"#, self.endpoint))
+            .map_err(|e| anyhow!(e))?;
+        config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .map_err(|e| anyhow!(e))?;
+        zenoh::open(config).await.map_err(|e| anyhow!(e))
+    }
+}
+
+fn free_port() -> anyhow::Result<u16> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    Ok(listener.local_addr()?.port())
+}
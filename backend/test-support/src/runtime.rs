@@ -0,0 +1,301 @@
+use chrono::Utc;
+use serde_json::{json, Value};
+use shared::mtp::{
+    topics, OperationMode, PeaConfig, PeaInstanceStatus, ServiceCommand, ServiceRuntimeState,
+    ServiceState, SourceMode,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::warn;
+use zenoh::liveliness::LivelinessToken;
+use zenoh::sample::Sample;
+
+use crate::eva_mock::EvaClient;
+
+/// Stand-in for a node runtime serving external PEAs.
+///
+/// Reacts to the runtime deploy and lifecycle topics and to service commands
+/// the way a connector would: service states move through the transient state
+/// into the stable one, every change is published on the PEA status and
+/// service state topics, and, with an EVA-ICS client, written through as
+/// `lvar:{pea}/{service}/state` (the state code). Procedure parameters are
+/// written as `lvar:{pea}/{service}/{parameter}` once the PEA is deployed.
+/// Commands are ignored until the PEA is deployed and started. Like a real
+/// runtime, it holds the liveliness token named in the deploy message while
+/// the PEA is deployed.
+pub struct MockPeaRuntime {
+    peas: Arc<Mutex<HashMap<String, PeaInstanceStatus>>>,
+    task: JoinHandle<()>,
+}
+
+impl MockPeaRuntime {
+    pub async fn start(session: zenoh::Session, eva: Option<EvaClient>) -> anyhow::Result<Self> {
+        let subscribe = |key: &'static str| {
+            let session = session.clone();
+            async move {
+                session
+                    .declare_subscriber(key)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Failed to subscribe to {}: {}", key, e))
+            }
+        };
+        let deploys = subscribe(topics::RUNTIME_PEA_DEPLOY_WILDCARD).await?;
+        let lifecycles = subscribe(topics::RUNTIME_PEA_LIFECYCLE_WILDCARD).await?;
+        let commands = subscribe(topics::PEA_SERVICE_COMMAND_WILDCARD).await?;
+        let parameters = subscribe(topics::PEA_SERVICE_PARAMETERS_WILDCARD).await?;
+
+        let peas = Arc::new(Mutex::new(HashMap::new()));
+        let runtime = Runtime {
+            session,
+            eva,
+            peas: peas.clone(),
+            tokens: Mutex::new(HashMap::new()),
+        };
+        let task = tokio::spawn(async move {
+            loop {
+                let sample = tokio::select! {
+                    sample = deploys.recv_async() => sample,
+                    sample = lifecycles.recv_async() => sample,
+                    sample = commands.recv_async() => sample,
+                    sample = parameters.recv_async() => sample,
+                };
+                match sample {
+                    Ok(sample) => runtime.handle(sample).await,
+                    Err(_) => break,
+                }
+            }
+        });
+        Ok(Self { peas, task })
+    }
+
+    /// Last status the runtime published for `pea_id`.
+    pub async fn status(&self, pea_id: &str) -> Option<PeaInstanceStatus> {
+        self.peas.lock().await.get(pea_id).cloned()
+    }
+}
+
+impl Drop for MockPeaRuntime {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct Runtime {
+    session: zenoh::Session,
+    eva: Option<EvaClient>,
+    peas: Arc<Mutex<HashMap<String, PeaInstanceStatus>>>,
+    tokens: Mutex<HashMap<String, LivelinessToken>>,
+}
+
+impl Runtime {
+    async fn handle(&self, sample: Sample) {
+        let key = sample.key_expr().as_str().to_string();
+        let Ok(payload) = serde_json::from_slice::<Value>(&sample.payload().to_bytes()) else {
+            warn!("Mock runtime ignored a non-JSON payload on {}", key);
+            return;
+        };
+        let segments: Vec<&str> = key.split('/').collect();
+        let Some(pea_id) = segment_after(&segments, "pea") else {
+            return;
+        };
+        if key.ends_with("/deploy") {
+            self.deploy(pea_id, &payload).await;
+        } else if key.ends_with("/lifecycle") {
+            self.lifecycle(pea_id, &payload).await;
+        } else if let Some(service_tag) = segment_after(&segments, "services") {
+            if key.ends_with("/parameters") {
+                self.parameters(pea_id, service_tag, &payload).await;
+            } else {
+                self.command(pea_id, service_tag, &payload).await;
+            }
+        }
+    }
+
+    async fn deploy(&self, pea_id: &str, payload: &Value) {
+        let mut peas = self.peas.lock().await;
+        match payload.get("action").and_then(Value::as_str) {
+            Some("deploy") => {
+                let Some(config) = payload
+                    .get("pea_config")
+                    .and_then(|config| serde_json::from_value::<PeaConfig>(config.clone()).ok())
+                else {
+                    warn!("Mock runtime got a deploy for {} without a config", pea_id);
+                    return;
+                };
+                peas.insert(pea_id.to_string(), deployed_status(&config));
+                if let Some(key) = payload.get("liveliness").and_then(Value::as_str) {
+                    self.declare_liveliness(pea_id, key).await;
+                }
+            }
+            Some("undeploy") => {
+                self.tokens.lock().await.remove(pea_id);
+                let Some(status) = peas.get_mut(pea_id) else {
+                    return;
+                };
+                status.deployed = false;
+                status.running = false;
+            }
+            _ => return,
+        }
+        if let Some(status) = peas.get_mut(pea_id) {
+            status.last_updated = Utc::now();
+            self.publish_status(status).await;
+        }
+    }
+
+    async fn lifecycle(&self, pea_id: &str, payload: &Value) {
+        let mut peas = self.peas.lock().await;
+        let Some(status) = peas.get_mut(pea_id).filter(|status| status.deployed) else {
+            return;
+        };
+        match payload.get("action").and_then(Value::as_str) {
+            Some("start") => status.running = true,
+            Some("stop") => status.running = false,
+            _ => return,
+        }
+        status.last_updated = Utc::now();
+        self.publish_status(status).await;
+    }
+
+    async fn command(&self, pea_id: &str, service_tag: &str, payload: &Value) {
+        let Some(command) = payload
+            .get("command")
+            .and_then(|command| serde_json::from_value::<ServiceCommand>(command.clone()).ok())
+        else {
+            return;
+        };
+        let mut peas = self.peas.lock().await;
+        let Some(status) = peas.get_mut(pea_id).filter(|status| status.running) else {
+            return;
+        };
+        let Some(index) = status.services.iter().position(|s| s.tag == service_tag) else {
+            return;
+        };
+        let Some(transient) = status.services[index].state.command_target(command) else {
+            return;
+        };
+        if command == ServiceCommand::Start {
+            status.services[index].current_procedure_id = payload
+                .get("procedure_id")
+                .and_then(Value::as_u64)
+                .map(|id| id as u32);
+        }
+        let mut next = Some(transient);
+        while let Some(state) = next {
+            status.services[index].state = state;
+            status.last_updated = Utc::now();
+            self.publish_service_state(pea_id, service_tag, state).await;
+            self.publish_status(status).await;
+            next = state.completion_target();
+        }
+    }
+
+    async fn parameters(&self, pea_id: &str, service_tag: &str, payload: &Value) {
+        let deployed = self
+            .peas
+            .lock()
+            .await
+            .get(pea_id)
+            .is_some_and(|status| status.deployed);
+        let (Some(eva), true) = (&self.eva, deployed) else {
+            return;
+        };
+        let Some(parameters) = payload.get("parameters").and_then(Value::as_array) else {
+            return;
+        };
+        for parameter in parameters {
+            let (Some(tag), Some(value)) = (
+                parameter.get("tag").and_then(Value::as_str),
+                parameter.get("value"),
+            ) else {
+                continue;
+            };
+            let oid = format!("lvar:{}/{}/{}", pea_id, service_tag, tag);
+            if let Err(e) = eva
+                .call("lvar.set", json!({ "i": oid, "value": value }))
+                .await
+            {
+                warn!("Mock runtime failed to write {}: {}", oid, e);
+            }
+        }
+    }
+
+    async fn declare_liveliness(&self, pea_id: &str, key: &str) {
+        match self
+            .session
+            .liveliness()
+            .declare_token(key.to_string())
+            .await
+        {
+            Ok(token) => {
+                self.tokens.lock().await.insert(pea_id.to_string(), token);
+            }
+            Err(e) => warn!("Mock runtime failed to declare liveliness {}: {}", key, e),
+        }
+    }
+
+    async fn publish_status(&self, status: &PeaInstanceStatus) {
+        let payload = serde_json::to_string(status).unwrap_or_else(|_| "{}".to_string());
+        if let Err(e) = self
+            .session
+            .put(topics::pea_status(&status.pea_id), payload)
+            .await
+        {
+            warn!("Mock runtime failed to publish status: {}", e);
+        }
+    }
+
+    async fn publish_service_state(&self, pea_id: &str, service_tag: &str, state: ServiceState) {
+        let payload = json!({ "state": state, "state_code": state.code() });
+        if let Err(e) = self
+            .session
+            .put(
+                topics::pea_service_state(pea_id, service_tag),
+                payload.to_string(),
+            )
+            .await
+        {
+            warn!("Mock runtime failed to publish service state: {}", e);
+        }
+        if let Some(eva) = &self.eva {
+            let oid = format!("lvar:{}/{}/state", pea_id, service_tag);
+            if let Err(e) = eva
+                .call("lvar.set", json!({ "i": oid, "value": state.code() }))
+                .await
+            {
+                warn!("Mock runtime failed to write {}: {}", oid, e);
+            }
+        }
+    }
+}
+
+fn deployed_status(config: &PeaConfig) -> PeaInstanceStatus {
+    PeaInstanceStatus {
+        pea_id: config.id.clone(),
+        deployed: true,
+        running: false,
+        services: config
+            .services
+            .iter()
+            .map(|service| ServiceRuntimeState {
+                tag: service.tag.clone(),
+                state: ServiceState::Idle,
+                current_procedure_id: None,
+                operation_mode: OperationMode::Automatic,
+                source_mode: SourceMode::Internal,
+            })
+            .collect(),
+        opcua_endpoint: Some(config.opcua_config.endpoint.clone()),
+        last_updated: Utc::now(),
+    }
+}
+
+fn segment_after<'a>(segments: &[&'a str], marker: &str) -> Option<&'a str> {
+    segments
+        .iter()
+        .position(|segment| *segment == marker)
+        .and_then(|index| segments.get(index + 1))
+        .copied()
+}
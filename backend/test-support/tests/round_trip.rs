@@ -0,0 +1,224 @@
+use serde_json::Value;
+use shared::mtp::{topics, ServiceCommand, ServiceState};
+use std::time::Duration;
+use test_support::{fixtures, settle, MockEva, MockPeaRuntime, Probe, ZenohRouter};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn service_state(status: &Value, service_tag: &str) -> Option<ServiceState> {
+    status["services"]
+        .as_array()?
+        .iter()
+        .find(|service| service["tag"] == service_tag)
+        .and_then(|service| serde_json::from_value(service["state"].clone()).ok())
+}
+
+async fn put(session: &zenoh::Session, key: String, payload: Value) {
+    session
+        .put(key, payload.to_string())
+        .await
+        .expect("Failed to publish");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deploy_start_command_status_round_trip() {
+    let router = ZenohRouter::start().await.unwrap();
+    let eva = MockEva::start("test-key").await.unwrap();
+    let _runtime = MockPeaRuntime::start(router.client().await.unwrap(), Some(eva.client()))
+        .await
+        .unwrap();
+    let operator = router.client().await.unwrap();
+
+    let pea_id = "reactor-1";
+    let config = fixtures::pea_config(pea_id);
+    let status = Probe::subscribe(&operator, &topics::pea_status(pea_id))
+        .await
+        .unwrap();
+    settle().await;
+
+    put(
+        &operator,
+        topics::runtime_pea_deploy(pea_id),
+        fixtures::deploy_message(&config),
+    )
+    .await;
+    let deployed = status
+        .wait_for(TIMEOUT, |s| s["deployed"] == true)
+        .await
+        .unwrap();
+    assert_eq!(
+        service_state(&deployed, fixtures::SERVICE_TAG),
+        Some(ServiceState::Idle)
+    );
+
+    put(
+        &operator,
+        topics::runtime_pea_lifecycle(pea_id),
+        fixtures::lifecycle_message("start"),
+    )
+    .await;
+    status
+        .wait_for(TIMEOUT, |s| s["running"] == true)
+        .await
+        .unwrap();
+
+    put(
+        &operator,
+        topics::pea_service_command(pea_id, fixtures::SERVICE_TAG),
+        fixtures::command_message(ServiceCommand::Start, Some(1)),
+    )
+    .await;
+    let executing = status
+        .wait_for(TIMEOUT, |s| {
+            service_state(s, fixtures::SERVICE_TAG) == Some(ServiceState::Execute)
+        })
+        .await
+        .unwrap();
+    assert_eq!(executing["services"][0]["current_procedure_id"], 1);
+
+    assert_eq!(
+        eva.item(&format!("lvar:{}/{}/state", pea_id, fixtures::SERVICE_TAG)),
+        Some(Value::from(ServiceState::Execute.code()))
+    );
+    assert!(eva.calls().iter().any(|call| call.method == "lvar.set"));
+    eva.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn commands_are_ignored_until_the_pea_runs() {
+    let router = ZenohRouter::start().await.unwrap();
+    let runtime = MockPeaRuntime::start(router.client().await.unwrap(), None)
+        .await
+        .unwrap();
+    let operator = router.client().await.unwrap();
+
+    let pea_id = "mixer-1";
+    let status = Probe::subscribe(&operator, &topics::pea_status(pea_id))
+        .await
+        .unwrap();
+    settle().await;
+
+    put(
+        &operator,
+        topics::runtime_pea_deploy(pea_id),
+        fixtures::deploy_message(&fixtures::pea_config(pea_id)),
+    )
+    .await;
+    status
+        .wait_for(TIMEOUT, |s| s["deployed"] == true)
+        .await
+        .unwrap();
+
+    put(
+        &operator,
+        topics::pea_service_command(pea_id, fixtures::SERVICE_TAG),
+        fixtures::command_message(ServiceCommand::Start, None),
+    )
+    .await;
+    put(
+        &operator,
+        topics::runtime_pea_deploy(pea_id),
+        fixtures::undeploy_message(),
+    )
+    .await;
+    let undeployed = status
+        .wait_for(TIMEOUT, |s| s["deployed"] == false)
+        .await
+        .unwrap();
+    assert_eq!(
+        service_state(&undeployed, fixtures::SERVICE_TAG),
+        Some(ServiceState::Idle)
+    );
+    assert!(!runtime.status(pea_id).await.unwrap().running);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn procedure_parameters_are_written_to_lvars() {
+    let router = ZenohRouter::start().await.unwrap();
+    let eva = MockEva::start("test-key").await.unwrap();
+    let _runtime = MockPeaRuntime::start(router.client().await.unwrap(), Some(eva.client()))
+        .await
+        .unwrap();
+    let operator = router.client().await.unwrap();
+
+    let pea_id = "dosing-1";
+    let status = Probe::subscribe(&operator, &topics::pea_status(pea_id))
+        .await
+        .unwrap();
+    settle().await;
+
+    put(
+        &operator,
+        topics::runtime_pea_deploy(pea_id),
+        fixtures::deploy_message(&fixtures::pea_config(pea_id)),
+    )
+    .await;
+    status
+        .wait_for(TIMEOUT, |s| s["deployed"] == true)
+        .await
+        .unwrap();
+
+    put(
+        &operator,
+        topics::pea_service_parameters(pea_id, fixtures::SERVICE_TAG),
+        fixtures::parameters_message(Some(1), &[("FIC_SP", Value::from(12.5))]),
+    )
+    .await;
+    let oid = format!("lvar:{}/{}/FIC_SP", pea_id, fixtures::SERVICE_TAG);
+    let deadline = tokio::time::Instant::now() + TIMEOUT;
+    while eva.item(&oid).is_none() && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(eva.item(&oid), Some(Value::from(12.5)));
+    eva.stop().await;
+}
+
+async fn alive(session: &zenoh::Session, pea_id: &str) -> bool {
+    let replies = session
+        .liveliness()
+        .get(topics::pea_liveliness(pea_id))
+        .await
+        .expect("Failed to query liveliness");
+    replies.recv_async().await.is_ok()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn runtime_holds_liveliness_while_deployed() {
+    let router = ZenohRouter::start().await.unwrap();
+    let _runtime = MockPeaRuntime::start(router.client().await.unwrap(), None)
+        .await
+        .unwrap();
+    let operator = router.client().await.unwrap();
+
+    let pea_id = "filter-1";
+    let status = Probe::subscribe(&operator, &topics::pea_status(pea_id))
+        .await
+        .unwrap();
+    settle().await;
+
+    put(
+        &operator,
+        topics::runtime_pea_deploy(pea_id),
+        fixtures::deploy_message(&fixtures::pea_config(pea_id)),
+    )
+    .await;
+    status
+        .wait_for(TIMEOUT, |s| s["deployed"] == true)
+        .await
+        .unwrap();
+    settle().await;
+    assert!(alive(&operator, pea_id).await);
+
+    put(
+        &operator,
+        topics::runtime_pea_deploy(pea_id),
+        fixtures::undeploy_message(),
+    )
+    .await;
+    status
+        .wait_for(TIMEOUT, |s| s["deployed"] == false)
+        .await
+        .unwrap();
+    settle().await;
+    assert!(!alive(&operator, pea_id).await);
+}
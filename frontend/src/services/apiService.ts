@@ -1,5 +1,5 @@
 import axios, { AxiosInstance } from 'axios'
-import { PeaConfig, ServiceCommand } from '../types/mtp'
+import { PeaConfig, PeaListQuery, PeaPage, ServiceCommand } from '../types/mtp'
 import { Recipe } from '../types/recipe'
 import { ZenohNode, KeyEntry, NodeConfigRequest, ConfigUpdateRequest } from '../types/mesh'
 import { RuntimeNode, RuntimeNodeHealthCheck, RuntimeNodeStatusSnapshot } from '../types/runtime'
@@ -144,11 +144,23 @@ class ApiService {
 
   // ─── PEA CRUD ────────────────────────────────────────────────────────────
 
-  async listPeas(): Promise<PeaConfig[]> {
-    const response = await this.client.get('/pea')
+  async listPeaPage(query: PeaListQuery = {}): Promise<PeaPage> {
+    const response = await this.client.get('/pea', { params: query })
     return response.data
   }
 
+  // Every PEA, fetched page by page.
+  async listPeas(): Promise<PeaConfig[]> {
+    const peas: PeaConfig[] = []
+    for (let page = 1; ; page++) {
+      const result = await this.listPeaPage({ page, page_size: 500 })
+      peas.push(...result.items)
+      if (result.items.length === 0 || peas.length >= result.total) {
+        return peas
+      }
+    }
+  }
+
   async getPea(id: string): Promise<PeaConfig> {
     const response = await this.client.get(`/pea/${id}`)
     return response.data
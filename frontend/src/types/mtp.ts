@@ -16,6 +16,21 @@ export interface PeaConfig {
   updated_at: string
 }
 
+export interface PeaListQuery {
+  page?: number
+  page_size?: number
+  name_contains?: string
+  service_tag?: string
+  sort_by?: 'name' | 'id' | 'created_at' | 'updated_at' | '-name' | '-id' | '-created_at' | '-updated_at'
+}
+
+export interface PeaPage {
+  total: number
+  page: number
+  page_size: number
+  items: PeaConfig[]
+}
+
 export interface WriterInfo {
   name: string
   version: string
@@ -58,6 +73,12 @@ export type ServiceParameter =
   | ({ type: 'DInt' } & DIntParameter)
   | ({ type: 'StringParam' } & StringParameter)
 
+export interface DisplayHints {
+  decimals?: number
+  unit?: string
+  labels?: Record<string, string>
+}
+
 export interface AnalogParameter {
   tag: string
   name: string
@@ -68,6 +89,7 @@ export interface AnalogParameter {
   v_max: number
   v_default: number
   tag_mapping: TagMapping | null
+  display?: DisplayHints
 }
 
 export interface BinaryParameter {
@@ -89,6 +111,7 @@ export interface DIntParameter {
   v_max: number
   v_default: number
   tag_mapping: TagMapping | null
+  display?: DisplayHints
 }
 
 export interface StringParameter {